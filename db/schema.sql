@@ -6,28 +6,61 @@ CREATE TABLE IF NOT EXISTS event_queue (
   action TEXT NOT NULL,
   track_path TEXT,
   extra TEXT,
-  is_synced INTEGER NOT NULL DEFAULT 0
+  is_synced INTEGER NOT NULL DEFAULT 0,
+  -- failed sync attempts so far, and the most recent error
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  -- set once attempts reach max_retries_on_error; the worker skips the event
+  failed_at INTEGER
 );
 
 CREATE INDEX IF NOT EXISTS idx_event_queue_unsynced ON event_queue(is_synced, timestamp);
 
--- playlist map
+-- playlist map: one remote playlist per (provider, logical playlist name)
 CREATE TABLE IF NOT EXISTS playlist_map (
-  id INTEGER PRIMARY KEY AUTOINCREMENT,
-  playlist_name TEXT UNIQUE NOT NULL,
+  provider TEXT NOT NULL,
+  playlist_name TEXT NOT NULL,
   remote_id TEXT,
   remote_snapshot_id TEXT,
   last_synced_at INTEGER,
-  remote_display_name TEXT
+  remote_display_name TEXT,
+  PRIMARY KEY (provider, playlist_name)
 );
 
--- track cache
+-- track cache: resolved remote track per (provider, local file path)
 CREATE TABLE IF NOT EXISTS track_cache (
-  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  provider TEXT NOT NULL,
+  local_path TEXT NOT NULL,
   isrc TEXT,
-  local_path TEXT UNIQUE,
   remote_id TEXT,
-  resolved_at INTEGER
+  resolved_at INTEGER,
+  -- set when resolution failed; the worker skips the file until
+  -- `unresolved_retry_secs` have passed or it is resolved again
+  not_found_at INTEGER,
+  -- set once remote_id has been compared with the ISRC in the file's tags
+  -- (see the worker's stale cache check); cleared when remote_id changes
+  isrc_checked_at INTEGER,
+  -- ISRC read from the file's tags (NULL for none) when the file had this
+  -- mtime (nanoseconds) and size; read again once either changes
+  file_mtime_ns INTEGER,
+  file_size INTEGER,
+  file_isrc TEXT,
+  -- ISRC the provider reported for remote_id after a metadata search;
+  -- read for every provider so the others can search by it
+  remote_isrc TEXT,
+  PRIMARY KEY (provider, local_path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_track_cache_remote_id ON track_cache(remote_id);
+
+-- manual track overrides: pinned remote track per (provider, local file path);
+-- consulted before track_cache/ISRC/search and never re-resolved
+CREATE TABLE IF NOT EXISTS overrides (
+  provider TEXT NOT NULL,
+  local_path TEXT NOT NULL,
+  remote_uri TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  PRIMARY KEY (provider, local_path)
 );
 
 -- playlist cache: stores metadata for the last-read .m3u file so we can
@@ -68,6 +101,21 @@ CREATE TABLE IF NOT EXISTS remote_playlist_contents_cache (
   PRIMARY KEY (provider_name, playlist_name)
 );
 
+-- Last known remote track list (in order) per provider playlist.  Written
+-- after every live listing and kept current after each successful sync, so
+-- reconciles diff against it instead of relisting.  listed_at is the time of
+-- the last live listing; older snapshots (remote_snapshot_max_age_secs) and
+-- snapshots removed after a failed operation force a relist.
+CREATE TABLE IF NOT EXISTS remote_snapshot (
+  provider TEXT NOT NULL,
+  playlist_name TEXT NOT NULL,
+  remote_id TEXT NOT NULL,
+  uris TEXT NOT NULL,
+  listed_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL,
+  PRIMARY KEY (provider, playlist_name)
+);
+
 -- Provider playlist list cache: persists the full (id, name) list returned by
 -- list_user_playlists() across process restarts so every new run can skip the
 -- O(n) per-playlist API roundtrips caused by TIDAL's pagination model.
@@ -84,4 +132,18 @@ CREATE TABLE IF NOT EXISTS processing_locks (
   worker_id TEXT,
   locked_at INTEGER,
   expires_at INTEGER
-);
\ No newline at end of file
+);
+-- small key/value store for run bookkeeping, e.g. `last_reconcile_ts` (unix
+-- time the last reconcile scan started, used by `reconcile --changed-only`)
+CREATE TABLE IF NOT EXISTS sync_state (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+-- Prometheus-style counter totals added by worker runs (see src/metrics.rs);
+-- `labels` holds the rendered label set, e.g. provider="spotify",status="200"
+CREATE TABLE IF NOT EXISTS metrics (
+  name TEXT NOT NULL,
+  labels TEXT NOT NULL DEFAULT '',
+  value INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (name, labels)
+);
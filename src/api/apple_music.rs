@@ -0,0 +1,618 @@
+use super::{
+    Provider, ProviderCapabilities, ProviderError, ProviderResult, RequestSpec, TrackMatch,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the URIs handed out by `search_track_uri`.
+const URI_PREFIX: &str = "applemusic:song:";
+
+/// Page size used when listing library playlists and their tracks.
+const PAGE_SIZE: usize = 100;
+
+/// Lifetime of a signed developer token; Apple accepts at most six months.
+pub const DEVELOPER_TOKEN_TTL_SECS: i64 = 15_777_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredToken {
+    /// ES256 JWT sent as the bearer token.
+    pub developer_token: String,
+    pub developer_token_expires_at: i64, // epoch seconds
+    /// Token of the user's library, obtained through MusicKit.
+    pub music_user_token: String,
+    /// Catalog storefront of the account, e.g. "us".
+    pub storefront: String,
+    /// Key material used to sign a new developer token once it expires;
+    /// without it `auth applemusic` has to be run again.
+    pub team_id: Option<String>,
+    pub key_id: Option<String>,
+    pub private_key_path: Option<PathBuf>,
+}
+
+/// Sign a developer token (an ES256 JWT) with the MusicKit private key in
+/// `private_key_pem` (the `.p8` file downloaded from the Apple developer
+/// portal).  Returns the token and its expiry in epoch seconds.
+pub fn sign_developer_token(
+    team_id: &str,
+    key_id: &str,
+    private_key_pem: &str,
+    now: i64,
+) -> Result<(String, i64)> {
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    let der_b64: String = private_key_pem
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("-----"))
+        .collect();
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(der_b64)
+        .context("private key is not a PEM file")?;
+    let rng = SystemRandom::new();
+    let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &der, &rng)
+        .map_err(|e| anyhow!("private key is not a PKCS#8 P-256 key: {}", e))?;
+
+    let expires_at = now + DEVELOPER_TOKEN_TTL_SECS;
+    let header = json!({ "alg": "ES256", "kid": key_id });
+    let claims = json!({ "iss": team_id, "iat": now, "exp": expires_at });
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = key
+        .sign(&rng, signing_input.as_bytes())
+        .map_err(|_| anyhow!("signing the developer token failed"))?;
+    Ok((
+        format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ),
+        expires_at,
+    ))
+}
+
+/// Apple Music provider backed by the Apple Music API
+/// (`api.music.apple.com/v1`).  Requests carry the developer token as the
+/// bearer token and the user's library token in the `Music-User-Token`
+/// header; catalog songs are stored as `applemusic:song:{id}` URIs.
+///
+/// The API cannot delete or rename library playlists, nor remove tracks
+/// from them: like Spotify's unfollow, `delete_playlist` only forgets the
+/// playlist (it stays in the library), and renames and removals are logged
+/// and skipped.
+/// The endpoint may be overridden by the APPLE_MUSIC_API_BASE env var (useful for tests).
+pub struct AppleMusicProvider {
+    client: Client,
+    db_path: PathBuf,
+    config: crate::config::Config,
+    has_token: bool,
+    token: tokio::sync::Mutex<Option<StoredToken>>,
+    /// Cached result of `list_user_playlists()` so we only fetch the full
+    /// library once per worker run instead of once per playlist.
+    playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl AppleMusicProvider {
+    pub fn new(db_path: PathBuf, config: crate::config::Config) -> Self {
+        let has_token = crate::db::open_tuned(&db_path)
+            .ok()
+            .and_then(|conn| crate::db::load_credential_with_client(&conn, "applemusic").ok())
+            .flatten()
+            .is_some_and(|(json, _, _)| serde_json::from_str::<StoredToken>(&json).is_ok());
+        Self {
+            client: Client::new(),
+            db_path,
+            config,
+            has_token,
+            token: tokio::sync::Mutex::new(None),
+            playlist_cache: tokio::sync::Mutex::new(None),
+        }
+    }
+    fn is_authenticated(&self) -> bool {
+        self.has_token
+    }
+    fn name(&self) -> &str {
+        "applemusic"
+    }
+
+    fn api_base() -> String {
+        env::var("APPLE_MUSIC_API_BASE").unwrap_or_else(|_| "https://api.music.apple.com/v1".into())
+    }
+
+    /// Strip the `applemusic:song:` prefix, returning `None` for empty ids.
+    fn song_id(uri: &str) -> Option<&str> {
+        let id = uri.strip_prefix(URI_PREFIX).unwrap_or(uri).trim();
+        if id.is_empty() || id.contains(':') {
+            None
+        } else {
+            Some(id)
+        }
+    }
+
+    async fn load_token_from_db(&self) -> Result<Option<StoredToken>> {
+        let db_path = self.db_path.clone();
+        let json_opt =
+            tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
+                let conn = crate::db::open_tuned(db_path)?;
+                Ok(crate::db::load_credential_with_client(&conn, "applemusic")?
+                    .map(|(json, _, _)| json))
+            })
+            .await??;
+
+        if let Some(s) = json_opt {
+            let st: StoredToken =
+                serde_json::from_str(&s).map_err(|e| anyhow!("parse token json: {}", e))?;
+            Ok(Some(st))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Sign a new developer token with the stored key and persist it.
+    async fn renew_developer_token(&self, st: &mut StoredToken) -> Result<()> {
+        let (Some(team_id), Some(key_id), Some(key_path)) =
+            (&st.team_id, &st.key_id, &st.private_key_path)
+        else {
+            return Err(anyhow!(
+                "apple music developer token expired and no signing key is stored; run `auth applemusic` again"
+            ));
+        };
+        let pem = std::fs::read_to_string(key_path)
+            .with_context(|| format!("reading MusicKit key {}", key_path.display()))?;
+        let (token, expires_at) =
+            sign_developer_token(team_id, key_id, &pem, Utc::now().timestamp())?;
+        st.developer_token = token;
+        st.developer_token_expires_at = expires_at;
+        save_token(&self.db_path, st).await?;
+        log::info!("Apple Music developer token renewed");
+        Ok(())
+    }
+
+    /// The stored token with a developer token valid for at least a minute.
+    async fn token(&self) -> Result<StoredToken> {
+        let mut lock = self.token.lock().await;
+        if lock.is_none() {
+            *lock = self.load_token_from_db().await?;
+        }
+        let st = lock
+            .as_mut()
+            .ok_or_else(|| anyhow!("no apple music token stored; run `auth applemusic`"))?;
+        if Utc::now().timestamp() >= st.developer_token_expires_at - 60 {
+            self.renew_developer_token(st).await?;
+        }
+        Ok(st.clone())
+    }
+
+    fn catalog_path(storefront: &str, rest: &str) -> String {
+        format!("{}/catalog/{}{}", Self::api_base(), storefront, rest)
+    }
+
+    /// Execute a request with the `Music-User-Token` header.
+    async fn send(&self, op: &str, spec: RequestSpec) -> ProviderResult<reqwest::Response> {
+        let st = self.token().await?;
+        let spec = spec.header("Music-User-Token", st.music_user_token);
+        self.execute_request(op, &spec).await
+    }
+
+    /// Like [`Self::send`], returning the JSON body (`Value::Null` for
+    /// empty bodies).
+    async fn request_json(
+        &self,
+        op: &str,
+        spec: RequestSpec,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> ProviderResult<Value> {
+        let resp = self.send(op, spec).await?;
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(resp, context, playlist_id).await);
+        }
+        let txt = resp.text().await?;
+        if txt.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&txt)?)
+    }
+
+    /// Collect the `data` items of a paginated library endpoint.  Apple
+    /// answers 404 for relationships without items (an empty playlist's
+    /// tracks), which yields an empty list here.
+    async fn list_pages(&self, op: &str, path: &str, context: &str) -> ProviderResult<Vec<Value>> {
+        let mut out = Vec::new();
+        loop {
+            let url = format!(
+                "{}{}?limit={}&offset={}",
+                Self::api_base(),
+                path,
+                PAGE_SIZE,
+                out.len()
+            );
+            let resp = self.send(op, RequestSpec::get(&url)).await?;
+            if resp.status() == reqwest::StatusCode::NOT_FOUND && out.is_empty() {
+                return Ok(out);
+            }
+            if !resp.status().is_success() {
+                return Err(ProviderError::from_response(resp, context, None).await);
+            }
+            let j: Value = resp.json().await?;
+            let page = j["data"].as_array().cloned().unwrap_or_default();
+            let done = page.is_empty() || j["next"].as_str().is_none();
+            out.extend(page);
+            if done {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// List all library playlists of the user as (id, name).
+    /// Results are cached for the lifetime of the provider instance.
+    pub async fn list_user_playlists(&self) -> Result<Vec<(String, String)>> {
+        {
+            let cache = self.playlist_cache.lock().await;
+            if let Some(ref cached) = *cache {
+                return Ok(cached.clone());
+            }
+        }
+        let playlists: Vec<(String, String)> = self
+            .list_pages(
+                "list_user_playlists",
+                "/me/library/playlists",
+                "list playlists failed",
+            )
+            .await?
+            .iter()
+            .filter_map(|pl| {
+                let id = pl["id"].as_str()?.to_string();
+                Some((
+                    id,
+                    pl["attributes"]["name"].as_str().unwrap_or("").to_string(),
+                ))
+            })
+            .collect();
+        *self.playlist_cache.lock().await = Some(playlists.clone());
+        Ok(playlists)
+    }
+
+    async fn cache_update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Vec<(String, String)>),
+    {
+        let mut cache = self.playlist_cache.lock().await;
+        if let Some(ref mut entries) = *cache {
+            f(entries);
+        }
+    }
+
+    fn track_match(song: &Value) -> Option<TrackMatch> {
+        let id = song["id"].as_str()?;
+        let attrs = &song["attributes"];
+        Some(TrackMatch {
+            uri: format!("{}{}", URI_PREFIX, id),
+            name: attrs["name"].as_str().map(str::to_string),
+            artist: attrs["artistName"].as_str().map(str::to_string),
+            album: attrs["albumName"].as_str().map(str::to_string),
+            duration_ms: attrs["durationInMillis"].as_u64(),
+            isrc: attrs["isrc"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// Store `token` as the "applemusic" credential.
+pub async fn save_token(db_path: &Path, token: &StoredToken) -> Result<()> {
+    let json = serde_json::to_string(token)?;
+    let key_id = token.key_id.clone();
+    let db_path = db_path.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let conn = crate::db::open_tuned(db_path)?;
+        crate::db::save_credential_raw(&conn, "applemusic", &json, key_id.as_deref(), None)?;
+        Ok(())
+    })
+    .await??;
+    Ok(())
+}
+
+#[async_trait]
+impl Provider for AppleMusicProvider {
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(format!("Bearer {}", self.token().await?.developer_token))
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // A 401 means the developer token was rejected: sign a new one when
+        // the key is stored, otherwise pick up a token from a new
+        // `auth applemusic` run.
+        let mut lock = self.token.lock().await;
+        *lock = self.load_token_from_db().await?;
+        if let Some(st) = lock.as_mut() {
+            if st.private_key_path.is_some() {
+                self.renew_developer_token(st).await?;
+            }
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        AppleMusicProvider::name(self)
+    }
+    fn is_authenticated(&self) -> bool {
+        AppleMusicProvider::is_authenticated(self)
+    }
+
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
+        // Reuse an existing playlist with the same name so a lost mapping
+        // does not create duplicates.
+        match self.list_user_playlists().await {
+            Ok(playlists) => {
+                if let Some((existing_id, _)) = playlists.iter().find(|(_id, n)| n == name) {
+                    return Ok(existing_id.clone());
+                }
+            }
+            Err(e) => {
+                // Non-fatal: if listing fails we fall through to create.
+                log::warn!(
+                    "AppleMusicProvider ensure_playlist: could not list playlists to check for '{}': {}",
+                    name,
+                    e
+                );
+            }
+        }
+
+        let url = format!("{}/me/library/playlists", Self::api_base());
+        let mut attributes = json!({ "name": name });
+        if !description.is_empty() {
+            attributes["description"] = json!(description);
+        }
+        let j = self
+            .request_json(
+                "ensure_playlist",
+                RequestSpec::post(&url).json(json!({ "attributes": attributes })),
+                "create playlist failed",
+                None,
+            )
+            .await?;
+        let id = j["data"][0]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no id"))?
+            .to_string();
+        let (id_c, name_c) = (id.clone(), name.to_string());
+        self.cache_update(move |entries| entries.push((id_c, name_c)))
+            .await;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        log::warn!(
+            "Apple Music cannot rename library playlists; {} keeps its name instead of '{}'",
+            playlist_id,
+            new_name
+        );
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        let data: Vec<Value> = uris
+            .iter()
+            .filter_map(|u| Self::song_id(u))
+            .map(|id| json!({ "id": id, "type": "songs" }))
+            .collect();
+        if data.is_empty() {
+            return Ok(());
+        }
+        let url = format!(
+            "{}/me/library/playlists/{}/tracks",
+            Self::api_base(),
+            playlist_id
+        );
+        self.request_json(
+            "add_tracks",
+            RequestSpec::post(&url).json(json!({ "data": data })),
+            "add tracks failed",
+            Some(playlist_id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        if !uris.is_empty() {
+            log::warn!(
+                "Apple Music cannot remove tracks from library playlists; {} track(s) stay in {}",
+                uris.len(),
+                playlist_id
+            );
+        }
+        Ok(())
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        // There is no delete endpoint for library playlists; forget the
+        // playlist like Spotify's unfollow, leaving it for the user to
+        // delete in the Music app.
+        log::warn!(
+            "Apple Music cannot delete library playlists; {} is no longer synced but stays in the library",
+            playlist_id
+        );
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+        Ok(())
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        // Library tracks have library ids; the catalog id the URIs use is
+        // in playParams (absent for uploaded songs, which are skipped).
+        let mut seen = HashSet::new();
+        Ok(self
+            .list_pages(
+                "list_playlist_tracks",
+                &format!("/me/library/playlists/{}/tracks", playlist_id),
+                "list playlist tracks failed",
+            )
+            .await?
+            .iter()
+            .filter_map(|t| {
+                t["attributes"]["playParams"]["catalogId"]
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .filter(|id| seen.insert(id.clone()))
+            .map(|id| format!("{}{}", URI_PREFIX, id))
+            .collect())
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
+        self.search_track_uri_with_album(title, artist, None).await
+    }
+
+    async fn search_track_uri_with_album(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+    ) -> ProviderResult<Option<String>> {
+        let mut found = self
+            .search_track_candidates(title, artist, album, 1)
+            .await?;
+        if found.is_empty() && album.is_some() {
+            found = self.search_track_candidates(title, artist, None, 1).await?;
+        }
+        Ok(found.into_iter().next().map(|m| m.uri))
+    }
+
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        // Catalog search is plain full-text; the album only narrows it.
+        let mut term = format!("{} {}", artist, title);
+        if let Some(album) = album.filter(|a| !a.is_empty()) {
+            term.push(' ');
+            term.push_str(album);
+        }
+        let st = self.token().await?;
+        let url = format!(
+            "{}?types=songs&term={}&limit={}",
+            Self::catalog_path(&st.storefront, "/search"),
+            urlencoding::encode(&term),
+            limit.clamp(1, 25)
+        );
+        let j = self
+            .request_json(
+                "search_track_candidates",
+                RequestSpec::get(&url),
+                "search failed",
+                None,
+            )
+            .await?;
+        Ok(j["results"]["songs"]["data"]
+            .as_array()
+            .map(|items| items.iter().filter_map(Self::track_match).collect())
+            .unwrap_or_default())
+    }
+
+    async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
+        let isrc = isrc.trim();
+        if isrc.is_empty() {
+            return Ok(None);
+        }
+        let st = self.token().await?;
+        let url = format!(
+            "{}?filter[isrc]={}",
+            Self::catalog_path(&st.storefront, "/songs"),
+            urlencoding::encode(isrc)
+        );
+        let j = self
+            .request_json(
+                "search_track_uri_by_isrc",
+                RequestSpec::get(&url),
+                "isrc lookup failed",
+                None,
+            )
+            .await?;
+        Ok(j["data"]
+            .as_array()
+            .and_then(|items| items.iter().find_map(Self::track_match))
+            .map(|m| m.uri))
+    }
+
+    async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
+        let Some(id) = Self::song_id(uri) else {
+            return Ok(None);
+        };
+        let st = self.token().await?;
+        let url = Self::catalog_path(&st.storefront, &format!("/songs/{}", id));
+        let j = self
+            .request_json(
+                "lookup_track_isrc",
+                RequestSpec::get(&url),
+                "track lookup failed",
+                None,
+            )
+            .await?;
+        Ok(j["data"][0]["attributes"]["isrc"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string))
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        let url = format!("{}/me/library/playlists/{}", Self::api_base(), playlist_id);
+        match self
+            .request_json(
+                "playlist_is_valid",
+                RequestSpec::get(&url),
+                "get playlist failed",
+                Some(playlist_id),
+            )
+            .await
+        {
+            Ok(j) => Ok(Some(
+                j["data"][0]["attributes"]["name"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+            )),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: true,
+            supports_reorder: false,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: false,
+        }
+    }
+
+    fn validate_uri(&self, uri: &str) -> bool {
+        uri.starts_with(URI_PREFIX) && Self::song_id(uri).is_some()
+    }
+}
@@ -0,0 +1,89 @@
+use crate::api::apple_music::{save_token, sign_developer_token, StoredToken};
+use crate::config::Config;
+use anyhow::{anyhow, Context, Result};
+use reqwest::Client;
+use tracing::info;
+
+fn prompt(question: &str) -> Result<String> {
+    println!("{}", question);
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Interactive setup for Apple Music:
+/// 1. Ask for the team id, the MusicKit key id and the path of its `.p8`
+///    private key, and sign a developer token with them.
+/// 2. Print the developer token; the user authorizes their library with it
+///    through MusicKit JS (`await MusicKit.getInstance().authorize()`) and
+///    pastes the resulting Music-User-Token back.
+/// 3. Look up the account's storefront.
+/// 4. Store everything in the DB credentials table under provider
+///    "applemusic"; the key path is kept so expired developer tokens are
+///    signed again automatically.
+pub async fn run_apple_music_auth(cfg: &Config) -> Result<()> {
+    let team_id = prompt("Enter your Apple developer team id:")?;
+    if team_id.is_empty() {
+        return Err(anyhow!("no team id provided"));
+    }
+    let key_id = prompt("Enter your MusicKit key id:")?;
+    if key_id.is_empty() {
+        return Err(anyhow!("no key id provided"));
+    }
+    let key_path = prompt("Enter the path of the MusicKit private key (.p8):")?;
+    let key_path = std::fs::canonicalize(&key_path)
+        .with_context(|| format!("reading MusicKit key {}", key_path))?;
+    let pem = std::fs::read_to_string(&key_path)
+        .with_context(|| format!("reading MusicKit key {}", key_path.display()))?;
+    let (developer_token, expires_at) =
+        sign_developer_token(&team_id, &key_id, &pem, chrono::Utc::now().timestamp())?;
+
+    println!(
+        "\nDeveloper token:\n\n{}\n\nConfigure MusicKit JS with this token, run \
+         `await MusicKit.getInstance().authorize()` in the browser console and \
+         paste the returned Music-User-Token here:",
+        developer_token
+    );
+    let music_user_token = prompt("")?;
+    if music_user_token.is_empty() {
+        return Err(anyhow!("no Music-User-Token provided"));
+    }
+
+    let base = std::env::var("APPLE_MUSIC_API_BASE")
+        .unwrap_or_else(|_| "https://api.music.apple.com/v1".into());
+    let resp = Client::new()
+        .get(format!("{}/me/storefront", base))
+        .bearer_auth(&developer_token)
+        .header("Music-User-Token", &music_user_token)
+        .send()
+        .await?;
+    let status = resp.status();
+    let txt = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!("storefront lookup failed: {} => {}", status, txt));
+    }
+    let j: serde_json::Value = serde_json::from_str(&txt)?;
+    let storefront = j["data"][0]["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("no storefront in response: {}", txt))?
+        .to_string();
+
+    let stored_token = StoredToken {
+        developer_token,
+        developer_token_expires_at: expires_at,
+        music_user_token,
+        storefront: storefront.clone(),
+        team_id: Some(team_id),
+        key_id: Some(key_id),
+        private_key_path: Some(key_path),
+    };
+    save_token(&cfg.db_path, &stored_token).await?;
+
+    info!("Apple Music token saved to DB for provider 'applemusic'");
+    println!(
+        "Saved token to DB (storefront {}). You can now run the worker which will use the Apple Music provider.",
+        storefront
+    );
+
+    Ok(())
+}
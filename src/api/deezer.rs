@@ -0,0 +1,587 @@
+use super::{
+    Provider, ProviderCapabilities, ProviderError, ProviderResult, RequestSpec, TrackMatch,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+
+/// Prefix of the URIs handed out by `search_track_uri`.
+const URI_PREFIX: &str = "deezer:track:";
+
+/// Page size used when listing playlists and playlist tracks.
+const PAGE_SIZE: usize = 100;
+
+/// Deezer error code for "quota exceeded" (50 requests per 5 seconds).
+const QUOTA_EXCEEDED: i64 = 4;
+/// Deezer error codes for a missing permission or an invalid access token.
+const PERMISSION_ERRORS: [i64; 2] = [200, 300];
+/// Deezer error code for an unknown object (track, playlist, ...).
+const DATA_NOT_FOUND: i64 = 800;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_at: i64, // epoch seconds, 0 = never (offline_access)
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// Deezer provider backed by the public REST API (`api.deezer.com`).
+/// Tracks are stored as `deezer:track:{id}` URIs.  Deezer has no refresh
+/// tokens: `auth deezer` asks for the `offline_access` permission, whose
+/// tokens do not expire.  Errors are mostly reported as HTTP 200 with an
+/// `error` object, which [`DeezerProvider::request_json`] maps to
+/// [`ProviderError`] variants.
+/// The endpoint may be overridden by the DEEZER_API_BASE env var (useful for tests).
+pub struct DeezerProvider {
+    client: Client,
+    db_path: std::path::PathBuf,
+    config: crate::config::Config,
+    has_token: bool,
+    token: tokio::sync::Mutex<Option<StoredToken>>,
+    /// Cached result of `list_user_playlists()` so we only fetch the full
+    /// library once per worker run instead of once per playlist.
+    playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl DeezerProvider {
+    pub fn new(db_path: std::path::PathBuf, config: crate::config::Config) -> Self {
+        let has_token = crate::db::open_tuned(&db_path)
+            .ok()
+            .and_then(|conn| crate::db::load_credential_with_client(&conn, "deezer").ok())
+            .flatten()
+            .is_some_and(|(json, _, _)| serde_json::from_str::<StoredToken>(&json).is_ok());
+        Self {
+            client: Client::new(),
+            db_path,
+            config,
+            has_token,
+            token: tokio::sync::Mutex::new(None),
+            playlist_cache: tokio::sync::Mutex::new(None),
+        }
+    }
+    fn is_authenticated(&self) -> bool {
+        self.has_token
+    }
+    fn name(&self) -> &str {
+        "deezer"
+    }
+
+    fn api_base() -> String {
+        env::var("DEEZER_API_BASE").unwrap_or_else(|_| "https://api.deezer.com".into())
+    }
+
+    /// Strip the `deezer:track:` prefix, returning `None` for non-numeric ids.
+    fn track_id(uri: &str) -> Option<&str> {
+        let id = uri.strip_prefix(URI_PREFIX).unwrap_or(uri).trim();
+        if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// Deezer ids are numbers in most responses; accept strings too.
+    fn id_string(v: &Value) -> Option<String> {
+        match v {
+            Value::Number(n) => Some(n.to_string()),
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    async fn load_token_from_db(&self) -> Result<Option<StoredToken>> {
+        let db_path = self.db_path.clone();
+        let json_opt =
+            tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
+                let conn = crate::db::open_tuned(db_path)?;
+                Ok(crate::db::load_credential_with_client(&conn, "deezer")?
+                    .map(|(json, _, _)| json))
+            })
+            .await??;
+
+        if let Some(s) = json_opt {
+            let st: StoredToken =
+                serde_json::from_str(&s).map_err(|e| anyhow!("parse token json: {}", e))?;
+            Ok(Some(st))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        let mut lock = self.token.lock().await;
+        if lock.is_none() {
+            *lock = self.load_token_from_db().await?;
+        }
+        let st = lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("no deezer token stored; run `auth deezer`"))?;
+        if st.expires_at != 0 && Utc::now().timestamp() >= st.expires_at {
+            return Err(anyhow!("deezer token expired; run `auth deezer` again"));
+        }
+        Ok(st.access_token.clone())
+    }
+
+    /// Build an API URL; the access token is sent as the `access_token`
+    /// query parameter, the only form Deezer documents.
+    async fn url(&self, path: &str, query: &[(&str, &str)]) -> ProviderResult<String> {
+        let token = self.access_token().await?;
+        let mut url = format!(
+            "{}{}?access_token={}",
+            Self::api_base(),
+            path,
+            urlencoding::encode(&token)
+        );
+        for (k, v) in query {
+            url.push_str(&format!("&{}={}", k, urlencoding::encode(v)));
+        }
+        Ok(url)
+    }
+
+    /// Execute a request and return its JSON body.  Deezer reports most
+    /// errors as `{"error": {"type", "message", "code"}}` with HTTP 200:
+    /// quota errors map to [`ProviderError::RateLimited`], token errors to
+    /// [`ProviderError::Unauthorized`] and unknown objects to
+    /// [`ProviderError::PlaylistNotFound`] when `playlist_id` is given, or
+    /// to `Value::Null` (a plain "not found") otherwise.
+    async fn request_json(
+        &self,
+        op: &str,
+        spec: &RequestSpec,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> ProviderResult<Value> {
+        let resp = self.execute_request(op, spec).await?;
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(resp, context, playlist_id).await);
+        }
+        let j: Value = resp.json().await?;
+        let Some(err) = j.get("error").filter(|e| e.is_object()) else {
+            return Ok(j);
+        };
+        let code = err["code"].as_i64().unwrap_or_default();
+        match (code, playlist_id) {
+            (QUOTA_EXCEEDED, _) => Err(ProviderError::RateLimited {
+                retry_after: Some(5),
+            }),
+            (c, _) if PERMISSION_ERRORS.contains(&c) => Err(ProviderError::Unauthorized),
+            (DATA_NOT_FOUND, Some(pid)) => Err(ProviderError::PlaylistNotFound {
+                playlist_id: pid.to_string(),
+            }),
+            (DATA_NOT_FOUND, None) => Ok(Value::Null),
+            _ => Err(ProviderError::Other(anyhow!(
+                "{}: {} (code {})",
+                context,
+                err["message"].as_str().unwrap_or(""),
+                code
+            ))),
+        }
+    }
+
+    /// Collect the `data` items of a paginated list endpoint.
+    async fn list_pages(
+        &self,
+        op: &str,
+        path: &str,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> ProviderResult<Vec<Value>> {
+        let mut out = Vec::new();
+        loop {
+            let index = out.len().to_string();
+            let limit = PAGE_SIZE.to_string();
+            let url = self
+                .url(path, &[("index", &index), ("limit", &limit)])
+                .await?;
+            let j = self
+                .request_json(op, &RequestSpec::get(&url), context, playlist_id)
+                .await?;
+            let page = j["data"].as_array().cloned().unwrap_or_default();
+            let done = page.is_empty() || j["next"].as_str().is_none();
+            out.extend(page);
+            if done {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// List all playlists of the authenticated user (except "Loved Tracks").
+    /// Results are cached for the lifetime of the provider instance.
+    pub async fn list_user_playlists(&self) -> Result<Vec<(String, String)>> {
+        {
+            let cache = self.playlist_cache.lock().await;
+            if let Some(ref cached) = *cache {
+                return Ok(cached.clone());
+            }
+        }
+        let playlists: Vec<(String, String)> = self
+            .list_pages(
+                "list_user_playlists",
+                "/user/me/playlists",
+                "list playlists failed",
+                None,
+            )
+            .await?
+            .iter()
+            .filter(|pl| !pl["is_loved_track"].as_bool().unwrap_or(false))
+            .filter_map(|pl| {
+                let id = Self::id_string(&pl["id"])?;
+                Some((id, pl["title"].as_str().unwrap_or("").to_string()))
+            })
+            .collect();
+        *self.playlist_cache.lock().await = Some(playlists.clone());
+        Ok(playlists)
+    }
+
+    async fn cache_update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Vec<(String, String)>),
+    {
+        let mut cache = self.playlist_cache.lock().await;
+        if let Some(ref mut entries) = *cache {
+            f(entries);
+        }
+    }
+
+    /// `POST /playlist/{id}` with the given fields (title, description).
+    async fn update_playlist(
+        &self,
+        op: &str,
+        playlist_id: &str,
+        fields: &[(&str, &str)],
+    ) -> ProviderResult<()> {
+        let url = self
+            .url(&format!("/playlist/{}", playlist_id), fields)
+            .await?;
+        self.request_json(
+            op,
+            &RequestSpec::post(&url),
+            "update playlist failed",
+            Some(playlist_id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// `POST` / `DELETE /playlist/{id}/tracks` for the track ids in `uris`.
+    async fn change_tracks(
+        &self,
+        op: &str,
+        playlist_id: &str,
+        uris: &[String],
+        add: bool,
+    ) -> ProviderResult<()> {
+        let songs: Vec<&str> = uris.iter().filter_map(|u| Self::track_id(u)).collect();
+        if songs.is_empty() {
+            return Ok(());
+        }
+        let url = self
+            .url(
+                &format!("/playlist/{}/tracks", playlist_id),
+                &[("songs", &songs.join(","))],
+            )
+            .await?;
+        let (spec, context) = if add {
+            (RequestSpec::post(&url), "add tracks failed")
+        } else {
+            (RequestSpec::delete(&url), "remove tracks failed")
+        };
+        self.request_json(op, &spec, context, Some(playlist_id))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for DeezerProvider {
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(format!("Bearer {}", self.access_token().await?))
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Deezer has no refresh grant; pick up a token stored by a new
+        // `auth deezer` run instead.
+        let mut lock = self.token.lock().await;
+        *lock = self.load_token_from_db().await?;
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        DeezerProvider::name(self)
+    }
+    fn is_authenticated(&self) -> bool {
+        DeezerProvider::is_authenticated(self)
+    }
+
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
+        // Reuse an existing playlist with the same title so a lost mapping
+        // does not create duplicates.
+        match self.list_user_playlists().await {
+            Ok(playlists) => {
+                if let Some((existing_id, _)) = playlists.iter().find(|(_id, n)| n == name) {
+                    return Ok(existing_id.clone());
+                }
+            }
+            Err(e) => {
+                // Non-fatal: if listing fails we fall through to create.
+                log::warn!(
+                    "DeezerProvider ensure_playlist: could not list playlists to check for '{}': {}",
+                    name,
+                    e
+                );
+            }
+        }
+
+        let url = self.url("/user/me/playlists", &[("title", name)]).await?;
+        let j = self
+            .request_json(
+                "ensure_playlist",
+                &RequestSpec::post(&url),
+                "create playlist failed",
+                None,
+            )
+            .await?;
+        let id = Self::id_string(&j["id"]).ok_or_else(|| anyhow!("no id"))?;
+        // Playlists are created with a title only.
+        if !description.is_empty() {
+            self.update_playlist_description(&id, description).await?;
+        }
+        let (id_c, name_c) = (id.clone(), name.to_string());
+        self.cache_update(move |entries| entries.push((id_c, name_c)))
+            .await;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        self.update_playlist("rename_playlist", playlist_id, &[("title", new_name)])
+            .await?;
+        self.cache_update(|entries| {
+            for (id, n) in entries.iter_mut() {
+                if id == playlist_id {
+                    *n = new_name.to_string();
+                }
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.change_tracks("add_tracks", playlist_id, uris, true)
+            .await
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.change_tracks("remove_tracks", playlist_id, uris, false)
+            .await
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        let url = self.url(&format!("/playlist/{}", playlist_id), &[]).await?;
+        self.request_json(
+            "delete_playlist",
+            &RequestSpec::delete(&url),
+            "delete playlist failed",
+            Some(playlist_id),
+        )
+        .await?;
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+        Ok(())
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .list_pages(
+                "list_playlist_tracks",
+                &format!("/playlist/{}/tracks", playlist_id),
+                "list playlist tracks failed",
+                Some(playlist_id),
+            )
+            .await?
+            .iter()
+            .filter_map(|t| Self::id_string(&t["id"]))
+            .filter(|id| seen.insert(id.clone()))
+            .map(|id| format!("{}{}", URI_PREFIX, id))
+            .collect())
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
+        self.search_track_uri_with_album(title, artist, None).await
+    }
+
+    async fn search_track_uri_with_album(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+    ) -> ProviderResult<Option<String>> {
+        let mut found = self
+            .search_track_candidates(title, artist, album, 1)
+            .await?;
+        if found.is_empty() && album.is_some() {
+            found = self.search_track_candidates(title, artist, None, 1).await?;
+        }
+        Ok(found.into_iter().next().map(|m| m.uri))
+    }
+
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        // Deezer's advanced search syntax: field:"value".
+        let mut q = format!("artist:\"{}\" track:\"{}\"", artist, title);
+        if let Some(album) = album.filter(|a| !a.is_empty()) {
+            q.push_str(&format!(" album:\"{}\"", album));
+        }
+        let limit = limit.max(1).to_string();
+        let url = self
+            .url("/search/track", &[("q", &q), ("limit", &limit)])
+            .await?;
+        let j = self
+            .request_json(
+                "search_track_candidates",
+                &RequestSpec::get(&url),
+                "search failed",
+                None,
+            )
+            .await?;
+        Ok(j["data"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|t| {
+                        let id = Self::id_string(&t["id"])?;
+                        Some(TrackMatch {
+                            uri: format!("{}{}", URI_PREFIX, id),
+                            name: t["title"].as_str().map(str::to_string),
+                            artist: t["artist"]["name"].as_str().map(str::to_string),
+                            album: t["album"]["title"].as_str().map(str::to_string),
+                            duration_ms: t["duration"].as_u64().map(|s| s * 1000),
+                            isrc: t["isrc"].as_str().map(str::to_string),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
+        // Deezer resolves ISRCs directly: GET /track/isrc:{isrc}.
+        let isrc = isrc.trim();
+        if isrc.is_empty() {
+            return Ok(None);
+        }
+        let url = self
+            .url(&format!("/track/isrc:{}", urlencoding::encode(isrc)), &[])
+            .await?;
+        let j = self
+            .request_json(
+                "search_track_uri_by_isrc",
+                &RequestSpec::get(&url),
+                "isrc lookup failed",
+                None,
+            )
+            .await?;
+        Ok(Self::id_string(&j["id"]).map(|id| format!("{}{}", URI_PREFIX, id)))
+    }
+
+    async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
+        let Some(id) = Self::track_id(uri) else {
+            return Ok(None);
+        };
+        let url = self.url(&format!("/track/{}", id), &[]).await?;
+        let j = self
+            .request_json(
+                "lookup_track_isrc",
+                &RequestSpec::get(&url),
+                "track lookup failed",
+                None,
+            )
+            .await?;
+        Ok(j["isrc"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string))
+    }
+
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        let Some(id) = Self::track_id(uri) else {
+            return Ok(None);
+        };
+        let url = self.url(&format!("/track/{}", id), &[]).await?;
+        let j = self
+            .request_json(
+                "track_exists",
+                &RequestSpec::get(&url),
+                "track lookup failed",
+                None,
+            )
+            .await?;
+        // A removed track is a DataNotFound error, read as Null.
+        Ok(Some(!j.is_null()))
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        let url = self.url(&format!("/playlist/{}", playlist_id), &[]).await?;
+        let j = self
+            .request_json(
+                "playlist_is_valid",
+                &RequestSpec::get(&url),
+                "get playlist failed",
+                None,
+            )
+            .await?;
+        Ok(j["title"].as_str().map(str::to_string))
+    }
+
+    async fn update_playlist_description(
+        &self,
+        playlist_id: &str,
+        description: &str,
+    ) -> ProviderResult<()> {
+        self.update_playlist(
+            "update_playlist_description",
+            playlist_id,
+            &[("description", description)],
+        )
+        .await
+    }
+
+    async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: true,
+            supports_reorder: false,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
+    }
+
+    fn validate_uri(&self, uri: &str) -> bool {
+        uri.starts_with(URI_PREFIX) && Self::track_id(uri).is_some()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::Config;
+use crate::db;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use tracing::info;
+use url::Url;
+
+/// Permissions requested from Deezer; `offline_access` makes the access
+/// token permanent, since Deezer has no refresh tokens.
+const PERMS: &str = "basic_access,manage_library,delete_library,offline_access";
+
+/// Manual OAuth helper for Deezer, following the same paste-the-redirect-URL
+/// flow as the YouTube Music helper:
+/// 1. Build the Deezer authorization URL for the app and print it.
+/// 2. User approves and copies the full redirect URL back into this CLI.
+/// 3. The `code` param is exchanged for an access token.
+/// 4. The token is stored in the DB credentials table under provider "deezer".
+pub async fn run_deezer_auth(cfg: &Config) -> Result<()> {
+    use std::io;
+
+    println!("Enter your Deezer application id:");
+    let mut app_id = String::new();
+    io::stdin().read_line(&mut app_id)?;
+    let app_id = app_id.trim().to_string();
+    if app_id.is_empty() {
+        return Err(anyhow!("no application id provided"));
+    }
+
+    println!("Enter your Deezer application secret:");
+    let mut secret = String::new();
+    io::stdin().read_line(&mut secret)?;
+    let secret = secret.trim().to_string();
+    if secret.is_empty() {
+        return Err(anyhow!("no application secret provided"));
+    }
+
+    println!("Enter your redirect URI (leave blank for http://127.0.0.1:8888/):");
+    let mut redirect_uri = String::new();
+    io::stdin().read_line(&mut redirect_uri)?;
+    let redirect_uri = {
+        let trimmed = redirect_uri.trim();
+        if trimmed.is_empty() {
+            "http://127.0.0.1:8888/".to_string()
+        } else {
+            trimmed.to_string()
+        }
+    };
+
+    let mut url = Url::parse("https://connect.deezer.com/oauth/auth.php")?;
+    url.query_pairs_mut()
+        .append_pair("app_id", &app_id)
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("perms", PERMS);
+
+    println!(
+        "Open this URL in your browser and authorize the application:\n\n{}\n",
+        url
+    );
+    println!("After authorizing, you'll be redirected to your redirect URI. Copy the full redirect URL and paste it here.");
+    println!("Paste redirect URL:");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    let parsed = Url::parse(input).map_err(|e| anyhow!("invalid url pasted: {}", e))?;
+    if let Some((_, reason)) = parsed.query_pairs().find(|(k, _)| k == "error_reason") {
+        return Err(anyhow!("authorization refused: {}", reason));
+    }
+    let code = parsed
+        .query_pairs()
+        .find(|(k, _)| k == "code")
+        .ok_or_else(|| anyhow!("no code in redirect URL"))?
+        .1
+        .into_owned();
+
+    // Exchange code for a token; `output=json` avoids the default
+    // form-encoded response.
+    let mut token_url = Url::parse("https://connect.deezer.com/oauth/access_token.php")?;
+    token_url
+        .query_pairs_mut()
+        .append_pair("app_id", &app_id)
+        .append_pair("secret", &secret)
+        .append_pair("code", &code)
+        .append_pair("output", "json");
+    let resp = Client::new().get(token_url).send().await?;
+    let status = resp.status();
+    let txt = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!("token exchange failed: {} => {}", status, txt));
+    }
+    // An invalid code yields a plain-text "wrong code" body.
+    let j: serde_json::Value =
+        serde_json::from_str(&txt).map_err(|_| anyhow!("token exchange failed: {}", txt.trim()))?;
+    let access_token = j["access_token"]
+        .as_str()
+        .ok_or_else(|| anyhow!("no access_token in response: {}", txt))?
+        .to_string();
+    // `expires` is 0 for offline_access tokens and is sometimes sent as a string.
+    let expires = j["expires"]
+        .as_i64()
+        .or_else(|| j["expires"].as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(0);
+    let stored_token = crate::api::deezer::StoredToken {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_at: if expires > 0 {
+            chrono::Utc::now().timestamp() + expires
+        } else {
+            0
+        },
+        refresh_token: None,
+        scope: Some(PERMS.to_string()),
+    };
+    let token_json = serde_json::to_string(&stored_token)?;
+    let db_path = cfg.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let conn = crate::db::open_tuned(db_path)?;
+        db::save_credential_raw(&conn, "deezer", &token_json, Some(&app_id), Some(&secret))?;
+        Ok(())
+    })
+    .await??;
+
+    info!("Deezer token saved to DB for provider 'deezer'");
+    println!("Saved token to DB. You can now run the worker which will use the Deezer provider.");
+
+    Ok(())
+}
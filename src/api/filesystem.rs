@@ -0,0 +1,298 @@
+use super::{Provider, ProviderCapabilities, ProviderError, ProviderResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the file in the output directory that maps playlist ids to
+/// their current names.
+const INDEX_FILE: &str = ".playlist-ids.json";
+
+/// Export "provider" for self-hosted players such as Navidrome: instead of
+/// talking to an API, every remote playlist is an `.m3u` file in
+/// `filesystem_export_dir`, e.g. the server's playlist folder.  Tracks need
+/// no search: a local file maps straight to its path on the server, which
+/// is `root_folder` replaced by `filesystem_export_path_prefix` (see
+/// [`FileSystemProvider::server_path`]); the URIs are those paths.
+///
+/// Playlist ids are the file names chosen at creation and stay the same
+/// when a playlist is renamed; the current name of each id is kept in
+/// `.playlist-ids.json` next to the playlists.  The name is also written as
+/// a `#PLAYLIST:` line, which Navidrome shows instead of the file name.
+pub struct FileSystemProvider {
+    client: reqwest::Client,
+    output_dir: PathBuf,
+    path_prefix: String,
+    config: crate::config::Config,
+    /// Serializes the read-modify-write cycles on the index and playlists.
+    lock: tokio::sync::Mutex<()>,
+}
+
+/// File stem used for a playlist name: path separators become
+/// [`crate::config::DEFAULT_FLATTENING_DELIMITER`].
+fn file_stem(name: &str) -> String {
+    let stem = name
+        .trim_matches(['/', '\\'])
+        .replace(['/', '\\'], crate::config::DEFAULT_FLATTENING_DELIMITER);
+    if stem.trim().is_empty() {
+        "playlist".to_string()
+    } else {
+        stem
+    }
+}
+
+impl FileSystemProvider {
+    pub fn new(config: crate::config::Config) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            output_dir: config.filesystem_export_dir.clone(),
+            path_prefix: config.filesystem_export_path_prefix.clone(),
+            config,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Path of `local_path` as the server sees it: the root-relative path
+    /// (extra roots keep their label, as in playlist keys) under
+    /// `filesystem_export_path_prefix`.  Without a prefix, or for files
+    /// outside the roots, the local path is used as is.
+    pub fn server_path(&self, local_path: &Path) -> String {
+        let prefix = self.path_prefix.trim_end_matches('/');
+        if self.path_prefix.is_empty() || self.config.root_for_path(local_path).is_none() {
+            return local_path.display().to_string();
+        }
+        let rel = self
+            .config
+            .playlist_key_for_folder(local_path)
+            .replace('\\', "/");
+        format!("{}/{}", prefix, rel.trim_start_matches('/'))
+    }
+
+    fn load_index(&self) -> Result<BTreeMap<String, String>> {
+        match std::fs::read_to_string(self.output_dir.join(INDEX_FILE)) {
+            Ok(json) => serde_json::from_str(&json).context("parsing playlist index"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).context("reading playlist index"),
+        }
+    }
+
+    fn save_index(&self, index: &BTreeMap<String, String>) -> Result<()> {
+        let json = serde_json::to_string_pretty(index)?;
+        crate::playlist::write_atomically(&self.output_dir.join(INDEX_FILE), |f| {
+            use std::io::Write;
+            f.write_all(json.as_bytes())
+        })
+    }
+
+    /// The file and name of playlist `id`, or PlaylistNotFound.
+    fn locate(&self, id: &str) -> ProviderResult<(PathBuf, String)> {
+        let name =
+            self.load_index()?
+                .get(id)
+                .cloned()
+                .ok_or_else(|| ProviderError::PlaylistNotFound {
+                    playlist_id: id.to_string(),
+                })?;
+        let path = self.output_dir.join(format!("{}.m3u", file_stem(&name)));
+        if !path.exists() {
+            return Err(ProviderError::PlaylistNotFound {
+                playlist_id: id.to_string(),
+            });
+        }
+        Ok((path, name))
+    }
+
+    fn write_playlist(path: &Path, name: &str, tracks: &[String]) -> Result<()> {
+        crate::playlist::write_atomically(path, |f| {
+            use std::io::Write;
+            writeln!(f, "#EXTM3U")?;
+            writeln!(f, "#PLAYLIST:{}", name)?;
+            for t in tracks {
+                writeln!(f, "{}", t)?;
+            }
+            Ok(())
+        })
+        .with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Rewrite the tracks of playlist `id` with `edit`.
+    async fn edit_tracks(
+        &self,
+        id: &str,
+        edit: impl FnOnce(&mut Vec<String>),
+    ) -> ProviderResult<()> {
+        let _guard = self.lock.lock().await;
+        let (path, name) = self.locate(id)?;
+        let mut tracks = crate::playlist::read_playlist_entries(&path)?;
+        edit(&mut tracks);
+        Self::write_playlist(&path, &name, &tracks)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for FileSystemProvider {
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(String::new())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+    fn is_authenticated(&self) -> bool {
+        true
+    }
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: false,
+            supports_reorder: true,
+            supports_cover_art: false,
+            supports_positional_add: true,
+            hard_delete: true,
+        }
+    }
+    fn local_track_uri(&self, local_path: &Path) -> Option<String> {
+        Some(self.server_path(local_path))
+    }
+
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
+        let _guard = self.lock.lock().await;
+        std::fs::create_dir_all(&self.output_dir)
+            .with_context(|| format!("creating {}", self.output_dir.display()))?;
+        let mut index = self.load_index()?;
+        let stem = file_stem(name);
+        if let Some((id, _)) = index.iter().find(|(_, n)| file_stem(n) == stem) {
+            let id = id.clone();
+            let path = self.output_dir.join(format!("{}.m3u", stem));
+            if !path.exists() {
+                Self::write_playlist(&path, name, &[])?;
+            }
+            return Ok(id);
+        }
+        // The stem becomes the id; a renamed playlist may still hold it.
+        let mut id = stem.clone();
+        let mut n = 2;
+        while index.contains_key(&id) {
+            id = format!("{} ({})", stem, n);
+            n += 1;
+        }
+        let path = self.output_dir.join(format!("{}.m3u", stem));
+        // Adopt an existing file of that name, keeping its tracks.
+        let tracks = if path.exists() {
+            crate::playlist::read_playlist_entries(&path)?
+        } else {
+            Vec::new()
+        };
+        Self::write_playlist(&path, name, &tracks)?;
+        index.insert(id.clone(), name.to_string());
+        self.save_index(&index)?;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        let _guard = self.lock.lock().await;
+        let (path, _) = self.locate(playlist_id)?;
+        let tracks = crate::playlist::read_playlist_entries(&path)?;
+        let new_path = self.output_dir.join(format!("{}.m3u", file_stem(new_name)));
+        Self::write_playlist(&new_path, new_name, &tracks)?;
+        if new_path != path {
+            std::fs::remove_file(&path)?;
+        }
+        let mut index = self.load_index()?;
+        index.insert(playlist_id.to_string(), new_name.to_string());
+        self.save_index(&index)?;
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.add_tracks_at(playlist_id, uris, None).await
+    }
+
+    async fn add_tracks_at(
+        &self,
+        playlist_id: &str,
+        uris: &[String],
+        position: Option<usize>,
+    ) -> ProviderResult<()> {
+        self.edit_tracks(playlist_id, |tracks| {
+            let new: Vec<String> = uris
+                .iter()
+                .filter(|u| !tracks.contains(u))
+                .cloned()
+                .collect();
+            let at = position.unwrap_or(tracks.len()).min(tracks.len());
+            tracks.splice(at..at, new);
+        })
+        .await
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.edit_tracks(playlist_id, |tracks| tracks.retain(|t| !uris.contains(t)))
+            .await
+    }
+
+    async fn reorder_tracks(
+        &self,
+        playlist_id: &str,
+        ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        self.edit_tracks(playlist_id, |tracks| {
+            // Listed tracks first in the given order, then any others.
+            let mut reordered: Vec<String> = ordered_uris
+                .iter()
+                .filter(|u| tracks.contains(u))
+                .cloned()
+                .collect();
+            reordered.extend(tracks.iter().filter(|t| !ordered_uris.contains(t)).cloned());
+            *tracks = reordered;
+        })
+        .await
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        let _guard = self.lock.lock().await;
+        let mut index = self.load_index()?;
+        if let Some(name) = index.remove(playlist_id) {
+            let path = self.output_dir.join(format!("{}.m3u", file_stem(&name)));
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            self.save_index(&index)?;
+        }
+        Ok(())
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        let (path, _) = self.locate(playlist_id)?;
+        Ok(crate::playlist::read_playlist_entries(&path)?)
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        match self.locate(playlist_id) {
+            Ok((_, name)) => Ok(Some(name)),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Tracks are mapped by path (see [`Provider::local_track_uri`]), so
+    /// there is nothing to search.
+    async fn search_track_uri(
+        &self,
+        _title: &str,
+        _artist: &str,
+    ) -> ProviderResult<Option<String>> {
+        Ok(None)
+    }
+}
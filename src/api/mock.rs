@@ -1,22 +1,95 @@
-use super::Provider;
-use anyhow::Result;
+use super::{Provider, ProviderCapabilities, ProviderResult};
 use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tracing::info;
 
+/// A playlist operation recorded by [`MockProvider`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    EnsurePlaylist {
+        name: String,
+    },
+    RenamePlaylist {
+        playlist_id: String,
+        new_name: String,
+    },
+    AddTracks {
+        playlist_id: String,
+        uris: Vec<String>,
+    },
+    InsertTracks {
+        playlist_id: String,
+        uris: Vec<String>,
+        position: usize,
+    },
+    RemoveTracks {
+        playlist_id: String,
+        uris: Vec<String>,
+    },
+    DeletePlaylist {
+        playlist_id: String,
+    },
+}
+
 /// A simple mock provider used in tests and when no real credentials are present.
 /// It logs operations and returns deterministic fake IDs/URIs.
+///
+/// Every playlist operation is also appended to a shared call log, and the
+/// tracks added/removed are kept per playlist so `list_playlist_tracks`
+/// reflects earlier calls.  Build it with [`MockProvider::authenticated`] to
+/// hand it to `worker::run_worker_once_with`.
 pub struct MockProvider {
     client: reqwest::Client,
+    authenticated: bool,
+    positional_add: bool,
+    calls: Arc<Mutex<Vec<MockCall>>>,
+    playlists: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockProvider {
     pub fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
+            authenticated: false,
+            positional_add: false,
+            calls: Arc::new(Mutex::new(Vec::new())),
+            playlists: Mutex::new(HashMap::new()),
         }
     }
+    /// A mock that reports itself as authenticated.
+    pub fn authenticated() -> Self {
+        Self {
+            authenticated: true,
+            ..Self::new()
+        }
+    }
+    /// Honour insert positions in `add_tracks_at`, recording them as
+    /// [`MockCall::InsertTracks`].
+    pub fn with_positional_add(mut self) -> Self {
+        self.positional_add = true;
+        self
+    }
+    /// Shared handle to the call log; stays valid after the provider is
+    /// moved into an `Arc<dyn Provider>`.
+    pub fn call_log(&self) -> Arc<Mutex<Vec<MockCall>>> {
+        self.calls.clone()
+    }
+    /// Snapshot of the calls recorded so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+    fn record(&self, call: MockCall) {
+        self.calls.lock().unwrap().push(call);
+    }
     fn is_authenticated(&self) -> bool {
-        false
+        self.authenticated
     }
     fn name(&self) -> &str {
         "mock"
@@ -28,10 +101,10 @@ impl Provider for MockProvider {
     fn http_client(&self) -> &reqwest::Client {
         &self.client
     }
-    async fn get_bearer(&self) -> Result<String> {
+    async fn get_bearer(&self) -> ProviderResult<String> {
         Ok("Bearer mock".to_string())
     }
-    async fn refresh_token(&self) -> Result<()> {
+    async fn refresh_token(&self) -> ProviderResult<()> {
         Ok(())
     }
     fn name(&self) -> &str {
@@ -40,53 +113,121 @@ impl Provider for MockProvider {
     fn is_authenticated(&self) -> bool {
         MockProvider::is_authenticated(self)
     }
-    async fn ensure_playlist(&self, name: &str, _description: &str) -> Result<String> {
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
         info!("MockProvider: ensure_playlist {}", name);
+        self.record(MockCall::EnsurePlaylist {
+            name: name.to_string(),
+        });
         Ok(format!("mock-playlist-{}", name))
     }
 
-    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> Result<()> {
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
         info!(
             "MockProvider: rename_playlist {} -> {}",
             playlist_id, new_name
         );
+        self.record(MockCall::RenamePlaylist {
+            playlist_id: playlist_id.to_string(),
+            new_name: new_name.to_string(),
+        });
         Ok(())
     }
 
-    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
         info!(
             "MockProvider: add_tracks {} -> {} tracks",
             playlist_id,
             uris.len()
         );
+        self.record(MockCall::AddTracks {
+            playlist_id: playlist_id.to_string(),
+            uris: uris.to_vec(),
+        });
+        self.playlists
+            .lock()
+            .unwrap()
+            .entry(playlist_id.to_string())
+            .or_default()
+            .extend(uris.iter().cloned());
         Ok(())
     }
 
-    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn add_tracks_at(
+        &self,
+        playlist_id: &str,
+        uris: &[String],
+        position: Option<usize>,
+    ) -> ProviderResult<()> {
+        let Some(position) = position.filter(|_| self.positional_add) else {
+            return self.add_tracks(playlist_id, uris).await;
+        };
+        info!(
+            "MockProvider: add_tracks {} -> {} tracks at {}",
+            playlist_id,
+            uris.len(),
+            position
+        );
+        self.record(MockCall::InsertTracks {
+            playlist_id: playlist_id.to_string(),
+            uris: uris.to_vec(),
+            position,
+        });
+        let mut playlists = self.playlists.lock().unwrap();
+        let tracks = playlists.entry(playlist_id.to_string()).or_default();
+        let at = position.min(tracks.len());
+        tracks.splice(at..at, uris.iter().cloned());
+        Ok(())
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
         info!(
             "MockProvider: remove_tracks {} -> {} tracks",
             playlist_id,
             uris.len()
         );
+        self.record(MockCall::RemoveTracks {
+            playlist_id: playlist_id.to_string(),
+            uris: uris.to_vec(),
+        });
+        if let Some(tracks) = self.playlists.lock().unwrap().get_mut(playlist_id) {
+            tracks.retain(|u| !uris.contains(u));
+        }
         Ok(())
     }
 
-    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
         info!("MockProvider: delete_playlist {}", playlist_id);
+        self.record(MockCall::DeletePlaylist {
+            playlist_id: playlist_id.to_string(),
+        });
+        self.playlists.lock().unwrap().remove(playlist_id);
         Ok(())
     }
 
-    async fn search_track_uri(&self, title: &str, artist: &str) -> Result<Option<String>> {
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
         info!("MockProvider: search {} - {}", title, artist);
         Ok(Some(format!("mock:track:{}:{}", title, artist)))
     }
 
-    async fn list_playlist_tracks(&self, playlist_id: &str) -> Result<Vec<String>> {
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
         info!("MockProvider: list_playlist_tracks {}", playlist_id);
-        Ok(Vec::new())
+        Ok(self
+            .playlists
+            .lock()
+            .unwrap()
+            .get(playlist_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_positional_add: self.positional_add,
+            ..ProviderCapabilities::default()
+        }
     }
 
-    async fn playlist_is_valid(&self, playlist_id: &str) -> Result<Option<String>> {
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
         info!("MockProvider: playlist_is_valid {}", playlist_id);
         Ok(Some(String::new()))
     }
@@ -1,12 +1,217 @@
+pub mod apple_music;
+pub mod apple_music_auth;
+pub mod deezer;
+pub mod deezer_auth;
+pub mod filesystem;
 pub mod mock;
+pub mod oauth_callback;
 pub mod pkce;
+pub mod plex;
+pub mod plex_auth;
+pub mod rate_limit;
+pub mod refresh_gate;
 pub mod spotify;
 pub mod spotify_auth;
+pub mod subsonic;
+pub mod subsonic_auth;
 pub mod tidal;
 pub mod tidal_auth;
+pub mod ytmusic;
+pub mod ytmusic_auth;
 
 use crate::config::Config;
-use anyhow::Result;
+use std::collections::HashMap;
+
+/// Batch size used by providers that do not declare their own limit.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 50;
+
+/// Number of search results the worker asks [`Provider::search_track_candidates`] for.
+pub const DEFAULT_SEARCH_CANDIDATES: usize = 10;
+
+/// Structured error returned by [`Provider`] operations.
+///
+/// The worker matches on these variants to decide whether to back off,
+/// recreate a missing playlist, or give up, instead of inspecting error
+/// strings.  Anything without a dedicated variant is carried as
+/// [`ProviderError::Other`].
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The remote playlist no longer exists (e.g. deleted by the user).
+    PlaylistNotFound {
+        playlist_id: String,
+    },
+    /// The provider asked us to slow down (HTTP 429).
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    /// Credentials were rejected even after a token refresh (HTTP 401).
+    Unauthorized,
+    Other(anyhow::Error),
+}
+
+/// Result alias used by all [`Provider`] methods.
+pub type ProviderResult<T> = std::result::Result<T, ProviderError>;
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::PlaylistNotFound { playlist_id } => {
+                write!(f, "playlist not found: {}", playlist_id)
+            }
+            ProviderError::RateLimited { retry_after } => {
+                write!(f, "rate_limited retry_after={:?}", retry_after)
+            }
+            ProviderError::Unauthorized => write!(f, "unauthorized"),
+            ProviderError::Other(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProviderError::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ProviderError {
+    fn from(e: anyhow::Error) -> Self {
+        // Preserve the variant when a ProviderError travelled through an
+        // anyhow-returning helper.
+        match e.downcast::<ProviderError>() {
+            Ok(pe) => pe,
+            Err(e) => ProviderError::Other(e),
+        }
+    }
+}
+
+macro_rules! provider_error_from {
+    ($($t:ty),*) => {
+        $(impl From<$t> for ProviderError {
+            fn from(e: $t) -> Self {
+                ProviderError::Other(e.into())
+            }
+        })*
+    };
+}
+provider_error_from!(
+    reqwest::Error,
+    serde_json::Error,
+    rusqlite::Error,
+    std::io::Error,
+    tokio::task::JoinError
+);
+
+impl ProviderError {
+    /// Build an error from a non-success HTTP response.
+    ///
+    /// 401 maps to [`ProviderError::Unauthorized`] and 429 to
+    /// [`ProviderError::RateLimited`] (honouring `Retry-After`).  When
+    /// `playlist_id` is given, a 404 maps to [`ProviderError::PlaylistNotFound`];
+    /// everything else becomes [`ProviderError::Other`] with `context`, the
+    /// status and the response body in the message.
+    pub async fn from_response(
+        resp: reqwest::Response,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> Self {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.trim().parse::<u64>().ok());
+        let body = resp.text().await.unwrap_or_default();
+        Self::from_status(status, &body, retry_after, context, playlist_id)
+    }
+
+    /// Synchronous core of [`Self::from_response`] for callers that have
+    /// already consumed the response body.
+    pub fn from_status(
+        status: reqwest::StatusCode,
+        body: &str,
+        retry_after: Option<u64>,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> Self {
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED => ProviderError::Unauthorized,
+            reqwest::StatusCode::TOO_MANY_REQUESTS => ProviderError::RateLimited { retry_after },
+            reqwest::StatusCode::NOT_FOUND if playlist_id.is_some() => {
+                ProviderError::PlaylistNotFound {
+                    playlist_id: playlist_id.unwrap_or_default().to_string(),
+                }
+            }
+            _ => ProviderError::Other(anyhow::anyhow!("{}: {} => {}", context, status, body)),
+        }
+    }
+}
+
+/// What a provider can do, returned by [`Provider::capabilities`].  The
+/// worker consults these flags instead of knowing about particular
+/// providers.
+///
+/// The [`Default`] matches the trait's default methods: folder nesting,
+/// ISRC lookups (the default finds nothing, so trying is harmless),
+/// playlist deletes that remove the playlist, and nothing else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProviderCapabilities {
+    /// Hierarchical playlist folders (`online_playlist_structure = "folders"`).
+    pub supports_folders: bool,
+    /// [`Provider::search_track_uri_by_isrc`] can find tracks; otherwise the
+    /// worker goes straight to metadata search.
+    pub supports_isrc_search: bool,
+    /// [`Provider::reorder_tracks`] reorders; otherwise `"mirror"` order
+    /// mode leaves the remote order alone.
+    pub supports_reorder: bool,
+    /// [`Provider::set_playlist_cover`] uploads covers; the worker only reads
+    /// cover files for providers that set this.
+    pub supports_cover_art: bool,
+    /// [`Provider::add_tracks_at`] honours its position; the worker then
+    /// inserts incremental adds where the local playlist has them.
+    pub supports_positional_add: bool,
+    /// [`Provider::delete_playlist`] deletes the playlist, rather than only
+    /// unfollowing or forgetting it (Spotify, Apple Music).
+    pub hard_delete: bool,
+}
+
+impl Default for ProviderCapabilities {
+    fn default() -> Self {
+        Self {
+            supports_folders: true,
+            supports_isrc_search: true,
+            supports_reorder: false,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
+    }
+}
+
+/// A search result returned by [`Provider::search_track_candidates`].
+///
+/// Every field but `uri` is None when the provider does not report it; a
+/// candidate without `duration_ms` is never rejected on duration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackMatch {
+    pub uri: String,
+    pub name: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub isrc: Option<String>,
+}
+
+impl TrackMatch {
+    pub fn from_uri(uri: String) -> Self {
+        Self {
+            uri,
+            ..Default::default()
+        }
+    }
+}
 
 /// Describes an HTTP request to be executed by [`Provider::execute_request`].
 ///
@@ -20,6 +225,9 @@ pub struct RequestSpec {
     /// `content-type` header IS supplied via [`RequestSpec::header`], the body
     /// is serialized manually so only one Content-Type header is sent.
     pub(crate) json: Option<serde_json::Value>,
+    /// Optional raw body (e.g. base64 image data), sent instead of `json`.
+    /// Set its media type with a `content-type` header.
+    pub(crate) body: Option<Vec<u8>>,
     /// Extra headers applied *after* the JSON body so they can override Content-Type.
     pub(crate) headers: Vec<(String, String)>,
 }
@@ -30,6 +238,7 @@ impl RequestSpec {
             method,
             url: url.into(),
             json: None,
+            body: None,
             headers: vec![],
         }
     }
@@ -55,6 +264,12 @@ impl RequestSpec {
         self
     }
 
+    /// Attach a raw body; pair it with a `content-type` header.
+    pub fn body(mut self, bytes: Vec<u8>) -> Self {
+        self.body = Some(bytes);
+        self
+    }
+
     /// Append an extra HTTP header.  Applied *after* the JSON body so it can
     /// override `Content-Type` when an API requires a custom media type.
     pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
@@ -64,7 +279,9 @@ impl RequestSpec {
 }
 
 /// Provider trait: a minimal set of operations the worker needs.
-/// Implementations: spotify::SpotifyProvider, mock::MockProvider, tidal::TidalProvider.
+/// Implementations: spotify::SpotifyProvider, mock::MockProvider, tidal::TidalProvider,
+/// subsonic::SubsonicProvider, ytmusic::YtMusicProvider, deezer::DeezerProvider,
+/// apple_music::AppleMusicProvider, filesystem::FileSystemProvider, plex::PlexProvider.
 #[async_trait::async_trait]
 pub trait Provider: Send + Sync {
     // ------------------------------------------------------------------
@@ -76,16 +293,40 @@ pub trait Provider: Send + Sync {
 
     /// Return a valid `"Bearer <token>"` string, loading and/or refreshing
     /// the stored access token as needed.
-    async fn get_bearer(&self) -> Result<String>;
+    async fn get_bearer(&self) -> ProviderResult<String>;
+
+    /// Force-refresh the access token.
+    async fn refresh_token(&self) -> ProviderResult<()>;
+
+    /// Number of token refreshes completed so far.  [`Self::execute_request`]
+    /// reads it before each attempt and hands it to
+    /// [`Self::refresh_token_since`] on a 401.  The default (no shared
+    /// refresh) is always 0.
+    fn refresh_generation(&self) -> u64 {
+        0
+    }
 
-    /// Force-refresh the access token.  Called automatically by
+    /// Force-refresh the access token unless a refresh completed since
+    /// generation `seen` (see [`Self::refresh_generation`]), so requests
+    /// rejected with the same stale token share one refresh even when their
+    /// 401s arrive one after another.  Called automatically by
     /// [`Self::execute_request`] when the server returns 401 Unauthorized.
-    async fn refresh_token(&self) -> Result<()>;
+    /// The default ignores `seen` and calls [`Self::refresh_token`].
+    async fn refresh_token_since(&self, _seen: u64) -> ProviderResult<()> {
+        self.refresh_token().await
+    }
 
     // ------------------------------------------------------------------
     // Provided: shared request execution with automatic retry / back-off
     // ------------------------------------------------------------------
 
+    /// Return the limiter gating this provider's HTTP requests, if any.
+    /// [`Self::execute_request`] waits on it before every attempt.  The
+    /// default is no limiting.
+    fn rate_limiter(&self) -> Option<&rate_limit::RateLimiter> {
+        None
+    }
+
     /// Return the project configuration for this provider.
     ///
     /// The default implementation returns a configuration with all fields at
@@ -103,7 +344,7 @@ pub trait Provider: Send + Sync {
 
     /// Execute the HTTP request described by `spec`, retrying automatically on:
     ///
-    /// - **401 Unauthorized** – calls [`Self::refresh_token`] once, then retries.
+    /// - **401 Unauthorized** – calls [`Self::refresh_token_since`] once, then retries.
     /// - **429 Too Many Requests** – sleeps for `Retry-After + 1` seconds, up to
     ///   [`Config::max_retries_on_error`] attempts.
     ///
@@ -112,12 +353,19 @@ pub trait Provider: Send + Sync {
     /// callers are responsible for checking `resp.status().is_success()`.
     ///
     /// **This method must not be overridden** by provider implementations.
-    async fn execute_request(&self, op: &str, spec: &RequestSpec) -> Result<reqwest::Response> {
+    async fn execute_request(
+        &self,
+        op: &str,
+        spec: &RequestSpec,
+    ) -> ProviderResult<reqwest::Response> {
         use reqwest::header::AUTHORIZATION;
         let max_retries = self.config().max_retries_on_error;
         let mut attempt: u32 = 0;
         loop {
             attempt += 1;
+            // Read before the bearer, so a refresh finishing after this
+            // request was sent is seen on its 401.
+            let seen = self.refresh_generation();
             let bearer = self.get_bearer().await?;
             let client = self.http_client();
             let mut builder = match spec.method {
@@ -126,7 +374,12 @@ pub trait Provider: Send + Sync {
                 reqwest::Method::PUT => client.put(&spec.url),
                 reqwest::Method::PATCH => client.patch(&spec.url),
                 reqwest::Method::DELETE => client.delete(&spec.url),
-                ref m => return Err(anyhow::anyhow!("execute_request: unsupported method {}", m)),
+                ref m => {
+                    return Err(ProviderError::Other(anyhow::anyhow!(
+                        "execute_request: unsupported method {}",
+                        m
+                    )))
+                }
             };
             builder = builder.header(AUTHORIZATION, &bearer);
             // If a content-type override is present in spec.headers, serialize the
@@ -141,18 +394,33 @@ pub trait Provider: Send + Sync {
             if let Some(body) = &spec.json {
                 if has_ct_override {
                     let bytes = serde_json::to_vec(body).map_err(|e| {
-                        anyhow::anyhow!("execute_request: JSON serialization failed: {}", e)
+                        ProviderError::Other(anyhow::anyhow!(
+                            "execute_request: JSON serialization failed: {}",
+                            e
+                        ))
                     })?;
                     builder = builder.body(bytes);
                 } else {
                     builder = builder.json(body);
                 }
+            } else if let Some(bytes) = &spec.body {
+                builder = builder.body(bytes.clone());
             }
             for (k, v) in &spec.headers {
                 builder = builder.header(k.as_str(), v.as_str());
             }
-            let resp = builder.send().await?;
+            if let Some(limiter) = self.rate_limiter() {
+                limiter.acquire().await;
+            }
+            let resp = match builder.send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    crate::metrics::provider_request(self.name(), "error");
+                    return Err(e.into());
+                }
+            };
             let status = resp.status();
+            crate::metrics::provider_request(self.name(), status.as_str());
 
             if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt <= max_retries {
                 let retry_after = resp
@@ -179,7 +447,7 @@ pub trait Provider: Send + Sync {
                     self.name(),
                     op,
                 );
-                self.refresh_token().await?;
+                self.refresh_token_since(seen).await?;
                 continue;
             }
 
@@ -192,46 +460,158 @@ pub trait Provider: Send + Sync {
     // ------------------------------------------------------------------
 
     /// Ensure playlist exists (create or fetch) and return remote playlist id.
-    async fn ensure_playlist(&self, name: &str, description: &str) -> Result<String>;
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String>;
 
     /// Rename a playlist remote id
-    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> Result<()>;
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()>;
 
     /// Add tracks (URIs) to playlist (batching done by caller)
-    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()>;
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()>;
+
+    /// Insert tracks so the first one lands at zero-based `position` (appended
+    /// when `None`).  Only called with a position when the provider's
+    /// capabilities include `supports_positional_add`; the default ignores
+    /// it and appends, leaving the order to the reorder pass.
+    async fn add_tracks_at(
+        &self,
+        playlist_id: &str,
+        uris: &[String],
+        _position: Option<usize>,
+    ) -> ProviderResult<()> {
+        self.add_tracks(playlist_id, uris).await
+    }
 
     /// Remove tracks (URIs) from playlist
-    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()>;
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()>;
 
     /// Delete a playlist entirely on the provider side
-    async fn delete_playlist(&self, playlist_id: &str) -> Result<()>;
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()>;
 
     /// List all track URIs currently in a remote playlist.
     /// Implementations should return a de-duplicated, stable list.
-    async fn list_playlist_tracks(&self, playlist_id: &str) -> Result<Vec<String>>;
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>>;
+
+    /// Reorder a remote playlist so its tracks follow `ordered_uris`.
+    ///
+    /// Called by the worker after adds/removes when `playlist_order_mode` is
+    /// `"mirror"` and the provider's capabilities include `supports_reorder`.
+    /// The default implementation is a no-op.
+    async fn reorder_tracks(
+        &self,
+        _playlist_id: &str,
+        _ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        Ok(())
+    }
 
     /// Search for a track by metadata: title, artist. Return a remote URI if found.
-    async fn search_track_uri(&self, title: &str, artist: &str) -> Result<Option<String>>;
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>>;
+
+    /// Like [`Self::search_track_uri`], preferring the version of the track
+    /// released on `album` (e.g. the single over a compilation or live
+    /// album).  The default ignores the album.
+    async fn search_track_uri_with_album(
+        &self,
+        title: &str,
+        artist: &str,
+        _album: Option<&str>,
+    ) -> ProviderResult<Option<String>> {
+        self.search_track_uri(title, artist).await
+    }
+
+    /// Up to `limit` search results for a track, best first, with their
+    /// metadata where the provider reports it so the worker can reject wrong
+    /// versions (karaoke, sped-up edits, ...) and `resolve-track` can show why
+    /// a match was chosen.  The default wraps
+    /// [`Self::search_track_uri_with_album`] and yields at most one candidate.
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        _limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        Ok(self
+            .search_track_uri_with_album(title, artist, album)
+            .await?
+            .map(TrackMatch::from_uri)
+            .into_iter()
+            .collect())
+    }
 
     /// Search for a track by ISRC, if supported by the provider. Default returns None.
-    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> Result<Option<String>> {
+    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
 
+    /// Look up several ISRCs at once, returning the URI found for each
+    /// requested ISRC (misses are absent).  Reconcile resolves a playlist's
+    /// ISRCs through this before searching by metadata.  The default does
+    /// one [`Self::search_track_uri_by_isrc`] per ISRC, skipping failed
+    /// lookups; providers whose API takes several ISRCs per request override
+    /// it.
+    async fn search_track_uris_by_isrc(
+        &self,
+        isrcs: &[String],
+    ) -> ProviderResult<HashMap<String, String>> {
+        let mut found = HashMap::new();
+        for isrc in isrcs {
+            match self.search_track_uri_by_isrc(isrc).await {
+                Ok(Some(uri)) => {
+                    found.insert(isrc.clone(), uri);
+                }
+                Ok(None) => {}
+                Err(e) => log::debug!("{} ISRC lookup {} failed: {}", self.name(), isrc, e),
+            }
+        }
+        Ok(found)
+    }
+
     /// Lookup track metadata (e.g., ISRC) given a resolved URI. Default returns None.
-    async fn lookup_track_isrc(&self, _uri: &str) -> Result<Option<String>> {
+    async fn lookup_track_isrc(&self, _uri: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
 
+    /// Check whether the track behind a resolved URI still exists, e.g.
+    /// after it was taken down or relinked.  `Ok(None)` means the provider
+    /// cannot tell.  The default treats a track whose ISRC can be looked up
+    /// as existing and anything else as unknown; providers with a cheap
+    /// lookup by id override it.
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        Ok(self.lookup_track_isrc(uri).await?.map(|_| true))
+    }
+
     /// Check whether a remote playlist id is still valid and accessible.
     ///
     /// Returns `Ok(Some(current_name))` when valid, `Ok(None)` when the playlist
     /// no longer exists or is inaccessible.  The default implementation always
     /// returns `Ok(Some(""))` (assumes perpetual validity).
-    async fn playlist_is_valid(&self, _playlist_id: &str) -> Result<Option<String>> {
+    async fn playlist_is_valid(&self, _playlist_id: &str) -> ProviderResult<Option<String>> {
         Ok(Some(String::new()))
     }
 
+    /// Replace a playlist's description (see `playlist_description_template`).
+    /// The default implementation is a no-op for providers that cannot update
+    /// descriptions after creation.
+    async fn update_playlist_description(
+        &self,
+        _playlist_id: &str,
+        _description: &str,
+    ) -> ProviderResult<()> {
+        Ok(())
+    }
+
+    /// Upload `image_bytes` (a JPEG or PNG read from the playlist folder) as the
+    /// playlist's cover.  Only called when the provider's capabilities
+    /// include `supports_cover_art`; the default implementation is a no-op.
+    async fn set_playlist_cover(
+        &self,
+        _playlist_id: &str,
+        _image_bytes: &[u8],
+    ) -> ProviderResult<()> {
+        Ok(())
+    }
+
     /// Evict a known-dead playlist id from the provider's playlist list cache
     /// (both in-memory and persisted) so that the next `ensure_playlist` call
     /// performs a live fetch rather than returning the stale UUID.
@@ -246,14 +626,30 @@ pub trait Provider: Send + Sync {
     /// Return true if the provider is authenticated and ready to process events.
     fn is_authenticated(&self) -> bool;
 
-    /// Whether this provider supports hierarchical playlist folders.
+    /// What this provider supports; see [`ProviderCapabilities`].
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    /// Shorthand for the `supports_folders` capability.
     fn supports_folder_nesting(&self) -> bool {
-        true
+        self.capabilities().supports_folders
+    }
+
+    /// Shorthand for the `supports_cover_art` capability.
+    fn supports_playlist_cover(&self) -> bool {
+        self.capabilities().supports_cover_art
+    }
+
+    /// Shorthand for the `supports_positional_add` capability.
+    fn supports_positional_add(&self) -> bool {
+        self.capabilities().supports_positional_add
     }
 
     /// Maximum number of track URIs to send in a single batch request.
-    fn max_batch_size(&self, cfg: &Config) -> usize {
-        cfg.max_batch_size_spotify
+    /// Providers with a configurable or documented limit override this.
+    fn max_batch_size(&self, _cfg: &Config) -> usize {
+        DEFAULT_MAX_BATCH_SIZE
     }
 
     /// Validate a resolved remote URI before it is sent to the provider.
@@ -261,4 +657,211 @@ pub trait Provider: Send + Sync {
     fn validate_uri(&self, _uri: &str) -> bool {
         true
     }
+
+    /// The URI of a local file for providers that address tracks by path
+    /// (see [`filesystem::FileSystemProvider`]).  When this returns Some the
+    /// worker uses it without consulting the track cache or searching.  The
+    /// default is None.
+    fn local_track_uri(&self, _local_path: &std::path::Path) -> Option<String> {
+        None
+    }
+}
+
+/// Pick the first candidate whose duration is within `tolerance_secs` of the
+/// local file's.  Candidates are accepted unconditionally when either duration
+/// is unknown or `tolerance_secs` is 0; rejected candidates are logged at
+/// debug level.
+pub fn pick_track_match(
+    candidates: Vec<TrackMatch>,
+    local_duration_ms: Option<u64>,
+    tolerance_secs: u64,
+) -> Option<TrackMatch> {
+    candidates
+        .into_iter()
+        .find(|m| within_duration_tolerance(m, local_duration_ms, tolerance_secs))
+}
+
+/// The duration check of [`pick_track_match`] for one candidate.
+fn within_duration_tolerance(
+    m: &TrackMatch,
+    local_duration_ms: Option<u64>,
+    tolerance_secs: u64,
+) -> bool {
+    let (Some(local), Some(remote)) = (local_duration_ms, m.duration_ms) else {
+        return true;
+    };
+    if tolerance_secs == 0 || local.abs_diff(remote) <= tolerance_secs * 1000 {
+        return true;
+    }
+    log::debug!(
+        "rejected match {} duration_ms={} local_duration_ms={} tolerance_secs={}",
+        m.uri,
+        remote,
+        local,
+        tolerance_secs
+    );
+    false
+}
+
+/// The local side of a metadata search, as scored by [`match_confidence`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalTrack<'a> {
+    pub title: &'a str,
+    /// Empty when only a title could be derived (e.g. from the file name).
+    pub artist: &'a str,
+    pub album: Option<&'a str>,
+    pub duration_ms: Option<u64>,
+}
+
+/// Lowercased alphanumeric words of `s`.
+fn match_tokens(s: &str) -> std::collections::HashSet<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// How well `candidate` matches `local`, from 0.0 to 1.0: the weighted mean
+/// of the title (0.4) and artist (0.3) word overlap, whether the albums
+/// match (0.15) and how close the durations are (0.15, falling to 0 at 30s
+/// apart).  Parts either side does not know are left out of the mean; a
+/// candidate with nothing to compare scores 1.0, so providers that only
+/// return ids are never abstained from.
+pub fn match_confidence(local: &LocalTrack, candidate: &TrackMatch) -> f64 {
+    fn overlap(a: &str, b: &str) -> Option<f64> {
+        let (a, b) = (match_tokens(a), match_tokens(b));
+        if a.is_empty() || b.is_empty() {
+            return None;
+        }
+        Some(2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64)
+    }
+    let parts = [
+        (
+            0.4,
+            candidate
+                .name
+                .as_deref()
+                .and_then(|n| overlap(local.title, n)),
+        ),
+        (
+            0.3,
+            candidate
+                .artist
+                .as_deref()
+                .and_then(|a| overlap(local.artist, a)),
+        ),
+        (
+            0.15,
+            local.album.zip(candidate.album.as_deref()).map(|(l, r)| {
+                if album_names_match(l, r) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }),
+        ),
+        (
+            0.15,
+            local
+                .duration_ms
+                .zip(candidate.duration_ms)
+                .map(|(l, r)| (1.0 - l.abs_diff(r) as f64 / 30_000.0).max(0.0)),
+        ),
+    ];
+    let (weight, score) = parts
+        .iter()
+        .filter_map(|(w, s)| s.map(|s| (*w, w * s)))
+        .fold((0.0, 0.0), |(tw, ts), (w, s)| (tw + w, ts + s));
+    if weight == 0.0 {
+        1.0
+    } else {
+        score / weight
+    }
+}
+
+/// How well the title and artist of `candidate` together cover the words of
+/// `stem` (a file name split on `" - "`), from 0.0 to 1.0; unlike
+/// [`match_confidence`] this does not depend on which side was taken as the
+/// artist.  None when the provider reports neither title nor artist.
+pub fn stem_match_score(stem: &str, candidate: &TrackMatch) -> Option<f64> {
+    let words = match_tokens(&format!(
+        "{} {}",
+        candidate.name.as_deref().unwrap_or(""),
+        candidate.artist.as_deref().unwrap_or("")
+    ));
+    let stem = match_tokens(stem);
+    if words.is_empty() || stem.is_empty() {
+        return None;
+    }
+    Some(2.0 * stem.intersection(&words).count() as f64 / (stem.len() + words.len()) as f64)
+}
+
+/// [`pick_track_match`] with a confidence floor.  With `min_confidence` 0
+/// the first candidate within the duration tolerance is picked as before;
+/// otherwise the best-scoring one (by [`match_confidence`]) is, and only
+/// when it scores at least `min_confidence`; below that the search abstains
+/// and returns None.  The confidence is logged either way so the threshold
+/// can be tuned.
+pub fn pick_confident_match(
+    candidates: Vec<TrackMatch>,
+    local: &LocalTrack,
+    tolerance_secs: u64,
+    min_confidence: f64,
+) -> Option<TrackMatch> {
+    let mut best: Option<(TrackMatch, f64)> = None;
+    for m in candidates
+        .into_iter()
+        .filter(|m| within_duration_tolerance(m, local.duration_ms, tolerance_secs))
+    {
+        let confidence = match_confidence(local, &m);
+        if best.as_ref().is_none_or(|(_, b)| confidence > *b) {
+            best = Some((m, confidence));
+        }
+        if min_confidence <= 0.0 {
+            break;
+        }
+    }
+    best.and_then(|(m, confidence)| {
+        if confidence < min_confidence {
+            log::info!(
+                "abstained from match {} for artist={:?} title={:?} confidence={:.2} min_match_confidence={:.2}",
+                m.uri,
+                local.artist,
+                local.title,
+                confidence,
+                min_confidence
+            );
+            None
+        } else {
+            log::debug!(
+                "matched {} for artist={:?} title={:?} confidence={:.2}",
+                m.uri,
+                local.artist,
+                local.title,
+                confidence
+            );
+            Some(m)
+        }
+    })
+}
+
+/// Loose album-name comparison for search disambiguation: case, punctuation
+/// and bracketed suffixes such as "(Deluxe Edition)" or "[Remastered]" are
+/// ignored.
+pub fn album_names_match(a: &str, b: &str) -> bool {
+    fn normalize(s: &str) -> String {
+        let mut depth = 0usize;
+        let mut out = String::new();
+        for c in s.chars() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth = depth.saturating_sub(1),
+                c if depth == 0 && c.is_alphanumeric() => out.extend(c.to_lowercase()),
+                _ => {}
+            }
+        }
+        out
+    }
+    let (a, b) = (normalize(a), normalize(b));
+    !a.is_empty() && a == b
 }
@@ -0,0 +1,193 @@
+//! One-shot loopback HTTP listener used by the interactive auth helpers to
+//! capture the OAuth `code` from the provider's redirect, so the user does
+//! not have to copy the redirect URL back into the terminal.
+//!
+//! Only plain `http://127.0.0.1:<port>/...` or `http://localhost:<port>/...`
+//! redirect URIs can be served; anything else makes [`CallbackListener::bind`]
+//! return `None` and the caller falls back to the paste flow.
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+use url::Url;
+
+/// How long to wait for the browser to hit the redirect URI.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct CallbackListener {
+    listener: TcpListener,
+    path: String,
+}
+
+impl CallbackListener {
+    /// Bind a listener for `redirect_uri`.  Returns `None` (after logging why)
+    /// when the URI is not a loopback http URI or the port cannot be bound.
+    pub fn bind(redirect_uri: &str) -> Option<Self> {
+        let url = Url::parse(redirect_uri).ok()?;
+        let host = url.host_str()?;
+        if url.scheme() != "http" || !matches!(host, "127.0.0.1" | "localhost") {
+            log::info!(
+                "redirect URI {} is not a loopback http URI; using paste flow",
+                redirect_uri
+            );
+            return None;
+        }
+        let port = url.port().unwrap_or(80);
+        match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => Some(Self {
+                listener,
+                path: url.path().to_string(),
+            }),
+            Err(e) => {
+                log::warn!(
+                    "could not bind 127.0.0.1:{} for the OAuth callback ({}); using paste flow",
+                    port,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Block until the browser is redirected to the listener and return the
+    /// `code` query parameter.  Requests for other paths (e.g. `/favicon.ico`)
+    /// are answered with 404 and ignored.  When `expected_state` is given the
+    /// `state` parameter must match it.
+    pub fn wait_for_code(self, expected_state: Option<&str>) -> Result<String> {
+        // Poll a non-blocking listener so an abandoned login cannot hang
+        // the CLI forever.
+        self.listener.set_nonblocking(true)?;
+        let deadline = std::time::Instant::now() + CALLBACK_TIMEOUT;
+        loop {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if std::time::Instant::now() >= deadline {
+                        return Err(anyhow!("timed out waiting for the OAuth redirect"));
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+            let mut request_line = String::new();
+            BufReader::new(&stream).read_line(&mut request_line)?;
+            // "GET /callback?code=...&state=... HTTP/1.1"
+            let target = request_line.split_whitespace().nth(1).unwrap_or("");
+            let parsed = Url::parse(&format!("http://127.0.0.1{}", target));
+            let parsed = match parsed {
+                Ok(u) if u.path() == self.path => u,
+                _ => {
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    );
+                    continue;
+                }
+            };
+            let param = |name: &str| {
+                parsed
+                    .query_pairs()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.into_owned())
+            };
+            let outcome = if let Some(err) = param("error") {
+                Err(anyhow!("authorization denied: {}", err))
+            } else if expected_state.is_some() && param("state").as_deref() != expected_state {
+                Err(anyhow!("OAuth state mismatch in redirect"))
+            } else {
+                param("code").ok_or_else(|| anyhow!("no code in redirect URL"))
+            };
+            let body = match outcome {
+                Ok(_) => "Authorization received. You can close this window.",
+                Err(_) => "Authorization failed. Check the terminal for details.",
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            return outcome;
+        }
+    }
+}
+
+/// Best-effort attempt to open `url` in the user's browser.  Failure is not
+/// an error: the URL is always printed as well.
+pub fn open_browser(url: &str) {
+    let cmd = if cfg!(target_os = "macos") {
+        ("open", vec![url])
+    } else if cfg!(target_os = "windows") {
+        ("cmd", vec!["/C", "start", "", url])
+    } else {
+        ("xdg-open", vec![url])
+    };
+    if let Err(e) = std::process::Command::new(cmd.0)
+        .args(&cmd.1)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+    {
+        log::debug!("could not open browser via {}: {}", cmd.0, e);
+    }
+}
+
+/// Obtain an authorization code for `auth_url`.
+///
+/// Unless `no_browser` is set, this binds a [`CallbackListener`] on
+/// `redirect_uri`, opens the browser and waits for the redirect.  If that is
+/// not possible (or `no_browser` is set) the user is asked to paste the full
+/// redirect URL instead.
+pub async fn obtain_code(
+    auth_url: &Url,
+    redirect_uri: &str,
+    state: &str,
+    no_browser: bool,
+) -> Result<String> {
+    let listener = if no_browser {
+        None
+    } else {
+        CallbackListener::bind(redirect_uri)
+    };
+    if let Some(listener) = listener {
+        println!(
+            "Opening your browser to authorize the application. If it does not open, visit:\n\n{}\n",
+            auth_url
+        );
+        open_browser(auth_url.as_str());
+        println!("Waiting for the redirect to {} ...", redirect_uri);
+        let state = state.to_string();
+        return tokio::task::spawn_blocking(move || listener.wait_for_code(Some(&state))).await?;
+    }
+
+    println!(
+        "Open this URL in your browser and authorize the application:\n\n{}\n",
+        auth_url
+    );
+    println!("After authorizing, you'll be redirected to your redirect URI. Copy the full redirect URL and paste it here.");
+    println!("Paste redirect URL:");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    code_from_redirect_url(input.trim(), state)
+}
+
+/// Extract the `code` from a pasted redirect URL, rejecting a mismatched
+/// `state` when one is present.
+pub fn code_from_redirect_url(redirect_url: &str, state: &str) -> Result<String> {
+    let parsed = Url::parse(redirect_url).map_err(|e| anyhow!("invalid url pasted: {}", e))?;
+    let param = |name: &str| {
+        parsed
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    if let Some(err) = param("error") {
+        return Err(anyhow!("authorization denied: {}", err));
+    }
+    if param("state").is_some_and(|s| s != state) {
+        return Err(anyhow!("OAuth state mismatch in redirect"));
+    }
+    param("code").ok_or_else(|| anyhow!("no code in redirect URL"))
+}
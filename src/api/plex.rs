@@ -0,0 +1,651 @@
+use super::{Provider, ProviderCapabilities, ProviderError, ProviderResult};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Client identifier sent as `X-Plex-Client-Identifier` / `X-Plex-Product`.
+const CLIENT_NAME: &str = "music-file-playlist-online-sync";
+/// Prefix of the URIs handed out by `search_track_uri`.
+const URI_PREFIX: &str = "plex:item:";
+/// Items are added as a comma-separated list in the `uri` query parameter,
+/// so keep batches small enough that the request line stays short.
+const MAX_BATCH_SIZE: usize = 50;
+/// Plex metadata type of music tracks.
+const TRACK_TYPE: &str = "10";
+
+/// Credentials stored as the `token_json` of the `plex` row in the
+/// credentials table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlexCredentials {
+    pub server_url: String,
+    /// The `X-Plex-Token` sent with every request.
+    pub token: String,
+    /// Title of the music library to use; the first music library when None.
+    #[serde(default)]
+    pub library: Option<String>,
+}
+
+impl PlexCredentials {
+    pub fn new(server_url: &str, token: &str, library: Option<&str>) -> Self {
+        Self {
+            server_url: server_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            library: library
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string),
+        }
+    }
+}
+
+/// The music library playlists are built from.
+struct Library {
+    section_id: String,
+    /// `machineIdentifier` of the server, needed for `server://` item URIs.
+    machine_id: String,
+}
+
+/// Provider for Plex Media Server playlists.  Requests carry the
+/// `X-Plex-Token` header, so like Subsonic this provider talks to the
+/// server directly instead of going through `execute_request`.
+///
+/// Local files are matched by path when the server sees the library under
+/// the same paths: [`PlexProvider::load_library`] indexes the file of every
+/// track, and [`Provider::local_track_uri`] looks local paths up there.
+/// Files the index does not know are searched by title and artist in the
+/// music section.
+pub struct PlexProvider {
+    client: Client,
+    db_path: std::path::PathBuf,
+    config: crate::config::Config,
+    creds: tokio::sync::Mutex<Option<PlexCredentials>>,
+    authenticated: bool,
+    library: tokio::sync::OnceCell<Library>,
+    /// File path on the server -> ratingKey, filled by `load_library`.
+    path_index: std::sync::OnceLock<HashMap<String, String>>,
+    /// Cached result of `list_user_playlists()` so we only fetch the full
+    /// list once per worker run instead of once per playlist.
+    playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl PlexProvider {
+    /// Build a provider from the credentials stored in the DB (if any).
+    pub fn new(db_path: std::path::PathBuf, config: crate::config::Config) -> Self {
+        let creds = Self::load_credentials(&db_path).ok().flatten();
+        Self::with_credentials(creds, db_path, config)
+    }
+
+    /// Build a provider with explicit credentials (used by the auth flow and
+    /// tests).
+    pub fn with_credentials(
+        creds: Option<PlexCredentials>,
+        db_path: std::path::PathBuf,
+        config: crate::config::Config,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            db_path,
+            config,
+            authenticated: creds.is_some(),
+            creds: tokio::sync::Mutex::new(creds),
+            library: tokio::sync::OnceCell::new(),
+            path_index: std::sync::OnceLock::new(),
+            playlist_cache: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn load_credentials(db_path: &std::path::Path) -> Result<Option<PlexCredentials>> {
+        let conn = crate::db::open_tuned(db_path)?;
+        match crate::db::load_credential_with_client(&conn, "plex")? {
+            Some((json, _, _)) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Strip the `plex:item:` prefix, returning `None` for empty keys.
+    fn item_key(uri: &str) -> Option<&str> {
+        let key = uri.strip_prefix(URI_PREFIX).unwrap_or(uri).trim();
+        if key.is_empty() {
+            None
+        } else {
+            Some(key)
+        }
+    }
+
+    /// Send a request to the server and return the parsed JSON body (Null
+    /// for an empty body).  When `playlist_id` is given a 404 maps to
+    /// `PlaylistNotFound`.
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        params: &[(&str, String)],
+        playlist_id: Option<&str>,
+    ) -> ProviderResult<serde_json::Value> {
+        let creds = self
+            .creds
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("no plex credentials stored"))?;
+        let url = format!("{}{}", creds.server_url, path);
+        let resp = match self
+            .client
+            .request(method, &url)
+            .query(params)
+            .header("X-Plex-Token", &creds.token)
+            .header("X-Plex-Client-Identifier", CLIENT_NAME)
+            .header("X-Plex-Product", CLIENT_NAME)
+            .header("Accept", "application/json")
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                crate::metrics::provider_request("plex", "error");
+                return Err(e.into());
+            }
+        };
+        crate::metrics::provider_request("plex", resp.status().as_str());
+        if !resp.status().is_success() {
+            let context = format!("plex {} failed", path);
+            return Err(ProviderError::from_response(resp, &context, playlist_id).await);
+        }
+        let text = resp.text().await?;
+        if text.trim().is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// The configured music library, resolved once.
+    async fn library(&self) -> ProviderResult<&Library> {
+        self.library
+            .get_or_try_init(|| async {
+                let identity = self.call(Method::GET, "/identity", &[], None).await?;
+                let machine_id = identity["MediaContainer"]["machineIdentifier"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                if machine_id.is_empty() {
+                    return Err(anyhow!("plex /identity returned no machineIdentifier").into());
+                }
+                let wanted = self
+                    .creds
+                    .lock()
+                    .await
+                    .as_ref()
+                    .and_then(|c| c.library.clone());
+                let sections = self
+                    .call(Method::GET, "/library/sections", &[], None)
+                    .await?;
+                let section = sections["MediaContainer"]["Directory"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|d| d["type"].as_str() == Some("artist"))
+                    .find(|d| match &wanted {
+                        Some(w) => d["title"]
+                            .as_str()
+                            .is_some_and(|t| t.trim().eq_ignore_ascii_case(w)),
+                        None => true,
+                    })
+                    .and_then(|d| json_key(&d["key"]))
+                    .ok_or_else(|| match &wanted {
+                        Some(w) => anyhow!("no plex music library named {:?}", w),
+                        None => anyhow!("no plex music library found"),
+                    })?;
+                Ok(Library {
+                    section_id: section,
+                    machine_id,
+                })
+            })
+            .await
+    }
+
+    /// Verify the stored credentials and resolve the music library.
+    pub async fn ping(&self) -> Result<()> {
+        self.library().await?;
+        Ok(())
+    }
+
+    /// Index the file path of every track in the music library so local
+    /// files on paths the server shares map to their item without a search.
+    /// Returns the number of indexed files; runs the request only once.
+    pub async fn load_library(&self) -> Result<usize> {
+        if let Some(index) = self.path_index.get() {
+            return Ok(index.len());
+        }
+        let section = self.library().await?.section_id.clone();
+        let body = self
+            .call(
+                Method::GET,
+                &format!("/library/sections/{}/all", section),
+                &[("type", TRACK_TYPE.to_string())],
+                None,
+            )
+            .await?;
+        let mut index = HashMap::new();
+        for item in body["MediaContainer"]["Metadata"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let Some(key) = json_key(&item["ratingKey"]) else {
+                continue;
+            };
+            for media in item["Media"].as_array().into_iter().flatten() {
+                for part in media["Part"].as_array().into_iter().flatten() {
+                    if let Some(file) = part["file"].as_str() {
+                        index.insert(file.to_string(), key.clone());
+                    }
+                }
+            }
+        }
+        let count = index.len();
+        let _ = self.path_index.set(index);
+        Ok(count)
+    }
+
+    /// `server://` URI of the given library items, as `/playlists` expects.
+    fn items_uri(library: &Library, keys: &[&str]) -> String {
+        format!(
+            "server://{}/com.plexapp.plugins.library/library/metadata/{}",
+            library.machine_id,
+            keys.join(",")
+        )
+    }
+
+    /// List all non-smart audio playlists as (ratingKey, title) pairs.
+    pub async fn list_user_playlists(&self) -> Result<Vec<(String, String)>> {
+        {
+            let cache = self.playlist_cache.lock().await;
+            if let Some(ref cached) = *cache {
+                return Ok(cached.clone());
+            }
+        }
+        let body = self
+            .call(
+                Method::GET,
+                "/playlists",
+                &[("playlistType", "audio".to_string())],
+                None,
+            )
+            .await?;
+        let out: Vec<(String, String)> = body["MediaContainer"]["Metadata"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|p| !p["smart"].as_bool().unwrap_or(false))
+            .filter_map(|p| {
+                Some((
+                    json_key(&p["ratingKey"])?,
+                    p["title"].as_str().unwrap_or("").to_string(),
+                ))
+            })
+            .collect();
+        *self.playlist_cache.lock().await = Some(out.clone());
+        Ok(out)
+    }
+
+    /// The (playlistItemID, ratingKey) pairs of a playlist in playlist
+    /// order; items are removed and moved by playlistItemID.
+    async fn playlist_items(&self, playlist_id: &str) -> ProviderResult<Vec<(String, String)>> {
+        let body = self
+            .call(
+                Method::GET,
+                &format!("/playlists/{}/items", playlist_id),
+                &[],
+                Some(playlist_id),
+            )
+            .await?;
+        Ok(body["MediaContainer"]["Metadata"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|i| Some((json_key(&i["playlistItemID"])?, json_key(&i["ratingKey"])?)))
+            .collect())
+    }
+
+    async fn cache_update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Vec<(String, String)>),
+    {
+        let mut cache = self.playlist_cache.lock().await;
+        if let Some(ref mut entries) = *cache {
+            f(entries);
+        }
+    }
+}
+
+/// Plex keys are usually strings, but some endpoints emit numbers.
+fn json_key(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Provider for PlexProvider {
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        // Plex has no bearer token; the token is sent as X-Plex-Token.
+        Ok(String::new())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Nothing expires; re-read the stored credentials in case they were
+        // replaced via `auth plex` while we were running.
+        let db_path = self.db_path.clone();
+        let loaded =
+            tokio::task::spawn_blocking(move || Self::load_credentials(&db_path)).await??;
+        if loaded.is_some() {
+            *self.creds.lock().await = loaded;
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        "plex"
+    }
+    fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
+        if let Some((id, _)) = self
+            .list_user_playlists()
+            .await?
+            .into_iter()
+            .find(|(_, n)| n == name)
+        {
+            return Ok(id);
+        }
+        let uri = format!(
+            "server://{}/com.plexapp.plugins.library",
+            self.library().await?.machine_id
+        );
+        let body = self
+            .call(
+                Method::POST,
+                "/playlists",
+                &[
+                    ("type", "audio".to_string()),
+                    ("title", name.to_string()),
+                    ("smart", "0".to_string()),
+                    ("uri", uri),
+                ],
+                None,
+            )
+            .await?;
+        let id = json_key(&body["MediaContainer"]["Metadata"][0]["ratingKey"])
+            .ok_or_else(|| anyhow!("plex playlist creation returned no ratingKey"))?;
+        if !description.is_empty() {
+            self.update_playlist_description(&id, description).await?;
+        }
+        let (id_c, name_c) = (id.clone(), name.to_string());
+        self.cache_update(move |entries| {
+            if !entries.iter().any(|(i, _)| i == &id_c) {
+                entries.push((id_c, name_c));
+            }
+        })
+        .await;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        self.call(
+            Method::PUT,
+            &format!("/playlists/{}", playlist_id),
+            &[("title", new_name.to_string())],
+            Some(playlist_id),
+        )
+        .await?;
+        self.cache_update(|entries| {
+            for (id, n) in entries.iter_mut() {
+                if id == playlist_id {
+                    *n = new_name.to_string();
+                }
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn update_playlist_description(
+        &self,
+        playlist_id: &str,
+        description: &str,
+    ) -> ProviderResult<()> {
+        self.call(
+            Method::PUT,
+            &format!("/playlists/{}", playlist_id),
+            &[("summary", description.to_string())],
+            Some(playlist_id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        let keys: Vec<&str> = uris.iter().filter_map(|u| Self::item_key(u)).collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let uri = Self::items_uri(self.library().await?, &keys);
+        self.call(
+            Method::PUT,
+            &format!("/playlists/{}/items", playlist_id),
+            &[("uri", uri)],
+            Some(playlist_id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        let wanted: HashSet<&str> = uris.iter().filter_map(|u| Self::item_key(u)).collect();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        for (item_id, _) in self
+            .playlist_items(playlist_id)
+            .await?
+            .into_iter()
+            .filter(|(_, key)| wanted.contains(key.as_str()))
+        {
+            self.call(
+                Method::DELETE,
+                &format!("/playlists/{}/items/{}", playlist_id, item_id),
+                &[],
+                Some(playlist_id),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn reorder_tracks(
+        &self,
+        playlist_id: &str,
+        ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        let items = self.playlist_items(playlist_id).await?;
+        // Playlist item ids in the wanted order: the first unused item of
+        // each listed track, then any others in their current order.
+        let mut used = vec![false; items.len()];
+        let mut desired: Vec<&str> = Vec::with_capacity(items.len());
+        for key in ordered_uris.iter().filter_map(|u| Self::item_key(u)) {
+            if let Some(i) = (0..items.len()).find(|&i| !used[i] && items[i].1 == key) {
+                used[i] = true;
+                desired.push(&items[i].0);
+            }
+        }
+        desired.extend(
+            (0..items.len())
+                .filter(|&i| !used[i])
+                .map(|i| items[i].0.as_str()),
+        );
+
+        // Move each item right after its predecessor, skipping the ones
+        // already in place.
+        let mut current: Vec<&str> = items.iter().map(|(id, _)| id.as_str()).collect();
+        for pos in 0..desired.len() {
+            if current[pos] == desired[pos] {
+                continue;
+            }
+            let params: Vec<(&str, String)> = match pos {
+                0 => vec![],
+                _ => vec![("after", desired[pos - 1].to_string())],
+            };
+            self.call(
+                Method::PUT,
+                &format!("/playlists/{}/items/{}/move", playlist_id, desired[pos]),
+                &params,
+                Some(playlist_id),
+            )
+            .await?;
+            current.retain(|id| *id != desired[pos]);
+            current.insert(pos, desired[pos]);
+        }
+        Ok(())
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        self.call(
+            Method::DELETE,
+            &format!("/playlists/{}", playlist_id),
+            &[],
+            Some(playlist_id),
+        )
+        .await?;
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+        Ok(())
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .playlist_items(playlist_id)
+            .await?
+            .into_iter()
+            .filter(|(_, key)| seen.insert(key.clone()))
+            .map(|(_, key)| format!("{}{}", URI_PREFIX, key))
+            .collect())
+    }
+
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        let Some(key) = Self::item_key(uri) else {
+            return Ok(None);
+        };
+        // Passing the key maps a 404 to PlaylistNotFound.
+        match self
+            .call(
+                Method::GET,
+                &format!("/library/metadata/{}", key),
+                &[],
+                Some(key),
+            )
+            .await
+        {
+            Ok(_) => Ok(Some(true)),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(Some(false)),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        match self
+            .call(
+                Method::GET,
+                &format!("/playlists/{}", playlist_id),
+                &[],
+                Some(playlist_id),
+            )
+            .await
+        {
+            Ok(body) => Ok(Some(
+                body["MediaContainer"]["Metadata"][0]["title"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+            )),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
+        let section = self.library().await?.section_id.clone();
+        let body = self
+            .call(
+                Method::GET,
+                &format!("/library/sections/{}/search", section),
+                &[
+                    ("type", TRACK_TYPE.to_string()),
+                    ("query", title.to_string()),
+                ],
+                None,
+            )
+            .await?;
+        let tracks = match body["MediaContainer"]["Metadata"].as_array() {
+            Some(t) if !t.is_empty() => t.clone(),
+            _ => return Ok(None),
+        };
+        // The search matches titles only; prefer the track by the wanted
+        // artist (the album artist, or the track artist on compilations)
+        // and fall back to an exact title match, then the top hit.
+        let eq = |v: &serde_json::Value, want: &str| {
+            v.as_str()
+                .is_some_and(|s| s.trim().eq_ignore_ascii_case(want.trim()))
+        };
+        let best = tracks
+            .iter()
+            .find(|t| {
+                eq(&t["title"], title)
+                    && (eq(&t["grandparentTitle"], artist) || eq(&t["originalTitle"], artist))
+            })
+            .or_else(|| tracks.iter().find(|t| eq(&t["title"], title)))
+            .unwrap_or(&tracks[0]);
+        Ok(json_key(&best["ratingKey"]).map(|key| format!("{}{}", URI_PREFIX, key)))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: false,
+            supports_reorder: true,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
+    }
+
+    fn max_batch_size(&self, _cfg: &crate::config::Config) -> usize {
+        MAX_BATCH_SIZE
+    }
+
+    fn validate_uri(&self, uri: &str) -> bool {
+        uri.starts_with(URI_PREFIX) && Self::item_key(uri).is_some()
+    }
+
+    fn local_track_uri(&self, local_path: &std::path::Path) -> Option<String> {
+        self.path_index
+            .get()?
+            .get(local_path.to_string_lossy().as_ref())
+            .map(|key| format!("{}{}", URI_PREFIX, key))
+    }
+}
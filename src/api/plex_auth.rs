@@ -0,0 +1,54 @@
+use crate::api::plex::{PlexCredentials, PlexProvider};
+use crate::config::Config;
+use crate::db;
+use anyhow::{anyhow, Result};
+use tracing::info;
+
+/// Interactive helper to store a Plex server URL and `X-Plex-Token` in the
+/// DB, along with the music library to sync into.
+pub async fn run_plex_auth(cfg: &Config) -> Result<()> {
+    use std::io;
+
+    let prompt = |label: &str| -> Result<String> {
+        println!("{}", label);
+        let mut s = String::new();
+        io::stdin().read_line(&mut s)?;
+        Ok(s.trim().to_string())
+    };
+
+    let server_url = prompt("Enter your Plex server URL (e.g. http://plex.local:32400):")?;
+    if server_url.is_empty() {
+        return Err(anyhow!("no server URL provided"));
+    }
+    let token = prompt("Enter your X-Plex-Token:")?;
+    if token.is_empty() {
+        return Err(anyhow!("no token provided"));
+    }
+    let library = prompt("Enter the name of the music library (blank for the first one):")?;
+
+    let creds = PlexCredentials::new(&server_url, &token, Some(&library));
+
+    // Verify before saving so a typo doesn't silently disable syncing.
+    let provider =
+        PlexProvider::with_credentials(Some(creds.clone()), cfg.db_path.clone(), cfg.clone());
+    provider
+        .ping()
+        .await
+        .map_err(|e| anyhow!("plex check failed: {}", e))?;
+
+    let token_json = serde_json::to_string(&creds)?;
+    let db_path = cfg.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let conn = crate::db::open_tuned(db_path)?;
+        db::save_credential_raw(&conn, "plex", &token_json, None, None)?;
+        Ok(())
+    })
+    .await??;
+
+    info!("Plex credentials saved to DB for provider 'plex'");
+    println!(
+        "Saved credentials to DB. You can now run the worker which will use the Plex provider."
+    );
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Token-bucket rate limiter shared by every HTTP request a provider makes.
+//!
+//! Providers hold an `Arc<RateLimiter>` so that clones (and every concurrent
+//! call on the same provider) draw from the same bucket.  The limiter only
+//! smooths the request rate; the 429 handling in
+//! [`super::Provider::execute_request`] remains the backstop.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    /// Maximum number of tokens the bucket can hold (burst size).
+    capacity: f64,
+    state: tokio::sync::Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Build a limiter allowing `requests_per_sec` requests per second with a
+    /// burst of the same size (at least one request).  Returns `None` when
+    /// `requests_per_sec` is zero, negative or not finite, which disables
+    /// limiting.
+    pub fn new(requests_per_sec: f64) -> Option<Arc<Self>> {
+        if !requests_per_sec.is_finite() || requests_per_sec <= 0.0 {
+            return None;
+        }
+        let capacity = requests_per_sec.max(1.0);
+        Some(Arc::new(Self {
+            rate: requests_per_sec,
+            capacity,
+            state: tokio::sync::Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }))
+    }
+
+    /// Wait until a request may be sent.
+    ///
+    /// A token is reserved immediately (the bucket may go negative) and the
+    /// caller then sleeps off the debt outside the lock, so concurrent callers
+    /// are spaced `1 / rate` seconds apart in arrival order.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.state.lock().await;
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+            bucket.last_refill = now;
+            bucket.tokens -= 1.0;
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / self.rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            log::trace!("rate limiter: waiting {:?} before next request", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
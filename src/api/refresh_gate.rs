@@ -0,0 +1,50 @@
+//! Per-provider serialization of OAuth token refreshes.
+//!
+//! Concurrent callers of `get_bearer` (or several requests answered with 401
+//! at once) would otherwise each send a refresh grant for the same refresh
+//! token.  Providers that rotate refresh tokens invalidate the old one on the
+//! first refresh, so the others fail and can leave the stored credentials
+//! unusable.  [`RefreshGate`] lets one refresh run at a time; callers that
+//! waited for a refresh started by another task use its result instead of
+//! refreshing again.
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default)]
+pub struct RefreshGate {
+    /// Held while a refresh is in progress.
+    lock: tokio::sync::Mutex<()>,
+    /// Number of refreshes that completed successfully.
+    generation: AtomicU64,
+}
+
+impl RefreshGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current generation.  Read it *before* looking at the token that
+    /// may need refreshing and pass it to [`RefreshGate::refresh`].
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Run `refresh` unless a refresh completed since generation `seen`, in
+    /// which case the token it stored is current and this returns right
+    /// away.  A failed refresh does not count, so the next waiter retries.
+    pub async fn refresh<F, Fut>(&self, seen: u64, refresh: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let _guard = self.lock.lock().await;
+        if self.generation() != seen {
+            log::debug!("token was refreshed by another task; reusing it");
+            return Ok(());
+        }
+        refresh().await?;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
-use super::{Provider, RequestSpec};
+use super::rate_limit::RateLimiter;
+use super::refresh_gate::RefreshGate;
+use super::{
+    Provider, ProviderCapabilities, ProviderError, ProviderResult, RequestSpec, TrackMatch,
+};
 use crate::db;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -10,6 +14,11 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
+use std::sync::Arc;
+
+/// Results requested per search when a market is set: unplayable ones are
+/// dropped, so a single result would often leave nothing.
+const PLAYABLE_SEARCH_LIMIT: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredToken {
@@ -20,6 +29,20 @@ pub struct StoredToken {
     pub scope: Option<String>,
 }
 
+/// Scopes that allow changing playlists; `playlist-modify-private` covers
+/// the private playlists [`Provider::ensure_playlist`] creates.
+pub const WRITE_SCOPES: &[&str] = &["playlist-modify-private", "playlist-modify-public"];
+
+/// Whether a granted `scope` (space separated, as Spotify returns it)
+/// contains none of `any_of`.  An unknown scope (missing or empty, as in
+/// tokens stored without one) is not held against the token.
+pub fn scope_lacks(scope: Option<&str>, any_of: &[&str]) -> bool {
+    match scope.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(granted) => !granted.split_whitespace().any(|s| any_of.contains(&s)),
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,7 +76,7 @@ mod tests {
 
         // Verify DB row exists and contains the new entry
         {
-            let conn = rusqlite::Connection::open(&path).expect("open db");
+            let conn = crate::db::open_tuned(&path).expect("open db");
             let got = crate::db::get_provider_playlist_list_cache(&conn, "spotify")
                 .expect("get cache");
             assert!(got.is_some());
@@ -66,7 +89,7 @@ mod tests {
         // Update the name
         prov.cache_update_name("pl1", "My Playlist Renamed").await;
         {
-            let conn = rusqlite::Connection::open(&path).expect("open db");
+            let conn = crate::db::open_tuned(&path).expect("open db");
             let got = crate::db::get_provider_playlist_list_cache(&conn, "spotify")
                 .expect("get cache");
             assert!(got.is_some());
@@ -78,7 +101,7 @@ mod tests {
         // Remove the entry
         prov.cache_remove_entry("pl1").await;
         {
-            let conn = rusqlite::Connection::open(&path).expect("open db");
+            let conn = crate::db::open_tuned(&path).expect("open db");
             let got = crate::db::get_provider_playlist_list_cache(&conn, "spotify")
                 .expect("get cache");
             // DB row should still exist but be an empty entries vector
@@ -102,10 +125,15 @@ pub struct SpotifyProvider {
     db_path: std::path::PathBuf,
     config: crate::config::Config,
     token: tokio::sync::Mutex<Option<StoredToken>>,
+    /// Lets only one refresh of `token` run at a time.
+    refresh_gate: RefreshGate,
     user_id: tokio::sync::Mutex<Option<String>>,
     /// Cached result of `list_user_playlists()` so we only fetch the full
     /// library once per worker run instead of once per playlist.
     playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+    /// Shared limiter for every request to Spotify
+    /// (`spotify_requests_per_sec`); `None` when disabled.
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl SpotifyProvider {
@@ -145,7 +173,11 @@ impl SpotifyProvider {
             );
             return Ok(None);
         }
-        Err(anyhow!("playlist_is_accessible failed: {}", status))
+        Err(
+            ProviderError::from_response(resp, "playlist_is_accessible failed", None)
+                .await
+                .into(),
+        )
     }
     /// List all track URIs for a given Spotify playlist.
     async fn list_playlist_tracks_internal(&self, playlist_id: &str) -> Result<Vec<String>> {
@@ -201,7 +233,7 @@ impl SpotifyProvider {
         {
             let db_path = self.db_path.clone();
             let cached = tokio::task::spawn_blocking(move || {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::get_provider_playlist_list_cache(&conn, "spotify")
             })
             .await??;
@@ -253,7 +285,7 @@ impl SpotifyProvider {
             let db_path = self.db_path.clone();
             let to_save = playlists.clone();
             let _ = tokio::task::spawn_blocking(move || {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", &to_save)
             })
             .await;
@@ -280,7 +312,7 @@ impl SpotifyProvider {
                 let entries_clone = entries.clone();
                 let db_path = self.db_path.clone();
                 let _ = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", &entries_clone)
                 })
                 .await;
@@ -295,7 +327,7 @@ impl SpotifyProvider {
             let pid = playlist_id.to_string();
             let nm = new_name.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "spotify") {
                     if let Some(e) = entries.iter_mut().find(|(id, _)| id == &pid) {
                         e.1 = nm.clone();
@@ -331,7 +363,7 @@ impl SpotifyProvider {
                 let entries_clone = entries.clone();
                 let db_path = self.db_path.clone();
                 let _ = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", &entries_clone)
                 })
                 .await;
@@ -344,7 +376,7 @@ impl SpotifyProvider {
             let id = playlist_id.to_string();
             let nm = name.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "spotify") {
                     entries.push((id.clone(), nm.clone()));
                     crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", &entries)?;
@@ -376,7 +408,7 @@ impl SpotifyProvider {
                     let entries_clone = entries.clone();
                     let db_path = self.db_path.clone();
                     let _ = tokio::task::spawn_blocking(move || {
-                        let conn = rusqlite::Connection::open(&db_path)?;
+                        let conn = crate::db::open_tuned(&db_path)?;
                         crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", &entries_clone)
                     })
                     .await;
@@ -389,7 +421,7 @@ impl SpotifyProvider {
             let db_path = self.db_path.clone();
             let pid = playlist_id.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "spotify") {
                     let before = entries.len();
                     entries.retain(|(id, _)| id != &pid);
@@ -413,7 +445,7 @@ impl SpotifyProvider {
     ) -> Self {
         // If either client_id or client_secret is empty, try to load from DB
         let (client_id, client_secret) = if client_id.is_empty() || client_secret.is_empty() {
-            if let Ok(conn) = rusqlite::Connection::open(&db_path) {
+            if let Ok(conn) = crate::db::open_tuned(&db_path) {
                 if let Ok(Some((_token_json, db_client_id, db_client_secret))) =
                     crate::db::load_credential_with_client(&conn, "spotify")
                 {
@@ -430,19 +462,23 @@ impl SpotifyProvider {
         } else {
             (client_id, client_secret)
         };
+        let rate_limiter = RateLimiter::new(config.spotify_requests_per_sec);
         Self {
             client: Client::new(),
             client_id,
             client_secret,
             db_path,
             config,
+            rate_limiter,
             token: tokio::sync::Mutex::new(None),
+            refresh_gate: RefreshGate::new(),
             user_id: tokio::sync::Mutex::new(None),
             playlist_cache: tokio::sync::Mutex::new(None),
         }
     }
     fn is_authenticated(&self) -> bool {
-        !self.client_id.is_empty() && !self.client_secret.is_empty()
+        // The client secret is optional for tokens obtained via PKCE.
+        !self.client_id.is_empty()
     }
     fn name(&self) -> &str {
         "spotify"
@@ -461,11 +497,93 @@ impl SpotifyProvider {
         env::var("SPOTIFY_API_BASE").unwrap_or_else(|_| "https://api.spotify.com/v1".into())
     }
 
+    /// `&market=...` for search URLs; empty when `spotify_market` is unset.
+    fn market_query(&self) -> String {
+        let market = self.config.spotify_market.trim();
+        if market.is_empty() {
+            String::new()
+        } else {
+            format!("&market={}", urlencoding::encode(market))
+        }
+    }
+
+    /// Results to ask a search for when `wanted` are needed.
+    fn search_limit(&self, wanted: usize) -> usize {
+        if self.config.spotify_market.trim().is_empty() {
+            wanted
+        } else {
+            wanted.max(PLAYABLE_SEARCH_LIMIT)
+        }
+    }
+
+    /// False (and logged) for a search result Spotify marks as not playable
+    /// in the requested market.  Without a market `is_playable` is absent
+    /// and every result counts as playable.
+    fn is_playable(&self, item: &serde_json::Value) -> bool {
+        if item["is_playable"].as_bool() != Some(false) {
+            return true;
+        }
+        log::info!(
+            "spotify: {} \"{}\" is not playable in market {}; skipped",
+            item["uri"].as_str().unwrap_or("?"),
+            item["name"].as_str().unwrap_or(""),
+            self.config.spotify_market.trim()
+        );
+        false
+    }
+
+    /// Playable items of an `isrc:` search.
+    async fn isrc_search_items(&self, isrc: &str) -> ProviderResult<Vec<serde_json::Value>> {
+        let q = format!("isrc:{}", isrc);
+        let url = format!(
+            "{}/search?q={}&type=track&limit={}{}",
+            Self::api_base(),
+            urlencoding::encode(&q),
+            self.search_limit(1),
+            self.market_query()
+        );
+        let spec = RequestSpec::get(&url).header("accept", "application/json");
+        let resp = self
+            .execute_request("search_track_uri_by_isrc", &spec)
+            .await?;
+        if !resp.status().is_success() {
+            return Ok(Vec::new());
+        }
+        let j: serde_json::Value = resp.json().await?;
+        Ok(j["tracks"]["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|it| self.is_playable(it))
+            .cloned()
+            .collect())
+    }
+
+    /// Look for another recording of an unplayable result through its ISRC.
+    async fn playable_by_isrc(
+        &self,
+        unplayable: &serde_json::Value,
+    ) -> ProviderResult<Option<serde_json::Value>> {
+        let Some(isrc) = unplayable["external_ids"]["isrc"].as_str() else {
+            return Ok(None);
+        };
+        let found = self.isrc_search_items(isrc).await?.into_iter().next();
+        if let Some(alt) = &found {
+            log::info!(
+                "spotify: using {} (same ISRC {}) instead of unplayable {}",
+                alt["uri"].as_str().unwrap_or("?"),
+                isrc,
+                unplayable["uri"].as_str().unwrap_or("?")
+            );
+        }
+        Ok(found)
+    }
+
     async fn load_token_from_db(&self) -> Result<Option<StoredToken>> {
         let db_path = self.db_path.clone();
         let json_opt =
             tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
-                let conn = rusqlite::Connection::open(db_path)?;
+                let conn = crate::db::open_tuned(db_path)?;
                 Ok(crate::db::load_credential_with_client(&conn, "spotify")?
                     .map(|(json, _, _)| json))
             })
@@ -488,7 +606,7 @@ impl SpotifyProvider {
         let client_id = self.client_id.clone();
         let client_secret = self.client_secret.clone();
         tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-            let conn = rusqlite::Connection::open(db_path)?;
+            let conn = crate::db::open_tuned(db_path)?;
             db::save_credential_raw(&conn, "spotify", &s, Some(&client_id), Some(&client_secret))?;
             Ok(())
         })
@@ -497,46 +615,78 @@ impl SpotifyProvider {
     }
 
     async fn ensure_token(&self) -> Result<()> {
-        let mut lock = self.token.lock().await;
-        if lock.is_none() {
-            if let Some(st) = self.load_token_from_db().await? {
-                *lock = Some(st);
+        // Read the generation before the token so a refresh finishing in
+        // between is noticed by `refresh_shared`.
+        let seen = self.refresh_gate.generation();
+        let expired = {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                if let Some(st) = self.load_token_from_db().await? {
+                    *lock = Some(st);
+                }
             }
-        }
-        if let Some(st) = &*lock {
             let now = Utc::now().timestamp();
-            if now + 30 >= st.expires_at {
-                debug!("Spotify token is near expiry, refreshing");
-                // clone so we can update persisted token in refresh
-                let mut cur = st.clone();
-                self.refresh_token_internal(&mut cur).await?;
-                *lock = Some(cur);
-            }
+            lock.as_ref().is_some_and(|st| now + 30 >= st.expires_at)
+        };
+        if expired {
+            debug!("Spotify token is near expiry, refreshing");
+            self.refresh_shared(seen).await?;
         }
         Ok(())
     }
 
+    /// Refresh the stored token through `refresh_gate`, unless another task
+    /// refreshed it since generation `seen`.
+    async fn refresh_shared(&self, seen: u64) -> Result<()> {
+        self.refresh_gate
+            .refresh(seen, || async {
+                let mut cur = self
+                    .token
+                    .lock()
+                    .await
+                    .clone()
+                    .ok_or_else(|| anyhow!("no spotify token loaded"))?;
+                self.refresh_token_internal(&mut cur).await?;
+                *self.token.lock().await = Some(cur);
+                Ok(())
+            })
+            .await
+    }
+
     async fn refresh_token_internal(&self, cur: &mut StoredToken) -> Result<()> {
         let refresh_token = cur
             .refresh_token
             .clone()
             .ok_or_else(|| anyhow!("no refresh token"))?;
-        let params = [
-            ("grant_type", "refresh_token"),
-            ("refresh_token", &refresh_token),
-        ];
-        let auth_header = format!(
-            "Basic {}",
-            general_purpose::STANDARD.encode(format!("{}:{}", self.client_id, self.client_secret))
-        );
         let url = format!("{}/api/token", Self::auth_base());
-        let resp = self
-            .client
-            .post(&url)
-            .header(AUTHORIZATION, auth_header)
-            .form(&params)
-            .send()
-            .await?;
+        let req = if self.client_secret.is_empty() {
+            // Tokens obtained via PKCE (`auth spotify --pkce`) have no client
+            // secret; the client_id goes in the body instead of Basic auth.
+            let params = [
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+                ("client_id", self.client_id.as_str()),
+            ];
+            self.client.post(&url).form(&params)
+        } else {
+            let params = [
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ];
+            let auth_header = format!(
+                "Basic {}",
+                general_purpose::STANDARD
+                    .encode(format!("{}:{}", self.client_id, self.client_secret))
+            );
+            self.client
+                .post(&url)
+                .header(AUTHORIZATION, auth_header)
+                .form(&params)
+        };
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        let resp = req.send().await?;
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
@@ -555,10 +705,31 @@ impl SpotifyProvider {
         if let Some(s) = scope {
             cur.scope = Some(s);
         }
+        // Under PKCE Spotify rotates the refresh token on every refresh.
+        if let Some(rt) = j["refresh_token"].as_str() {
+            cur.refresh_token = Some(rt.to_string());
+        }
         self.persist_token_to_db(cur).await?;
         Ok(())
     }
 
+    /// Force a token refresh using the stored refresh_token, failing when no
+    /// token is stored (used by the `doctor` CLI command).
+    pub async fn test_refresh_token(&self) -> Result<()> {
+        let seen = self.refresh_gate.generation();
+        {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                *lock = Some(
+                    self.load_token_from_db()
+                        .await?
+                        .ok_or_else(|| anyhow!("no spotify token stored in DB"))?,
+                );
+            }
+        }
+        self.refresh_shared(seen).await
+    }
+
     pub async fn get_bearer(&self) -> Result<String> {
         self.ensure_token().await?;
         let lock = self.token.lock().await;
@@ -566,7 +737,31 @@ impl SpotifyProvider {
         Ok(format!("Bearer {}", st.access_token))
     }
 
-    async fn get_user_id(&self) -> Result<String> {
+    /// Fail `op` with a request to re-authorize when the stored token was
+    /// granted none of `any_of`, instead of sending a request Spotify would
+    /// reject with an unexplained 403.
+    async fn require_scope(&self, op: &str, any_of: &[&str]) -> ProviderResult<()> {
+        self.ensure_token().await?;
+        let scope = self
+            .token
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|st| st.scope.clone());
+        if scope_lacks(scope.as_deref(), any_of) {
+            return Err(anyhow!(
+                "spotify {} needs the {} scope, but the stored token only grants {:?}; add it to spotify_scopes if missing and re-run `auth spotify`",
+                op,
+                any_of.join(" or "),
+                scope.unwrap_or_default()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Id of the signed-in user, from `GET /me` (cached per provider).
+    pub async fn get_user_id(&self) -> Result<String> {
         {
             let g = self.user_id.lock().await;
             if let Some(u) = g.as_ref() {
@@ -599,21 +794,32 @@ impl Provider for SpotifyProvider {
     fn http_client(&self) -> &reqwest::Client {
         &self.client
     }
-    async fn get_bearer(&self) -> Result<String> {
-        SpotifyProvider::get_bearer(self).await
+    fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(SpotifyProvider::get_bearer(self).await?)
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Force-refresh the access token regardless of expiry.
+        self.refresh_token_since(self.refresh_gate.generation())
+            .await
     }
-    async fn refresh_token(&self) -> Result<()> {
-        // Force-refresh the access token regardless of expiry, e.g. after a 401.
-        let mut lock = self.token.lock().await;
-        if lock.is_none() {
-            if let Some(st) = self.load_token_from_db().await? {
-                *lock = Some(st);
+    fn refresh_generation(&self) -> u64 {
+        self.refresh_gate.generation()
+    }
+    async fn refresh_token_since(&self, seen: u64) -> ProviderResult<()> {
+        // After a 401: requests rejected with the same token share a single
+        // refresh.
+        let loaded = {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                *lock = self.load_token_from_db().await?;
             }
-        }
-        if let Some(st) = &*lock {
-            let mut cur = st.clone();
-            self.refresh_token_internal(&mut cur).await?;
-            *lock = Some(cur);
+            lock.is_some()
+        };
+        if loaded {
+            self.refresh_shared(seen).await?;
         }
         Ok(())
     }
@@ -623,7 +829,7 @@ impl Provider for SpotifyProvider {
     fn is_authenticated(&self) -> bool {
         SpotifyProvider::is_authenticated(self)
     }
-    async fn ensure_playlist(&self, name: &str, description: &str) -> Result<String> {
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
         // Before creating a new playlist, check whether the user already owns
         // one with this exact name. This keeps Spotify behavior aligned with
         // TIDAL and prevents duplicates when the local mapping is missing or
@@ -649,6 +855,8 @@ impl Provider for SpotifyProvider {
             }
         }
 
+        self.require_scope("ensure_playlist", &["playlist-modify-private"])
+            .await?;
         let user_id = self.get_user_id().await?;
         let url = format!(
             "{}/users/{}/playlists",
@@ -664,10 +872,8 @@ impl Provider for SpotifyProvider {
             .json(body)
             .header("content-type", "application/json");
         let resp = self.execute_request("ensure_playlist", &spec).await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("create playlist failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(resp, "create playlist failed", None).await);
         }
         let j: serde_json::Value = resp.json().await?;
         let id = j["id"]
@@ -681,64 +887,148 @@ impl Provider for SpotifyProvider {
         Ok(id)
     }
 
-    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> Result<()> {
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        self.require_scope("rename_playlist", WRITE_SCOPES).await?;
         let url = format!("{}/playlists/{}", Self::api_base(), playlist_id);
         let body = json!({ "name": new_name });
         let resp = self
             .execute_request("rename_playlist", &RequestSpec::put(&url).json(body))
             .await?;
         if !resp.status().is_success() {
-            return Err(anyhow!("rename failed: {}", resp.status()));
+            return Err(
+                ProviderError::from_response(resp, "rename failed", Some(playlist_id)).await,
+            );
         }
         self.cache_update_name(playlist_id, new_name).await;
         Ok(())
     }
 
-    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn update_playlist_description(
+        &self,
+        playlist_id: &str,
+        description: &str,
+    ) -> ProviderResult<()> {
+        self.require_scope("update_playlist_description", WRITE_SCOPES)
+            .await?;
+        let url = format!("{}/playlists/{}", Self::api_base(), playlist_id);
+        let body = json!({ "description": description });
+        let resp = self
+            .execute_request(
+                "update_playlist_description",
+                &RequestSpec::put(&url).json(body),
+            )
+            .await?;
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(
+                resp,
+                "description update failed",
+                Some(playlist_id),
+            )
+            .await);
+        }
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.add_tracks_at(playlist_id, uris, None).await
+    }
+
+    async fn add_tracks_at(
+        &self,
+        playlist_id: &str,
+        uris: &[String],
+        position: Option<usize>,
+    ) -> ProviderResult<()> {
+        self.require_scope("add_tracks", WRITE_SCOPES).await?;
         let url = format!("{}/playlists/{}/tracks", Self::api_base(), playlist_id);
-        let body = json!({ "uris": uris });
+        let mut body = json!({ "uris": uris });
+        if let Some(position) = position {
+            body["position"] = json!(position);
+        }
         let resp = self
             .execute_request("add_tracks", &RequestSpec::post(&url).json(body))
             .await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("add tracks failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(
+                ProviderError::from_response(resp, "add tracks failed", Some(playlist_id)).await,
+            );
         }
         Ok(())
     }
 
-    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        self.require_scope("remove_tracks", WRITE_SCOPES).await?;
         let url = format!("{}/playlists/{}/tracks", Self::api_base(), playlist_id);
         let tracks: Vec<serde_json::Value> = uris.iter().map(|u| json!({ "uri": u })).collect();
         let body = json!({ "tracks": tracks });
         let resp = self
             .execute_request("remove_tracks", &RequestSpec::delete(&url).json(body))
             .await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("remove tracks failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(
+                resp,
+                "remove tracks failed",
+                Some(playlist_id),
+            )
+            .await);
         }
         Ok(())
     }
 
-    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+    async fn reorder_tracks(
+        &self,
+        playlist_id: &str,
+        ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        // PUT /playlists/{id}/tracks with a `uris` body replaces the playlist
+        // items in the given order, but only accepts 100 URIs per request.
+        // Replace with the first chunk, then append the rest in order.
+        self.require_scope("reorder_tracks", WRITE_SCOPES).await?;
+        let url = format!("{}/playlists/{}/tracks", Self::api_base(), playlist_id);
+        let mut chunks = ordered_uris.chunks(100);
+        let first: &[String] = chunks.next().unwrap_or(&[]);
+        let resp = self
+            .execute_request(
+                "reorder_tracks",
+                &RequestSpec::put(&url).json(json!({ "uris": first })),
+            )
+            .await?;
+        if !resp.status().is_success() {
+            return Err(ProviderError::from_response(
+                resp,
+                "reorder tracks failed",
+                Some(playlist_id),
+            )
+            .await);
+        }
+        for chunk in chunks {
+            self.add_tracks(playlist_id, chunk).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
         // Spotify does not support hard-deleting playlists; instead, the
         // current user "unfollows" the playlist (DELETE /playlists/{id}/followers).
+        self.require_scope("delete_playlist", WRITE_SCOPES).await?;
         let url = format!("{}/playlists/{}/followers", Self::api_base(), playlist_id);
         let resp = self
             .execute_request("delete_playlist", &RequestSpec::delete(&url))
             .await?;
         if !resp.status().is_success() {
-            return Err(anyhow!("delete playlist failed: {}", resp.status()));
+            return Err(ProviderError::from_response(
+                resp,
+                "delete playlist failed",
+                Some(playlist_id),
+            )
+            .await);
         }
         self.cache_remove_entry(playlist_id).await;
         Ok(())
     }
 
-    async fn playlist_is_valid(&self, playlist_id: &str) -> Result<Option<String>> {
-        self.playlist_is_accessible(playlist_id).await
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        Ok(self.playlist_is_accessible(playlist_id).await?)
     }
 
     async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
@@ -750,7 +1040,7 @@ impl Provider for SpotifyProvider {
             let mut cache = self.playlist_cache.lock().await;
             if cache.is_none() {
                 let loaded = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::get_provider_playlist_list_cache(&conn, "spotify")
                 })
                 .await
@@ -770,7 +1060,7 @@ impl Provider for SpotifyProvider {
         let db_path = self.db_path.clone();
         let pid2 = playlist_id.to_string();
         let _ = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-            let conn = rusqlite::Connection::open(&db_path)?;
+            let conn = crate::db::open_tuned(&db_path)?;
             match updated {
                 Some(ref entries) => {
                     crate::db::upsert_provider_playlist_list_cache(&conn, "spotify", entries)?;
@@ -788,16 +1078,18 @@ impl Provider for SpotifyProvider {
         .await;
     }
 
-    async fn list_playlist_tracks(&self, playlist_id: &str) -> Result<Vec<String>> {
-        self.list_playlist_tracks_internal(playlist_id).await
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        Ok(self.list_playlist_tracks_internal(playlist_id).await?)
     }
 
-    async fn search_track_uri(&self, title: &str, artist: &str) -> Result<Option<String>> {
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
         let q = format!("track:{} artist:{}", title, artist);
         let url = format!(
-            "{}/search?q={}&type=track&limit=1",
+            "{}/search?q={}&type=track&limit={}{}",
             Self::api_base(),
-            urlencoding::encode(&q)
+            urlencoding::encode(&q),
+            self.search_limit(1),
+            self.market_query()
         );
         let spec = RequestSpec::get(&url).header("accept", "application/json");
         let resp = self.execute_request("search_track_uri", &spec).await?;
@@ -805,38 +1097,126 @@ impl Provider for SpotifyProvider {
             return Ok(None);
         }
         let j: serde_json::Value = resp.json().await?;
-        if let Some(first) = j["tracks"]["items"].as_array().and_then(|a| a.get(0)) {
-            if let Some(uri) = first["uri"].as_str() {
-                return Ok(Some(uri.to_string()));
-            }
+        let items = j["tracks"]["items"].as_array().cloned().unwrap_or_default();
+        if let Some(first) = items.iter().find(|it| self.is_playable(it)) {
+            return Ok(first["uri"].as_str().map(str::to_string));
+        }
+        // Nothing playable in the market: another release of the same
+        // recording (same ISRC) may be.
+        match items.first() {
+            Some(best) => Ok(self
+                .playable_by_isrc(best)
+                .await?
+                .and_then(|alt| alt["uri"].as_str().map(str::to_string))),
+            None => Ok(None),
         }
-        Ok(None)
     }
 
-    async fn search_track_uri_by_isrc(&self, isrc: &str) -> Result<Option<String>> {
-        let q = format!("isrc:{}", isrc);
-        let url = format!(
-            "{}/search?q={}&type=track&limit=1",
-            Self::api_base(),
-            urlencoding::encode(&q)
-        );
-        let spec = RequestSpec::get(&url).header("accept", "application/json");
-        let resp = self
-            .execute_request("search_track_uri_by_isrc", &spec)
-            .await?;
-        if !resp.status().is_success() {
-            return Ok(None);
+    async fn search_track_uri_with_album(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+    ) -> ProviderResult<Option<String>> {
+        if album.map(str::trim).unwrap_or("").is_empty() {
+            return self.search_track_uri(title, artist).await;
         }
-        let j: serde_json::Value = resp.json().await?;
-        if let Some(first) = j["tracks"]["items"].as_array().and_then(|a| a.get(0)) {
-            if let Some(uri) = first["uri"].as_str() {
-                return Ok(Some(uri.to_string()));
+        Ok(self
+            .search_track_candidates(title, artist, album, super::DEFAULT_SEARCH_CANDIDATES)
+            .await?
+            .into_iter()
+            .next()
+            .map(|m| m.uri))
+    }
+
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        let album = album.map(str::trim).filter(|a| !a.is_empty());
+        // The search endpoint accepts 1..=50 results per page.
+        let limit = limit.clamp(1, 50);
+        let mut queries = vec![format!("track:{} artist:{}", title, artist)];
+        if let Some(a) = album {
+            queries.insert(
+                0,
+                format!("track:{} artist:{} album:\"{}\"", title, artist, a),
+            );
+        }
+        // Results released on the tagged album come first. The plain query is
+        // only sent when the album-qualified one found no such result.
+        let mut on_album: Vec<TrackMatch> = Vec::new();
+        let mut others: Vec<TrackMatch> = Vec::new();
+        for q in queries {
+            if !on_album.is_empty() {
+                break;
+            }
+            let url = format!(
+                "{}/search?q={}&type=track&limit={}{}",
+                Self::api_base(),
+                urlencoding::encode(&q),
+                limit,
+                self.market_query()
+            );
+            let spec = RequestSpec::get(&url).header("accept", "application/json");
+            let resp = self.execute_request("search_track_uri", &spec).await?;
+            if !resp.status().is_success() {
+                continue;
+            }
+            let j: serde_json::Value = resp.json().await?;
+            let mut items = j["tracks"]["items"].as_array().cloned().unwrap_or_default();
+            let unplayable_best = items.first().cloned();
+            items.retain(|it| self.is_playable(it));
+            if items.is_empty() {
+                if let Some(best) = unplayable_best {
+                    items.extend(self.playable_by_isrc(&best).await?);
+                }
+            }
+            for it in items.iter() {
+                let Some(uri) = it["uri"].as_str() else {
+                    continue;
+                };
+                if on_album.iter().chain(others.iter()).any(|m| m.uri == uri) {
+                    continue;
+                }
+                let text = |v: &serde_json::Value| v.as_str().map(str::to_string);
+                let m = TrackMatch {
+                    uri: uri.to_string(),
+                    name: text(&it["name"]),
+                    artist: text(&it["artists"][0]["name"]),
+                    album: text(&it["album"]["name"]),
+                    duration_ms: it["duration_ms"].as_u64(),
+                    isrc: text(&it["external_ids"]["isrc"]),
+                };
+                let matches_album = album.is_some_and(|a| {
+                    it["album"]["name"]
+                        .as_str()
+                        .is_some_and(|name| super::album_names_match(name, a))
+                });
+                if matches_album {
+                    on_album.push(m);
+                } else {
+                    others.push(m);
+                }
             }
         }
-        Ok(None)
+        on_album.extend(others);
+        on_album.truncate(limit);
+        Ok(on_album)
+    }
+
+    async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
+        Ok(self
+            .isrc_search_items(isrc)
+            .await?
+            .first()
+            .and_then(|it| it["uri"].as_str().map(str::to_string)))
     }
 
-    async fn lookup_track_isrc(&self, uri: &str) -> Result<Option<String>> {
+    async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
         // Expect URIs like "spotify:track:{id}" or full spotify track URLs; extract id
         let id = if let Some(i) = uri.rsplit(':').next() {
             i.to_string()
@@ -864,4 +1244,60 @@ impl Provider for SpotifyProvider {
         }
         Ok(None)
     }
+
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        let Some(id) = uri.rsplit(':').next().filter(|id| !id.is_empty()) else {
+            return Ok(None);
+        };
+        let url = format!("{}/tracks/{}", Self::api_base(), id);
+        let resp = self
+            .execute_request("track_exists", &RequestSpec::get(&url))
+            .await?;
+        match resp.status() {
+            s if s.is_success() => Ok(Some(true)),
+            // Ids Spotify no longer knows come back as 400 "invalid id".
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::BAD_REQUEST => Ok(Some(false)),
+            _ => Err(ProviderError::from_response(resp, "spotify track lookup failed", None).await),
+        }
+    }
+
+    /// `PUT /playlists/{id}/images` takes base64 JPEG data of at most 256 KB
+    /// (after encoding); larger or PNG covers are re-encoded and downscaled.
+    /// Needs the `ugc-image-upload` scope.
+    async fn set_playlist_cover(
+        &self,
+        playlist_id: &str,
+        image_bytes: &[u8],
+    ) -> ProviderResult<()> {
+        const MAX_BASE64_BYTES: usize = 256 * 1024;
+        self.require_scope("set_playlist_cover", &["ugc-image-upload"])
+            .await?;
+        let jpeg = crate::util::jpeg_within_size(image_bytes, MAX_BASE64_BYTES / 4 * 3)?;
+        let url = format!("{}/playlists/{}/images", Self::api_base(), playlist_id);
+        let spec = RequestSpec::put(&url)
+            .header("content-type", "image/jpeg")
+            .body(general_purpose::STANDARD.encode(jpeg).into_bytes());
+        let resp = self.execute_request("set_playlist_cover", &spec).await?;
+        if !resp.status().is_success() {
+            return Err(
+                ProviderError::from_response(resp, "set cover failed", Some(playlist_id)).await,
+            );
+        }
+        Ok(())
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: true,
+            supports_isrc_search: true,
+            supports_reorder: true,
+            supports_cover_art: true,
+            supports_positional_add: true,
+            hard_delete: false,
+        }
+    }
+
+    fn max_batch_size(&self, cfg: &crate::config::Config) -> usize {
+        cfg.max_batch_size_spotify
+    }
 }
@@ -1,3 +1,5 @@
+use crate::api::oauth_callback::obtain_code;
+use crate::api::pkce::{code_challenge_s256, generate_code_verifier};
 use crate::config::Config;
 use crate::db;
 use anyhow::{anyhow, Result};
@@ -7,14 +9,15 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 use url::Url;
 
-/// This module implements a simple manual OAuth helper:
-/// 1. Build the Spotify authorization URL and print it.
-/// 2. User opens it in a browser, approves and gets redirected to the redirect URI (which may fail if it's a dummy).
-/// 3. User copies the full redirect URL and pastes it into this CLI.
-/// 4. The CLI extracts the `code` param and exchanges it for an access_token + refresh_token.
-/// 5. The tokens are stored in the DB credentials table as JSON.
-///
-/// This avoids running an embedded HTTP server and works well for manual setup.
+/// This module implements a simple interactive OAuth helper:
+/// 1. Build the Spotify authorization URL and open it in the browser.
+/// 2. A one-shot listener on the (loopback) redirect URI captures the `code` param.
+///    With `--no-browser`, or if the port cannot be bound, the user copies the
+///    full redirect URL and pastes it into this CLI instead.
+/// 3. The `code` is exchanged for an access_token + refresh_token.  With `--pkce`
+///    the exchange uses a code verifier instead of the client_secret, which is
+///    then not needed at all.
+/// 4. The tokens are stored in the DB credentials table as JSON.
 #[derive(Serialize, Deserialize)]
 struct TokenResponse {
     access_token: String,
@@ -24,7 +27,7 @@ struct TokenResponse {
     scope: Option<String>,
 }
 
-pub async fn run_spotify_auth(cfg: &Config) -> Result<()> {
+pub async fn run_spotify_auth(cfg: &Config, no_browser: bool, pkce: bool) -> Result<()> {
     use std::io;
 
     println!("Enter your Spotify client_id:");
@@ -35,13 +38,21 @@ pub async fn run_spotify_auth(cfg: &Config) -> Result<()> {
         return Err(anyhow!("no client_id provided"));
     }
 
-    println!("Enter your Spotify client_secret:");
-    let mut client_secret = String::new();
-    io::stdin().read_line(&mut client_secret)?;
-    let client_secret = client_secret.trim().to_string();
-    if client_secret.is_empty() {
-        return Err(anyhow!("no client_secret provided"));
-    }
+    // PKCE is meant for public (desktop) clients, so no secret is needed.
+    let client_secret = if pkce {
+        String::new()
+    } else {
+        println!("Enter your Spotify client_secret:");
+        let mut client_secret = String::new();
+        io::stdin().read_line(&mut client_secret)?;
+        let client_secret = client_secret.trim().to_string();
+        if client_secret.is_empty() {
+            return Err(anyhow!(
+                "no client_secret provided (use --pkce to authorize without one)"
+            ));
+        }
+        client_secret
+    };
 
     println!("Enter your Spotify redirect URI (leave blank for http://127.0.0.1:8888/):");
     let mut redirect_uri = String::new();
@@ -56,62 +67,75 @@ pub async fn run_spotify_auth(cfg: &Config) -> Result<()> {
     };
 
     // Build the auth URL
-    let scopes = vec![
-        "playlist-modify-private",
-        "playlist-modify-public",
-        "playlist-read-private",
-        "user-read-private",
-        "user-read-email",
-    ];
+    let state = generate_code_verifier();
+    let verifier = generate_code_verifier();
+    let scopes = &cfg.spotify_scopes;
+    if scopes.is_empty() {
+        return Err(anyhow!("spotify_scopes is empty; no scopes to request"));
+    }
+    if crate::api::spotify::scope_lacks(Some(&scopes.join(" ")), crate::api::spotify::WRITE_SCOPES)
+    {
+        println!(
+            "Warning: spotify_scopes has no playlist-modify-* scope; playlists can be read but not changed."
+        );
+    }
     let mut url = Url::parse("https://accounts.spotify.com/authorize")?;
     url.query_pairs_mut()
         .append_pair("response_type", "code")
         .append_pair("client_id", &client_id)
         .append_pair("scope", &scopes.join(" "))
         .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("state", &state)
         .append_pair("show_dialog", "true");
+    if pkce {
+        url.query_pairs_mut()
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("code_challenge", &code_challenge_s256(&verifier));
+    }
 
-    println!(
-        "Open this URL in your browser and authorize the application:\n\n{}\n",
-        url
-    );
-    println!("After authorizing, you'll be redirected to your redirect URI. Copy the full redirect URL and paste it here.");
-    println!("Paste redirect URL:");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
-    let input = input.trim();
-    let parsed = Url::parse(input).map_err(|e| anyhow!("invalid url pasted: {}", e))?;
-    let code = parsed
-        .query_pairs()
-        .find(|(k, _)| k == "code")
-        .ok_or_else(|| anyhow!("no code in redirect URL"))?
-        .1
-        .into_owned();
+    let code = obtain_code(&url, &redirect_uri, &state, no_browser).await?;
 
     // Exchange code for tokens
     let client = Client::new();
-    let params = [
-        ("grant_type", "authorization_code"),
-        ("code", &code),
-        ("redirect_uri", &redirect_uri),
-    ];
-    let auth_header = format!(
-        "Basic {}",
-        general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret))
-    );
-    let resp = client
-        .post("https://accounts.spotify.com/api/token")
-        .header("Authorization", auth_header)
-        .form(&params)
-        .send()
-        .await?;
+    let req = if pkce {
+        // The verifier proves we started the flow; no client secret is sent.
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("client_id", client_id.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ];
+        client
+            .post("https://accounts.spotify.com/api/token")
+            .form(&params)
+    } else {
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &redirect_uri),
+        ];
+        let auth_header = format!(
+            "Basic {}",
+            general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret))
+        );
+        client
+            .post("https://accounts.spotify.com/api/token")
+            .header("Authorization", auth_header)
+            .form(&params)
+    };
+    let resp = req.send().await?;
     let status = resp.status();
     if !status.is_success() {
         let txt = resp.text().await.unwrap_or_default();
         return Err(anyhow!("token exchange failed: {} => {}", status, txt));
     }
 
-    let tr: TokenResponse = resp.json().await?;
+    let mut tr: TokenResponse = resp.json().await?;
+    // Keep what was asked for when the grant does not say what it covers.
+    if tr.scope.as_deref().is_none_or(|s| s.trim().is_empty()) {
+        tr.scope = Some(scopes.join(" "));
+    }
     // Compute expires_at as now + expires_in
     let expires_at = chrono::Utc::now().timestamp() + tr.expires_in;
     // Build the stored token to match what the provider expects
@@ -128,13 +152,13 @@ pub async fn run_spotify_auth(cfg: &Config) -> Result<()> {
     let client_id = client_id.to_string();
     let client_secret = client_secret.to_string();
     tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-        let conn = rusqlite::Connection::open(db_path)?;
+        let conn = crate::db::open_tuned(db_path)?;
         db::save_credential_raw(
             &conn,
             "spotify",
             &token_json,
             Some(&client_id),
-            Some(&client_secret),
+            Some(client_secret.as_str()).filter(|s| !s.is_empty()),
         )?;
         Ok(())
     })
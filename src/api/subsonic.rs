@@ -0,0 +1,512 @@
+use super::{Provider, ProviderCapabilities, ProviderError, ProviderResult};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use md5::{Digest, Md5};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Subsonic API version we speak.  1.13.0 introduced salted-token auth and
+/// 1.14.0 made `createPlaylist` return the new playlist.
+const API_VERSION: &str = "1.16.1";
+/// Client name sent as the `c` parameter.
+const CLIENT_NAME: &str = "music-file-playlist-online-sync";
+/// Prefix of the URIs handed out by `search_track_uri`.
+const URI_PREFIX: &str = "subsonic:song:";
+/// Song ids are sent as repeated query parameters, so keep batches small
+/// enough that the request line stays well below common URL length limits.
+const MAX_BATCH_SIZE: usize = 50;
+
+/// Credentials stored as the `token_json` of the `subsonic` row in the
+/// credentials table.  Only the salted token is persisted, never the
+/// plain-text password: `token = md5(password + salt)`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubsonicCredentials {
+    pub server_url: String,
+    pub username: String,
+    pub token: String,
+    pub salt: String,
+}
+
+impl SubsonicCredentials {
+    /// Derive salted-token credentials from a password using a fresh
+    /// random salt.
+    pub fn from_password(server_url: &str, username: &str, password: &str) -> Self {
+        let salt: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(12)
+            .map(char::from)
+            .collect();
+        Self::with_salt(server_url, username, password, &salt)
+    }
+
+    /// Derive salted-token credentials from a password and a given salt.
+    pub fn with_salt(server_url: &str, username: &str, password: &str, salt: &str) -> Self {
+        Self {
+            server_url: server_url.trim_end_matches('/').to_string(),
+            username: username.to_string(),
+            token: md5_hex(&format!("{}{}", password, salt)),
+            salt: salt.to_string(),
+        }
+    }
+}
+
+fn md5_hex(input: &str) -> String {
+    Md5::digest(input.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Provider for Subsonic-compatible servers (Navidrome, Gonic, Airsonic, ...).
+/// Authentication travels as `u`/`t`/`s`/`v`/`c` query parameters on every
+/// request, so this provider talks to the server directly instead of going
+/// through the bearer-token based `execute_request`.
+pub struct SubsonicProvider {
+    client: Client,
+    db_path: std::path::PathBuf,
+    config: crate::config::Config,
+    creds: tokio::sync::Mutex<Option<SubsonicCredentials>>,
+    authenticated: bool,
+    /// Cached result of `list_user_playlists()` so we only fetch the full
+    /// library once per worker run instead of once per playlist.
+    playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl SubsonicProvider {
+    /// Build a provider from the credentials stored in the DB (if any).
+    pub fn new(db_path: std::path::PathBuf, config: crate::config::Config) -> Self {
+        let creds = Self::load_credentials(&db_path).ok().flatten();
+        Self::with_credentials(creds, db_path, config)
+    }
+
+    /// Build a provider with explicit credentials (used by the auth flow and
+    /// tests).
+    pub fn with_credentials(
+        creds: Option<SubsonicCredentials>,
+        db_path: std::path::PathBuf,
+        config: crate::config::Config,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            db_path,
+            config,
+            authenticated: creds.is_some(),
+            creds: tokio::sync::Mutex::new(creds),
+            playlist_cache: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn load_credentials(db_path: &std::path::Path) -> Result<Option<SubsonicCredentials>> {
+        let conn = crate::db::open_tuned(db_path)?;
+        match crate::db::load_credential_with_client(&conn, "subsonic")? {
+            Some((json, _, _)) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+    fn name(&self) -> &str {
+        "subsonic"
+    }
+
+    /// Strip the `subsonic:song:` prefix, returning `None` for empty ids.
+    fn song_id(uri: &str) -> Option<&str> {
+        let id = uri.strip_prefix(URI_PREFIX).unwrap_or(uri).trim();
+        if id.is_empty() {
+            None
+        } else {
+            Some(id)
+        }
+    }
+
+    /// Call a Subsonic REST method and return the inner `subsonic-response`
+    /// object.  Subsonic reports most failures with HTTP 200 and
+    /// `status: "failed"`, so the envelope is inspected as well: error 40/41
+    /// map to `Unauthorized` and error 70 ("data not found") maps to
+    /// `PlaylistNotFound` when `playlist_id` is given.
+    async fn call(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+        playlist_id: Option<&str>,
+    ) -> ProviderResult<serde_json::Value> {
+        let creds = self
+            .creds
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("no subsonic credentials stored"))?;
+        let mut query: Vec<(&str, String)> = vec![
+            ("u", creds.username.clone()),
+            ("t", creds.token.clone()),
+            ("s", creds.salt.clone()),
+            ("v", API_VERSION.to_string()),
+            ("c", CLIENT_NAME.to_string()),
+            ("f", "json".to_string()),
+        ];
+        query.extend(params.iter().cloned());
+        let url = format!("{}/rest/{}", creds.server_url, method);
+        let resp = match self.client.get(&url).query(&query).send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                crate::metrics::provider_request("subsonic", "error");
+                return Err(e.into());
+            }
+        };
+        crate::metrics::provider_request("subsonic", resp.status().as_str());
+        if !resp.status().is_success() {
+            let context = format!("subsonic {} failed", method);
+            return Err(ProviderError::from_response(resp, &context, playlist_id).await);
+        }
+        let j: serde_json::Value = resp.json().await?;
+        let body = j
+            .get("subsonic-response")
+            .cloned()
+            .ok_or_else(|| anyhow!("subsonic {}: missing subsonic-response", method))?;
+        if body["status"].as_str() == Some("ok") {
+            return Ok(body);
+        }
+        let code = body["error"]["code"].as_i64().unwrap_or(0);
+        let message = body["error"]["message"].as_str().unwrap_or("");
+        Err(match (code, playlist_id) {
+            (40 | 41, _) => ProviderError::Unauthorized,
+            (70, Some(pid)) => ProviderError::PlaylistNotFound {
+                playlist_id: pid.to_string(),
+            },
+            _ => ProviderError::Other(anyhow!(
+                "subsonic {} failed: error {} {}",
+                method,
+                code,
+                message
+            )),
+        })
+    }
+
+    /// Verify the stored credentials against the server.
+    pub async fn ping(&self) -> Result<()> {
+        self.call("ping", &[], None).await?;
+        Ok(())
+    }
+
+    /// List all playlists visible to the user as (id, name) pairs.
+    pub async fn list_user_playlists(&self) -> Result<Vec<(String, String)>> {
+        {
+            let cache = self.playlist_cache.lock().await;
+            if let Some(ref cached) = *cache {
+                return Ok(cached.clone());
+            }
+        }
+        let body = self.call("getPlaylists", &[], None).await?;
+        let out: Vec<(String, String)> = body["playlists"]["playlist"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|p| {
+                        Some((
+                            json_id(&p["id"])?,
+                            p["name"].as_str().unwrap_or("").to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        *self.playlist_cache.lock().await = Some(out.clone());
+        Ok(out)
+    }
+
+    /// Return the song ids of a playlist in playlist order (duplicates kept,
+    /// since `updatePlaylist` removes by index).
+    async fn playlist_entries(&self, playlist_id: &str) -> ProviderResult<(String, Vec<String>)> {
+        let body = self
+            .call(
+                "getPlaylist",
+                &[("id", playlist_id.to_string())],
+                Some(playlist_id),
+            )
+            .await?;
+        let name = body["playlist"]["name"].as_str().unwrap_or("").to_string();
+        let ids = body["playlist"]["entry"]
+            .as_array()
+            .map(|a| a.iter().filter_map(|e| json_id(&e["id"])).collect())
+            .unwrap_or_default();
+        Ok((name, ids))
+    }
+
+    async fn cache_update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Vec<(String, String)>),
+    {
+        let mut cache = self.playlist_cache.lock().await;
+        if let Some(ref mut entries) = *cache {
+            f(entries);
+        }
+    }
+}
+
+/// Subsonic ids are strings, but some servers emit numeric JSON values.
+fn json_id(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Provider for SubsonicProvider {
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        // Subsonic has no bearer token; auth is sent as query parameters.
+        Ok(String::new())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Nothing expires; re-read the stored credentials in case they were
+        // replaced via `auth subsonic` while we were running.
+        let db_path = self.db_path.clone();
+        let loaded =
+            tokio::task::spawn_blocking(move || Self::load_credentials(&db_path)).await??;
+        if loaded.is_some() {
+            *self.creds.lock().await = loaded;
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        SubsonicProvider::name(self)
+    }
+    fn is_authenticated(&self) -> bool {
+        SubsonicProvider::is_authenticated(self)
+    }
+
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
+        if let Some((id, _)) = self
+            .list_user_playlists()
+            .await?
+            .into_iter()
+            .find(|(_, n)| n == name)
+        {
+            return Ok(id);
+        }
+        let body = self
+            .call("createPlaylist", &[("name", name.to_string())], None)
+            .await?;
+        let id = match json_id(&body["playlist"]["id"]) {
+            Some(id) => id,
+            None => {
+                // Servers older than API 1.14 return an empty body; look the
+                // new playlist up by name instead.
+                *self.playlist_cache.lock().await = None;
+                self.list_user_playlists()
+                    .await?
+                    .into_iter()
+                    .find(|(_, n)| n == name)
+                    .map(|(id, _)| id)
+                    .ok_or_else(|| anyhow!("subsonic createPlaylist returned no id"))?
+            }
+        };
+        if !description.is_empty() {
+            self.call(
+                "updatePlaylist",
+                &[
+                    ("playlistId", id.clone()),
+                    ("comment", description.to_string()),
+                ],
+                Some(&id),
+            )
+            .await?;
+        }
+        let (id_c, name_c) = (id.clone(), name.to_string());
+        self.cache_update(move |entries| {
+            if !entries.iter().any(|(i, _)| i == &id_c) {
+                entries.push((id_c, name_c));
+            }
+        })
+        .await;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        self.call(
+            "updatePlaylist",
+            &[
+                ("playlistId", playlist_id.to_string()),
+                ("name", new_name.to_string()),
+            ],
+            Some(playlist_id),
+        )
+        .await?;
+        self.cache_update(|entries| {
+            for (id, n) in entries.iter_mut() {
+                if id == playlist_id {
+                    *n = new_name.to_string();
+                }
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        let mut params = vec![("playlistId", playlist_id.to_string())];
+        params.extend(
+            uris.iter()
+                .filter_map(|u| Self::song_id(u))
+                .map(|id| ("songIdToAdd", id.to_string())),
+        );
+        if params.len() == 1 {
+            return Ok(());
+        }
+        self.call("updatePlaylist", &params, Some(playlist_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        // updatePlaylist removes by position, so resolve every occurrence of
+        // the requested songs to its index in the current playlist.
+        let wanted: HashSet<&str> = uris.iter().filter_map(|u| Self::song_id(u)).collect();
+        let (_, entries) = self.playlist_entries(playlist_id).await?;
+        let mut params = vec![("playlistId", playlist_id.to_string())];
+        params.extend(
+            entries
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| wanted.contains(id.as_str()))
+                .map(|(i, _)| ("songIndexToRemove", i.to_string())),
+        );
+        if params.len() == 1 {
+            return Ok(());
+        }
+        self.call("updatePlaylist", &params, Some(playlist_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn reorder_tracks(
+        &self,
+        playlist_id: &str,
+        ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        // createPlaylist with an existing playlistId replaces its songs.
+        let mut params = vec![("playlistId", playlist_id.to_string())];
+        params.extend(
+            ordered_uris
+                .iter()
+                .filter_map(|u| Self::song_id(u))
+                .map(|id| ("songId", id.to_string())),
+        );
+        self.call("createPlaylist", &params, Some(playlist_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        self.call(
+            "deletePlaylist",
+            &[("id", playlist_id.to_string())],
+            Some(playlist_id),
+        )
+        .await?;
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+        Ok(())
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        let (_, ids) = self.playlist_entries(playlist_id).await?;
+        let mut seen = HashSet::new();
+        Ok(ids
+            .into_iter()
+            .filter(|id| seen.insert(id.clone()))
+            .map(|id| format!("{}{}", URI_PREFIX, id))
+            .collect())
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        match self.playlist_entries(playlist_id).await {
+            Ok((name, _)) => Ok(Some(name)),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        let Some(id) = Self::song_id(uri) else {
+            return Ok(None);
+        };
+        // Passing the id makes error 70 (not found) come back as
+        // PlaylistNotFound rather than a generic error.
+        match self
+            .call("getSong", &[("id", id.to_string())], Some(id))
+            .await
+        {
+            Ok(_) => Ok(Some(true)),
+            Err(ProviderError::PlaylistNotFound { .. }) => Ok(Some(false)),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
+        let body = self
+            .call(
+                "search3",
+                &[
+                    ("query", format!("{} {}", artist, title)),
+                    ("songCount", "10".to_string()),
+                    ("artistCount", "0".to_string()),
+                    ("albumCount", "0".to_string()),
+                ],
+                None,
+            )
+            .await?;
+        let songs = match body["searchResult3"]["song"].as_array() {
+            Some(s) if !s.is_empty() => s.clone(),
+            _ => return Ok(None),
+        };
+        // search3 is a fuzzy full-text search; prefer an exact title/artist
+        // match and fall back to the server's top hit.
+        let eq = |v: &serde_json::Value, want: &str| {
+            v.as_str()
+                .is_some_and(|s| s.trim().eq_ignore_ascii_case(want.trim()))
+        };
+        let best = songs
+            .iter()
+            .find(|s| eq(&s["title"], title) && eq(&s["artist"], artist))
+            .or_else(|| songs.iter().find(|s| eq(&s["title"], title)))
+            .unwrap_or(&songs[0]);
+        Ok(json_id(&best["id"]).map(|id| format!("{}{}", URI_PREFIX, id)))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: false,
+            supports_reorder: true,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
+    }
+
+    fn max_batch_size(&self, _cfg: &crate::config::Config) -> usize {
+        MAX_BATCH_SIZE
+    }
+
+    fn validate_uri(&self, uri: &str) -> bool {
+        uri.starts_with(URI_PREFIX) && Self::song_id(uri).is_some()
+    }
+}
@@ -0,0 +1,58 @@
+use crate::api::subsonic::{SubsonicCredentials, SubsonicProvider};
+use crate::config::Config;
+use crate::db;
+use anyhow::{anyhow, Result};
+use tracing::info;
+
+/// Interactive helper to store Subsonic/Navidrome credentials in the DB.
+/// The password is only used to derive the salted token (`md5(password +
+/// salt)`); the plain-text password is never persisted.
+pub async fn run_subsonic_auth(cfg: &Config) -> Result<()> {
+    use std::io;
+
+    let prompt = |label: &str| -> Result<String> {
+        println!("{}", label);
+        let mut s = String::new();
+        io::stdin().read_line(&mut s)?;
+        Ok(s.trim().to_string())
+    };
+
+    let server_url = prompt("Enter your Subsonic server URL (e.g. https://music.example.com):")?;
+    if server_url.is_empty() {
+        return Err(anyhow!("no server URL provided"));
+    }
+    let username = prompt("Enter your Subsonic username:")?;
+    if username.is_empty() {
+        return Err(anyhow!("no username provided"));
+    }
+    let password = prompt("Enter your Subsonic password:")?;
+    if password.is_empty() {
+        return Err(anyhow!("no password provided"));
+    }
+
+    let creds = SubsonicCredentials::from_password(&server_url, &username, &password);
+
+    // Verify before saving so a typo doesn't silently disable syncing.
+    let provider =
+        SubsonicProvider::with_credentials(Some(creds.clone()), cfg.db_path.clone(), cfg.clone());
+    provider
+        .ping()
+        .await
+        .map_err(|e| anyhow!("subsonic ping failed: {}", e))?;
+
+    let token_json = serde_json::to_string(&creds)?;
+    let db_path = cfg.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let conn = crate::db::open_tuned(db_path)?;
+        db::save_credential_raw(&conn, "subsonic", &token_json, Some(&username), None)?;
+        Ok(())
+    })
+    .await??;
+
+    info!("Subsonic credentials saved to DB for provider 'subsonic'");
+    println!(
+        "Saved credentials to DB. You can now run the worker which will use the Subsonic provider."
+    );
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
-use super::{Provider, RequestSpec};
+use super::rate_limit::RateLimiter;
+use super::refresh_gate::RefreshGate;
+use super::{
+    Provider, ProviderCapabilities, ProviderError, ProviderResult, RequestSpec, TrackMatch,
+};
 use crate::db;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -10,6 +14,14 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Hard limit of the TIDAL playlist items relationship endpoint; larger
+/// payloads fail with "size must be between 1 and 20".
+const TIDAL_MAX_BATCH_SIZE: usize = 20;
+
+/// ISRCs asked for per `GET /tracks?filter[isrc]=...` request.
+const TIDAL_ISRC_BATCH_SIZE: usize = 20;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StoredToken {
@@ -21,6 +33,29 @@ pub struct StoredToken {
     pub user_id: Option<i64>,
 }
 
+/// Numeric id of the user `access_token` belongs to, from `GET /users/me`.
+/// `list_user_playlists` needs it for the `userCollections` endpoint.
+pub async fn fetch_user_id(client: &Client, access_token: &str) -> Result<i64> {
+    let url = format!("{}/users/me", TidalProvider::base_url());
+    let resp = client
+        .get(&url)
+        .header(AUTHORIZATION, format!("Bearer {}", access_token))
+        .header(reqwest::header::ACCEPT, "application/vnd.api+json")
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(anyhow!("GET /users/me failed: {} - {}", status, body));
+    }
+    let j: serde_json::Value = resp.json().await?;
+    let id = &j["data"]["id"];
+    id.as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| id.as_i64())
+        .ok_or_else(|| anyhow!("no numeric user id in /users/me response: {}", j))
+}
+
 /// Minimal Tidal provider implementation. It uses a base URL from env var `TIDAL_API_BASE` for
 /// easier testing (mockito). Authentication & endpoints may need tweaks depending on your Tidal
 /// application details; this is a best-effort implementation using documented endpoints.
@@ -31,6 +66,8 @@ pub struct TidalProvider {
     db_path: std::path::PathBuf,
     config: crate::config::Config,
     token: tokio::sync::Mutex<Option<StoredToken>>,
+    /// Lets only one refresh of `token` run at a time.
+    refresh_gate: RefreshGate,
     /// Optional logical root folder name under which this application
     /// should group all created playlists in the user's TIDAL collection.
     root_folder_name: Option<String>,
@@ -44,6 +81,9 @@ pub struct TidalProvider {
     /// a worker run to avoid re-fetching all playlist items on every call.
     item_id_cache:
         tokio::sync::Mutex<HashMap<String, (HashMap<String, Vec<String>>, Option<String>)>>,
+    /// Shared limiter for every request to TIDAL (`tidal_requests_per_sec`);
+    /// `None` when disabled.
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl TidalProvider {
@@ -64,7 +104,7 @@ impl TidalProvider {
         {
             let db_path = self.db_path.clone();
             let cached = tokio::task::spawn_blocking(move || {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::get_provider_playlist_list_cache(&conn, "tidal")
             })
             .await??;
@@ -82,20 +122,16 @@ impl TidalProvider {
         self.ensure_token().await?;
         let base = Self::base_url();
 
-        // Require explicit numeric user id from the stored token; this is
-        // provided by the JSON pasted from the TIDAL API reference site.
+        // The numeric user id is stored by `auth tidal` or discovered by
+        // `ensure_token` from `/users/me`.
         let user_id = {
             let lock = self.token.lock().await;
             lock.as_ref().and_then(|t| t.user_id)
         }
-        .ok_or_else(|| {
-            anyhow!(
-                "no user_id in tidal token; re-run Tidal auth with JSON that includes 'user_id'"
-            )
-        })?;
+        .ok_or_else(|| anyhow!("no user_id in tidal token; re-run `auth tidal`"))?;
 
-        let cc = Self::country_code();
-        let locale = Self::locale();
+        let cc = self.country_code();
+        let locale = self.locale();
         // First page: v2 userCollections with include=playlists, which
         // returns playlist resources in `included` and a relationship with
         // pagination links.
@@ -106,10 +142,12 @@ impl TidalProvider {
         let resp = self
             .execute_request("list_user_playlists", &RequestSpec::get(&url))
             .await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("list playlists failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(
+                ProviderError::from_response(resp, "list playlists failed", None)
+                    .await
+                    .into(),
+            );
         }
         let j: serde_json::Value = resp.json().await?;
 
@@ -201,7 +239,7 @@ impl TidalProvider {
             let db_path = self.db_path.clone();
             let to_save = playlists.clone();
             let _ = tokio::task::spawn_blocking(move || {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", &to_save)
             })
             .await;
@@ -227,7 +265,7 @@ impl TidalProvider {
                 let entries_clone = entries.clone();
                 let db_path = self.db_path.clone();
                 let _ = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", &entries_clone)
                 })
                 .await;
@@ -240,7 +278,7 @@ impl TidalProvider {
             let pid = playlist_id.to_string();
             let nm = new_name.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "tidal") {
                     if let Some(e) = entries.iter_mut().find(|(id, _)| id == &pid) {
                         e.1 = nm.clone();
@@ -272,7 +310,7 @@ impl TidalProvider {
                 let entries_clone = entries.clone();
                 let db_path = self.db_path.clone();
                 let _ = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", &entries_clone)
                 })
                 .await;
@@ -285,7 +323,7 @@ impl TidalProvider {
             let id = playlist_id.to_string();
             let nm = name.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "tidal") {
                     entries.push((id.clone(), nm.clone()));
                     crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", &entries)?;
@@ -314,7 +352,7 @@ impl TidalProvider {
                     let entries_clone = entries.clone();
                     let db_path = self.db_path.clone();
                     let _ = tokio::task::spawn_blocking(move || {
-                        let conn = rusqlite::Connection::open(&db_path)?;
+                        let conn = crate::db::open_tuned(&db_path)?;
                         crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", &entries_clone)
                     })
                     .await;
@@ -327,7 +365,7 @@ impl TidalProvider {
             let db_path = self.db_path.clone();
             let pid = playlist_id.to_string();
             let res = tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 if let Ok(Some(mut entries)) = crate::db::get_provider_playlist_list_cache(&conn, "tidal") {
                     let before = entries.len();
                     entries.retain(|(id, _)| id != &pid);
@@ -356,7 +394,7 @@ impl TidalProvider {
         let db_path = self.db_path.clone();
         let pid = playlist_id.to_string();
         if let Err(e) = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-            let conn = rusqlite::Connection::open(&db_path)?;
+            let conn = crate::db::open_tuned(&db_path)?;
             crate::db::delete_playlist_item_id_cache(&conn, "tidal", &pid)
         })
         .await
@@ -370,11 +408,14 @@ impl TidalProvider {
         }
     }
 
-    fn country_code() -> String {
-        std::env::var("TIDAL_COUNTRY_CODE").unwrap_or_else(|_| "US".into())
+    /// `tidal_country_code` from the config, unless `TIDAL_COUNTRY_CODE` is set.
+    fn country_code(&self) -> String {
+        std::env::var("TIDAL_COUNTRY_CODE")
+            .unwrap_or_else(|_| self.config.tidal_country_code.to_ascii_uppercase())
     }
-    fn locale() -> String {
-        std::env::var("TIDAL_LOCALE").unwrap_or_else(|_| "en-US".into())
+    /// `tidal_locale` from the config, unless `TIDAL_LOCALE` is set.
+    fn locale(&self) -> String {
+        std::env::var("TIDAL_LOCALE").unwrap_or_else(|_| self.config.tidal_locale.clone())
     }
     pub fn new(
         client_id: String,
@@ -385,7 +426,7 @@ impl TidalProvider {
     ) -> Self {
         // If either client_id or client_secret is empty, try to load from DB
         let (client_id, client_secret) = if client_id.is_empty() || client_secret.is_empty() {
-            if let Ok(conn) = rusqlite::Connection::open(&db_path) {
+            if let Ok(conn) = crate::db::open_tuned(&db_path) {
                 if let Ok(Some((_token_json, db_client_id, db_client_secret))) =
                     crate::db::load_credential_with_client(&conn, "tidal")
                 {
@@ -402,13 +443,16 @@ impl TidalProvider {
         } else {
             (client_id, client_secret)
         };
+        let rate_limiter = RateLimiter::new(config.tidal_requests_per_sec);
         Self {
             client: Client::new(),
             client_id,
             client_secret,
             db_path,
             config,
+            rate_limiter,
             token: tokio::sync::Mutex::new(None),
+            refresh_gate: RefreshGate::new(),
             root_folder_name,
             root_folder_id: tokio::sync::Mutex::new(None),
             playlist_cache: tokio::sync::Mutex::new(None),
@@ -436,7 +480,7 @@ impl TidalProvider {
         let db_path = self.db_path.clone();
         let json_opt =
             tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
-                let conn = rusqlite::Connection::open(db_path)?;
+                let conn = crate::db::open_tuned(db_path)?;
                 Ok(
                     crate::db::load_credential_with_client(&conn, "tidal")?
                         .map(|(json, _, _)| json),
@@ -460,7 +504,7 @@ impl TidalProvider {
         let client_id = self.client_id.clone();
         let client_secret = self.client_secret.clone();
         tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-            let conn = rusqlite::Connection::open(db_path)?;
+            let conn = crate::db::open_tuned(db_path)?;
             db::save_credential_raw(&conn, "tidal", &s, Some(&client_id), Some(&client_secret))?;
             Ok(())
         })
@@ -469,33 +513,65 @@ impl TidalProvider {
     }
 
     async fn ensure_token(&self) -> Result<()> {
-        let mut lock = self.token.lock().await;
-        if lock.is_none() {
-            if let Some(st) = self.load_token_from_db().await? {
-                *lock = Some(st);
+        // Read the generation before the token so a refresh finishing in
+        // between is noticed by `refresh_shared`.
+        let seen = self.refresh_gate.generation();
+        let (loaded, expired) = {
+            let mut lock = self.token.lock().await;
+            let loaded = lock.is_none();
+            if loaded {
+                if let Some(st) = self.load_token_from_db().await? {
+                    *lock = Some(st);
+                }
             }
-        }
-        // If token is near expiry, refresh if we have a refresh token
-        if let Some(st) = &*lock {
             let now = Utc::now().timestamp();
-            if now + 30 >= st.expires_at {
-                log::debug!("Tidal token near expiry, attempting refresh");
-                // attempt refresh if refresh_token present
-                let mut cur = st.clone();
-                // propagate errors so callers (and tests) notice failures
-                self.refresh_token_internal(&mut cur).await?;
-                *lock = Some(cur);
+            (
+                loaded,
+                lock.as_ref().is_some_and(|st| now + 30 >= st.expires_at),
+            )
+        };
+        // If token is near expiry, refresh if we have a refresh token
+        if expired {
+            log::debug!("Tidal token near expiry, attempting refresh");
+            // propagate errors so callers (and tests) notice failures
+            self.refresh_shared(seen).await?;
+        }
+        // Tokens stored before the auth helper looked up the user id lack
+        // it; discover it once per provider so `list_user_playlists` works.
+        if loaded {
+            let mut lock = self.token.lock().await;
+            if let Some(st) = lock.as_mut().filter(|st| st.user_id.is_none()) {
+                self.discover_user_id(st).await;
             }
         }
         Ok(())
     }
 
+    /// Fill in `st.user_id` from `/users/me` and persist it.  Failures are
+    /// only logged: everything but `list_user_playlists` works without it.
+    async fn discover_user_id(&self, st: &mut StoredToken) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        match fetch_user_id(&self.client, &st.access_token).await {
+            Ok(id) => {
+                log::info!("Discovered TIDAL user id {}", id);
+                st.user_id = Some(id);
+                if let Err(e) = self.persist_token_to_db(st).await {
+                    log::warn!("Failed to store the TIDAL user id: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Could not look up the TIDAL user id: {}", e),
+        }
+    }
+
     /// Force a token refresh using the stored refresh_token.
     ///
     /// This is primarily intended for the `AuthTest Tidal` CLI helper so
     /// users can verify that their client_id / client_secret and pasted
     /// token JSON support refresh before running playlist operations.
     pub async fn test_refresh_token(&self) -> Result<()> {
+        let seen = self.refresh_gate.generation();
         // Ensure we have a token loaded from DB first.
         {
             let mut lock = self.token.lock().await;
@@ -508,20 +584,27 @@ impl TidalProvider {
             }
         }
 
-        // Clone current token, attempt a refresh, then persist back into the
-        // in-memory cache (and DB via refresh_token_internal).
-        let mut cur = {
-            let lock = self.token.lock().await;
-            lock.as_ref()
-                .cloned()
-                .ok_or_else(|| anyhow!("no tidal token loaded"))?
-        };
-
-        self.refresh_token_internal(&mut cur).await?;
+        // Refresh and store the token in the in-memory cache (and DB via
+        // refresh_token_internal).
+        self.refresh_shared(seen).await
+    }
 
-        let mut lock = self.token.lock().await;
-        *lock = Some(cur);
-        Ok(())
+    /// Refresh the stored token through `refresh_gate`, unless another task
+    /// refreshed it since generation `seen`.
+    async fn refresh_shared(&self, seen: u64) -> Result<()> {
+        self.refresh_gate
+            .refresh(seen, || async {
+                let mut cur = self
+                    .token
+                    .lock()
+                    .await
+                    .clone()
+                    .ok_or_else(|| anyhow!("no tidal token loaded"))?;
+                self.refresh_token_internal(&mut cur).await?;
+                *self.token.lock().await = Some(cur);
+                Ok(())
+            })
+            .await
     }
 
     async fn refresh_token_internal(&self, cur: &mut StoredToken) -> Result<()> {
@@ -540,6 +623,9 @@ impl TidalProvider {
         );
         // Use the documented TIDAL OAuth2 token endpoint
         let url = format!("{}/v1/oauth2/token", Self::auth_base());
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
         let resp = self
             .client
             .post(&url)
@@ -569,10 +655,32 @@ impl TidalProvider {
         if let Some(s) = scope {
             cur.scope = Some(s);
         }
+        // Keep the stored (or discovered) user id; the refresh response
+        // normally omits it.
+        if cur.user_id.is_none() {
+            cur.user_id = j["user_id"].as_i64();
+        }
         self.persist_token_to_db(cur).await?;
         Ok(())
     }
 
+    /// Id of the user the stored token belongs to, asked from `/users/me`
+    /// (refreshing an expired token first).
+    pub async fn current_user_id(&self) -> Result<i64> {
+        self.ensure_token().await?;
+        let access_token = self
+            .token
+            .lock()
+            .await
+            .as_ref()
+            .map(|st| st.access_token.clone())
+            .ok_or_else(|| anyhow!("no tidal token loaded"))?;
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        fetch_user_id(&self.client, &access_token).await
+    }
+
     /// expose credentials for tests
     pub fn creds(&self) -> (&str, &str) {
         (self.client_id.as_str(), self.client_secret.as_str())
@@ -629,7 +737,7 @@ impl TidalProvider {
             let db_path = self.db_path.clone();
             let pid = playlist_id.to_string();
             match tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::get_playlist_item_id_cache(&conn, "tidal", &pid)
             })
             .await
@@ -674,7 +782,7 @@ impl TidalProvider {
         let mut full_map: HashMap<String, Vec<String>> = HashMap::new();
 
         let base = Self::base_url();
-        let cc = Self::country_code();
+        let cc = self.country_code();
         let mut next_url = format!(
             "{}/playlists/{}/relationships/items?countryCode={}",
             base, playlist_id, cc
@@ -799,7 +907,7 @@ impl TidalProvider {
             let map_clone = full_map.clone();
             let etag_clone = etag.clone();
             if let Err(e) = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-                let conn = rusqlite::Connection::open(&db_path)?;
+                let conn = crate::db::open_tuned(&db_path)?;
                 crate::db::upsert_playlist_item_id_cache(
                     &conn,
                     "tidal",
@@ -825,7 +933,7 @@ impl TidalProvider {
     async fn list_playlist_track_ids(&self, playlist_id: &str) -> Result<Vec<String>> {
         let mut out: Vec<String> = Vec::new();
         let base = Self::base_url();
-        let cc = Self::country_code();
+        let cc = self.country_code();
         let mut next_url = format!(
             "{}/playlists/{}/relationships/items?countryCode={}",
             base, playlist_id, cc
@@ -916,6 +1024,72 @@ impl TidalProvider {
         Ok(out)
     }
 
+    /// List the playlist's items in remote order as `(track_id, item_id)`
+    /// pairs.  Unlike [`Self::resolve_playlist_item_ids`] this preserves
+    /// duplicates and position, which reordering needs.
+    async fn list_playlist_items_ordered(
+        &self,
+        playlist_id: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let mut out: Vec<(String, String)> = Vec::new();
+        let base = Self::base_url();
+        let cc = self.country_code();
+        let mut next_url = format!(
+            "{}/playlists/{}/relationships/items?countryCode={}",
+            base, playlist_id, cc
+        );
+
+        loop {
+            let resp = self
+                .execute_request("list_playlist_items_ordered", &RequestSpec::get(&next_url))
+                .await?;
+            let status = resp.status();
+            if status == reqwest::StatusCode::NOT_FOUND {
+                return Ok(Vec::new());
+            }
+            if !status.is_success() {
+                let txt = resp.text().await.unwrap_or_default();
+                return Err(anyhow!(
+                    "Failed to list TIDAL playlist items for {}: {} => {}",
+                    playlist_id,
+                    status,
+                    txt
+                ));
+            }
+            let j: serde_json::Value = resp.json().await?;
+            if let Some(items) = j.get("data").and_then(|d| d.as_array()) {
+                for item in items {
+                    if item.get("type").and_then(|v| v.as_str()) != Some("tracks") {
+                        continue;
+                    }
+                    let track_id = item.get("id").and_then(|v| v.as_str());
+                    let item_id = item
+                        .get("meta")
+                        .and_then(|m| m.get("itemId"))
+                        .and_then(|v| v.as_str());
+                    if let (Some(t), Some(i)) = (track_id, item_id) {
+                        out.push((t.to_string(), i.to_string()));
+                    }
+                }
+            }
+            match j
+                .get("links")
+                .and_then(|l| l.get("next"))
+                .and_then(|v| v.as_str())
+            {
+                Some(next) if !next.is_empty() => {
+                    next_url = if next.starts_with("http") {
+                        next.to_string()
+                    } else {
+                        format!("{}{}", base, next)
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(out)
+    }
+
     /// Return the configured root folder name (trimmed) if any.
     fn root_folder(&self) -> Option<String> {
         self.root_folder_name
@@ -1056,6 +1230,79 @@ impl TidalProvider {
         }
         Ok(())
     }
+
+    /// Convert a failed playlist mutation into a [`ProviderError`].  TIDAL
+    /// answers 404 for unknown tracks as well, so only report
+    /// `PlaylistNotFound` when the body names the playlist itself.
+    async fn playlist_response_error(
+        resp: reqwest::Response,
+        context: &str,
+        playlist_id: &str,
+    ) -> ProviderError {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.trim().parse::<u64>().ok());
+        let body = resp.text().await.unwrap_or_default();
+        let pid = body.contains("Playlists with id").then_some(playlist_id);
+        ProviderError::from_status(status, &body, retry_after, context, pid)
+    }
+
+    /// Build a [`TrackMatch`] from one `/search/tracks` item.  Fields are read
+    /// from `attributes` when the item is a JSON:API resource and from the
+    /// item itself otherwise; ids that are empty or zero yield None.
+    fn track_match_from_search_item(item: &serde_json::Value) -> Option<TrackMatch> {
+        let id = match &item["id"] {
+            serde_json::Value::String(s) if !s.is_empty() && s != "0" => s.clone(),
+            serde_json::Value::Number(n) if n.as_i64().is_some_and(|n| n > 0) => n.to_string(),
+            _ => return None,
+        };
+        let attrs = if item["attributes"].is_object() {
+            &item["attributes"]
+        } else {
+            item
+        };
+        let text = |v: &serde_json::Value| v.as_str().map(str::to_string);
+        let duration_ms = match &attrs["duration"] {
+            serde_json::Value::Number(n) => n.as_u64().map(|secs| secs * 1000),
+            serde_json::Value::String(s) => iso8601_duration_ms(s),
+            _ => None,
+        };
+        Some(TrackMatch {
+            uri: format!("tidal:track:{}", id),
+            name: text(&attrs["title"]),
+            artist: text(&attrs["artist"]["name"]).or_else(|| text(&attrs["artists"][0]["name"])),
+            album: text(&attrs["album"]["title"]),
+            duration_ms,
+            isrc: text(&attrs["isrc"]),
+        })
+    }
+}
+
+/// Parse an ISO 8601 duration as used by the TIDAL v2 API (e.g. "PT3M25S")
+/// into milliseconds.
+fn iso8601_duration_ms(s: &str) -> Option<u64> {
+    let rest = s.strip_prefix("PT")?;
+    let mut ms = 0f64;
+    let mut num = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' | '.' => num.push(c),
+            'H' | 'M' | 'S' => {
+                let v: f64 = num.parse().ok()?;
+                num.clear();
+                ms += v * match c {
+                    'H' => 3_600_000.0,
+                    'M' => 60_000.0,
+                    _ => 1_000.0,
+                };
+            }
+            _ => return None,
+        }
+    }
+    num.is_empty().then_some(ms.round() as u64)
 }
 
 #[async_trait]
@@ -1066,21 +1313,32 @@ impl Provider for TidalProvider {
     fn http_client(&self) -> &reqwest::Client {
         &self.client
     }
-    async fn get_bearer(&self) -> Result<String> {
-        TidalProvider::get_bearer(self).await
+    fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
     }
-    async fn refresh_token(&self) -> Result<()> {
-        // Force-refresh the access token regardless of expiry, e.g. after a 401.
-        let mut lock = self.token.lock().await;
-        if lock.is_none() {
-            if let Some(st) = self.load_token_from_db().await? {
-                *lock = Some(st);
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(TidalProvider::get_bearer(self).await?)
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Force-refresh the access token regardless of expiry.
+        self.refresh_token_since(self.refresh_gate.generation())
+            .await
+    }
+    fn refresh_generation(&self) -> u64 {
+        self.refresh_gate.generation()
+    }
+    async fn refresh_token_since(&self, seen: u64) -> ProviderResult<()> {
+        // After a 401: requests rejected with the same token share a single
+        // refresh.
+        let loaded = {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                *lock = self.load_token_from_db().await?;
             }
-        }
-        if let Some(st) = &*lock {
-            let mut cur = st.clone();
-            self.refresh_token_internal(&mut cur).await?;
-            *lock = Some(cur);
+            lock.is_some()
+        };
+        if loaded {
+            self.refresh_shared(seen).await?;
         }
         Ok(())
     }
@@ -1090,11 +1348,18 @@ impl Provider for TidalProvider {
     fn is_authenticated(&self) -> bool {
         TidalProvider::is_authenticated(self)
     }
-    fn supports_folder_nesting(&self) -> bool {
-        false
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: true,
+            supports_reorder: true,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
     }
     fn max_batch_size(&self, cfg: &crate::config::Config) -> usize {
-        cfg.max_batch_size_tidal
+        cfg.max_batch_size_tidal.clamp(1, TIDAL_MAX_BATCH_SIZE)
     }
     fn validate_uri(&self, uri: &str) -> bool {
         // Only accept URIs whose trailing numeric id is a strictly positive
@@ -1103,32 +1368,45 @@ impl Provider for TidalProvider {
         let id = uri.rsplit(':').next().unwrap_or("").trim();
         id.parse::<u64>().ok().filter(|&n| n > 0).is_some()
     }
-    async fn playlist_is_valid(&self, playlist_id: &str) -> Result<Option<String>> {
-        // Check whether the playlist UUID still appears in the user's
-        // TIDAL library.  If it doesn't, the cached mapping is stale.
-        match self.list_user_playlists().await {
-            Ok(playlists) => {
-                if let Some((_id, name)) = playlists.iter().find(|(id, _name)| id == playlist_id) {
-                    Ok(Some(name.clone()))
-                } else {
-                    log::debug!(
-                        "TIDAL playlist {} not found in user library; treating mapping as invalid",
-                        playlist_id
-                    );
-                    Ok(None)
-                }
-            }
-            Err(e) => {
-                // If we can't list playlists (e.g. network error), propagate
-                // so the caller can retry rather than silently assuming valid.
-                Err(anyhow!(
-                    "playlist_is_valid: failed to list user playlists: {}",
-                    e
-                ))
-            }
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        // Ask for the playlist itself rather than consulting the (possibly
+        // cached) library listing, so a playlist deleted in the app is
+        // noticed on the next run.  A 404 means the mapping is stale; other
+        // failures propagate so the caller retries rather than recreating.
+        let url = format!(
+            "{}/playlists/{}?countryCode={}",
+            Self::base_url(),
+            playlist_id,
+            self.country_code()
+        );
+        let resp = self
+            .execute_request("playlist_is_valid", &RequestSpec::get(&url))
+            .await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            log::debug!(
+                "TIDAL playlist {} no longer exists; treating mapping as invalid",
+                playlist_id
+            );
+            // Keep ensure_playlist from finding the dead id by name.
+            Provider::invalidate_playlist_list_cache(self, playlist_id).await;
+            return Ok(None);
         }
+        if !resp.status().is_success() {
+            return Err(
+                ProviderError::from_response(resp, "tidal playlist_is_valid failed", None).await,
+            );
+        }
+        let j: serde_json::Value = resp.json().await?;
+        let attrs = &j["data"]["attributes"];
+        Ok(Some(
+            attrs["name"]
+                .as_str()
+                .or_else(|| attrs["title"].as_str())
+                .unwrap_or("")
+                .to_string(),
+        ))
     }
-    async fn ensure_playlist(&self, name: &str, description: &str) -> Result<String> {
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
         // Before creating a new playlist, check whether the user already owns
         // one with this exact name.  This prevents duplicates when the cached
         // UUID becomes stale but the playlist still exists under a different id.
@@ -1155,7 +1433,7 @@ impl Provider for TidalProvider {
 
         let base = Self::base_url();
         // JSON:API-style endpoint: POST /playlists
-        let url = format!("{}/playlists?countryCode={}", base, Self::country_code());
+        let url = format!("{}/playlists?countryCode={}", base, self.country_code());
         // Minimal JSON:API payload; TIDAL's API expects a `data` wrapper.
         let body = json!({
             "data": {
@@ -1172,14 +1450,10 @@ impl Provider for TidalProvider {
             .json(body)
             .header("content-type", "application/vnd.tidal.v1+json");
         let resp = self.execute_request("ensure_playlist", &spec).await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "tidal create playlist failed: {} => {}",
-                status,
-                txt
-            ));
+        if !resp.status().is_success() {
+            return Err(
+                ProviderError::from_response(resp, "tidal create playlist failed", None).await,
+            );
         }
         let j: serde_json::Value = resp.json().await?;
         // Tidal JSON:API responses return id under data.id
@@ -1212,14 +1486,14 @@ impl Provider for TidalProvider {
         return Ok(id_str);
     }
 
-    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> Result<()> {
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
         let base = Self::base_url();
         // JSON:API-style playlist update: PATCH /playlists/{id}
         let url = format!(
             "{}/playlists/{}?countryCode={}",
             base,
             playlist_id,
-            Self::country_code()
+            self.country_code()
         );
         let body = json!({
             "data": {
@@ -1235,23 +1509,58 @@ impl Provider for TidalProvider {
             .json(body)
             .header("content-type", "application/vnd.tidal.v1+json");
         let resp = self.execute_request("rename_playlist", &spec).await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("tidal rename failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(
+                Self::playlist_response_error(resp, "tidal rename failed", playlist_id).await,
+            );
         }
         self.cache_update_name(playlist_id, new_name).await;
         Ok(())
     }
 
-    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn update_playlist_description(
+        &self,
+        playlist_id: &str,
+        description: &str,
+    ) -> ProviderResult<()> {
+        let url = format!(
+            "{}/playlists/{}?countryCode={}",
+            Self::base_url(),
+            playlist_id,
+            self.country_code()
+        );
+        let body = json!({
+            "data": {
+                "type": "playlists",
+                "id": playlist_id,
+                "attributes": { "description": description }
+            }
+        });
+        let spec = RequestSpec::patch(&url)
+            .json(body)
+            .header("content-type", "application/vnd.tidal.v1+json");
+        let resp = self
+            .execute_request("update_playlist_description", &spec)
+            .await?;
+        if !resp.status().is_success() {
+            return Err(Self::playlist_response_error(
+                resp,
+                "tidal description update failed",
+                playlist_id,
+            )
+            .await);
+        }
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
         let base = Self::base_url();
         // JSON:API relationship endpoint: POST /playlists/{id}/relationships/items
         let url = format!(
             "{}/playlists/{}/relationships/items?countryCode={}",
             base,
             playlist_id,
-            Self::country_code(),
+            self.country_code(),
         );
         // Convert URIs like "tidal:track:{id}" into JSON:API relationship objects
         // { "data": [{"type": "tracks", "id": "{id}"}, ...] }.
@@ -1292,17 +1601,20 @@ impl Provider for TidalProvider {
             .json(body)
             .header("content-type", "application/vnd.api+json");
         let resp = self.execute_request("add_tracks", &spec).await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("tidal add tracks failed: {} => {}", status, txt));
+        if !resp.status().is_success() {
+            return Err(Self::playlist_response_error(
+                resp,
+                "tidal add tracks failed",
+                playlist_id,
+            )
+            .await);
         }
         // New tracks were added; positions are now stale.
         self.invalidate_item_id_cache(playlist_id).await;
         Ok(())
     }
 
-    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> Result<()> {
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
         let base = Self::base_url();
 
         // Normalize requested URIs into raw track ids and build a set
@@ -1373,7 +1685,7 @@ impl Provider for TidalProvider {
             // payload may exceed 20 even when the caller only passed ≤20 URIs.
             // Chunk the data array to stay within the limit.
             const TIDAL_DELETE_MAX: usize = 20;
-            let mut chunk_err: Option<anyhow::Error> = None;
+            let mut chunk_err: Option<ProviderError> = None;
             'chunks: for chunk in data.chunks(TIDAL_DELETE_MAX) {
                 let body = json!({ "data": chunk });
                 let mut spec = RequestSpec::delete(&url)
@@ -1383,10 +1695,15 @@ impl Provider for TidalProvider {
                     spec = spec.header("if-match", e.as_str());
                 }
                 let resp = self.execute_request("remove_tracks", &spec).await?;
-                let status = resp.status();
-                if !status.is_success() {
-                    let txt = resp.text().await.unwrap_or_default();
-                    chunk_err = Some(anyhow!("tidal remove tracks failed: {} => {}", status, txt));
+                if !resp.status().is_success() {
+                    chunk_err = Some(
+                        Self::playlist_response_error(
+                            resp,
+                            "tidal remove tracks failed",
+                            playlist_id,
+                        )
+                        .await,
+                    );
                     break 'chunks;
                 }
             }
@@ -1416,14 +1733,90 @@ impl Provider for TidalProvider {
         }
     }
 
-    async fn search_track_uri(&self, title: &str, artist: &str) -> Result<Option<String>> {
+    async fn reorder_tracks(
+        &self,
+        playlist_id: &str,
+        ordered_uris: &[String],
+    ) -> ProviderResult<()> {
+        // TIDAL moves items via PATCH on the items relationship with
+        // `meta.positionBefore` naming the item to insert in front of.  Walk the
+        // desired order back to front, moving each item directly before its
+        // successor whenever it isn't already there.
+        let mut current: Vec<(String, String)> =
+            self.list_playlist_items_ordered(playlist_id).await?;
+
+        // Map each desired track onto a concrete item id (first unused match).
+        let mut used: HashSet<String> = HashSet::new();
+        let mut target: Vec<(String, String)> = Vec::new();
+        for u in ordered_uris {
+            let id = u.rsplit(':').next().unwrap_or("").trim();
+            if let Some((t, i)) = current
+                .iter()
+                .find(|(t, i)| t == id && !used.contains(i))
+                .cloned()
+            {
+                used.insert(i.clone());
+                target.push((t, i));
+            }
+        }
+        if target.len() < 2 {
+            return Ok(());
+        }
+
+        let url = format!(
+            "{}/playlists/{}/relationships/items",
+            Self::base_url(),
+            playlist_id
+        );
+        for idx in (0..target.len() - 1).rev() {
+            let (track_id, item_id) = &target[idx];
+            let before_id = &target[idx + 1].1;
+            let pos = current.iter().position(|(_, i)| i == item_id);
+            let before_pos = current.iter().position(|(_, i)| i == before_id);
+            if let (Some(p), Some(b)) = (pos, before_pos) {
+                if p + 1 == b {
+                    continue;
+                }
+            }
+            let body = json!({
+                "data": [ { "type": "tracks", "id": track_id, "meta": { "itemId": item_id } } ],
+                "meta": { "positionBefore": before_id }
+            });
+            let spec = RequestSpec::patch(&url)
+                .json(body)
+                .header("content-type", "application/vnd.api+json");
+            let resp = self.execute_request("reorder_tracks", &spec).await?;
+            if !resp.status().is_success() {
+                return Err(Self::playlist_response_error(
+                    resp,
+                    "tidal reorder tracks failed",
+                    playlist_id,
+                )
+                .await);
+            }
+            // Mirror the move locally so later skip checks stay accurate.
+            if let Some(p) = pos {
+                let moved = current.remove(p);
+                let b = current
+                    .iter()
+                    .position(|(_, i)| i == before_id)
+                    .unwrap_or(current.len());
+                current.insert(b, moved);
+            }
+        }
+        // Positions changed; cached item ids/ETag are stale.
+        self.invalidate_item_id_cache(playlist_id).await;
+        Ok(())
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
         let base = Self::base_url();
         let q = format!("{} {}", title, artist);
         let url = format!(
             "{}/search/tracks?query={}&limit=1&countryCode={}",
             base,
             urlencoding::encode(&q),
-            Self::country_code()
+            self.country_code()
         );
         let resp = self
             .execute_request("search_track_uri", &RequestSpec::get(&url))
@@ -1453,7 +1846,50 @@ impl Provider for TidalProvider {
         Ok(None)
     }
 
-    async fn search_track_uri_by_isrc(&self, isrc: &str) -> Result<Option<String>> {
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        let base = Self::base_url();
+        let q = format!("{} {}", title, artist);
+        let url = format!(
+            "{}/search/tracks?query={}&limit={}&countryCode={}",
+            base,
+            urlencoding::encode(&q),
+            limit.max(1),
+            self.country_code()
+        );
+        let resp = self
+            .execute_request("search_track_candidates", &RequestSpec::get(&url))
+            .await?;
+        if !resp.status().is_success() {
+            return Ok(Vec::new());
+        }
+        let j: serde_json::Value = resp.json().await?;
+        let items = j["items"]
+            .as_array()
+            .or_else(|| j["items"]["items"].as_array());
+        let mut matches: Vec<TrackMatch> = items
+            .into_iter()
+            .flatten()
+            .filter_map(Self::track_match_from_search_item)
+            .take(limit.max(1))
+            .collect();
+        // Stable sort: results released on the tagged album come first.
+        if let Some(album) = album.map(str::trim).filter(|a| !a.is_empty()) {
+            matches.sort_by_key(|m| {
+                !m.album
+                    .as_deref()
+                    .is_some_and(|name| super::album_names_match(name, album))
+            });
+        }
+        Ok(matches)
+    }
+
+    async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
         let base = Self::base_url();
         // Use the dedicated ISRC filter endpoint, e.g.:
         //   /tracks?countryCode=US&filter%5Bisrc%5D=DEVF11900580
@@ -1461,7 +1897,7 @@ impl Provider for TidalProvider {
         let url = format!(
             "{}/tracks?countryCode={}&filter%5Bisrc%5D={}",
             base,
-            Self::country_code(),
+            self.country_code(),
             isrc
         );
         let resp = self
@@ -1486,7 +1922,69 @@ impl Provider for TidalProvider {
         Ok(None)
     }
 
-    async fn lookup_track_isrc(&self, uri: &str) -> Result<Option<String>> {
+    /// One `/tracks` request per [`TIDAL_ISRC_BATCH_SIZE`] ISRCs, repeating
+    /// `filter[isrc]`; results are matched back by their `isrc` attribute.
+    /// A chunk the API rejects is looked up one ISRC at a time.
+    async fn search_track_uris_by_isrc(
+        &self,
+        isrcs: &[String],
+    ) -> ProviderResult<HashMap<String, String>> {
+        let base = Self::base_url();
+        let mut found = HashMap::new();
+        for chunk in isrcs.chunks(TIDAL_ISRC_BATCH_SIZE) {
+            let filters: String = chunk
+                .iter()
+                .map(|isrc| format!("&filter%5Bisrc%5D={}", isrc))
+                .collect();
+            let url = format!(
+                "{}/tracks?countryCode={}{}",
+                base,
+                self.country_code(),
+                filters
+            );
+            let resp = self
+                .execute_request("search_track_uris_by_isrc", &RequestSpec::get(&url))
+                .await?;
+            if !resp.status().is_success() {
+                log::debug!(
+                    "batched ISRC lookup failed with {}; looking up one by one",
+                    resp.status()
+                );
+                for isrc in chunk {
+                    if let Some(uri) = self.search_track_uri_by_isrc(isrc).await? {
+                        found.insert(isrc.clone(), uri);
+                    }
+                }
+                continue;
+            }
+            let j: serde_json::Value = resp.json().await?;
+            for item in j["data"].as_array().into_iter().flatten() {
+                let id = match &item["id"] {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    _ => continue,
+                };
+                if id.is_empty() || id == "0" {
+                    continue;
+                }
+                let isrc = match item["attributes"]["isrc"].as_str() {
+                    Some(code) => chunk.iter().find(|i| i.eq_ignore_ascii_case(code)),
+                    // Without the attribute only a single-ISRC answer is unambiguous.
+                    None if chunk.len() == 1 => chunk.first(),
+                    None => None,
+                };
+                if let Some(isrc) = isrc {
+                    // Keep the first track per ISRC, as the single lookup does.
+                    found
+                        .entry(isrc.clone())
+                        .or_insert_with(|| format!("tidal:track:{}", id));
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
         // Expect URIs like "tidal:track:{id}"; extract the id portion.
         let id = if let Some(i) = uri.rsplit(':').next() {
             i.to_string()
@@ -1497,7 +1995,7 @@ impl Provider for TidalProvider {
             return Ok(None);
         }
         let base = Self::base_url();
-        let cc = Self::country_code();
+        let cc = self.country_code();
         let url = format!("{}/tracks/{}?countryCode={}", base, id, cc);
         let resp = self
             .execute_request("lookup_track_isrc", &RequestSpec::get(&url))
@@ -1514,6 +2012,26 @@ impl Provider for TidalProvider {
         Ok(None)
     }
 
+    async fn track_exists(&self, uri: &str) -> ProviderResult<Option<bool>> {
+        let Some(id) = uri.rsplit(':').next().filter(|id| !id.is_empty()) else {
+            return Ok(None);
+        };
+        let url = format!(
+            "{}/tracks/{}?countryCode={}",
+            Self::base_url(),
+            id,
+            self.country_code()
+        );
+        let resp = self
+            .execute_request("track_exists", &RequestSpec::get(&url))
+            .await?;
+        match resp.status() {
+            s if s.is_success() => Ok(Some(true)),
+            reqwest::StatusCode::NOT_FOUND => Ok(Some(false)),
+            _ => Err(ProviderError::from_response(resp, "tidal track lookup failed", None).await),
+        }
+    }
+
     async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
         // Remove only the dead entry from the in-memory cache.  If the
         // in-memory cache is cold (None), load it from the DB first so we
@@ -1525,7 +2043,7 @@ impl Provider for TidalProvider {
             if cache.is_none() {
                 // Warm from DB so we can edit it in place.
                 let loaded = tokio::task::spawn_blocking(move || {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = crate::db::open_tuned(&db_path)?;
                     crate::db::get_provider_playlist_list_cache(&conn, "tidal")
                 })
                 .await
@@ -1546,7 +2064,7 @@ impl Provider for TidalProvider {
         let db_path = self.db_path.clone();
         let pid2 = playlist_id.to_string();
         let _ = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-            let conn = rusqlite::Connection::open(&db_path)?;
+            let conn = crate::db::open_tuned(&db_path)?;
             match updated {
                 Some(ref entries) => {
                     crate::db::upsert_provider_playlist_list_cache(&conn, "tidal", entries)?;
@@ -1566,33 +2084,32 @@ impl Provider for TidalProvider {
         .await;
     }
 
-    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
         let base = Self::base_url();
         let url = format!(
             "{}/playlists/{}?countryCode={}",
             base,
             playlist_id,
-            Self::country_code()
+            self.country_code()
         );
         let resp = self
             .execute_request("delete_playlist", &RequestSpec::delete(&url))
             .await?;
-        let status = resp.status();
-        if !status.is_success() {
-            let txt = resp.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "tidal delete playlist failed: {} => {}",
-                status,
-                txt
-            ));
+        if !resp.status().is_success() {
+            return Err(Self::playlist_response_error(
+                resp,
+                "tidal delete playlist failed",
+                playlist_id,
+            )
+            .await);
         }
         self.cache_remove_entry(playlist_id).await;
         Ok(())
     }
 
-    async fn list_playlist_tracks(&self, playlist_id: &str) -> Result<Vec<String>> {
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
         // For TIDAL we expose track ids as opaque "URIs"; reconciliation
         // logic only cares about set equality, not the scheme itself.
-        self.list_playlist_track_ids(playlist_id).await
+        Ok(self.list_playlist_track_ids(playlist_id).await?)
     }
 }
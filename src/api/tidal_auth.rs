@@ -1,12 +1,27 @@
+use crate::api::oauth_callback::{open_browser, CallbackListener};
+use crate::api::pkce::{code_challenge_s256, generate_code_verifier};
 use crate::config::Config;
 use crate::db;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
+use url::Url;
 
-/// Simple helper to persist Tidal token JSON into the DB.
-/// For this flow we ask the user to obtain a token via the
-/// TIDAL API reference site and paste the raw JSON response.
+/// Scopes requested by both authorization flows.
+const TIDAL_SCOPES: &str =
+    "playlists.read playlists.write collection.read collection.write user.read";
+
+/// Grant type of the token polls in the device-authorization flow.
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Simple helper to persist Tidal tokens into the DB.
+/// By default it runs the authorization-code + PKCE flow, capturing the
+/// redirect with a one-shot loopback listener.  With `--no-browser`, or if
+/// the redirect port cannot be bound, it uses the device-authorization grant
+/// instead: the user enters a short code on the TIDAL site from any device
+/// while we poll the token endpoint.
 #[derive(Serialize, Deserialize)]
 struct TokenBlob {
     access_token: String,
@@ -17,9 +32,45 @@ struct TokenBlob {
     user_id: Option<i64>,
 }
 
-pub async fn run_tidal_auth(cfg: &Config) -> Result<()> {
-    use chrono::Utc;
-    use std::io::{self, Read};
+/// Response of the device-authorization endpoint.  TIDAL answers in
+/// camelCase; the RFC 8628 snake_case names are accepted as well.
+#[derive(Deserialize)]
+struct DeviceAuthorization {
+    #[serde(alias = "deviceCode")]
+    device_code: String,
+    #[serde(alias = "userCode")]
+    user_code: String,
+    #[serde(alias = "verificationUri")]
+    verification_uri: String,
+    #[serde(alias = "verificationUriComplete", default)]
+    verification_uri_complete: Option<String>,
+    #[serde(alias = "expiresIn", default = "default_device_expires_in")]
+    expires_in: u64,
+    #[serde(default = "default_device_interval")]
+    interval: u64,
+}
+
+fn default_device_expires_in() -> u64 {
+    300
+}
+
+fn default_device_interval() -> u64 {
+    5
+}
+
+fn auth_base() -> String {
+    std::env::var("TIDAL_AUTH_BASE").unwrap_or_else(|_| "https://auth.tidal.com".into())
+}
+
+fn basic_auth(client_id: &str, client_secret: &str) -> String {
+    format!(
+        "Basic {}",
+        general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret))
+    )
+}
+
+pub async fn run_tidal_auth(cfg: &Config, no_browser: bool) -> Result<()> {
+    use std::io;
 
     println!("Enter your Tidal client_id:");
     let mut client_id = String::new();
@@ -37,26 +88,187 @@ pub async fn run_tidal_auth(cfg: &Config) -> Result<()> {
         return Err(anyhow!("no client_secret provided"));
     }
 
-    println!("\nNow obtain a TIDAL OAuth token using the official API reference site.");
-    println!("1. Open: https://tidal-music.github.io/tidal-api-reference/ in your browser.");
-    println!("2. Use your TIDAL client_id and client_secret to authorize the app.");
+    if !no_browser {
+        println!("Enter your Tidal redirect URI (leave blank for http://127.0.0.1:8888/):");
+        let mut redirect_uri = String::new();
+        io::stdin().read_line(&mut redirect_uri)?;
+        let redirect_uri = {
+            let trimmed = redirect_uri.trim();
+            if trimmed.is_empty() {
+                "http://127.0.0.1:8888/".to_string()
+            } else {
+                trimmed.to_string()
+            }
+        };
+        if let Some(listener) = CallbackListener::bind(&redirect_uri) {
+            let tr = authorize_with_listener(listener, &client_id, &client_secret, &redirect_uri)
+                .await?;
+            return save_token(cfg, tr, client_id, client_secret).await;
+        }
+    }
+
+    run_tidal_device_auth(cfg, &client_id, &client_secret).await
+}
+
+/// Authorize with the device-authorization grant and store the tokens.
+/// Prints the verification URL and user code, then polls the token
+/// endpoint until the user has approved the request or the code expires.
+pub async fn run_tidal_device_auth(
+    cfg: &Config,
+    client_id: &str,
+    client_secret: &str,
+) -> Result<()> {
+    let tr = authorize_with_device_code(client_id, client_secret).await?;
+    save_token(cfg, tr, client_id.to_string(), client_secret.to_string()).await
+}
+
+async fn authorize_with_device_code(client_id: &str, client_secret: &str) -> Result<TokenBlob> {
+    let client = Client::new();
+    let resp = client
+        .post(format!("{}/v1/oauth2/device_authorization", auth_base()))
+        .form(&[("client_id", client_id), ("scope", TIDAL_SCOPES)])
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let txt = resp.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "device authorization request failed: {} => {}",
+            status,
+            txt
+        ));
+    }
+    let device: DeviceAuthorization = resp.json().await?;
+
+    // TIDAL returns the verification URL without a scheme.
+    let url = device
+        .verification_uri_complete
+        .as_deref()
+        .unwrap_or(&device.verification_uri);
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    println!(
+        "To authorize the application, visit:\n\n{}\n\nand enter the code {}",
+        url, device.user_code
+    );
     println!(
-        "3. In your browser dev tools (Network tab), find the token request to auth.tidal.com."
+        "Waiting for authorization (the code expires in {} minutes) ...",
+        device.expires_in.div_ceil(60)
     );
-    println!("4. In the Response tab of that request, copy the full JSON body (access_token, refresh_token, etc.).");
-    println!("\nPaste the JSON response below, then press Ctrl+D (on Linux/macOS) when you're done pasting:\n");
-
-    let mut buf = String::new();
-    io::stdin().read_to_string(&mut buf)?;
-    let buf = buf.trim();
-    if buf.is_empty() {
-        return Err(anyhow!("no JSON pasted"));
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(device.expires_in);
+    let mut interval = device.interval;
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        if std::time::Instant::now() >= deadline {
+            return Err(anyhow!(
+                "the device code expired before it was authorized; run `auth tidal` again"
+            ));
+        }
+        let resp = client
+            .post(format!("{}/v1/oauth2/token", auth_base()))
+            .header("Authorization", basic_auth(client_id, client_secret))
+            .form(&[
+                ("grant_type", DEVICE_CODE_GRANT),
+                ("client_id", client_id),
+                ("device_code", device.device_code.as_str()),
+                ("scope", TIDAL_SCOPES),
+            ])
+            .send()
+            .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp.json().await?);
+        }
+        let txt = resp.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<serde_json::Value>(&txt)
+            .ok()
+            .and_then(|j| j["error"].as_str().map(str::to_string))
+            .unwrap_or_default();
+        match error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += 5,
+            "expired_token" => {
+                return Err(anyhow!(
+                    "the device code expired before it was authorized; run `auth tidal` again"
+                ))
+            }
+            _ => {
+                return Err(anyhow!(
+                    "device token request failed: {} => {}",
+                    status,
+                    txt
+                ))
+            }
+        }
     }
+}
+
+/// Open the TIDAL login page and exchange the code captured by `listener`.
+async fn authorize_with_listener(
+    listener: CallbackListener,
+    client_id: &str,
+    client_secret: &str,
+    redirect_uri: &str,
+) -> Result<TokenBlob> {
+    let verifier = generate_code_verifier();
+    let state = generate_code_verifier();
+    let mut url = Url::parse("https://login.tidal.com/authorize")?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("scope", TIDAL_SCOPES)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("code_challenge", &code_challenge_s256(&verifier))
+        .append_pair("state", &state);
+
+    println!(
+        "Opening your browser to authorize the application. If it does not open, visit:\n\n{}\n",
+        url
+    );
+    open_browser(url.as_str());
+    println!("Waiting for the redirect to {} ...", redirect_uri);
+    let code = tokio::task::spawn_blocking(move || listener.wait_for_code(Some(&state))).await??;
 
-    let tr: TokenBlob = serde_json::from_str(buf)
-        .map_err(|e| anyhow!("failed to parse pasted JSON as token response: {}", e))?;
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", verifier.as_str()),
+    ];
+    let resp = Client::new()
+        .post(format!("{}/v1/oauth2/token", auth_base()))
+        .header("Authorization", basic_auth(client_id, client_secret))
+        .form(&params)
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let txt = resp.text().await.unwrap_or_default();
+        return Err(anyhow!("token exchange failed: {} => {}", status, txt));
+    }
+    Ok(resp.json().await?)
+}
 
-    let expires_at = Utc::now().timestamp() + tr.expires_in.unwrap_or(3600);
+async fn save_token(
+    cfg: &Config,
+    tr: TokenBlob,
+    client_id: String,
+    client_secret: String,
+) -> Result<()> {
+    let expires_at = chrono::Utc::now().timestamp() + tr.expires_in.unwrap_or(3600);
+    // `list_user_playlists` needs the user id; not every token response has it.
+    let user_id = match tr.user_id {
+        Some(id) => id,
+        None => crate::api::tidal::fetch_user_id(&Client::new(), &tr.access_token)
+            .await
+            .context("looking up the TIDAL user id")?,
+    };
     // Build the stored token to match what the provider expects
     let stored_token = crate::api::tidal::StoredToken {
         access_token: tr.access_token,
@@ -64,16 +276,14 @@ pub async fn run_tidal_auth(cfg: &Config) -> Result<()> {
         expires_at,
         refresh_token: tr.refresh_token,
         scope: tr.scope,
-        user_id: tr.user_id,
+        user_id: Some(user_id),
     };
     let token_json = serde_json::to_string(&stored_token)?;
 
     // Persist to DB (blocking), together with client_id/client_secret
     let db_path = cfg.db_path.clone();
-    let client_id = client_id.to_string();
-    let client_secret = client_secret.to_string();
     tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-        let conn = rusqlite::Connection::open(db_path)?;
+        let conn = crate::db::open_tuned(db_path)?;
         db::save_credential_raw(
             &conn,
             "tidal",
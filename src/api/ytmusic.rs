@@ -0,0 +1,622 @@
+use super::refresh_gate::RefreshGate;
+use super::{Provider, ProviderCapabilities, ProviderError, ProviderResult, RequestSpec};
+use crate::db;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::debug;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::env;
+
+/// Prefix of the URIs handed out by `search_track_uri`.
+const URI_PREFIX: &str = "ytmusic:video:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_at: i64, // epoch seconds
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// YouTube Music provider backed by the YouTube Data API v3.
+/// Playlists are regular YouTube playlists and tracks are videos, so URIs
+/// take the form `ytmusic:video:{videoId}`.  Note that the Data API is
+/// quota-limited (a `search.list` call costs 100 units of the default 10k
+/// daily quota), so the track cache matters even more than for Spotify.
+/// Endpoints may be overridden by YTMUSIC_AUTH_BASE and YTMUSIC_API_BASE env vars (useful for tests).
+pub struct YtMusicProvider {
+    client: Client,
+    client_id: String,
+    client_secret: String,
+    db_path: std::path::PathBuf,
+    config: crate::config::Config,
+    token: tokio::sync::Mutex<Option<StoredToken>>,
+    /// Lets only one refresh of `token` run at a time.
+    refresh_gate: RefreshGate,
+    /// Cached result of `list_user_playlists()` so we only fetch the full
+    /// library once per worker run instead of once per playlist.
+    playlist_cache: tokio::sync::Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl YtMusicProvider {
+    pub fn new(
+        client_id: String,
+        client_secret: String,
+        db_path: std::path::PathBuf,
+        config: crate::config::Config,
+    ) -> Self {
+        // If either client_id or client_secret is empty, try to load from DB
+        let (client_id, client_secret) = if client_id.is_empty() || client_secret.is_empty() {
+            if let Ok(conn) = crate::db::open_tuned(&db_path) {
+                if let Ok(Some((_token_json, db_client_id, db_client_secret))) =
+                    crate::db::load_credential_with_client(&conn, "ytmusic")
+                {
+                    (
+                        db_client_id.unwrap_or(client_id),
+                        db_client_secret.unwrap_or(client_secret),
+                    )
+                } else {
+                    (client_id, client_secret)
+                }
+            } else {
+                (client_id, client_secret)
+            }
+        } else {
+            (client_id, client_secret)
+        };
+        Self {
+            client: Client::new(),
+            client_id,
+            client_secret,
+            db_path,
+            config,
+            token: tokio::sync::Mutex::new(None),
+            refresh_gate: RefreshGate::new(),
+            playlist_cache: tokio::sync::Mutex::new(None),
+        }
+    }
+    fn is_authenticated(&self) -> bool {
+        !self.client_id.is_empty() && !self.client_secret.is_empty()
+    }
+    fn name(&self) -> &str {
+        "ytmusic"
+    }
+
+    fn auth_base() -> String {
+        env::var("YTMUSIC_AUTH_BASE").unwrap_or_else(|_| "https://oauth2.googleapis.com".into())
+    }
+    fn api_base() -> String {
+        env::var("YTMUSIC_API_BASE")
+            .unwrap_or_else(|_| "https://www.googleapis.com/youtube/v3".into())
+    }
+
+    /// Strip the `ytmusic:video:` prefix, returning `None` for empty ids.
+    fn video_id(uri: &str) -> Option<&str> {
+        let id = uri.strip_prefix(URI_PREFIX).unwrap_or(uri).trim();
+        if id.is_empty() {
+            None
+        } else {
+            Some(id)
+        }
+    }
+
+    async fn load_token_from_db(&self) -> Result<Option<StoredToken>> {
+        let db_path = self.db_path.clone();
+        let json_opt =
+            tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
+                let conn = crate::db::open_tuned(db_path)?;
+                Ok(crate::db::load_credential_with_client(&conn, "ytmusic")?
+                    .map(|(json, _, _)| json))
+            })
+            .await??;
+
+        if let Some(s) = json_opt {
+            let st: StoredToken =
+                serde_json::from_str(&s).map_err(|e| anyhow!("parse token json: {}", e))?;
+            Ok(Some(st))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn persist_token_to_db(&self, st: &StoredToken) -> Result<()> {
+        let db_path = self.db_path.clone();
+        let s = serde_json::to_string(&st)?;
+        // Pass the client credentials explicitly so the UPSERT does not
+        // overwrite them with NULL and wipe them from the DB on every refresh.
+        let client_id = self.client_id.clone();
+        let client_secret = self.client_secret.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let conn = crate::db::open_tuned(db_path)?;
+            db::save_credential_raw(&conn, "ytmusic", &s, Some(&client_id), Some(&client_secret))?;
+            Ok(())
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn ensure_token(&self) -> Result<()> {
+        // Read the generation before the token so a refresh finishing in
+        // between is noticed by `refresh_shared`.
+        let seen = self.refresh_gate.generation();
+        let expired = {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                if let Some(st) = self.load_token_from_db().await? {
+                    *lock = Some(st);
+                }
+            }
+            let now = Utc::now().timestamp();
+            lock.as_ref().is_some_and(|st| now + 30 >= st.expires_at)
+        };
+        if expired {
+            debug!("YouTube token is near expiry, refreshing");
+            self.refresh_shared(seen).await?;
+        }
+        Ok(())
+    }
+
+    /// Refresh the stored token through `refresh_gate`, unless another task
+    /// refreshed it since generation `seen`.
+    async fn refresh_shared(&self, seen: u64) -> Result<()> {
+        self.refresh_gate
+            .refresh(seen, || async {
+                let mut cur = self
+                    .token
+                    .lock()
+                    .await
+                    .clone()
+                    .ok_or_else(|| anyhow!("no youtube token loaded"))?;
+                self.refresh_token_internal(&mut cur).await?;
+                *self.token.lock().await = Some(cur);
+                Ok(())
+            })
+            .await
+    }
+
+    async fn refresh_token_internal(&self, cur: &mut StoredToken) -> Result<()> {
+        let refresh_token = cur
+            .refresh_token
+            .clone()
+            .ok_or_else(|| anyhow!("no refresh token"))?;
+        // Google expects the client credentials in the form body rather than
+        // a Basic auth header.
+        let params = [
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        let url = format!("{}/token", Self::auth_base());
+        let resp = self.client.post(&url).form(&params).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to refresh token: {} - {}", status, body));
+        }
+        let j: serde_json::Value = resp.json().await?;
+        let access_token = j["access_token"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no access_token"))?
+            .to_string();
+        let expires_in = j["expires_in"].as_i64().unwrap_or(3600);
+        cur.access_token = access_token;
+        cur.token_type = "Bearer".into();
+        cur.expires_at = Utc::now().timestamp() + expires_in;
+        if let Some(s) = j["scope"].as_str() {
+            cur.scope = Some(s.to_string());
+        }
+        // Google only returns a new refresh token when it rotates it.
+        if let Some(rt) = j["refresh_token"].as_str() {
+            cur.refresh_token = Some(rt.to_string());
+        }
+        self.persist_token_to_db(cur).await?;
+        Ok(())
+    }
+
+    pub async fn get_bearer(&self) -> Result<String> {
+        self.ensure_token().await?;
+        let lock = self.token.lock().await;
+        let st = lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("no youtube token loaded"))?;
+        Ok(format!("Bearer {}", st.access_token))
+    }
+
+    /// Convert a failed Data API response into a [`ProviderError`].  The API
+    /// reports quota exhaustion as 403 with reason `quotaExceeded` /
+    /// `rateLimitExceeded`, and uses 404 for both unknown videos and unknown
+    /// playlists, so the error reason in the body decides the variant.
+    async fn response_error(
+        resp: reqwest::Response,
+        context: &str,
+        playlist_id: Option<&str>,
+    ) -> ProviderError {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.trim().parse::<u64>().ok());
+        let body = resp.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::FORBIDDEN
+            && (body.contains("quotaExceeded") || body.contains("rateLimitExceeded"))
+        {
+            return ProviderError::RateLimited { retry_after };
+        }
+        let pid = playlist_id.filter(|_| body.contains("playlistNotFound"));
+        ProviderError::from_status(status, &body, retry_after, context, pid)
+    }
+
+    /// List all playlists owned by the authenticated user.
+    /// Results are cached for the lifetime of the provider instance.
+    pub async fn list_user_playlists(&self) -> Result<Vec<(String, String)>> {
+        {
+            let cache = self.playlist_cache.lock().await;
+            if let Some(ref cached) = *cache {
+                return Ok(cached.clone());
+            }
+        }
+        let mut playlists = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/playlists?part=snippet&mine=true&maxResults=50",
+                Self::api_base()
+            );
+            if let Some(ref t) = page_token {
+                url.push_str(&format!("&pageToken={}", urlencoding::encode(t)));
+            }
+            let resp = self
+                .execute_request("list_user_playlists", &RequestSpec::get(&url))
+                .await?;
+            if !resp.status().is_success() {
+                return Err(Self::response_error(resp, "list playlists failed", None)
+                    .await
+                    .into());
+            }
+            let j: serde_json::Value = resp.json().await?;
+            if let Some(items) = j["items"].as_array() {
+                for pl in items {
+                    let id = pl["id"].as_str().unwrap_or("").to_string();
+                    let name = pl["snippet"]["title"].as_str().unwrap_or("").to_string();
+                    playlists.push((id, name));
+                }
+            }
+            page_token = j["nextPageToken"].as_str().map(|s| s.to_string());
+            if page_token.is_none() {
+                break;
+            }
+        }
+        *self.playlist_cache.lock().await = Some(playlists.clone());
+        Ok(playlists)
+    }
+
+    /// List (playlistItemId, videoId) pairs for a playlist in playlist order.
+    /// Deleting an entry needs the item id, not the video id.
+    async fn list_playlist_items(
+        &self,
+        playlist_id: &str,
+    ) -> ProviderResult<Vec<(String, String)>> {
+        let mut out = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/playlistItems?part=contentDetails&maxResults=50&playlistId={}",
+                Self::api_base(),
+                urlencoding::encode(playlist_id)
+            );
+            if let Some(ref t) = page_token {
+                url.push_str(&format!("&pageToken={}", urlencoding::encode(t)));
+            }
+            let resp = self
+                .execute_request("list_playlist_items", &RequestSpec::get(&url))
+                .await?;
+            if !resp.status().is_success() {
+                return Err(Self::response_error(
+                    resp,
+                    "list playlist items failed",
+                    Some(playlist_id),
+                )
+                .await);
+            }
+            let j: serde_json::Value = resp.json().await?;
+            if let Some(items) = j["items"].as_array() {
+                for it in items {
+                    let item_id = it["id"].as_str().unwrap_or("");
+                    if let Some(video_id) = it["contentDetails"]["videoId"].as_str() {
+                        out.push((item_id.to_string(), video_id.to_string()));
+                    }
+                }
+            }
+            page_token = j["nextPageToken"].as_str().map(|s| s.to_string());
+            if page_token.is_none() {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Fetch a playlist's snippet, or `None` if it does not exist.
+    async fn playlist_snippet(
+        &self,
+        playlist_id: &str,
+    ) -> ProviderResult<Option<serde_json::Value>> {
+        let url = format!(
+            "{}/playlists?part=snippet&id={}",
+            Self::api_base(),
+            urlencoding::encode(playlist_id)
+        );
+        let resp = self
+            .execute_request("playlist_snippet", &RequestSpec::get(&url))
+            .await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(Self::response_error(resp, "get playlist failed", None).await);
+        }
+        let j: serde_json::Value = resp.json().await?;
+        Ok(j["items"]
+            .as_array()
+            .and_then(|a| a.first())
+            .map(|p| p["snippet"].clone()))
+    }
+
+    async fn cache_update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Vec<(String, String)>),
+    {
+        let mut cache = self.playlist_cache.lock().await;
+        if let Some(ref mut entries) = *cache {
+            f(entries);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for YtMusicProvider {
+    fn config(&self) -> &crate::config::Config {
+        &self.config
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(YtMusicProvider::get_bearer(self).await?)
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        // Force-refresh the access token regardless of expiry.
+        self.refresh_token_since(self.refresh_gate.generation())
+            .await
+    }
+    fn refresh_generation(&self) -> u64 {
+        self.refresh_gate.generation()
+    }
+    async fn refresh_token_since(&self, seen: u64) -> ProviderResult<()> {
+        // After a 401: requests rejected with the same token share a single
+        // refresh.
+        let loaded = {
+            let mut lock = self.token.lock().await;
+            if lock.is_none() {
+                *lock = self.load_token_from_db().await?;
+            }
+            lock.is_some()
+        };
+        if loaded {
+            self.refresh_shared(seen).await?;
+        }
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        YtMusicProvider::name(self)
+    }
+    fn is_authenticated(&self) -> bool {
+        YtMusicProvider::is_authenticated(self)
+    }
+
+    async fn ensure_playlist(&self, name: &str, description: &str) -> ProviderResult<String> {
+        // Reuse an existing playlist with the same title so a lost mapping
+        // does not create duplicates.
+        match self.list_user_playlists().await {
+            Ok(playlists) => {
+                if let Some((existing_id, _)) = playlists.iter().find(|(_id, n)| n == name) {
+                    return Ok(existing_id.clone());
+                }
+            }
+            Err(e) => {
+                // Non-fatal: if listing fails we fall through to create.
+                log::warn!(
+                    "YtMusicProvider ensure_playlist: could not list playlists to check for '{}': {}",
+                    name,
+                    e
+                );
+            }
+        }
+
+        let url = format!("{}/playlists?part=snippet,status", Self::api_base());
+        let body = json!({
+            "snippet": { "title": name, "description": description },
+            "status": { "privacyStatus": "private" }
+        });
+        let resp = self
+            .execute_request("ensure_playlist", &RequestSpec::post(&url).json(body))
+            .await?;
+        if !resp.status().is_success() {
+            return Err(Self::response_error(resp, "create playlist failed", None).await);
+        }
+        let j: serde_json::Value = resp.json().await?;
+        let id = j["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no id"))?
+            .to_string();
+        let (id_c, name_c) = (id.clone(), name.to_string());
+        self.cache_update(move |entries| entries.push((id_c, name_c)))
+            .await;
+        Ok(id)
+    }
+
+    async fn rename_playlist(&self, playlist_id: &str, new_name: &str) -> ProviderResult<()> {
+        // playlists.update replaces the whole snippet, so carry the current
+        // description over instead of clearing it.
+        let snippet = self.playlist_snippet(playlist_id).await?.ok_or_else(|| {
+            ProviderError::PlaylistNotFound {
+                playlist_id: playlist_id.to_string(),
+            }
+        })?;
+        let url = format!("{}/playlists?part=snippet", Self::api_base());
+        let body = json!({
+            "id": playlist_id,
+            "snippet": {
+                "title": new_name,
+                "description": snippet["description"].as_str().unwrap_or("")
+            }
+        });
+        let resp = self
+            .execute_request("rename_playlist", &RequestSpec::put(&url).json(body))
+            .await?;
+        if !resp.status().is_success() {
+            return Err(Self::response_error(resp, "rename failed", Some(playlist_id)).await);
+        }
+        self.cache_update(|entries| {
+            for (id, n) in entries.iter_mut() {
+                if id == playlist_id {
+                    *n = new_name.to_string();
+                }
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn add_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        // The Data API has no batch insert; each video is its own request.
+        let url = format!("{}/playlistItems?part=snippet", Self::api_base());
+        for video_id in uris.iter().filter_map(|u| Self::video_id(u)) {
+            let body = json!({
+                "snippet": {
+                    "playlistId": playlist_id,
+                    "resourceId": { "kind": "youtube#video", "videoId": video_id }
+                }
+            });
+            let resp = self
+                .execute_request("add_tracks", &RequestSpec::post(&url).json(body))
+                .await?;
+            if !resp.status().is_success() {
+                return Err(
+                    Self::response_error(resp, "add tracks failed", Some(playlist_id)).await,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_tracks(&self, playlist_id: &str, uris: &[String]) -> ProviderResult<()> {
+        let wanted: HashSet<&str> = uris.iter().filter_map(|u| Self::video_id(u)).collect();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        for (item_id, video_id) in self.list_playlist_items(playlist_id).await? {
+            if !wanted.contains(video_id.as_str()) {
+                continue;
+            }
+            let url = format!(
+                "{}/playlistItems?id={}",
+                Self::api_base(),
+                urlencoding::encode(&item_id)
+            );
+            let resp = self
+                .execute_request("remove_tracks", &RequestSpec::delete(&url))
+                .await?;
+            if !resp.status().is_success() {
+                return Err(
+                    Self::response_error(resp, "remove tracks failed", Some(playlist_id)).await,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> ProviderResult<()> {
+        let url = format!(
+            "{}/playlists?id={}",
+            Self::api_base(),
+            urlencoding::encode(playlist_id)
+        );
+        let resp = self
+            .execute_request("delete_playlist", &RequestSpec::delete(&url))
+            .await?;
+        if !resp.status().is_success() {
+            return Err(
+                Self::response_error(resp, "delete playlist failed", Some(playlist_id)).await,
+            );
+        }
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+        Ok(())
+    }
+
+    async fn playlist_is_valid(&self, playlist_id: &str) -> ProviderResult<Option<String>> {
+        Ok(self
+            .playlist_snippet(playlist_id)
+            .await?
+            .map(|s| s["title"].as_str().unwrap_or("").to_string()))
+    }
+
+    async fn invalidate_playlist_list_cache(&self, playlist_id: &str) {
+        self.cache_update(|entries| entries.retain(|(id, _)| id != playlist_id))
+            .await;
+    }
+
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .list_playlist_items(playlist_id)
+            .await?
+            .into_iter()
+            .filter(|(_, v)| seen.insert(v.clone()))
+            .map(|(_, v)| format!("{}{}", URI_PREFIX, v))
+            .collect())
+    }
+
+    async fn search_track_uri(&self, title: &str, artist: &str) -> ProviderResult<Option<String>> {
+        // videoCategoryId=10 restricts results to the "Music" category.
+        let q = format!("{} {}", artist, title);
+        let url = format!(
+            "{}/search?part=snippet&type=video&videoCategoryId=10&maxResults=1&q={}",
+            Self::api_base(),
+            urlencoding::encode(&q)
+        );
+        let spec = RequestSpec::get(&url).header("accept", "application/json");
+        let resp = self.execute_request("search_track_uri", &spec).await?;
+        if !resp.status().is_success() {
+            return Err(Self::response_error(resp, "search failed", None).await);
+        }
+        let j: serde_json::Value = resp.json().await?;
+        Ok(j["items"]
+            .as_array()
+            .and_then(|a| a.first())
+            .and_then(|it| it["id"]["videoId"].as_str())
+            .map(|id| format!("{}{}", URI_PREFIX, id)))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_folders: false,
+            supports_isrc_search: false,
+            supports_reorder: false,
+            supports_cover_art: false,
+            supports_positional_add: false,
+            hard_delete: true,
+        }
+    }
+
+    fn validate_uri(&self, uri: &str) -> bool {
+        uri.starts_with(URI_PREFIX) && Self::video_id(uri).is_some()
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::Config;
+use crate::db;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use url::Url;
+
+/// Manual OAuth helper for YouTube Music, following the same paste-the-
+/// redirect-URL flow as the Spotify helper:
+/// 1. Build the Google authorization URL (offline access for a refresh token) and print it.
+/// 2. User approves and copies the full redirect URL back into this CLI.
+/// 3. The `code` param is exchanged for access + refresh tokens.
+/// 4. The tokens are stored in the DB credentials table under provider "ytmusic".
+#[derive(Serialize, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+}
+
+pub async fn run_ytmusic_auth(cfg: &Config) -> Result<()> {
+    use std::io;
+
+    println!("Enter your Google OAuth client_id:");
+    let mut client_id = String::new();
+    io::stdin().read_line(&mut client_id)?;
+    let client_id = client_id.trim().to_string();
+    if client_id.is_empty() {
+        return Err(anyhow!("no client_id provided"));
+    }
+
+    println!("Enter your Google OAuth client_secret:");
+    let mut client_secret = String::new();
+    io::stdin().read_line(&mut client_secret)?;
+    let client_secret = client_secret.trim().to_string();
+    if client_secret.is_empty() {
+        return Err(anyhow!("no client_secret provided"));
+    }
+
+    println!("Enter your redirect URI (leave blank for http://127.0.0.1:8888/):");
+    let mut redirect_uri = String::new();
+    io::stdin().read_line(&mut redirect_uri)?;
+    let redirect_uri = {
+        let trimmed = redirect_uri.trim();
+        if trimmed.is_empty() {
+            "http://127.0.0.1:8888/".to_string()
+        } else {
+            trimmed.to_string()
+        }
+    };
+
+    // `access_type=offline` + `prompt=consent` make Google return a refresh
+    // token even if the app was authorized before.
+    let mut url = Url::parse("https://accounts.google.com/o/oauth2/v2/auth")?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &client_id)
+        .append_pair("scope", "https://www.googleapis.com/auth/youtube")
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("access_type", "offline")
+        .append_pair("prompt", "consent");
+
+    println!(
+        "Open this URL in your browser and authorize the application:\n\n{}\n",
+        url
+    );
+    println!("After authorizing, you'll be redirected to your redirect URI. Copy the full redirect URL and paste it here.");
+    println!("Paste redirect URL:");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    let parsed = Url::parse(input).map_err(|e| anyhow!("invalid url pasted: {}", e))?;
+    let code = parsed
+        .query_pairs()
+        .find(|(k, _)| k == "code")
+        .ok_or_else(|| anyhow!("no code in redirect URL"))?
+        .1
+        .into_owned();
+
+    // Exchange code for tokens
+    let client = Client::new();
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", client_id.as_str()),
+        ("client_secret", client_secret.as_str()),
+    ];
+    let resp = client
+        .post("https://oauth2.googleapis.com/token")
+        .form(&params)
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let txt = resp.text().await.unwrap_or_default();
+        return Err(anyhow!("token exchange failed: {} => {}", status, txt));
+    }
+
+    let tr: TokenResponse = resp.json().await?;
+    let expires_at = chrono::Utc::now().timestamp() + tr.expires_in;
+    let stored_token = crate::api::ytmusic::StoredToken {
+        access_token: tr.access_token,
+        token_type: tr.token_type,
+        expires_at,
+        refresh_token: tr.refresh_token,
+        scope: tr.scope,
+    };
+    let token_json = serde_json::to_string(&stored_token)?;
+    let db_path = cfg.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+        let conn = crate::db::open_tuned(db_path)?;
+        db::save_credential_raw(
+            &conn,
+            "ytmusic",
+            &token_json,
+            Some(&client_id),
+            Some(&client_secret),
+        )?;
+        Ok(())
+    })
+    .await??;
+
+    info!("YouTube Music tokens saved to DB for provider 'ytmusic'");
+    println!(
+        "Saved tokens to DB. You can now run the worker which will use the YouTube Music provider."
+    );
+
+    Ok(())
+}
@@ -25,19 +25,47 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Run the watcher (long-running)
-    Watcher,
+    Watcher {
+        /// Don't start a watcher; make the running one write its pending
+        /// debounced playlists (and enqueue their events) now
+        #[arg(long)]
+        flush_now: bool,
+    },
     /// Run the worker once (one-shot)
     Worker {
         /// Trust the track/playlist cache even if the files have changed on disk.
         /// Useful when running Create events and you know the cache is still valid.
         #[arg(long)]
         trust_cache: bool,
+        /// Resolve and log all planned changes without modifying remote
+        /// playlists or marking events synced.
+        #[arg(long)]
+        dry_run: bool,
+        /// List remote playlists again instead of diffing against the stored
+        /// remote snapshot.
+        #[arg(long)]
+        force_relist: bool,
+        /// Print the run summary as one line of JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Run a full reconciliation scan of the root folder
     Reconcile {
         /// Trust the track/playlist cache even if local files have changed on disk.
         #[arg(long)]
         trust_cache: bool,
+        /// List remote playlists again instead of diffing against the stored
+        /// remote snapshot.
+        #[arg(long)]
+        force_relist: bool,
+        /// Print the summary of the worker run as one line of JSON
+        #[arg(long)]
+        json: bool,
+        /// Only sync folders whose tracks or playlist changed since the last
+        /// reconcile (or that are not synced yet); local playlists are still
+        /// rewritten everywhere.
+        #[arg(long)]
+        changed_only: bool,
     },
     /// Reconcile a single playlist folder, optionally restricted to one provider
     ReconcilePlaylist {
@@ -52,23 +80,138 @@ enum Commands {
         /// Trust the track/playlist cache even if local files have changed on disk.
         #[arg(long)]
         trust_cache: bool,
+        /// List remote playlists again instead of diffing against the stored
+        /// remote snapshot.
+        #[arg(long)]
+        force_relist: bool,
+    },
+    /// Sync one playlist right away: enqueue a Create event for its logical
+    /// key and process only that playlist, ignoring the rest of the queue
+    SyncOne {
+        /// Logical playlist key (folder path relative to root_folder)
+        #[arg(long, value_name = "PLAYLIST")]
+        playlist: String,
+
+        /// Provider to sync to (e.g. "spotify" or "tidal"); omit to sync all configured providers
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+
+        /// Trust the track/playlist cache even if local files have changed on disk.
+        #[arg(long)]
+        trust_cache: bool,
+        /// List remote playlists again instead of diffing against the stored
+        /// remote snapshot.
+        #[arg(long)]
+        force_relist: bool,
+    },
+    /// Show the ranked search candidates for a local file and which one would
+    /// be chosen, without touching any playlist (or, unless --refresh, the
+    /// track cache)
+    ResolveTrack {
+        /// Path to the local media file
+        #[arg(long, value_name = "PATH")]
+        file: PathBuf,
+
+        /// Provider to search (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER", default_value = "spotify")]
+        provider: String,
+
+        /// Maximum number of candidates to fetch per search
+        #[arg(long, default_value_t = lib::api::DEFAULT_SEARCH_CANDIDATES)]
+        limit: usize,
+
+        /// Replace the file's track-cache entry with the new result (e.g.
+        /// to fix a bad cached match)
+        #[arg(long)]
+        refresh: bool,
     },
     /// Validate config file and exit
-    ConfigValidate,
+    ConfigValidate {
+        /// Also print the effective naming settings and the remote playlist
+        /// name each enabled provider would use for some sample keys
+        #[arg(long)]
+        explain: bool,
+
+        /// Sample playlist key for --explain (e.g. "Rock/Album1"); may be
+        /// repeated
+        #[arg(long = "key", value_name = "KEY", requires = "explain")]
+        keys: Vec<String>,
+    },
+    /// Check config, database schema, provider credentials and folder
+    /// permissions; exits non-zero if any check fails
+    Doctor,
     /// Auth helpers
     Auth {
         #[command(subcommand)]
         sub: AuthCommands,
     },
+    /// Show each provider's stored token, its expiry and a validity probe;
+    /// exits non-zero if an enabled provider has no usable credentials
+    AuthStatus,
     /// Auth test helpers
     AuthTest {
         #[command(subcommand)]
         sub: AuthTestCommands,
     },
     /// Show the status of the event queue
-    QueueStatus,
+    QueueStatus {
+        /// Print the events as a JSON array instead of one line each
+        #[arg(long)]
+        json: bool,
+        /// Only show events for this playlist key (e.g. "Rock/Album1")
+        #[arg(long, value_name = "KEY")]
+        playlist: Option<String>,
+        /// Only show events with this action
+        #[arg(long, value_parser = ["add", "remove", "rename", "create", "delete"])]
+        action: Option<String>,
+        /// Show at most this many events (oldest first)
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+    },
     /// Clear all unsynced events from the event queue
     QueueClear,
+    /// List events that failed `max_retries_on_error` times and are no longer retried
+    QueueFailed {
+        /// Reset their attempt counters so the next worker run retries them
+        #[arg(long)]
+        requeue: bool,
+    },
+    /// Delete synced events older than the retention period
+    QueuePrune {
+        /// Retention in seconds; defaults to `synced_event_retention_secs`
+        #[arg(long)]
+        older_than_secs: Option<u64>,
+    },
+    /// Write the whole event queue (synced history included) to a JSON file
+    QueueExport {
+        #[arg(long, value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Load events from a `queue-export` JSON file, keeping their ids and sync state
+    QueueImport {
+        #[arg(long, value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// List playlist_map rows (local playlist -> remote playlist id)
+    ListMappings {
+        /// Only show mappings for this provider (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+        /// Print the rows as a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Forget the remote snapshots, cached remote contents and track matches
+    /// (keeping ISRCs, overrides and credentials) and queue every mapped
+    /// playlist for a full reconcile
+    ResyncAll {
+        /// Only clear the state of this provider (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
     /// Delete remote playlists for a provider whose names match a regex
     DeletePlaylists {
         /// Provider to operate on (e.g. "spotify" or "tidal")
@@ -86,6 +229,14 @@ enum Commands {
         /// Only delete playlists that are NOT referenced in the local playlist_map DB
         #[arg(long)]
         orphan_only: bool,
+
+        /// Do not ask for confirmation (unless more than 50 playlists match)
+        #[arg(long)]
+        yes: bool,
+
+        /// Abort without deleting anything if more than this many playlists match
+        #[arg(long, value_name = "N", default_value_t = DELETE_PLAYLISTS_SANITY_LIMIT)]
+        max: usize,
     },
     /// Troubleshooting helpers
     Troubleshoot {
@@ -97,6 +248,83 @@ enum Commands {
         #[command(subcommand)]
         sub: DbCommands,
     },
+    /// Track cache maintenance helpers
+    Cache {
+        #[command(subcommand)]
+        sub: CacheCommands,
+    },
+    /// Pin local files to a remote track, bypassing cache, ISRC and search
+    Override {
+        #[command(subcommand)]
+        sub: OverrideCommands,
+    },
+    /// Adopt existing remote playlists: map local folders to them and seed
+    /// the track cache from their tracks so the first sync skips the search
+    Import {
+        /// Provider the remote playlists belong to (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: String,
+        /// Playlist folder (absolute or relative to root_folder) and remote
+        /// playlist id, e.g. "Artist/Album=37i9dQZF1DXcBWIGoYBM5M"; repeatable
+        #[arg(long = "map", value_name = "FOLDER=PLAYLIST_ID", required = true)]
+        mappings: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum OverrideCommands {
+    /// Pin a local file to a remote track URI on one provider
+    Set {
+        /// Provider the URI belongs to (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: String,
+        /// Path to the local media file
+        #[arg(long, value_name = "PATH")]
+        file: PathBuf,
+        /// Remote track URI, e.g. "spotify:track:4uLU6hMCjMI75M1A2tKUQC"
+        #[arg(long, value_name = "URI")]
+        uri: String,
+    },
+    /// Remove the override of a local file so it is resolved normally again
+    Remove {
+        /// Provider the override belongs to
+        #[arg(long, value_name = "PROVIDER")]
+        provider: String,
+        /// Path to the local media file
+        #[arg(long, value_name = "PATH")]
+        file: PathBuf,
+    },
+    /// List all overrides (optionally filtered by provider)
+    List {
+        /// Filter to a single provider (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Delete negative (unresolved) track cache entries so they are retried
+    /// on the next worker run
+    Prune {
+        /// Only prune entries for this provider (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+    },
+    /// Show track cache resolution counts per provider and the number of
+    /// mapped playlists
+    Stats {
+        /// Print the counts as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that the remote tracks of resolved entries still exist and
+    /// re-resolve (or remove) those that are gone
+    Verify {
+        /// Provider whose entries to check (e.g. "spotify" or "tidal")
+        #[arg(long, value_name = "PROVIDER")]
+        provider: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -158,9 +386,35 @@ enum TroubleshootFileCommands {
 #[derive(Subcommand)]
 enum AuthCommands {
     /// Authorize Spotify and store tokens in DB (interactive)
-    Spotify,
+    Spotify {
+        /// Don't open a browser or listen for the redirect; paste the redirect URL instead
+        #[arg(long)]
+        no_browser: bool,
+        /// Use the PKCE flow, which does not need a client_secret
+        #[arg(long)]
+        pkce: bool,
+    },
     /// Authorize Tidal and store tokens in DB (interactive)
-    Tidal,
+    Tidal {
+        /// Don't open a browser or listen for the redirect; authorize with a device code instead
+        #[arg(long)]
+        no_browser: bool,
+    },
+    /// Store Subsonic/Navidrome server credentials in DB (interactive)
+    Subsonic,
+    /// Authorize YouTube Music and store tokens in DB (interactive)
+    Ytmusic,
+    /// Authorize Deezer and store the token in DB (interactive)
+    Deezer,
+    /// Sign an Apple Music developer token and store it with a Music-User-Token in DB (interactive)
+    Applemusic,
+    /// Store a Plex server URL and X-Plex-Token in DB (interactive)
+    Plex,
+    /// Force a refresh of the stored token of a provider and store the result
+    Refresh {
+        /// Provider whose token to refresh, e.g. "spotify" or "tidal"
+        provider: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -171,6 +425,97 @@ enum AuthTestCommands {
     Tidal,
 }
 
+/// Ask `question` on the terminal; true only for an answer starting with
+/// "y".  Callers skip this when `--yes` is given.
+fn confirm(question: &str) -> bool {
+    confirm_from(&mut std::io::stdin().lock(), question)
+}
+
+/// [`confirm`], reading the answer from `input`.
+fn confirm_from(input: &mut impl std::io::BufRead, question: &str) -> bool {
+    use std::io::Write;
+    print!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    input.read_line(&mut answer).is_ok()
+        && answer.trim_start().to_ascii_lowercase().starts_with('y')
+}
+
+/// Print what a worker run did, as one line of JSON with `json` (logs may
+/// share stdout, so the summary stays on its own line).
+fn print_run_summary(
+    summary: &lib::worker::WorkerRunSummary,
+    dry_run: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(summary)?);
+        return Ok(());
+    }
+    println!(
+        "{}{} playlist(s) processed: {} track(s) added, {} removed, {} rename(s), {} delete(s), {} unresolved.",
+        if dry_run { "Dry run: " } else { "" },
+        summary.playlists_processed,
+        summary.tracks_added,
+        summary.tracks_removed,
+        summary.renames,
+        summary.deletes,
+        summary.unresolved
+    );
+    if !summary.errors.is_empty() {
+        let errors: Vec<String> = summary
+            .errors
+            .iter()
+            .map(|(provider, n)| format!("{} {}", provider, n))
+            .collect();
+        println!("Failed playlists: {}", errors.join(", "));
+    }
+    Ok(())
+}
+
+/// Number of matches above which `delete-playlists` asks for confirmation
+/// even with `--yes`; also the default `--max`.
+const DELETE_PLAYLISTS_SANITY_LIMIT: usize = 50;
+
+/// Safety interlock before `delete-playlists` deletes `count` playlists:
+/// more than `max` is an error, otherwise the user is asked unless `--yes`
+/// is given and the count is within [`DELETE_PLAYLISTS_SANITY_LIMIT`].
+/// The answer is read from `input`.  Returns whether to go ahead.
+fn confirm_playlist_deletion(
+    count: usize,
+    provider: &str,
+    yes: bool,
+    max: usize,
+    input: &mut impl std::io::BufRead,
+) -> anyhow::Result<bool> {
+    if count > max {
+        anyhow::bail!(
+            "{} {} playlists matched, more than --max {}; narrow --name-regex or raise --max",
+            count,
+            provider,
+            max
+        );
+    }
+    let question = format!("Delete {} {} playlist(s)?", count, provider);
+    if count > DELETE_PLAYLISTS_SANITY_LIMIT {
+        println!(
+            "That is more than {} playlists; confirm to continue.",
+            DELETE_PLAYLISTS_SANITY_LIMIT
+        );
+        return Ok(confirm_from(input, &question));
+    }
+    Ok(yes || confirm_from(input, &question))
+}
+
+/// `--force-relist`: a config copy that never uses the stored remote snapshot.
+fn relist_config(cfg: &Config, force_relist: bool) -> Config {
+    let mut cfg = cfg.clone();
+    if force_relist {
+        cfg.remote_snapshot_max_age_secs = 0;
+    }
+    cfg
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -189,8 +534,16 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Runs before the config is loaded so a broken config is reported as a
+    // failed check instead of aborting.
+    if let Commands::Doctor = cli.command {
+        let ok = troubleshoot::doctor(&resolved_config_path).await;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
     let cfg = Config::from_path(&resolved_config_path)
         .with_context(|| format!("loading config from {}", resolved_config_path.display()))?;
+    lib::secrets::configure(&cfg)?;
 
     // Initialize log->tracing bridge and structured logging.  We try to
     // create the directory specified in the config so that the rolling file
@@ -203,14 +556,14 @@ async fn main() -> Result<()> {
     // layer that writes to `/dev/null`. this keeps the subscriber type
     // consistent regardless of whether we could successfully create the
     // rolling file appender.
-    // compute a file logging layer, falling back to a plain stdout-based
+    // compute a file log writer, falling back to a plain stdout-based
     // NonBlocking writer when the rolling appender cannot be created.  we call
     // `non_blocking(std::io::stdout())` each time so that the associated
     // `WorkerGuard` is owned and dropped appropriately.
     // if we have a writable log directory we will create the rolling appender
     // normally; if we can't open the file ourselves we skip calling `daily` to
     // avoid the internal panic that was observed earlier.
-    let file_layer = if let Ok(()) = std::fs::create_dir_all(&cfg.log_dir) {
+    let file_writer = if let Ok(()) = std::fs::create_dir_all(&cfg.log_dir) {
         let probe = cfg.log_dir.join("music-sync.log");
         match std::fs::OpenOptions::new()
             .create(true)
@@ -222,7 +575,7 @@ async fn main() -> Result<()> {
                 // ask the appender to manage it as well.
                 let app = tracing_appender::rolling::daily(&cfg.log_dir, "music-sync.log");
                 let (non_blocking, _guard) = tracing_appender::non_blocking(app);
-                fmt::layer().with_writer(non_blocking)
+                non_blocking
             }
             Err(e) => {
                 eprintln!(
@@ -231,7 +584,7 @@ async fn main() -> Result<()> {
                     e
                 );
                 let (nb, _guard) = tracing_appender::non_blocking(std::io::stdout());
-                fmt::layer().with_writer(nb)
+                nb
             }
         }
     } else {
@@ -244,18 +597,21 @@ async fn main() -> Result<()> {
             )
         );
         let (nb, _guard) = tracing_appender::non_blocking(std::io::stdout());
-        fmt::layer().with_writer(nb)
+        nb
     };
 
-    // Honor RUST_LOG if set, otherwise default to info.
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-
-    let stdout_layer = fmt::layer().with_writer(std::io::stdout);
+    // Honor RUST_LOG if set, otherwise use the configured level.
+    let env_filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(cfg.log_level.to_ascii_lowercase()));
 
+    // Both outputs use `log_format`; exactly one of each pair is set.
+    let json = cfg.log_format == "json";
     let subscriber = tracing_subscriber::registry()
         .with(env_filter)
-        .with(stdout_layer)
-        .with(file_layer);
+        .with(json.then(|| fmt::layer().json().with_writer(std::io::stdout)))
+        .with((!json).then(|| fmt::layer().with_writer(std::io::stdout)))
+        .with(json.then(|| fmt::layer().json().with_writer(file_writer.clone())))
+        .with((!json).then(|| fmt::layer().with_writer(file_writer)));
 
     // Install as global default tracing subscriber without triggering
     // tracing-subscriber’s internal log bridge (we already call LogTracer).
@@ -263,33 +619,74 @@ async fn main() -> Result<()> {
         .expect("failed to set global tracing subscriber");
 
     match cli.command {
-        Commands::Watcher => {
-            lib::watcher::run_watcher(&cfg).with_context(|| "running watcher".to_string())?;
+        Commands::Watcher { flush_now: true } => {
+            let pid = lib::watcher::request_flush(&cfg)?;
+            println!(
+                "Asked watcher (pid {}) to flush pending playlist writes",
+                pid
+            );
         }
-        Commands::Worker { trust_cache } => {
-            lib::worker::run_worker_once(&cfg, None, trust_cache)
+        Commands::Watcher { flush_now: false } => {
+            lib::watcher::run_watcher(&cfg, &resolved_config_path)
+                .with_context(|| "running watcher".to_string())?;
+        }
+        Commands::Worker {
+            trust_cache,
+            dry_run,
+            force_relist,
+            json,
+        } => {
+            let cfg = relist_config(&cfg, force_relist);
+            let summary = lib::worker::run_worker_once(&cfg, None, trust_cache, dry_run)
                 .await
                 .with_context(|| "running worker".to_string())?;
+            print_run_summary(&summary, dry_run, json)?;
         }
-        Commands::Reconcile { trust_cache } => {
+        Commands::Reconcile {
+            trust_cache,
+            force_relist,
+            changed_only,
+            json,
+        } => {
+            let cfg = relist_config(&cfg, force_relist);
             // 1. Purge any DB-tracked playlists whose local folder is gone.
             lib::worker::purge_deleted_playlists(&cfg)
                 .await
                 .with_context(|| "purge deleted playlists failed".to_string())?;
             // 2. Scan the folder tree, write local .m3u files, and enqueue Create
-            //    events for every playlist.
-            lib::worker::run_nightly_reconcile(&cfg)
+            //    events for every (changed) playlist.
+            lib::worker::run_reconcile_scan(&cfg, changed_only)
                 .with_context(|| "reconcile scan failed".to_string())?;
             // 3. Drain the event queue so the remote is synced in the same run.
-            lib::worker::run_worker_once(&cfg, None, trust_cache)
+            let summary = lib::worker::run_worker_once(&cfg, None, trust_cache, false)
                 .await
                 .with_context(|| "worker run after reconcile failed".to_string())?;
+            print_run_summary(&summary, false, json)?;
+        }
+        Commands::SyncOne {
+            playlist,
+            provider,
+            trust_cache,
+            force_relist,
+        } => {
+            let cfg = relist_config(&cfg, force_relist);
+            lib::worker::sync_one_playlist(&cfg, &playlist, provider.as_deref(), trust_cache)
+                .await
+                .with_context(|| {
+                    format!(
+                        "sync-one {:?} provider={}",
+                        playlist,
+                        provider.as_deref().unwrap_or("<all>")
+                    )
+                })?;
         }
         Commands::ReconcilePlaylist {
             path,
             provider,
             trust_cache,
+            force_relist,
         } => {
+            let cfg = relist_config(&cfg, force_relist);
             lib::worker::reconcile_single_playlist(&cfg, &path, provider.as_deref(), trust_cache)
                 .await
                 .with_context(|| {
@@ -300,9 +697,23 @@ async fn main() -> Result<()> {
                     )
                 })?;
         }
-        Commands::ConfigValidate => {
+        Commands::Doctor => unreachable!("handled before the config is loaded"),
+        Commands::ConfigValidate { explain, keys } => {
             match lib::config::Config::from_path(&resolved_config_path.as_path()) {
-                Ok(_) => println!("OK"),
+                Ok(validated) => {
+                    println!("OK");
+                    if explain {
+                        let keys = if keys.is_empty() {
+                            troubleshoot::EXPLAIN_SAMPLE_KEYS
+                                .iter()
+                                .map(|k| k.to_string())
+                                .collect()
+                        } else {
+                            keys
+                        };
+                        print!("\n{}", troubleshoot::explain_config(&validated, &keys)?);
+                    }
+                }
                 Err(e) => {
                     eprintln!("Config validation failed: {}", e);
                     std::process::exit(2);
@@ -310,13 +721,41 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Auth { sub } => match sub {
-            AuthCommands::Spotify => {
-                lib::api::spotify_auth::run_spotify_auth(&cfg).await?;
+            AuthCommands::Spotify { no_browser, pkce } => {
+                lib::api::spotify_auth::run_spotify_auth(&cfg, no_browser, pkce).await?;
+            }
+            AuthCommands::Tidal { no_browser } => {
+                lib::api::tidal_auth::run_tidal_auth(&cfg, no_browser).await?;
+            }
+            AuthCommands::Subsonic => {
+                lib::api::subsonic_auth::run_subsonic_auth(&cfg).await?;
             }
-            AuthCommands::Tidal => {
-                lib::api::tidal_auth::run_tidal_auth(&cfg).await?;
+            AuthCommands::Ytmusic => {
+                lib::api::ytmusic_auth::run_ytmusic_auth(&cfg).await?;
+            }
+            AuthCommands::Deezer => {
+                lib::api::deezer_auth::run_deezer_auth(&cfg).await?;
+            }
+            AuthCommands::Plex => {
+                lib::api::plex_auth::run_plex_auth(&cfg).await?;
+            }
+            AuthCommands::Applemusic => {
+                lib::api::apple_music_auth::run_apple_music_auth(&cfg).await?;
+            }
+            AuthCommands::Refresh { provider } => {
+                println!(
+                    "{}",
+                    troubleshoot::refresh_credentials(&cfg, &provider).await?
+                );
             }
         },
+        Commands::AuthStatus => {
+            let (report, usable) = troubleshoot::auth_status(&cfg).await?;
+            print!("{}", report);
+            if !usable {
+                std::process::exit(1);
+            }
+        }
         Commands::AuthTest { sub } => {
             use futures::future::BoxFuture;
             use std::sync::Arc;
@@ -366,7 +805,7 @@ async fn main() -> Result<()> {
                 }
 
                 // Scan mapping DB for all mapped Test MFPOS N playlists, find highest N
-                let (mapped_name, mapped_id, mapped_num) = rusqlite::Connection::open(&db_path)
+                let (mapped_name, mapped_id, mapped_num) = lib::db::open_tuned(&db_path)
                     .ok()
                     .and_then(|conn| {
                         let mut highest_n = 0;
@@ -403,7 +842,7 @@ async fn main() -> Result<()> {
                     println!("Renaming playlist '{}' to '{}'", mapped_name, new_name);
                     match rename_playlist(mapped_id.clone(), new_name.clone()).await {
                         Ok(()) => {
-                            if let Ok(conn) = rusqlite::Connection::open(&db_path) {
+                            if let Ok(conn) = lib::db::open_tuned(&db_path) {
                                 // Migrate the existing mapping from the old logical
                                 // name to the new one so the old key is removed and
                                 // future runs find the correct highest index.
@@ -431,7 +870,7 @@ async fn main() -> Result<()> {
                 println!("Creating playlist: {}", test_name);
                 let pid = ensure_playlist(test_name.clone(), desc).await?;
                 println!("Created playlist with id: {}", pid);
-                if let Ok(conn) = rusqlite::Connection::open(&db_path) {
+                if let Ok(conn) = lib::db::open_tuned(&db_path) {
                     let _ = music_file_playlist_online_sync::db::upsert_playlist_map(
                         &conn,
                         provider_name,
@@ -463,7 +902,7 @@ async fn main() -> Result<()> {
                         let spotify = spotify.clone();
                         Box::new(move |n: String, d: String| {
                             let spotify = spotify.clone();
-                            Box::pin(async move { spotify.ensure_playlist(&n, &d).await })
+                            Box::pin(async move { Ok(spotify.ensure_playlist(&n, &d).await?) })
                                 as BoxFuture<'static, anyhow::Result<String>>
                         })
                     };
@@ -471,7 +910,7 @@ async fn main() -> Result<()> {
                         let spotify = spotify.clone();
                         Box::new(move |id: String, n: String| {
                             let spotify = spotify.clone();
-                            Box::pin(async move { spotify.rename_playlist(&id, &n).await })
+                            Box::pin(async move { Ok(spotify.rename_playlist(&id, &n).await?) })
                                 as BoxFuture<'static, anyhow::Result<()>>
                         })
                     };
@@ -519,7 +958,7 @@ async fn main() -> Result<()> {
                         let tidal = tidal.clone();
                         Box::new(move |n: String, d: String| {
                             let tidal = tidal.clone();
-                            Box::pin(async move { tidal.ensure_playlist(&n, &d).await })
+                            Box::pin(async move { Ok(tidal.ensure_playlist(&n, &d).await?) })
                                 as BoxFuture<'static, anyhow::Result<String>>
                         })
                     };
@@ -527,7 +966,7 @@ async fn main() -> Result<()> {
                         let tidal = tidal.clone();
                         Box::new(move |id: String, n: String| {
                             let tidal = tidal.clone();
-                            Box::pin(async move { tidal.rename_playlist(&id, &n).await })
+                            Box::pin(async move { Ok(tidal.rename_playlist(&id, &n).await?) })
                                 as BoxFuture<'static, anyhow::Result<()>>
                         })
                     };
@@ -546,11 +985,31 @@ async fn main() -> Result<()> {
                 }
             },
         },
-        Commands::QueueStatus => {
+        Commands::ResolveTrack {
+            file,
+            provider,
+            limit,
+            refresh,
+        } => {
+            troubleshoot::resolve_track(&cfg, &provider, &file, limit, refresh).await?;
+        }
+        Commands::QueueStatus {
+            json,
+            playlist,
+            action,
+            limit,
+        } => {
             let db_path = cfg.db_path.clone();
-            match rusqlite::Connection::open(&db_path) {
-                Ok(conn) => match music_file_playlist_online_sync::db::fetch_unsynced_events(&conn)
-                {
+            match lib::db::open_tuned(&db_path) {
+                Ok(conn) => match music_file_playlist_online_sync::db::fetch_events_filtered(
+                    &conn,
+                    playlist.as_deref(),
+                    action.as_deref(),
+                    limit,
+                ) {
+                    Ok(events) if json => {
+                        println!("{}", serde_json::to_string_pretty(&events)?);
+                    }
                     Ok(events) => {
                         for event in &events {
                             println!(
@@ -564,7 +1023,24 @@ async fn main() -> Result<()> {
                                 event.timestamp_ms
                             );
                         }
-                        println!("Queue contains {} unsynced event(s).", events.len());
+                        if playlist.is_some() || action.is_some() || limit.is_some() {
+                            let total =
+                                music_file_playlist_online_sync::db::count_pending_events(&conn)
+                                    .unwrap_or_default();
+                            println!("Showing {} of {} unsynced event(s).", events.len(), total);
+                        } else {
+                            println!("Queue contains {} unsynced event(s).", events.len());
+                        }
+                        if let Ok(failed) =
+                            music_file_playlist_online_sync::db::fetch_failed_events(&conn)
+                        {
+                            if !failed.is_empty() {
+                                println!(
+                                    "{} failed event(s) are no longer retried; see `queue-failed`.",
+                                    failed.len()
+                                );
+                            }
+                        }
                     }
                     Err(e) => {
                         eprintln!("Failed to fetch queue events: {}", e);
@@ -579,7 +1055,7 @@ async fn main() -> Result<()> {
         }
         Commands::QueueClear => {
             let db_path = cfg.db_path.clone();
-            match rusqlite::Connection::open(&db_path) {
+            match lib::db::open_tuned(&db_path) {
                 Ok(mut conn) => {
                     match music_file_playlist_online_sync::db::clear_unsynced_events(&mut conn) {
                         Ok(removed) => {
@@ -597,11 +1073,173 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::QueueFailed { requeue } => {
+            let conn = match lib::db::open_or_create(&cfg.db_path) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("Failed to open DB: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if requeue {
+                match music_file_playlist_online_sync::db::requeue_failed_events(&conn) {
+                    Ok(n) => println!("Requeued {} failed event(s).", n),
+                    Err(e) => {
+                        eprintln!("Failed to requeue events: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                match music_file_playlist_online_sync::db::fetch_failed_events(&conn) {
+                    Ok(failed) => {
+                        for f in &failed {
+                            println!(
+                                "- id: {} | playlist: {} | action: {:?} | track: {:?} | attempts: {} | failed_at: {} | error: {}",
+                                f.event.id,
+                                f.event.playlist_name,
+                                f.event.action,
+                                f.event.track_path,
+                                f.attempts,
+                                f.failed_at,
+                                f.last_error.as_deref().unwrap_or("")
+                            );
+                        }
+                        println!("{} failed event(s).", failed.len());
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to fetch failed events: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Commands::QueuePrune { older_than_secs } => {
+            let retention = older_than_secs.unwrap_or(cfg.synced_event_retention_secs);
+            let pruned = lib::db::open_or_create(&cfg.db_path).and_then(|conn| {
+                music_file_playlist_online_sync::db::prune_synced_events(&conn, retention)
+            });
+            match pruned {
+                Ok(n) => println!("Pruned {} synced event(s) older than {}s.", n, retention),
+                Err(e) => {
+                    eprintln!("Failed to prune queue events: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::QueueExport { file } => {
+            let exported = lib::db::open_or_create(&cfg.db_path)
+                .and_then(|conn| lib::db::export_events(&conn))
+                .and_then(|events| {
+                    std::fs::write(&file, serde_json::to_string_pretty(&events)?)?;
+                    Ok(events.len())
+                });
+            match exported {
+                Ok(n) => println!("Exported {} event(s) to {}.", n, file.display()),
+                Err(e) => {
+                    eprintln!("Failed to export queue: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::QueueImport { file } => {
+            let imported = std::fs::read_to_string(&file)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| Ok(serde_json::from_str::<Vec<lib::models::Event>>(&s)?))
+                .and_then(|events| {
+                    let mut conn = lib::db::open_or_create(&cfg.db_path)?;
+                    lib::db::import_events(&mut conn, &events)
+                });
+            match imported {
+                Ok(n) => println!("Imported {} event(s) from {}.", n, file.display()),
+                Err(e) => {
+                    eprintln!("Failed to import queue: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::ListMappings { provider, json } => {
+            let rows = lib::db::open_tuned(&cfg.db_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|conn| {
+                    music_file_playlist_online_sync::db::list_playlist_maps(
+                        &conn,
+                        provider.as_deref(),
+                    )
+                });
+            match rows {
+                Ok(rows) if json => {
+                    let out: Vec<serde_json::Value> = rows
+                        .iter()
+                        .map(|(prov, pl, rid, synced)| {
+                            serde_json::json!({
+                                "provider": prov,
+                                "playlist_name": pl,
+                                "remote_id": rid,
+                                "last_synced_at": synced,
+                            })
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                }
+                Ok(rows) => {
+                    println!(
+                        "{:<10} {:<45} {:<40} LAST SYNCED",
+                        "PROVIDER", "PLAYLIST", "REMOTE ID"
+                    );
+                    println!("{}", "-".repeat(110));
+                    for (prov, pl, rid, synced) in &rows {
+                        let synced = synced
+                            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                            .map(|dt| dt.to_rfc3339())
+                            .unwrap_or_else(|| "never".into());
+                        println!(
+                            "{:<10} {:<45} {:<40} {}",
+                            prov,
+                            pl,
+                            rid.as_deref().unwrap_or("(none)"),
+                            synced
+                        );
+                    }
+                    println!("({} row(s))", rows.len());
+                }
+                Err(e) => {
+                    eprintln!("Failed to list playlist mappings: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::ResyncAll { provider, yes } => {
+            let scope = provider
+                .as_deref()
+                .map(|p| format!("{} ", p))
+                .unwrap_or_default();
+            if !yes
+                && !confirm(&format!(
+                    "Clear all cached {}remote state and re-resolve every track on the next run?",
+                    scope
+                ))
+            {
+                println!("Aborted.");
+                return Ok(());
+            }
+            match lib::worker::resync_all(&cfg, provider.as_deref()) {
+                Ok((reset, queued)) => println!(
+                    "Cleared {} remote snapshot(s) and {} track match(es); queued {} playlist(s) for a full reconcile.",
+                    reset.snapshots, reset.track_matches, queued
+                ),
+                Err(e) => {
+                    eprintln!("resync-all failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
         Commands::DeletePlaylists {
             provider,
             name_regex,
             dry_run,
             orphan_only,
+            yes,
+            max,
         } => {
             use regex::Regex;
             use std::collections::HashSet;
@@ -620,7 +1258,7 @@ async fn main() -> Result<()> {
             // When --orphan-only is set, load all remote_ids tracked locally
             // so we can exclude them from deletion.
             let tracked_ids: HashSet<String> = if orphan_only {
-                match rusqlite::Connection::open(&cfg.db_path) {
+                match lib::db::open_tuned(&cfg.db_path) {
                     Ok(conn) => {
                         match music_file_playlist_online_sync::db::get_all_remote_ids_for_provider(
                             &conn,
@@ -705,6 +1343,16 @@ async fn main() -> Result<()> {
                         println!("Dry run: no playlists were deleted.");
                         return Ok(());
                     }
+                    if !confirm_playlist_deletion(
+                        matches.len(),
+                        "Spotify",
+                        yes,
+                        max,
+                        &mut std::io::stdin().lock(),
+                    )? {
+                        println!("Aborted.");
+                        return Ok(());
+                    }
 
                     let mut failed = 0usize;
                     for (id, name) in matches.drain(..) {
@@ -793,6 +1441,16 @@ async fn main() -> Result<()> {
                         println!("Dry run: no playlists were deleted.");
                         return Ok(());
                     }
+                    if !confirm_playlist_deletion(
+                        matches.len(),
+                        "Tidal",
+                        yes,
+                        max,
+                        &mut std::io::stdin().lock(),
+                    )? {
+                        println!("Aborted.");
+                        return Ok(());
+                    }
 
                     let mut failed = 0usize;
                     for (id, name) in matches.drain(..) {
@@ -825,7 +1483,7 @@ async fn main() -> Result<()> {
         Commands::Db { sub } => match sub {
             DbCommands::PlaylistMap { sub } => match sub {
                 DbPlaylistMapCommands::List { provider } => {
-                    match rusqlite::Connection::open(&cfg.db_path) {
+                    match lib::db::open_tuned(&cfg.db_path) {
                         Ok(conn) => {
                             match music_file_playlist_online_sync::db::list_playlist_map_entries(
                                 &conn,
@@ -865,7 +1523,7 @@ async fn main() -> Result<()> {
                     }
                 }
                 DbPlaylistMapCommands::Remove { provider, playlist } => {
-                    match rusqlite::Connection::open(&cfg.db_path) {
+                    match lib::db::open_tuned(&cfg.db_path) {
                         Ok(conn) => {
                             match music_file_playlist_online_sync::db::delete_playlist_map(
                                 &conn, &provider, &playlist,
@@ -890,7 +1548,192 @@ async fn main() -> Result<()> {
                 }
             },
         },
+        Commands::Cache { sub } => match sub {
+            CacheCommands::Prune { provider } => {
+                let pruned = lib::db::open_tuned(&cfg.db_path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|conn| {
+                        music_file_playlist_online_sync::db::run_migrations(&conn)?;
+                        music_file_playlist_online_sync::db::prune_unresolved_track_cache(
+                            &conn,
+                            provider.as_deref(),
+                        )
+                    });
+                match pruned {
+                    Ok(n) => println!("Pruned {} unresolved track cache entries.", n),
+                    Err(e) => {
+                        eprintln!("Failed to prune track cache: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            CacheCommands::Stats { json } => {
+                let conn = lib::db::open_or_create(&cfg.db_path)?;
+                let providers = lib::db::track_cache_stats(&conn)?;
+                let playlists = lib::db::count_mapped_playlists(&conn)?;
+                let mut total = lib::db::TrackCacheStats {
+                    provider: "total".into(),
+                    ..Default::default()
+                };
+                for p in &providers {
+                    total.total += p.total;
+                    total.resolved += p.resolved;
+                    total.isrc_only += p.isrc_only;
+                    total.unresolved += p.unresolved;
+                }
+                if json {
+                    let out = serde_json::json!({
+                        "track_cache": {
+                            "total": total.total,
+                            "resolved": total.resolved,
+                            "isrc_only": total.isrc_only,
+                            "unresolved": total.unresolved,
+                        },
+                        "providers": providers,
+                        "mapped_playlists": playlists,
+                    });
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                } else {
+                    println!(
+                        "{:<10} {:>10} {:>10} {:>10} {:>10}",
+                        "PROVIDER", "TOTAL", "RESOLVED", "ISRC ONLY", "UNRESOLVED"
+                    );
+                    println!("{}", "-".repeat(54));
+                    for p in providers.iter().chain(std::iter::once(&total)) {
+                        println!(
+                            "{:<10} {:>10} {:>10} {:>10} {:>10}",
+                            p.provider, p.total, p.resolved, p.isrc_only, p.unresolved
+                        );
+                    }
+                    println!("\nMapped playlists: {}", playlists);
+                }
+            }
+            CacheCommands::Verify { provider } => {
+                let prov = troubleshoot::build_provider(&cfg, &provider)?;
+                let summary = lib::worker::verify_track_cache(&cfg, prov).await?;
+                println!(
+                    "Checked {} cached {} tracks: {} exist, {} could not be checked, {} repaired, {} removed.",
+                    summary.checked,
+                    provider,
+                    summary.alive,
+                    summary.unknown,
+                    summary.repaired,
+                    summary.removed
+                );
+            }
+        },
+        Commands::Import { provider, mappings } => {
+            let prov = troubleshoot::build_provider(&cfg, &provider)?;
+            for mapping in &mappings {
+                let (folder, remote_id) = mapping
+                    .rsplit_once('=')
+                    .filter(|(f, id)| !f.trim().is_empty() && !id.trim().is_empty())
+                    .with_context(|| {
+                        format!("invalid --map {:?}; expected FOLDER=PLAYLIST_ID", mapping)
+                    })?;
+                let summary = lib::worker::import_playlist(
+                    &cfg,
+                    prov.clone(),
+                    Path::new(folder.trim()),
+                    remote_id.trim(),
+                )
+                .await?;
+                println!(
+                    "Imported \"{}\" -> {} on {}: {} matched by ISRC, {} by position, {} local and {} remote unmatched.",
+                    summary.playlist_name,
+                    remote_id.trim(),
+                    provider,
+                    summary.matched_by_isrc,
+                    summary.matched_by_order,
+                    summary.unmatched_local,
+                    summary.unmatched_remote
+                );
+            }
+        }
+        Commands::Override { sub } => {
+            let conn = lib::db::open_or_create(&cfg.db_path)?;
+            match sub {
+                OverrideCommands::Set {
+                    provider,
+                    file,
+                    uri,
+                } => {
+                    if !file.is_file() {
+                        anyhow::bail!("file does not exist: {}", file.display());
+                    }
+                    let uri = uri.trim();
+                    if uri.is_empty() {
+                        anyhow::bail!("--uri must not be empty");
+                    }
+                    // The worker keys tracks by absolute path.
+                    let local = std::path::absolute(&file)?.display().to_string();
+                    lib::db::set_override(&conn, &provider, &local, uri)?;
+                    println!("Pinned {} -> {} on {}.", local, uri, provider);
+                }
+                OverrideCommands::Remove { provider, file } => {
+                    let local = std::path::absolute(&file)?.display().to_string();
+                    if lib::db::remove_override(&conn, &provider, &local)? {
+                        println!("Removed override for {} on {}.", local, provider);
+                    } else {
+                        println!("No override for {} on {}.", local, provider);
+                    }
+                }
+                OverrideCommands::List { provider } => {
+                    let rows = lib::db::list_overrides(&conn, provider.as_deref())?;
+                    if rows.is_empty() {
+                        println!("No overrides.");
+                    }
+                    for (provider, local, uri) in rows {
+                        println!("{}\t{}\t{}", provider, local, uri);
+                    }
+                }
+            }
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delete_playlists_bails_above_max() {
+        let err = confirm_playlist_deletion(11, "Spotify", true, 10, &mut "y\n".as_bytes())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--max 10"), "{}", err);
+    }
+
+    #[test]
+    fn delete_playlists_yes_skips_the_prompt() {
+        // No answer on the input would mean "no".
+        assert!(confirm_playlist_deletion(3, "Tidal", true, 50, &mut "".as_bytes()).unwrap());
+        assert!(!confirm_playlist_deletion(3, "Tidal", false, 50, &mut "".as_bytes()).unwrap());
+        assert!(confirm_playlist_deletion(3, "Tidal", false, 50, &mut "y\n".as_bytes()).unwrap());
+        // Past the sanity limit --yes is not enough.
+        assert!(!confirm_playlist_deletion(60, "Tidal", true, 100, &mut "".as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn delete_playlists_max_defaults_to_the_sanity_limit() {
+        let cli = Cli::try_parse_from([
+            "cli",
+            "delete-playlists",
+            "--provider",
+            "spotify",
+            "--name-regex",
+            ".*",
+        ])
+        .unwrap();
+        let Commands::DeletePlaylists { max, .. } = cli.command else {
+            panic!("expected delete-playlists");
+        };
+        assert_eq!(max, DELETE_PLAYLISTS_SANITY_LIMIT);
+        assert_eq!(max, 50);
+        assert!(
+            confirm_playlist_deletion(51, "Spotify", true, max, &mut "y\n".as_bytes()).is_err()
+        );
+    }
+}
@@ -8,6 +8,10 @@ use std::collections::HashMap;
 /// Algorithm:
 /// - Track an insertion-ordered map of track_path -> (index, action).
 /// - Cancel Add followed by Remove (or vice-versa) for the same track_path.
+/// - A Rename rewrites pending track paths under `/{from}/` to `/{to}/`, so an
+///   op queued before the rename still cancels against one queued after it.
+/// - A Delete drops every pending track op, and track ops after it are
+///   ignored until a Create re-opens the playlist.
 /// - Preserve temporal ordering of surviving track ops by sorting on insertion index.
 /// - Create/Delete/Rename events are preserved in their original order and placed before track ops.
 pub fn collapse_events(events: &[Event]) -> Vec<Event> {
@@ -18,9 +22,11 @@ pub fn collapse_events(events: &[Event]) -> Vec<Event> {
     let mut track_state: HashMap<String, (usize, EventAction)> = HashMap::new();
     let mut next_index: usize = 0;
     let mut other_ops: Vec<Event> = Vec::new(); // holds create/delete/rename events to be preserved in order
+    let mut deleted = false;
 
     for ev in events {
         match &ev.action {
+            EventAction::Add | EventAction::Remove if deleted => {}
             EventAction::Add => {
                 if let Some(tp) = &ev.track_path {
                     match track_state.get(tp).map(|(_, a)| a) {
@@ -49,7 +55,34 @@ pub fn collapse_events(events: &[Event]) -> Vec<Event> {
                     }
                 }
             }
-            EventAction::Rename { .. } | EventAction::Create | EventAction::Delete => {
+            EventAction::Rename { from, to } => {
+                let (from_seg, to_seg) = (format!("/{}/", from), format!("/{}/", to));
+                if !from.is_empty() && !to.is_empty() {
+                    track_state = track_state
+                        .into_iter()
+                        .map(|(path, state)| match path.find(&from_seg) {
+                            Some(pos) => (
+                                format!(
+                                    "{}{}{}",
+                                    &path[..pos],
+                                    to_seg,
+                                    &path[pos + from_seg.len()..]
+                                ),
+                                state,
+                            ),
+                            None => (path, state),
+                        })
+                        .collect();
+                }
+                other_ops.push(ev.clone());
+            }
+            EventAction::Delete => {
+                track_state.clear();
+                deleted = true;
+                other_ops.push(ev.clone());
+            }
+            EventAction::Create => {
+                deleted = false;
                 other_ops.push(ev.clone());
             }
         }
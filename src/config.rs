@@ -3,19 +3,34 @@ use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
+    /// Primary music root.  Playlist keys for folders under it are the plain
+    /// folder path relative to it.
+    #[serde(default)]
     pub root_folder: PathBuf,
+    /// Additional music roots watched alongside `root_folder` (which may be
+    /// left empty when only this list is used; the first entry then acts as
+    /// the primary root).  Playlist keys for folders under an additional
+    /// root are prefixed with that root's final path segment, e.g.
+    /// `/archive/Artist/Album` -> `archive/Artist/Album`, so they cannot
+    /// collide with keys from the primary root.
     #[serde(default)]
-    pub whitelist: String,
+    pub root_folders: Vec<PathBuf>,
+    /// Regex patterns matched against full folder paths; a folder is kept
+    /// when any of them matches.  Empty keeps every folder.  Written as a
+    /// TOML array; a single colon-separated string is still accepted but
+    /// deprecated (see [`deserialize_pattern_list`]).
+    #[serde(default, deserialize_with = "deserialize_pattern_list")]
+    pub whitelist: Vec<String>,
     /// Optional whitelist for local playlist generation (`whitelist_playlist_watch_folders`). Falls back to `whitelist` when empty.
     #[serde(rename = "whitelist_playlist_watch_folders")]
     #[serde(alias = "local_whitelist")]
-    #[serde(default)]
-    pub local_whitelist: String,
+    #[serde(default, deserialize_with = "deserialize_pattern_list")]
+    pub local_whitelist: Vec<String>,
     /// Optional whitelist for remote syncing (`whitelist_playlist_sync_to_remote_folders`). Falls back to `whitelist` when empty.
     #[serde(rename = "whitelist_playlist_sync_to_remote_folders")]
     #[serde(alias = "remote_whitelist")]
-    #[serde(default)]
-    pub remote_whitelist: String,
+    #[serde(default, deserialize_with = "deserialize_pattern_list")]
+    pub remote_whitelist: Vec<String>,
     #[serde(default = "default_local_template")]
     pub local_playlist_template: String,
     #[serde(default = "default_remote_template")]
@@ -30,22 +45,50 @@ pub struct Config {
     /// - "${path_to_parent}"  -> logical path to the playlist folder's
     ///                            parent. For remote playlists this includes
     ///                            `online_root_playlist` when set. In flat
-    ///                            mode, when `online_folder_flattening_delimiter`
-    ///                            is non-empty, filesystem separators in this
-    ///                            path are replaced with that delimiter.
+    ///                            mode, filesystem separators in this path are
+    ///                            replaced with the flattening delimiter (see
+    ///                            `online_folder_flattening_delimiter`).
     /// - "${relative_path}"   -> legacy alias expanded as
     ///                            `path_to_parent + folder_name` so existing
     ///                            configs keep working.
+    /// - "${parent_name}"     -> final segment of the parent path (e.g. the
+    ///   genre folder "Rock"); empty at the root.
+    /// - "${track_count}"     -> number of media files in the folder.
+    ///
+    /// Unknown placeholders are left as they are.
     #[serde(default)]
     pub remote_playlist_template_flat: String,
     #[serde(default)]
     pub remote_playlist_template_folders: String,
+    /// Description given to remote playlists when they are created (and
+    /// refreshed on config-driven renames where the provider allows it).
+    /// Expanded like the name templates, except that `${track_count}` is the
+    /// number of tracks in the local playlist.  Empty -> no description.
     #[serde(default)]
     pub playlist_description_template: String,
+    /// Track ordering: "append" (alphabetical local playlists, remote adds
+    /// appended), "sync_order" (local playlists ordered by mtime, oldest
+    /// first), "mtime_desc" (by mtime, newest first), "track_number" (local
+    /// playlists ordered by disc/track tags) or "mirror" (remote playlists
+    /// are additionally reordered to follow the `.m3u`).
     #[serde(default = "default_playlist_order_mode")]
     pub playlist_order_mode: String,
+    /// "flat" (every track below the folder), "linked" (references to the
+    /// child folders' playlists) or "linked_with_local_tracks" (the child
+    /// references followed by the folder's own tracks).
     #[serde(default = "default_playlist_mode")]
     pub playlist_mode: String,
+    /// File format of flat local playlists: "m3u" (default), "xspf" or "pls".
+    /// The extension of `local_playlist_template` follows the format, e.g.
+    /// "Album1.m3u" becomes "Album1.xspf".  Linked playlists are always M3U.
+    #[serde(default = "default_playlist_format")]
+    pub playlist_format: String,
+    /// Threads writing local playlists during the watcher's initial scan and
+    /// the nightly reconcile (default 4).  Folders are independent, so more
+    /// threads help on large libraries; 1 writes them one after another,
+    /// which is kinder to slow storage.
+    #[serde(default = "default_playlist_write_threads")]
+    pub playlist_write_threads: usize,
     #[serde(default = "default_linked_reference_format")]
     pub linked_reference_format: String,
     #[serde(default = "default_debounce")]
@@ -67,10 +110,38 @@ pub struct Config {
     #[serde(default = "default_deferred_trigger_delay")]
     pub watcher_deferred_trigger_delay_sec: u64,
 
+    /// How the watcher detects changes: "inotify" (filesystem notifications)
+    /// or "poll" (rescan the roots every `poll_interval_sec` seconds and diff
+    /// against the previous scan), for SMB/NFS mounts where notifications
+    /// are unreliable.
+    #[serde(default = "default_watch_mode")]
+    pub watch_mode: String,
+    /// Seconds between rescans when `watch_mode = "poll"`.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_sec: u64,
+    /// Address the watcher serves Prometheus metrics on (`GET /metrics`),
+    /// e.g. "127.0.0.1:9110".  Empty (default) -> no metrics endpoint.
+    #[serde(default)]
+    pub metrics_listen: String,
+
     #[serde(default = "default_log_dir")]
     pub log_dir: PathBuf,
+    /// Least severe level logged: "error", "warn", "info" (default),
+    /// "debug", "trace" or "off".  `RUST_LOG`, when set, takes precedence.
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Log line format on stdout and in the log file: "text" (default) or
+    /// "json", one object per event.
+    #[serde(default = "default_log_format")]
+    pub log_format: String,
     #[serde(default = "default_token_refresh_interval")]
     pub token_refresh_interval: u64,
+    /// File holding a passphrase that encrypts the provider tokens stored in
+    /// the DB (see [`crate::secrets`]); the `MUSIC_SYNC_CREDENTIALS_KEY`
+    /// environment variable takes precedence.  Unset (default) and no env
+    /// var -> tokens are stored in plaintext.
+    #[serde(default)]
+    pub credentials_key_file: Option<PathBuf>,
 
     // Worker/timing
     #[serde(default = "default_nightly_cron")]
@@ -80,6 +151,8 @@ pub struct Config {
     #[serde(default)]
     pub queue_length_stop_cloud_sync_threshold: Option<u64>,
 
+    /// Retries per provider request, and the number of failed worker runs
+    /// after which queued events are marked failed (see `queue-failed`).
     #[serde(default = "default_max_retries")]
     pub max_retries_on_error: u32,
 
@@ -94,15 +167,152 @@ pub struct Config {
     #[serde(default = "default_max_batch_tidal")]
     pub max_batch_size_tidal: usize,
 
+    /// Maximum sustained request rate (requests/second) against the Spotify
+    /// and TIDAL APIs, shared by all calls of a worker run.  Bursts up to
+    /// the same number of requests are allowed.  0 disables the limiter.
+    #[serde(default = "default_spotify_requests_per_sec")]
+    pub spotify_requests_per_sec: f64,
+    #[serde(default = "default_tidal_requests_per_sec")]
+    pub tidal_requests_per_sec: f64,
+
+    /// TIDAL catalog region (ISO 3166-1 alpha-2, e.g. "DE") and locale
+    /// (e.g. "de-DE") sent with every TIDAL request; searches only find
+    /// tracks available in that region.  The `TIDAL_COUNTRY_CODE` and
+    /// `TIDAL_LOCALE` environment variables override them.
+    /// Spotify market for searches: an ISO 3166-1 alpha-2 code, or
+    /// "from_token" for the account's own country.  Results that cannot be
+    /// played there are skipped (falling back to an ISRC search for another
+    /// recording).  Empty sends no market and keeps every result.
+    #[serde(default)]
+    pub spotify_market: String,
+
+    /// OAuth scopes `auth spotify` requests.  The default covers private and
+    /// public playlist changes plus cover uploads; e.g. add
+    /// "playlist-read-collaborative" to see collaborative playlists.
+    /// Changes need a new `auth spotify`.  Writes are refused with a request
+    /// to re-authorize when the stored token lacks the scope they need.
+    #[serde(default = "default_spotify_scopes")]
+    pub spotify_scopes: Vec<String>,
+
+    #[serde(default = "default_tidal_country_code")]
+    pub tidal_country_code: String,
+    #[serde(default = "default_tidal_locale")]
+    pub tidal_locale: String,
+
+    /// Seconds to wait before retrying a track that could not be matched on
+    /// a provider (negative cache).  `cache prune` clears these entries early.
+    #[serde(default = "default_unresolved_retry_secs")]
+    pub unresolved_retry_secs: u64,
+
+    /// Metadata-search results whose duration differs from the local file's
+    /// by more than this many seconds are rejected (karaoke, sped-up or
+    /// live versions).  0 disables the check.
+    #[serde(default = "default_match_duration_tolerance_secs")]
+    pub match_duration_tolerance_secs: u64,
+
+    /// Minimum confidence (0.0-1.0) a metadata-search result needs to be
+    /// added; see [`crate::api::match_confidence`] for how it is computed.
+    /// Below it the track is left out and recorded as unresolved rather than
+    /// risk a wrong match.  0 (the default) accepts the first result within
+    /// the duration tolerance.
+    #[serde(default)]
+    pub min_match_confidence: f64,
+
+    /// Words that mark a bracketed title group or `" - ..."` suffix as noise
+    /// to drop for the normalized retry of a metadata search, e.g.
+    /// "(Remastered 2011)" or "- Radio Edit".  The unmodified title is always
+    /// searched first.
+    #[serde(default = "default_search_strip_tokens")]
+    pub search_strip_tokens: Vec<String>,
+    /// Also drop diacritics ("Björk" -> "Bjork") in the normalized retry.
+    #[serde(default = "default_true")]
+    pub search_strip_diacritics: bool,
+
+    /// Look up a missing ISRC on MusicBrainz (artist/title/album/duration)
+    /// before falling back to a metadata search.  Limited to 1 request/sec.
+    #[serde(default)]
+    pub enable_musicbrainz: bool,
+
+    /// Providers the worker may use ("spotify", "tidal", "ytmusic",
+    /// "subsonic", "deezer", "applemusic", "plex", "filesystem").  Empty (default)
+    /// enables every provider with stored credentials (and "filesystem" when
+    /// `filesystem_export_dir` is set); otherwise providers not listed are
+    /// skipped even when credentials are stored.
+    #[serde(default)]
+    pub enabled_providers: Vec<String>,
+
+    /// Directory the "filesystem" provider writes its `.m3u` playlists to,
+    /// e.g. a Navidrome playlist folder.  Empty (default) disables it.
+    #[serde(default)]
+    pub filesystem_export_dir: PathBuf,
+    /// Server-side path of `root_folder` used for the tracks in exported
+    /// playlists, e.g. "/music" when the server mounts the library there.
+    /// Empty -> the local absolute paths.
+    #[serde(default)]
+    pub filesystem_export_path_prefix: String,
+
+    /// How many providers the worker updates at the same time for one
+    /// playlist (default 4); the rest wait for a free slot.  1 processes
+    /// them one after another.
+    #[serde(default = "default_max_concurrent_providers")]
+    pub max_concurrent_providers: usize,
+
+    /// Seconds a playlist's stored remote snapshot is trusted for reconciles
+    /// before the remote is listed again (default 1 day).  A failed operation
+    /// also forces a relist.  0 always lists; `--force-relist` does so for
+    /// one run.
+    #[serde(default = "default_remote_snapshot_max_age_secs")]
+    pub remote_snapshot_max_age_secs: u64,
+
+    /// Lease in seconds on a playlist's processing lock (default 600).  The
+    /// worker renews it while it works on the playlist, so this only needs to
+    /// outlast the longest single step, not the whole playlist.
+    #[serde(default = "default_lock_ttl_secs")]
+    pub lock_ttl_secs: i64,
+
+    /// First retry delay in seconds after a failed provider call (default 1);
+    /// it doubles with every attempt up to `backoff_max_secs` (default 60).
+    #[serde(default = "default_backoff_base_secs")]
+    pub backoff_base_secs: u64,
+    #[serde(default = "default_backoff_max_secs")]
+    pub backoff_max_secs: u64,
+
+    /// Seconds synced events are kept in `event_queue` before the worker
+    /// deletes them (default 7 days).  `queue-prune` applies it on demand.
+    #[serde(default = "default_synced_event_retention_secs")]
+    pub synced_event_retention_secs: u64,
+
     // path to database file
     #[serde(default = "default_db_path")]
     pub db_path: PathBuf,
 
     /// Whitelist of file extensions to treat as track/media files.
-    /// Examples: ["*.mp3", "*.flac", "wav"]. Case-insensitive.
+    /// Examples: ["*.mp3", "*.flac", "wav"]. Case-insensitive.  Entries that
+    /// are more than an extension are globs over the file name, e.g.
+    /// "*.hi-res.flac" (see [`crate::util::path_matches_extensions`]).
     #[serde(default = "default_file_extensions")]
     pub file_extensions: Vec<String>,
 
+    /// Gitignore-style glob patterns for files and folders to leave out of
+    /// tracking and playlist writes, e.g. `[".stfolder", "@eaDir", "*.part"]`.
+    /// Applied on top of the whitelists: an ignored path is never tracked,
+    /// even if a whitelist matches it.  See [`crate::util::IgnoreMatcher`].
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+
+    /// Name of a marker file that excludes the folder containing it, and
+    /// all of its subfolders, from tracking and playlists (the `.nomedia`
+    /// idiom).  Default `.noplaylist`; empty disables the check.
+    #[serde(default = "default_no_playlist_marker")]
+    pub no_playlist_marker: String,
+    /// Name of a marker file that keeps the playlist of the folder
+    /// containing it local: the playlist file is still written, but no
+    /// provider events are queued for it and the worker drops any that
+    /// are.  Unlike the whitelists it does not apply to subfolders.
+    /// Default `.localonly`; empty disables the check.
+    #[serde(default = "default_local_only_marker")]
+    pub local_only_marker: String,
+
     /// Optional logical root playlist name for online providers.
     /// When set, all remote playlists will be nested under this logical root
     /// according to `online_playlist_structure`.
@@ -111,8 +321,7 @@ pub struct Config {
 
     /// Structure for online playlist naming: "flat" or "folders".
     /// - "flat": playlists are a single-level list; when
-    ///   `online_folder_flattening_delimiter` is set and `online_root_playlist`
-    ///   is non-empty, remote playlist names become
+    ///   `online_root_playlist` is non-empty, remote playlist names become
     ///   "<online_root_playlist><delim><local_name>".
     /// - "folders": providers that support folders may represent nested
     ///   structure; for providers that do not (e.g. Tidal), the implementation
@@ -122,13 +331,80 @@ pub struct Config {
 
     /// Delimiter used when flattening folder structure into a single playlist
     /// name for online providers in "flat" mode or when a provider does not
-    /// support folder nesting. If empty and `online_root_playlist` is set, a
-    /// sensible default (" - ") is used so that all synced playlists remain
-    /// clearly namespaced under the root.
+    /// support folder nesting. If empty and `online_root_playlist` is set,
+    /// [`DEFAULT_FLATTENING_DELIMITER`] is used so that all synced playlists
+    /// remain clearly namespaced under the root (see
+    /// [`Config::flattening_delimiter`]).
     #[serde(default)]
     pub online_folder_flattening_delimiter: String,
+
+    /// Optional regex used to derive search metadata from a file name (the
+    /// stem, without extension) when the file has no usable tags.  Named
+    /// capture groups `title` (required), `artist` and `album` are used,
+    /// e.g. `^\d+ (?P<title>.+) \((?P<artist>[^)]+)\)$`.  Files that do
+    /// not match fall back to the `"Artist - Title"` heuristic.
+    #[serde(default)]
+    pub filename_parse_regex: String,
+
+    /// Strip duplicate-file suffixes (`filename_copy_suffix_patterns`) from
+    /// a file name before deriving search metadata from it, so "Song copy
+    /// 2.mp3" is searched as "Song".  Files with a title tag are searched by
+    /// their tags and never stripped.  Default true.
+    #[serde(default = "default_true")]
+    pub strip_filename_copy_suffix: bool,
+
+    /// Regexes of the suffixes removed by `strip_filename_copy_suffix`, each
+    /// matched at the end of the file name stem.  Default `[" copy \d+"]`
+    /// (case-insensitive, as left by macOS); add `" - Copy"` or
+    /// `" \(\d+\)"` for Windows-style duplicates.
+    #[serde(default = "default_filename_copy_suffix_patterns")]
+    pub filename_copy_suffix_patterns: Vec<String>,
+}
+
+/// Flattening delimiter used when `online_folder_flattening_delimiter` is
+/// empty but `online_root_playlist` is set.
+pub const DEFAULT_FLATTENING_DELIMITER: &str = " - ";
+
+/// Read a whitelist given either as an array of patterns or, as in older
+/// configs, as one string with the patterns joined by `:`.  The string form
+/// cannot express patterns containing a colon and logs a deprecation
+/// warning.  Blank entries are dropped.
+fn deserialize_pattern_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Patterns {
+        List(Vec<String>),
+        Joined(String),
+    }
+    let patterns = match Patterns::deserialize(deserializer)? {
+        Patterns::List(list) => list,
+        Patterns::Joined(joined) => {
+            if !joined.trim().is_empty() {
+                log::warn!(
+                    "whitelist {:?} is a colon-separated string; this form is deprecated, \
+                     write the patterns as a TOML array instead",
+                    joined
+                );
+            }
+            joined.split(':').map(String::from).collect()
+        }
+    };
+    Ok(patterns
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect())
 }
 
+fn default_local_only_marker() -> String {
+    ".localonly".into()
+}
+fn default_no_playlist_marker() -> String {
+    crate::util::DEFAULT_NO_PLAYLIST_MARKER.into()
+}
 fn default_local_template() -> String {
     "${folder_name}.m3u".into()
 }
@@ -147,6 +423,12 @@ fn default_linked_reference_format() -> String {
 fn default_debounce() -> u64 {
     250
 }
+fn default_watch_mode() -> String {
+    "inotify".into()
+}
+fn default_poll_interval() -> u64 {
+    60
+}
 fn default_instant_trigger_threshold() -> usize {
     20
 }
@@ -156,6 +438,12 @@ fn default_deferred_trigger_delay() -> u64 {
 fn default_log_dir() -> PathBuf {
     "/var/log/music-sync".into()
 }
+fn default_log_level() -> String {
+    "info".into()
+}
+fn default_log_format() -> String {
+    "text".into()
+}
 fn default_token_refresh_interval() -> u64 {
     3600
 }
@@ -172,6 +460,86 @@ fn default_max_batch_spotify() -> usize {
 fn default_max_batch_tidal() -> usize {
     20
 }
+fn default_spotify_requests_per_sec() -> f64 {
+    10.0
+}
+fn default_spotify_scopes() -> Vec<String> {
+    vec![
+        "playlist-modify-private",
+        "playlist-modify-public",
+        "playlist-read-private",
+        "ugc-image-upload",
+        "user-read-private",
+        "user-read-email",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+fn default_tidal_requests_per_sec() -> f64 {
+    5.0
+}
+fn default_tidal_country_code() -> String {
+    "US".into()
+}
+fn default_tidal_locale() -> String {
+    "en-US".into()
+}
+fn default_unresolved_retry_secs() -> u64 {
+    30 * 24 * 3600
+}
+fn default_playlist_format() -> String {
+    "m3u".into()
+}
+fn default_playlist_write_threads() -> usize {
+    4
+}
+fn default_search_strip_tokens() -> Vec<String> {
+    [
+        "remaster",
+        "remastered",
+        "radio edit",
+        "single version",
+        "album version",
+        "mono",
+        "stereo",
+        "explicit",
+        "clean",
+        "bonus track",
+        "feat",
+        "ft",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+fn default_true() -> bool {
+    true
+}
+fn default_filename_copy_suffix_patterns() -> Vec<String> {
+    vec![r"(?i) copy \d+".to_string()]
+}
+fn default_max_concurrent_providers() -> usize {
+    4
+}
+fn default_remote_snapshot_max_age_secs() -> u64 {
+    24 * 3600
+}
+fn default_lock_ttl_secs() -> i64 {
+    600
+}
+fn default_backoff_base_secs() -> u64 {
+    1
+}
+fn default_backoff_max_secs() -> u64 {
+    60
+}
+fn default_match_duration_tolerance_secs() -> u64 {
+    10
+}
+fn default_synced_event_retention_secs() -> u64 {
+    7 * 24 * 3600
+}
 fn default_db_path() -> PathBuf {
     "/var/lib/music-sync/music-sync.db".into()
 }
@@ -189,7 +557,7 @@ fn default_file_extensions() -> Vec<String> {
 
 impl Config {
     /// Returns the whitelist used for local playlist generation (fallbacks to legacy field).
-    pub fn effective_local_whitelist(&self) -> &str {
+    pub fn effective_local_whitelist(&self) -> &[String] {
         if !self.local_whitelist.is_empty() {
             &self.local_whitelist
         } else {
@@ -198,7 +566,7 @@ impl Config {
     }
 
     /// Returns the whitelist used for remote syncing (fallbacks to legacy field).
-    pub fn effective_remote_whitelist(&self) -> &str {
+    pub fn effective_remote_whitelist(&self) -> &[String] {
         if !self.remote_whitelist.is_empty() {
             &self.remote_whitelist
         } else {
@@ -206,13 +574,378 @@ impl Config {
         }
     }
 
+    /// Check that every whitelist pattern compiles, naming the setting and
+    /// pattern of the first one that does not.
+    fn validate_whitelists(&self) -> anyhow::Result<()> {
+        for (key, patterns) in [
+            ("whitelist", &self.whitelist),
+            ("whitelist_playlist_watch_folders", &self.local_whitelist),
+            (
+                "whitelist_playlist_sync_to_remote_folders",
+                &self.remote_whitelist,
+            ),
+        ] {
+            for pat in patterns {
+                if let Err(e) = regex::Regex::new(pat) {
+                    anyhow::bail!("invalid {} pattern {:?}: {}", key, pat, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile `filename_parse_regex`.  Returns None when it is empty and an
+    /// error when the pattern is invalid or lacks a `title` group.
+    pub fn compiled_filename_parse_regex(&self) -> anyhow::Result<Option<regex::Regex>> {
+        if self.filename_parse_regex.trim().is_empty() {
+            return Ok(None);
+        }
+        let re = regex::Regex::new(&self.filename_parse_regex).map_err(|e| {
+            anyhow::anyhow!(
+                "invalid filename_parse_regex {:?}: {}",
+                self.filename_parse_regex,
+                e
+            )
+        })?;
+        if !re.capture_names().any(|n| n == Some("title")) {
+            anyhow::bail!(
+                "filename_parse_regex {:?} must contain a named capture group `title`",
+                self.filename_parse_regex
+            );
+        }
+        Ok(Some(re))
+    }
+
+    /// Compile `filename_copy_suffix_patterns`, each anchored at the end of
+    /// the stem.  Empty when `strip_filename_copy_suffix` is off.
+    pub fn compiled_filename_copy_suffixes(&self) -> anyhow::Result<Vec<regex::Regex>> {
+        if !self.strip_filename_copy_suffix {
+            return Ok(Vec::new());
+        }
+        self.filename_copy_suffix_patterns
+            .iter()
+            .map(|p| {
+                regex::Regex::new(&format!("(?:{})$", p)).map_err(|e| {
+                    anyhow::anyhow!("invalid filename_copy_suffix_patterns entry {:?}: {}", p, e)
+                })
+            })
+            .collect()
+    }
+
+    /// All configured music roots, primary first, without duplicates.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let mut roots: Vec<PathBuf> = Vec::new();
+        if !self.root_folder.as_os_str().is_empty() {
+            roots.push(self.root_folder.clone());
+        }
+        for r in &self.root_folders {
+            if !r.as_os_str().is_empty() && !roots.contains(r) {
+                roots.push(r.clone());
+            }
+        }
+        roots
+    }
+
+    /// The primary root (see [`Self::roots`]).
+    pub fn primary_root(&self) -> PathBuf {
+        self.roots().into_iter().next().unwrap_or_default()
+    }
+
+    /// Key prefix used for playlists under an additional root: its final
+    /// path segment.
+    fn root_label(root: &std::path::Path) -> String {
+        root.file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.display().to_string())
+    }
+
+    /// Return the root containing `path` (the longest match when roots are
+    /// nested) and its index in [`Self::roots`].
+    pub fn root_for_path(&self, path: &std::path::Path) -> Option<(usize, PathBuf)> {
+        self.roots()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, r)| path.starts_with(r))
+            .max_by_key(|(_, r)| r.components().count())
+    }
+
+    /// Path of `folder` relative to the root that contains it (used for the
+    /// `${path_to_parent}` placeholder of local playlist files).  Folders
+    /// outside every root are returned unchanged.
+    pub fn root_relative(&self, folder: &std::path::Path) -> PathBuf {
+        match self.root_for_path(folder) {
+            Some((_, root)) => folder.strip_prefix(&root).unwrap_or(folder).to_path_buf(),
+            None => folder.to_path_buf(),
+        }
+    }
+
+    /// Logical playlist key (as stored in the event queue and playlist_map)
+    /// for a playlist folder.  Non-UTF-8 bytes in folder names become U+FFFD,
+    /// like track paths (see [`crate::util::resolve_lossy_path`]).
+    pub fn playlist_key_for_folder(&self, folder: &std::path::Path) -> String {
+        match self.root_for_path(folder) {
+            Some((0, root)) => folder
+                .strip_prefix(&root)
+                .unwrap_or(folder)
+                .display()
+                .to_string(),
+            Some((_, root)) => {
+                let label = PathBuf::from(Self::root_label(&root));
+                label
+                    .join(folder.strip_prefix(&root).unwrap_or(folder))
+                    .display()
+                    .to_string()
+            }
+            None => folder.display().to_string(),
+        }
+    }
+
+    /// Inverse of [`Self::playlist_key_for_folder`]: the on-disk folder for a
+    /// logical playlist key.
+    pub fn playlist_folder_for_key(&self, key: &str) -> PathBuf {
+        let roots = self.roots();
+        let key_path = std::path::Path::new(key);
+        for root in roots.iter().skip(1) {
+            let label = Self::root_label(root);
+            if let Ok(rest) = key_path.strip_prefix(&label) {
+                return crate::util::resolve_lossy_path(&root.join(rest));
+            }
+        }
+        let folder = roots.first().cloned().unwrap_or_default().join(key_path);
+        crate::util::resolve_lossy_path(&folder)
+    }
+
+    /// True when `folder` holds the `local_only_marker` file, i.e. its
+    /// playlist is written locally but not synced to any provider.
+    pub fn is_local_only(&self, folder: &std::path::Path) -> bool {
+        !self.local_only_marker.is_empty() && folder.join(&self.local_only_marker).is_file()
+    }
+
+    /// Expand `local_playlist_template` for a playlist folder and give the
+    /// result the extension of `playlist_format` (flat mode only).
+    /// `${track_count}` counts the media files below `folder`.
+    pub fn local_playlist_file_name(
+        &self,
+        folder: &std::path::Path,
+        folder_name: &str,
+        path_to_parent: &str,
+    ) -> String {
+        let name = crate::util::expand_template(
+            &self.local_playlist_template,
+            folder_name,
+            path_to_parent,
+            crate::util::template_parent_name(path_to_parent),
+            || self.count_folder_tracks(folder),
+        );
+        let format = self.playlist_format.to_ascii_lowercase();
+        if self.playlist_mode != "flat" || format == "m3u" {
+            return name;
+        }
+        let stem = match name.rsplit_once('.') {
+            Some((stem, ext)) if crate::playlist::is_playlist_extension(ext) => stem,
+            _ => name.as_str(),
+        };
+        format!("{}.{}", stem, format)
+    }
+
+    /// The delimiter that replaces path separators in flattened remote
+    /// playlist names: `online_folder_flattening_delimiter`, or
+    /// [`DEFAULT_FLATTENING_DELIMITER`] when that is empty and
+    /// `online_root_playlist` is set.  Empty means paths keep their "/".
+    pub fn flattening_delimiter(&self) -> &str {
+        if self.online_folder_flattening_delimiter.is_empty()
+            && !self.online_root_playlist.trim().is_empty()
+        {
+            DEFAULT_FLATTENING_DELIMITER
+        } else {
+            &self.online_folder_flattening_delimiter
+        }
+    }
+
+    /// Media files below `folder` that its flat playlist would list, for the
+    /// `${track_count}` placeholder.
+    pub fn count_folder_tracks(&self, folder: &std::path::Path) -> usize {
+        crate::playlist::count_tracks(folder, &self.file_extensions, &self.ignore_matcher())
+    }
+
+    /// True if the worker may use `provider` (see `enabled_providers`).
+    pub fn provider_enabled(&self, provider: &str) -> bool {
+        self.enabled_providers.is_empty()
+            || self
+                .enabled_providers
+                .iter()
+                .any(|p| p.trim().eq_ignore_ascii_case(provider))
+    }
+
+    /// Retry delay before attempt `attempt + 1`: `backoff_base_secs * 2^attempt`,
+    /// capped at `backoff_max_secs`.
+    pub fn backoff_secs(&self, attempt: u32) -> u64 {
+        self.backoff_base_secs
+            .saturating_mul(1u64 << attempt.min(63))
+            .min(self.backoff_max_secs)
+    }
+
+    /// Compile `ignore_patterns` (plus `no_playlist_marker`) against the
+    /// configured roots.
+    pub fn ignore_matcher(&self) -> crate::util::IgnoreMatcher {
+        crate::util::IgnoreMatcher::new(&self.ignore_patterns, self.roots())
+            .with_marker(&self.no_playlist_marker)
+    }
+
+    /// Check settings that serde cannot validate on its own.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.compiled_filename_parse_regex()?;
+        self.compiled_filename_copy_suffixes()?;
+        self.validate_whitelists()?;
+        if self.log_level.parse::<log::LevelFilter>().is_err() {
+            anyhow::bail!(
+                "unknown log_level {:?}; expected \"error\", \"warn\", \"info\", \"debug\", \
+                 \"trace\" or \"off\"",
+                self.log_level
+            );
+        }
+        if !matches!(self.log_format.as_str(), "text" | "json") {
+            anyhow::bail!(
+                "unknown log_format {:?}; expected \"text\" or \"json\"",
+                self.log_format
+            );
+        }
+        for pat in &self.file_extensions {
+            if let Err(e) = crate::util::validate_extension_pattern(pat) {
+                anyhow::bail!("invalid file_extensions pattern {:?}: {}", pat, e);
+            }
+        }
+        if self.playlist_write_threads == 0 {
+            anyhow::bail!("playlist_write_threads must be at least 1");
+        }
+        match self.watch_mode.as_str() {
+            "inotify" => {}
+            "poll" if self.poll_interval_sec == 0 => {
+                anyhow::bail!("poll_interval_sec must be greater than 0 when watch_mode = \"poll\"")
+            }
+            "poll" => {}
+            other => anyhow::bail!(
+                "unknown watch_mode {:?}; expected \"inotify\" or \"poll\"",
+                other
+            ),
+        }
+        if !matches!(
+            self.playlist_format.to_ascii_lowercase().as_str(),
+            "m3u" | "xspf" | "pls"
+        ) {
+            anyhow::bail!(
+                "unknown playlist_format {:?}; expected \"m3u\", \"xspf\" or \"pls\"",
+                self.playlist_format
+            );
+        }
+        if let Some(unknown) = self.enabled_providers.iter().find(|p| {
+            ![
+                "spotify",
+                "tidal",
+                "ytmusic",
+                "subsonic",
+                "deezer",
+                "applemusic",
+                "plex",
+                "filesystem",
+            ]
+            .iter()
+            .any(|known| p.trim().eq_ignore_ascii_case(known))
+        }) {
+            anyhow::bail!(
+                "unknown provider {:?} in enabled_providers; expected \"spotify\", \"tidal\", \"ytmusic\", \"subsonic\", \"deezer\", \"applemusic\", \"plex\" or \"filesystem\"",
+                unknown
+            );
+        }
+        let market = self.spotify_market.trim();
+        if !(market.is_empty()
+            || market == "from_token"
+            || (market.len() == 2 && market.chars().all(|c| c.is_ascii_alphabetic())))
+        {
+            anyhow::bail!(
+                "spotify_market {:?} must be empty, \"from_token\" or an ISO 3166-1 alpha-2 code such as \"DE\"",
+                self.spotify_market
+            );
+        }
+        if let Some(bad) = self
+            .spotify_scopes
+            .iter()
+            .find(|s| s.is_empty() || s.contains(char::is_whitespace))
+        {
+            anyhow::bail!(
+                "spotify_scopes entry {:?} must be a single scope such as \"playlist-modify-private\"",
+                bad
+            );
+        }
+        let cc = &self.tidal_country_code;
+        if cc.len() != 2 || !cc.chars().all(|c| c.is_ascii_alphabetic()) {
+            anyhow::bail!(
+                "tidal_country_code {:?} is not an ISO 3166-1 alpha-2 code such as \"US\" or \"DE\"",
+                cc
+            );
+        }
+        if !is_locale_code(&self.tidal_locale) {
+            anyhow::bail!(
+                "tidal_locale {:?} is not a locale such as \"en-US\" or \"de\"",
+                self.tidal_locale
+            );
+        }
+        if !self.metrics_listen.is_empty()
+            && self.metrics_listen.parse::<std::net::SocketAddr>().is_err()
+        {
+            anyhow::bail!(
+                "metrics_listen {:?} is not an address such as \"127.0.0.1:9110\"",
+                self.metrics_listen
+            );
+        }
+        if !(0.0..=1.0).contains(&self.min_match_confidence) {
+            anyhow::bail!(
+                "min_match_confidence must be between 0.0 and 1.0, got {}",
+                self.min_match_confidence
+            );
+        }
+        if self.lock_ttl_secs <= 0 {
+            anyhow::bail!("lock_ttl_secs must be greater than 0");
+        }
+        let roots = self.roots();
+        if roots.is_empty() {
+            anyhow::bail!("no music root configured: set `root_folder` or `root_folders`");
+        }
+        let mut labels = std::collections::HashSet::new();
+        for root in roots.iter().skip(1) {
+            let label = Self::root_label(root);
+            if !labels.insert(label.clone()) {
+                anyhow::bail!(
+                    "root_folders entries must have distinct final path segments; {:?} is used twice",
+                    label
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn from_path(path: &std::path::Path) -> anyhow::Result<Self> {
         let s = std::fs::read_to_string(path)?;
         let cfg: Config = toml::from_str(&s)?;
+        cfg.validate()?;
         Ok(cfg)
     }
 }
 
+/// True for "ll" or "ll-CC": a 2-3 letter ISO 639 language, optionally
+/// followed by a 2 letter ISO 3166-1 region.
+fn is_locale_code(s: &str) -> bool {
+    let mut parts = s.split('-');
+    let lang_ok = parts
+        .next()
+        .is_some_and(|l| (2..=3).contains(&l.len()) && l.chars().all(|c| c.is_ascii_alphabetic()));
+    let region_ok = match parts.next() {
+        None => true,
+        Some(r) => r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()),
+    };
+    lang_ok && region_ok && parts.next().is_none()
+}
+
 impl Default for Config {
     /// Return a `Config` with every field at its serde default value.
     /// `root_folder` is set to `"."` (current directory).
@@ -1,18 +1,42 @@
-use crate::models::{Event, EventAction};
+use crate::models::{Event, EventAction, FailedEvent};
 use anyhow::{Context, Result};
 use chrono::Utc;
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use std::path::Path;
 
 /// A thread-safe connection pool backed by r2d2 + rusqlite.
 pub type DbPool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
 
+/// How long a connection waits for another writer's lock before failing
+/// with `database is locked`.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(5000);
+
+/// Apply the settings every connection needs so the watcher, worker and CLI
+/// can share the database concurrently: WAL journaling (readers don't block
+/// the writer), a busy timeout, and `synchronous=NORMAL` (safe with WAL).
+fn tune_connection(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |r| r.get::<_, String>(0))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(())
+}
+
+/// Open a connection with [`tune_connection`] applied.  Use this instead of
+/// `Connection::open` everywhere; it does not run migrations (see
+/// [`open_or_create`]).
+pub fn open_tuned<P: AsRef<Path>>(path: P) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    tune_connection(&conn)?;
+    Ok(conn)
+}
+
 /// Create a connection pool for the given database path.
 /// The pool is pre-initialized with a small number of connections so that
 /// `spawn_blocking` tasks can check out a connection without opening a new one
 /// every time.
 pub fn create_pool(path: &Path) -> Result<DbPool> {
-    let manager = r2d2_sqlite::SqliteConnectionManager::file(path);
+    let manager =
+        r2d2_sqlite::SqliteConnectionManager::file(path).with_init(|c| tune_connection(c));
     let pool = r2d2::Pool::builder()
         .max_size(4)
         .build(manager)
@@ -28,83 +52,142 @@ pub fn create_pool(path: &Path) -> Result<DbPool> {
 }
 
 pub fn open_or_create(path: &Path) -> Result<Connection> {
-    let conn = Connection::open(path)?;
+    let conn = open_tuned(path)?;
     run_migrations(&conn)?;
     Ok(conn)
 }
 
+/// SQL schema baked into the binary at compile time so that migrations do
+/// not depend on the current working directory or an installed schema file.
+const SCHEMA_SQL: &str = include_str!("../db/schema.sql");
+
+/// Return true if `table` exists but has no column named `column`.
+fn table_lacks_column(conn: &Connection, table: &str, column: &str) -> bool {
+    let exists: bool = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")
+        .and_then(|mut s| s.exists(params![table]))
+        .unwrap_or(false);
+    if !exists {
+        return false;
+    }
+    let has_col: bool = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))
+        .and_then(|mut s| s.exists(params![column]))
+        .unwrap_or(false);
+    !has_col
+}
+
 pub fn run_migrations(conn: &Connection) -> Result<()> {
-    // In development/tests, the schema file lives at db/schema.sql relative
-    // to the project root. In packaged installs, it is installed to
-    // /usr/share/music-file-playlist-online-sync/schema.sql and the
-    // .install hook already applies it once at install/upgrade time.
+    // ------------------------------------------------------------------
+    // Legacy layouts that must be rebuilt before the schema is applied.
     //
-    // At runtime we treat missing schema files as "no-op" so that
-    // packaged binaries do not fail if the on-disk schema file is absent.
-
-    // Try local development path first.
-    let candidates = [
-        "db/schema.sql",
-        "/usr/share/music-file-playlist-online-sync/schema.sql",
-    ];
-
-    let mut last_err: Option<std::io::Error> = None;
-    let mut applied = false;
-    for path in &candidates {
-        match std::fs::read_to_string(path) {
-            Ok(sql) => {
-                conn.execute_batch(&sql)
-                    .with_context(|| format!("applying DB schema from {}", path))?;
-                applied = true;
-                break;
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                // Try next candidate.
-                last_err = Some(e);
-                continue;
-            }
-            Err(e) => return Err(e).with_context(|| format!("reading DB schema from {}", path)),
-        }
+    // `playlist_map` and `track_cache` used to be keyed by a single string of
+    // the form "<provider>::<name>" (and, in the very first releases, by the
+    // bare name for Spotify).  They now carry an explicit `provider` column
+    // that is part of the primary key.  Rebuild old tables by splitting the
+    // legacy key; unprefixed rows are attributed to Spotify.
+    // ------------------------------------------------------------------
+    if table_lacks_column(conn, "playlist_map", "provider") {
+        // Very old databases may also lack remote_display_name.
+        let _ = conn.execute(
+            "ALTER TABLE playlist_map ADD COLUMN remote_display_name TEXT;",
+            [],
+        );
+        conn.execute_batch(
+            "ALTER TABLE playlist_map RENAME TO playlist_map_legacy; \
+             CREATE TABLE playlist_map ( \
+               provider TEXT NOT NULL, \
+               playlist_name TEXT NOT NULL, \
+               remote_id TEXT, \
+               remote_snapshot_id TEXT, \
+               last_synced_at INTEGER, \
+               remote_display_name TEXT, \
+               PRIMARY KEY (provider, playlist_name) \
+             ); \
+             INSERT OR REPLACE INTO playlist_map \
+               (provider, playlist_name, remote_id, remote_snapshot_id, last_synced_at, remote_display_name) \
+             SELECT \
+               CASE WHEN instr(playlist_name, '::') > 0 \
+                    THEN substr(playlist_name, 1, instr(playlist_name, '::') - 1) ELSE 'spotify' END, \
+               CASE WHEN instr(playlist_name, '::') > 0 \
+                    THEN substr(playlist_name, instr(playlist_name, '::') + 2) ELSE playlist_name END, \
+               remote_id, remote_snapshot_id, last_synced_at, remote_display_name \
+             FROM playlist_map_legacy; \
+             DROP TABLE playlist_map_legacy;",
+        )
+        .with_context(|| "migrating playlist_map to per-provider keys")?;
+    }
+    if table_lacks_column(conn, "track_cache", "provider") {
+        conn.execute_batch(
+            "ALTER TABLE track_cache RENAME TO track_cache_legacy; \
+             CREATE TABLE track_cache ( \
+               provider TEXT NOT NULL, \
+               local_path TEXT NOT NULL, \
+               isrc TEXT, \
+               remote_id TEXT, \
+               resolved_at INTEGER, \
+               PRIMARY KEY (provider, local_path) \
+             ); \
+             INSERT OR REPLACE INTO track_cache \
+               (provider, local_path, isrc, remote_id, resolved_at) \
+             SELECT \
+               CASE WHEN instr(local_path, '::') > 0 \
+                    THEN substr(local_path, 1, instr(local_path, '::') - 1) ELSE 'spotify' END, \
+               CASE WHEN instr(local_path, '::') > 0 \
+                    THEN substr(local_path, instr(local_path, '::') + 2) ELSE local_path END, \
+               isrc, remote_id, resolved_at \
+             FROM track_cache_legacy WHERE local_path IS NOT NULL; \
+             DROP TABLE track_cache_legacy;",
+        )
+        .with_context(|| "migrating track_cache to per-provider keys")?;
     }
 
-    // If we didn't apply any schema file, that's not necessarily an error –
-    // packaged installs may have already applied schema via the .install
-    // hook.  Log at debug level so the situation is visible during
-    // development.
-    if !applied {
-        if let Some(e) = last_err {
-            tracing::debug!(
-                "DB schema file not found at any known location; skipping migrations: {}",
-                e
-            );
-        }
+    // The schema only uses CREATE ... IF NOT EXISTS statements, so applying
+    // it on every open is idempotent.
+    conn.execute_batch(SCHEMA_SQL)
+        .with_context(|| "applying embedded DB schema")?;
+
+    // Negative track cache entries used to be implied by a NULL remote_id;
+    // they are now flagged explicitly.  Carry old negative entries over.
+    if table_lacks_column(conn, "track_cache", "not_found_at") {
+        conn.execute_batch(
+            "ALTER TABLE track_cache ADD COLUMN not_found_at INTEGER; \
+             UPDATE track_cache SET not_found_at = resolved_at WHERE remote_id IS NULL;",
+        )
+        .with_context(|| "adding track_cache.not_found_at")?;
     }
 
-    // ------------------------------------------------------------------
-    // Perform one–time data migrations that cannot be expressed via raw
-    // schema.sql.  Historically the `track_cache.local_path` column held
-    // the bare filesystem path for Spotify entries; other providers were
-    // added later and namespaced by prefixing with "<provider>::".  To
-    // allow us to switch to a uniformly-prefixed key we update any rows
-    // that lack the separator so that subsequent lookups work correctly.
-    // This is safe to run on every start because the WHERE clause only
-    // matches unprefixed values.
-    //
-    // We deliberately ignore "no such table" errors, which can happen if
-    // the schema has not been applied yet; in that case the caller will
-    // apply the schema and run migrations again when it re‑opens the
-    // database.
-    let _ = conn.execute(
-        "UPDATE track_cache SET local_path = 'spotify::' || local_path \
-         WHERE local_path NOT LIKE '%::%';",
-        [],
-    );
+    if table_lacks_column(conn, "track_cache", "isrc_checked_at") {
+        conn.execute_batch("ALTER TABLE track_cache ADD COLUMN isrc_checked_at INTEGER;")
+            .with_context(|| "adding track_cache.isrc_checked_at")?;
+    }
 
-    // Add remote_display_name column to playlist_map if it doesn't exist yet.
-    let _ = conn.execute(
-        "ALTER TABLE playlist_map ADD COLUMN remote_display_name TEXT;",
-        [],
-    );
+    if table_lacks_column(conn, "track_cache", "file_mtime_ns") {
+        conn.execute_batch(
+            "ALTER TABLE track_cache ADD COLUMN file_mtime_ns INTEGER; \
+             ALTER TABLE track_cache ADD COLUMN file_size INTEGER; \
+             ALTER TABLE track_cache ADD COLUMN file_isrc TEXT;",
+        )
+        .with_context(|| "adding track_cache file ISRC columns")?;
+    }
+
+    if table_lacks_column(conn, "track_cache", "remote_isrc") {
+        conn.execute_batch("ALTER TABLE track_cache ADD COLUMN remote_isrc TEXT;")
+            .with_context(|| "adding track_cache.remote_isrc")?;
+    }
+
+    // Retry bookkeeping for queued events (dead-letter support).
+    if table_lacks_column(conn, "event_queue", "attempts") {
+        conn.execute_batch(
+            "ALTER TABLE event_queue ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0; \
+             ALTER TABLE event_queue ADD COLUMN last_error TEXT; \
+             ALTER TABLE event_queue ADD COLUMN failed_at INTEGER;",
+        )
+        .with_context(|| "adding event_queue retry columns")?;
+    }
 
     // Ensure the remote_playlist_contents_cache table exists.  It may be
     // absent in databases created before this feature was added.
@@ -145,15 +228,6 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
          );",
     );
 
-    // Historically playlist_map stored bare playlist_name keys for Spotify.
-    // We now uniformly prefix every key with "<provider>::".  Migrate any
-    // remaining bare keys by prefixing them with "spotify::".
-    let _ = conn.execute(
-        "UPDATE playlist_map SET playlist_name = 'spotify::' || playlist_name \
-         WHERE playlist_name NOT LIKE '%::%';",
-        [],
-    );
-
     // playlist_cache was originally keyed by playlist_name alone, which
     // caused cross-provider URI contamination (Spotify URIs served to
     // Tidal, etc.).  The new schema uses a composite PK
@@ -172,18 +246,16 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
         if !has_provider_col {
             let _ = conn.execute("DROP TABLE IF EXISTS playlist_cache", []);
             // Re-apply schema so the new table is created immediately.
-            for path in &candidates {
-                if let Ok(sql) = std::fs::read_to_string(path) {
-                    let _ = conn.execute_batch(&sql);
-                    break;
-                }
-            }
+            let _ = conn.execute_batch(SCHEMA_SQL);
         }
     }
 
     Ok(())
 }
 
+/// Queue an event for the worker.  Pending duplicates are not inserted
+/// twice, and a pending add/remove of the same track is replaced by the
+/// opposite action.
 pub fn enqueue_event(
     conn: &Connection,
     playlist_name: &str,
@@ -191,69 +263,244 @@ pub fn enqueue_event(
     track_path: Option<&str>,
     extra: Option<&str>,
 ) -> Result<()> {
-    let action_str = match action {
-        EventAction::Add => "add",
-        EventAction::Remove => "remove",
-        EventAction::Rename { .. } => "rename",
-        EventAction::Create => "create",
-        EventAction::Delete => "delete",
+    let action_str = action_name(action);
+    // IMMEDIATE: the watcher enqueues from several threads at once, and a
+    // deferred read-then-write transaction fails with SQLITE_BUSY instead of
+    // waiting when two of them upgrade to a write lock.
+    let tx = rusqlite::Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    // Skip the insert when an identical event is already pending and no
+    // delete/create/rename of the playlist was queued after it; past one of
+    // those the earlier copy no longer stands for this event (Add A, Delete,
+    // Create, Add A must keep the second add).
+    let duplicate = tx
+        .prepare(
+            "SELECT 1 FROM event_queue e WHERE e.is_synced = 0 AND e.failed_at IS NULL \
+             AND e.playlist_name = ?1 AND e.action = ?2 AND e.track_path IS ?3 AND e.extra IS ?4 \
+             AND NOT EXISTS (SELECT 1 FROM event_queue l WHERE l.is_synced = 0 \
+             AND l.failed_at IS NULL AND l.playlist_name = ?1 AND l.id > e.id \
+             AND l.action IN ('delete', 'create', 'rename'))",
+        )?
+        .exists(params![playlist_name, action_str, track_path, extra])?;
+    if duplicate {
+        return Ok(());
+    }
+    // An add and a remove of the same track cancel out: keep only the
+    // latest intent.
+    let opposite = match action {
+        EventAction::Add => Some("remove"),
+        EventAction::Remove => Some("add"),
+        _ => None,
     };
+    if let (Some(opposite), Some(track)) = (opposite, track_path) {
+        tx.execute(
+            "DELETE FROM event_queue WHERE is_synced = 0 AND failed_at IS NULL \
+             AND playlist_name = ?1 AND action = ?2 AND track_path = ?3",
+            params![playlist_name, opposite, track],
+        )?;
+    }
     let now = chrono::Utc::now().timestamp_millis();
-    conn.execute(
+    tx.execute(
         "INSERT INTO event_queue (timestamp, playlist_name, action, track_path, extra, is_synced) VALUES (?1, ?2, ?3, ?4, ?5, 0)",
         params![now, playlist_name, action_str, track_path, extra],
     )?;
+    tx.commit()?;
     Ok(())
 }
 
-pub fn fetch_unsynced_events(conn: &Connection) -> Result<Vec<Event>> {
-    let mut stmt = conn.prepare("SELECT id, timestamp, playlist_name, action, track_path, extra, is_synced FROM event_queue WHERE is_synced = 0 ORDER BY timestamp ASC")?;
-    let rows = stmt.query_map([], |r| {
-        let action: String = r.get(3)?;
-        let event_action = match action.as_str() {
-            "add" => EventAction::Add,
-            "remove" => EventAction::Remove,
-            "rename" => {
-                // attempt to parse extra JSON for rename details {"from":"...","to":"..."}
-                let extra_json: Option<String> = r.get(5).ok();
-                if let Some(es) = extra_json {
-                    if let Ok(j) = serde_json::from_str::<serde_json::Value>(&es) {
-                        let from = j
-                            .get("from")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        let to = j
-                            .get("to")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        EventAction::Rename { from, to }
-                    } else {
-                        EventAction::Rename {
-                            from: "".into(),
-                            to: "".into(),
-                        }
-                    }
+/// Value stored in `event_queue.action` for an action.
+fn action_name(action: &EventAction) -> &'static str {
+    match action {
+        EventAction::Add => "add",
+        EventAction::Remove => "remove",
+        EventAction::Rename { .. } => "rename",
+        EventAction::Create => "create",
+        EventAction::Delete => "delete",
+    }
+}
+
+/// Columns selected by [`event_from_row`], in order.
+const EVENT_COLUMNS: &str = "id, timestamp, playlist_name, action, track_path, extra, is_synced";
+
+fn event_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<Event> {
+    let action: String = r.get(3)?;
+    let event_action = match action.as_str() {
+        "add" => EventAction::Add,
+        "remove" => EventAction::Remove,
+        "rename" => {
+            // attempt to parse extra JSON for rename details {"from":"...","to":"..."}
+            let extra_json: Option<String> = r.get(5).ok();
+            if let Some(es) = extra_json {
+                if let Ok(j) = serde_json::from_str::<serde_json::Value>(&es) {
+                    let from = j
+                        .get("from")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let to = j
+                        .get("to")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    EventAction::Rename { from, to }
                 } else {
                     EventAction::Rename {
                         from: "".into(),
                         to: "".into(),
                     }
                 }
+            } else {
+                EventAction::Rename {
+                    from: "".into(),
+                    to: "".into(),
+                }
             }
-            "create" => EventAction::Create,
-            "delete" => EventAction::Delete,
-            _ => EventAction::Create,
+        }
+        "create" => EventAction::Create,
+        "delete" => EventAction::Delete,
+        _ => EventAction::Create,
+    };
+    Ok(Event {
+        id: r.get(0)?,
+        timestamp_ms: r.get(1)?,
+        playlist_name: r.get(2)?,
+        action: event_action,
+        track_path: r.get(4).ok(),
+        extra: r.get(5).ok(),
+        is_synced: r.get::<_, i64>(6)? != 0,
+    })
+}
+
+/// Fetch unsynced events that have not been marked failed, oldest first.
+pub fn fetch_unsynced_events(conn: &Connection) -> Result<Vec<Event>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM event_queue WHERE is_synced = 0 AND failed_at IS NULL ORDER BY timestamp ASC",
+        EVENT_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], event_from_row)?;
+    let mut v = Vec::new();
+    for r in rows {
+        v.push(r?);
+    }
+    Ok(v)
+}
+
+/// Like [`fetch_unsynced_events`], narrowed in SQL to the playlist key
+/// `playlist` and the stored action name `action` ("add", "remove",
+/// "rename", "create" or "delete") when given, and capped at the `limit`
+/// oldest events.
+pub fn fetch_events_filtered(
+    conn: &Connection,
+    playlist: Option<&str>,
+    action: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<Event>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM event_queue WHERE is_synced = 0 AND failed_at IS NULL \
+         AND (?1 IS NULL OR playlist_name = ?1) AND (?2 IS NULL OR action = ?2) \
+         ORDER BY timestamp ASC LIMIT ?3",
+        EVENT_COLUMNS
+    ))?;
+    // A negative LIMIT means no limit in SQLite.
+    let limit = limit.map_or(-1, |n| n as i64);
+    let rows = stmt.query_map(params![playlist, action, limit], event_from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Every event in the queue, synced history included, in insertion order
+/// (for `queue-export`).
+pub fn export_events(conn: &Connection) -> Result<Vec<Event>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM event_queue ORDER BY id ASC",
+        EVENT_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], event_from_row)?;
+    let mut v = Vec::new();
+    for r in rows {
+        v.push(r?);
+    }
+    Ok(v)
+}
+
+/// Load events written by [`export_events`], keeping their ids, timestamps
+/// and sync state.  A row with the same id is replaced (its retry state is
+/// reset).  Returns the number of events imported.
+pub fn import_events(conn: &mut Connection, events: &[Event]) -> Result<usize> {
+    let tx = conn.transaction()?;
+    for ev in events {
+        // A rename's from/to live in `extra`; rebuild it unless it already
+        // carries the same names.
+        let extra = match &ev.action {
+            EventAction::Rename { from, to } => {
+                let stored = ev
+                    .extra
+                    .as_deref()
+                    .and_then(|e| serde_json::from_str::<serde_json::Value>(e).ok());
+                match stored {
+                    Some(j) if j["from"] == from.as_str() && j["to"] == to.as_str() => {
+                        ev.extra.clone()
+                    }
+                    _ => Some(serde_json::json!({ "from": from, "to": to }).to_string()),
+                }
+            }
+            _ => ev.extra.clone(),
         };
-        Ok(Event {
-            id: r.get(0)?,
-            timestamp_ms: r.get(1)?,
-            playlist_name: r.get(2)?,
-            action: event_action,
-            track_path: r.get(4).ok(),
-            extra: r.get(5).ok(),
-            is_synced: r.get::<_, i64>(6)? != 0,
+        tx.execute(
+            "INSERT OR REPLACE INTO event_queue (id, timestamp, playlist_name, action, track_path, extra, is_synced) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                ev.id,
+                ev.timestamp_ms,
+                ev.playlist_name,
+                action_name(&ev.action),
+                ev.track_path,
+                extra,
+                ev.is_synced as i64
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(events.len())
+}
+
+/// Record a failed sync attempt for `ids`: bump `attempts`, store `error`,
+/// and mark events that reached `max_attempts` as failed so the worker stops
+/// retrying them.  Returns the number of events newly marked failed.
+pub fn record_event_failure(
+    conn: &mut Connection,
+    ids: &[i64],
+    error: &str,
+    max_attempts: u32,
+) -> Result<usize> {
+    let tx = conn.transaction()?;
+    let mut failed = 0;
+    for id in ids {
+        tx.execute(
+            "UPDATE event_queue SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+            params![id, error],
+        )?;
+        failed += tx.execute(
+            "UPDATE event_queue SET failed_at = strftime('%s','now') \
+             WHERE id = ?1 AND failed_at IS NULL AND attempts >= ?2",
+            params![id, max_attempts],
+        )?;
+    }
+    tx.commit()?;
+    Ok(failed)
+}
+
+/// List events the worker gave up on, oldest first.
+pub fn fetch_failed_events(conn: &Connection) -> Result<Vec<FailedEvent>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, attempts, last_error, failed_at FROM event_queue \
+         WHERE is_synced = 0 AND failed_at IS NOT NULL ORDER BY timestamp ASC",
+        EVENT_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], |r| {
+        Ok(FailedEvent {
+            event: event_from_row(r)?,
+            attempts: r.get(7)?,
+            last_error: r.get(8)?,
+            failed_at: r.get(9)?,
         })
     })?;
     let mut v = Vec::new();
@@ -263,6 +510,16 @@ pub fn fetch_unsynced_events(conn: &Connection) -> Result<Vec<Event>> {
     Ok(v)
 }
 
+/// Put failed events back in the queue with a fresh retry budget.
+/// Returns the number of events requeued.
+pub fn requeue_failed_events(conn: &Connection) -> Result<usize> {
+    let n = conn.execute(
+        "UPDATE event_queue SET attempts = 0, failed_at = NULL WHERE is_synced = 0 AND failed_at IS NOT NULL",
+        [],
+    )?;
+    Ok(n)
+}
+
 /// Clear all unsynced events from the event_queue table.
 /// Returns the number of rows removed.
 pub fn clear_unsynced_events(conn: &mut Connection) -> Result<usize> {
@@ -284,9 +541,22 @@ pub fn mark_events_synced(conn: &mut Connection, ids: &[i64]) -> Result<()> {
     Ok(())
 }
 
+/// Delete synced events enqueued more than `older_than_secs` seconds ago.
+/// Unsynced events are never touched.  Returns the number of rows removed.
+pub fn prune_synced_events(conn: &Connection, older_than_secs: u64) -> Result<usize> {
+    let cutoff_ms = Utc::now().timestamp_millis() - (older_than_secs as i64).saturating_mul(1000);
+    let removed = conn.execute(
+        "DELETE FROM event_queue WHERE is_synced = 1 AND timestamp < ?1",
+        params![cutoff_ms],
+    )?;
+    Ok(removed)
+}
+
 /// Save raw credential JSON for a provider (provider = "spotify" or "tidal")
 
-/// Save raw credential JSON for a provider, with optional client_id/client_secret
+/// Save raw credential JSON for a provider, with optional client_id/client_secret.
+/// The JSON is encrypted when a credentials passphrase is configured (see
+/// [`crate::secrets`]).
 pub fn save_credential_raw(
     conn: &Connection,
     provider: &str,
@@ -294,6 +564,13 @@ pub fn save_credential_raw(
     client_id: Option<&str>,
     client_secret: Option<&str>,
 ) -> Result<()> {
+    let sealed;
+    let json_blob = if crate::secrets::is_enabled() {
+        sealed = crate::secrets::encrypt(provider, json_blob)?;
+        sealed.as_str()
+    } else {
+        json_blob
+    };
     conn.execute(
         "INSERT INTO credentials (provider, token_json, client_id, client_secret, last_refreshed) VALUES (?1, ?2, ?3, ?4, strftime('%s','now')) ON CONFLICT(provider) DO UPDATE SET token_json = excluded.token_json, client_id = excluded.client_id, client_secret = excluded.client_secret, last_refreshed = strftime('%s','now')",
         params![provider, json_blob, client_id, client_secret],
@@ -303,7 +580,9 @@ pub fn save_credential_raw(
 
 /// Load raw credential JSON for a provider
 
-/// Load raw credential JSON and client_id/client_secret for a provider
+/// Load raw credential JSON and client_id/client_secret for a provider.
+/// Encrypted JSON is decrypted; a plaintext row is re-saved encrypted when a
+/// credentials passphrase is configured (see [`crate::secrets`]).
 pub fn load_credential_with_client(
     conn: &Connection,
     provider: &str,
@@ -320,12 +599,26 @@ pub fn load_credential_with_client(
             ))
         })
         .optional()?;
-    Ok(row)
+    let Some((stored, client_id, client_secret)) = row else {
+        return Ok(None);
+    };
+    let json = if crate::secrets::is_encrypted(&stored) {
+        crate::secrets::decrypt(provider, &stored)?
+    } else {
+        if crate::secrets::is_enabled() {
+            conn.execute(
+                "UPDATE credentials SET token_json = ?1 WHERE provider = ?2",
+                params![crate::secrets::encrypt(provider, &stored)?, provider],
+            )?;
+            log::info!("Encrypted the stored {} credentials", provider);
+        }
+        stored
+    };
+    Ok(Some((json, client_id, client_secret)))
 }
 
-fn playlist_map_key(provider: &str, playlist_name: &str) -> String {
-    // All providers are uniformly namespaced as "<provider>::<playlist_name>".
-    format!("{}::{}", provider.to_lowercase(), playlist_name)
+fn provider_key(provider: &str) -> String {
+    provider.to_lowercase()
 }
 
 /// Get remote_id for playlist from playlist_map, scoped by provider
@@ -334,11 +627,13 @@ pub fn get_remote_playlist_id(
     provider: &str,
     playlist_name: &str,
 ) -> Result<Option<String>> {
-    let key = playlist_map_key(provider, playlist_name);
-    let mut stmt =
-        conn.prepare("SELECT remote_id FROM playlist_map WHERE playlist_name = ?1 LIMIT 1")?;
+    let mut stmt = conn.prepare(
+        "SELECT remote_id FROM playlist_map WHERE provider = ?1 AND playlist_name = ?2 LIMIT 1",
+    )?;
     let row = stmt
-        .query_row(params![key], |r| r.get::<_, Option<String>>(0))
+        .query_row(params![provider_key(provider), playlist_name], |r| {
+            r.get::<_, Option<String>>(0)
+        })
         .optional()?;
     Ok(row.flatten())
 }
@@ -350,10 +645,9 @@ pub fn upsert_playlist_map(
     playlist_name: &str,
     remote_id: &str,
 ) -> Result<()> {
-    let key = playlist_map_key(provider, playlist_name);
     conn.execute(
-        "INSERT INTO playlist_map (playlist_name, remote_id, last_synced_at) VALUES (?1, ?2, strftime('%s','now')) ON CONFLICT(playlist_name) DO UPDATE SET remote_id = excluded.remote_id, last_synced_at = strftime('%s','now')",
-        params![key, remote_id],
+        "INSERT INTO playlist_map (provider, playlist_name, remote_id, last_synced_at) VALUES (?1, ?2, ?3, strftime('%s','now')) ON CONFLICT(provider, playlist_name) DO UPDATE SET remote_id = excluded.remote_id, last_synced_at = strftime('%s','now')",
+        params![provider_key(provider), playlist_name, remote_id],
     )?;
     Ok(())
 }
@@ -364,11 +658,13 @@ pub fn get_remote_display_name(
     provider: &str,
     playlist_name: &str,
 ) -> Result<Option<String>> {
-    let key = playlist_map_key(provider, playlist_name);
-    let mut stmt = conn
-        .prepare("SELECT remote_display_name FROM playlist_map WHERE playlist_name = ?1 LIMIT 1")?;
+    let mut stmt = conn.prepare(
+        "SELECT remote_display_name FROM playlist_map WHERE provider = ?1 AND playlist_name = ?2 LIMIT 1",
+    )?;
     let row = stmt
-        .query_row(params![key], |r| r.get::<_, Option<String>>(0))
+        .query_row(params![provider_key(provider), playlist_name], |r| {
+            r.get::<_, Option<String>>(0)
+        })
         .optional()?;
     Ok(row.flatten())
 }
@@ -380,10 +676,9 @@ pub fn set_remote_display_name(
     playlist_name: &str,
     display_name: &str,
 ) -> Result<()> {
-    let key = playlist_map_key(provider, playlist_name);
     conn.execute(
-        "UPDATE playlist_map SET remote_display_name = ?1 WHERE playlist_name = ?2",
-        params![display_name, key],
+        "UPDATE playlist_map SET remote_display_name = ?1 WHERE provider = ?2 AND playlist_name = ?3",
+        params![display_name, provider_key(provider), playlist_name],
     )?;
     Ok(())
 }
@@ -393,11 +688,10 @@ pub fn get_all_remote_ids_for_provider(
     conn: &Connection,
     provider: &str,
 ) -> Result<std::collections::HashSet<String>> {
-    let prefix = format!("{}::", provider.to_lowercase());
     let mut stmt = conn.prepare(
-        "SELECT remote_id FROM playlist_map WHERE playlist_name LIKE ?1 AND remote_id IS NOT NULL",
+        "SELECT remote_id FROM playlist_map WHERE provider = ?1 AND remote_id IS NOT NULL",
     )?;
-    let rows = stmt.query_map(params![format!("{}%", prefix)], |r| r.get::<_, String>(0))?;
+    let rows = stmt.query_map(params![provider_key(provider)], |r| r.get::<_, String>(0))?;
     let mut ids = std::collections::HashSet::new();
     for row in rows {
         ids.insert(row?);
@@ -405,13 +699,14 @@ pub fn get_all_remote_ids_for_provider(
     Ok(ids)
 }
 
-/// Delete a playlist_map entry by playlist_name, scoped by provider
+/// Delete a playlist_map entry by playlist_name, scoped by provider, along
+/// with its remote snapshot.
 pub fn delete_playlist_map(conn: &Connection, provider: &str, playlist_name: &str) -> Result<()> {
-    let key = playlist_map_key(provider, playlist_name);
     conn.execute(
-        "DELETE FROM playlist_map WHERE playlist_name = ?1",
-        params![key],
+        "DELETE FROM playlist_map WHERE provider = ?1 AND playlist_name = ?2",
+        params![provider_key(provider), playlist_name],
     )?;
+    delete_remote_snapshot(conn, provider, playlist_name)?;
     Ok(())
 }
 
@@ -421,32 +716,54 @@ pub fn list_playlist_map_entries(
     conn: &Connection,
     provider_filter: Option<&str>,
 ) -> Result<Vec<(String, String, Option<String>, Option<String>, Option<i64>)>> {
-    let like_prefix = match provider_filter {
-        Some(p) => format!("{}::%", p.to_lowercase()),
-        None => "%".to_string(),
-    };
     let mut stmt = conn.prepare(
-        "SELECT playlist_name, remote_id, remote_display_name, last_synced_at \
-         FROM playlist_map WHERE playlist_name LIKE ?1 ORDER BY playlist_name",
+        "SELECT provider, playlist_name, remote_id, remote_display_name, last_synced_at \
+         FROM playlist_map WHERE ?1 IS NULL OR provider = ?1 \
+         ORDER BY provider, playlist_name",
     )?;
-    let rows = stmt.query_map(params![like_prefix], |r| {
+    let rows = stmt.query_map(params![provider_filter.map(provider_key)], |r| {
         Ok((
             r.get::<_, String>(0)?,
-            r.get::<_, Option<String>>(1)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, Option<String>>(2)?,
+            r.get::<_, Option<String>>(3)?,
+            r.get::<_, Option<i64>>(4)?,
+        ))
+    })?;
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// `(provider, playlist_name, remote_id, last_synced_at)` as returned by
+/// [`list_playlist_maps`].
+pub type PlaylistMapRow = (String, String, Option<String>, Option<i64>);
+
+/// List playlist_map rows, optionally filtered to a single provider.  Rows
+/// are sorted by playlist_name and then provider so the output is stable
+/// across runs.
+pub fn list_playlist_maps(
+    conn: &Connection,
+    provider: Option<&str>,
+) -> Result<Vec<PlaylistMapRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT provider, playlist_name, remote_id, last_synced_at \
+         FROM playlist_map WHERE ?1 IS NULL OR provider = ?1 \
+         ORDER BY playlist_name, provider",
+    )?;
+    let rows = stmt.query_map(params![provider.map(provider_key)], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
             r.get::<_, Option<String>>(2)?,
             r.get::<_, Option<i64>>(3)?,
         ))
     })?;
     let mut out = Vec::new();
     for row in rows {
-        let (key, remote_id, display_name, synced_at) = row?;
-        // key format is "<provider>::<playlist_name>"
-        let (prov, pl) = if let Some(sep) = key.find("::") {
-            (key[..sep].to_string(), key[sep + 2..].to_string())
-        } else {
-            (String::new(), key.clone())
-        };
-        out.push((prov, pl, remote_id, display_name, synced_at));
+        out.push(row?);
     }
     Ok(out)
 }
@@ -554,6 +871,83 @@ pub fn upsert_remote_playlist_contents_cache(
     Ok(())
 }
 
+/// Last known remote contents of a playlist, see the `remote_snapshot` table.
+#[derive(Debug, Clone)]
+pub struct RemoteSnapshot {
+    pub remote_id: String,
+    pub uris: Vec<String>,
+    /// Unix time of the live listing the snapshot is based on.
+    pub listed_at: i64,
+}
+
+pub fn get_remote_snapshot(
+    conn: &Connection,
+    provider: &str,
+    playlist_name: &str,
+) -> Result<Option<RemoteSnapshot>> {
+    let row = conn
+        .query_row(
+            "SELECT remote_id, uris, listed_at FROM remote_snapshot \
+             WHERE provider = ?1 AND playlist_name = ?2",
+            params![provider, playlist_name],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, i64>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    Ok(row.map(|(remote_id, uris, listed_at)| RemoteSnapshot {
+        remote_id,
+        uris: serde_json::from_str(&uris).unwrap_or_default(),
+        listed_at,
+    }))
+}
+
+/// Store the remote contents of a playlist.  `listed_at` is the time of the
+/// live listing they derive from (now for a fresh listing, the previous
+/// snapshot's value when applying our own changes to it).
+pub fn upsert_remote_snapshot(
+    conn: &Connection,
+    provider: &str,
+    playlist_name: &str,
+    remote_id: &str,
+    uris: &[String],
+    listed_at: i64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO remote_snapshot (provider, playlist_name, remote_id, uris, listed_at, updated_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+         ON CONFLICT(provider, playlist_name) DO UPDATE SET \
+         remote_id = excluded.remote_id, uris = excluded.uris, \
+         listed_at = excluded.listed_at, updated_at = excluded.updated_at",
+        params![
+            provider,
+            playlist_name,
+            remote_id,
+            serde_json::to_string(uris)?,
+            listed_at,
+            Utc::now().timestamp()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Forget a playlist's snapshot so the next reconcile lists it live.
+pub fn delete_remote_snapshot(
+    conn: &Connection,
+    provider: &str,
+    playlist_name: &str,
+) -> Result<()> {
+    conn.execute(
+        "DELETE FROM remote_snapshot WHERE provider = ?1 AND playlist_name = ?2",
+        params![provider, playlist_name],
+    )?;
+    Ok(())
+}
+
 /// Migrate a playlist cache entry from one logical name to another, scoped by
 /// provider.  When a playlist folder is renamed, the worker performs a similar
 /// migration on `playlist_map` so that remote IDs aren't duplicated; the cache
@@ -576,16 +970,6 @@ pub fn migrate_playlist_cache(
     Ok(())
 }
 
-fn track_cache_key(provider: &str, local_path: &str) -> String {
-    // All providers are now namespaced in the cache key.  In the very first
-    // releases only Spotify existed and we stored the raw local path; that
-    // caused a mismatch when additional providers were added.  We still need
-    // to support old databases, so `run_migrations` will rewrite any
-    // existing rows that lack a prefix.  Once the migration has run the
-    // helper can consistently prepend the provider name.
-    format!("{}::{}", provider.to_lowercase(), local_path)
-}
-
 /// Lookup a track cache entry by local path, scoped by provider
 pub fn get_track_cache_by_local(
     conn: &Connection,
@@ -593,12 +977,11 @@ pub fn get_track_cache_by_local(
     local_path: &str,
 ) -> Result<Option<(Option<String>, Option<String>, i64)>> {
     // Returns (isrc, remote_id, resolved_at) if an entry exists.
-    let key = track_cache_key(provider, local_path);
     let mut stmt = conn.prepare(
-        "SELECT isrc, remote_id, COALESCE(resolved_at,0) FROM track_cache WHERE local_path = ?1 LIMIT 1",
+        "SELECT isrc, remote_id, COALESCE(resolved_at,0) FROM track_cache WHERE provider = ?1 AND local_path = ?2 LIMIT 1",
     )?;
     let row = stmt
-        .query_row(params![key], |r| {
+        .query_row(params![provider_key(provider), local_path], |r| {
             Ok((
                 r.get::<_, Option<String>>(0)?,
                 r.get::<_, Option<String>>(1)?,
@@ -609,7 +992,9 @@ pub fn get_track_cache_by_local(
     Ok(row)
 }
 
-/// Upsert track cache entry: (provider, local_path) -> (isrc, remote_id)
+/// Upsert track cache entry: (provider, local_path) -> (isrc, remote_id).
+/// This clears any negative entry recorded by [`mark_track_unresolved`], and
+/// the ISRC check of [`mark_track_isrc_checked`] when `remote_id` changes.
 pub fn upsert_track_cache(
     conn: &Connection,
     provider: &str,
@@ -617,14 +1002,440 @@ pub fn upsert_track_cache(
     isrc: Option<&str>,
     remote_id: Option<&str>,
 ) -> Result<()> {
-    let key = track_cache_key(provider, local_path);
     conn.execute(
-        "INSERT INTO track_cache (isrc, local_path, remote_id, resolved_at) VALUES (?1, ?2, ?3, strftime('%s','now')) ON CONFLICT(local_path) DO UPDATE SET isrc = excluded.isrc, remote_id = excluded.remote_id, resolved_at = strftime('%s','now')",
-        params![isrc, key, remote_id],
+        "INSERT INTO track_cache (provider, local_path, isrc, remote_id, resolved_at, not_found_at) VALUES (?1, ?2, ?3, ?4, strftime('%s','now'), NULL) ON CONFLICT(provider, local_path) DO UPDATE SET isrc = excluded.isrc, remote_id = excluded.remote_id, resolved_at = strftime('%s','now'), not_found_at = NULL, isrc_checked_at = CASE WHEN track_cache.remote_id IS excluded.remote_id THEN track_cache.isrc_checked_at END",
+        params![provider_key(provider), local_path, isrc, remote_id],
+    )?;
+    Ok(())
+}
+
+/// True if the cached remote id of `local_path` on `provider` has already
+/// been compared with the file's ISRC.
+pub fn track_isrc_checked(conn: &Connection, provider: &str, local_path: &str) -> Result<bool> {
+    let checked = conn
+        .query_row(
+            "SELECT isrc_checked_at IS NOT NULL FROM track_cache WHERE provider = ?1 AND local_path = ?2",
+            params![provider_key(provider), local_path],
+            |r| r.get::<_, bool>(0),
+        )
+        .optional()?;
+    Ok(checked.unwrap_or(false))
+}
+
+/// Record that the cached remote id of `local_path` on `provider` matches
+/// the file's ISRC (or cannot be compared with it).
+pub fn mark_track_isrc_checked(conn: &Connection, provider: &str, local_path: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE track_cache SET isrc_checked_at = strftime('%s','now') WHERE provider = ?1 AND local_path = ?2",
+        params![provider_key(provider), local_path],
+    )?;
+    Ok(())
+}
+
+/// The ISRC read earlier from the tags of `local_path` (for any provider),
+/// if the file still has the given mtime and size: `Some(None)` means it
+/// had none, `None` that the file must be read again.
+pub fn cached_file_isrc(
+    conn: &Connection,
+    local_path: &str,
+    mtime_ns: i64,
+    size: i64,
+) -> Result<Option<Option<String>>> {
+    Ok(conn
+        .query_row(
+            "SELECT file_isrc FROM track_cache WHERE local_path = ?1 AND file_mtime_ns = ?2 \
+             AND file_size = ?3 LIMIT 1",
+            params![local_path, mtime_ns, size],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// Remember the ISRC read from the tags of `local_path` at the given mtime
+/// and size (see [`cached_file_isrc`]).
+pub fn store_file_isrc(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+    mtime_ns: i64,
+    size: i64,
+    isrc: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO track_cache (provider, local_path, file_mtime_ns, file_size, file_isrc) \
+         VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(provider, local_path) DO UPDATE SET \
+         file_mtime_ns = excluded.file_mtime_ns, file_size = excluded.file_size, \
+         file_isrc = excluded.file_isrc",
+        params![provider_key(provider), local_path, mtime_ns, size, isrc],
+    )?;
+    Ok(())
+}
+
+/// Remember the ISRC `provider` reported for the track `local_path` was
+/// matched to, for the other providers (see [`shared_remote_isrc`]).
+pub fn store_remote_isrc(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+    isrc: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO track_cache (provider, local_path, remote_isrc) VALUES (?1, ?2, ?3) \
+         ON CONFLICT(provider, local_path) DO UPDATE SET remote_isrc = excluded.remote_isrc",
+        params![provider_key(provider), local_path, isrc],
+    )?;
+    Ok(())
+}
+
+/// The ISRC any provider reported for its match of `local_path` (the most
+/// recently resolved one if several did), so a provider can search by ISRC
+/// for files without one in their tags.
+pub fn shared_remote_isrc(conn: &Connection, local_path: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT remote_isrc FROM track_cache WHERE local_path = ?1 AND remote_isrc IS NOT NULL \
+             ORDER BY COALESCE(resolved_at, 0) DESC LIMIT 1",
+            params![local_path],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// Resolved track cache entries of `provider` as `(local_path, isrc,
+/// remote_id)`, ordered by path.
+pub fn resolved_track_cache_entries(
+    conn: &Connection,
+    provider: &str,
+) -> Result<Vec<(String, Option<String>, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT local_path, isrc, remote_id FROM track_cache WHERE provider = ?1 AND remote_id IS NOT NULL ORDER BY local_path",
+    )?;
+    let rows = stmt
+        .query_map(params![provider_key(provider)], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Forget the track cache entry (positive or negative) of `local_path` on
+/// `provider` so it is resolved again.  Returns whether there was one.
+pub fn delete_track_cache_entry(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+) -> Result<bool> {
+    let n = conn.execute(
+        "DELETE FROM track_cache WHERE provider = ?1 AND local_path = ?2",
+        params![provider_key(provider), local_path],
+    )?;
+    Ok(n > 0)
+}
+
+/// Lookup the pinned remote URI for `local_path` on `provider`, if any.
+pub fn get_override(conn: &Connection, provider: &str, local_path: &str) -> Result<Option<String>> {
+    let uri = conn
+        .query_row(
+            "SELECT remote_uri FROM overrides WHERE provider = ?1 AND local_path = ?2",
+            params![provider_key(provider), local_path],
+            |r| r.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(uri)
+}
+
+/// Pin `local_path` to `remote_uri` on `provider`, replacing any earlier
+/// override.  The provider's playlist cache is cleared so playlists that
+/// contain the file are re-read on the next run.
+pub fn set_override(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+    remote_uri: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO overrides (provider, local_path, remote_uri, created_at) VALUES (?1, ?2, ?3, strftime('%s','now')) ON CONFLICT(provider, local_path) DO UPDATE SET remote_uri = excluded.remote_uri, created_at = excluded.created_at",
+        params![provider_key(provider), local_path, remote_uri],
+    )?;
+    invalidate_provider_playlist_cache(conn, provider)?;
+    Ok(())
+}
+
+/// Delete the override for `local_path` on `provider`.  Returns false when
+/// there was none.
+pub fn remove_override(conn: &Connection, provider: &str, local_path: &str) -> Result<bool> {
+    let n = conn.execute(
+        "DELETE FROM overrides WHERE provider = ?1 AND local_path = ?2",
+        params![provider_key(provider), local_path],
+    )?;
+    if n > 0 {
+        invalidate_provider_playlist_cache(conn, provider)?;
+    }
+    Ok(n > 0)
+}
+
+/// List overrides as `(provider, local_path, remote_uri)`, optionally for
+/// a single provider.
+pub fn list_overrides(
+    conn: &Connection,
+    provider: Option<&str>,
+) -> Result<Vec<(String, String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT provider, local_path, remote_uri FROM overrides WHERE ?1 IS NULL OR provider = ?1 ORDER BY provider, local_path",
+    )?;
+    let rows = stmt
+        .query_map(params![provider.map(provider_key)], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Clear the playlist cache of `provider`, so cached playlist URIs are
+/// rebuilt from the track cache on the next run.
+pub fn invalidate_provider_playlist_cache(conn: &Connection, provider: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM playlist_cache WHERE lower(provider_name) = ?1",
+        params![provider_key(provider)],
+    )?;
+    Ok(())
+}
+
+/// Copy the resolved track cache entries of `from_local` (for every provider)
+/// to `to_local`, so a file that was moved keeps its remote match without a
+/// fresh search.  Existing entries for `to_local` are overwritten; negative
+/// entries are not copied.  Returns the number of rows written.
+pub fn copy_track_cache_entries(
+    conn: &Connection,
+    from_local: &str,
+    to_local: &str,
+) -> Result<usize> {
+    let n = conn.execute(
+        "INSERT INTO track_cache (provider, local_path, isrc, remote_id, resolved_at, not_found_at) SELECT provider, ?2, isrc, remote_id, resolved_at, NULL FROM track_cache WHERE local_path = ?1 AND remote_id IS NOT NULL ON CONFLICT(provider, local_path) DO UPDATE SET isrc = excluded.isrc, remote_id = excluded.remote_id, resolved_at = excluded.resolved_at, not_found_at = NULL",
+        params![from_local, to_local],
+    )?;
+    Ok(n)
+}
+
+/// Record that `local_path` could not be resolved on `provider` (negative
+/// cache entry), keeping any ISRC that was extracted from the file.
+pub fn mark_track_unresolved(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+    isrc: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO track_cache (provider, local_path, isrc, remote_id, resolved_at, not_found_at) VALUES (?1, ?2, ?3, NULL, strftime('%s','now'), strftime('%s','now')) ON CONFLICT(provider, local_path) DO UPDATE SET isrc = COALESCE(excluded.isrc, track_cache.isrc), remote_id = NULL, resolved_at = strftime('%s','now'), not_found_at = strftime('%s','now')",
+        params![provider_key(provider), local_path, isrc],
     )?;
     Ok(())
 }
 
+/// Return when `local_path` was last recorded as unresolved on `provider`,
+/// or None if there is no negative entry for it.
+pub fn get_track_unresolved_at(
+    conn: &Connection,
+    provider: &str,
+    local_path: &str,
+) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT not_found_at FROM track_cache WHERE provider = ?1 AND local_path = ?2 LIMIT 1",
+    )?;
+    let row = stmt
+        .query_row(params![provider_key(provider), local_path], |r| {
+            r.get::<_, Option<i64>>(0)
+        })
+        .optional()?;
+    Ok(row.flatten())
+}
+
+/// Delete negative track cache entries (optionally for one provider) so the
+/// next worker run retries resolving those files.  Returns the number of
+/// rows removed.
+pub fn prune_unresolved_track_cache(conn: &Connection, provider: Option<&str>) -> Result<usize> {
+    let n = match provider {
+        Some(p) => conn.execute(
+            "DELETE FROM track_cache WHERE not_found_at IS NOT NULL AND provider = ?1",
+            params![provider_key(p)],
+        )?,
+        None => conn.execute("DELETE FROM track_cache WHERE not_found_at IS NOT NULL", [])?,
+    };
+    Ok(n)
+}
+
+/// Rows cleared by [`reset_remote_state`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RemoteStateReset {
+    /// `remote_snapshot` rows deleted.
+    pub snapshots: usize,
+    /// `track_cache` rows whose remote match (or negative result) was dropped.
+    pub track_matches: usize,
+}
+
+/// Forget what is known about the remote side (optionally of one provider)
+/// so the next sync relists and re-resolves everything: remote snapshots,
+/// the cached remote contents, item ids and playlist lists, the local
+/// playlist cache and the track cache matches.  ISRCs read from the files,
+/// overrides, credentials and the playlist mappings are kept.
+pub fn reset_remote_state(
+    conn: &mut Connection,
+    provider: Option<&str>,
+) -> Result<RemoteStateReset> {
+    let provider = provider.map(provider_key);
+    let tx = conn.transaction()?;
+    let snapshots = tx.execute(
+        "DELETE FROM remote_snapshot WHERE ?1 IS NULL OR provider = ?1",
+        params![provider],
+    )?;
+    tx.execute(
+        "UPDATE playlist_map SET remote_snapshot_id = NULL WHERE ?1 IS NULL OR provider = ?1",
+        params![provider],
+    )?;
+    for table in [
+        "remote_playlist_contents_cache",
+        "remote_playlist_item_id_cache",
+        "provider_playlist_list_cache",
+    ] {
+        tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE ?1 IS NULL OR lower(provider_name) = ?1",
+                table
+            ),
+            params![provider],
+        )?;
+    }
+    tx.execute(
+        "DELETE FROM playlist_cache WHERE ?1 IS NULL OR lower(provider_name) = ?1",
+        params![provider],
+    )?;
+    let track_matches = tx.execute(
+        "UPDATE track_cache SET remote_id = NULL, resolved_at = NULL, not_found_at = NULL, \
+         isrc_checked_at = NULL \
+         WHERE (?1 IS NULL OR provider = ?1) AND (remote_id IS NOT NULL OR not_found_at IS NOT NULL)",
+        params![provider],
+    )?;
+    // Rows that held nothing but the match are empty now; the ISRCs read
+    // from local files stay cached.
+    tx.execute(
+        "DELETE FROM track_cache WHERE (?1 IS NULL OR provider = ?1) AND isrc IS NULL \
+         AND remote_id IS NULL AND file_mtime_ns IS NULL",
+        params![provider],
+    )?;
+    tx.commit()?;
+    Ok(RemoteStateReset {
+        snapshots,
+        track_matches,
+    })
+}
+
+/// Track cache row counts for one provider, as returned by
+/// [`track_cache_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct TrackCacheStats {
+    pub provider: String,
+    pub total: i64,
+    /// Rows with a resolved `remote_id`.
+    pub resolved: i64,
+    /// Rows with an ISRC but no `remote_id`.
+    pub isrc_only: i64,
+    /// Rows with neither (usually negative entries waiting to be retried).
+    pub unresolved: i64,
+}
+
+/// Count track cache rows by resolution state, one entry per provider,
+/// sorted by provider.
+pub fn track_cache_stats(conn: &Connection) -> Result<Vec<TrackCacheStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT provider, COUNT(*), \
+         SUM(remote_id IS NOT NULL), \
+         SUM(remote_id IS NULL AND isrc IS NOT NULL), \
+         SUM(remote_id IS NULL AND isrc IS NULL) \
+         FROM track_cache GROUP BY provider ORDER BY provider",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok(TrackCacheStats {
+            provider: r.get(0)?,
+            total: r.get(1)?,
+            resolved: r.get(2)?,
+            isrc_only: r.get(3)?,
+            unresolved: r.get(4)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Number of distinct playlist keys in playlist_map (across providers).
+pub fn count_mapped_playlists(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(DISTINCT playlist_name) FROM playlist_map",
+        [],
+        |r| r.get(0),
+    )?)
+}
+
+/// Whether any provider has a playlist_map row for `playlist_name`.
+pub fn is_playlist_mapped(conn: &Connection, playlist_name: &str) -> Result<bool> {
+    Ok(conn
+        .prepare("SELECT 1 FROM playlist_map WHERE playlist_name = ?1")?
+        .exists(params![playlist_name])?)
+}
+
+/// Read a value from the `sync_state` key/value table.
+pub fn get_sync_state(conn: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM sync_state WHERE key = ?1",
+            params![key],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// Insert or replace a value in the `sync_state` key/value table.
+pub fn set_sync_state(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO sync_state (key, value) VALUES (?1, ?2) \
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Add each `(name, labels, delta)` to its total in the `metrics` table.
+pub fn add_metric_counters(conn: &Connection, deltas: &[(String, String, u64)]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    for (name, labels, delta) in deltas {
+        tx.execute(
+            "INSERT INTO metrics (name, labels, value) VALUES (?1, ?2, ?3) \
+             ON CONFLICT(name, labels) DO UPDATE SET value = value + excluded.value",
+            params![name, labels, *delta as i64],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Every stored metric total as (name, labels, value), ordered by name and labels.
+pub fn get_metric_counters(conn: &Connection) -> Result<Vec<(String, String, i64)>> {
+    let mut stmt = conn.prepare("SELECT name, labels, value FROM metrics ORDER BY name, labels")?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Number of queued events that are neither synced nor marked failed.
+pub fn count_pending_events(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM event_queue WHERE is_synced = 0 AND failed_at IS NULL",
+        [],
+        |r| r.get(0),
+    )?)
+}
+
 /// Lookup a track cache entry by remote URI. Returns (isrc, local_path, resolved_at)
 /// if an entry exists. This is used when we receive an event originating from
 /// another provider; we convert it back to a local file path so that the target
@@ -658,7 +1469,10 @@ pub fn try_acquire_playlist_lock(
 ) -> Result<bool> {
     let now = Utc::now().timestamp();
     let expires_at = now + ttl_seconds;
-    let tx = conn.transaction()?;
+    // IMMEDIATE takes the write lock up front: a deferred read-then-write
+    // transaction fails with SQLITE_BUSY (ignoring busy_timeout) when another
+    // provider of the same run grabs its lock concurrently.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
     // check existing lock
     let row_opt = {
         let mut stmt = tx.prepare(
@@ -691,6 +1505,22 @@ pub fn try_acquire_playlist_lock(
     Ok(true)
 }
 
+/// Extend a processing lock held by `worker_id` to `ttl_seconds` from now.
+/// Returns false if the lock is no longer ours (expired and taken over).
+pub fn renew_playlist_lock(
+    conn: &Connection,
+    playlist_name: &str,
+    worker_id: &str,
+    ttl_seconds: i64,
+) -> Result<bool> {
+    let expires_at = Utc::now().timestamp() + ttl_seconds;
+    let n = conn.execute(
+        "UPDATE processing_locks SET expires_at = ?3 WHERE playlist_name = ?1 AND worker_id = ?2",
+        params![playlist_name, worker_id, expires_at],
+    )?;
+    Ok(n > 0)
+}
+
 /// Release a processing lock (only if owned by this worker_id)
 pub fn release_playlist_lock(
     conn: &mut Connection,
@@ -3,9 +3,11 @@ pub mod api;
 pub mod collapse;
 pub mod config;
 pub mod db;
+pub mod metrics;
 pub mod models;
 pub mod playlist;
 pub mod retry;
+pub mod secrets;
 pub mod util;
 pub mod watcher;
 
@@ -0,0 +1,189 @@
+//! Prometheus-style sync counters.
+//!
+//! The worker usually runs as its own process, so counters are collected in
+//! memory with [`inc`] and added to the `metrics` table by [`flush`] at the
+//! end of a run.  The watcher serves the stored totals, plus the live
+//! `events_pending` gauge, in the text exposition format on
+//! `metrics_listen` (see [`serve`]).
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Counters not yet flushed, keyed by (name, rendered labels).
+static PENDING: Lazy<Mutex<BTreeMap<(String, String), u64>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// The exposed counters with their help text, and whether their samples have
+/// labels (unlabelled counters are exposed as 0 before their first increment).
+const COUNTERS: [(&str, &str, bool); 4] = [
+    (
+        "tracks_resolved_total",
+        "Local tracks matched to a provider track by ISRC or metadata search, by provider.",
+        true,
+    ),
+    (
+        "tracks_unresolved_total",
+        "Local tracks no provider track was found for, by provider.",
+        true,
+    ),
+    (
+        "provider_requests_total",
+        "HTTP requests sent to providers, by provider and response status.",
+        true,
+    ),
+    (
+        "sync_errors_total",
+        "Playlists whose queued events failed to sync in a worker run.",
+        false,
+    ),
+];
+
+/// Render `labels` as `key="value",...`, escaping values as the exposition
+/// format requires.
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", k, v)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Add one to the counter `name` with `labels`.
+pub fn inc(name: &str, labels: &[(&str, &str)]) {
+    let key = (name.to_string(), render_labels(labels));
+    *PENDING.lock().unwrap().entry(key).or_insert(0) += 1;
+}
+
+/// Count one provider HTTP request answered with `status` ("error" when no
+/// response arrived).
+pub fn provider_request(provider: &str, status: &str) {
+    inc(
+        "provider_requests_total",
+        &[("provider", provider), ("status", status)],
+    );
+}
+
+/// Add the counters collected since the last flush to the `metrics` table.
+pub fn flush(conn: &rusqlite::Connection) -> Result<()> {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let deltas: Vec<(String, String, u64)> = pending
+        .into_iter()
+        .map(|((name, labels), n)| (name, labels, n))
+        .collect();
+    crate::db::add_metric_counters(conn, &deltas)
+}
+
+/// The stored counters and the `events_pending` gauge in the Prometheus
+/// text exposition format.
+pub fn render(conn: &rusqlite::Connection) -> Result<String> {
+    let stored = crate::db::get_metric_counters(conn)?;
+    let mut out = String::new();
+    for (name, help, labelled) in COUNTERS {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} counter\n",
+            name, help, name
+        ));
+        let mut any = false;
+        for (_, labels, value) in stored.iter().filter(|(n, _, _)| n == name) {
+            any = true;
+            if labels.is_empty() {
+                out.push_str(&format!("{} {}\n", name, value));
+            } else {
+                out.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+            }
+        }
+        if !any && !labelled {
+            out.push_str(&format!("{} 0\n", name));
+        }
+    }
+    out.push_str(&format!(
+        "# HELP events_pending Queued events not yet synced or failed.\n\
+         # TYPE events_pending gauge\nevents_pending {}\n",
+        crate::db::count_pending_events(conn)?
+    ));
+    Ok(out)
+}
+
+/// Answer one HTTP request: `GET /metrics` gets [`render`], anything else
+/// a 404.
+fn respond(stream: &mut std::net::TcpStream, db_pool: &crate::db::DbPool) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // Read up to the end of the headers; only the request line matters.
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < 8192 {
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let request = String::from_utf8_lossy(&buf);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if request.starts_with("GET ") && path == "/metrics" {
+        let rendered = db_pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|conn| render(&conn));
+        match rendered {
+            Ok(body) => ("200 OK", body),
+            Err(e) => ("500 Internal Server Error", format!("{:#}\n", e)),
+        }
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Serve `/metrics` on `addr` from a background thread until `shutdown` is
+/// set.  Fails right away when the address cannot be bound.
+pub fn serve(
+    addr: &str,
+    db_pool: crate::db::DbPool,
+    shutdown: Arc<AtomicBool>,
+) -> Result<std::thread::JoinHandle<()>> {
+    let listener = std::net::TcpListener::bind(addr)
+        .with_context(|| format!("binding metrics_listen address {}", addr))?;
+    listener.set_nonblocking(true)?;
+    log::info!("Serving metrics on http://{}/metrics", addr);
+    Ok(std::thread::spawn(move || {
+        while !shutdown.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((mut stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    if let Err(e) = respond(&mut stream, &db_pool) {
+                        log::debug!("metrics request failed: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    log::warn!("metrics listener error: {}", e);
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+            }
+        }
+    }))
+}
@@ -20,3 +20,14 @@ pub struct Event {
     pub extra: Option<String>,
     pub is_synced: bool,
 }
+
+/// An event the worker gave up on after `max_retries_on_error` failed
+/// attempts.  It stays in `event_queue` (unsynced) until requeued or cleared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedEvent {
+    pub event: Event,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    /// Unix timestamp (seconds) when the event was marked failed.
+    pub failed_at: i64,
+}
@@ -1,33 +1,36 @@
+use crate::util::{path_matches_extensions, IgnoreMatcher};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-/// Return true if the given path's extension matches any of the configured
-/// file_extensions patterns ("*.mp3", "mp3", ".mp3"), case-insensitive.
-fn path_matches_extensions(path: &Path, exts: &[String]) -> bool {
-    let ext_os = match path.extension() {
-        Some(e) => e,
-        None => return false,
-    };
-    let ext = match ext_os.to_str() {
-        Some(s) => s.to_ascii_lowercase(),
-        None => return false,
-    };
-    for pat in exts {
-        let mut p = pat.trim();
-        if p.is_empty() {
-            continue;
-        }
-        // strip common prefixes: "*." or "."
-        if let Some(stripped) = p.strip_prefix("*.") {
-            p = stripped;
-        } else if let Some(stripped) = p.strip_prefix('.') {
-            p = stripped;
-        }
-        if ext == p.to_ascii_lowercase() {
-            return true;
+/// Write `playlist_path` through a temp file in the same directory that is
+/// renamed over it once `write` has finished, so a crash never leaves a
+/// truncated playlist behind.  An existing file's permissions are kept.
+pub(crate) fn write_atomically<F>(playlist_path: &Path, write: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>,
+{
+    let file_name = playlist_path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .ok_or_else(|| anyhow::anyhow!("invalid playlist path {:?}", playlist_path))?;
+    let tmp_path =
+        playlist_path.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()));
+    let result = (|| -> anyhow::Result<()> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+        write(&mut out)?;
+        let file = out.into_inner().map_err(|e| e.into_error())?;
+        if let Ok(md) = std::fs::metadata(playlist_path) {
+            file.set_permissions(md.permissions())?;
         }
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, playlist_path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
     }
-    false
+    result
 }
 
 /// Write a flat .m3u playlist for folder: all matching media files recursively.
@@ -35,88 +38,511 @@ fn path_matches_extensions(path: &Path, exts: &[String]) -> bool {
 /// Behavior is aligned with the original shell script implementation:
 /// - Only files whose extensions match `file_extensions` are included.
 /// - Files are flattened from the subtree rooted at `target_folder`.
-/// - Playlist uses M3U with `#EXTM3U` header and `#EXTINF` metadata lines.
+/// - Playlist uses M3U with `#EXTM3U` header and `#EXTINF` metadata lines
+///   carrying the track duration and `Artist - Title` from its tags (or
+///   `-1` and the file name when the file cannot be read).
 /// - Paths inside the playlist are relative to `target_folder`.
 pub fn write_flat_playlist(
     target_folder: &Path,
     playlist_path: &Path,
     order_mode: &str,
     file_extensions: &[String],
+) -> anyhow::Result<()> {
+    write_flat_playlist_filtered(
+        target_folder,
+        playlist_path,
+        order_mode,
+        file_extensions,
+        &IgnoreMatcher::default(),
+    )
+}
+
+/// Like [`write_flat_playlist`], leaving out files and folders matched by
+/// `ignore` (the `ignore_patterns` config option).
+pub fn write_flat_playlist_filtered(
+    target_folder: &Path,
+    playlist_path: &Path,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> anyhow::Result<()> {
+    if !target_folder.is_dir() {
+        return Ok(());
+    }
+    let files = collect_flat_tracks(
+        target_folder,
+        playlist_path,
+        order_mode,
+        file_extensions,
+        ignore,
+    );
+    write_atomically(playlist_path, |file| {
+        // M3U header
+        writeln!(file, "#EXTM3U")?;
+
+        for p in files.iter() {
+            let (duration, title) = crate::util::extinf_for_path(p);
+
+            let relpath = pathdiff::diff_paths(p, target_folder).unwrap_or_else(|| p.clone());
+
+            writeln!(file, "#EXTINF:{},{}", duration, title)?;
+            writeln!(file, "{}", relpath.display())?;
+        }
+        Ok(())
+    })
+}
+
+/// Write a flat playlist in `format` ("m3u", "xspf" or "pls", see the
+/// `playlist_format` config option).
+pub fn write_flat_playlist_as(
+    format: &str,
+    target_folder: &Path,
+    playlist_path: &Path,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> anyhow::Result<()> {
+    match format.to_ascii_lowercase().as_str() {
+        "xspf" => write_xspf_playlist(
+            target_folder,
+            playlist_path,
+            order_mode,
+            file_extensions,
+            ignore,
+        ),
+        "pls" => write_pls_playlist(
+            target_folder,
+            playlist_path,
+            order_mode,
+            file_extensions,
+            ignore,
+        ),
+        _ => write_flat_playlist_filtered(
+            target_folder,
+            playlist_path,
+            order_mode,
+            file_extensions,
+            ignore,
+        ),
+    }
+}
+
+/// Write the same tracks as [`write_flat_playlist_filtered`] as an XSPF
+/// playlist.  Each `<track>` carries a `<location>` (the path relative to
+/// `target_folder`, percent-encoded as a URI reference) and, when the file
+/// is tagged, `<title>`, `<creator>`, `<album>` and `<duration>` (ms).
+pub fn write_xspf_playlist(
+    target_folder: &Path,
+    playlist_path: &Path,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> anyhow::Result<()> {
+    if !target_folder.is_dir() {
+        return Ok(());
+    }
+
+    let files = collect_flat_tracks(
+        target_folder,
+        playlist_path,
+        order_mode,
+        file_extensions,
+        ignore,
+    );
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    if let Some(name) = target_folder.file_name().map(|s| s.to_string_lossy()) {
+        out.push_str(&format!("  <title>{}</title>\n", xml_escape(&name)));
+    }
+    out.push_str("  <trackList>\n");
+    for p in files.iter() {
+        let relpath = pathdiff::diff_paths(p, target_folder).unwrap_or_else(|| p.clone());
+        out.push_str("    <track>\n");
+        out.push_str(&format!(
+            "      <location>{}</location>\n",
+            xml_escape(&path_to_uri_reference(&relpath))
+        ));
+        let meta = crate::util::extract_artist_title_from_path(p).unwrap_or_default();
+        for (elem, value) in [
+            ("title", meta.title),
+            ("creator", meta.artist),
+            ("album", meta.album),
+        ] {
+            if let Some(v) = value {
+                out.push_str(&format!("      <{0}>{1}</{0}>\n", elem, xml_escape(&v)));
+            }
+        }
+        if let Some(ms) = crate::util::duration_ms_from_path(p) {
+            out.push_str(&format!("      <duration>{}</duration>\n", ms));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+
+    write_atomically(playlist_path, |file| file.write_all(out.as_bytes()))
+}
+
+/// Write the same tracks as [`write_flat_playlist_filtered`] as a PLS
+/// (version 2) playlist: `FileN` holds the path relative to `target_folder`,
+/// `TitleN` and `LengthN` the `#EXTINF` title and duration (`-1` when
+/// unknown).  A folder without tracks yields `NumberOfEntries=0`.
+pub fn write_pls_playlist(
+    target_folder: &Path,
+    playlist_path: &Path,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
 ) -> anyhow::Result<()> {
     if !target_folder.is_dir() {
         return Ok(());
     }
-    use std::io::Write;
+    let files = collect_flat_tracks(
+        target_folder,
+        playlist_path,
+        order_mode,
+        file_extensions,
+        ignore,
+    );
+    write_atomically(playlist_path, |file| {
+        writeln!(file, "[playlist]")?;
+        for (i, p) in files.iter().enumerate() {
+            let n = i + 1;
+            let (duration, title) = crate::util::extinf_for_path(p);
+            let relpath = pathdiff::diff_paths(p, target_folder).unwrap_or_else(|| p.clone());
+            writeln!(file, "File{}={}", n, relpath.display())?;
+            writeln!(file, "Title{}={}", n, title)?;
+            writeln!(file, "Length{}={}", n, duration)?;
+        }
+        writeln!(file, "NumberOfEntries={}", files.len())?;
+        writeln!(file, "Version=2")?;
+        Ok(())
+    })
+}
+
+/// Read the track (or, for linked playlists, child playlist) references of a
+/// local playlist, in order, as written in the file: paths relative to the
+/// playlist's folder or absolute paths.  XSPF files (by extension) yield
+/// their decoded `<location>`s and PLS files their `FileN` values; anything
+/// else is read as M3U.  Bytes that are not valid UTF-8 read as U+FFFD; see
+/// [`crate::util::resolve_lossy_path`] for finding such files on disk.
+pub fn read_playlist_entries(playlist_path: &Path) -> anyhow::Result<Vec<String>> {
+    let content = String::from_utf8_lossy(&std::fs::read(playlist_path)?).into_owned();
+    let ext = playlist_path
+        .extension()
+        .map(|e| e.to_string_lossy())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if ext == "pls" {
+        let mut entries: Vec<(u32, String)> = content
+            .lines()
+            .filter_map(|l| {
+                let (key, value) = l.split_once('=')?;
+                let n = key.trim().strip_prefix("File")?.parse().ok()?;
+                Some((n, value.trim().to_string()))
+            })
+            .collect();
+        entries.sort_by_key(|(n, _)| *n);
+        return Ok(entries.into_iter().map(|(_, v)| v).collect());
+    }
+    if ext != "xspf" {
+        return Ok(content
+            .lines()
+            .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+            .map(str::to_string)
+            .collect());
+    }
+    let mut entries = Vec::new();
+    let mut rest = content.as_str();
+    while let Some(start) = rest.find("<location>") {
+        rest = &rest[start + "<location>".len()..];
+        let Some(end) = rest.find("</location>") else {
+            break;
+        };
+        let uri = xml_unescape(rest[..end].trim());
+        rest = &rest[end..];
+        let path = match uri.strip_prefix("file://") {
+            Some(abs) => abs.to_string(),
+            None => uri,
+        };
+        entries.push(
+            urlencoding::decode(&path)
+                .map(|s| s.into_owned())
+                .unwrap_or(path),
+        );
+    }
+    Ok(entries)
+}
+
+/// True for the file extensions of the supported local playlist formats.
+pub fn is_playlist_extension(ext: &str) -> bool {
+    ["m3u", "m3u8", "xspf", "pls"]
+        .iter()
+        .any(|e| ext.eq_ignore_ascii_case(e))
+}
 
+/// True for files with a playlist extension (see [`is_playlist_extension`]),
+/// which are never treated as tracks.
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| is_playlist_extension(&e.to_string_lossy()))
+}
+
+/// Percent-encode each segment of a relative path for use as a URI reference.
+fn path_to_uri_reference(path: &Path) -> String {
+    path.components()
+        .map(|c| urlencoding::encode(&c.as_os_str().to_string_lossy()).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Decode the predefined XML entities and numeric character references.
+fn xml_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "amp" => Some('&'),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|n| n.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Media files below `target_folder` in playlist order (see
+/// `playlist_order_mode`).
+fn collect_flat_tracks(
+    target_folder: &Path,
+    playlist_path: &Path,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> Vec<PathBuf> {
+    collect_tracks(
+        target_folder,
+        playlist_path,
+        usize::MAX,
+        order_mode,
+        file_extensions,
+        ignore,
+    )
+}
+
+/// Number of media files anywhere below `folder` (what its flat playlist
+/// lists), for the `${track_count}` template placeholder.
+pub fn count_tracks(folder: &Path, file_extensions: &[String], ignore: &IgnoreMatcher) -> usize {
+    WalkDir::new(folder)
+        .into_iter()
+        .filter_entry(|e| !ignore.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file()
+                && !is_playlist_file(e.path())
+                && path_matches_extensions(e.path(), file_extensions)
+        })
+        .count()
+}
+
+/// Media files at most `max_depth` levels below `target_folder` (1: the
+/// folder's own files) in playlist order.  Playlist files, including
+/// `playlist_path` itself, are never tracks, whatever `file_extensions`
+/// says.
+fn collect_tracks(
+    target_folder: &Path,
+    playlist_path: &Path,
+    max_depth: usize,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> Vec<PathBuf> {
     let mut files: Vec<PathBuf> = WalkDir::new(target_folder)
+        .max_depth(max_depth)
         .into_iter()
+        .filter_entry(|e| !ignore.is_ignored(e.path(), e.file_type().is_dir()))
         .filter_map(|e| e.ok())
         .map(|e| e.path().to_path_buf())
         .filter(|p| p.is_file())
+        .filter(|p| p != playlist_path && !is_playlist_file(p))
         .filter(|p| path_matches_extensions(p, file_extensions))
         .collect();
 
+    let mtime = |p: &PathBuf| {
+        std::fs::metadata(p)
+            .and_then(|m| m.modified())
+            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+    };
     if order_mode == "sync_order" {
         // sort by modification time ascending
-        files.sort_by_key(|p| {
-            std::fs::metadata(p)
-                .and_then(|m| m.modified())
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+        files.sort_by_cached_key(mtime);
+    } else if order_mode == "mtime_desc" {
+        // newest first
+        files.sort_by_cached_key(|p| std::cmp::Reverse(mtime(p)));
+    } else if order_mode == "track_number" {
+        // sort by (disc, track) tags; untagged files follow alphabetically
+        files.sort_by_cached_key(|p| {
+            let numbers = crate::util::disc_track_numbers_from_path(p);
+            (numbers.is_none(), numbers, p.clone())
         });
     } else {
         // default: alphabetical
         files.sort();
     }
+    files
+}
 
-    let mut file = std::fs::File::create(playlist_path)?;
-
-    // M3U header
-    writeln!(file, "#EXTM3U")?;
-
-    for p in files.iter() {
-        let title = p.file_name().and_then(|s| s.to_str()).unwrap_or("");
-
-        let relpath = pathdiff::diff_paths(p, target_folder).unwrap_or_else(|| p.clone());
+/// For linked mode, create a playlist that references direct children playlists (not implemented in prototype)
+pub fn write_linked_playlist(
+    target_folder: &Path,
+    playlist_path: &Path,
+    linked_reference_format: &str,
+    local_playlist_template: &str,
+) -> anyhow::Result<()> {
+    write_linked_playlist_filtered(
+        target_folder,
+        playlist_path,
+        linked_reference_format,
+        local_playlist_template,
+        &[],
+        &IgnoreMatcher::default(),
+    )
+}
 
-        writeln!(file, "#EXTINF:-1,{}", title)?;
-        writeln!(file, "{}", relpath.display())?;
-    }
+/// Like [`write_linked_playlist`], skipping child folders matched by `ignore`.
+/// `file_extensions` is used to count a child's tracks when the template
+/// uses `${track_count}`.
+pub fn write_linked_playlist_filtered(
+    target_folder: &Path,
+    playlist_path: &Path,
+    linked_reference_format: &str,
+    local_playlist_template: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> anyhow::Result<()> {
+    write_linked_entries(
+        target_folder,
+        playlist_path,
+        linked_reference_format,
+        local_playlist_template,
+        file_extensions,
+        ignore,
+        None,
+    )
+}
 
-    Ok(())
+/// Like [`write_linked_playlist_filtered`], for the
+/// "linked_with_local_tracks" playlist mode: after the child playlist
+/// references, the media files directly inside `target_folder` follow as
+/// `#EXTINF` entries in `order_mode` order, so loose tracks next to
+/// subfolders are not left out.  Track paths follow
+/// `linked_reference_format` like the references.
+pub fn write_linked_playlist_with_local_tracks(
+    target_folder: &Path,
+    playlist_path: &Path,
+    linked_reference_format: &str,
+    local_playlist_template: &str,
+    order_mode: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+) -> anyhow::Result<()> {
+    let tracks = collect_tracks(
+        target_folder,
+        playlist_path,
+        1,
+        order_mode,
+        file_extensions,
+        ignore,
+    );
+    write_linked_entries(
+        target_folder,
+        playlist_path,
+        linked_reference_format,
+        local_playlist_template,
+        file_extensions,
+        ignore,
+        Some(tracks),
+    )
 }
 
-/// For linked mode, create a playlist that references direct children playlists (not implemented in prototype)
-pub fn write_linked_playlist(
+fn write_linked_entries(
     target_folder: &Path,
     playlist_path: &Path,
     linked_reference_format: &str,
     local_playlist_template: &str,
+    file_extensions: &[String],
+    ignore: &IgnoreMatcher,
+    local_tracks: Option<Vec<PathBuf>>,
 ) -> anyhow::Result<()> {
     if !target_folder.is_dir() {
         return Ok(());
     }
     // write references to immediate child playlists
-    let mut file = std::fs::File::create(playlist_path)?;
     let mut children: Vec<std::path::PathBuf> = Vec::new();
     if let Ok(read) = std::fs::read_dir(target_folder) {
         for e in read.filter_map(|r| r.ok()) {
             let p = e.path();
-            if p.is_dir() {
+            if p.is_dir() && !ignore.is_ignored(&p, true) {
                 children.push(p);
             }
         }
     }
     children.sort();
 
-    use std::io::Write;
+    let parent_name = target_folder
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let mut lines = Vec::with_capacity(children.len());
     for child in children.iter() {
         // child playlist filename based on template; for linked playlists,
         // the logical parent is the current target_folder, so path_to_parent
-        // is empty and folder_name identifies the child.
-        let folder_name = child.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        // is empty, parent_name is the target folder's name and folder_name
+        // identifies the child.
+        let folder_name = child
+            .file_name()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default();
         let path_to_parent = String::new();
-        let child_playlist_name =
-            crate::util::expand_template(local_playlist_template, folder_name, &path_to_parent);
+        let child_playlist_name = crate::util::expand_template(
+            local_playlist_template,
+            &folder_name,
+            &path_to_parent,
+            &parent_name,
+            || count_tracks(child, file_extensions, ignore),
+        );
         let child_playlist_path = child.join(child_playlist_name);
         let line = if linked_reference_format == "absolute" {
             child_playlist_path.display().to_string()
@@ -126,7 +552,25 @@ pub fn write_linked_playlist(
                 .unwrap_or(child_playlist_path.clone());
             relpath.display().to_string()
         };
-        writeln!(file, "{}", line)?;
+        lines.push(line);
     }
-    Ok(())
+    write_atomically(playlist_path, |file| {
+        if local_tracks.is_some() {
+            writeln!(file, "#EXTM3U")?;
+        }
+        for line in &lines {
+            writeln!(file, "{}", line)?;
+        }
+        for p in local_tracks.iter().flatten() {
+            let (duration, title) = crate::util::extinf_for_path(p);
+            let path = if linked_reference_format == "absolute" {
+                p.clone()
+            } else {
+                pathdiff::diff_paths(p, target_folder).unwrap_or_else(|| p.clone())
+            };
+            writeln!(file, "#EXTINF:{},{}", duration, title)?;
+            writeln!(file, "{}", path.display())?;
+        }
+        Ok(())
+    })
 }
@@ -0,0 +1,185 @@
+//! Optional at-rest encryption of the provider tokens in the `credentials`
+//! table.
+//!
+//! When a passphrase is configured (the `MUSIC_SYNC_CREDENTIALS_KEY`
+//! environment variable, or the file named by `credentials_key_file`),
+//! [`crate::db::save_credential_raw`] stores `token_json` sealed with
+//! ChaCha20-Poly1305 under a key derived from the passphrase with
+//! PBKDF2-HMAC-SHA256, and [`crate::db::load_credential_with_client`] opens
+//! it again.  Plaintext rows written before a passphrase was set are still
+//! read, and re-saved encrypted the first time.  Without a passphrase tokens
+//! are stored as before.  `client_id` / `client_secret` stay in plaintext.
+//!
+//! Sealed values look like `enc:v1:<base64 of salt | nonce | ciphertext>`;
+//! the provider name is bound as associated data, so a row copied to
+//! another provider fails to open.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+
+/// Environment variable holding the passphrase; it takes precedence over
+/// `credentials_key_file`.
+pub const PASSPHRASE_ENV: &str = "MUSIC_SYNC_CREDENTIALS_KEY";
+
+const PREFIX: &str = "enc:v1:";
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+struct KeyState {
+    /// Passphrase from `credentials_key_file`, set by [`configure`].
+    configured: Option<String>,
+    /// Passphrase the cached keys were derived from.
+    derived_for: String,
+    /// Derived keys by salt, so PBKDF2 runs once per salt and passphrase.
+    keys: HashMap<[u8; SALT_LEN], [u8; 32]>,
+    /// Salt used when sealing, chosen once per process.
+    seal_salt: Option<[u8; SALT_LEN]>,
+}
+
+static STATE: Lazy<Mutex<KeyState>> = Lazy::new(|| {
+    Mutex::new(KeyState {
+        configured: None,
+        derived_for: String::new(),
+        keys: HashMap::new(),
+        seal_salt: None,
+    })
+});
+
+/// Read the passphrase from `cfg.credentials_key_file`, if set.  Fails when
+/// the file cannot be read or is empty.
+pub fn configure(cfg: &crate::config::Config) -> Result<()> {
+    let passphrase = match &cfg.credentials_key_file {
+        Some(path) => {
+            let s = std::fs::read_to_string(path)
+                .with_context(|| format!("reading credentials_key_file {}", path.display()))?;
+            let s = s.trim().to_string();
+            if s.is_empty() {
+                return Err(anyhow!("credentials_key_file {} is empty", path.display()));
+            }
+            Some(s)
+        }
+        None => None,
+    };
+    set_passphrase(passphrase);
+    Ok(())
+}
+
+/// Set (or clear) the passphrase normally read by [`configure`].
+pub fn set_passphrase(passphrase: Option<String>) {
+    STATE.lock().unwrap().configured = passphrase;
+}
+
+fn passphrase(state: &KeyState) -> Option<String> {
+    std::env::var(PASSPHRASE_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim().to_string())
+        .or_else(|| state.configured.clone())
+}
+
+/// True when a passphrase is configured, i.e. tokens are stored encrypted.
+pub fn is_enabled() -> bool {
+    passphrase(&STATE.lock().unwrap()).is_some()
+}
+
+/// True for values sealed by [`encrypt`].
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// The key for `salt`, deriving it when not cached.  Errors without a
+/// passphrase.
+fn key_for(state: &mut KeyState, salt: [u8; SALT_LEN]) -> Result<LessSafeKey> {
+    let passphrase = passphrase(state).ok_or_else(|| {
+        anyhow!(
+            "no credentials passphrase configured; set {} or credentials_key_file",
+            PASSPHRASE_ENV
+        )
+    })?;
+    if state.derived_for != passphrase {
+        state.derived_for = passphrase.clone();
+        state.keys.clear();
+    }
+    let key = *state.keys.entry(salt).or_insert_with(|| {
+        let mut key = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        key
+    });
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| anyhow!("bad key"))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+/// Seal the token JSON of `provider`.
+pub fn encrypt(provider: &str, plaintext: &str) -> Result<String> {
+    let rng = SystemRandom::new();
+    let mut state = STATE.lock().unwrap();
+    let salt = match state.seal_salt {
+        Some(salt) => salt,
+        None => {
+            let mut salt = [0u8; SALT_LEN];
+            rng.fill(&mut salt)
+                .map_err(|_| anyhow!("no random salt available"))?;
+            state.seal_salt = Some(salt);
+            salt
+        }
+    };
+    let key = key_for(&mut state, salt)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut nonce)
+        .map_err(|_| anyhow!("no random nonce available"))?;
+    let mut sealed = plaintext.as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(provider.as_bytes()),
+        &mut sealed,
+    )
+    .map_err(|_| anyhow!("encrypting the {} credentials failed", provider))?;
+    let mut blob = Vec::with_capacity(SALT_LEN + NONCE_LEN + sealed.len());
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&sealed);
+    Ok(format!(
+        "{}{}",
+        PREFIX,
+        general_purpose::STANDARD.encode(blob)
+    ))
+}
+
+/// Open a value sealed by [`encrypt`] for the same `provider`.
+pub fn decrypt(provider: &str, stored: &str) -> Result<String> {
+    let blob = stored
+        .strip_prefix(PREFIX)
+        .and_then(|b| general_purpose::STANDARD.decode(b).ok())
+        .filter(|b| b.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| anyhow!("the stored {} credentials are malformed", provider))?;
+    let (salt, rest) = blob.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let key = key_for(&mut STATE.lock().unwrap(), salt.try_into().unwrap())
+        .with_context(|| format!("the stored {} credentials are encrypted", provider))?;
+    let mut sealed = sealed.to_vec();
+    let plain = key
+        .open_in_place(
+            Nonce::try_assume_unique_for_key(nonce).unwrap(),
+            Aad::from(provider.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| {
+            anyhow!(
+                "cannot decrypt the stored {} credentials; wrong passphrase?",
+                provider
+            )
+        })?;
+    Ok(String::from_utf8(plain.to_vec())?)
+}
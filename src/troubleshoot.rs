@@ -5,13 +5,10 @@ use anyhow::{Context, Result};
 use chrono::Utc;
 use std::path::Path;
 
-/// TTL used for negative track cache entries (same constant as in worker.rs)
-const NEGATIVE_CACHE_TTL_SECS: i64 = 30 * 24 * 3600;
-
 /// Print a bunch of information about a local file that may be relevant when
 /// troubleshooting sync problems.  The current implementation focuses on the
 /// track cache: it will show extracted ISRC, any cache entries for the hard-
-/// coded providers (spotify/tidal), and whether the file exists on disk.  If
+/// coded providers (spotify/tidal/subsonic/ytmusic), and whether the file exists on disk.  If
 /// no cache entry exists the output makes that clear.  We avoid performing any
 /// network lookups so this command can be run even when offline.
 
@@ -31,16 +28,34 @@ pub fn file_info(cfg: &Config, path: &Path) -> Result<()> {
         } else {
             println!("  extracted isrc: <none>");
         }
+        if let Some(meta) = util::extract_artist_title_from_path(path) {
+            println!(
+                "  tag metadata: artist={:?} title={:?} album={:?}",
+                meta.artist, meta.title, meta.album
+            );
+        } else {
+            println!("  tag metadata: <none>");
+        }
     } else {
         println!("exists: no");
     }
 
     // open database once
-    let conn = rusqlite::Connection::open(&cfg.db_path)
+    let conn = crate::db::open_tuned(&cfg.db_path)
         .with_context(|| format!("opening database at {}", cfg.db_path.display()))?;
 
     // examine track cache for each supported provider
-    for provider in ["spotify", "tidal"].iter() {
+    for provider in [
+        "spotify",
+        "tidal",
+        "subsonic",
+        "ytmusic",
+        "deezer",
+        "applemusic",
+        "plex",
+    ]
+    .iter()
+    {
         match db::get_track_cache_by_local(&conn, provider, &path.display().to_string()) {
             Ok(Some((isrc_opt, remote_opt, resolved_at))) => {
                 println!("\ntrack cache (provider={})", provider);
@@ -49,12 +64,16 @@ pub fn file_info(cfg: &Config, path: &Path) -> Result<()> {
                 let now = Utc::now().timestamp();
                 let age = now - resolved_at;
                 println!("  resolved_at: {} ({age}s ago)", resolved_at);
-                if remote_opt.is_none() {
-                    if age < NEGATIVE_CACHE_TTL_SECS {
-                        println!(
-                            "  negative-cache TTL remaining: {}s",
-                            NEGATIVE_CACHE_TTL_SECS - age
-                        );
+                let not_found_at =
+                    db::get_track_unresolved_at(&conn, provider, &path.display().to_string())
+                        .ok()
+                        .flatten();
+                if let Some(not_found_at) = not_found_at {
+                    let ttl = cfg.unresolved_retry_secs as i64;
+                    let neg_age = now - not_found_at;
+                    println!("  not_found_at: {} ({neg_age}s ago)", not_found_at);
+                    if neg_age < ttl {
+                        println!("  negative-cache TTL remaining: {}s", ttl - neg_age);
                     } else {
                         println!("  negative-cache entry expired");
                     }
@@ -74,18 +93,19 @@ pub fn file_info(cfg: &Config, path: &Path) -> Result<()> {
 
     // If the path looks like a playlist file, try to map it back to a logical
     // playlist name and dump any cached playlist metadata.
-    if path
-        .extension()
-        .and_then(|s| s.to_str())
-        .map_or(false, |ext| ext.eq_ignore_ascii_case("m3u"))
-    {
-        if let Some(rel) = path
-            .parent()
-            .and_then(|p| p.strip_prefix(&cfg.root_folder).ok())
-        {
-            let playlist_name = rel.display().to_string();
+    if crate::playlist::is_playlist_file(path) {
+        if let Some(folder) = path.parent().filter(|p| cfg.root_for_path(p).is_some()) {
+            let playlist_name = cfg.playlist_key_for_folder(folder);
             // Show playlist cache entries for all providers.
-            for provider in &["spotify", "tidal"] {
+            for provider in &[
+                "spotify",
+                "tidal",
+                "subsonic",
+                "ytmusic",
+                "deezer",
+                "applemusic",
+                "plex",
+            ] {
                 if let Ok(Some((mtime, size, hash, uris_json))) =
                     db::get_playlist_cache(&conn, &playlist_name, provider)
                 {
@@ -100,7 +120,17 @@ pub fn file_info(cfg: &Config, path: &Path) -> Result<()> {
                 }
             }
             // If no entries found for any provider, say so.
-            let any_found = ["spotify", "tidal"].iter().any(|p| {
+            let any_found = [
+                "spotify",
+                "tidal",
+                "subsonic",
+                "ytmusic",
+                "deezer",
+                "applemusic",
+                "plex",
+            ]
+            .iter()
+            .any(|p| {
                 db::get_playlist_cache(&conn, &playlist_name, p)
                     .ok()
                     .flatten()
@@ -115,22 +145,12 @@ pub fn file_info(cfg: &Config, path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Lookup a single media file using a remote provider and update the track cache
-/// accordingly.  This mirrors the logic used by the worker when resolving
-/// playlist entries but is exposed as a CLI helper for manual debugging.
-pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Result<()> {
-    println!(
-        "=== lookup '{}' via provider '{}' ===",
-        path.display(),
-        provider_name
-    );
-
-    if !path.exists() {
-        println!("file does not exist");
-        return Ok(());
-    }
-
-    // build provider instance
+/// Build an unconfigured provider instance by name for the CLI helpers.
+/// Credentials are loaded from the database on first use.
+pub fn build_provider(
+    cfg: &Config,
+    provider_name: &str,
+) -> Result<std::sync::Arc<dyn crate::api::Provider>> {
     let prov: std::sync::Arc<dyn crate::api::Provider> =
         match provider_name.to_ascii_lowercase().as_str() {
             "spotify" => {
@@ -159,6 +179,35 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
                     cfg.clone(),
                 ))
             }
+            "ytmusic" => {
+                use crate::api::ytmusic::YtMusicProvider;
+                std::sync::Arc::new(YtMusicProvider::new(
+                    String::new(),
+                    String::new(),
+                    cfg.db_path.clone(),
+                    cfg.clone(),
+                ))
+            }
+            "subsonic" => {
+                use crate::api::subsonic::SubsonicProvider;
+                std::sync::Arc::new(SubsonicProvider::new(cfg.db_path.clone(), cfg.clone()))
+            }
+            "deezer" => {
+                use crate::api::deezer::DeezerProvider;
+                std::sync::Arc::new(DeezerProvider::new(cfg.db_path.clone(), cfg.clone()))
+            }
+            "applemusic" => {
+                use crate::api::apple_music::AppleMusicProvider;
+                std::sync::Arc::new(AppleMusicProvider::new(cfg.db_path.clone(), cfg.clone()))
+            }
+            "plex" => {
+                use crate::api::plex::PlexProvider;
+                std::sync::Arc::new(PlexProvider::new(cfg.db_path.clone(), cfg.clone()))
+            }
+            "filesystem" => {
+                use crate::api::filesystem::FileSystemProvider;
+                std::sync::Arc::new(FileSystemProvider::new(cfg.clone()))
+            }
             "mock" => {
                 use crate::api::mock::MockProvider;
                 std::sync::Arc::new(MockProvider::new())
@@ -167,6 +216,25 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
                 return Err(anyhow::anyhow!("unknown provider '{}'", other));
             }
         };
+    Ok(prov)
+}
+
+/// Lookup a single media file using a remote provider and update the track cache
+/// accordingly.  This mirrors the logic used by the worker when resolving
+/// playlist entries but is exposed as a CLI helper for manual debugging.
+pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Result<()> {
+    println!(
+        "=== lookup '{}' via provider '{}' ===",
+        path.display(),
+        provider_name
+    );
+
+    if !path.exists() {
+        println!("file does not exist");
+        return Ok(());
+    }
+
+    let prov = build_provider(cfg, provider_name)?;
 
     if !prov.is_authenticated() {
         println!("provider not authenticated (skipping lookup)");
@@ -176,7 +244,7 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
     let path_str = path.display().to_string();
     // show existing cache entry if any
     if let Ok(Some((isrc, remote, resolved))) = {
-        let conn = rusqlite::Connection::open(&cfg.db_path)?;
+        let conn = crate::db::open_tuned(&cfg.db_path)?;
         db::get_track_cache_by_local(&conn, provider_name, &path_str)
     } {
         println!(
@@ -188,7 +256,33 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
     }
 
     // try resolution
-    let isrc_opt = util::extract_isrc_from_path(path);
+    let mut isrc_opt = util::extract_isrc_from_path(path);
+    if isrc_opt.is_none() && cfg.enable_musicbrainz {
+        let filename_regex = cfg.compiled_filename_parse_regex()?;
+        let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
+        if let Some((artist, title)) =
+            util::artist_title_candidates_stripped(path, filename_regex.as_ref(), &copy_suffixes)
+                .into_iter()
+                .next()
+        {
+            match util::musicbrainz_lookup_isrc(
+                &artist,
+                &title,
+                util::album_for_path_with(path, filename_regex.as_ref()).as_deref(),
+                util::duration_ms_from_path(path),
+                cfg.match_duration_tolerance_secs,
+            )
+            .await
+            {
+                Ok(Some(isrc)) => {
+                    println!("musicbrainz isrc: {}", isrc);
+                    isrc_opt = Some(isrc);
+                }
+                Ok(None) => println!("musicbrainz isrc: <none>"),
+                Err(e) => println!("musicbrainz lookup failed: {}", e),
+            }
+        }
+    }
     let mut uri_opt: Option<String> = None;
 
     if let Some(ref isrc) = isrc_opt {
@@ -210,23 +304,57 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
 
     // fallback metadata search
     if uri_opt.is_none() {
-        let fname = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
-        let stem = if let Some((base, _ext)) = fname.rsplit_once('.') {
-            base
+        if let Some(meta) = util::extract_artist_title_from_path(path) {
+            println!(
+                "tag metadata: artist={:?} title={:?} album={:?}",
+                meta.artist, meta.title, meta.album
+            );
         } else {
-            fname
-        };
-        let mut candidates: Vec<(&str, &str)> = Vec::new();
-        if let Some((left, right)) = stem.split_once(" - ") {
-            let left = left.trim();
-            let right = right.trim();
-            candidates.push((left, right));
-            candidates.push((right, left));
-        } else {
-            candidates.push(("", stem));
+            println!("no artist/title tags; falling back to filename");
         }
+        let filename_regex = cfg.compiled_filename_parse_regex()?;
+        let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
+        let album = util::album_for_path_with(path, filename_regex.as_ref());
+        let duration_ms = util::duration_ms_from_path(path);
+        let candidates = util::with_normalized_search_terms(
+            util::artist_title_candidates_stripped(path, filename_regex.as_ref(), &copy_suffixes),
+            &cfg.search_strip_tokens,
+            cfg.search_strip_diacritics,
+        );
         for (artist, title) in candidates {
-            match prov.search_track_uri(title, artist).await {
+            let found = prov
+                .search_track_candidates(
+                    &title,
+                    &artist,
+                    album.as_deref(),
+                    crate::api::DEFAULT_SEARCH_CANDIDATES,
+                )
+                .await
+                .map(|candidates| {
+                    let local = crate::api::LocalTrack {
+                        title: &title,
+                        artist: &artist,
+                        album: album.as_deref(),
+                        duration_ms,
+                    };
+                    for c in &candidates {
+                        println!(
+                            "candidate {} duration_ms={:?} (local {:?}) confidence={:.2}",
+                            c.uri,
+                            c.duration_ms,
+                            duration_ms,
+                            crate::api::match_confidence(&local, c)
+                        );
+                    }
+                    crate::api::pick_confident_match(
+                        candidates,
+                        &local,
+                        cfg.match_duration_tolerance_secs,
+                        cfg.min_match_confidence,
+                    )
+                    .map(|m| m.uri)
+                });
+            match found {
                 Ok(Some(u)) => {
                     println!(
                         "found via metadata search '{}' - '{}' -> {}",
@@ -251,14 +379,17 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
         let isrc_c = isrc_opt.clone();
         let uri_c = uri_opt.clone();
         tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-            let conn = rusqlite::Connection::open(db_path)?;
-            db::upsert_track_cache(
-                &conn,
-                &provider_c,
-                &path_c,
-                isrc_c.as_deref(),
-                uri_c.as_deref(),
-            )?;
+            let conn = crate::db::open_tuned(db_path)?;
+            match uri_c.as_deref() {
+                Some(uri) => db::upsert_track_cache(
+                    &conn,
+                    &provider_c,
+                    &path_c,
+                    isrc_c.as_deref(),
+                    Some(uri),
+                )?,
+                None => db::mark_track_unresolved(&conn, &provider_c, &path_c, isrc_c.as_deref())?,
+            }
             Ok(())
         })
         .await??;
@@ -268,6 +399,648 @@ pub async fn file_lookup(cfg: &Config, provider_name: &str, path: &Path) -> Resu
     Ok(())
 }
 
+/// Print the ranked search candidates a provider returns for a local file
+/// and which one the worker would pick, with the album, duration and
+/// confidence checks that decided it.  Read-only unless `refresh` is set: then the file's
+/// track-cache entry is replaced by a fresh resolution (the ISRC from its
+/// tags first, like the worker), which fixes a cached bad match.  No
+/// playlist is touched.
+pub async fn resolve_track(
+    cfg: &Config,
+    provider_name: &str,
+    path: &Path,
+    limit: usize,
+    refresh: bool,
+) -> Result<()> {
+    if !path.exists() {
+        anyhow::bail!("file does not exist: {}", path.display());
+    }
+    let prov = build_provider(cfg, provider_name)?;
+    let filename_regex = cfg.compiled_filename_parse_regex()?;
+    let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
+    let album = util::album_for_path_with(path, filename_regex.as_ref());
+    let duration_ms = util::duration_ms_from_path(path);
+    let tolerance = cfg.match_duration_tolerance_secs;
+    println!(
+        "=== resolve '{}' via provider '{}' ===",
+        path.display(),
+        provider_name
+    );
+    println!(
+        "local: album={:?} duration={} tolerance={}s min_confidence={:.2}",
+        album,
+        fmt_duration(duration_ms),
+        tolerance,
+        cfg.min_match_confidence
+    );
+
+    let mut resolved: Option<String> = None;
+    let isrc = util::extract_isrc_from_path(path);
+    if refresh {
+        if let Some(isrc) = &isrc {
+            match prov.search_track_uri_by_isrc(isrc).await {
+                Ok(found) => {
+                    println!("isrc {}: {}", isrc, found.as_deref().unwrap_or("no match"));
+                    resolved = found;
+                }
+                Err(e) => println!("isrc {}: lookup failed: {}", isrc, e),
+            }
+        }
+    }
+
+    let candidates = util::with_normalized_search_terms(
+        util::artist_title_candidates_stripped(path, filename_regex.as_ref(), &copy_suffixes),
+        &cfg.search_strip_tokens,
+        cfg.search_strip_diacritics,
+    );
+    for (artist, title) in candidates {
+        println!("\nsearch: artist={:?} title={:?}", artist, title);
+        let candidates = match prov
+            .search_track_candidates(&title, &artist, album.as_deref(), limit)
+            .await
+        {
+            Ok(c) => c,
+            Err(e) => {
+                println!("  search failed: {}", e);
+                continue;
+            }
+        };
+        if candidates.is_empty() {
+            println!("  no results");
+            continue;
+        }
+        let local = crate::api::LocalTrack {
+            title: &title,
+            artist: &artist,
+            album: album.as_deref(),
+            duration_ms,
+        };
+        let chosen = crate::api::pick_confident_match(
+            candidates.clone(),
+            &local,
+            tolerance,
+            cfg.min_match_confidence,
+        );
+        for (rank, c) in candidates.iter().enumerate() {
+            let album_match = match (album.as_deref(), c.album.as_deref()) {
+                (Some(local), Some(remote)) => {
+                    if crate::api::album_names_match(local, remote) {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                }
+                _ => "n/a",
+            };
+            let delta = match (duration_ms, c.duration_ms) {
+                (Some(l), Some(r)) => format!("{:+.1}s", (r as f64 - l as f64) / 1000.0),
+                _ => "n/a".into(),
+            };
+            let confidence = crate::api::match_confidence(&local, c);
+            let verdict = if chosen.as_ref().is_some_and(|m| m.uri == c.uri) {
+                "<- chosen"
+            } else if tolerance > 0
+                && duration_ms
+                    .zip(c.duration_ms)
+                    .is_some_and(|(l, r)| l.abs_diff(r) > tolerance * 1000)
+            {
+                "rejected: duration"
+            } else if confidence < cfg.min_match_confidence {
+                "rejected: confidence"
+            } else {
+                ""
+            };
+            println!(
+                "  #{} {} '{}' - '{}' album={:?} duration={} ({}) album_match={} isrc={:?} confidence={:.2} {}",
+                rank + 1,
+                c.uri,
+                c.name.as_deref().unwrap_or("?"),
+                c.artist.as_deref().unwrap_or("?"),
+                c.album.as_deref().unwrap_or("?"),
+                fmt_duration(c.duration_ms),
+                delta,
+                album_match,
+                c.isrc,
+                confidence,
+                verdict
+            );
+        }
+        if let Some(m) = chosen {
+            // The worker stops at the first search that yields a match.
+            resolved = resolved.or(Some(m.uri));
+            break;
+        }
+    }
+
+    if refresh {
+        let conn = db::open_or_create(&cfg.db_path)
+            .with_context(|| format!("opening database at {}", cfg.db_path.display()))?;
+        let local_path = path.display().to_string();
+        let previous = db::get_track_cache_by_local(&conn, provider_name, &local_path)?
+            .and_then(|(_, remote_id, _)| remote_id);
+        db::delete_track_cache_entry(&conn, provider_name, &local_path)?;
+        match &resolved {
+            Some(uri) => db::upsert_track_cache(
+                &conn,
+                provider_name,
+                &local_path,
+                isrc.as_deref(),
+                Some(uri),
+            )?,
+            None => db::mark_track_unresolved(&conn, provider_name, &local_path, isrc.as_deref())?,
+        }
+        println!(
+            "\ntrack cache: {} -> {}",
+            previous.as_deref().unwrap_or("<none>"),
+            resolved.as_deref().unwrap_or("<unresolved>")
+        );
+    }
+    Ok(())
+}
+
+fn fmt_duration(ms: Option<u64>) -> String {
+    match ms {
+        Some(ms) => format!("{}:{:02}", ms / 60_000, (ms / 1000) % 60),
+        None => "?".into(),
+    }
+}
+
+/// Check that the setup at `config_path` is usable and print one
+/// `[PASS]`/`[FAIL]` line per check: the config loads and validates, the DB
+/// opens and has every table/column of the embedded schema, each provider
+/// with stored credentials can refresh its token (or, for Subsonic, ping
+/// the server), and the roots and `log_dir` are readable and writable.
+/// Returns true when every check passed.
+pub async fn doctor(config_path: &Path) -> bool {
+    let mut failures = 0usize;
+    let mut report = |name: &str, outcome: Result<String>| match outcome {
+        Ok(detail) => println!("[PASS] {}: {}", name, detail),
+        Err(e) => {
+            failures += 1;
+            println!("[FAIL] {}: {:#}", name, e);
+        }
+    };
+
+    let cfg = match Config::from_path(config_path) {
+        Ok(cfg) => {
+            report("config", Ok(format!("{} is valid", config_path.display())));
+            cfg
+        }
+        Err(e) => {
+            report(
+                "config",
+                Err(e.context(format!("loading {}", config_path.display()))),
+            );
+            println!("1 check failed");
+            return false;
+        }
+    };
+
+    match crate::secrets::configure(&cfg) {
+        Ok(()) if crate::secrets::is_enabled() => report(
+            "credentials key",
+            Ok("stored tokens are encrypted".to_string()),
+        ),
+        Ok(()) => {}
+        Err(e) => report("credentials key", Err(e)),
+    }
+
+    let conn = db::open_or_create(&cfg.db_path)
+        .with_context(|| format!("opening database at {}", cfg.db_path.display()));
+    report(
+        "database",
+        conn.as_ref()
+            .map(|_| format!("{} opened and migrated", cfg.db_path.display()))
+            .map_err(|e| anyhow::anyhow!("{:#}", e)),
+    );
+    if let Ok(conn) = &conn {
+        report("schema", missing_schema(conn));
+
+        let mut any_credentials = false;
+        for provider in CREDENTIAL_PROVIDERS {
+            match db::load_credential_with_client(conn, provider) {
+                Ok(Some(_)) => {
+                    any_credentials = true;
+                    let outcome = check_provider_credentials(&cfg, provider)
+                        .await
+                        .map(|what| format!("credentials stored, {}", what));
+                    report(&format!("auth {}", provider), outcome);
+                }
+                Ok(None) => println!("[ -- ] auth {}: no credentials stored", provider),
+                Err(e) => report(&format!("auth {}", provider), Err(e)),
+            }
+        }
+        if !any_credentials {
+            report(
+                "auth",
+                Err(anyhow::anyhow!(
+                    "no provider has stored credentials; run `auth <provider>` first"
+                )),
+            );
+        }
+    }
+
+    for root in cfg.roots() {
+        report(
+            &format!("root {}", root.display()),
+            check_dir_read_write(&root, false),
+        );
+    }
+    report(
+        &format!("log_dir {}", cfg.log_dir.display()),
+        check_dir_read_write(&cfg.log_dir, true),
+    );
+
+    match failures {
+        0 => println!("all checks passed"),
+        1 => println!("1 check failed"),
+        n => println!("{} checks failed", n),
+    }
+    failures == 0
+}
+
+/// Sample playlist keys used by `config-validate --explain` when none are
+/// given.
+pub const EXPLAIN_SAMPLE_KEYS: [&str; 3] = ["Album1", "Rock/Album1", "Rock/Live/Album2"];
+
+/// Describe how remote playlist names are computed from `cfg`: the root
+/// playlist, structure and flattening delimiter, the effective template for
+/// each structure, and the remote name each enabled provider would use for
+/// every key in `sample_keys`.  Providers without folder support always use
+/// the flat template and delimiter.
+pub fn explain_config(cfg: &Config, sample_keys: &[String]) -> Result<String> {
+    let or_unset = |s: &str| {
+        if s.is_empty() {
+            "<unset>".to_string()
+        } else {
+            format!("{:?}", s)
+        }
+    };
+    let effective = |specific: &str| {
+        if specific.is_empty() {
+            format!(
+                "{} (remote_playlist_template)",
+                or_unset(&cfg.remote_playlist_template)
+            )
+        } else {
+            or_unset(specific)
+        }
+    };
+    let mut out = String::new();
+    out.push_str(&format!(
+        "online_root_playlist: {}\n",
+        or_unset(cfg.online_root_playlist.trim())
+    ));
+    out.push_str(&format!(
+        "online_playlist_structure: {:?}\n",
+        cfg.online_playlist_structure
+    ));
+    if cfg.online_folder_flattening_delimiter.is_empty() && !cfg.flattening_delimiter().is_empty() {
+        out.push_str(&format!(
+            "online_folder_flattening_delimiter: {:?} (default, online_root_playlist is set)\n",
+            cfg.flattening_delimiter()
+        ));
+    } else {
+        out.push_str(&format!(
+            "online_folder_flattening_delimiter: {}\n",
+            or_unset(&cfg.online_folder_flattening_delimiter)
+        ));
+    }
+    out.push_str(&format!(
+        "template (flat): {}\n",
+        effective(&cfg.remote_playlist_template_flat)
+    ));
+    out.push_str(&format!(
+        "template (folders): {}\n",
+        effective(&cfg.remote_playlist_template_folders)
+    ));
+
+    for provider_name in [
+        "spotify",
+        "tidal",
+        "ytmusic",
+        "subsonic",
+        "deezer",
+        "applemusic",
+        "plex",
+        "filesystem",
+    ] {
+        if !cfg.provider_enabled(provider_name) {
+            continue;
+        }
+        let provider = build_provider(cfg, provider_name)?;
+        let supports_folders = provider.capabilities().supports_folders;
+        let structure = if cfg.online_playlist_structure == "folders" && supports_folders {
+            "folders"
+        } else {
+            "flat"
+        };
+        out.push_str(&format!(
+            "\n{} (folder nesting {}; uses {} structure):\n",
+            provider_name,
+            if supports_folders {
+                "supported"
+            } else {
+                "unsupported"
+            },
+            structure
+        ));
+        for key in sample_keys {
+            let name = crate::worker::compute_remote_playlist_name(
+                cfg,
+                provider_name,
+                key,
+                supports_folders,
+            );
+            out.push_str(&format!("  {:?} -> {:?}\n", key, name));
+        }
+    }
+    Ok(out)
+}
+
+/// Compare the tables/columns of `conn` with a fresh DB built from the
+/// embedded schema and fail listing whatever is missing.
+fn missing_schema(conn: &rusqlite::Connection) -> Result<String> {
+    fn columns(
+        conn: &rusqlite::Connection,
+    ) -> Result<std::collections::BTreeMap<String, Vec<String>>> {
+        let mut stmt = conn.prepare(
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p \
+             WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' ORDER BY m.name, p.cid",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = std::collections::BTreeMap::<String, Vec<String>>::new();
+        for row in rows {
+            let (table, column) = row?;
+            out.entry(table).or_default().push(column);
+        }
+        Ok(out)
+    }
+
+    let expected_conn = rusqlite::Connection::open_in_memory()?;
+    db::run_migrations(&expected_conn)?;
+    let expected = columns(&expected_conn)?;
+    let actual = columns(conn)?;
+    let mut missing = Vec::new();
+    for (table, cols) in &expected {
+        match actual.get(table) {
+            None => missing.push(format!("table {}", table)),
+            Some(have) => missing.extend(
+                cols.iter()
+                    .filter(|c| !have.contains(c))
+                    .map(|c| format!("column {}.{}", table, c)),
+            ),
+        }
+    }
+    if missing.is_empty() {
+        Ok(format!("all {} tables present", expected.len()))
+    } else {
+        Err(anyhow::anyhow!("missing {}", missing.join(", ")))
+    }
+}
+
+/// Exercise the stored credentials of `provider` against its API.
+async fn check_provider_credentials(cfg: &Config, provider: &str) -> Result<&'static str> {
+    match provider {
+        "spotify" => {
+            crate::api::spotify::SpotifyProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                cfg.clone(),
+            )
+            .test_refresh_token()
+            .await?;
+            Ok("token refreshed")
+        }
+        "tidal" => {
+            crate::api::tidal::TidalProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                None,
+                cfg.clone(),
+            )
+            .test_refresh_token()
+            .await?;
+            Ok("token refreshed")
+        }
+        "subsonic" => {
+            crate::api::subsonic::SubsonicProvider::new(cfg.db_path.clone(), cfg.clone())
+                .ping()
+                .await?;
+            Ok("server ping succeeded")
+        }
+        other => {
+            build_provider(cfg, other)?.refresh_token().await?;
+            Ok("token refreshed")
+        }
+    }
+}
+
+/// Providers that keep credentials in the `credentials` table.
+const CREDENTIAL_PROVIDERS: [&str; 7] = [
+    "spotify",
+    "tidal",
+    "ytmusic",
+    "subsonic",
+    "deezer",
+    "applemusic",
+    "plex",
+];
+
+/// Report for `auth-status`: for each provider with stored credentials,
+/// when the token expires, whether it can be refreshed, and the result of a
+/// lightweight probe (see [`probe_credentials`]).  The probe refreshes an
+/// expired token like any request would; nothing else is changed.
+///
+/// The flag is false when an enabled provider has no usable credentials.
+/// Enabled means listed in `enabled_providers`, or with an empty list any
+/// provider with stored credentials (one of which must then exist).
+pub async fn auth_status(cfg: &Config) -> Result<(String, bool)> {
+    let conn = db::open_or_create(&cfg.db_path)
+        .with_context(|| format!("opening database at {}", cfg.db_path.display()))?;
+    let now = Utc::now().timestamp();
+    let mut out = String::new();
+    let mut usable = true;
+    let mut any_stored = false;
+    for provider in CREDENTIAL_PROVIDERS {
+        let enabled = cfg.provider_enabled(provider);
+        let Some((json, _, _)) = db::load_credential_with_client(&conn, provider)? else {
+            if enabled && !cfg.enabled_providers.is_empty() {
+                usable = false;
+                out.push_str(&format!(
+                    "{} [enabled]: no credentials stored; run `auth {}`\n",
+                    provider, provider
+                ));
+            }
+            continue;
+        };
+        any_stored = true;
+        out.push_str(&format!(
+            "{} [{}]\n  token: stored\n",
+            provider,
+            if enabled { "enabled" } else { "disabled" }
+        ));
+        let token: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+        out.push_str(&format!("  expires: {}\n", describe_expiry(&token, now)));
+        let has_refresh = token["refresh_token"]
+            .as_str()
+            .is_some_and(|s| !s.is_empty());
+        out.push_str(&format!(
+            "  refresh token: {}\n",
+            if has_refresh { "yes" } else { "no" }
+        ));
+        let scope = token["scope"].as_str();
+        if provider == "spotify"
+            && crate::api::spotify::scope_lacks(scope, crate::api::spotify::WRITE_SCOPES)
+        {
+            if enabled {
+                usable = false;
+            }
+            out.push_str(&format!(
+                "  scope: {:?} lacks playlist-modify-private/-public, so playlists cannot be changed; re-run `auth spotify`\n",
+                scope.unwrap_or_default()
+            ));
+        }
+        match probe_credentials(cfg, provider).await {
+            Ok(detail) => out.push_str(&format!("  probe: {}\n", detail)),
+            Err(e) => {
+                if enabled {
+                    usable = false;
+                }
+                out.push_str(&format!("  probe: FAILED: {:#}\n", e));
+            }
+        }
+    }
+    if cfg.enabled_providers.is_empty() && !any_stored {
+        usable = false;
+        out.push_str("no provider has stored credentials; run `auth <provider>` first\n");
+    }
+    Ok((out, usable))
+}
+
+/// When a stored token expires, e.g. "2024-05-01 12:00:00 UTC (valid for
+/// 59 min)", or "never".
+fn describe_expiry(token: &serde_json::Value, now: i64) -> String {
+    let expires_at = token["expires_at"]
+        .as_i64()
+        .or_else(|| token["developer_token_expires_at"].as_i64())
+        .filter(|&t| t > 0);
+    match expires_at {
+        Some(t) => {
+            let when = chrono::DateTime::from_timestamp(t, 0)
+                .map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| t.to_string());
+            let state = if t <= now {
+                "expired".to_string()
+            } else {
+                format!("valid for {} min", (t - now) / 60)
+            };
+            format!("{} ({})", when, state)
+        }
+        None => "never".to_string(),
+    }
+}
+
+/// `auth refresh`: refresh the stored token of `provider` through the
+/// provider's own refresh path, which stores the result, and report the
+/// new expiry.  Providers without a refresh grant re-read (Deezer,
+/// Subsonic) or re-sign (Apple Music) their credentials instead.  Fails
+/// when nothing is stored or the refresh is rejected.
+pub async fn refresh_credentials(cfg: &Config, provider: &str) -> Result<String> {
+    let provider = provider.to_ascii_lowercase();
+    if !CREDENTIAL_PROVIDERS.contains(&provider.as_str()) {
+        anyhow::bail!(
+            "unknown provider {:?}; expected one of {}",
+            provider,
+            CREDENTIAL_PROVIDERS.join(", ")
+        );
+    }
+    let conn = db::open_or_create(&cfg.db_path)
+        .with_context(|| format!("opening database at {}", cfg.db_path.display()))?;
+    if db::load_credential_with_client(&conn, &provider)?.is_none() {
+        anyhow::bail!(
+            "no {} credentials stored; run `auth {}` first",
+            provider,
+            provider
+        );
+    }
+    build_provider(cfg, &provider)?
+        .refresh_token()
+        .await
+        .with_context(|| format!("refreshing the {} token", provider))?;
+    let (json, _, _) = db::load_credential_with_client(&conn, &provider)?
+        .ok_or_else(|| anyhow::anyhow!("the {} credentials disappeared", provider))?;
+    let token: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+    Ok(format!(
+        "{} token refreshed; expires: {}",
+        provider,
+        describe_expiry(&token, Utc::now().timestamp())
+    ))
+}
+
+/// Check that the stored credentials of `provider` work with a cheap
+/// request: `GET /me` for Spotify, `GET /users/me` for Tidal and `ping` for
+/// Subsonic.  Other providers only load their bearer token.
+async fn probe_credentials(cfg: &Config, provider: &str) -> Result<String> {
+    match provider {
+        "spotify" => {
+            let user = crate::api::spotify::SpotifyProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                cfg.clone(),
+            )
+            .get_user_id()
+            .await?;
+            Ok(format!("GET /me ok (user {})", user))
+        }
+        "tidal" => {
+            let user = crate::api::tidal::TidalProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                None,
+                cfg.clone(),
+            )
+            .current_user_id()
+            .await?;
+            Ok(format!("GET /users/me ok (user {})", user))
+        }
+        "subsonic" => {
+            crate::api::subsonic::SubsonicProvider::new(cfg.db_path.clone(), cfg.clone())
+                .ping()
+                .await?;
+            Ok("server ping ok".to_string())
+        }
+        "plex" => {
+            crate::api::plex::PlexProvider::new(cfg.db_path.clone(), cfg.clone())
+                .ping()
+                .await?;
+            Ok("music library found".to_string())
+        }
+        other => {
+            build_provider(cfg, other)?.get_bearer().await?;
+            Ok("token loaded".to_string())
+        }
+    }
+}
+
+/// Check that `dir` can be listed and written to by creating and removing a
+/// probe file.  With `create`, a missing directory is created first (as the
+/// CLI does for `log_dir`).
+fn check_dir_read_write(dir: &Path, create: bool) -> Result<String> {
+    if create {
+        std::fs::create_dir_all(dir).with_context(|| "cannot create directory".to_string())?;
+    }
+    std::fs::read_dir(dir).with_context(|| "not readable".to_string())?;
+    let probe = dir.join(format!(".music-sync-doctor-{}", std::process::id()));
+    std::fs::write(&probe, b"").with_context(|| "not writable".to_string())?;
+    let _ = std::fs::remove_file(&probe);
+    Ok("readable and writable".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,9 +1056,9 @@ mod tests {
 
         let cfg = Config {
             root_folder: root.clone(),
-            whitelist: String::new(),
-            local_whitelist: String::new(),
-            remote_whitelist: String::new(),
+            whitelist: Vec::new(),
+            local_whitelist: Vec::new(),
+            remote_whitelist: Vec::new(),
             local_playlist_template: "${folder_name}.m3u".into(),
             remote_playlist_template: "${relative_path}".into(),
             remote_playlist_template_flat: String::new(),
@@ -293,10 +1066,15 @@ mod tests {
             playlist_description_template: String::new(),
             playlist_order_mode: "append".into(),
             playlist_mode: "flat".into(),
+            playlist_format: "m3u".into(),
+            playlist_write_threads: 4,
             linked_reference_format: "relative".into(),
             debounce_ms: 0,
             log_dir: td.path().join("log"),
+            log_level: "info".into(),
+            log_format: "text".into(),
             token_refresh_interval: 0,
+            credentials_key_file: None,
             watcher_instant_trigger_threshold: 0,
             watcher_deferred_trigger_delay_sec: 0,
             nightly_reconcile_cron: String::new(),
@@ -304,11 +1082,42 @@ mod tests {
             max_retries_on_error: 0,
             max_batch_size_spotify: 0,
             max_batch_size_tidal: 0,
+            synced_event_retention_secs: 7 * 24 * 3600,
+            poll_interval_sec: 60,
+            metrics_listen: String::new(),
+            watch_mode: "inotify".into(),
+            ignore_patterns: Vec::new(),
+            no_playlist_marker: ".noplaylist".into(),
+            local_only_marker: ".localonly".into(),
+            root_folders: Vec::new(),
+            spotify_requests_per_sec: 0.0,
+            tidal_requests_per_sec: 0.0,
+            spotify_market: String::new(),
+            spotify_scopes: Vec::new(),
+            tidal_country_code: "US".into(),
+            tidal_locale: "en-US".into(),
+            unresolved_retry_secs: 30 * 24 * 3600,
+            match_duration_tolerance_secs: 10,
+            min_match_confidence: 0.0,
+            search_strip_tokens: Vec::new(),
+            search_strip_diacritics: true,
+            enable_musicbrainz: false,
+            enabled_providers: Vec::new(),
+            filesystem_export_dir: std::path::PathBuf::new(),
+            filesystem_export_path_prefix: String::new(),
+            max_concurrent_providers: 4,
+            remote_snapshot_max_age_secs: 24 * 3600,
+            lock_ttl_secs: 600,
+            backoff_base_secs: 1,
+            backoff_max_secs: 60,
             db_path: db_file.clone(),
             file_extensions: vec!["*.mp3".into()],
             online_root_playlist: String::new(),
             online_playlist_structure: "flat".into(),
             online_folder_flattening_delimiter: String::new(),
+            filename_parse_regex: String::new(),
+            strip_filename_copy_suffix: true,
+            filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
         };
 
         let test_file = cfg.root_folder.join("song.mp3");
@@ -318,7 +1127,7 @@ mod tests {
         file_lookup(&cfg, "mock", &test_file).await?;
 
         // verify cache entry created
-        let conn = rusqlite::Connection::open(&db_file)?;
+        let conn = crate::db::open_tuned(&db_file)?;
         let entry = db::get_track_cache_by_local(&conn, "mock", &test_file.display().to_string())?;
         assert!(entry.is_some());
         let (_isrc, remote, _ts) = entry.unwrap();
@@ -336,9 +1145,9 @@ mod tests {
 
         let cfg = Config {
             root_folder: root.clone(),
-            whitelist: String::new(),
-            local_whitelist: String::new(),
-            remote_whitelist: String::new(),
+            whitelist: Vec::new(),
+            local_whitelist: Vec::new(),
+            remote_whitelist: Vec::new(),
             local_playlist_template: "${folder_name}.m3u".into(),
             remote_playlist_template: "${relative_path}".into(),
             remote_playlist_template_flat: String::new(),
@@ -346,10 +1155,15 @@ mod tests {
             playlist_description_template: String::new(),
             playlist_order_mode: "append".into(),
             playlist_mode: "flat".into(),
+            playlist_format: "m3u".into(),
+            playlist_write_threads: 4,
             linked_reference_format: "relative".into(),
             debounce_ms: 0,
             log_dir: td.path().join("log"),
+            log_level: "info".into(),
+            log_format: "text".into(),
             token_refresh_interval: 0,
+            credentials_key_file: None,
             watcher_instant_trigger_threshold: 0,
             watcher_deferred_trigger_delay_sec: 0,
             nightly_reconcile_cron: String::new(),
@@ -357,11 +1171,42 @@ mod tests {
             max_retries_on_error: 0,
             max_batch_size_spotify: 0,
             max_batch_size_tidal: 0,
+            synced_event_retention_secs: 7 * 24 * 3600,
+            poll_interval_sec: 60,
+            metrics_listen: String::new(),
+            watch_mode: "inotify".into(),
+            ignore_patterns: Vec::new(),
+            no_playlist_marker: ".noplaylist".into(),
+            local_only_marker: ".localonly".into(),
+            root_folders: Vec::new(),
+            spotify_requests_per_sec: 0.0,
+            tidal_requests_per_sec: 0.0,
+            spotify_market: String::new(),
+            spotify_scopes: Vec::new(),
+            tidal_country_code: "US".into(),
+            tidal_locale: "en-US".into(),
+            unresolved_retry_secs: 30 * 24 * 3600,
+            match_duration_tolerance_secs: 10,
+            min_match_confidence: 0.0,
+            search_strip_tokens: Vec::new(),
+            search_strip_diacritics: true,
+            enable_musicbrainz: false,
+            enabled_providers: Vec::new(),
+            filesystem_export_dir: std::path::PathBuf::new(),
+            filesystem_export_path_prefix: String::new(),
+            max_concurrent_providers: 4,
+            remote_snapshot_max_age_secs: 24 * 3600,
+            lock_ttl_secs: 600,
+            backoff_base_secs: 1,
+            backoff_max_secs: 60,
             db_path: db_file.clone(),
             file_extensions: vec!["*.mp3".into()],
             online_root_playlist: String::new(),
             online_playlist_structure: "flat".into(),
             online_folder_flattening_delimiter: String::new(),
+            filename_parse_regex: String::new(),
+            strip_filename_copy_suffix: true,
+            filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
         };
 
         let test_path = cfg.root_folder.join("doesnotexist.mp3");
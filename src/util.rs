@@ -1,4 +1,15 @@
-pub fn expand_template(template: &str, folder_name: &str, path_to_parent: &str) -> String {
+/// Expand the template placeholders: `${folder_name}`, `${path_to_parent}`,
+/// `${relative_path}`, `${parent_name}` (the final segment of the parent
+/// path, empty at the root) and `${track_count}`.  `track_count` is only
+/// called when the template uses it.  Unknown placeholders are left as they
+/// are.
+pub fn expand_template(
+    template: &str,
+    folder_name: &str,
+    path_to_parent: &str,
+    parent_name: &str,
+    track_count: impl FnOnce() -> usize,
+) -> String {
     // `path_to_parent` is intended to be the logical path from the
     // configured root to the playlist folder's *parent* (or equivalent
     // logical parent for remote playlists). Callers are responsible for
@@ -10,35 +21,518 @@ pub fn expand_template(template: &str, folder_name: &str, path_to_parent: &str)
     // playlist folder itself, i.e. `path_to_parent + folder_name`.
     let full_path = format!("{}{}", path_to_parent, folder_name);
 
-    template
+    let expanded = template
         .replace("${folder_name}", folder_name)
         .replace("${path_to_parent}", path_to_parent)
         .replace("${relative_path}", &full_path)
+        .replace("${parent_name}", parent_name);
+    if expanded.contains("${track_count}") {
+        expanded.replace("${track_count}", &track_count().to_string())
+    } else {
+        expanded
+    }
+}
+
+/// `${parent_name}` for a filesystem-style `path_to_parent` such as
+/// "Genre/Rock/": its last segment ("Rock"), or "" when the path is empty.
+pub fn template_parent_name(path_to_parent: &str) -> &str {
+    path_to_parent
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+}
+
+/// Map a path back to the file it was derived from.  Paths are kept as
+/// strings in the event queue, track cache and local playlists, converted
+/// with `to_string_lossy`, so bytes that are not valid UTF-8 (common in
+/// Latin-1 names from legacy rips) read back as U+FFFD and the string no
+/// longer names the file.  Each component missing on disk is matched by its
+/// lossy form against the entries of its directory, taking the first in
+/// sorted order if several collide.  Paths that exist, or that match
+/// nothing, are returned unchanged.
+pub fn resolve_lossy_path(path: &std::path::Path) -> std::path::PathBuf {
+    if path.exists() || !path.to_string_lossy().contains('\u{FFFD}') {
+        return path.to_path_buf();
+    }
+    let mut resolved = std::path::PathBuf::new();
+    for component in path.components() {
+        let next = resolved.join(component);
+        if next.exists() {
+            resolved = next;
+            continue;
+        }
+        let wanted = component.as_os_str().to_string_lossy();
+        let dir = if resolved.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            resolved.as_path()
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return path.to_path_buf();
+        };
+        let mut matches: Vec<std::ffi::OsString> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name())
+            .filter(|name| name.to_string_lossy() == wanted)
+            .collect();
+        matches.sort();
+        match matches.into_iter().next() {
+            Some(name) => resolved.push(name),
+            None => return path.to_path_buf(),
+        }
+    }
+    resolved
 }
 
-/// Attempt to extract an ISRC code from the audio file's metadata tags.
-/// Returns None if the file has no readable tags or no ISRC field.
+/// Attempt to extract an ISRC code from the audio file's metadata tags:
+/// ID3v2 `TSRC` (MP3), the `ISRC` Vorbis comment (FLAC/OGG/Opus) or the
+/// `----:com.apple.iTunes:ISRC` atom (MP4/M4A).  The primary tag is tried
+/// first, then any other tag in the file.  The code is normalized with
+/// [`normalize_isrc`].  Returns None if the file has no readable tags or no
+/// ISRC field.
 pub fn extract_isrc_from_path(path: &std::path::Path) -> Option<String> {
+    use lofty::config::ParseOptions;
+    use lofty::file::TaggedFileExt;
+    use lofty::probe::Probe;
+    use lofty::tag::ItemKey;
+
+    // Only the tags are needed; skipping the audio properties also accepts
+    // files whose stream headers lofty cannot parse.
+    let tagged_file = Probe::open(path)
+        .ok()?
+        .options(ParseOptions::new().read_properties(false))
+        .guess_file_type()
+        .ok()?
+        .read()
+        .ok()?;
+
+    let primary = tagged_file.primary_tag();
+    primary
+        .into_iter()
+        .chain(
+            tagged_file
+                .tags()
+                .iter()
+                .filter(|t| Some(t.tag_type()) != primary.map(|p| p.tag_type())),
+        )
+        .filter_map(|t| t.get_string(&ItemKey::Isrc))
+        .find_map(normalize_isrc)
+}
+
+/// Normalize an ISRC as stored by different taggers ("gb-aaa-00-00001",
+/// "GB AAA 00 00001", ...) to the bare 12-character uppercase form.
+/// Returns None when nothing but separators remain.
+pub fn normalize_isrc(raw: &str) -> Option<String> {
+    let code: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    (!code.is_empty()).then_some(code)
+}
+
+/// Structured track metadata read from an audio file's tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackMetadata {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+}
+
+/// Attempt to read artist/title/album from the audio file's metadata tags
+/// (ID3v2, Vorbis comments, MP4 atoms, ... via lofty).
+/// Returns None if the file has no readable tags or neither a title nor an
+/// artist is present.
+pub fn extract_artist_title_from_path(path: &std::path::Path) -> Option<TrackMetadata> {
     use lofty::file::TaggedFileExt;
     use lofty::probe::read_from_path;
-    use lofty::tag::{ItemKey, Tag};
+    use lofty::tag::{Accessor, Tag};
 
+    let tagged_file = read_from_path(path).ok()?;
+    let tag: Tag = tagged_file
+        .primary_tag()
+        .cloned()
+        .or_else(|| tagged_file.first_tag().cloned())?;
+
+    let clean = |v: Option<std::borrow::Cow<'_, str>>| {
+        v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+    };
+    let meta = TrackMetadata {
+        artist: clean(tag.artist()),
+        title: clean(tag.title()),
+        album: clean(tag.album()),
+    };
+    if meta.title.is_none() && meta.artist.is_none() {
+        return None;
+    }
+    Some(meta)
+}
+
+/// Build the `#EXTINF` duration (whole seconds) and display title for a
+/// track in an extended M3U playlist.
+///
+/// The duration comes from the file's audio properties and the title is
+/// `Artist - Title` from its tags.  Anything lofty cannot read falls back to
+/// `-1` and the file name, so an unparsable file never fails the caller.
+pub fn extinf_for_path(path: &std::path::Path) -> (i64, String) {
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::probe::read_from_path;
+    use lofty::tag::Accessor;
+
+    let file_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default()
+        .to_string();
     let tagged_file = match read_from_path(path) {
         Ok(tf) => tf,
-        Err(_) => return None,
+        Err(e) => {
+            log::debug!("extinf: could not read {}: {}", path.display(), e);
+            return (-1, file_name);
+        }
     };
 
-    let tag: Option<Tag> = tagged_file
+    let secs = tagged_file.properties().duration().as_secs();
+    let duration = if secs > 0 { secs as i64 } else { -1 };
+
+    let tag = tagged_file
         .primary_tag()
-        .cloned()
-        .or_else(|| tagged_file.first_tag().cloned());
+        .or_else(|| tagged_file.first_tag());
+    let clean = |v: Option<std::borrow::Cow<'_, str>>| {
+        v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+    };
+    let artist = tag.and_then(|t| clean(t.artist()));
+    let title = tag.and_then(|t| clean(t.title()));
+    let display = match (artist, title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        (None, Some(title)) => title,
+        _ => file_name,
+    };
+    (duration, display)
+}
+
+/// Read the file's playback duration in milliseconds from its audio
+/// properties.  Returns None when the file cannot be read or reports no
+/// duration.
+pub fn duration_ms_from_path(path: &std::path::Path) -> Option<u64> {
+    use lofty::file::AudioFile;
+    use lofty::probe::read_from_path;
+
+    let ms = read_from_path(path)
+        .ok()?
+        .properties()
+        .duration()
+        .as_millis() as u64;
+    (ms > 0).then_some(ms)
+}
 
-    let tag = match tag {
-        Some(t) => t,
-        None => return None,
+/// Read `(disc_number, track_number)` from the file's tags.  A missing disc
+/// number counts as disc 1.  Returns None when the file cannot be read or
+/// has no track number.
+pub fn disc_track_numbers_from_path(path: &std::path::Path) -> Option<(u32, u32)> {
+    use lofty::file::TaggedFileExt;
+    use lofty::probe::read_from_path;
+    use lofty::tag::Accessor;
+
+    let tagged_file = read_from_path(path).ok()?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+    let track = tag.track()?;
+    Some((tag.disk().unwrap_or(1), track))
+}
+
+/// Derive `(artist, title)` search candidates from a file name alone.
+///
+/// The extension is stripped and, if the stem contains `" - "`, both the
+/// "Artist - Title" and "Title - Artist" orderings are returned. Otherwise
+/// the whole stem is used as the title with an empty artist.
+pub fn filename_artist_title_candidates(path: &std::path::Path) -> Vec<(String, String)> {
+    candidates_from_stem(&file_stem_without_suffixes(path, &[]))
+}
+
+/// The file name of `path` without its extension and without a trailing
+/// match of any of `suffixes` (see `Config::compiled_filename_copy_suffixes`).
+fn file_stem_without_suffixes(path: &std::path::Path, suffixes: &[regex::Regex]) -> String {
+    let fname = path
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let mut stem = if let Some((base, _ext)) = fname.rsplit_once('.') {
+        base
+    } else {
+        &fname
+    };
+    for re in suffixes {
+        if let Some(m) = re.find(stem) {
+            // Never strip a whole name down to nothing.
+            if !stem[..m.start()].trim().is_empty() {
+                stem = stem[..m.start()].trim_end();
+            }
+        }
+    }
+    stem.to_string()
+}
+
+fn candidates_from_stem(stem: &str) -> Vec<(String, String)> {
+    let mut candidates = Vec::new();
+    if let Some((left, right)) = stem.split_once(" - ") {
+        let left = left.trim().to_string();
+        let right = right.trim().to_string();
+        candidates.push((left.clone(), right.clone()));
+        candidates.push((right, left));
+    } else {
+        candidates.push((String::new(), stem.to_string()));
+    }
+    candidates
+}
+
+/// Parse a file name (stem, without extension) with the configured
+/// `filename_parse_regex`, reading the `artist`, `title` and `album` named
+/// groups.  Returns None when the regex does not match or captures no title.
+pub fn filename_metadata_from_regex(
+    path: &std::path::Path,
+    re: &regex::Regex,
+) -> Option<TrackMetadata> {
+    metadata_from_stem(&path.file_stem()?.to_string_lossy(), re)
+}
+
+fn metadata_from_stem(stem: &str, re: &regex::Regex) -> Option<TrackMetadata> {
+    let caps = re.captures(stem)?;
+    let group = |name: &str| {
+        caps.name(name)
+            .map(|m| m.as_str().trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let meta = TrackMetadata {
+        artist: group("artist"),
+        title: group("title"),
+        album: group("album"),
     };
+    meta.title.as_ref()?;
+    Some(meta)
+}
+
+/// Build the ordered list of `(artist, title)` pairs to try when searching a
+/// provider for a local file. Tag-derived metadata comes first when the file
+/// carries a title tag; the filename heuristic is only used as a fallback.
+pub fn artist_title_candidates(path: &std::path::Path) -> Vec<(String, String)> {
+    artist_title_candidates_with(path, None)
+}
+
+/// Like [`artist_title_candidates`], but when tags are missing the file name
+/// is first parsed with `filename_regex` (see [`filename_metadata_from_regex`])
+/// before falling back to the `" - "` heuristic.
+pub fn artist_title_candidates_with(
+    path: &std::path::Path,
+    filename_regex: Option<&regex::Regex>,
+) -> Vec<(String, String)> {
+    artist_title_candidates_stripped(path, filename_regex, &[])
+}
+
+/// Like [`artist_title_candidates_with`], removing a trailing match of any
+/// of `suffixes` (duplicate-file suffixes such as `" copy 2"`) from the file
+/// name before parsing it.  Tag metadata is used as is.
+pub fn artist_title_candidates_stripped(
+    path: &std::path::Path,
+    filename_regex: Option<&regex::Regex>,
+    suffixes: &[regex::Regex],
+) -> Vec<(String, String)> {
+    if let Some(meta) = extract_artist_title_from_path(path) {
+        if let Some(title) = meta.title {
+            return vec![(meta.artist.unwrap_or_default(), title)];
+        }
+    }
+    let stem = file_stem_without_suffixes(path, suffixes);
+    if let Some(meta) = filename_regex.and_then(|re| metadata_from_stem(&stem, re)) {
+        if let Some(title) = meta.title {
+            return vec![(meta.artist.unwrap_or_default(), title)];
+        }
+    }
+    candidates_from_stem(&stem)
+}
+
+/// The album to disambiguate a search with: the album tag, else the
+/// `album` group of `filename_regex` when the file has no tags.
+pub fn album_for_path_with(
+    path: &std::path::Path,
+    filename_regex: Option<&regex::Regex>,
+) -> Option<String> {
+    match extract_artist_title_from_path(path) {
+        Some(meta) => meta.album,
+        None => filename_regex
+            .and_then(|re| filename_metadata_from_regex(path, re))
+            .and_then(|meta| meta.album),
+    }
+}
+
+/// Normalize a search `(title, artist)` for a second, looser provider search:
+/// NFKD normalization (full-width forms become ASCII), optionally without
+/// diacritics ("Björk" -> "Bjork"), and with bracketed groups or a trailing
+/// `" - ..."` suffix of the title removed when they contain one of
+/// `strip_tokens` as whole words ("(Remastered 2011)", "- Radio Edit").
+pub fn normalize_search_terms(
+    title: &str,
+    artist: &str,
+    strip_tokens: &[String],
+    strip_diacritics: bool,
+) -> (String, String) {
+    let fold = |s: &str| {
+        let nfkd = icu_normalizer::DecomposingNormalizerBorrowed::new_nfkd().normalize(s);
+        let folded: String = nfkd
+            .chars()
+            .filter(|c| !(strip_diacritics && is_combining_mark(*c)))
+            .collect();
+        collapse_whitespace(&folded)
+    };
+    let title = fold(title);
+    let tokens: Vec<String> = strip_tokens.iter().map(|t| words(t)).collect();
+    let is_noise = |s: &str| {
+        let haystack = format!(" {} ", words(s));
+        tokens
+            .iter()
+            .any(|t| !t.is_empty() && haystack.contains(&format!(" {} ", t)))
+    };
+
+    // Drop noisy (...) / [...] groups.
+    let mut stripped = String::new();
+    let mut rest = title.as_str();
+    while let Some(open) = rest.find(['(', '[']) {
+        let close_ch = if rest[open..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(len) = rest[open..].find(close_ch) else {
+            break;
+        };
+        let group = &rest[open..open + len + 1];
+        stripped.push_str(&rest[..open]);
+        if !is_noise(&group[1..group.len() - 1]) {
+            stripped.push_str(group);
+        }
+        rest = &rest[open + len + 1..];
+    }
+    stripped.push_str(rest);
+    // Drop a noisy " - Radio Edit" style suffix.
+    if let Some((head, tail)) = stripped.rsplit_once(" - ") {
+        if !head.trim().is_empty() && is_noise(tail) {
+            stripped.truncate(head.len());
+        }
+    }
+    (collapse_whitespace(&stripped), fold(artist))
+}
+
+/// `candidates` followed by their [`normalize_search_terms`] forms, skipping
+/// forms that are unchanged or already present, so exact matches are tried
+/// first.
+pub fn with_normalized_search_terms(
+    candidates: Vec<(String, String)>,
+    strip_tokens: &[String],
+    strip_diacritics: bool,
+) -> Vec<(String, String)> {
+    let mut out = candidates.clone();
+    for (artist, title) in candidates {
+        let (title, artist) =
+            normalize_search_terms(&title, &artist, strip_tokens, strip_diacritics);
+        if !title.is_empty() && !out.contains(&(artist.clone(), title.clone())) {
+            out.push((artist, title));
+        }
+    }
+    out
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
 
-    tag.get_string(&ItemKey::Isrc).map(|s| s.to_string())
+/// Lowercase alphanumeric words of `s`, joined by single spaces.
+fn words(s: &str) -> String {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Minimum MusicBrainz search score (0-100) for a recording to be trusted.
+const MUSICBRAINZ_MIN_SCORE: u64 = 90;
+
+/// Look up an ISRC for a track on the MusicBrainz web service, for files that
+/// carry no ISRC tag.  Recordings must score at least
+/// [`MUSICBRAINZ_MIN_SCORE`] and, when both lengths are known, lie within
+/// `tolerance_secs` of `duration_ms` (0 disables that check).  Requests are
+/// limited process-wide to MusicBrainz's 1 request per second; the endpoint
+/// can be overridden with `MUSICBRAINZ_API_BASE` (e.g. for tests).
+pub async fn musicbrainz_lookup_isrc(
+    artist: &str,
+    title: &str,
+    album: Option<&str>,
+    duration_ms: Option<u64>,
+    tolerance_secs: u64,
+) -> anyhow::Result<Option<String>> {
+    use std::sync::{Arc, OnceLock};
+    static LIMITER: OnceLock<Option<Arc<crate::api::rate_limit::RateLimiter>>> = OnceLock::new();
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+    if title.trim().is_empty() {
+        return Ok(None);
+    }
+    // Lucene query syntax: quote values and escape embedded quotes.
+    let quoted = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut query = format!("recording:{}", quoted(title.trim()));
+    if !artist.trim().is_empty() {
+        query.push_str(&format!(" AND artist:{}", quoted(artist.trim())));
+    }
+    if let Some(album) = album.map(str::trim).filter(|a| !a.is_empty()) {
+        query.push_str(&format!(" AND release:{}", quoted(album)));
+    }
+    let base = std::env::var("MUSICBRAINZ_API_BASE")
+        .unwrap_or_else(|_| "https://musicbrainz.org/ws/2".into());
+    let url = format!(
+        "{}/recording?query={}&fmt=json&limit=10",
+        base,
+        urlencoding::encode(&query)
+    );
+
+    if let Some(limiter) = LIMITER.get_or_init(|| crate::api::rate_limit::RateLimiter::new(1.0)) {
+        limiter.acquire().await;
+    }
+    let client = CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            // MusicBrainz rejects requests without a descriptive User-Agent.
+            .user_agent(concat!(
+                "music-file-playlist-online-sync/",
+                env!("CARGO_PKG_VERSION"),
+                " ( https://github.com/Balibaloo/music-playlist-online-sync )"
+            ))
+            .build()
+            .unwrap_or_default()
+    });
+    let resp = client.get(&url).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("musicbrainz search failed: {}", resp.status());
+    }
+    let j: serde_json::Value = resp.json().await?;
+    let isrc = j["recordings"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["score"].as_u64().unwrap_or(0) >= MUSICBRAINZ_MIN_SCORE)
+        .filter(|r| match (duration_ms, r["length"].as_u64()) {
+            (Some(local), Some(remote)) if tolerance_secs > 0 => {
+                local.abs_diff(remote) <= tolerance_secs * 1000
+            }
+            _ => true,
+        })
+        .find_map(|r| r["isrcs"].as_array()?.first()?.as_str())
+        .map(|s| s.trim().to_uppercase());
+    Ok(isrc)
 }
 
 /// Compute a lightweight fingerprint for the given file by hashing its
@@ -67,3 +561,322 @@ pub fn hash_file(path: &std::path::Path) -> anyhow::Result<String> {
     }
     Ok(format!("{:x}", hasher.finalize()))
 }
+
+/// Call `f` on every item from up to `threads` threads (at least one) and
+/// return once all are done.  Items are handed out in order; with one
+/// thread this is a plain loop on the calling thread.
+pub fn for_each_parallel<T: Sync>(items: &[T], threads: usize, f: impl Fn(&T) + Sync) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        items.iter().for_each(f);
+        return;
+    }
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                while let Some(item) = items.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    f(item);
+                }
+            });
+        }
+    });
+}
+
+/// Cover file names looked up in a playlist folder, in order of preference
+/// (compared case-insensitively).
+const COVER_FILE_NAMES: &[&str] = &[
+    "folder.jpg",
+    "folder.jpeg",
+    "folder.png",
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+];
+
+/// Find a cover image (`folder.jpg`, `cover.png`, ...) directly inside `folder`.
+pub fn find_cover_image(folder: &std::path::Path) -> Option<std::path::PathBuf> {
+    let files: Vec<std::path::PathBuf> = std::fs::read_dir(folder)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    COVER_FILE_NAMES.iter().find_map(|wanted| {
+        files
+            .iter()
+            .find(|p| {
+                p.file_name()
+                    .map(|n| n.to_string_lossy())
+                    .is_some_and(|n| n.eq_ignore_ascii_case(wanted))
+            })
+            .cloned()
+    })
+}
+
+/// Return `image_bytes` as a JPEG of at most `max_bytes`.  JPEGs already
+/// within the limit are passed through; anything else is re-encoded, first
+/// at lower quality and then at shrinking dimensions until it fits.
+pub fn jpeg_within_size(image_bytes: &[u8], max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+    use anyhow::Context;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::imageops::FilterType;
+
+    let is_jpeg = image_bytes.starts_with(&[0xFF, 0xD8, 0xFF]);
+    if is_jpeg && image_bytes.len() <= max_bytes {
+        return Ok(image_bytes.to_vec());
+    }
+    let mut img = image::load_from_memory(image_bytes)
+        .context("decoding cover image")?
+        .to_rgb8();
+    let mut quality = 90u8;
+    loop {
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, quality)
+            .encode_image(&img)
+            .context("encoding cover image")?;
+        if out.len() <= max_bytes {
+            return Ok(out);
+        }
+        if quality > 60 {
+            quality -= 15;
+            continue;
+        }
+        let (w, h) = img.dimensions();
+        if w <= 64 || h <= 64 {
+            anyhow::bail!("cover image does not fit in {} bytes", max_bytes);
+        }
+        img = image::imageops::resize(&img, w * 3 / 4, h * 3 / 4, FilterType::Triangle);
+    }
+}
+
+/// Default `no_playlist_marker` file name.
+pub const DEFAULT_NO_PLAYLIST_MARKER: &str = ".noplaylist";
+
+/// One compiled `ignore_patterns` entry.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    re: regex::Regex,
+    /// Pattern ended with `/`: only directories match.
+    dir_only: bool,
+    /// Pattern contained a `/`: matched against the root-relative path
+    /// instead of a single path component.
+    anchored: bool,
+}
+
+/// Gitignore-style glob matcher for the `ignore_patterns` config option.
+///
+/// Supported syntax: `*` and `?` within one path component, `**` across
+/// components, `[...]` character classes, a trailing `/` for directories
+/// only, and a leading (or inner) `/` to anchor the pattern at the music
+/// root.  Patterns without a `/` match a file or folder name at any depth,
+/// so `@eaDir` ignores every such folder and everything below it.  Blank
+/// lines and `#` comments are skipped; negation (`!`) is not supported.
+///
+/// With [`IgnoreMatcher::with_marker`], folders containing a marker file
+/// (`no_playlist_marker`) are ignored together with everything below them.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreMatcher {
+    rules: Vec<IgnoreRule>,
+    roots: Vec<std::path::PathBuf>,
+    marker: Option<String>,
+}
+
+/// Translate one glob into an anchored regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::from("^");
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    out.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    out.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(len) => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = class
+                        .strip_prefix('!')
+                        .map_or(class.clone(), |c| format!("^{}", c));
+                    out.push('[');
+                    out.push_str(&class.replace('\\', "\\\\"));
+                    out.push(']');
+                    i += len + 2;
+                    continue;
+                }
+                None => out.push_str("\\["),
+            },
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    out.push('$');
+    out
+}
+
+/// Compiled glob `file_extensions` entries, by pattern (see
+/// [`path_matches_extensions`]).
+static EXTENSION_GLOBS: once_cell::sync::Lazy<
+    std::sync::Mutex<std::collections::HashMap<String, regex::Regex>>,
+> = once_cell::sync::Lazy::new(Default::default);
+
+/// The extension a `file_extensions` entry stands for ("mp3" for "mp3",
+/// ".mp3" and "*.mp3"), or None when it is a glob matched against the whole
+/// file name, such as "*.hi-res.flac".
+fn plain_extension(pattern: &str) -> Option<&str> {
+    let ext = pattern
+        .strip_prefix("*.")
+        .or_else(|| pattern.strip_prefix('.'))
+        .unwrap_or(pattern);
+    (!ext.contains(['*', '?', '[', '.', '/'])).then_some(ext)
+}
+
+/// Check that a `file_extensions` entry compiles.
+pub fn validate_extension_pattern(pattern: &str) -> anyhow::Result<()> {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    if plain_extension(&pattern).is_none() {
+        regex::Regex::new(&glob_to_regex(&pattern))?;
+    }
+    Ok(())
+}
+
+/// Return true if the file name of `path` matches any of the configured
+/// `file_extensions` patterns, case-insensitive.  A plain extension
+/// ("mp3", ".mp3", "*.mp3") is compared with the file's extension; any
+/// other pattern is a glob over the file name (`*`, `?` and `[...]`, as in
+/// `ignore_patterns`), e.g. "*.hi-res.flac".  Playlist files never match,
+/// so they are never tracks and editing one enqueues no track events.
+pub fn path_matches_extensions(path: &std::path::Path, exts: &[String]) -> bool {
+    if crate::playlist::is_playlist_file(path) {
+        return false;
+    }
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let name = name.to_string_lossy().to_ascii_lowercase();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    exts.iter().any(|pat| {
+        let pat = pat.trim().to_ascii_lowercase();
+        if pat.is_empty() {
+            return false;
+        }
+        if let Some(plain) = plain_extension(&pat) {
+            return ext.as_deref() == Some(plain);
+        }
+        let mut globs = EXTENSION_GLOBS.lock().unwrap();
+        if !globs.contains_key(&pat) {
+            match regex::Regex::new(&glob_to_regex(&pat)) {
+                Ok(re) => {
+                    globs.insert(pat.clone(), re);
+                }
+                Err(e) => {
+                    log::warn!("Invalid file_extensions pattern {:?}: {}", pat, e);
+                    return false;
+                }
+            }
+        }
+        globs[&pat].is_match(&name)
+    })
+}
+
+impl IgnoreMatcher {
+    /// Compile `patterns`; anchored patterns are relative to whichever of
+    /// `roots` contains the checked path.  Invalid patterns are logged and
+    /// skipped.
+    pub fn new(patterns: &[String], roots: Vec<std::path::PathBuf>) -> Self {
+        let mut rules = Vec::new();
+        for raw in patterns {
+            let pat = raw.trim();
+            if pat.is_empty() || pat.starts_with('#') {
+                continue;
+            }
+            let dir_only = pat.ends_with('/');
+            let pat = pat.trim_end_matches('/');
+            let anchored = pat.contains('/');
+            let pat = pat.trim_start_matches('/');
+            match regex::Regex::new(&glob_to_regex(pat)) {
+                Ok(re) => rules.push(IgnoreRule {
+                    re,
+                    dir_only,
+                    anchored,
+                }),
+                Err(e) => log::warn!("Invalid ignore pattern {:?}: {}", raw, e),
+            }
+        }
+        Self {
+            rules,
+            roots,
+            marker: None,
+        }
+    }
+
+    /// Also ignore every folder below a root that contains a file named
+    /// `marker`, and all of its descendants.  An empty name disables this.
+    pub fn with_marker(mut self, marker: &str) -> Self {
+        let marker = marker.trim();
+        self.marker = (!marker.is_empty()).then(|| marker.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.marker.is_none()
+    }
+
+    /// True if `path` (a directory when `is_dir`) or any folder above it
+    /// matches an ignore pattern or contains the marker file.
+    pub fn is_ignored(&self, path: &std::path::Path, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let root = self
+            .roots
+            .iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count());
+        if let (Some(marker), Some(root)) = (&self.marker, root) {
+            // The roots themselves are always scanned.
+            let mut dir = if is_dir { Some(path) } else { path.parent() };
+            while let Some(d) = dir.filter(|d| d.starts_with(root) && d != root) {
+                if d.join(marker).is_file() {
+                    return true;
+                }
+                dir = d.parent();
+            }
+        }
+        let rel = root.and_then(|r| path.strip_prefix(r).ok()).unwrap_or(path);
+        let comps: Vec<String> = rel
+            .components()
+            .filter_map(|c| match c {
+                std::path::Component::Normal(s) => Some(s.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        for i in 0..comps.len() {
+            let comp_is_dir = i + 1 < comps.len() || is_dir;
+            let prefix = comps[..=i].join("/");
+            for rule in &self.rules {
+                if rule.dir_only && !comp_is_dir {
+                    continue;
+                }
+                let target = if rule.anchored { &prefix } else { &comps[i] };
+                if rule.re.is_match(target) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
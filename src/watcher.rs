@@ -2,7 +2,7 @@ use crate::config::Config;
 use crate::db;
 use crate::models::EventAction;
 use crate::playlist;
-use crate::util;
+use crate::util::{path_matches_extensions, IgnoreMatcher};
 use anyhow::Context;
 use log::{debug, info, trace, warn};
 use notify::event::RemoveKind;
@@ -13,7 +13,8 @@ use notify::{
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 use walkdir::WalkDir;
@@ -26,6 +27,52 @@ pub struct FolderNode {
     pub tracks: HashSet<PathBuf>,
 }
 
+/// How long after a `FileRemove` a `FileCreate` of the same file name is
+/// still treated as the second half of a move.
+pub const MOVE_DETECTION_WINDOW: Duration = Duration::from_secs(2);
+
+/// Size and inode of a tracked file, used to tell a moved file apart from an
+/// unrelated file that happens to share its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    pub len: u64,
+    pub ino: Option<u64>,
+}
+
+impl FileIdentity {
+    pub fn of(path: &Path) -> Option<Self> {
+        let md = std::fs::metadata(path).ok()?;
+        Some(Self::from_metadata(&md))
+    }
+
+    fn from_metadata(md: &std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let ino = {
+            use std::os::unix::fs::MetadataExt;
+            Some(md.ino())
+        };
+        #[cfg(not(unix))]
+        let ino = None;
+        Self { len: md.len(), ino }
+    }
+
+    /// Same inode (a rename) when both are known; otherwise same size.
+    fn matches(&self, other: &Self) -> bool {
+        match (self.ino, other.ino) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.len == other.len,
+        }
+    }
+}
+
+/// A recently removed track that may reappear elsewhere as a move.
+#[derive(Debug, Clone)]
+struct PendingRemove {
+    path: PathBuf,
+    at: Instant,
+    identity: Option<FileIdentity>,
+}
+
 impl FolderNode {
     pub fn new(path: PathBuf) -> Self {
         Self {
@@ -36,46 +83,28 @@ impl FolderNode {
     }
 }
 
-/// In-memory tree model for the watched root folder.
+/// In-memory tree model for the watched root folder(s).
 /// Stores a map path -> FolderNode for immediate-folder/tracks bookkeeping.
 #[derive(Debug)]
 pub struct InMemoryTree {
+    /// Primary root (the first entry of `roots`).
     pub root: PathBuf,
+    /// Every watched root.
+    pub roots: Vec<PathBuf>,
     pub nodes: HashMap<PathBuf, FolderNode>,
     /// Optional list of regex patterns applied to folder paths (as strings).
     /// If set, only folders whose full path matches at least one pattern are included.
     pub whitelist: Option<Vec<Regex>>,
-}
-
-/// Return true if the given path's extension matches any of the configured
-/// file_extensions patterns ("*.mp3", "mp3", ".mp3"), case-insensitive.
-fn path_matches_extensions(path: &Path, exts: &[String]) -> bool {
-    let ext_os = if let Some(e) = path.extension() {
-        e
-    } else {
-        return false;
-    };
-    let ext = if let Some(s) = ext_os.to_str() {
-        s.to_ascii_lowercase()
-    } else {
-        return false;
-    };
-    for pat in exts {
-        let mut p = pat.trim();
-        if p.is_empty() {
-            continue;
-        }
-        // strip common prefixes: "*." or "."
-        if let Some(stripped) = p.strip_prefix("*.") {
-            p = stripped;
-        } else if let Some(stripped) = p.strip_prefix('.') {
-            p = stripped;
-        }
-        if ext == p.to_ascii_lowercase() {
-            return true;
-        }
-    }
-    false
+    /// `ignore_patterns` matcher; ignored files and folders are never
+    /// tracked, regardless of the whitelist.
+    pub ignore: IgnoreMatcher,
+    /// `file_extensions` the tree was built with; [`Self::rescan_folder`]
+    /// keeps the same files.  None keeps every file.
+    pub file_extensions: Option<Vec<String>>,
+    /// Identity of every tracked file, recorded while the file still exists.
+    file_ids: HashMap<PathBuf, FileIdentity>,
+    /// Tracks removed within the last [`MOVE_DETECTION_WINDOW`].
+    recent_removes: Vec<PendingRemove>,
 }
 
 /// Return true if the path lies inside a Samba temporary folder such as
@@ -85,25 +114,19 @@ fn is_smb_temp_path(path: &Path) -> bool {
 
     for comp in path.components() {
         if let Component::Normal(os) = comp {
-            if let Some(s) = os.to_str() {
-                if s.starts_with(".::TMPNAME:") {
-                    return true;
-                }
+            if os.to_string_lossy().starts_with(".::TMPNAME:") {
+                return true;
             }
         }
     }
     false
 }
 
-/// Compile the colon-separated regex list into regex objects.
-pub fn compile_whitelist(patterns: Option<&str>) -> Option<Vec<Regex>> {
-    let patterns = match patterns.map(|s| s.trim()) {
-        Some(p) if !p.is_empty() => p,
-        _ => return None,
-    };
-
+/// Compile whitelist patterns into regex objects.  Invalid patterns are
+/// rejected by [`Config::validate`]; any that get here are skipped.
+pub fn compile_whitelist(patterns: Option<&[String]>) -> Option<Vec<Regex>> {
     let mut compiled: Vec<Regex> = Vec::new();
-    for pat in patterns.split(':') {
+    for pat in patterns.unwrap_or_default() {
         let pat = pat.trim();
         if pat.is_empty() {
             continue;
@@ -132,22 +155,53 @@ pub fn matches_whitelist(path: &Path, whitelist: &Option<Vec<Regex>>) -> bool {
 
 impl InMemoryTree {
     /// Build the tree by scanning the filesystem under root.
-    /// - If `whitelist` is Some, it is treated as a colon-separated list of regex patterns
+    /// - If `whitelist` is Some, it is a list of regex patterns
     ///   evaluated against the full folder path (e.g. "/raid/.../My Folder"). Only
     ///   directories whose path matches at least one pattern are included.
     /// - Only files whose extensions match the optional file_extensions whitelist are kept.
     pub fn build(
         root: &Path,
-        whitelist: Option<&str>,
+        whitelist: Option<&[String]>,
+        file_extensions: Option<&[String]>,
+    ) -> anyhow::Result<Self> {
+        Self::build_multi(&[root.to_path_buf()], whitelist, file_extensions)
+    }
+
+    /// Like [`Self::build`], scanning every root in `roots` into one tree.
+    /// The first root becomes [`Self::root`].  Folders containing the
+    /// default `.noplaylist` marker are skipped.
+    pub fn build_multi(
+        roots: &[PathBuf],
+        whitelist: Option<&[String]>,
+        file_extensions: Option<&[String]>,
+    ) -> anyhow::Result<Self> {
+        let ignore = IgnoreMatcher::new(&[], roots.to_vec())
+            .with_marker(crate::util::DEFAULT_NO_PLAYLIST_MARKER);
+        Self::build_with_ignore(roots, whitelist, file_extensions, ignore)
+    }
+
+    /// Like [`Self::build_multi`], additionally pruning every file and folder
+    /// matched by `ignore` (ignored folders are not descended into).
+    pub fn build_with_ignore(
+        roots: &[PathBuf],
+        whitelist: Option<&[String]>,
         file_extensions: Option<&[String]>,
+        ignore: IgnoreMatcher,
     ) -> anyhow::Result<Self> {
         let wl = compile_whitelist(whitelist);
 
         let mut nodes: HashMap<PathBuf, FolderNode> = HashMap::new();
+        let mut file_ids: HashMap<PathBuf, FileIdentity> = HashMap::new();
+
+        let walker = roots.iter().flat_map(|root| {
+            WalkDir::new(root)
+                .follow_links(false)
+                .min_depth(0)
+                .into_iter()
+                .filter_entry(|e| !ignore.is_ignored(e.path(), e.file_type().is_dir()))
+        });
 
-        let walker = WalkDir::new(root).follow_links(false).min_depth(0);
-
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        for entry in walker.filter_map(|e| e.ok()) {
             let path = entry.path().to_path_buf();
             if is_smb_temp_path(&path) {
                 continue;
@@ -168,7 +222,7 @@ impl InMemoryTree {
                 let allowed = if let Some(exts) = file_extensions {
                     path_matches_extensions(&path, exts)
                 } else {
-                    true
+                    !playlist::is_playlist_file(&path)
                 };
                 if allowed {
                     if let Some(parent) = entry.path().parent() {
@@ -178,6 +232,9 @@ impl InMemoryTree {
                             .or_insert_with(|| FolderNode::new(parent.clone()));
                         node.tracks.insert(path.clone());
                     }
+                    if let Ok(md) = entry.metadata() {
+                        file_ids.insert(path.clone(), FileIdentity::from_metadata(&md));
+                    }
                 }
             }
         }
@@ -200,12 +257,32 @@ impl InMemoryTree {
         }
 
         Ok(Self {
-            root: root.to_path_buf(),
+            root: roots.first().cloned().unwrap_or_default(),
+            roots: roots.to_vec(),
             nodes,
             whitelist: wl,
+            ignore,
+            file_extensions: file_extensions.map(<[String]>::to_vec),
+            file_ids,
+            recent_removes: Vec::new(),
         })
     }
 
+    /// The watched root containing `path` (the longest match when roots are
+    /// nested), if any.
+    pub fn root_for(&self, path: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count())
+            .map(|r| r.as_path())
+    }
+
+    /// True if `path` is one of the watched roots.
+    pub fn is_root(&self, path: &Path) -> bool {
+        self.roots.iter().any(|r| r == path)
+    }
+
     /// Helper to find the folder node nearest ancestor for a file path.
     pub fn folder_for_path(&self, path: &Path) -> Option<PathBuf> {
         let mut p = if path.is_dir() {
@@ -214,9 +291,10 @@ impl InMemoryTree {
             path.parent().map(|x| x.to_path_buf())?
         };
 
+        let root = self.root_for(&p)?.to_path_buf();
         loop {
-            // Only consider paths under the configured root.
-            if !p.starts_with(&self.root) {
+            // Only consider paths under the root that contains the file.
+            if !p.starts_with(&root) {
                 break;
             }
 
@@ -233,7 +311,7 @@ impl InMemoryTree {
                 return Some(p.clone());
             }
 
-            if p == self.root {
+            if p == root {
                 break;
             }
             if !p.pop() {
@@ -243,10 +321,107 @@ impl InMemoryTree {
         None
     }
 
+    /// Re-read the tracks of `folder` (one level, not its subfolders) from
+    /// disk and bring its node up to date, returning an Add or Remove for
+    /// every track that appeared or disappeared since it was last seen.
+    /// Used when events may have been missed, e.g. for a folder that was
+    /// already known when it is created or renamed again.  Folders that are
+    /// not tracked, ignored or unreadable yield nothing.
+    pub fn rescan_folder(&mut self, folder: &Path) -> Vec<LogicalOp> {
+        let mut out: Vec<LogicalOp> = Vec::new();
+        if !self.nodes.contains_key(folder) || self.ignore.is_ignored(folder, true) {
+            return out;
+        }
+        let read = match std::fs::read_dir(folder) {
+            Ok(read) => read,
+            Err(e) => {
+                debug!("Not rescanning {:?}: {}", folder, e);
+                return out;
+            }
+        };
+        let mut on_disk: HashMap<PathBuf, Option<FileIdentity>> = HashMap::new();
+        let mut children: HashSet<PathBuf> = HashSet::new();
+        for entry in read.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let Ok(ft) = entry.file_type() else {
+                continue;
+            };
+            if is_smb_temp_path(&path) || self.ignore.is_ignored(&path, ft.is_dir()) {
+                continue;
+            }
+            if ft.is_dir() {
+                if self.nodes.contains_key(&path) {
+                    children.insert(path);
+                }
+            } else if ft.is_file()
+                && !playlist::is_playlist_file(&path)
+                && self
+                    .file_extensions
+                    .as_deref()
+                    .is_none_or(|exts| path_matches_extensions(&path, exts))
+            {
+                let identity = entry
+                    .metadata()
+                    .ok()
+                    .map(|md| FileIdentity::from_metadata(&md));
+                on_disk.insert(path, identity);
+            }
+        }
+
+        let node = self.nodes.get_mut(folder).expect("checked above");
+        node.children = children;
+        let mut removed: Vec<PathBuf> = node
+            .tracks
+            .iter()
+            .filter(|t| !on_disk.contains_key(*t))
+            .cloned()
+            .collect();
+        let mut added: Vec<PathBuf> = on_disk
+            .keys()
+            .filter(|t| !node.tracks.contains(*t))
+            .cloned()
+            .collect();
+        removed.sort();
+        added.sort();
+        for track in removed {
+            node.tracks.remove(&track);
+            self.file_ids.remove(&track);
+            out.push(LogicalOp::Remove {
+                playlist_folder: folder.to_path_buf(),
+                track_path: track,
+            });
+        }
+        for track in added {
+            node.tracks.insert(track.clone());
+            if let Some(Some(id)) = on_disk.get(&track) {
+                self.file_ids.insert(track.clone(), *id);
+            }
+            out.push(LogicalOp::Add {
+                playlist_folder: folder.to_path_buf(),
+                track_path: track,
+            });
+        }
+        if !out.is_empty() {
+            info!(
+                "Rescan of {:?} found {} track change(s) missed by the watcher",
+                folder,
+                out.len()
+            );
+        }
+        out
+    }
+
     /// Apply a synthetic event (used in tests) and return list of generated logical operations
     /// that the watcher should enqueue + playlist rebuild targets.
     pub fn apply_synthetic_event(&mut self, op: SyntheticEvent) -> Vec<LogicalOp> {
         let mut out: Vec<LogicalOp> = Vec::new();
+        let op = match self.filter_ignored(op) {
+            Some(op) => op,
+            None => return out,
+        };
+        let now = Instant::now();
+        self.recent_removes
+            .retain(|r| now.duration_since(r.at) <= MOVE_DETECTION_WINDOW);
         match op {
             SyntheticEvent::FileCreate(p) => {
                 if let Some(folder) = self.folder_for_path(&p) {
@@ -258,10 +433,21 @@ impl InMemoryTree {
                         node.tracks.insert(p.clone());
                         self.nodes.insert(folder.clone(), node);
                     }
+                    let identity = FileIdentity::of(&p);
+                    if let Some(id) = identity {
+                        self.file_ids.insert(p.clone(), id);
+                    }
+                    let moved_from = self.take_move_source(&p, identity);
                     out.push(LogicalOp::Add {
                         playlist_folder: folder,
-                        track_path: p,
+                        track_path: p.clone(),
                     });
+                    if let Some(from_path) = moved_from {
+                        out.push(LogicalOp::TrackMoved {
+                            from_path,
+                            to_path: p,
+                        });
+                    }
                 }
             }
             SyntheticEvent::FileRemove(p) => {
@@ -269,6 +455,11 @@ impl InMemoryTree {
                     if let Some(node) = self.nodes.get_mut(&folder) {
                         node.tracks.remove(&p);
                     }
+                    self.recent_removes.push(PendingRemove {
+                        path: p.clone(),
+                        at: now,
+                        identity: self.file_ids.remove(&p),
+                    });
                     out.push(LogicalOp::Remove {
                         playlist_folder: folder,
                         track_path: p,
@@ -276,8 +467,10 @@ impl InMemoryTree {
                 }
             }
             SyntheticEvent::FileRename { from, to } => {
-                // treat as remove then add
-                if let Some(from_folder) = self.folder_for_path(&from) {
+                // treat as remove then add; an ignored side is skipped
+                let from_ignored = self.ignore.is_ignored(&from, false);
+                let to_ignored = self.ignore.is_ignored(&to, false);
+                if let Some(from_folder) = self.folder_for_path(&from).filter(|_| !from_ignored) {
                     if let Some(node) = self.nodes.get_mut(&from_folder) {
                         node.tracks.remove(&from);
                     }
@@ -286,7 +479,7 @@ impl InMemoryTree {
                         track_path: from.clone(),
                     });
                 }
-                if let Some(to_folder) = self.folder_for_path(&to) {
+                if let Some(to_folder) = self.folder_for_path(&to).filter(|_| !to_ignored) {
                     if let Some(node) = self.nodes.get_mut(&to_folder) {
                         node.tracks.insert(to.clone());
                     }
@@ -295,14 +488,42 @@ impl InMemoryTree {
                         track_path: to.clone(),
                     });
                 }
+                if let Some(id) = self.file_ids.remove(&from) {
+                    self.file_ids.insert(to.clone(), id);
+                }
+                if !from_ignored && !to_ignored {
+                    out.push(LogicalOp::TrackMoved {
+                        from_path: from,
+                        to_path: to,
+                    });
+                }
             }
             SyntheticEvent::FolderRename { from, to } => {
                 // handle folder rename/move -> emit PlaylistRename
                 let from_folder = from.clone();
                 let to_folder = to.clone();
-                // move node entry if exists
-                if self.nodes.contains_key(&from_folder) {
-                    let node = self.nodes.remove(&from_folder).unwrap();
+                // move node entry if exists, rebasing its paths
+                let known =
+                    self.nodes.contains_key(&from_folder) || self.nodes.contains_key(&to_folder);
+                if let Some(mut node) = self.nodes.remove(&from_folder) {
+                    let rebase = |p: &PathBuf| match p.strip_prefix(&from_folder) {
+                        Ok(rel) => to_folder.join(rel),
+                        Err(_) => p.clone(),
+                    };
+                    node.path = to_folder.clone();
+                    node.tracks = node.tracks.iter().map(rebase).collect();
+                    node.children = node.children.iter().map(rebase).collect();
+                    let moved: Vec<PathBuf> = self
+                        .file_ids
+                        .keys()
+                        .filter(|p| p.parent() == Some(from_folder.as_path()))
+                        .cloned()
+                        .collect();
+                    for p in moved {
+                        if let Some(id) = self.file_ids.remove(&p) {
+                            self.file_ids.insert(rebase(&p), id);
+                        }
+                    }
                     self.nodes.entry(to_folder.clone()).or_insert(node);
                 } else {
                     self.nodes
@@ -319,9 +540,14 @@ impl InMemoryTree {
                     from_folder: from_folder.clone(),
                     to_folder: to_folder.clone(),
                 });
+                if known {
+                    out.extend(self.rescan_folder(&to_folder));
+                }
             }
             SyntheticEvent::FolderCreate(p) => {
-                // create node
+                // create node; a folder we already had may have changed
+                // while its events were missed
+                let known = self.nodes.contains_key(&p);
                 self.nodes
                     .entry(p.clone())
                     .or_insert_with(|| FolderNode::new(p.clone()));
@@ -331,7 +557,12 @@ impl InMemoryTree {
                         node.children.insert(p.clone());
                     }
                 }
-                out.push(LogicalOp::Create { playlist_folder: p });
+                out.push(LogicalOp::Create {
+                    playlist_folder: p.clone(),
+                });
+                if known {
+                    out.extend(self.rescan_folder(&p));
+                }
             }
             SyntheticEvent::FolderRemove(p) => {
                 // remove node and children membership
@@ -346,6 +577,113 @@ impl InMemoryTree {
         }
         out
     }
+
+    /// Synthetic events that turn this tree into `newer` (a fresh scan),
+    /// used by the polling watcher.  Removals come first (tracks, then
+    /// folders deepest-first) so a track that changed folders is seen as a
+    /// move, followed by creations (folders shallowest-first, then tracks).
+    pub fn diff_events(&self, newer: &InMemoryTree) -> Vec<SyntheticEvent> {
+        let tracks = |t: &InMemoryTree| -> HashSet<PathBuf> {
+            t.nodes
+                .values()
+                .flat_map(|n| n.tracks.iter().cloned())
+                .collect()
+        };
+        let (old_tracks, new_tracks) = (tracks(self), tracks(newer));
+        let depth = |p: &PathBuf| p.components().count();
+
+        let mut removed_tracks: Vec<&PathBuf> = old_tracks.difference(&new_tracks).collect();
+        removed_tracks.sort();
+        let mut removed_folders: Vec<&PathBuf> = self
+            .nodes
+            .keys()
+            .filter(|p| !newer.nodes.contains_key(*p))
+            .collect();
+        removed_folders.sort_by(|a, b| depth(b).cmp(&depth(a)).then(a.cmp(b)));
+        let mut created_folders: Vec<&PathBuf> = newer
+            .nodes
+            .keys()
+            .filter(|p| !self.nodes.contains_key(*p))
+            .collect();
+        created_folders.sort_by(|a, b| depth(a).cmp(&depth(b)).then(a.cmp(b)));
+        let mut created_tracks: Vec<&PathBuf> = new_tracks.difference(&old_tracks).collect();
+        created_tracks.sort();
+
+        let mut out: Vec<SyntheticEvent> = Vec::new();
+        out.extend(
+            removed_tracks
+                .into_iter()
+                .map(|p| SyntheticEvent::FileRemove(p.clone())),
+        );
+        out.extend(
+            removed_folders
+                .into_iter()
+                .map(|p| SyntheticEvent::FolderRemove(p.clone())),
+        );
+        out.extend(
+            created_folders
+                .into_iter()
+                .map(|p| SyntheticEvent::FolderCreate(p.clone())),
+        );
+        out.extend(
+            created_tracks
+                .into_iter()
+                .map(|p| SyntheticEvent::FileCreate(p.clone())),
+        );
+        out
+    }
+
+    /// If `to` looks like the destination of a track removed moments ago
+    /// (same file name in another folder, matching identity when both are
+    /// known), consume that pending remove and return its path.
+    fn take_move_source(&mut self, to: &Path, identity: Option<FileIdentity>) -> Option<PathBuf> {
+        let name = to.file_name()?;
+        let idx = self.recent_removes.iter().rposition(|r| {
+            r.path.file_name() == Some(name)
+                && r.path.parent() != to.parent()
+                && match (r.identity, identity) {
+                    (Some(a), Some(b)) => a.matches(&b),
+                    _ => true,
+                }
+        })?;
+        let from = self.recent_removes.remove(idx).path;
+        debug!("Correlated remove {:?} + create {:?} as a move", from, to);
+        Some(from)
+    }
+
+    /// Drop events for ignored paths.  A folder moved out of (or into) an
+    /// ignored location becomes a plain remove (or create).
+    fn filter_ignored(&self, op: SyntheticEvent) -> Option<SyntheticEvent> {
+        if self.ignore.is_empty() {
+            return Some(op);
+        }
+        let ignored = |p: &Path, is_dir: bool| {
+            let hit = self.ignore.is_ignored(p, is_dir);
+            if hit {
+                trace!("Ignoring event for {:?} (matches ignore_patterns)", p);
+            }
+            hit
+        };
+        match op {
+            SyntheticEvent::FileCreate(ref p) | SyntheticEvent::FileRemove(ref p) => {
+                (!ignored(p, false)).then_some(op)
+            }
+            SyntheticEvent::FolderCreate(ref p) | SyntheticEvent::FolderRemove(ref p) => {
+                (!ignored(p, true)).then_some(op)
+            }
+            SyntheticEvent::FileRename { ref from, ref to } => {
+                (!(ignored(from, false) && ignored(to, false))).then_some(op)
+            }
+            SyntheticEvent::FolderRename { from, to } => {
+                match (ignored(&from, true), ignored(&to, true)) {
+                    (false, false) => Some(SyntheticEvent::FolderRename { from, to }),
+                    (true, false) => Some(SyntheticEvent::FolderCreate(to)),
+                    (false, true) => Some(SyntheticEvent::FolderRemove(from)),
+                    (true, true) => None,
+                }
+            }
+        }
+    }
 }
 
 /// Logical operations produced by watcher when interpreting FS events.
@@ -370,6 +708,12 @@ pub enum LogicalOp {
         from_folder: PathBuf,
         to_folder: PathBuf,
     },
+    // A track file moved (emitted after its Remove/Add ops) so the cached
+    // remote match can follow it instead of being searched for again.
+    TrackMoved {
+        from_path: PathBuf,
+        to_path: PathBuf,
+    },
 }
 
 /// Synthetic event type for unit tests (so tests don't rely on real notify events).
@@ -383,45 +727,181 @@ pub enum SyntheticEvent {
     FolderRemove(PathBuf),
 }
 
+/// Scan the configured roots into a tree, respecting the local whitelist,
+/// `file_extensions` and `ignore_patterns`.
+fn scan_tree(cfg: &Config) -> anyhow::Result<InMemoryTree> {
+    let roots = cfg.roots();
+    let local_whitelist = cfg.effective_local_whitelist();
+    InMemoryTree::build_with_ignore(
+        &roots,
+        if local_whitelist.is_empty() {
+            None
+        } else {
+            Some(local_whitelist)
+        },
+        Some(&cfg.file_extensions),
+        cfg.ignore_matcher(),
+    )
+    .with_context(|| format!("building in-memory tree from root(s) {:?}", roots))
+}
+
+/// Express a synthetic event as the notify event the watcher callback would
+/// receive for it, so the polling watcher can reuse the callback unchanged.
+fn synthetic_to_notify(ev: SyntheticEvent) -> NotifyEvent {
+    use notify::event::CreateKind;
+    match ev {
+        SyntheticEvent::FileCreate(p) => {
+            NotifyEvent::new(EventKind::Create(CreateKind::File)).add_path(p)
+        }
+        SyntheticEvent::FolderCreate(p) => {
+            NotifyEvent::new(EventKind::Create(CreateKind::Folder)).add_path(p)
+        }
+        SyntheticEvent::FileRemove(p) => {
+            NotifyEvent::new(EventKind::Remove(RemoveKind::File)).add_path(p)
+        }
+        SyntheticEvent::FolderRemove(p) => {
+            NotifyEvent::new(EventKind::Remove(RemoveKind::Folder)).add_path(p)
+        }
+        SyntheticEvent::FileRename { from, to } | SyntheticEvent::FolderRename { from, to } => {
+            NotifyEvent::new(EventKind::Modify(notify::event::ModifyKind::Name(
+                notify::event::RenameMode::Both,
+            )))
+            .add_path(from)
+            .add_path(to)
+        }
+    }
+}
+
+/// Sleep for `duration` in short steps; returns true as soon as `shutdown`
+/// is set.
+fn sleep_unless_shutdown(duration: Duration, shutdown: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if shutdown.load(Ordering::SeqCst) {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
+
+/// Polling watcher: rescan every `poll_interval_sec`, diff against the live
+/// tree and feed the differences through `handle` as notify events.  Each
+/// rescan uses the current `settings`.  Returns once `shutdown` is set.
+fn run_poll_loop<F>(
+    settings: &SharedSettings,
+    tree: Arc<Mutex<InMemoryTree>>,
+    shutdown: &AtomicBool,
+    mut handle: F,
+) where
+    F: FnMut(NotifyResult<NotifyEvent>),
+{
+    let cfg = settings.read().unwrap().cfg.clone();
+    let interval = Duration::from_secs(cfg.poll_interval_sec.max(1));
+    info!(
+        "Polling root(s) {:?} for changes every {:?}",
+        cfg.roots(),
+        interval
+    );
+    while !sleep_unless_shutdown(interval, shutdown) {
+        let live = settings.read().unwrap().clone();
+        let scanned = match scan_tree(&live.cfg) {
+            Ok(t) => t,
+            Err(e) => {
+                warn!("Polling rescan failed: {:#}", e);
+                continue;
+            }
+        };
+        let events = match tree.lock() {
+            Ok(t) => t.diff_events(&scanned),
+            Err(_) => continue,
+        };
+        if !events.is_empty() {
+            debug!("Polling rescan found {} change(s)", events.len());
+        }
+        for ev in events {
+            handle(Ok(synthetic_to_notify(ev)));
+        }
+    }
+}
+
+/// True if `e` means the inotify watch limit was exhausted.
+fn is_watch_limit_error(e: &notify::Error) -> bool {
+    match &e.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        // ENOSPC from inotify_add_watch
+        notify::ErrorKind::Io(io) => io.raw_os_error() == Some(28),
+        _ => false,
+    }
+}
+
+/// Number of folders a recursive inotify watch on `roots` needs (one watch
+/// per directory, whitelist and ignore patterns notwithstanding).
+fn count_watch_folders(roots: &[PathBuf]) -> usize {
+    roots
+        .iter()
+        .flat_map(|root| WalkDir::new(root).follow_links(false))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .count()
+}
+
+/// The per-user inotify watch limit, when the platform exposes one.
+fn inotify_max_user_watches() -> Option<usize> {
+    std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn warn_watch_limit(cfg: &Config, folder_count: usize, limit: Option<usize>) {
+    warn!(
+        "inotify watch limit reached: {} folder(s) to watch, fs.inotify.max_user_watches = {}. \
+         Raise the limit (e.g. `sysctl fs.inotify.max_user_watches=524288`, persisted in \
+         /etc/sysctl.d/) or set watch_mode = \"poll\". Falling back to polling every {}s.",
+        folder_count,
+        limit.map_or_else(|| "unknown".to_string(), |l| l.to_string()),
+        cfg.poll_interval_sec
+    );
+}
+
 /// Internal helper: perform the initial DB open, tree build, and playlist
 /// writes. This is used by the long-running watcher as well as tests that
 /// only need to verify the initial playlist generation.
 fn build_initial_tree_and_playlists(cfg: &Config) -> anyhow::Result<InMemoryTree> {
-    info!("Starting watcher with root {:?}", cfg.root_folder);
+    let roots = cfg.roots();
+    info!("Starting watcher with root(s) {:?}", roots);
     // Open DB (blocking)
     let _conn = db::open_or_create(&cfg.db_path)
         .with_context(|| format!("opening or creating DB at {}", cfg.db_path.display()))?;
 
     // Build initial in-memory tree, respecting optional whitelist and file_extensions
-    let local_whitelist = cfg.effective_local_whitelist();
-    let tree = InMemoryTree::build(
-        &cfg.root_folder,
-        if local_whitelist.is_empty() {
-            None
-        } else {
-            Some(local_whitelist)
-        },
-        Some(&cfg.file_extensions),
-    )
-    .with_context(|| {
-        format!(
-            "building in-memory tree from root {}",
-            cfg.root_folder.display()
-        )
-    })?;
+    let tree = scan_tree(cfg)?;
+    let ignore = tree.ignore.clone();
     info!("Initial scan complete: {} folders", tree.nodes.len());
 
-    // Initial playlist writes (flat or linked mode)
-    for (folder, _node) in tree.nodes.iter() {
-        let folder_name = folder.file_name().and_then(|s| s.to_str()).unwrap_or("");
-        let rel = folder
-            .strip_prefix(&cfg.root_folder)
-            .unwrap_or(folder)
-            .to_path_buf();
-
-        // Local template uses folder_name and path_to_parent; the logical
-        // playlist key used in the DB is the folder path relative to root
-        // (rel.display()).
+    // Initial playlist writes (flat or linked mode); folders are
+    // independent, so several are written at once.
+    let folders: Vec<&PathBuf> = tree.nodes.keys().collect();
+    info!(
+        "Writing {} initial playlist(s) with up to {} thread(s)",
+        folders.len(),
+        cfg.playlist_write_threads
+    );
+    crate::util::for_each_parallel(&folders, cfg.playlist_write_threads, |folder| {
+        let folder_name = folder
+            .file_name()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default();
+        let rel = cfg.root_relative(folder);
+
+        // Local template uses folder_name and path_to_parent (relative to
+        // the folder's own root); the logical playlist key used in the DB
+        // comes from `Config::playlist_key_for_folder`.
         let path_to_parent = rel
             .parent()
             .map(|p| p.to_path_buf())
@@ -436,30 +916,45 @@ fn build_initial_tree_and_playlists(cfg: &Config) -> anyhow::Result<InMemoryTree
             s
         };
 
-        let playlist_name = util::expand_template(
-            &cfg.local_playlist_template,
-            folder_name,
-            &path_to_parent_str,
-        );
+        let playlist_name = cfg.local_playlist_file_name(folder, &folder_name, &path_to_parent_str);
         let playlist_path = folder.join(playlist_name);
         if cfg.playlist_mode == "flat" {
-            if let Err(e) = playlist::write_flat_playlist(
+            if let Err(e) = playlist::write_flat_playlist_as(
+                &cfg.playlist_format,
                 folder,
                 &playlist_path,
                 &cfg.playlist_order_mode,
                 &cfg.file_extensions,
+                &ignore,
             ) {
                 warn!(
                     "Failed to write initial playlist {:?}: {}",
                     playlist_path, e
                 );
             }
+        } else if cfg.playlist_mode == "linked_with_local_tracks" {
+            if let Err(e) = playlist::write_linked_playlist_with_local_tracks(
+                folder,
+                &playlist_path,
+                &cfg.linked_reference_format,
+                &cfg.local_playlist_template,
+                &cfg.playlist_order_mode,
+                &cfg.file_extensions,
+                &ignore,
+            ) {
+                warn!(
+                    "Failed to write initial linked playlist {:?}: {}",
+                    playlist_path, e
+                );
+            }
         } else {
-            if let Err(e) = playlist::write_linked_playlist(
+            if let Err(e) = playlist::write_linked_playlist_filtered(
                 folder,
                 &playlist_path,
                 &cfg.linked_reference_format,
                 &cfg.local_playlist_template,
+                &cfg.file_extensions,
+                &ignore,
             ) {
                 warn!(
                     "Failed to write initial linked playlist {:?}: {}",
@@ -467,7 +962,7 @@ fn build_initial_tree_and_playlists(cfg: &Config) -> anyhow::Result<InMemoryTree
                 );
             }
         }
-    }
+    });
 
     Ok(tree)
 }
@@ -479,44 +974,371 @@ pub fn run_watcher_initial_pass(cfg: &Config) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The config-derived state the watcher threads read for every event and
+/// debounce pass, replaced as a whole by a SIGHUP reload.
+struct LiveSettings {
+    cfg: Config,
+    ignore: IgnoreMatcher,
+    remote_whitelist: Option<Vec<Regex>>,
+}
+
+impl LiveSettings {
+    fn new(cfg: Config) -> Self {
+        Self {
+            ignore: cfg.ignore_matcher(),
+            remote_whitelist: compile_whitelist(Some(cfg.effective_remote_whitelist())),
+            cfg,
+        }
+    }
+}
+
+type SharedSettings = Arc<RwLock<Arc<LiveSettings>>>;
+
+/// Outcome of comparing a re-read config with the one a watcher runs with:
+/// the config to switch to and the changes, each as "field: old -> new".
+#[derive(Debug)]
+pub struct ConfigReload {
+    pub config: Config,
+    /// Changes the running watcher picks up.
+    pub applied: Vec<String>,
+    /// Changes that only take effect after a restart; `config` keeps the
+    /// running values for them.
+    pub needs_restart: Vec<String>,
+}
+
+/// Compare `new` with the `running` config for a SIGHUP reload.  Debounce
+/// timing, whitelists, ignore patterns, `file_extensions`, the local
+/// playlist template and order, and the worker triggers are picked up.
+/// The roots, DB, watch and metrics settings and the playlist mode and
+/// format decide what is watched and which files are written, so they
+/// need a restart.  Settings only the worker reads are not listed: each
+/// worker run loads the config itself.
+pub fn plan_config_reload(running: &Config, new: Config) -> ConfigReload {
+    fn describe(field: &str, old: &dyn std::fmt::Debug, new: &dyn std::fmt::Debug) -> String {
+        format!("{}: {:?} -> {:?}", field, old, new)
+    }
+    let mut config = new;
+    let mut applied = Vec::new();
+    let mut needs_restart = Vec::new();
+    macro_rules! restart_only {
+        ($($field:ident),+) => {$(
+            if running.$field != config.$field {
+                needs_restart.push(describe(stringify!($field), &running.$field, &config.$field));
+                config.$field = running.$field.clone();
+            }
+        )+};
+    }
+    macro_rules! reloadable {
+        ($($field:ident),+) => {$(
+            if running.$field != config.$field {
+                applied.push(describe(stringify!($field), &running.$field, &config.$field));
+            }
+        )+};
+    }
+    restart_only!(
+        root_folder,
+        root_folders,
+        db_path,
+        watch_mode,
+        poll_interval_sec,
+        metrics_listen,
+        playlist_mode,
+        playlist_format
+    );
+    reloadable!(
+        debounce_ms,
+        whitelist,
+        local_whitelist,
+        remote_whitelist,
+        ignore_patterns,
+        no_playlist_marker,
+        local_only_marker,
+        file_extensions,
+        local_playlist_template,
+        linked_reference_format,
+        playlist_order_mode,
+        watcher_instant_trigger_threshold,
+        watcher_deferred_trigger_delay_sec
+    );
+    ConfigReload {
+        config,
+        applied,
+        needs_restart,
+    }
+}
+
+/// Re-read `config_path` and switch the running watcher to it (see
+/// [`plan_config_reload`]), logging each change.  The in-memory tree takes
+/// the new local whitelist and ignore patterns for later events; folders
+/// already scanned are not rescanned.  A config that fails to load or
+/// validate is logged and the running one kept.
+fn reload_settings(config_path: &Path, settings: &SharedSettings, tree: &Mutex<InMemoryTree>) {
+    let new = match Config::from_path(config_path) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            warn!(
+                "Config reload from {:?} failed; keeping the running config: {:#}",
+                config_path, e
+            );
+            return;
+        }
+    };
+    let running = settings.read().unwrap().cfg.clone();
+    let reload = plan_config_reload(&running, new);
+    for change in &reload.applied {
+        info!("Config reload: {}", change);
+    }
+    for change in &reload.needs_restart {
+        warn!(
+            "Config reload: {} needs a watcher restart; keeping the running value",
+            change
+        );
+    }
+    if reload.applied.is_empty() && reload.needs_restart.is_empty() {
+        info!("Config reload: no watcher settings changed");
+    }
+    let live = LiveSettings::new(reload.config);
+    if let Ok(mut t) = tree.lock() {
+        t.whitelist = compile_whitelist(Some(live.cfg.effective_local_whitelist()));
+        t.ignore = live.ignore.clone();
+        t.file_extensions = Some(live.cfg.file_extensions.clone());
+    }
+    *settings.write().unwrap() = Arc::new(live);
+}
+
+/// Set `shutdown` on SIGINT (Ctrl-C) or SIGTERM (`systemctl stop`),
+/// `flush_now` on every SIGUSR1 (`watcher --flush-now`) and `reload` on
+/// every SIGHUP.
+fn spawn_signal_listener(
+    shutdown: Arc<AtomicBool>,
+    flush_now: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        let rt = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                warn!("Failed to start the signal listener: {}", e);
+                return;
+            }
+        };
+        rt.block_on(async {
+            #[cfg(unix)]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
+                let (mut term, mut usr1, mut hup) = match (
+                    signal(SignalKind::terminate()),
+                    signal(SignalKind::user_defined1()),
+                    signal(SignalKind::hangup()),
+                ) {
+                    (Ok(term), Ok(usr1), Ok(hup)) => (term, usr1, hup),
+                    (term, usr1, hup) => {
+                        warn!(
+                            "Failed to listen for SIGTERM/SIGUSR1/SIGHUP: {:?} {:?} {:?}",
+                            term.err(),
+                            usr1.err(),
+                            hup.err()
+                        );
+                        let _ = tokio::signal::ctrl_c().await;
+                        info!("SIGINT received; stopping watcher");
+                        return;
+                    }
+                };
+                loop {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {
+                            info!("SIGINT received; stopping watcher");
+                            break;
+                        }
+                        _ = term.recv() => {
+                            info!("SIGTERM received; stopping watcher");
+                            break;
+                        }
+                        _ = usr1.recv() => {
+                            info!("SIGUSR1 received; flushing pending playlist writes");
+                            flush_now.store(true, Ordering::SeqCst);
+                        }
+                        _ = hup.recv() => {
+                            info!("SIGHUP received; reloading config");
+                            reload.store(true, Ordering::SeqCst);
+                        }
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = (flush_now, reload);
+                let _ = tokio::signal::ctrl_c().await;
+                info!("Ctrl-C received; stopping watcher");
+            }
+        });
+        shutdown.store(true, Ordering::SeqCst);
+    });
+}
+
+/// File holding the running watcher's process id, next to the DB.
+pub fn watcher_pid_path(cfg: &Config) -> PathBuf {
+    cfg.db_path.with_file_name("watcher.pid")
+}
+
+/// Ask the running watcher (found through [`watcher_pid_path`]) to flush its
+/// pending debounced playlist writes now; returns its process id.
+pub fn request_flush(cfg: &Config) -> anyhow::Result<u32> {
+    let pid_path = watcher_pid_path(cfg);
+    let pid: u32 = std::fs::read_to_string(&pid_path)
+        .with_context(|| format!("reading {:?}; is the watcher running?", pid_path))?
+        .trim()
+        .parse()
+        .with_context(|| format!("parsing pid in {:?}", pid_path))?;
+    let status = std::process::Command::new("kill")
+        .args(["-USR1", &pid.to_string()])
+        .status()
+        .context("running kill")?;
+    if !status.success() {
+        anyhow::bail!(
+            "could not signal watcher pid {} (stale {:?}?)",
+            pid,
+            pid_path
+        );
+    }
+    Ok(pid)
+}
+
+/// Tell the debounce thread to write every pending playlist now and wait
+/// for it (and its queued DB inserts) to finish.
+fn finish_shutdown(
+    flush: &AtomicBool,
+    debounce_thread: thread::JoinHandle<()>,
+) -> anyhow::Result<()> {
+    flush.store(true, Ordering::SeqCst);
+    if debounce_thread.join().is_err() {
+        warn!("Debounce thread panicked during shutdown");
+    }
+    info!("Watcher stopped");
+    Ok(())
+}
+
 /// Start the watcher; this is the long-running entry point called by the CLI.
-pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
+/// Runs until SIGINT/SIGTERM, then flushes pending playlist writes and
+/// returns `Ok(())`.  SIGUSR1 flushes them without stopping, and SIGHUP
+/// reloads `config_path` (see [`plan_config_reload`]).
+pub fn run_watcher(cfg: &Config, config_path: &Path) -> anyhow::Result<()> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flush_now = Arc::new(AtomicBool::new(false));
+    let reload = Arc::new(AtomicBool::new(false));
+    spawn_signal_listener(shutdown.clone(), flush_now.clone(), reload.clone());
+    let pid_path = watcher_pid_path(cfg);
+    if let Err(e) = std::fs::write(&pid_path, std::process::id().to_string()) {
+        warn!(
+            "Failed to write {:?} (watcher --flush-now will not find this watcher): {}",
+            pid_path, e
+        );
+    }
+    let result = run_watcher_inner(cfg, shutdown, flush_now, Some((config_path, reload)));
+    let _ = std::fs::remove_file(&pid_path);
+    result
+}
+
+/// [`run_watcher`] driven by flags instead of signals: stops once `shutdown`
+/// is set and flushes pending playlist writes whenever `flush_now` is set
+/// (the flag is cleared again once the flush has been taken).
+pub fn run_watcher_until(
+    cfg: &Config,
+    shutdown: Arc<AtomicBool>,
+    flush_now: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    run_watcher_inner(cfg, shutdown, flush_now, None)
+}
+
+/// [`run_watcher_until`] that also reloads `config_path` whenever `reload`
+/// is set, as SIGHUP does for [`run_watcher`].  The flag is cleared once
+/// the reload has been taken.
+pub fn run_watcher_until_reloading(
+    cfg: &Config,
+    config_path: &Path,
+    shutdown: Arc<AtomicBool>,
+    flush_now: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    run_watcher_inner(cfg, shutdown, flush_now, Some((config_path, reload)))
+}
+
+fn run_watcher_inner(
+    cfg: &Config,
+    shutdown: Arc<AtomicBool>,
+    flush_now: Arc<AtomicBool>,
+    reload: Option<(&Path, Arc<AtomicBool>)>,
+) -> anyhow::Result<()> {
     // Perform initial scan and playlist writes, then keep the watcher running.
     let tree = build_initial_tree_and_playlists(cfg)?;
+    // One pool for the short-lived enqueue threads instead of opening (and
+    // migrating) the DB for every event.
+    let db_pool = db::create_pool(&cfg.db_path)?;
+    // Stops on its own once `shutdown` is set.
+    let _metrics_thread = if cfg.metrics_listen.is_empty() {
+        None
+    } else {
+        Some(crate::metrics::serve(
+            &cfg.metrics_listen,
+            db_pool.clone(),
+            shutdown.clone(),
+        )?)
+    };
     // Shared debounce queue: map playlist folder -> (earliest_due Instant, accumulated file-event count).
     // Count is incremented only for direct (leaf) file events; ancestor-only insertions use count=0.
     let debounce_map: Arc<Mutex<HashMap<PathBuf, (Instant, usize)>>> =
         Arc::new(Mutex::new(HashMap::new()));
-    let _debounce_ms = cfg.debounce_ms;
 
     // Wrap in-memory tree in Arc<Mutex<...>> so notify callback can update it concurrently
     let tree = Arc::new(Mutex::new(tree));
-    let remote_whitelist = Arc::new(compile_whitelist(Some(cfg.effective_remote_whitelist())));
+    let settings: SharedSettings = Arc::new(RwLock::new(Arc::new(LiveSettings::new(cfg.clone()))));
+    let reload = reload.map(|(path, requested)| (path.to_path_buf(), requested));
 
     // Shared collection of join handles for short-lived DB-enqueue threads.
-    let enqueue_handles: Arc<Mutex<Vec<std::thread::JoinHandle<()>>>> = Arc::new(Mutex::new(Vec::new()));
+    let enqueue_handles: Arc<Mutex<Vec<std::thread::JoinHandle<()>>>> =
+        Arc::new(Mutex::new(Vec::new()));
+
+    // Set once the watcher has stopped delivering events: the debounce thread
+    // then writes every pending playlist regardless of its timer and exits.
+    let flush = Arc::new(AtomicBool::new(false));
 
     // Spawn debounce worker thread: writes playlists when their debounce timer elapses and enqueues
     // a generic Create event for the playlist.
-    {
+    let debounce_thread = {
         let debounce_map = debounce_map.clone();
-        let cfg = cfg.clone();
-        let db_path = cfg.db_path.clone();
-        let _tree = tree.clone();
-        let remote_whitelist = remote_whitelist.clone();
+        let flush = flush.clone();
+        let flush_now = flush_now.clone();
+        let settings = settings.clone();
+        let db_pool = db_pool.clone();
+        let tree = tree.clone();
         let enqueue_handles = enqueue_handles.clone();
         thread::spawn(move || {
             // Deadline for deferred worker spawn after an over-threshold batch.
             // None means no pending deferred trigger.
             let mut deferred_trigger: Option<Instant> = None;
             loop {
+                if let Some((config_path, requested)) = &reload {
+                    if requested.swap(false, Ordering::SeqCst) {
+                        reload_settings(config_path, &settings, &tree);
+                    }
+                }
+                let live = settings.read().unwrap().clone();
+                let (cfg, ignore, remote_whitelist) =
+                    (&live.cfg, &live.ignore, &live.remote_whitelist);
+                let flushing = flush.load(Ordering::SeqCst);
+                // A forced flush takes every entry now; entries are removed from the
+                // map either way, so the timer path never writes them a second time.
+                let forced = flush_now.swap(false, Ordering::SeqCst);
                 // collect due playlists
                 let due: Vec<(PathBuf, usize)> = {
                     let mut guard = debounce_map.lock().unwrap();
                     let now = Instant::now();
                     let mut ready = Vec::new();
                     guard.retain(|folder, &mut (t, count)| {
-                        if t <= now {
+                        if t <= now || flushing || forced {
                             ready.push((folder.clone(), count));
                             false // remove from map
                         } else {
@@ -525,17 +1347,20 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                     });
                     ready
                 };
+                if forced {
+                    info!("Flushing {} pending playlist(s) on request", due.len());
+                }
 
                 // Sum leaf file-event counts across all due folders.
                 // Each file Add or Remove contributes 1; a file move (Remove+Add) contributes 2.
                 let total_events: usize = due.iter().map(|(_, c)| *c).sum();
                 for (folder, _) in due {
                     // Write local playlist and enqueue a generic create/update event for playlist (watcher enqueues per-file ops too)
-                    let folder_name = folder.file_name().and_then(|s| s.to_str()).unwrap_or("");
-                    let rel = folder
-                        .strip_prefix(&cfg.root_folder)
-                        .unwrap_or(&folder)
-                        .to_path_buf();
+                    let folder_name = folder
+                        .file_name()
+                        .map(|s| s.to_string_lossy())
+                        .unwrap_or_default();
+                    let rel = cfg.root_relative(&folder);
 
                     let path_to_parent = rel
                         .parent()
@@ -551,29 +1376,42 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                         s
                     };
 
-                    let playlist_name = util::expand_template(
-                        &cfg.local_playlist_template,
-                        folder_name,
-                        &path_to_parent_str,
-                    );
+                    let playlist_name =
+                        cfg.local_playlist_file_name(&folder, &folder_name, &path_to_parent_str);
                     let playlist_path = folder.join(&playlist_name);
 
                     // choose playlist mode
                     if cfg.playlist_mode == "flat" {
-                        if let Err(e) = playlist::write_flat_playlist(
+                        if let Err(e) = playlist::write_flat_playlist_as(
+                            &cfg.playlist_format,
                             &folder,
                             &playlist_path,
                             &cfg.playlist_order_mode,
                             &cfg.file_extensions,
+                            ignore,
                         ) {
                             warn!("Failed to write playlist {:?}: {}", playlist_path, e);
                         }
+                    } else if cfg.playlist_mode == "linked_with_local_tracks" {
+                        if let Err(e) = playlist::write_linked_playlist_with_local_tracks(
+                            &folder,
+                            &playlist_path,
+                            &cfg.linked_reference_format,
+                            &cfg.local_playlist_template,
+                            &cfg.playlist_order_mode,
+                            &cfg.file_extensions,
+                            ignore,
+                        ) {
+                            warn!("Failed to write linked playlist {:?}: {}", playlist_path, e);
+                        }
                     } else {
-                        if let Err(e) = playlist::write_linked_playlist(
+                        if let Err(e) = playlist::write_linked_playlist_filtered(
                             &folder,
                             &playlist_path,
                             &cfg.linked_reference_format,
                             &cfg.local_playlist_template,
+                            &cfg.file_extensions,
+                            ignore,
                         ) {
                             warn!("Failed to write linked playlist {:?}: {}", playlist_path, e);
                         }
@@ -581,13 +1419,12 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
 
                     // enqueue a generic Create event for the playlist into DB
                     // Run DB mutations in a short-lived blocking thread so we don't block the worker loop
-                    // Use the folder path relative to root as the logical
-                    // playlist key for the event queue.
-                    if matches_whitelist(&folder, &remote_whitelist) {
-                        let playlist_name2 = rel.display().to_string();
-                        let db_path2 = db_path.clone();
+                    // Use the folder's logical playlist key for the event queue.
+                    if matches_whitelist(&folder, remote_whitelist) && !cfg.is_local_only(&folder) {
+                        let playlist_name2 = cfg.playlist_key_for_folder(&folder);
+                        let db_pool2 = db_pool.clone();
                         let h = std::thread::spawn(move || {
-                            if let Ok(conn) = db::open_or_create(std::path::Path::new(&db_path2)) {
+                            if let Ok(conn) = db_pool2.get() {
                                 if let Err(e) = db::enqueue_event(
                                     &conn,
                                     &playlist_name2,
@@ -599,8 +1436,8 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                                 }
                             } else {
                                 warn!(
-                                    "Failed to open DB at {} to enqueue event",
-                                    db_path2.display()
+                                    "Failed to get a DB connection to enqueue event for {}",
+                                    playlist_name2
                                 );
                             }
                         });
@@ -609,10 +1446,20 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                     }
                 }
 
-                // Decide how to handle the batch based on its size.
-                // A file move counts as 2 events (Remove + Add), so the effective move
-                // threshold is half of watcher_instant_trigger_threshold.
-                let instant_threshold = cfg.watcher_instant_trigger_threshold;
+                // Shutting down: wait for the enqueue threads and leave the
+                // worker to the next scheduled run.
+                if flushing {
+                    let handles = std::mem::take(&mut *enqueue_handles.lock().unwrap());
+                    for h in handles {
+                        let _ = h.join();
+                    }
+                    break;
+                }
+
+                // Decide how to handle the batch based on its size.
+                // A file move counts as 2 events (Remove + Add), so the effective move
+                // threshold is half of watcher_instant_trigger_threshold.
+                let instant_threshold = cfg.watcher_instant_trigger_threshold;
                 if total_events > 0 && instant_threshold > 0 && total_events <= instant_threshold {
                     // Small batch: spawn the worker immediately.
                     info!(
@@ -664,169 +1511,381 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                 // small sleep to avoid busy-looping
                 std::thread::sleep(Duration::from_millis(50));
             }
-        });
-    }
+        })
+    };
 
     // Now wire up notify to feed events into the in-memory tree and debounce map.
     let debounce_map_cb = debounce_map.clone();
     let tree_cb = tree.clone();
-    let cfg_cb = cfg.clone();
-    let db_path = cfg_cb.db_path.clone();
-    let remote_whitelist_cb = remote_whitelist.clone();
+    let settings_cb = settings.clone();
     let enqueue_handles_cb = enqueue_handles.clone();
+    let watch_limit_hit = Arc::new(AtomicBool::new(false));
+    let watch_limit_hit_cb = watch_limit_hit.clone();
+
+    // Handler for each FS event, fed either by a RecommendedWatcher or, in
+    // poll mode, by the rescan loop.
+    let handle_event = move |res: NotifyResult<NotifyEvent>| {
+        let live = settings_cb.read().unwrap().clone();
+        let (cfg_cb, ignore_cb, remote_whitelist_cb) =
+            (&live.cfg, &live.ignore, &live.remote_whitelist);
+        match res {
+            Ok(ev) => {
+                // convert notify::Event into synthetic events and apply
+                let mut synths: Vec<SyntheticEvent> = Vec::new();
+                // If multiple paths provided it's often a rename; try to distinguish
+                // folder vs file rename using the in-memory tree when possible.
+                if ev.paths.len() >= 2 {
+                    let from = ev.paths[0].clone();
+                    let to = ev.paths[1].clone();
+
+                    // Ignore Samba temporary paths entirely.
+                    if is_smb_temp_path(&from) || is_smb_temp_path(&to) {
+                        return;
+                    }
+                    // Renames entirely within ignored paths are dropped here;
+                    // one-sided ones are resolved by `apply_synthetic_event`.
+                    if ignore_cb.is_ignored(&from, from.is_dir())
+                        && ignore_cb.is_ignored(&to, to.is_dir())
+                    {
+                        return;
+                    }
 
-    // Create a RecommendedWatcher that will call our closure for each FS event.
-    let mut watcher: RecommendedWatcher = match RecommendedWatcher::new(
-        move |res: NotifyResult<NotifyEvent>| {
-            match res {
-                Ok(ev) => {
-                    // convert notify::Event into synthetic events and apply
-                    let mut synths: Vec<SyntheticEvent> = Vec::new();
-                    // If multiple paths provided it's often a rename; try to distinguish
-                    // folder vs file rename using the in-memory tree when possible.
-                    if ev.paths.len() >= 2 {
-                        let from = ev.paths[0].clone();
-                        let to = ev.paths[1].clone();
-
-                        // Ignore Samba temporary paths entirely.
-                        if is_smb_temp_path(&from) || is_smb_temp_path(&to) {
-                            return;
-                        }
-
-                        let mut treat_as_folder_rename = false;
-                        if let Ok(t) = tree_cb.lock() {
-                            if t.nodes.contains_key(&from) || t.nodes.contains_key(&to) {
-                                treat_as_folder_rename = true;
-                            }
+                    let mut treat_as_folder_rename = false;
+                    if let Ok(t) = tree_cb.lock() {
+                        if t.nodes.contains_key(&from) || t.nodes.contains_key(&to) {
+                            treat_as_folder_rename = true;
                         }
+                    }
 
-                        if treat_as_folder_rename {
-                            synths.push(SyntheticEvent::FolderRename { from, to });
+                    if treat_as_folder_rename {
+                        synths.push(SyntheticEvent::FolderRename { from, to });
+                    } else {
+                        // Only treat the rename as a track event if at least one of the
+                        // paths matches the configured file extension filter.
+                        if path_matches_extensions(&from, &cfg_cb.file_extensions)
+                            || path_matches_extensions(&to, &cfg_cb.file_extensions)
+                        {
+                            synths.push(SyntheticEvent::FileRename { from, to });
                         } else {
-                            // Only treat the rename as a track event if at least one of the
-                            // paths matches the configured file extension filter.
-                            if path_matches_extensions(&from, &cfg_cb.file_extensions)
-                                || path_matches_extensions(&to, &cfg_cb.file_extensions)
-                            {
-                                synths.push(SyntheticEvent::FileRename { from, to });
-                            } else {
-                                trace!(
-                                    "NotifyEvent: skipping file rename {:?} -> {:?} (extension not in filter)",
-                                    from, to
-                                );
-                            }
+                            trace!(
+                                "NotifyEvent: skipping file rename {:?} -> {:?} (extension not in filter)",
+                                from, to
+                            );
                         }
-                    } else {
-                        for path in ev.paths.iter() {
-                            if is_smb_temp_path(path) {
-                                continue;
-                            }
-                            let is_file = path.is_file();
-                            let is_dir = path.is_dir();
-
-                            match &ev.kind {
-                                EventKind::Create(_) => {
-                                    if is_file {
-                                        // Only treat matching media files as track events
-                                        if path_matches_extensions(path, &cfg_cb.file_extensions) {
-                                            synths.push(SyntheticEvent::FileCreate(path.clone()));
-                                        }
-                                    } else if is_dir {
-                                        synths.push(SyntheticEvent::FolderCreate(path.clone()));
+                    }
+                } else {
+                    for path in ev.paths.iter() {
+                        if is_smb_temp_path(path) || ignore_cb.is_ignored(path, path.is_dir()) {
+                            continue;
+                        }
+                        let is_file = path.is_file();
+                        let is_dir = path.is_dir();
+
+                        match &ev.kind {
+                            EventKind::Create(_) => {
+                                if is_file {
+                                    // Only treat matching media files as track events
+                                    if path_matches_extensions(path, &cfg_cb.file_extensions) {
+                                        synths.push(SyntheticEvent::FileCreate(path.clone()));
                                     }
+                                } else if is_dir {
+                                    synths.push(SyntheticEvent::FolderCreate(path.clone()));
                                 }
-                                EventKind::Remove(remove_kind) => {
-                                    if is_file {
-                                        if path_matches_extensions(path, &cfg_cb.file_extensions) {
-                                            synths.push(SyntheticEvent::FileRemove(path.clone()));
-                                        }
-                                    } else if is_dir {
-                                        synths.push(SyntheticEvent::FolderRemove(path.clone()));
-                                    } else {
-                                        // After a remove, the path may no longer exist on disk,
-                                        // so fall back to the RemoveKind from notify.
-                                        match remove_kind {
-                                            RemoveKind::File | RemoveKind::Any => {
-                                                if path_matches_extensions(
-                                                    path,
-                                                    &cfg_cb.file_extensions,
-                                                ) {
-                                                    synths.push(SyntheticEvent::FileRemove(
-                                                        path.clone(),
-                                                    ));
-                                                }
-                                            }
-                                            RemoveKind::Folder => {
-                                                synths.push(SyntheticEvent::FolderRemove(
-                                                    path.clone(),
-                                                ));
+                            }
+                            EventKind::Remove(remove_kind) => {
+                                if is_file {
+                                    if path_matches_extensions(path, &cfg_cb.file_extensions) {
+                                        synths.push(SyntheticEvent::FileRemove(path.clone()));
+                                    }
+                                } else if is_dir {
+                                    synths.push(SyntheticEvent::FolderRemove(path.clone()));
+                                } else {
+                                    // After a remove, the path may no longer exist on disk,
+                                    // so fall back to the RemoveKind from notify.
+                                    match remove_kind {
+                                        RemoveKind::File | RemoveKind::Any => {
+                                            if path_matches_extensions(
+                                                path,
+                                                &cfg_cb.file_extensions,
+                                            ) {
+                                                synths
+                                                    .push(SyntheticEvent::FileRemove(path.clone()));
                                             }
-                                            _ => {}
                                         }
+                                        RemoveKind::Folder => {
+                                            synths.push(SyntheticEvent::FolderRemove(path.clone()));
+                                        }
+                                        _ => {}
                                     }
                                 }
-                                EventKind::Modify(_) => {
-                                    if is_file {
-                                        // treat modify as create/update of file
-                                        if path_matches_extensions(path, &cfg_cb.file_extensions) {
-                                            synths.push(SyntheticEvent::FileCreate(path.clone()));
-                                        }
+                            }
+                            EventKind::Modify(_) => {
+                                if is_file {
+                                    // treat modify as create/update of file
+                                    if path_matches_extensions(path, &cfg_cb.file_extensions) {
+                                        synths.push(SyntheticEvent::FileCreate(path.clone()));
                                     }
                                 }
-                                _ => {}
                             }
+                            _ => {}
                         }
                     }
-                    if !synths.is_empty() {
-                        info!(
-                            "NotifyEvent received: kind={:?}, paths={:?}, attrs={:?} -> {} synthetic event(s)",
-                            ev.kind, ev.paths, ev.attrs, synths.len()
-                        );
-                    } else {
-                        trace!(
-                            "NotifyEvent received (no matching files): kind={:?}, paths={:?}",
-                            ev.kind,
-                            ev.paths
-                        );
-                    }
-                    if !synths.is_empty() {
-                        // apply to in-memory tree and enqueue DB events
-                        if let Ok(mut t) = tree_cb.lock() {
-                            for s in synths.into_iter() {
-                                let ops = t.apply_synthetic_event(s.clone());
-                                if !ops.is_empty() {
-                                    debug!("InMemoryTree produced {} logical op(s) for synthetic event {:?}", ops.len(), s);
-                                }
-                                for op in ops {
-                                    match op {
-                                        LogicalOp::Add {
-                                            playlist_folder,
-                                            track_path,
-                                        } => {
-                                            // Respect folder whitelist before enqueuing events
-                                            if let Some(ref wlvec) = t.whitelist {
-                                                let path_str = playlist_folder.to_string_lossy();
-                                                if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                                                    continue;
+                }
+                if !synths.is_empty() {
+                    info!(
+                        "NotifyEvent received: kind={:?}, paths={:?}, attrs={:?} -> {} synthetic event(s)",
+                        ev.kind, ev.paths, ev.attrs, synths.len()
+                    );
+                } else {
+                    trace!(
+                        "NotifyEvent received (no matching files): kind={:?}, paths={:?}",
+                        ev.kind,
+                        ev.paths
+                    );
+                }
+                if !synths.is_empty() {
+                    // apply to in-memory tree and enqueue DB events
+                    if let Ok(mut t) = tree_cb.lock() {
+                        for s in synths.into_iter() {
+                            let ops = t.apply_synthetic_event(s.clone());
+                            if !ops.is_empty() {
+                                debug!("InMemoryTree produced {} logical op(s) for synthetic event {:?}", ops.len(), s);
+                            }
+                            for op in ops {
+                                match op {
+                                    LogicalOp::Add {
+                                        playlist_folder,
+                                        track_path,
+                                    } => {
+                                        // Respect folder whitelist before enqueuing events
+                                        if let Some(ref wlvec) = t.whitelist {
+                                            let path_str = playlist_folder.to_string_lossy();
+                                            if !wlvec.iter().any(|re| re.is_match(&path_str)) {
+                                                continue;
+                                            }
+                                        }
+                                        info!(
+                                            "LogicalOp::Add playlist_folder={:?}, track_path={:?}",
+                                            playlist_folder, track_path
+                                        );
+
+                                        // Build the list of playlist folders that should reflect this
+                                        // track change: the immediate folder plus any ancestor folders
+                                        // that are represented as playlist nodes (so parent playlists
+                                        // stay in sync online as well).
+                                        let mut target_folders: Vec<std::path::PathBuf> =
+                                            Vec::new();
+                                        target_folders.push(playlist_folder.clone());
+                                        if let Some(mut p) =
+                                            playlist_folder.parent().map(|x| x.to_path_buf())
+                                        {
+                                            while t.root_for(&p).is_some() {
+                                                if t.nodes.contains_key(&p) {
+                                                    target_folders.push(p.clone());
+                                                }
+                                                if t.is_root(&p) {
+                                                    break;
+                                                }
+                                                if let Some(np) =
+                                                    p.parent().map(|x| x.to_path_buf())
+                                                {
+                                                    p = np;
+                                                } else {
+                                                    break;
+                                                }
+                                            }
+                                        }
+
+                                        // Debounce playlist rewrite for all affected folders.
+                                        // index 0 is the leaf (direct parent of the file); rest are ancestors.
+                                        if let Ok(mut dm) = debounce_map_cb.lock() {
+                                            for (i, folder) in target_folders.iter().enumerate() {
+                                                let new_t = Instant::now()
+                                                    + Duration::from_millis(cfg_cb.debounce_ms);
+                                                if i == 0 {
+                                                    dm.entry(folder.clone())
+                                                        .and_modify(|(t, c)| {
+                                                            *t = new_t;
+                                                            *c += 1;
+                                                        })
+                                                        .or_insert((new_t, 1));
+                                                } else {
+                                                    dm.entry(folder.clone())
+                                                        .and_modify(|(t, _c)| {
+                                                            *t = new_t;
+                                                        })
+                                                        .or_insert((new_t, 0));
                                                 }
                                             }
-                                            info!("LogicalOp::Add playlist_folder={:?}, track_path={:?}", playlist_folder, track_path);
-
-                                            // Build the list of playlist folders that should reflect this
-                                            // track change: the immediate folder plus any ancestor folders
-                                            // that are represented as playlist nodes (so parent playlists
-                                            // stay in sync online as well).
-                                            let mut target_folders: Vec<std::path::PathBuf> =
-                                                Vec::new();
-                                            target_folders.push(playlist_folder.clone());
+                                        }
+
+                                        let remote_target_folders: Vec<std::path::PathBuf> =
+                                            target_folders
+                                                .iter()
+                                                .filter(|folder| {
+                                                    matches_whitelist(folder, remote_whitelist_cb)
+                                                        && !cfg_cb.is_local_only(folder)
+                                                })
+                                                .cloned()
+                                                .collect();
+                                        if remote_target_folders.is_empty() {
+                                            continue;
+                                        }
+
+                                        // Enqueue add events for the immediate folder and all parent
+                                        // playlists so that remote parent playlists receive the track
+                                        // updates as well.
+                                        let db_pool2 = db_pool.clone();
+                                        let track = track_path.to_string_lossy().to_string();
+                                        let playlist_names: Vec<String> = remote_target_folders
+                                            .iter()
+                                            .map(|folder| cfg_cb.playlist_key_for_folder(folder))
+                                            .collect();
+                                        let h = thread::spawn(move || {
+                                            if let Ok(conn) = db_pool2.get() {
+                                                for pname in playlist_names {
+                                                    if let Err(e) = db::enqueue_event(
+                                                        &conn,
+                                                        &pname,
+                                                        &EventAction::Add,
+                                                        Some(&track),
+                                                        None,
+                                                    ) {
+                                                        warn!("Failed to enqueue add event for {}: {}", pname, e);
+                                                    }
+                                                }
+                                            }
+                                        });
+                                        enqueue_handles_cb.lock().unwrap().push(h);
+                                    }
+                                    LogicalOp::Remove {
+                                        playlist_folder,
+                                        track_path,
+                                    } => {
+                                        if let Some(ref wlvec) = t.whitelist {
+                                            let path_str = playlist_folder.to_string_lossy();
+                                            if !wlvec.iter().any(|re| re.is_match(&path_str)) {
+                                                continue;
+                                            }
+                                        }
+                                        info!("LogicalOp::Remove playlist_folder={:?}, track_path={:?}", playlist_folder, track_path);
+
+                                        let mut target_folders: Vec<std::path::PathBuf> =
+                                            Vec::new();
+                                        target_folders.push(playlist_folder.clone());
+                                        if let Some(mut p) =
+                                            playlist_folder.parent().map(|x| x.to_path_buf())
+                                        {
+                                            while t.root_for(&p).is_some() {
+                                                if t.nodes.contains_key(&p) {
+                                                    target_folders.push(p.clone());
+                                                }
+                                                if t.is_root(&p) {
+                                                    break;
+                                                }
+                                                if let Some(np) =
+                                                    p.parent().map(|x| x.to_path_buf())
+                                                {
+                                                    p = np;
+                                                } else {
+                                                    break;
+                                                }
+                                            }
+                                        }
+
+                                        if let Ok(mut dm) = debounce_map_cb.lock() {
+                                            for (i, folder) in target_folders.iter().enumerate() {
+                                                let new_t = Instant::now()
+                                                    + Duration::from_millis(cfg_cb.debounce_ms);
+                                                if i == 0 {
+                                                    dm.entry(folder.clone())
+                                                        .and_modify(|(t, c)| {
+                                                            *t = new_t;
+                                                            *c += 1;
+                                                        })
+                                                        .or_insert((new_t, 1));
+                                                } else {
+                                                    dm.entry(folder.clone())
+                                                        .and_modify(|(t, _c)| {
+                                                            *t = new_t;
+                                                        })
+                                                        .or_insert((new_t, 0));
+                                                }
+                                            }
+                                        }
+
+                                        let remote_target_folders: Vec<std::path::PathBuf> =
+                                            target_folders
+                                                .iter()
+                                                .filter(|folder| {
+                                                    matches_whitelist(folder, remote_whitelist_cb)
+                                                        && !cfg_cb.is_local_only(folder)
+                                                })
+                                                .cloned()
+                                                .collect();
+                                        if remote_target_folders.is_empty() {
+                                            continue;
+                                        }
+
+                                        let db_pool2 = db_pool.clone();
+                                        let track = track_path.to_string_lossy().to_string();
+                                        let playlist_names: Vec<String> = remote_target_folders
+                                            .iter()
+                                            .map(|folder| cfg_cb.playlist_key_for_folder(folder))
+                                            .collect();
+                                        let h = thread::spawn(move || {
+                                            if let Ok(conn) = db_pool2.get() {
+                                                for pname in playlist_names {
+                                                    if let Err(e) = db::enqueue_event(
+                                                        &conn,
+                                                        &pname,
+                                                        &EventAction::Remove,
+                                                        Some(&track),
+                                                        None,
+                                                    ) {
+                                                        warn!("Failed to enqueue remove event for {}: {}", pname, e);
+                                                    }
+                                                }
+                                            }
+                                        });
+                                        enqueue_handles_cb.lock().unwrap().push(h);
+                                    }
+                                    LogicalOp::Create { playlist_folder } => {
+                                        if let Some(ref wlvec) = t.whitelist {
+                                            let path_str = playlist_folder.to_string_lossy();
+                                            if !wlvec.iter().any(|re| re.is_match(&path_str)) {
+                                                continue;
+                                            }
+                                        }
+                                        debug!(
+                                            "LogicalOp::Create playlist_folder={:?}",
+                                            playlist_folder
+                                        );
+                                        if let Ok(mut dm) = debounce_map_cb.lock() {
+                                            let new_t = Instant::now()
+                                                + Duration::from_millis(cfg_cb.debounce_ms);
+                                            dm.entry(playlist_folder.clone())
+                                                .and_modify(|(t, c)| {
+                                                    *t = new_t;
+                                                    *c += 1;
+                                                })
+                                                .or_insert((new_t, 1));
                                             if let Some(mut p) =
                                                 playlist_folder.parent().map(|x| x.to_path_buf())
                                             {
-                                                while p.starts_with(&cfg_cb.root_folder) {
+                                                while t.root_for(&p).is_some() {
                                                     if t.nodes.contains_key(&p) {
-                                                        target_folders.push(p.clone());
+                                                        let new_t = Instant::now()
+                                                            + Duration::from_millis(
+                                                                cfg_cb.debounce_ms,
+                                                            );
+                                                        dm.entry(p.clone())
+                                                            .and_modify(|(t, _c)| {
+                                                                *t = new_t;
+                                                            })
+                                                            .or_insert((new_t, 0));
                                                     }
-                                                    if p == cfg_cb.root_folder {
+                                                    if t.is_root(&p) {
                                                         break;
                                                     }
                                                     if let Some(np) =
@@ -838,104 +1897,207 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                                                     }
                                                 }
                                             }
-
-                                            // Debounce playlist rewrite for all affected folders.
-                                            // index 0 is the leaf (direct parent of the file); rest are ancestors.
-                                            if let Ok(mut dm) = debounce_map_cb.lock() {
-                                                for (i, folder) in target_folders.iter().enumerate()
-                                                {
-                                                    let new_t = Instant::now()
-                                                        + Duration::from_millis(cfg_cb.debounce_ms);
-                                                    if i == 0 {
-                                                        dm.entry(folder.clone())
-                                                            .and_modify(|(t, c)| {
-                                                                *t = new_t;
-                                                                *c += 1;
-                                                            })
-                                                            .or_insert((new_t, 1));
-                                                    } else {
-                                                        dm.entry(folder.clone())
+                                        }
+                                    }
+                                    LogicalOp::Delete { playlist_folder } => {
+                                        if let Some(ref wlvec) = t.whitelist {
+                                            let path_str = playlist_folder.to_string_lossy();
+                                            if !wlvec.iter().any(|re| re.is_match(&path_str)) {
+                                                continue;
+                                            }
+                                        }
+                                        info!(
+                                            "LogicalOp::Delete playlist_folder={:?}",
+                                            playlist_folder
+                                        );
+                                        // For deletes, debounce only ancestor folders (for linked playlists),
+                                        // and enqueue a Delete event for the removed playlist itself.
+                                        if let Ok(mut dm) = debounce_map_cb.lock() {
+                                            if let Some(mut p) =
+                                                playlist_folder.parent().map(|x| x.to_path_buf())
+                                            {
+                                                while t.root_for(&p).is_some() {
+                                                    if t.nodes.contains_key(&p) {
+                                                        let new_t = Instant::now()
+                                                            + Duration::from_millis(
+                                                                cfg_cb.debounce_ms,
+                                                            );
+                                                        dm.entry(p.clone())
                                                             .and_modify(|(t, _c)| {
                                                                 *t = new_t;
                                                             })
                                                             .or_insert((new_t, 0));
                                                     }
+                                                    if t.is_root(&p) {
+                                                        break;
+                                                    }
+                                                    if let Some(np) =
+                                                        p.parent().map(|x| x.to_path_buf())
+                                                    {
+                                                        p = np;
+                                                    } else {
+                                                        break;
+                                                    }
                                                 }
                                             }
+                                        }
 
-                                            let remote_target_folders: Vec<std::path::PathBuf> =
-                                                target_folders
-                                                    .iter()
-                                                    .filter(|folder| {
-                                                        matches_whitelist(
-                                                            folder,
-                                                            &remote_whitelist_cb,
-                                                        )
-                                                    })
-                                                    .cloned()
-                                                    .collect();
-                                            if remote_target_folders.is_empty() {
-                                                continue;
-                                            }
-
-                                            // Enqueue add events for the immediate folder and all parent
-                                            // playlists so that remote parent playlists receive the track
-                                            // updates as well.
-                                            let db_path2 = db_path.clone();
-                                            let root_folder = cfg_cb.root_folder.clone();
-                                            let track = track_path.to_string_lossy().to_string();
-                                            let playlist_names: Vec<String> = remote_target_folders
-                                                .iter()
-                                                .map(|folder| {
-                                                    folder
-                                                        .strip_prefix(&root_folder)
-                                                        .unwrap_or(folder)
-                                                        .display()
-                                                        .to_string()
-                                                })
-                                                .collect();
+                                        let remote_delete = matches_whitelist(
+                                            &playlist_folder,
+                                            remote_whitelist_cb,
+                                        );
+                                        if remote_delete {
+                                            // Enqueue a Delete event so the worker can eventually delete
+                                            // the corresponding remote playlist.
+                                            let db_pool2 = db_pool.clone();
+                                            let pname =
+                                                cfg_cb.playlist_key_for_folder(&playlist_folder);
                                             let h = thread::spawn(move || {
-                                                if let Ok(conn) = db::open_or_create(
-                                                    std::path::Path::new(&db_path2),
-                                                ) {
-                                                    for pname in playlist_names {
-                                                        if let Err(e) = db::enqueue_event(
-                                                            &conn,
-                                                            &pname,
-                                                            &EventAction::Add,
-                                                            Some(&track),
-                                                            None,
-                                                        ) {
-                                                            warn!("Failed to enqueue add event for {}: {}", pname, e);
-                                                        }
+                                                if let Ok(conn) = db_pool2.get() {
+                                                    if let Err(e) = db::enqueue_event(
+                                                        &conn,
+                                                        &pname,
+                                                        &EventAction::Delete,
+                                                        None,
+                                                        None,
+                                                    ) {
+                                                        warn!(
+                                                            "Failed to enqueue delete event: {}",
+                                                            e
+                                                        );
                                                     }
                                                 }
                                             });
                                             enqueue_handles_cb.lock().unwrap().push(h);
                                         }
-                                        LogicalOp::Remove {
-                                            playlist_folder,
-                                            track_path,
-                                        } => {
-                                            if let Some(ref wlvec) = t.whitelist {
-                                                let path_str = playlist_folder.to_string_lossy();
-                                                if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                                                    continue;
-                                                }
+                                    }
+                                    LogicalOp::PlaylistRename {
+                                        from_folder,
+                                        to_folder,
+                                    } => {
+                                        // Use the source folder to decide whether this playlist
+                                        // should be tracked at all.
+                                        if let Some(ref wlvec) = t.whitelist {
+                                            let path_str = from_folder.to_string_lossy();
+                                            if !wlvec.iter().any(|re| re.is_match(&path_str)) {
+                                                continue;
                                             }
-                                            info!("LogicalOp::Remove playlist_folder={:?}, track_path={:?}", playlist_folder, track_path);
-
-                                            let mut target_folders: Vec<std::path::PathBuf> =
-                                                Vec::new();
-                                            target_folders.push(playlist_folder.clone());
+                                        }
+                                        info!("LogicalOp::PlaylistRename from_folder={:?}, to_folder={:?}", from_folder, to_folder);
+
+                                        // Rename the local playlist file on disk so that we don't
+                                        // leave behind a stale playlist with the old folder name.
+                                        let from_rel = cfg_cb.root_relative(&from_folder);
+                                        let to_rel = cfg_cb.root_relative(&to_folder);
+
+                                        let from_folder_name = from_folder
+                                            .file_name()
+                                            .map(|s| s.to_string_lossy())
+                                            .unwrap_or_default();
+                                        let to_folder_name = to_folder
+                                            .file_name()
+                                            .map(|s| s.to_string_lossy())
+                                            .unwrap_or_default();
+
+                                        let from_parent = from_rel
+                                            .parent()
+                                            .map(|p| p.to_path_buf())
+                                            .unwrap_or_else(|| std::path::PathBuf::new());
+                                        let to_parent = to_rel
+                                            .parent()
+                                            .map(|p| p.to_path_buf())
+                                            .unwrap_or_else(|| std::path::PathBuf::new());
+
+                                        let from_parent_str = if from_parent.as_os_str().is_empty()
+                                        {
+                                            String::new()
+                                        } else {
+                                            let mut s = from_parent.display().to_string();
+                                            if !s.ends_with(std::path::MAIN_SEPARATOR) {
+                                                s.push(std::path::MAIN_SEPARATOR);
+                                            }
+                                            s
+                                        };
+
+                                        let to_parent_str = if to_parent.as_os_str().is_empty() {
+                                            String::new()
+                                        } else {
+                                            let mut s = to_parent.display().to_string();
+                                            if !s.ends_with(std::path::MAIN_SEPARATOR) {
+                                                s.push(std::path::MAIN_SEPARATOR);
+                                            }
+                                            s
+                                        };
+
+                                        // The tracks have already moved, so both names
+                                        // count them in `to_folder`.
+                                        let from_playlist_name = cfg_cb.local_playlist_file_name(
+                                            &to_folder,
+                                            &from_folder_name,
+                                            &from_parent_str,
+                                        );
+                                        let to_playlist_name = cfg_cb.local_playlist_file_name(
+                                            &to_folder,
+                                            &to_folder_name,
+                                            &to_parent_str,
+                                        );
+                                        // The marker moved with the folder.
+                                        let local_only = cfg_cb.is_local_only(&to_folder);
+                                        let remote_allowed_from = !local_only
+                                            && matches_whitelist(&from_folder, remote_whitelist_cb);
+                                        let remote_allowed_to = !local_only
+                                            && matches_whitelist(&to_folder, remote_whitelist_cb);
+
+                                        // After a folder rename/move, the playlist file that was
+                                        // previously under `from_folder` is now physically located
+                                        // under `to_folder`. Rename that file in-place so we don't
+                                        // accumulate stale playlists with old names.
+                                        let from_playlist_path =
+                                            to_folder.join(&from_playlist_name);
+                                        let to_playlist_path = to_folder.join(&to_playlist_name);
+
+                                        if from_playlist_path != to_playlist_path
+                                            && from_playlist_path.exists()
+                                        {
+                                            if let Err(e) = std::fs::rename(
+                                                &from_playlist_path,
+                                                &to_playlist_path,
+                                            ) {
+                                                warn!("Failed to rename playlist file {:?} -> {:?}: {}", from_playlist_path, to_playlist_path, e);
+                                            }
+                                        }
+                                        // debounce both source and destination folders and ancestors
+                                        if let Ok(mut dm) = debounce_map_cb.lock() {
+                                            let new_t = Instant::now()
+                                                + Duration::from_millis(cfg_cb.debounce_ms);
+                                            dm.entry(from_folder.clone())
+                                                .and_modify(|(t, c)| {
+                                                    *t = new_t;
+                                                    *c += 1;
+                                                })
+                                                .or_insert((new_t, 1));
+                                            dm.entry(to_folder.clone())
+                                                .and_modify(|(t, c)| {
+                                                    *t = new_t;
+                                                    *c += 1;
+                                                })
+                                                .or_insert((new_t, 1));
                                             if let Some(mut p) =
-                                                playlist_folder.parent().map(|x| x.to_path_buf())
+                                                from_folder.parent().map(|x| x.to_path_buf())
                                             {
-                                                while p.starts_with(&cfg_cb.root_folder) {
+                                                while t.root_for(&p).is_some() {
                                                     if t.nodes.contains_key(&p) {
-                                                        target_folders.push(p.clone());
+                                                        let new_t = Instant::now()
+                                                            + Duration::from_millis(
+                                                                cfg_cb.debounce_ms,
+                                                            );
+                                                        dm.entry(p.clone())
+                                                            .and_modify(|(t, _c)| {
+                                                                *t = new_t;
+                                                            })
+                                                            .or_insert((new_t, 0));
                                                     }
-                                                    if p == cfg_cb.root_folder {
+                                                    if t.is_root(&p) {
                                                         break;
                                                     }
                                                     if let Some(np) =
@@ -947,502 +2109,228 @@ pub fn run_watcher(cfg: &Config) -> anyhow::Result<()> {
                                                     }
                                                 }
                                             }
-
-                                            if let Ok(mut dm) = debounce_map_cb.lock() {
-                                                for (i, folder) in target_folders.iter().enumerate()
-                                                {
-                                                    let new_t = Instant::now()
-                                                        + Duration::from_millis(cfg_cb.debounce_ms);
-                                                    if i == 0 {
-                                                        dm.entry(folder.clone())
-                                                            .and_modify(|(t, c)| {
-                                                                *t = new_t;
-                                                                *c += 1;
-                                                            })
-                                                            .or_insert((new_t, 1));
-                                                    } else {
-                                                        dm.entry(folder.clone())
+                                            if let Some(mut p) =
+                                                to_folder.parent().map(|x| x.to_path_buf())
+                                            {
+                                                while t.root_for(&p).is_some() {
+                                                    if t.nodes.contains_key(&p) {
+                                                        let new_t = Instant::now()
+                                                            + Duration::from_millis(
+                                                                cfg_cb.debounce_ms,
+                                                            );
+                                                        dm.entry(p.clone())
                                                             .and_modify(|(t, _c)| {
                                                                 *t = new_t;
                                                             })
                                                             .or_insert((new_t, 0));
                                                     }
+                                                    if t.is_root(&p) {
+                                                        break;
+                                                    }
+                                                    if let Some(np) =
+                                                        p.parent().map(|x| x.to_path_buf())
+                                                    {
+                                                        p = np;
+                                                    } else {
+                                                        break;
+                                                    }
                                                 }
                                             }
+                                        }
 
-                                            let remote_target_folders: Vec<std::path::PathBuf> =
-                                                target_folders
-                                                    .iter()
-                                                    .filter(|folder| {
-                                                        matches_whitelist(
-                                                            folder,
-                                                            &remote_whitelist_cb,
-                                                        )
-                                                    })
-                                                    .cloned()
-                                                    .collect();
-                                            if remote_target_folders.is_empty() {
-                                                continue;
-                                            }
-
-                                            let db_path2 = db_path.clone();
-                                            let root_folder = cfg_cb.root_folder.clone();
-                                            let track = track_path.to_string_lossy().to_string();
-                                            let playlist_names: Vec<String> = remote_target_folders
-                                                .iter()
-                                                .map(|folder| {
-                                                    folder
-                                                        .strip_prefix(&root_folder)
-                                                        .unwrap_or(folder)
-                                                        .display()
-                                                        .to_string()
-                                                })
-                                                .collect();
-                                            let h = thread::spawn(move || {
-                                                if let Ok(conn) = db::open_or_create(
-                                                    std::path::Path::new(&db_path2),
-                                                ) {
-                                                    for pname in playlist_names {
+                                        // enqueue a rename event (playlist rename) into DB: use old playlist name as key
+                                        let playlist_name_from =
+                                            cfg_cb.playlist_key_for_folder(&from_folder);
+                                        let playlist_name_to =
+                                            cfg_cb.playlist_key_for_folder(&to_folder);
+
+                                        match (remote_allowed_from, remote_allowed_to) {
+                                            (true, true) => {
+                                                let extra = match serde_json::json!({"from": playlist_name_from, "to": playlist_name_to}).to_string() {
+                                                        s => s,
+                                                    };
+                                                let db_pool2 = db_pool.clone();
+                                                let pname = playlist_name_from.clone();
+                                                let extra_clone = extra.clone();
+                                                let h = thread::spawn(move || {
+                                                    if let Ok(conn) = db_pool2.get() {
                                                         if let Err(e) = db::enqueue_event(
                                                             &conn,
                                                             &pname,
-                                                            &EventAction::Remove,
-                                                            Some(&track),
+                                                            &EventAction::Rename {
+                                                                from: playlist_name_from.clone(),
+                                                                to: playlist_name_to.clone(),
+                                                            },
                                                             None,
+                                                            Some(&extra_clone),
                                                         ) {
-                                                            warn!("Failed to enqueue remove event for {}: {}", pname, e);
-                                                        }
-                                                    }
-                                                }
-                                            });
-                                            enqueue_handles_cb.lock().unwrap().push(h);
-                                        }
-                                        LogicalOp::Create { playlist_folder } => {
-                                            if let Some(ref wlvec) = t.whitelist {
-                                                let path_str = playlist_folder.to_string_lossy();
-                                                if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                                                    continue;
-                                                }
-                                            }
-                                            debug!(
-                                                "LogicalOp::Create playlist_folder={:?}",
-                                                playlist_folder
-                                            );
-                                            if let Ok(mut dm) = debounce_map_cb.lock() {
-                                                let new_t = Instant::now()
-                                                    + Duration::from_millis(cfg_cb.debounce_ms);
-                                                dm.entry(playlist_folder.clone())
-                                                    .and_modify(|(t, c)| {
-                                                        *t = new_t;
-                                                        *c += 1;
-                                                    })
-                                                    .or_insert((new_t, 1));
-                                                if let Some(mut p) = playlist_folder
-                                                    .parent()
-                                                    .map(|x| x.to_path_buf())
-                                                {
-                                                    while p.starts_with(&cfg_cb.root_folder) {
-                                                        if t.nodes.contains_key(&p) {
-                                                            let new_t = Instant::now()
-                                                                + Duration::from_millis(
-                                                                    cfg_cb.debounce_ms,
-                                                                );
-                                                            dm.entry(p.clone())
-                                                                .and_modify(|(t, _c)| {
-                                                                    *t = new_t;
-                                                                })
-                                                                .or_insert((new_t, 0));
-                                                        }
-                                                        if p == cfg_cb.root_folder {
-                                                            break;
-                                                        }
-                                                        if let Some(np) =
-                                                            p.parent().map(|x| x.to_path_buf())
-                                                        {
-                                                            p = np;
-                                                        } else {
-                                                            break;
+                                                            warn!(
+                                                                "Failed to enqueue rename event: {}",
+                                                                e
+                                                            );
                                                         }
                                                     }
-                                                }
-                                            }
-                                        }
-                                        LogicalOp::Delete { playlist_folder } => {
-                                            if let Some(ref wlvec) = t.whitelist {
-                                                let path_str = playlist_folder.to_string_lossy();
-                                                if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                                                    continue;
-                                                }
+                                                });
+                                                enqueue_handles_cb.lock().unwrap().push(h);
                                             }
-                                            info!(
-                                                "LogicalOp::Delete playlist_folder={:?}",
-                                                playlist_folder
-                                            );
-                                            // For deletes, debounce only ancestor folders (for linked playlists),
-                                            // and enqueue a Delete event for the removed playlist itself.
-                                            if let Ok(mut dm) = debounce_map_cb.lock() {
-                                                if let Some(mut p) = playlist_folder
-                                                    .parent()
-                                                    .map(|x| x.to_path_buf())
-                                                {
-                                                    while p.starts_with(&cfg_cb.root_folder) {
-                                                        if t.nodes.contains_key(&p) {
-                                                            let new_t = Instant::now()
-                                                                + Duration::from_millis(
-                                                                    cfg_cb.debounce_ms,
-                                                                );
-                                                            dm.entry(p.clone())
-                                                                .and_modify(|(t, _c)| {
-                                                                    *t = new_t;
-                                                                })
-                                                                .or_insert((new_t, 0));
-                                                        }
-                                                        if p == cfg_cb.root_folder {
-                                                            break;
-                                                        }
-                                                        if let Some(np) =
-                                                            p.parent().map(|x| x.to_path_buf())
-                                                        {
-                                                            p = np;
-                                                        } else {
-                                                            break;
+                                            (true, false) => {
+                                                let db_pool2 = db_pool.clone();
+                                                let pname = playlist_name_from.clone();
+                                                let h = thread::spawn(move || {
+                                                    if let Ok(conn) = db_pool2.get() {
+                                                        if let Err(e) = db::enqueue_event(
+                                                            &conn,
+                                                            &pname,
+                                                            &EventAction::Delete,
+                                                            None,
+                                                            None,
+                                                        ) {
+                                                            warn!(
+                                                                "Failed to enqueue delete event: {}",
+                                                                e
+                                                            );
                                                         }
                                                     }
-                                                }
+                                                });
+                                                enqueue_handles_cb.lock().unwrap().push(h);
                                             }
-
-                                            let remote_delete = matches_whitelist(
-                                                &playlist_folder,
-                                                &remote_whitelist_cb,
-                                            );
-                                            if remote_delete {
-                                                // Enqueue a Delete event so the worker can eventually delete
-                                                // the corresponding remote playlist.
-                                                let db_path2 = db_path.clone();
-                                                let pname = playlist_folder
-                                                    .strip_prefix(&cfg_cb.root_folder)
-                                                    .unwrap_or(&playlist_folder)
-                                                    .display()
-                                                    .to_string();
+                                            (false, true) => {
+                                                let db_pool2 = db_pool.clone();
+                                                let pname = playlist_name_to.clone();
                                                 let h = thread::spawn(move || {
-                                                    if let Ok(conn) = db::open_or_create(
-                                                        std::path::Path::new(&db_path2),
-                                                    ) {
+                                                    if let Ok(conn) = db_pool2.get() {
                                                         if let Err(e) = db::enqueue_event(
                                                             &conn,
                                                             &pname,
-                                                            &EventAction::Delete,
+                                                            &EventAction::Create,
                                                             None,
                                                             None,
                                                         ) {
-                                                            warn!("Failed to enqueue delete event: {}", e);
+                                                            warn!(
+                                                                "Failed to enqueue create event: {}",
+                                                                e
+                                                            );
                                                         }
                                                     }
                                                 });
                                                 enqueue_handles_cb.lock().unwrap().push(h);
                                             }
+                                            _ => {}
                                         }
-                                        LogicalOp::PlaylistRename {
-                                            from_folder,
-                                            to_folder,
-                                        } => {
-                                            // Use the source folder to decide whether this playlist
-                                            // should be tracked at all.
-                                            if let Some(ref wlvec) = t.whitelist {
-                                                let path_str = from_folder.to_string_lossy();
-                                                if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                                                    continue;
-                                                }
-                                            }
-                                            info!("LogicalOp::PlaylistRename from_folder={:?}, to_folder={:?}", from_folder, to_folder);
-
-                                            // Rename the local playlist file on disk so that we don't
-                                            // leave behind a stale playlist with the old folder name.
-                                            let from_rel = from_folder
-                                                .strip_prefix(&cfg_cb.root_folder)
-                                                .unwrap_or(&from_folder)
-                                                .to_path_buf();
-                                            let to_rel = to_folder
-                                                .strip_prefix(&cfg_cb.root_folder)
-                                                .unwrap_or(&to_folder)
-                                                .to_path_buf();
-
-                                            let from_folder_name = from_folder
-                                                .file_name()
-                                                .and_then(|s| s.to_str())
-                                                .unwrap_or("");
-                                            let to_folder_name = to_folder
-                                                .file_name()
-                                                .and_then(|s| s.to_str())
-                                                .unwrap_or("");
-
-                                            let from_parent = from_rel
-                                                .parent()
-                                                .map(|p| p.to_path_buf())
-                                                .unwrap_or_else(|| std::path::PathBuf::new());
-                                            let to_parent = to_rel
-                                                .parent()
-                                                .map(|p| p.to_path_buf())
-                                                .unwrap_or_else(|| std::path::PathBuf::new());
-
-                                            let from_parent_str =
-                                                if from_parent.as_os_str().is_empty() {
-                                                    String::new()
-                                                } else {
-                                                    let mut s = from_parent.display().to_string();
-                                                    if !s.ends_with(std::path::MAIN_SEPARATOR) {
-                                                        s.push(std::path::MAIN_SEPARATOR);
-                                                    }
-                                                    s
-                                                };
-
-                                            let to_parent_str = if to_parent.as_os_str().is_empty()
-                                            {
-                                                String::new()
-                                            } else {
-                                                let mut s = to_parent.display().to_string();
-                                                if !s.ends_with(std::path::MAIN_SEPARATOR) {
-                                                    s.push(std::path::MAIN_SEPARATOR);
-                                                }
-                                                s
-                                            };
-
-                                            let from_playlist_name = util::expand_template(
-                                                &cfg_cb.local_playlist_template,
-                                                from_folder_name,
-                                                &from_parent_str,
-                                            );
-                                            let to_playlist_name = util::expand_template(
-                                                &cfg_cb.local_playlist_template,
-                                                to_folder_name,
-                                                &to_parent_str,
-                                            );
-                                            let remote_allowed_from = matches_whitelist(
-                                                &from_folder,
-                                                &remote_whitelist_cb,
-                                            );
-                                            let remote_allowed_to =
-                                                matches_whitelist(&to_folder, &remote_whitelist_cb);
-
-                                            // After a folder rename/move, the playlist file that was
-                                            // previously under `from_folder` is now physically located
-                                            // under `to_folder`. Rename that file in-place so we don't
-                                            // accumulate stale playlists with old names.
-                                            let from_playlist_path =
-                                                to_folder.join(&from_playlist_name);
-                                            let to_playlist_path =
-                                                to_folder.join(&to_playlist_name);
-
-                                            if from_playlist_path != to_playlist_path
-                                                && from_playlist_path.exists()
-                                            {
-                                                if let Err(e) = std::fs::rename(
-                                                    &from_playlist_path,
-                                                    &to_playlist_path,
+                                    }
+                                    LogicalOp::TrackMoved { from_path, to_path } => {
+                                        info!(
+                                            "LogicalOp::TrackMoved from_path={:?}, to_path={:?}",
+                                            from_path, to_path
+                                        );
+                                        // Copy cached matches before the worker sees the Add.
+                                        let db_pool2 = db_pool.clone();
+                                        let h = thread::spawn(move || {
+                                            if let Ok(conn) = db_pool2.get() {
+                                                if let Err(e) = db::copy_track_cache_entries(
+                                                    &conn,
+                                                    &from_path.to_string_lossy(),
+                                                    &to_path.to_string_lossy(),
                                                 ) {
-                                                    warn!("Failed to rename playlist file {:?} -> {:?}: {}", from_playlist_path, to_playlist_path, e);
-                                                }
-                                            }
-                                            // debounce both source and destination folders and ancestors
-                                            if let Ok(mut dm) = debounce_map_cb.lock() {
-                                                let new_t = Instant::now()
-                                                    + Duration::from_millis(cfg_cb.debounce_ms);
-                                                dm.entry(from_folder.clone())
-                                                    .and_modify(|(t, c)| {
-                                                        *t = new_t;
-                                                        *c += 1;
-                                                    })
-                                                    .or_insert((new_t, 1));
-                                                dm.entry(to_folder.clone())
-                                                    .and_modify(|(t, c)| {
-                                                        *t = new_t;
-                                                        *c += 1;
-                                                    })
-                                                    .or_insert((new_t, 1));
-                                                if let Some(mut p) =
-                                                    from_folder.parent().map(|x| x.to_path_buf())
-                                                {
-                                                    while p.starts_with(&cfg_cb.root_folder) {
-                                                        if t.nodes.contains_key(&p) {
-                                                            let new_t = Instant::now()
-                                                                + Duration::from_millis(
-                                                                    cfg_cb.debounce_ms,
-                                                                );
-                                                            dm.entry(p.clone())
-                                                                .and_modify(|(t, _c)| {
-                                                                    *t = new_t;
-                                                                })
-                                                                .or_insert((new_t, 0));
-                                                        }
-                                                        if p == cfg_cb.root_folder {
-                                                            break;
-                                                        }
-                                                        if let Some(np) =
-                                                            p.parent().map(|x| x.to_path_buf())
-                                                        {
-                                                            p = np;
-                                                        } else {
-                                                            break;
-                                                        }
-                                                    }
+                                                    warn!(
+                                                        "Failed to carry track cache over for {:?}: {}",
+                                                        to_path, e
+                                                    );
                                                 }
-                                                if let Some(mut p) =
-                                                    to_folder.parent().map(|x| x.to_path_buf())
-                                                {
-                                                    while p.starts_with(&cfg_cb.root_folder) {
-                                                        if t.nodes.contains_key(&p) {
-                                                            let new_t = Instant::now()
-                                                                + Duration::from_millis(
-                                                                    cfg_cb.debounce_ms,
-                                                                );
-                                                            dm.entry(p.clone())
-                                                                .and_modify(|(t, _c)| {
-                                                                    *t = new_t;
-                                                                })
-                                                                .or_insert((new_t, 0));
-                                                        }
-                                                        if p == cfg_cb.root_folder {
-                                                            break;
-                                                        }
-                                                        if let Some(np) =
-                                                            p.parent().map(|x| x.to_path_buf())
-                                                        {
-                                                            p = np;
-                                                        } else {
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                            // enqueue a rename event (playlist rename) into DB: use old playlist name as key
-                                            let rel_from = from_folder
-                                                .strip_prefix(&cfg_cb.root_folder)
-                                                .unwrap_or(&from_folder)
-                                                .display()
-                                                .to_string();
-                                            let playlist_name_from = rel_from.clone();
-
-                                            let rel_to = to_folder
-                                                .strip_prefix(&cfg_cb.root_folder)
-                                                .unwrap_or(&to_folder)
-                                                .display()
-                                                .to_string();
-                                            let playlist_name_to = rel_to.clone();
-
-                                            match (remote_allowed_from, remote_allowed_to) {
-                                                (true, true) => {
-                                                    let extra = match serde_json::json!({"from": playlist_name_from, "to": playlist_name_to}).to_string() {
-                                                            s => s,
-                                                        };
-                                                    let db_path2 = db_path.clone();
-                                                    let pname = playlist_name_from.clone();
-                                                    let extra_clone = extra.clone();
-                                                    let h = thread::spawn(move || {
-                                                        if let Ok(conn) = db::open_or_create(
-                                                            std::path::Path::new(&db_path2),
-                                                        ) {
-                                                            if let Err(e) = db::enqueue_event(
-                                                                &conn,
-                                                                &pname,
-                                                                &EventAction::Rename {
-                                                                    from: playlist_name_from
-                                                                        .clone(),
-                                                                    to: playlist_name_to.clone(),
-                                                                },
-                                                                None,
-                                                                Some(&extra_clone),
-                                                            ) {
-                                                                warn!(
-                                                                    "Failed to enqueue rename event: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    });
-                                                    enqueue_handles_cb.lock().unwrap().push(h);
-                                                }
-                                                (true, false) => {
-                                                    let db_path2 = db_path.clone();
-                                                    let pname = playlist_name_from.clone();
-                                                    let h = thread::spawn(move || {
-                                                        if let Ok(conn) = db::open_or_create(
-                                                            std::path::Path::new(&db_path2),
-                                                        ) {
-                                                            if let Err(e) = db::enqueue_event(
-                                                                &conn,
-                                                                &pname,
-                                                                &EventAction::Delete,
-                                                                None,
-                                                                None,
-                                                            ) {
-                                                                warn!(
-                                                                    "Failed to enqueue delete event: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    });
-                                                    enqueue_handles_cb.lock().unwrap().push(h);
-                                                }
-                                                (false, true) => {
-                                                    let db_path2 = db_path.clone();
-                                                    let pname = playlist_name_to.clone();
-                                                    let h = thread::spawn(move || {
-                                                        if let Ok(conn) = db::open_or_create(
-                                                            std::path::Path::new(&db_path2),
-                                                        ) {
-                                                            if let Err(e) = db::enqueue_event(
-                                                                &conn,
-                                                                &pname,
-                                                                &EventAction::Create,
-                                                                None,
-                                                                None,
-                                                            ) {
-                                                                warn!(
-                                                                    "Failed to enqueue create event: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    });
-                                                    enqueue_handles_cb.lock().unwrap().push(h);
-                                                }
-                                                _ => {}
                                             }
-                                        }
+                                        });
+                                        enqueue_handles_cb.lock().unwrap().push(h);
                                     }
                                 }
                             }
                         }
                     }
                 }
-                Err(e) => {
-                    warn!("notify error: {:?}", e);
+            }
+            Err(e) => {
+                if is_watch_limit_error(&e) {
+                    watch_limit_hit_cb.store(true, Ordering::SeqCst);
                 }
+                warn!("notify error: {:?}", e);
             }
-        },
+        }
+    };
+    let handle_event = Arc::new(handle_event);
+
+    if cfg.watch_mode == "poll" {
+        run_poll_loop(&settings, tree, &shutdown, move |res| handle_event(res));
+        return finish_shutdown(&flush, debounce_thread);
+    }
+
+    // inotify needs one watch per folder; compare against the kernel limit up
+    // front so an oversized library goes straight to polling.
+    let roots = cfg.roots();
+    let folder_count = count_watch_folders(&roots);
+    let watch_limit = inotify_max_user_watches();
+    match watch_limit {
+        Some(limit) => info!(
+            "Watching {} folder(s) under {} root(s) (fs.inotify.max_user_watches = {})",
+            folder_count,
+            roots.len(),
+            limit
+        ),
+        None => info!(
+            "Watching {} folder(s) under {} root(s)",
+            folder_count,
+            roots.len()
+        ),
+    }
+    if watch_limit.is_some_and(|limit| folder_count > limit) {
+        warn_watch_limit(cfg, folder_count, watch_limit);
+        run_poll_loop(&settings, tree, &shutdown, move |res| handle_event(res));
+        return finish_shutdown(&flush, debounce_thread);
+    }
+
+    // Create a RecommendedWatcher that will call our closure for each FS event.
+    let handler = handle_event.clone();
+    let mut watcher: RecommendedWatcher = match RecommendedWatcher::new(
+        move |res: NotifyResult<NotifyEvent>| handler(res),
         NotifyConfig::default(),
     ) {
         Ok(w) => w,
         Err(e) => {
-            warn!("Failed to create file watcher: {}", e);
-            // fallthrough; return Ok so watcher process still runs initial playlists
-            return Ok(());
+            warn!(
+                "Failed to create file watcher: {}; falling back to polling every {}s",
+                e, cfg.poll_interval_sec
+            );
+            run_poll_loop(&settings, tree, &shutdown, move |res| handle_event(res));
+            return finish_shutdown(&flush, debounce_thread);
         }
     };
 
-    // start watching root folder recursively
-    if let Err(e) = watcher.watch(&cfg.root_folder, RecursiveMode::Recursive) {
-        warn!("Failed to start watcher for {:?}: {}", cfg.root_folder, e);
-    } else {
-        info!("File watcher started on root {:?}", cfg.root_folder);
+    // start watching every root folder recursively
+    for root in roots.iter() {
+        if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+            if is_watch_limit_error(&e) {
+                warn_watch_limit(cfg, folder_count, watch_limit);
+                drop(watcher);
+                run_poll_loop(&settings, tree, &shutdown, move |res| handle_event(res));
+                return finish_shutdown(&flush, debounce_thread);
+            }
+            warn!("Failed to start watcher for {:?}: {}", root, e);
+        } else {
+            info!("File watcher started on root {:?}", root);
+        }
     }
     // keep watcher in scope; it will run for the lifetime of this function
 
-    // Block indefinitely so the watcher process stays alive and can
-    // continue receiving filesystem events.
-    loop {
-        std::thread::sleep(Duration::from_secs(60));
+    // Block until shutdown so the watcher process stays alive and can
+    // continue receiving filesystem events, switching to polling if the
+    // watch limit is hit later on (e.g. while new folders are added).
+    while !sleep_unless_shutdown(Duration::from_secs(5), &shutdown) {
+        if watch_limit_hit.load(Ordering::SeqCst) {
+            warn_watch_limit(cfg, count_watch_folders(&roots), watch_limit);
+            drop(watcher);
+            run_poll_loop(&settings, tree, &shutdown, move |res| handle_event(res));
+            return finish_shutdown(&flush, debounce_thread);
+        }
     }
+    // Stop receiving events before the final flush.
+    drop(watcher);
+    finish_shutdown(&flush, debounce_thread)
 }
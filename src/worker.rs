@@ -1,4 +1,8 @@
-use crate::api::{spotify::SpotifyProvider, tidal::TidalProvider, Provider};
+use crate::api::{
+    apple_music::AppleMusicProvider, deezer::DeezerProvider, filesystem::FileSystemProvider,
+    plex::PlexProvider, spotify::SpotifyProvider, subsonic::SubsonicProvider, tidal::TidalProvider,
+    ytmusic::YtMusicProvider, Provider, ProviderError,
+};
 use crate::collapse::collapse_events;
 use crate::config::Config;
 use crate::db;
@@ -7,14 +11,12 @@ use crate::watcher::{compile_whitelist, matches_whitelist};
 use anyhow::{Context, Result};
 use chrono::Utc;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use uuid::Uuid;
 
 const PHASE_WIDTH: usize = 10;
 
-/// negative lookup cache entries are valid for this many seconds (30 days)
-const NEGATIVE_CACHE_TTL_SECS: i64 = 30 * 24 * 3600;
-
 fn log_run_tag(run_id: &str) -> String {
     let short = &run_id[..std::cmp::min(8, run_id.len())];
     format!("[RUN:{run}]", run = short)
@@ -28,6 +30,426 @@ fn log_phase_tag(phase: &str) -> String {
     format!("[PH:{:<width$}]", phase, width = PHASE_WIDTH)
 }
 
+/// True if the worker should instantiate `provider`: it has stored
+/// credentials and is allowed by `enabled_providers`.  Providers that are
+/// skipped only because of that filter are logged.
+fn use_provider(cfg: &Config, provider: &str, has_credentials: bool) -> bool {
+    if !has_credentials {
+        return false;
+    }
+    if !cfg.provider_enabled(provider) {
+        log::info!(
+            "Skipping provider {}: credentials stored but not in enabled_providers",
+            provider
+        );
+        return false;
+    }
+    true
+}
+
+/// True if `local_path` has a negative track cache entry for `provider` that
+/// is younger than `cfg.unresolved_retry_secs`.
+async fn unresolved_recently(
+    cfg: &Config,
+    db_pool: &db::DbPool,
+    provider: &str,
+    local_path: &str,
+) -> Result<bool> {
+    let pool = db_pool.clone();
+    let provider = provider.to_string();
+    let local_path = local_path.to_string();
+    let not_found_at = tokio::task::spawn_blocking(move || -> Result<Option<i64>> {
+        let conn = pool.get()?;
+        db::get_track_unresolved_at(&conn, &provider, &local_path)
+    })
+    .await??;
+    Ok(not_found_at.is_some_and(|t| Utc::now().timestamp() - t < cfg.unresolved_retry_secs as i64))
+}
+
+/// The ISRC in the tags of `disk_path` (the file `local_path` keys in the
+/// track cache).  Blocking: the file is only opened when the cache has no
+/// entry for its current mtime and size, so unchanged files are read once
+/// and retagged ones again.
+fn local_file_isrc(
+    db_pool: &db::DbPool,
+    provider: &str,
+    local_path: &str,
+    disk_path: &std::path::Path,
+) -> Option<String> {
+    let stamp = std::fs::metadata(disk_path).ok().and_then(|md| {
+        let mtime = md
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        Some((mtime.as_nanos() as i64, md.len() as i64))
+    });
+    let (Some((mtime_ns, size)), Ok(conn)) = (stamp, db_pool.get()) else {
+        return crate::util::extract_isrc_from_path(disk_path);
+    };
+    match db::cached_file_isrc(&conn, local_path, mtime_ns, size) {
+        Ok(Some(isrc)) => return isrc,
+        Ok(None) => {}
+        Err(e) => log::debug!("reading the cached ISRC of {} failed: {}", local_path, e),
+    }
+    let isrc = crate::util::extract_isrc_from_path(disk_path);
+    if let Err(e) =
+        db::store_file_isrc(&conn, provider, local_path, mtime_ns, size, isrc.as_deref())
+    {
+        log::debug!("caching the ISRC of {} failed: {}", local_path, e);
+    }
+    isrc
+}
+
+/// The ISRC a provider reported for its match of `local_path` (see
+/// [`db::shared_remote_isrc`]), so this one can search by it.
+async fn shared_remote_isrc(db_pool: &db::DbPool, local_path: &str) -> Option<String> {
+    let pool = db_pool.clone();
+    let path = local_path.to_string();
+    let shared = tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        db::shared_remote_isrc(&*pool.get()?, &path)
+    })
+    .await;
+    match shared {
+        Ok(Ok(isrc)) => isrc,
+        Ok(Err(e)) => {
+            log::debug!("reading the shared ISRC of {} failed: {}", local_path, e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Ask `provider` for the ISRC of `uri`, the metadata match of
+/// `local_path`, and keep it for the other providers (see
+/// [`db::store_remote_isrc`]).
+async fn learn_remote_isrc(
+    db_pool: &db::DbPool,
+    provider: &dyn Provider,
+    local_path: &str,
+    uri: &str,
+) -> Option<String> {
+    let isrc = provider
+        .lookup_track_isrc(uri)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|isrc| crate::util::normalize_isrc(&isrc))?;
+    let pool = db_pool.clone();
+    let (name, path, code) = (
+        provider.name().to_string(),
+        local_path.to_string(),
+        isrc.clone(),
+    );
+    let stored = tokio::task::spawn_blocking(move || -> Result<()> {
+        db::store_remote_isrc(&*pool.get()?, &name, &path, &code)
+    })
+    .await;
+    if let Ok(Err(e)) = stored {
+        log::debug!("storing the ISRC of {} failed: {}", local_path, e);
+    }
+    Some(isrc)
+}
+
+/// Check a track cache hit against the file's tags: when the provider
+/// reports an ISRC for `cached_uri` that differs from the one in the file,
+/// the entry was a bad match and is deleted so the caller resolves the file
+/// again; returns true in that case.  Each cached URI is only checked once,
+/// and files without an ISRC or providers that cannot tell keep the entry.
+/// Lookup errors keep it too, and it is checked again next time.
+async fn drop_stale_cached_uri(
+    db_pool: &db::DbPool,
+    provider: &dyn Provider,
+    local_path: &str,
+    cached_uri: &str,
+) -> Result<bool> {
+    let pool = db_pool.clone();
+    let (provider_name, path) = (provider.name().to_string(), local_path.to_string());
+    let checked = tokio::task::spawn_blocking(move || -> Result<bool> {
+        db::track_isrc_checked(&*pool.get()?, &provider_name, &path)
+    })
+    .await??;
+    if checked {
+        return Ok(false);
+    }
+    let pool = db_pool.clone();
+    let (provider_name, path) = (provider.name().to_string(), local_path.to_string());
+    let local_isrc = tokio::task::spawn_blocking(move || {
+        let p = crate::util::resolve_lossy_path(std::path::Path::new(&path));
+        local_file_isrc(&pool, &provider_name, &path, &p)
+    })
+    .await
+    .unwrap_or(None);
+    let stale = match &local_isrc {
+        None => false,
+        Some(local) => match provider.lookup_track_isrc(cached_uri).await {
+            Ok(remote) => remote
+                .and_then(|r| crate::util::normalize_isrc(&r))
+                .is_some_and(|r| &r != local),
+            Err(e) => {
+                log::debug!(
+                    "isrc check of cached {} for {} failed: {}",
+                    cached_uri,
+                    local_path,
+                    e
+                );
+                return Ok(false);
+            }
+        },
+    };
+    if stale {
+        log::warn!(
+            "Cached {} match {} for {:?} has a different ISRC than the file ({}); resolving it again",
+            provider.name(),
+            cached_uri,
+            local_path,
+            local_isrc.as_deref().unwrap_or_default()
+        );
+    }
+    let pool = db_pool.clone();
+    let (provider_name, path) = (provider.name().to_string(), local_path.to_string());
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let conn = pool.get()?;
+        if stale {
+            db::delete_track_cache_entry(&conn, &provider_name, &path)?;
+        } else {
+            db::mark_track_isrc_checked(&conn, &provider_name, &path)?;
+        }
+        Ok(())
+    })
+    .await??;
+    Ok(stale)
+}
+
+/// The remote URI `local_path` is pinned to on `provider` (see
+/// `db::set_override`).  An override wins over the track cache, ISRC and
+/// metadata search and is never re-resolved.
+async fn override_uri(
+    db_pool: &db::DbPool,
+    provider: &str,
+    local_path: &str,
+) -> Result<Option<String>> {
+    let pool = db_pool.clone();
+    let provider = provider.to_string();
+    let local_path = local_path.to_string();
+    tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        let conn = pool.get()?;
+        db::get_override(&conn, &provider, &local_path)
+    })
+    .await?
+}
+
+/// Remote URIs the files still listed in the local playlist of
+/// `playlist_name` are known to map to on `provider` (overrides and cached
+/// matches; nothing is resolved).  Several files can share a URI, e.g.
+/// duplicate rips, so a Remove of one must not drop the URI the other
+/// still needs.
+async fn uris_still_listed(
+    cfg: &Config,
+    db_pool: &db::DbPool,
+    provider: &str,
+    playlist_name: &str,
+) -> Result<std::collections::HashSet<String>> {
+    let folder = cfg.playlist_folder_for_key(playlist_name);
+    let entries = crate::playlist::read_playlist_entries(&local_playlist_path(cfg, playlist_name))
+        .unwrap_or_default();
+    let pool = db_pool.clone();
+    let provider = provider.to_string();
+    tokio::task::spawn_blocking(move || -> Result<std::collections::HashSet<String>> {
+        let conn = pool.get()?;
+        let mut uris = std::collections::HashSet::new();
+        for line in entries {
+            let path = crate::util::resolve_lossy_path(&folder.join(line));
+            // A deleted file may still be listed until the watcher rewrites
+            // the playlist.
+            if !path.exists() {
+                continue;
+            }
+            let key = path.to_string_lossy();
+            let uri = match db::get_override(&conn, &provider, &key)? {
+                Some(uri) => Some(uri),
+                None => db::get_track_cache_by_local(&conn, &provider, &key)?
+                    .and_then(|(_, remote_id, _)| remote_id),
+            };
+            uris.extend(uri);
+        }
+        Ok(uris)
+    })
+    .await?
+}
+
+/// Ask MusicBrainz for the ISRC of a file without an ISRC tag, using the
+/// same artist/title/album the metadata search would use.  None when
+/// `enable_musicbrainz` is off or nothing trustworthy was found.
+async fn musicbrainz_isrc(
+    cfg: &Config,
+    path: &std::path::Path,
+    filename_regex: Option<&regex::Regex>,
+) -> Option<String> {
+    if !cfg.enable_musicbrainz {
+        return None;
+    }
+    let p = path.to_path_buf();
+    let re = filename_regex.cloned();
+    let suffixes = cfg.compiled_filename_copy_suffixes().unwrap_or_default();
+    let (candidate, album, duration_ms) = tokio::task::spawn_blocking(move || {
+        (
+            crate::util::artist_title_candidates_stripped(&p, re.as_ref(), &suffixes)
+                .into_iter()
+                .next(),
+            crate::util::album_for_path_with(&p, re.as_ref()),
+            crate::util::duration_ms_from_path(&p),
+        )
+    })
+    .await
+    .ok()?;
+    let (artist, title) = candidate?;
+    match crate::util::musicbrainz_lookup_isrc(
+        &artist,
+        &title,
+        album.as_deref(),
+        duration_ms,
+        cfg.match_duration_tolerance_secs,
+    )
+    .await
+    {
+        Ok(isrc) => {
+            log::debug!(
+                "musicbrainz isrc lookup track={} isrc={:?}",
+                path.display(),
+                isrc
+            );
+            isrc
+        }
+        Err(e) => {
+            log::warn!(
+                "musicbrainz isrc lookup failed track={} error={}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Metadata search for one `(artist, title)` candidate, returning the
+/// confident match (see [`crate::api::pick_confident_match`]).
+async fn search_confident_match(
+    cfg: &Config,
+    provider: &dyn Provider,
+    title: &str,
+    artist: &str,
+    album: Option<&str>,
+    duration_ms: Option<u64>,
+) -> crate::api::ProviderResult<Option<crate::api::TrackMatch>> {
+    let found = provider
+        .search_track_candidates(title, artist, album, crate::api::DEFAULT_SEARCH_CANDIDATES)
+        .await?;
+    Ok(crate::api::pick_confident_match(
+        found,
+        &crate::api::LocalTrack {
+            title,
+            artist,
+            album,
+            duration_ms,
+        },
+        cfg.match_duration_tolerance_secs,
+        cfg.min_match_confidence,
+    ))
+}
+
+/// Index of the candidate after `i` that is `candidates[i]` with artist and
+/// title swapped, i.e. the other ordering of a `" - "` file name split.
+fn swapped_ordering(candidates: &[(String, String)], i: usize) -> Option<usize> {
+    let (artist, title) = &candidates[i];
+    (i + 1..candidates.len()).find(|&j| &candidates[j].0 == title && &candidates[j].1 == artist)
+}
+
+/// Of the matches found for both orderings of a file name `stem`, the one
+/// whose title and artist cover the stem best
+/// ([`crate::api::stem_match_score`]); `first` on a tie or when the provider
+/// reports no metadata.
+fn better_ordering(
+    stem: &str,
+    first: crate::api::TrackMatch,
+    second: Option<crate::api::TrackMatch>,
+) -> crate::api::TrackMatch {
+    let Some(second) = second else {
+        return first;
+    };
+    let score = |m: &crate::api::TrackMatch| crate::api::stem_match_score(stem, m).unwrap_or(0.0);
+    if score(&second) > score(&first) {
+        log::debug!(
+            "preferring {} over {} for both orderings of {:?}",
+            second.uri,
+            first.uri,
+            stem
+        );
+        second
+    } else {
+        first
+    }
+}
+
+/// Metadata search for the file at `path`: the artist/title candidates of
+/// its tags or file name are searched in turn until one gives a confident
+/// match.  A hit for one ordering of a `" - "` file name does not rule out
+/// the other, so both are searched and the better match kept.  Failed
+/// searches skip the candidate.
+async fn search_file_by_metadata(
+    cfg: &Config,
+    provider: &dyn Provider,
+    path: &std::path::Path,
+    filename_regex: Option<&regex::Regex>,
+    copy_suffixes: &[regex::Regex],
+) -> Option<crate::api::TrackMatch> {
+    let p = path.to_path_buf();
+    let re = filename_regex.cloned();
+    let suffixes = copy_suffixes.to_vec();
+    let (candidates, album, duration_ms) = tokio::task::spawn_blocking(move || {
+        (
+            crate::util::artist_title_candidates_stripped(&p, re.as_ref(), &suffixes),
+            crate::util::album_for_path_with(&p, re.as_ref()),
+            crate::util::duration_ms_from_path(&p),
+        )
+    })
+    .await
+    .unwrap_or_default();
+    let candidates = crate::util::with_normalized_search_terms(
+        candidates,
+        &cfg.search_strip_tokens,
+        cfg.search_strip_diacritics,
+    );
+    for (i, (artist, title)) in candidates.iter().enumerate() {
+        let Ok(Some(found)) =
+            search_confident_match(cfg, provider, title, artist, album.as_deref(), duration_ms)
+                .await
+        else {
+            continue;
+        };
+        return Some(match swapped_ordering(&candidates, i) {
+            Some(j) => {
+                let other = search_confident_match(
+                    cfg,
+                    provider,
+                    &candidates[j].1,
+                    &candidates[j].0,
+                    album.as_deref(),
+                    duration_ms,
+                )
+                .await;
+                better_ordering(
+                    &format!("{} {}", artist, title),
+                    found,
+                    other.ok().flatten(),
+                )
+            }
+            None => found,
+        });
+    }
+    None
+}
+
 async fn mark_events_synced_async(pool: db::DbPool, ids: Vec<i64>) -> Result<()> {
     if ids.is_empty() {
         return Ok(());
@@ -41,6 +463,12 @@ async fn mark_events_synced_async(pool: db::DbPool, ids: Vec<i64>) -> Result<()>
     Ok(())
 }
 
+/// Name of the processing lock a provider takes for a playlist.  Locks are
+/// per provider so the providers of one playlist can run concurrently.
+fn provider_lock_key(playlist_name: &str, provider_name: &str) -> String {
+    format!("{}:{}", provider_name, playlist_name)
+}
+
 /// Release a playlist processing lock in a background blocking task.
 /// Ignores errors so callers can use this in error-recovery paths.
 async fn release_lock_async(pool: &db::DbPool, playlist_name: &str, worker_id: &str) {
@@ -55,28 +483,112 @@ async fn release_lock_async(pool: &db::DbPool, playlist_name: &str, worker_id: &
     .await;
 }
 
-/// Compute the set of remote track URIs that should be present for a playlist
-/// based on the current local playlist file contents.
-///
-/// This helper reads the local .m3u for the logical playlist key, resolves
-/// each referenced file to a remote URI using the same cache/lookup logic as
-/// the event-driven worker (track_cache first, then ISRC/metadata lookup),
-/// and returns the resulting URI set.
-pub async fn desired_remote_uris_for_playlist(
+/// Extend this worker's lease on a playlist lock by `cfg.lock_ttl_secs` so
+/// long-running processing does not lose it mid-run.  Failures are logged.
+async fn renew_lock_async(cfg: &Config, pool: &db::DbPool, lock_key: &str, worker_id: &str) {
+    let pool = pool.clone();
+    let key = lock_key.to_string();
+    let wid = worker_id.to_string();
+    let ttl = cfg.lock_ttl_secs;
+    let renewed = tokio::task::spawn_blocking(move || -> Result<bool> {
+        let conn = pool.get()?;
+        db::renew_playlist_lock(&conn, &key, &wid, ttl)
+    })
+    .await;
+    match renewed {
+        Ok(Ok(true)) => {}
+        Ok(Ok(false)) => log::warn!(
+            "{} lock_renew_lost lock={} (lease expired and was taken over)",
+            log_run_tag(worker_id),
+            lock_key
+        ),
+        Ok(Err(e)) => log::warn!(
+            "{} lock_renew_failed lock={} error={}",
+            log_run_tag(worker_id),
+            lock_key,
+            e
+        ),
+        Err(e) => log::warn!(
+            "{} lock_renew_failed lock={} error={}",
+            log_run_tag(worker_id),
+            lock_key,
+            e
+        ),
+    }
+}
+
+/// Set a newly created remote playlist's cover from a `folder.jpg`/`cover.png`
+/// in the playlist folder.  Providers without cover support are skipped and
+/// failures are only logged: a missing cover never fails a sync.
+async fn upload_playlist_cover(
     cfg: &Config,
+    provider: &dyn Provider,
     playlist_name: &str,
-    provider: Arc<dyn Provider>,
-    db_pool: &db::DbPool,
-    trust_cache: bool,
-) -> Result<(Vec<String>, usize)> {
-    use std::io::BufRead;
-
-    // Map logical playlist key back to on-disk .m3u path using the same
-    // template as reconcile/watcher: playlist_name is the relative folder
-    // path under root_folder.
-    let rel = std::path::Path::new(playlist_name);
-    let folder = cfg.root_folder.join(rel);
-    let folder_name = folder.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    remote_id: &str,
+    worker_id: &str,
+) {
+    if !provider.capabilities().supports_cover_art {
+        return;
+    }
+    let folder = cfg.playlist_folder_for_key(playlist_name);
+    let Some(cover) = crate::util::find_cover_image(&folder) else {
+        return;
+    };
+    let pl_tag = log_playlist_tag(playlist_name, provider.name());
+    let bytes = match std::fs::read(&cover) {
+        Ok(b) => b,
+        Err(e) => {
+            log::warn!(
+                "{} {} {} cover_read_failed path={} error={}",
+                log_run_tag(worker_id),
+                pl_tag,
+                log_phase_tag("COVER"),
+                cover.display(),
+                e
+            );
+            return;
+        }
+    };
+    match provider.set_playlist_cover(remote_id, &bytes).await {
+        Ok(()) => log::info!(
+            "{} {} {} cover_uploaded path={}",
+            log_run_tag(worker_id),
+            pl_tag,
+            log_phase_tag("COVER"),
+            cover.display()
+        ),
+        Err(e) => log::warn!(
+            "{} {} {} cover_upload_failed path={} error={}",
+            log_run_tag(worker_id),
+            pl_tag,
+            log_phase_tag("COVER"),
+            cover.display(),
+            e
+        ),
+    }
+}
+
+/// On-disk playlist file for a logical playlist key, using the same template
+/// as reconcile/watcher (see `Config::playlist_key_for_folder`).
+fn local_playlist_path(cfg: &Config, playlist_name: &str) -> std::path::PathBuf {
+    let (folder, folder_name, path_to_parent) = local_template_values(cfg, playlist_name);
+    let file_name = cfg.local_playlist_file_name(&folder, &folder_name, &path_to_parent);
+    folder.join(file_name)
+}
+
+/// Folder, `${folder_name}` and `${path_to_parent}` (root-relative, with a
+/// trailing separator when non-empty) of a logical playlist key.
+fn local_template_values(
+    cfg: &Config,
+    playlist_name: &str,
+) -> (std::path::PathBuf, String, String) {
+    let folder = cfg.playlist_folder_for_key(playlist_name);
+    let rel = cfg.root_relative(&folder);
+    let folder_name = folder
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default()
+        .to_string();
     let path_to_parent = rel
         .parent()
         .map(|p| p.to_path_buf())
@@ -90,12 +602,46 @@ pub async fn desired_remote_uris_for_playlist(
         }
         s
     };
-    let playlist_file_name = crate::util::expand_template(
-        &cfg.local_playlist_template,
-        folder_name,
-        &path_to_parent_str,
-    );
-    let playlist_path = folder.join(playlist_file_name);
+    (folder, folder_name, path_to_parent_str)
+}
+
+/// Expand `playlist_description_template` for a playlist.  Here
+/// `${track_count}` is the number of entries in the local playlist file (0
+/// when it does not exist yet).
+fn playlist_description(cfg: &Config, playlist_name: &str) -> String {
+    if cfg.playlist_description_template.is_empty() {
+        return String::new();
+    }
+    let (_, folder_name, path_to_parent) = local_template_values(cfg, playlist_name);
+    crate::util::expand_template(
+        &cfg.playlist_description_template,
+        &folder_name,
+        &path_to_parent,
+        crate::util::template_parent_name(&path_to_parent),
+        || {
+            crate::playlist::read_playlist_entries(&local_playlist_path(cfg, playlist_name))
+                .map(|entries| entries.len())
+                .unwrap_or(0)
+        },
+    )
+}
+
+/// Compute the set of remote track URIs that should be present for a playlist
+/// based on the current local playlist file contents.
+///
+/// This helper reads the local .m3u for the logical playlist key, resolves
+/// each referenced file to a remote URI using the same cache/lookup logic as
+/// the event-driven worker (track_cache first, then ISRC/metadata lookup),
+/// and returns the resulting URI set.
+pub async fn desired_remote_uris_for_playlist(
+    cfg: &Config,
+    playlist_name: &str,
+    provider: Arc<dyn Provider>,
+    db_pool: &db::DbPool,
+    trust_cache: bool,
+) -> Result<(Vec<String>, usize)> {
+    let folder = cfg.playlist_folder_for_key(playlist_name);
+    let playlist_path = local_playlist_path(cfg, playlist_name);
 
     if !playlist_path.exists() {
         return Ok((Vec::new(), 0));
@@ -138,85 +684,175 @@ pub async fn desired_remote_uris_for_playlist(
             }
             // parse the cached JSON and return
             let uris: Vec<String> = serde_json::from_str(&uris_json).unwrap_or_default();
-            // Count how many non-comment, non-blank lines the m3u file currently
-            // has so the caller can tell "empty file" from "all lookups failed".
-            let local_track_count = std::fs::File::open(&playlist_path)
-                .ok()
-                .map(|f| {
-                    use std::io::BufRead;
-                    std::io::BufReader::new(f)
-                        .lines()
-                        .filter_map(|l| l.ok())
-                        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
-                        .count()
-                })
+            // Count how many entries the playlist file currently has so the
+            // caller can tell "empty file" from "all lookups failed".
+            let local_track_count = crate::playlist::read_playlist_entries(&playlist_path)
+                .map(|entries| entries.len())
                 .unwrap_or(0);
             return Ok((uris, local_track_count));
         }
     }
 
-    let file = std::fs::File::open(&playlist_path)?;
-    let reader = std::io::BufReader::new(file);
-    let mut uris: Vec<String> = Vec::new();
+    let entries = crate::playlist::read_playlist_entries(&playlist_path)?;
+    let filename_regex = cfg.compiled_filename_parse_regex()?;
+    let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
     let mut local_track_count: usize = 0;
     let provider_name = provider.name().to_string();
 
-    for line in reader.lines() {
-        let line = line.unwrap_or_default();
-        if line.starts_with('#') || line.trim().is_empty() {
-            continue;
-        }
-        let local_path = folder.join(&line);
+    /// A playlist entry: resolved without a search, or still to be looked
+    /// up (by ISRC if it has one, else by metadata).
+    enum Slot {
+        Uri(String),
+        Pending {
+            local_path: std::path::PathBuf,
+            local_path_str: String,
+            isrc: Option<String>,
+        },
+    }
+    let mut slots: Vec<Slot> = Vec::new();
+
+    for line in entries {
+        let local_path = crate::util::resolve_lossy_path(&folder.join(&line));
         if !local_path.exists() {
             continue;
         }
         local_track_count += 1;
 
-        let local_path_str = local_path.display().to_string();
+        let local_path_str = local_path.to_string_lossy().to_string();
+
+        if let Some(uri) = provider.local_track_uri(&local_path) {
+            slots.push(Slot::Uri(uri));
+            continue;
+        }
+        if let Some(uri) = override_uri(db_pool, &provider_name, &local_path_str).await? {
+            slots.push(Slot::Uri(uri));
+            continue;
+        }
 
         // First, try the track cache by local path.
         // lookup existing cache entry (includes resolved_at timestamp)
         let pool = db_pool.clone();
         let provider_name_for_lookup = provider_name.clone();
         let local_path_for_lookup = local_path_str.clone();
-        let cached: Option<(Option<String>, Option<String>, i64)> = tokio::task::spawn_blocking(
-            move || -> Result<Option<(Option<String>, Option<String>, i64)>, anyhow::Error> {
-                let conn = pool.get()?;
-                Ok(db::get_track_cache_by_local(
-                    &conn,
-                    &provider_name_for_lookup,
-                    &local_path_for_lookup,
-                )?)
-            },
-        )
-        .await??;
+        let mut cached: Option<(Option<String>, Option<String>, i64)> =
+            tokio::task::spawn_blocking(
+                move || -> Result<Option<(Option<String>, Option<String>, i64)>, anyhow::Error> {
+                    let conn = pool.get()?;
+                    Ok(db::get_track_cache_by_local(
+                        &conn,
+                        &provider_name_for_lookup,
+                        &local_path_for_lookup,
+                    )?)
+                },
+            )
+            .await??;
+        if let Some((_, Some(uri), _)) = &cached {
+            if drop_stale_cached_uri(db_pool, provider.as_ref(), &local_path_str, uri).await? {
+                cached = None;
+            }
+        }
 
-        if let Some((_cached_isrc, cached_remote_id, resolved_at)) = &cached {
+        if let Some((_cached_isrc, cached_remote_id, _resolved_at)) = &cached {
             if let Some(uri) = cached_remote_id {
-                uris.push(uri.clone());
+                slots.push(Slot::Uri(uri.clone()));
+                continue;
+            } else if unresolved_recently(cfg, db_pool, &provider_name, &local_path_str).await? {
+                // track previously failed to resolve recently; skip further attempts
                 continue;
-            } else {
-                // negative cache hit? check TTL
-                let now = Utc::now().timestamp();
-                if now - *resolved_at < NEGATIVE_CACHE_TTL_SECS {
-                    // track previously failed to resolve recently; skip further attempts
-                    continue;
-                }
-                // else fall through and re-query
             }
+            // else fall through and re-query
         }
 
         // Try to extract ISRC from local file metadata and perform an ISRC-based search.
-        let p = local_path.clone();
-        let extracted =
-            tokio::task::spawn_blocking(move || crate::util::extract_isrc_from_path(&p))
+        let (pool, name, key, p) = (
+            db_pool.clone(),
+            provider_name.clone(),
+            local_path_str.clone(),
+            local_path.clone(),
+        );
+        let mut extracted =
+            tokio::task::spawn_blocking(move || local_file_isrc(&pool, &name, &key, &p))
                 .await
                 .unwrap_or(None);
+        if extracted.is_none() {
+            // An ISRC found on MusicBrainz by an earlier run is kept in the
+            // track cache; only ask again when there is none, and no other
+            // provider reported one for its match.
+            extracted = match cached.as_ref().and_then(|(isrc, _, _)| isrc.clone()) {
+                Some(isrc) => Some(isrc),
+                None => match shared_remote_isrc(db_pool, &local_path_str).await {
+                    Some(isrc) => Some(isrc),
+                    None => musicbrainz_isrc(cfg, &local_path, filename_regex.as_ref()).await,
+                },
+            };
+        }
+
+        slots.push(Slot::Pending {
+            local_path,
+            local_path_str,
+            isrc: extracted,
+        });
+    }
+
+    // Resolve every ISRC of the playlist at once; only the misses fall
+    // through to one metadata search each.
+    let isrcs: Vec<String> = {
+        let mut seen = std::collections::HashSet::new();
+        slots
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Pending {
+                    isrc: Some(isrc), ..
+                } => Some(isrc.clone()),
+                _ => None,
+            })
+            .filter(|isrc| seen.insert(isrc.clone()))
+            .collect()
+    };
+    let by_isrc = if isrcs.is_empty() || !provider.capabilities().supports_isrc_search {
+        std::collections::HashMap::new()
+    } else {
+        match provider.search_track_uris_by_isrc(&isrcs).await {
+            Ok(found) => {
+                log::debug!(
+                    "reconcile: {} of {} ISRC(s) in {} found on {}",
+                    found.len(),
+                    isrcs.len(),
+                    playlist_name,
+                    provider.name()
+                );
+                found
+            }
+            Err(e) => {
+                log::warn!(
+                    "reconcile: ISRC lookup for {} on {} failed, using metadata search: {}",
+                    playlist_name,
+                    provider.name(),
+                    e
+                );
+                std::collections::HashMap::new()
+            }
+        }
+    };
+
+    let mut uris: Vec<String> = Vec::new();
+    for slot in slots {
+        let (local_path, local_path_str, extracted) = match slot {
+            Slot::Uri(uri) => {
+                uris.push(uri);
+                continue;
+            }
+            Slot::Pending {
+                local_path,
+                local_path_str,
+                isrc,
+            } => (local_path, local_path_str, isrc),
+        };
 
         let mut uri_opt: Option<String> = None;
 
         if let Some(isrc) = extracted.clone() {
-            if let Ok(Some(u)) = provider.search_track_uri_by_isrc(&isrc).await {
+            if let Some(u) = by_isrc.get(&isrc).cloned() {
                 uri_opt = Some(u.clone());
 
                 // Persist into track_cache for future lookups.
@@ -238,74 +874,67 @@ pub async fn desired_remote_uris_for_playlist(
             }
         }
 
-        // Fallback: derive artist/title from filename and search.
+        // Fallback: derive artist/title from tags (or the filename) and search.
         if uri_opt.is_none() {
-            let fname = local_path
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-            let stem = if let Some((base, _ext)) = fname.rsplit_once('.') {
-                base
-            } else {
-                fname
-            };
-            let mut candidates: Vec<(&str, &str)> = Vec::new();
-            if let Some((left, right)) = stem.split_once(" - ") {
-                let left = left.trim();
-                let right = right.trim();
-                candidates.push((left, right));
-                candidates.push((right, left));
-            } else {
-                candidates.push(("", stem));
-            }
-            for (artist, title) in candidates.into_iter() {
-                if let Ok(Some(u)) = provider.search_track_uri(title, artist).await {
-                    uri_opt = Some(u.clone());
-
-                    // Persist into track_cache.
-                    let pool = db_pool.clone();
-                    let local_path_for_cache = local_path_str.clone();
-                    let provider_name_for_cache = provider.name().to_string();
-                    let isrc_clone = extracted.clone();
-                    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                        let conn = pool.get()?;
-                        let _ = db::upsert_track_cache(
-                            &conn,
-                            &provider_name_for_cache,
-                            &local_path_for_cache,
-                            isrc_clone.as_deref(),
-                            Some(&u),
-                        );
-                        Ok(())
-                    })
-                    .await??;
-
-                    break;
-                }
-            }
-        }
+            if let Some(found) = search_file_by_metadata(
+                cfg,
+                provider.as_ref(),
+                &local_path,
+                filename_regex.as_ref(),
+                &copy_suffixes,
+            )
+            .await
+            {
+                let u = found.uri;
+                uri_opt = Some(u.clone());
+                let isrc_clone = match extracted.clone() {
+                    Some(isrc) => Some(isrc),
+                    None => {
+                        learn_remote_isrc(db_pool, provider.as_ref(), &local_path_str, &u).await
+                    }
+                };
 
-        if let Some(u) = uri_opt {
-            uris.push(u);
-        } else {
-            log::warn!(
-                "Reconcile: could not resolve local track {:?} to remote URI for provider {}",
-                local_path,
-                provider.name()
-            );
-            // record negative result to avoid repeated lookups for a while
+                // Persist into track_cache.
+                let pool = db_pool.clone();
+                let local_path_for_cache = local_path_str.clone();
+                let provider_name_for_cache = provider.name().to_string();
+                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                    let conn = pool.get()?;
+                    let _ = db::upsert_track_cache(
+                        &conn,
+                        &provider_name_for_cache,
+                        &local_path_for_cache,
+                        isrc_clone.as_deref(),
+                        Some(&u),
+                    );
+                    Ok(())
+                })
+                .await??;
+            }
+        }
+
+        if let Some(u) = uri_opt {
+            crate::metrics::inc("tracks_resolved_total", &[("provider", provider.name())]);
+            uris.push(u);
+        } else {
+            crate::metrics::inc("tracks_unresolved_total", &[("provider", provider.name())]);
+            log::warn!(
+                "Reconcile: could not resolve local track {:?} to remote URI for provider {}",
+                local_path,
+                provider.name()
+            );
+            // record negative result to avoid repeated lookups for a while
             let pool = db_pool.clone();
             let local_path_for_cache = local_path_str.clone();
             let provider_name_for_cache = provider.name().to_string();
             let maybe_isrc = extracted.clone();
             tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
                 let conn = pool.get()?;
-                let _ = db::upsert_track_cache(
+                let _ = db::mark_track_unresolved(
                     &conn,
                     &provider_name_for_cache,
                     &local_path_for_cache,
                     maybe_isrc.as_deref(),
-                    None,
                 );
                 Ok(())
             })
@@ -386,6 +1015,32 @@ pub fn should_precompute_desired(has_delete: bool, has_track_ops: bool, has_rena
     !has_delete && !has_track_ops && !has_rename
 }
 
+/// Compute the tracks to add and remove so that `remote` matches `desired`.
+///
+/// Unlike a plain set difference, the result is ordered: additions follow the
+/// local `.m3u` sequence in `desired` and removals follow the current remote
+/// order, so providers that append in request order end up as close to the
+/// local ordering as possible.
+pub fn ordered_reconcile_diff(desired: &[String], remote: &[String]) -> (Vec<String>, Vec<String>) {
+    use std::collections::HashSet;
+    let desired_set: HashSet<&String> = desired.iter().collect();
+    let remote_set: HashSet<&String> = remote.iter().collect();
+
+    let mut seen: HashSet<&String> = HashSet::new();
+    let to_add: Vec<String> = desired
+        .iter()
+        .filter(|u| !remote_set.contains(u) && seen.insert(*u))
+        .cloned()
+        .collect();
+    let mut seen: HashSet<&String> = HashSet::new();
+    let to_remove: Vec<String> = remote
+        .iter()
+        .filter(|u| !desired_set.contains(u) && seen.insert(*u))
+        .cloned()
+        .collect();
+    (to_add, to_remove)
+}
+
 /// Compute the display name to use for a remote playlist on a given provider,
 /// based on the logical playlist key and configuration.
 ///
@@ -394,7 +1049,8 @@ pub fn should_precompute_desired(has_delete: bool, has_track_ops: bool, has_rena
 ///   playlists are logically nested.
 /// - Respecting `online_playlist_structure` ("flat" or "folders").
 /// - Using `online_folder_flattening_delimiter` when flattening hierarchies
-///   into a single playlist name.
+///   into a single playlist name, or " - " when it is empty and a root is
+///   set (see `Config::flattening_delimiter`).
 /// - Applying the appropriate remote playlist template based on structure:
 ///   `remote_playlist_template_flat` or `remote_playlist_template_folders`.
 ///   When these are empty, it falls back to `remote_playlist_template` and
@@ -405,14 +1061,16 @@ pub fn should_precompute_desired(has_delete: bool, has_track_ops: bool, has_rena
 ///                            key (e.g. "Album1").
 /// - "${path_to_parent}" -> the logical path to the playlist folder's
 ///                            parent, including `online_root_playlist` when
-///                            set. For flat mode, when
-///                            `online_folder_flattening_delimiter` is non-
-///                            empty, filesystem separators in this path are
-///                            replaced by that delimiter.
+///                            set. For flat mode, when a flattening
+///                            delimiter applies, filesystem separators in
+///                            this path are replaced by that delimiter.
 /// - "${relative_path}"  -> legacy alias, expanded as
 ///                            `path_to_parent + folder_name` so that existing
 ///                            configs continue to work.
-fn compute_remote_playlist_name(
+/// - "${parent_name}"    -> the final segment of the key's parent (not
+///   `online_root_playlist`), e.g. "Rock".
+/// - "${track_count}"    -> the number of media files in the local folder.
+pub fn compute_remote_playlist_name(
     cfg: &Config,
     _provider_name: &str,
     playlist_key: &str,
@@ -420,7 +1078,7 @@ fn compute_remote_playlist_name(
 ) -> String {
     let root = cfg.online_root_playlist.trim();
     let structure = cfg.online_playlist_structure.as_str();
-    let delim_cfg = cfg.online_folder_flattening_delimiter.as_str();
+    let delim_cfg = cfg.flattening_delimiter();
 
     // Normalize the logical playlist key to use "/" as a separator so we
     // can split it into parent path and folder_name. Existing databases may
@@ -497,7 +1155,196 @@ fn compute_remote_playlist_name(
         path_to_parent_fs
     };
 
-    crate::util::expand_template(template, &folder_name, &path_to_parent)
+    crate::util::expand_template(
+        template,
+        &folder_name,
+        &path_to_parent,
+        crate::util::template_parent_name(&parent_rel),
+        || cfg.count_folder_tracks(&cfg.playlist_folder_for_key(normalized_str)),
+    )
+}
+
+/// Remote track order expected after applying removes and (appended) adds.
+fn expected_order_after_mutations(
+    remote_before: Vec<String>,
+    removes: &std::collections::HashSet<String>,
+    adds: &[String],
+) -> Vec<String> {
+    let mut expected: Vec<String> = remote_before
+        .into_iter()
+        .filter(|u| !removes.contains(u))
+        .collect();
+    for u in adds {
+        if !expected.contains(u) {
+            expected.push(u.clone());
+        }
+    }
+    expected
+}
+
+/// Insert positions for `adds` so they land where the local `.m3u` order in
+/// `desired` has them: each new track goes right after the nearest preceding
+/// local track already in `remote`, or first when there is none.  Tracks that
+/// are not in `desired` are appended.  Neighbouring inserts are merged into a
+/// single `(position, uris)` group; the groups must be applied in order.
+/// Also returns the remote order expected once they are.
+fn positioned_adds(
+    desired: &[String],
+    remote: Vec<String>,
+    adds: &[String],
+) -> (Vec<(usize, Vec<String>)>, Vec<String>) {
+    use std::collections::HashSet;
+    let wanted: HashSet<&String> = adds.iter().collect();
+    let mut current = remote;
+    let mut groups: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut insert = |current: &mut Vec<String>, pos: usize, uri: &String| {
+        current.insert(pos, uri.clone());
+        match groups.last_mut() {
+            Some((start, group)) if *start + group.len() == pos => group.push(uri.clone()),
+            _ => groups.push((pos, vec![uri.clone()])),
+        }
+    };
+    let mut prev: Option<&String> = None;
+    for uri in desired {
+        if wanted.contains(uri) && !current.contains(uri) {
+            let pos = prev
+                .and_then(|p| current.iter().position(|c| c == p))
+                .map_or(0, |i| i + 1);
+            insert(&mut current, pos, uri);
+            prev = Some(uri);
+        } else if current.contains(uri) {
+            prev = Some(uri);
+        }
+    }
+    for uri in adds {
+        if !current.contains(uri) {
+            let pos = current.len();
+            insert(&mut current, pos, uri);
+        }
+    }
+    (groups, current)
+}
+
+/// Load the stored remote snapshot of a playlist (see `remote_snapshot`).
+/// Read errors are treated as "no snapshot", which only costs a relist.
+async fn load_remote_snapshot(
+    pool: &db::DbPool,
+    provider_name: &str,
+    playlist_name: &str,
+) -> Option<db::RemoteSnapshot> {
+    let pool = pool.clone();
+    let prov = provider_name.to_string();
+    let pl = playlist_name.to_string();
+    tokio::task::spawn_blocking(move || -> Result<Option<db::RemoteSnapshot>> {
+        let conn = pool.get()?;
+        db::get_remote_snapshot(&conn, &prov, &pl)
+    })
+    .await
+    .ok()
+    .and_then(|r| r.ok())
+    .flatten()
+}
+
+/// Store (`Some`) or forget (`None`) a playlist's remote snapshot.  Errors are
+/// ignored: a missing or stale-marked snapshot only costs a relist.
+async fn store_remote_snapshot(
+    pool: &db::DbPool,
+    provider_name: &str,
+    playlist_name: &str,
+    snapshot: Option<(&str, Vec<String>, i64)>,
+) {
+    let pool = pool.clone();
+    let prov = provider_name.to_string();
+    let pl = playlist_name.to_string();
+    let snapshot = snapshot.map(|(rid, uris, listed_at)| (rid.to_string(), uris, listed_at));
+    let _ = tokio::task::spawn_blocking(move || -> Result<()> {
+        let conn = pool.get()?;
+        match snapshot {
+            Some((rid, uris, listed_at)) => {
+                db::upsert_remote_snapshot(&conn, &prov, &pl, &rid, &uris, listed_at)
+            }
+            None => db::delete_remote_snapshot(&conn, &prov, &pl),
+        }
+    })
+    .await;
+}
+
+/// Log the mutations a dry run would apply to one remote playlist.  When the
+/// current remote contents are known, adds that are already present and
+/// removes that are already absent are reported as no-ops rather than planned.
+/// Returns the number of planned adds and removes.
+fn log_dry_run_plan(
+    worker_id: &str,
+    pl_tag: &str,
+    remote_id: &str,
+    remote_display_name: &str,
+    add_uris: &[String],
+    remove_uris: &[String],
+    remote_current: Option<&[String]>,
+) -> (usize, usize) {
+    let target = if remote_id.is_empty() {
+        "<new>"
+    } else {
+        remote_id
+    };
+    let mut planned_adds = 0usize;
+    let mut planned_removes = 0usize;
+    for uri in add_uris {
+        if remote_current.is_some_and(|r| r.contains(uri)) {
+            log::debug!(
+                "{} {} {} dry_run add_already_present uri={}",
+                log_run_tag(worker_id),
+                pl_tag,
+                log_phase_tag("DRY_RUN"),
+                uri
+            );
+            continue;
+        }
+        planned_adds += 1;
+        log::info!(
+            "{} {} {} dry_run would_add playlist=\"{}\" id={} uri={}",
+            log_run_tag(worker_id),
+            pl_tag,
+            log_phase_tag("DRY_RUN"),
+            remote_display_name,
+            target,
+            uri
+        );
+    }
+    for uri in remove_uris {
+        if remote_current.is_some_and(|r| !r.contains(uri)) {
+            log::debug!(
+                "{} {} {} dry_run remove_already_absent uri={}",
+                log_run_tag(worker_id),
+                pl_tag,
+                log_phase_tag("DRY_RUN"),
+                uri
+            );
+            continue;
+        }
+        planned_removes += 1;
+        log::info!(
+            "{} {} {} dry_run would_remove playlist=\"{}\" id={} uri={}",
+            log_run_tag(worker_id),
+            pl_tag,
+            log_phase_tag("DRY_RUN"),
+            remote_display_name,
+            target,
+            uri
+        );
+    }
+    log::info!(
+        "{} {} {} dry_run plan adds={} removes={} remote_tracks={}",
+        log_run_tag(worker_id),
+        pl_tag,
+        log_phase_tag("DRY_RUN"),
+        planned_adds,
+        planned_removes,
+        remote_current
+            .map(|r| r.len().to_string())
+            .unwrap_or_else(|| "unknown".into())
+    );
+    (planned_adds, planned_removes)
 }
 
 /// Worker orchestration: read unsynced events, group by playlist, collapse, apply rename then track adds/removes.
@@ -513,6 +1360,7 @@ async fn apply_in_batches(
     remote_display_name: &str,
     uris: Vec<String>,
     is_add: bool,
+    insert_at: Option<usize>,
     cfg: &Config,
     worker_id: &str,
     db_pool: &db::DbPool,
@@ -528,14 +1376,20 @@ async fn apply_in_batches(
     // cap here prevents 400 errors such as
     // "size must be between 1 and 20" which were flooding the
     // logs.
-    let batch_size = provider.max_batch_size(cfg);
+    // A zero batch size (e.g. an unset config value) would make `chunks` panic.
+    let batch_size = provider.max_batch_size(cfg).max(1);
+    let lock_key = provider_lock_key(playlist_name, &prov_name);
+    // Chunks of a positional add go one after another from `insert_at`.
+    let mut position = insert_at;
     for chunk in uris.chunks(batch_size) {
+        // Large playlists take many batches (and backoff sleeps); keep the lease alive.
+        renew_lock_async(cfg, db_pool, &lock_key, worker_id).await;
         let mut attempt = 0u32;
         let mut recreated = false;
         loop {
             attempt += 1;
             let res = if is_add {
-                provider.add_tracks(playlist_id, chunk).await
+                provider.add_tracks_at(playlist_id, chunk, position).await
             } else {
                 provider.remove_tracks(playlist_id, chunk).await
             };
@@ -550,40 +1404,12 @@ async fn apply_in_batches(
                         chunk.len(),
                         playlist_id
                     );
+                    position = position.map(|p| p + chunk.len());
                     break;
                 }
                 Err(e) => {
-                    let s = format!("{}", e);
-                    // Parse retry_after if provider included it in the error string like `retry_after=Some(5)`
-                    let retry_after_secs = s
-                        .split("retry_after=")
-                        .nth(1)
-                        .and_then(|rest| {
-                            // rest might be like "Some(5)" or "None" or "5"
-                            let token = rest.trim();
-                            if token.starts_with("Some(") {
-                                token.trim_start_matches("Some(").split(')').next()
-                            } else if token.starts_with("None") {
-                                None
-                            } else {
-                                // take digits prefix
-                                Some(
-                                    token
-                                        .split(|c: char| !c.is_digit(10))
-                                        .next()
-                                        .unwrap_or("")
-                                        .trim(),
-                                )
-                            }
-                        })
-                        .and_then(|s| s.parse::<u64>().ok());
-
-                    if s.contains("rate_limited") || retry_after_secs.is_some() {
-                        let wait = retry_after_secs.unwrap_or_else(|| {
-                            // exponential backoff cap 60s
-                            let exp = 2u64.saturating_pow(std::cmp::min(attempt, 6));
-                            std::cmp::min(exp, 60)
-                        });
+                    if let ProviderError::RateLimited { retry_after } = &e {
+                        let wait = retry_after.unwrap_or_else(|| cfg.backoff_secs(attempt));
                         let phase = if is_add { "BATCH_ADD" } else { "BATCH_REM" };
                         log::warn!(
                             "{} {} {} rate_limited wait_s={} error={}",
@@ -614,17 +1440,14 @@ async fn apply_in_batches(
                                 attempt,
                                 e
                             );
-                            return Err(e);
+                            return Err(e.into());
                         }
                         continue;
                     } else {
                         // Special handling: if the provider reports that the
                         // playlist id no longer exists, recreate it and retry
                         // this batch once with the new id.
-                        if !recreated
-                            && s.contains("tidal add tracks failed: 404 Not Found")
-                            && s.contains("Playlists with id")
-                        {
+                        if !recreated && matches!(e, ProviderError::PlaylistNotFound { .. }) {
                             let phase = if is_add { "BATCH_ADD" } else { "BATCH_REM" };
                             log::warn!(
                                 "{} {} {} playlist_missing_for_batch id={}",
@@ -643,7 +1466,13 @@ async fn apply_in_batches(
 
                             // Recreate the playlist via ensure_playlist using the
                             // current display name, then update playlist_map.
-                            match provider.ensure_playlist(remote_display_name, "").await {
+                            match provider
+                                .ensure_playlist(
+                                    remote_display_name,
+                                    &playlist_description(cfg, playlist_name),
+                                )
+                                .await
+                            {
                                 Ok(new_id) => {
                                     let pool = db_pool.clone();
                                     let pl = playlist_name.to_string();
@@ -681,7 +1510,9 @@ async fn apply_in_batches(
                                     // the fresh playlist id. Guard with `recreated` so
                                     // that if ensure_playlist returns the same (still
                                     // broken) id we don't loop forever.
+                                    // The new playlist is empty: append from here on.
                                     recreated = true;
+                                    position = None;
                                     attempt = 0;
                                     continue;
                                 }
@@ -694,7 +1525,7 @@ async fn apply_in_batches(
                                         log_phase_tag(phase),
                                         err
                                     );
-                                    return Err(err);
+                                    return Err(err.into());
                                 }
                             }
                         }
@@ -708,9 +1539,9 @@ async fn apply_in_batches(
                                 attempt,
                                 e
                             );
-                            return Err(e);
+                            return Err(e.into());
                         } else {
-                            let exp = std::cmp::min(1u64 << attempt, 60);
+                            let exp = cfg.backoff_secs(attempt);
                             let phase = if is_add { "BATCH_ADD" } else { "BATCH_REM" };
                             log::warn!(
                                 "{} {} {} batch_retry attempt={} error={} backoff_s={}",
@@ -739,67 +1570,148 @@ async fn apply_in_batches(
     Ok(())
 }
 
+/// What a worker run did (see [`run_worker_once`]).  Track, rename and
+/// delete counts add up over providers; in a dry run they are what the run
+/// would have done.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct WorkerRunSummary {
+    /// Playlists whose queued events were processed.
+    pub playlists_processed: usize,
+    /// Tracks added to remote playlists.
+    pub tracks_added: usize,
+    /// Tracks removed from remote playlists.
+    pub tracks_removed: usize,
+    /// Remote playlists renamed.
+    pub renames: usize,
+    /// Remote playlists deleted (or unfollowed).
+    pub deletes: usize,
+    /// Local tracks no remote track was found for.
+    pub unresolved: usize,
+    /// Failed playlists per provider; providers without failures are left out.
+    pub errors: std::collections::BTreeMap<String, usize>,
+}
+
+/// Process all pending events once and return what was done.
+///
+/// With `dry_run` set, every add/remove/rename/delete is still resolved
+/// (including listing the remote playlist contents) and logged, but no
+/// playlist is created or modified on any provider and no event is marked
+/// synced.
 pub async fn run_worker_once(
     cfg: &Config,
     provider_filter: Option<&str>,
     trust_cache: bool,
-) -> Result<()> {
-    let worker_id = Uuid::new_v4().to_string();
-
-    // Create a connection pool and run migrations once.
-    let db_pool = db::create_pool(&cfg.db_path)?;
-
-    // Track currently-held playlist locks so the Ctrl-C handler can release them.
-    let active_locks: Arc<std::sync::Mutex<Vec<String>>> =
-        Arc::new(std::sync::Mutex::new(Vec::new()));
+    dry_run: bool,
+) -> Result<WorkerRunSummary> {
+    let providers = build_providers(cfg).await?;
+    let summary =
+        run_worker_filtered(cfg, provider_filter, None, providers, trust_cache, dry_run).await?;
+    prune_after_run(cfg, dry_run).await?;
+    Ok(summary)
+}
 
-    // Spawn a Ctrl-C handler that releases all active locks before exiting.
-    {
-        let active_locks = active_locks.clone();
-        let pool = db_pool.clone();
-        let wid = worker_id.clone();
-        tokio::spawn(async move {
-            if tokio::signal::ctrl_c().await.is_ok() {
-                log::info!("Ctrl-C received; releasing active playlist locks before exit...");
-                let locks = active_locks.lock().unwrap().clone();
-                for playlist_name in &locks {
-                    release_lock_async(&pool, playlist_name, &wid).await;
-                    log::info!("Released lock for playlist: {}", playlist_name);
-                }
-                std::process::exit(130);
-            }
-        });
-    }
+/// [`run_worker_once`] with a fixed provider list instead of the providers
+/// built from stored credentials, so tests can drive the whole
+/// collapse -> resolve -> batch pipeline against e.g. `api::mock::MockProvider`.
+pub async fn run_worker_once_with(
+    cfg: &Config,
+    providers: Vec<(String, Arc<dyn Provider>)>,
+    trust_cache: bool,
+    dry_run: bool,
+) -> Result<WorkerRunSummary> {
+    let summary = run_worker_filtered(cfg, None, None, providers, trust_cache, dry_run).await?;
+    prune_after_run(cfg, dry_run).await?;
+    Ok(summary)
+}
 
-    // Fetch unsynced events (blocking)
-    let events: Vec<Event> = tokio::task::spawn_blocking({
-        let pool = db_pool.clone();
-        move || -> Result<Vec<Event>, anyhow::Error> {
-            let conn = pool.get().context("pool: fetch unsynced events")?;
-            db::fetch_unsynced_events(&conn).map_err(|e| e.into())
+/// Delete synced events past their retention after a (non dry) run.
+async fn prune_after_run(cfg: &Config, dry_run: bool) -> Result<()> {
+    if !dry_run {
+        let db_path = cfg.db_path.clone();
+        let retention = cfg.synced_event_retention_secs;
+        let pruned = tokio::task::spawn_blocking(move || -> Result<usize> {
+            let conn = db::open_or_create(&db_path)?;
+            db::prune_synced_events(&conn, retention)
+        })
+        .await??;
+        if pruned > 0 {
+            log::info!(
+                "Pruned {} synced event(s) older than {}s",
+                pruned,
+                retention
+            );
         }
-    })
-    .await??;
+    }
+    Ok(())
+}
 
-    if events.is_empty() {
-        log::info!("{} No pending events", log_run_tag(&worker_id));
-        return Ok(());
+/// Clear the remote state of `provider` (every provider when None, see
+/// [`db::reset_remote_state`]) and enqueue a `Create` event for every mapped
+/// playlist, so the next worker run relists and re-resolves each of them.
+/// Events are not per provider: the other providers reconcile the same
+/// playlists against their intact caches.  Returns what was cleared and the
+/// number of playlists queued.
+pub fn resync_all(cfg: &Config, provider: Option<&str>) -> Result<(db::RemoteStateReset, usize)> {
+    let mut conn = db::open_or_create(&cfg.db_path)?;
+    let reset = db::reset_remote_state(&mut conn, provider)?;
+    let mut names: Vec<String> = db::list_playlist_maps(&conn, provider)?
+        .into_iter()
+        .map(|(_, name, _, _)| name)
+        .collect();
+    names.dedup();
+    for name in &names {
+        db::enqueue_event(&conn, name, &EventAction::Create, None, None)?;
     }
+    log::info!(
+        "resync_all: cleared {} snapshot(s) and {} track match(es); queued {} playlist(s)",
+        reset.snapshots,
+        reset.track_matches,
+        names.len()
+    );
+    Ok((reset, names.len()))
+}
 
-    // Backpressure
-    if let Some(thresh) = cfg.queue_length_stop_cloud_sync_threshold {
-        if events.len() as u64 > thresh {
-            log::warn!(
-                "{} Queue length {} > threshold {}; stopping worker processing",
-                log_run_tag(&worker_id),
-                events.len(),
-                thresh
-            );
-            return Ok(());
-        }
+/// Enqueue a `Create` event for the logical playlist key `playlist_name` and
+/// process only that playlist's queued events, leaving the rest of the queue
+/// untouched.  The queue-length threshold is not applied; the per-playlist
+/// processing lock is taken exactly as in a normal worker run.
+pub async fn sync_one_playlist(
+    cfg: &Config,
+    playlist_name: &str,
+    provider_filter: Option<&str>,
+    trust_cache: bool,
+) -> Result<()> {
+    {
+        let db_path = cfg.db_path.clone();
+        let pname = playlist_name.to_string();
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let conn = db::open_or_create(&db_path)?;
+            db::enqueue_event(&conn, &pname, &EventAction::Create, None, None)?;
+            Ok(())
+        })
+        .await??;
     }
+    log::info!(
+        "sync_one_playlist: enqueued Create event for playlist \"{}\"",
+        playlist_name
+    );
+    run_worker_filtered(
+        cfg,
+        provider_filter,
+        Some(playlist_name),
+        build_providers(cfg).await?,
+        trust_cache,
+        false,
+    )
+    .await?;
+    Ok(())
+}
 
-    // Collect all authenticated providers
+/// Build every provider with stored credentials that `enabled_providers`
+/// allows, keyed by provider name.  This is the provider list
+/// [`run_worker_once`] hands to [`run_worker_once_with`].
+pub async fn build_providers(cfg: &Config) -> Result<Vec<(String, Arc<dyn Provider>)>> {
+    let db_pool = &db::create_pool(&cfg.db_path)?;
     let mut providers: Vec<(String, Arc<dyn Provider>)> = Vec::new();
     // Spotify
     let has_spotify = tokio::task::spawn_blocking({
@@ -810,8 +1722,7 @@ pub async fn run_worker_once(
         }
     })
     .await??;
-    if has_spotify {
-        log::info!("{} Using Spotify provider", log_run_tag(&worker_id));
+    if use_provider(cfg, "spotify", has_spotify) {
         providers.push((
             "spotify".to_string(),
             Arc::new(SpotifyProvider::new(
@@ -831,8 +1742,7 @@ pub async fn run_worker_once(
         }
     })
     .await??;
-    if has_tidal {
-        log::info!("{} Using Tidal provider", log_run_tag(&worker_id));
+    if use_provider(cfg, "tidal", has_tidal) {
         providers.push((
             "tidal".to_string(),
             Arc::new(TidalProvider::new(
@@ -848,88 +1758,312 @@ pub async fn run_worker_once(
             )),
         ));
     }
-    // If no real providers, do not consume the queue
-    if providers.is_empty() {
-        log::warn!(
-            "{} No valid provider credentials configured. Queue will not be consumed.",
-            log_run_tag(&worker_id)
-        );
-        return Ok(());
+    // YouTube Music
+    let has_ytmusic = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get().context("pool: load ytmusic credentials")?;
+            Ok(db::load_credential_with_client(&conn, "ytmusic")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "ytmusic", has_ytmusic) {
+        providers.push((
+            "ytmusic".to_string(),
+            Arc::new(YtMusicProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                cfg.clone(),
+            )),
+        ));
     }
-
-    // Apply optional provider filter (e.g. when reconciling a single playlist)
-    if let Some(filter) = provider_filter {
-        let filter_lc = filter.to_ascii_lowercase();
-        providers.retain(|(name, _)| name.eq_ignore_ascii_case(&filter_lc));
-        if providers.is_empty() {
-            anyhow::bail!(
-                "provider '{}' is not configured or not authenticated",
-                filter
-            );
+    // Subsonic / Navidrome
+    let has_subsonic = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get().context("pool: load subsonic credentials")?;
+            Ok(db::load_credential_with_client(&conn, "subsonic")?.is_some())
         }
-        log::info!(
-            "{} provider_filter={} – processing only this provider",
-            log_run_tag(&worker_id),
-            filter
-        );
+    })
+    .await??;
+    if use_provider(cfg, "subsonic", has_subsonic) {
+        providers.push((
+            "subsonic".to_string(),
+            Arc::new(SubsonicProvider::new(cfg.db_path.clone(), cfg.clone())),
+        ));
     }
-
-    let remote_whitelist = compile_whitelist(Some(cfg.effective_remote_whitelist()));
-
-    // Group events per playlist_name
-    use std::collections::HashMap;
-    let mut groups: HashMap<String, Vec<Event>> = HashMap::new();
-    for ev in events.into_iter() {
-        groups.entry(ev.playlist_name.clone()).or_default().push(ev);
+    // Deezer
+    let has_deezer = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get().context("pool: load deezer credentials")?;
+            Ok(db::load_credential_with_client(&conn, "deezer")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "deezer", has_deezer) {
+        providers.push((
+            "deezer".to_string(),
+            Arc::new(DeezerProvider::new(cfg.db_path.clone(), cfg.clone())),
+        ));
     }
-
-    // For each playlist, process all providers
-    // This ensures that for a given playlist, all configured
-    // providers are updated before moving on to the next one.
-    let total_playlists = groups.len();
-    for (playlist_idx, (playlist_name, evs)) in groups.iter().enumerate() {
-        log::info!(
-            "{} {} queue_progress playlist={}/{} name=\"{}\"",
-            log_run_tag(&worker_id),
-            log_phase_tag("QUEUE"),
-            playlist_idx + 1,
-            total_playlists,
-            playlist_name,
-        );
-        let playlist_folder = cfg.root_folder.join(playlist_name);
-        if !matches_whitelist(&playlist_folder, &remote_whitelist) {
-            let ids_to_mark: Vec<i64> = evs.iter().map(|ev| ev.id).collect();
-            mark_events_synced_async(db_pool.clone(), ids_to_mark).await?;
-            continue;
+    // Apple Music
+    let has_apple_music = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get().context("pool: load applemusic credentials")?;
+            Ok(db::load_credential_with_client(&conn, "applemusic")?.is_some())
         }
-        // Process providers sequentially (safety). Could be parallelized with locks.
-        // Collapse events and compute original IDs once (invariant across providers).
-        let collapsed = collapse_events(evs);
-        let original_ids: Vec<i64> = evs.iter().map(|ev| ev.id).collect();
-
-        let mut rename_opt: Option<(String, String)> = None;
-        let mut has_delete: bool = false;
-        let mut track_ops: Vec<(EventAction, Option<String>)> = Vec::new();
-        for op in &collapsed {
-            match &op.action {
-                EventAction::Rename { from, to } => rename_opt = Some((from.clone(), to.clone())),
-                EventAction::Delete => has_delete = true,
-                EventAction::Add => track_ops.push((EventAction::Add, op.track_path.clone())),
-                EventAction::Remove => track_ops.push((EventAction::Remove, op.track_path.clone())),
-                _ => {}
-            }
+    })
+    .await??;
+    if use_provider(cfg, "applemusic", has_apple_music) {
+        providers.push((
+            "applemusic".to_string(),
+            Arc::new(AppleMusicProvider::new(cfg.db_path.clone(), cfg.clone())),
+        ));
+    }
+    // Plex
+    let has_plex = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get().context("pool: load plex credentials")?;
+            Ok(db::load_credential_with_client(&conn, "plex")?.is_some())
         }
-
+    })
+    .await??;
+    if use_provider(cfg, "plex", has_plex) {
+        let plex = PlexProvider::new(cfg.db_path.clone(), cfg.clone());
+        // Index the library's file paths up front so tracks on paths the
+        // server shares skip the search; without it every track is searched.
+        match plex.load_library().await {
+            Ok(n) => log::debug!("plex: indexed {} library files", n),
+            Err(e) => log::warn!("plex: could not index the music library: {:#}", e),
+        }
+        providers.push(("plex".to_string(), Arc::new(plex)));
+    }
+    // Playlist export to a local folder; needs no credentials.
+    let has_export_dir = !cfg.filesystem_export_dir.as_os_str().is_empty();
+    if use_provider(cfg, "filesystem", has_export_dir) {
+        providers.push((
+            "filesystem".to_string(),
+            Arc::new(FileSystemProvider::new(cfg.clone())),
+        ));
+    }
+    Ok(providers)
+}
+
+/// Body of [`run_worker_once`].  When `playlist_filter` is set only events
+/// for that playlist key are processed and the backpressure threshold is
+/// skipped.  The run's metrics are stored whether or not it succeeds.
+async fn run_worker_filtered(
+    cfg: &Config,
+    provider_filter: Option<&str>,
+    playlist_filter: Option<&str>,
+    providers: Vec<(String, Arc<dyn Provider>)>,
+    trust_cache: bool,
+    dry_run: bool,
+) -> Result<WorkerRunSummary> {
+    let result = process_queue(
+        cfg,
+        provider_filter,
+        playlist_filter,
+        providers,
+        trust_cache,
+        dry_run,
+    )
+    .await;
+    let db_path = cfg.db_path.clone();
+    let flushed = tokio::task::spawn_blocking(move || -> Result<()> {
+        crate::metrics::flush(&db::open_or_create(&db_path)?)
+    })
+    .await?;
+    if let Err(e) = flushed {
+        log::warn!("Failed to store metrics: {:#}", e);
+    }
+    result
+}
+
+async fn process_queue(
+    cfg: &Config,
+    provider_filter: Option<&str>,
+    playlist_filter: Option<&str>,
+    mut providers: Vec<(String, Arc<dyn Provider>)>,
+    trust_cache: bool,
+    dry_run: bool,
+) -> Result<WorkerRunSummary> {
+    let worker_id = Uuid::new_v4().to_string();
+
+    // Create a connection pool and run migrations once.
+    let db_pool = db::create_pool(&cfg.db_path)?;
+
+    // Track currently-held playlist locks so the Ctrl-C handler can release them.
+    let active_locks: Arc<std::sync::Mutex<Vec<String>>> =
+        Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    // Spawn a Ctrl-C handler that releases all active locks before exiting.
+    {
+        let active_locks = active_locks.clone();
+        let pool = db_pool.clone();
+        let wid = worker_id.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                log::info!("Ctrl-C received; releasing active playlist locks before exit...");
+                let locks = active_locks.lock().unwrap().clone();
+                for playlist_name in &locks {
+                    release_lock_async(&pool, playlist_name, &wid).await;
+                    log::info!("Released lock for playlist: {}", playlist_name);
+                }
+                std::process::exit(130);
+            }
+        });
+    }
+
+    // Fetch unsynced events (blocking)
+    let mut events: Vec<Event> = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<Vec<Event>, anyhow::Error> {
+            let conn = pool.get().context("pool: fetch unsynced events")?;
+            db::fetch_unsynced_events(&conn).map_err(|e| e.into())
+        }
+    })
+    .await??;
+    if let Some(pl) = playlist_filter {
+        events.retain(|ev| ev.playlist_name == pl);
+    }
+
+    if events.is_empty() {
+        log::info!("{} No pending events", log_run_tag(&worker_id));
+        return Ok(WorkerRunSummary::default());
+    }
+
+    // Backpressure (not applied when a single playlist was requested)
+    if let Some(thresh) = cfg
+        .queue_length_stop_cloud_sync_threshold
+        .filter(|_| playlist_filter.is_none())
+    {
+        if events.len() as u64 > thresh {
+            log::warn!(
+                "{} Queue length {} > threshold {}; stopping worker processing",
+                log_run_tag(&worker_id),
+                events.len(),
+                thresh
+            );
+            return Ok(WorkerRunSummary::default());
+        }
+    }
+
+    for (name, _) in &providers {
+        log::info!("{} Using {} provider", log_run_tag(&worker_id), name);
+    }
+    // If no real providers, do not consume the queue
+    if providers.is_empty() {
+        log::warn!(
+            "{} No valid provider credentials configured. Queue will not be consumed.",
+            log_run_tag(&worker_id)
+        );
+        return Ok(WorkerRunSummary::default());
+    }
+
+    // Apply optional provider filter (e.g. when reconciling a single playlist)
+    if let Some(filter) = provider_filter {
+        let filter_lc = filter.to_ascii_lowercase();
+        providers.retain(|(name, _)| name.eq_ignore_ascii_case(&filter_lc));
+        if providers.is_empty() {
+            anyhow::bail!(
+                "provider '{}' is not configured or not authenticated",
+                filter
+            );
+        }
+        log::info!(
+            "{} provider_filter={} – processing only this provider",
+            log_run_tag(&worker_id),
+            filter
+        );
+    }
+
+    let remote_whitelist = compile_whitelist(Some(cfg.effective_remote_whitelist()));
+    let filename_regex = cfg.compiled_filename_parse_regex()?;
+    let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
+
+    // Group events per playlist_name
+    use std::collections::HashMap;
+    let mut groups: HashMap<String, Vec<Event>> = HashMap::new();
+    for ev in events.into_iter() {
+        groups.entry(ev.playlist_name.clone()).or_default().push(ev);
+    }
+
+    // Providers of a playlist run concurrently and all add to this.
+    let run_summary = std::sync::Mutex::new(WorkerRunSummary::default());
+
+    // For each playlist, process all providers
+    // This ensures that for a given playlist, all configured
+    // providers are updated before moving on to the next one.
+    let total_playlists = groups.len();
+    for (playlist_idx, (playlist_name, evs)) in groups.iter().enumerate() {
+        log::info!(
+            "{} {} queue_progress playlist={}/{} name=\"{}\"",
+            log_run_tag(&worker_id),
+            log_phase_tag("QUEUE"),
+            playlist_idx + 1,
+            total_playlists,
+            playlist_name,
+        );
+        let playlist_folder = cfg.playlist_folder_for_key(playlist_name);
+        let local_only = cfg.is_local_only(&playlist_folder);
+        if local_only {
+            log::info!(
+                "{} {} skipping local-only playlist name=\"{}\" events={}",
+                log_run_tag(&worker_id),
+                log_phase_tag("QUEUE"),
+                playlist_name,
+                evs.len()
+            );
+        }
+        if local_only || !matches_whitelist(&playlist_folder, &remote_whitelist) {
+            if !dry_run {
+                let ids_to_mark: Vec<i64> = evs.iter().map(|ev| ev.id).collect();
+                mark_events_synced_async(db_pool.clone(), ids_to_mark).await?;
+            }
+            continue;
+        }
+        run_summary.lock().unwrap().playlists_processed += 1;
+        // Collapse events and compute original IDs once (invariant across providers).
+        let collapsed = collapse_events(evs);
+        let original_ids: Vec<i64> = evs.iter().map(|ev| ev.id).collect();
+
+        let mut rename_opt: Option<(String, String)> = None;
+        let mut has_delete: bool = false;
+        // A Create after the Delete re-opens the playlist: delete the remote
+        // one, then create a fresh playlist and apply the track ops.
+        let mut recreated: bool = false;
+        let mut track_ops: Vec<(EventAction, Option<String>)> = Vec::new();
+        for op in &collapsed {
+            match &op.action {
+                EventAction::Rename { from, to } => rename_opt = Some((from.clone(), to.clone())),
+                EventAction::Delete => {
+                    has_delete = true;
+                    recreated = false;
+                }
+                EventAction::Create if has_delete => recreated = true,
+                EventAction::Add => track_ops.push((EventAction::Add, op.track_path.clone())),
+                EventAction::Remove => track_ops.push((EventAction::Remove, op.track_path.clone())),
+                _ => {}
+            }
+        }
+
         // When a folder rename is present, rewrite stale track paths so that
         // file-based resolution (ISRC extraction, etc.) can find the files at
         // their new location on disk.
         if let Some((ref from_name, ref to_name)) = rename_opt {
             let old_prefix = cfg
-                .root_folder
-                .join(from_name)
+                .playlist_folder_for_key(from_name)
+                .to_string_lossy()
+                .to_string();
+            let new_prefix = cfg
+                .playlist_folder_for_key(to_name)
                 .to_string_lossy()
                 .to_string();
-            let new_prefix = cfg.root_folder.join(to_name).to_string_lossy().to_string();
             for (_action, track_path_opt) in track_ops.iter_mut() {
                 if let Some(tp) = track_path_opt {
                     if tp.starts_with(&old_prefix) {
@@ -941,567 +2075,654 @@ pub async fn run_worker_once(
             }
         }
 
-        let mut all_providers_ok = true;
-        for (provider_name, provider) in &providers {
-            let pl_tag = log_playlist_tag(playlist_name, provider_name);
-            log::info!(
-                "{} {} {} starting playlist processing (events={})",
-                log_run_tag(&worker_id),
-                pl_tag,
-                log_phase_tag("START"),
-                evs.len()
-            );
+        // Process the providers of this playlist concurrently, at most
+        // `max_concurrent_providers` at a time.  Each provider takes its own
+        // lock and mapping; an error only fails that provider.
+        let provider_slots = tokio::sync::Semaphore::new(cfg.max_concurrent_providers.max(1));
+        let worker_id = &worker_id;
+        let db_pool = &db_pool;
+        let active_locks = &active_locks;
+        let filename_regex = &filename_regex;
+        let copy_suffixes = &copy_suffixes;
+        let original_ids = &original_ids;
+        let rename_opt = &rename_opt;
+        let track_ops = &track_ops;
+        let run_summary = &run_summary;
+        let outcomes = futures::future::join_all(providers.iter().map(
+            |(provider_name, provider)| {
+                let provider_slots = &provider_slots;
+                async move {
+                    let _slot = provider_slots.acquire().await;
+                    let pl_tag = log_playlist_tag(playlist_name, provider_name);
+                    let lock_key = provider_lock_key(playlist_name, provider_name);
+                    let result: Result<(bool, Option<String>)> = async {
+                        let mut provider_ok = true;
+                        let mut failure: Option<String> = None;
+                        log::info!(
+                            "{} {} {} starting playlist processing (events={})",
+                            log_run_tag(worker_id),
+                            pl_tag,
+                            log_phase_tag("START"),
+                            evs.len()
+                        );
 
-            // Try acquire lock (TTL = 10 minutes default)
-            let lock_acquired = tokio::task::spawn_blocking({
-                let pool = db_pool.clone();
-                let pl = playlist_name.clone();
-                let wid = worker_id.clone();
-                move || -> Result<bool, anyhow::Error> {
-                    let mut conn = pool.get()?;
-                    Ok(db::try_acquire_playlist_lock(&mut conn, &pl, &wid, 600)?)
-                }
-            })
-            .await??;
+                        // Try acquire lock (TTL = lock_ttl_secs, renewed while processing)
+                        let lock_acquired = tokio::task::spawn_blocking({
+                            let pool = db_pool.clone();
+                            let pl = lock_key.clone();
+                            let wid = worker_id.clone();
+                            let ttl = cfg.lock_ttl_secs;
+                            move || -> Result<bool, anyhow::Error> {
+                                let mut conn = pool.get()?;
+                                Ok(db::try_acquire_playlist_lock(&mut conn, &pl, &wid, ttl)?)
+                            }
+                        })
+                        .await??;
 
-            if !lock_acquired {
-                log::info!(
-                    "{} {} {} skipped: lock not acquired",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("LOCK")
-                );
-                all_providers_ok = false;
-                continue;
-            }
+                        if !lock_acquired {
+                            log::info!(
+                                "{} {} {} skipped: lock not acquired",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("LOCK")
+                            );
+                            provider_ok = false;
+                            return Ok((provider_ok, failure));
+                        }
 
-            // Register this lock so the Ctrl-C handler can release it if needed.
-            active_locks.lock().unwrap().push(playlist_name.clone());
+                        // Register this lock so the Ctrl-C handler can release it if needed.
+                        active_locks.lock().unwrap().push(lock_key.clone());
 
-            let adds = track_ops
-                .iter()
-                .filter(|(a, _)| matches!(a, EventAction::Add))
-                .count();
-            let removes = track_ops
-                .iter()
-                .filter(|(a, _)| matches!(a, EventAction::Remove))
-                .count();
-            let renames = if rename_opt.is_some() { 1 } else { 0 };
-            let deletes = if has_delete { 1 } else { 0 };
+                        let adds = track_ops
+                            .iter()
+                            .filter(|(a, _)| matches!(a, EventAction::Add))
+                            .count();
+                        let removes = track_ops
+                            .iter()
+                            .filter(|(a, _)| matches!(a, EventAction::Remove))
+                            .count();
+                        let renames = if rename_opt.is_some() { 1 } else { 0 };
+                        let deletes = if has_delete { 1 } else { 0 };
 
-            log::info!(
-            "{} {} {} events_collapsed adds={} removes={} deletes={} renames={} original_ids={}",
-            log_run_tag(&worker_id),
-            pl_tag,
-            log_phase_tag("COLLAPSE"),
-            adds,
-            removes,
-            deletes,
-            renames,
-            original_ids.len()
-        );
+                        log::info!(
+                        "{} {} {} events_collapsed adds={} removes={} deletes={} renames={} original_ids={}",
+                        log_run_tag(worker_id),
+                        pl_tag,
+                        log_phase_tag("COLLAPSE"),
+                        adds,
+                        removes,
+                        deletes,
+                        renames,
+                        original_ids.len()
+                    );
 
-            // Resolve remote playlist id from playlist_map (or create via provider.ensure_playlist if needed).
-            let remote_id_opt = tokio::task::spawn_blocking({
-                let pool = db_pool.clone();
-                let pl = playlist_name.clone();
-                let prov = provider_name.clone();
-                move || -> Result<Option<String>, anyhow::Error> {
-                    let conn = pool.get()?;
-                    db::get_remote_playlist_id(&conn, &prov, &pl).map_err(|e| e.into())
-                }
-            })
-            .await??;
+                        // Resolve remote playlist id from playlist_map (or create via provider.ensure_playlist if needed).
+                        let mut remote_id_opt = tokio::task::spawn_blocking({
+                            let pool = db_pool.clone();
+                            let pl = playlist_name.clone();
+                            let prov = provider_name.clone();
+                            move || -> Result<Option<String>, anyhow::Error> {
+                                let conn = pool.get()?;
+                                db::get_remote_playlist_id(&conn, &prov, &pl).map_err(|e| e.into())
+                            }
+                        })
+                        .await??;
 
-            // If this playlist is being deleted, attempt to delete remotely and clean up local state,
-            // then skip any add/remove operations.
-            if has_delete {
-                if let Some(remote_id) = remote_id_opt.clone() {
-                    let mut attempt = 0u32;
-                    loop {
-                        attempt += 1;
-                        let res = provider.delete_playlist(&remote_id).await;
-                        match res {
-                            Ok(_) => {
+                        // If this playlist is being deleted, attempt to delete remotely and clean up local state,
+                        // then skip any add/remove operations.
+                        if has_delete && dry_run {
+                            log::info!(
+                                "{} {} {} dry_run would_delete_playlist id={}",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("DRY_RUN"),
+                                remote_id_opt.as_deref().unwrap_or("<unmapped>")
+                            );
+                            if remote_id_opt.is_some() {
+                                run_summary.lock().unwrap().deletes += 1;
+                            }
+                            if !recreated {
+                                active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                                release_lock_async(db_pool, &lock_key, worker_id).await;
+                                return Ok((provider_ok, failure));
+                            }
+                            remote_id_opt = None;
+                        } else if has_delete {
+                            let mut delete_error: Option<String> = None;
+                            if let Some(remote_id) = remote_id_opt.clone() {
+                                let mut attempt = 0u32;
+                                loop {
+                                    attempt += 1;
+                                    let res = provider.delete_playlist(&remote_id).await;
+                                    match res {
+                                        Ok(_) => {
+                                            run_summary.lock().unwrap().deletes += 1;
+                                            // Providers without hard deletes only
+                                            // unfollow; the playlist stays in the
+                                            // user's library.
+                                            log::info!(
+                                                "{} {} {} {} id={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("DELETE"),
+                                                if provider.capabilities().hard_delete {
+                                                    "deleted_remote_playlist"
+                                                } else {
+                                                    "unfollowed_remote_playlist"
+                                                },
+                                                remote_id
+                                            );
+                                            break;
+                                        }
+                                        Err(e) => {
+                                            if attempt >= cfg.max_retries_on_error {
+                                                log::error!(
+                                                    "{} {} {} delete_failed attempts={} error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("DELETE"),
+                                                    attempt,
+                                                    e
+                                                );
+                                                delete_error = Some(format!("delete_failed: {}", e));
+                                                break;
+                                            } else {
+                                                log::warn!(
+                                                    "{} {} {} delete_retry attempt={} error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("DELETE"),
+                                                    attempt,
+                                                    e
+                                                );
+                                                let sleep_secs = cfg.backoff_secs(attempt);
+                                                log::info!(
+                                                    "{} {} {} Sleeping for {} seconds",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("DELETE"),
+                                                    sleep_secs
+                                                );
+                                                tokio::time::sleep(std::time::Duration::from_secs(sleep_secs))
+                                                    .await;
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                }
+                            } else {
                                 log::info!(
-                                    "{} {} {} deleted_remote_playlist id={}",
-                                    log_run_tag(&worker_id),
+                                    "{} {} {} no_remote_id_for_delete",
+                                    log_run_tag(worker_id),
                                     pl_tag,
-                                    log_phase_tag("DELETE"),
-                                    remote_id
+                                    log_phase_tag("DELETE")
                                 );
-                                break;
-                            }
-                            Err(e) => {
-                                if attempt >= cfg.max_retries_on_error {
-                                    log::error!(
-                                        "{} {} {} delete_failed attempts={} error={}",
-                                        log_run_tag(&worker_id),
-                                        pl_tag,
-                                        log_phase_tag("DELETE"),
-                                        attempt,
-                                        e
-                                    );
-                                    break;
-                                } else {
-                                    log::warn!(
-                                        "{} {} {} delete_retry attempt={} error={}",
-                                        log_run_tag(&worker_id),
-                                        pl_tag,
-                                        log_phase_tag("DELETE"),
-                                        attempt,
-                                        e
-                                    );
-                                    let sleep_secs = 1 << attempt;
-                                    log::info!(
-                                        "{} {} {} Sleeping for {} seconds",
-                                        log_run_tag(&worker_id),
-                                        pl_tag,
-                                        log_phase_tag("DELETE"),
-                                        sleep_secs
-                                    );
-                                    tokio::time::sleep(std::time::Duration::from_secs(sleep_secs))
-                                        .await;
-                                    continue;
-                                }
                             }
-                        }
-                    }
-                } else {
-                    log::info!(
-                        "{} {} {} no_remote_id_for_delete",
-                        log_run_tag(&worker_id),
-                        pl_tag,
-                        log_phase_tag("DELETE")
-                    );
-                }
-
-                // Remove local playlist_map entry regardless of whether remote deletion succeeded
-                let pl = playlist_name.clone();
-                let pool = db_pool.clone();
-                let prov = provider_name.clone();
-                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    let conn = pool.get()?;
-                    let _ = db::delete_playlist_map(&conn, &prov, &pl)?;
-                    Ok(())
-                })
-                .await??;
-
-                // Mark original events as synced (so they don't get retried forever)
-                let ids_clone = original_ids.clone();
-                let pool = db_pool.clone();
-                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    let mut conn = pool.get()?;
-                    if !ids_clone.is_empty() {
-                        db::mark_events_synced(&mut conn, &ids_clone)?;
-                    }
-                    Ok(())
-                })
-                .await??;
-
-                // release lock
-                active_locks.lock().unwrap().retain(|n| n != playlist_name);
-                release_lock_async(&db_pool, playlist_name, &worker_id).await;
 
-                log::info!(
-                    "{} {} {} playlist_deleted_and_events_synced count={}",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("FINALIZE"),
-                    original_ids.len()
-                );
-
-                // Move to next playlist/provider
-                continue;
-            }
-
-            // Precompute desired remote URIs based on the current local playlist
-            // so we can reconcile remote contents later once we have a playlist id.
-            //
-            // IMPORTANT: this operation can be expensive (it may hit the provider for
-            // every track in the local playlist) so avoid doing it unless we know we
-            // are going to perform a modification.  In particular we skip the work if
-            // there are any explicit track add/remove events or if the only thing we
-            // are doing is a rename.  A nightly ``Create`` event still needs the
-            // computation because it's the only way we can detect an out‑of‑sync
-            // playlist.
-            let mut reconcile_desired: Option<Vec<String>> = None;
-            let has_track_ops = !track_ops.is_empty();
-            if !has_delete && !has_track_ops && rename_opt.is_none() {
-                log::info!(
-                    "{} {} {} reconcile_compute_desired_uris",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("RESOLVE")
-                );
-                match desired_remote_uris_for_playlist(
-                    cfg,
-                    playlist_name,
-                    provider.clone(),
-                    &db_pool,
-                    trust_cache,
-                )
-                .await
-                {
-                    Ok((desired, local_track_count)) => {
-                        log::info!(
-                            "{} {} {} reconcile_desired_uris_computed count={} local_track_count={}",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("RESOLVE"),
-                            desired.len(),
-                            local_track_count
-                        );
-                        if !desired.is_empty() || local_track_count == 0 {
-                            // Either we have URIs to sync, or the local playlist is
-                            // genuinely empty (local_track_count == 0) and the remote
-                            // should be cleared to match.
-                            reconcile_desired = Some(desired);
-                        } else {
-                            // local_track_count > 0 but no URIs resolved — all lookups
-                            // failed.  Do not wipe the remote in this case.
-                            log::warn!(
-                                "{} {} {} reconcile_all_lookups_failed local_tracks={} – remote playlist left unchanged",
-                                log_run_tag(&worker_id),
-                                pl_tag,
-                                log_phase_tag("RESOLVE"),
-                                local_track_count
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "{} {} {} reconcile_compute_desired_uris_failed error={}",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("RESOLVE"),
-                            e
-                        );
-                    }
-                }
-            }
+                            // Keep the mapping and leave the events queued when the remote
+                            // delete failed, so the next run retries it (and eventually
+                            // marks the events failed).
+                            if let Some(err) = delete_error {
+                                failure = Some(err);
+                                provider_ok = false;
+                                active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                                release_lock_async(db_pool, &lock_key, worker_id).await;
+                                return Ok((provider_ok, failure));
+                            }
 
-            // Compute the desired remote display name for this playlist on this provider.
-            let remote_display_name = compute_remote_playlist_name(
-                cfg,
-                provider.name(),
-                playlist_name,
-                provider.supports_folder_nesting(),
-            );
+                            // Remove local playlist_map entry once the remote playlist is gone
+                            let pl = playlist_name.clone();
+                            let pool = db_pool.clone();
+                            let prov = provider_name.clone();
+                            tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                                let conn = pool.get()?;
+                                let _ = db::delete_playlist_map(&conn, &prov, &pl)?;
+                                Ok(())
+                            })
+                            .await??;
 
-            let mut remote_id = if let Some(rid) = remote_id_opt {
-                rid
-            } else {
-                // create via provider.ensure_playlist
-                match provider.ensure_playlist(&remote_display_name, "").await {
-                    Ok(rid) => {
-                        // persist
-                        let pl = playlist_name.clone();
-                        let pool = db_pool.clone();
-                        let rid_clone = rid.clone();
-                        let prov = provider_name.clone();
-                        let dn = remote_display_name.clone();
-                        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                            let conn = pool.get()?;
-                            db::upsert_playlist_map(&conn, &prov, &pl, &rid_clone)?;
-                            db::set_remote_display_name(&conn, &prov, &pl, &dn)?;
-                            Ok(())
-                        })
-                        .await??;
+                            if recreated {
                                 log::info!(
-                                    "{} {} {} ensure_playlist_persisted id={}",
-                                    log_run_tag(&worker_id),
+                                    "{} {} {} playlist_deleted_recreating",
+                                    log_run_tag(worker_id),
                                     pl_tag,
-                                    log_phase_tag("REMOTE_ID"),
-                                    rid
+                                    log_phase_tag("DELETE")
                                 );
-                        rid
-                    }
-                    Err(e) => {
-                        log::error!(
-                            "{} {} {} ensure_remote_playlist_failed error={}",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("REMOTE_ID"),
-                            e
-                        );
-                        // release lock and continue
-                        active_locks.lock().unwrap().retain(|n| n != playlist_name);
-                        release_lock_async(&db_pool, playlist_name, &worker_id).await;
-                        all_providers_ok = false;
-                        continue;
-                    }
-                }
-            };
+                                remote_id_opt = None;
+                            } else {
+                                // Mark original events as synced (so they don't get retried forever)
+                                let ids_clone = original_ids.clone();
+                                let pool = db_pool.clone();
+                                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                                    let mut conn = pool.get()?;
+                                    if !ids_clone.is_empty() {
+                                        db::mark_events_synced(&mut conn, &ids_clone)?;
+                                    }
+                                    Ok(())
+                                })
+                                .await??;
 
-            // If we have a stored remote id, verify with the provider that it is
-            // still a valid, accessible playlist. This covers the case where the
-            // user "deletes" (unfollows) a Spotify playlist in the client while
-            // our local mapping still points at the old id.
-            // Skipped when trust_cache is set: the stored remote_id is trusted as-is.
-            // verify playlist validity, retrying on transient errors (429/rate limit)
-            if trust_cache {
-                log::debug!(
-                    "{} {} {} trust_cache: skipping playlist_is_valid",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("REMOTE_ID")
-                );
-            } else {
-                {
-                    let mut attempt = 0u32;
-                    loop {
-                        attempt += 1;
-                        match provider.playlist_is_valid(&remote_id).await {
-                            Ok(None) => {
-                                log::warn!(
-                                    "{} {} {} remote_playlist_inaccessible id={}",
-                                    log_run_tag(&worker_id),
+                                // release lock
+                                active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                                release_lock_async(db_pool, &lock_key, worker_id).await;
+
+                                log::info!(
+                                    "{} {} {} playlist_deleted_and_events_synced count={}",
+                                    log_run_tag(worker_id),
                                     pl_tag,
-                                    log_phase_tag("REMOTE_ID"),
-                                    remote_id
+                                    log_phase_tag("FINALIZE"),
+                                    original_ids.len()
                                 );
-                                match provider.ensure_playlist(&remote_display_name, "").await {
-                                    Ok(new_id) => {
-                                        let pool = db_pool.clone();
-                                        let pl = playlist_name.clone();
-                                        let prov = provider_name.clone();
-                                        let new_id_clone = new_id.clone();
-                                        let dn = remote_display_name.clone();
-                                        tokio::task::spawn_blocking(
-                                            move || -> Result<(), anyhow::Error> {
-                                                let conn = pool.get()?;
-                                                db::upsert_playlist_map(
-                                                    &conn,
-                                                    &prov,
-                                                    &pl,
-                                                    &new_id_clone,
-                                                )?;
-                                                db::set_remote_display_name(
-                                                    &conn, &prov, &pl, &dn,
-                                                )?;
-                                                Ok(())
-                                            },
-                                        )
-                                        .await??;
-
-                                        log::info!(
-                                            "{} {} {} remote_playlist_recreated new_id={}",
-                                            log_run_tag(&worker_id),
-                                            pl_tag,
-                                            log_phase_tag("REMOTE_ID"),
-                                            new_id
-                                        );
-                                        remote_id = new_id;
-                                    }
-                                    Err(e) => {
-                                        log::error!(
-                                            "{} {} {} remote_playlist_recreate_failed error={}",
-                                            log_run_tag(&worker_id),
-                                            pl_tag,
-                                            log_phase_tag("REMOTE_ID"),
-                                            e
-                                        );
 
-                                        // Release lock and skip further processing for this playlist.
-                                        active_locks.lock().unwrap().retain(|n| n != playlist_name);
-                                        release_lock_async(&db_pool, playlist_name, &worker_id)
-                                            .await;
-                                        all_providers_ok = false;
-                                        continue;
-                                    }
-                                }
-                                break;
+                                // Move to next playlist/provider
+                                return Ok((provider_ok, failure));
                             }
-                            Ok(Some(current_remote_name)) => {
-                                // Opportunistically cache the remote display name
-                                // that the provider already fetched.
-                                if !current_remote_name.is_empty() {
-                                    let pool = db_pool.clone();
-                                    let pl = playlist_name.clone();
-                                    let prov = provider_name.clone();
-                                    let rn = current_remote_name.clone();
-                                    let _ = tokio::task::spawn_blocking(move || {
-                                        if let Ok(conn) = pool.get() {
-                                            let _ =
-                                                db::set_remote_display_name(&conn, &prov, &pl, &rn);
-                                        }
-                                    })
-                                    .await;
-                                }
-                                // normal case, keep existing id
-                                break;
-                            }
-                            Err(e) => {
-                                let s = format!("{}", e);
-                                if s.contains("rate_limited") || s.contains("429") {
-                                    // back off and retry
-                                    let exp = std::cmp::min(1u64 << attempt, 60);
-                                    log::warn!(
-                                        "{} {} {} rate_limited remote_id_wait_s={} error={}",
-                                        log_run_tag(&worker_id),
-                                        pl_tag,
-                                        log_phase_tag("REMOTE_ID"),
-                                        exp,
-                                        e
-                                    );
+                        }
+
+                        // Precompute desired remote URIs based on the current local playlist
+                        // so we can reconcile remote contents later once we have a playlist id.
+                        //
+                        // IMPORTANT: this operation can be expensive (it may hit the provider for
+                        // every track in the local playlist) so avoid doing it unless we know we
+                        // are going to perform a modification.  In particular we skip the work if
+                        // there are any explicit track add/remove events or if the only thing we
+                        // are doing is a rename.  A nightly ``Create`` event still needs the
+                        // computation because it's the only way we can detect an out‑of‑sync
+                        // playlist.
+                        let mut reconcile_desired: Option<Vec<String>> = None;
+                        let has_track_ops = !track_ops.is_empty();
+                        if (!has_delete || recreated) && !has_track_ops && rename_opt.is_none() {
+                            log::info!(
+                                "{} {} {} reconcile_compute_desired_uris",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("RESOLVE")
+                            );
+                            match desired_remote_uris_for_playlist(
+                                cfg,
+                                playlist_name,
+                                provider.clone(),
+                                db_pool,
+                                trust_cache,
+                            )
+                            .await
+                            {
+                                Ok((desired, local_track_count)) => {
+                                    run_summary.lock().unwrap().unresolved +=
+                                        local_track_count.saturating_sub(desired.len());
                                     log::info!(
-                                        "{} {} {} Sleeping for {} seconds",
-                                        log_run_tag(&worker_id),
+                                        "{} {} {} reconcile_desired_uris_computed count={} local_track_count={}",
+                                        log_run_tag(worker_id),
                                         pl_tag,
-                                        log_phase_tag("REMOTE_ID"),
-                                        exp
+                                        log_phase_tag("RESOLVE"),
+                                        desired.len(),
+                                        local_track_count
                                     );
-                                    tokio::time::sleep(std::time::Duration::from_secs(exp)).await;
-                                    if attempt >= cfg.max_retries_on_error {
-                                        log::error!(
-                                            "{} {} {} remote_id_check_give_up attempts={} error={}",
-                                            log_run_tag(&worker_id),
+                                    if !desired.is_empty() || local_track_count == 0 {
+                                        // Either we have URIs to sync, or the local playlist is
+                                        // genuinely empty (local_track_count == 0) and the remote
+                                        // should be cleared to match.
+                                        reconcile_desired = Some(desired);
+                                    } else {
+                                        // local_track_count > 0 but no URIs resolved — all lookups
+                                        // failed.  Do not wipe the remote in this case.
+                                        log::warn!(
+                                            "{} {} {} reconcile_all_lookups_failed local_tracks={} – remote playlist left unchanged",
+                                            log_run_tag(worker_id),
                                             pl_tag,
-                                            log_phase_tag("REMOTE_ID"),
-                                            attempt,
-                                            e
+                                            log_phase_tag("RESOLVE"),
+                                            local_track_count
                                         );
-                                        break;
                                     }
-                                    continue;
-                                } else {
+                                }
+                                Err(e) => {
                                     log::warn!(
-                                        "{} {} {} playlist_is_valid_check_failed id={} error={}",
-                                        log_run_tag(&worker_id),
+                                        "{} {} {} reconcile_compute_desired_uris_failed error={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("RESOLVE"),
+                                        e
+                                    );
+                                }
+                            }
+                        }
+
+                        // Compute the desired remote display name for this playlist on this provider.
+                        let remote_display_name = compute_remote_playlist_name(
+                            cfg,
+                            provider.name(),
+                            playlist_name,
+                            provider.capabilities().supports_folders,
+                        );
+
+                        // In dry-run mode a playlist that would have to be (re)created has
+                        // no usable remote id; its remote contents are taken to be empty.
+                        let mut dry_run_new_playlist = false;
+                        let mut remote_id = if let Some(rid) = remote_id_opt {
+                            rid
+                        } else if dry_run {
+                            log::info!(
+                                "{} {} {} dry_run would_create_playlist name=\"{}\"",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("DRY_RUN"),
+                                remote_display_name
+                            );
+                            dry_run_new_playlist = true;
+                            String::new()
+                        } else {
+                            // create via provider.ensure_playlist
+                            match provider
+                                .ensure_playlist(
+                                    &remote_display_name,
+                                    &playlist_description(cfg, playlist_name),
+                                )
+                                .await
+                            {
+                                Ok(rid) => {
+                                    // persist
+                                    let pl = playlist_name.clone();
+                                    let pool = db_pool.clone();
+                                    let rid_clone = rid.clone();
+                                    let prov = provider_name.clone();
+                                    let dn = remote_display_name.clone();
+                                    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                                        let conn = pool.get()?;
+                                        db::upsert_playlist_map(&conn, &prov, &pl, &rid_clone)?;
+                                        db::set_remote_display_name(&conn, &prov, &pl, &dn)?;
+                                        Ok(())
+                                    })
+                                    .await??;
+                                            log::info!(
+                                                "{} {} {} ensure_playlist_persisted id={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("REMOTE_ID"),
+                                                rid
+                                            );
+                                    upload_playlist_cover(cfg, provider.as_ref(), playlist_name, &rid, worker_id)
+                                        .await;
+                                    rid
+                                }
+                                Err(e) => {
+                                    log::error!(
+                                        "{} {} {} ensure_remote_playlist_failed error={}",
+                                        log_run_tag(worker_id),
                                         pl_tag,
                                         log_phase_tag("REMOTE_ID"),
-                                        remote_id,
                                         e
                                     );
-                                    break;
+                                    // release lock and continue
+                                    active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                                    release_lock_async(db_pool, &lock_key, worker_id).await;
+                                    failure = Some(format!("ensure_remote_playlist_failed: {}", e));
+                                    provider_ok = false;
+                                    return Ok((provider_ok, failure));
                                 }
                             }
-                        }
-                    }
-                }
-            } // end else !trust_cache
-
-            // If there was no explicit rename event for this playlist, still
-            // ensure that the remote playlist's display name matches what we
-            // compute from configuration (e.g. to apply `online_root_playlist`
-            // to playlists that were created before this option was enabled).
-            //
-            // We only attempt this if the desired remote display name differs
-            // from the logical local playlist name to avoid unnecessary rename
-            // calls when no online naming options are in use.
-            //
-            // To avoid hitting the provider API every run when the name is
-            // already correct, we check the cached remote display name in
-            // the database and skip the PATCH if it already matches.
-            let needs_cfg_rename = if rename_opt.is_none() && remote_display_name != *playlist_name
-            {
-                let cached_name = {
-                    let pool = db_pool.clone();
-                    let prov = provider_name.clone();
-                    let pl = playlist_name.clone();
-                    tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
-                        let conn = pool.get()?;
-                        Ok(db::get_remote_display_name(&conn, &prov, &pl)?)
-                    })
-                    .await??
-                };
-                if cached_name.as_deref() == Some(&remote_display_name) {
-                    log::info!(
-                        "{} {} {} display_name_already_correct id={} name={}",
-                        log_run_tag(&worker_id),
-                        pl_tag,
-                        log_phase_tag("RENAME"),
-                        remote_id,
-                        remote_display_name
-                    );
-                    false
-                } else {
-                    true
-                }
-            } else {
-                false
-            };
-            if needs_cfg_rename {
-                let mut attempt = 0u32;
-                loop {
-                    attempt += 1;
-                    let res = provider
-                        .rename_playlist(&remote_id, &remote_display_name)
-                        .await;
-                    match res {
-                        Ok(_) => {
-                            log::info!(
-                                "{} {} {} ensured_display_name id={} name={}",
-                                log_run_tag(&worker_id),
+                        };
+
+                        // If we have a stored remote id, verify with the provider that it is
+                        // still a valid, accessible playlist. This covers the case where the
+                        // user "deletes" (unfollows) a Spotify playlist in the client while
+                        // our local mapping still points at the old id.
+                        // Skipped when trust_cache is set: the stored remote_id is trusted as-is.
+                        // verify playlist validity, retrying on transient errors (429/rate limit)
+                        if trust_cache {
+                            log::debug!(
+                                "{} {} {} trust_cache: skipping playlist_is_valid",
+                                log_run_tag(worker_id),
                                 pl_tag,
-                                log_phase_tag("RENAME"),
-                                remote_id,
-                                remote_display_name
+                                log_phase_tag("REMOTE_ID")
                             );
-                            // Cache the display name so subsequent runs skip the rename.
+                        } else if dry_run_new_playlist {
+                            // nothing to verify yet
+                        } else {
+                            {
+                                let mut attempt = 0u32;
+                                loop {
+                                    attempt += 1;
+                                    match provider.playlist_is_valid(&remote_id).await {
+                                        Ok(None) => {
+                                            log::warn!(
+                                                "{} {} {} remote_playlist_inaccessible id={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("REMOTE_ID"),
+                                                remote_id
+                                            );
+                                            if dry_run {
+                                                log::info!(
+                                                    "{} {} {} dry_run would_recreate_playlist name=\"{}\"",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("DRY_RUN"),
+                                                    remote_display_name
+                                                );
+                                                dry_run_new_playlist = true;
+                                                remote_id = String::new();
+                                                break;
+                                            }
+                                            match provider
+                                .ensure_playlist(
+                                    &remote_display_name,
+                                    &playlist_description(cfg, playlist_name),
+                                )
+                                .await
                             {
+                                                Ok(new_id) => {
+                                                    let pool = db_pool.clone();
+                                                    let pl = playlist_name.clone();
+                                                    let prov = provider_name.clone();
+                                                    let new_id_clone = new_id.clone();
+                                                    let dn = remote_display_name.clone();
+                                                    tokio::task::spawn_blocking(
+                                                        move || -> Result<(), anyhow::Error> {
+                                                            let conn = pool.get()?;
+                                                            db::upsert_playlist_map(
+                                                                &conn,
+                                                                &prov,
+                                                                &pl,
+                                                                &new_id_clone,
+                                                            )?;
+                                                            db::set_remote_display_name(
+                                                                &conn, &prov, &pl, &dn,
+                                                            )?;
+                                                            Ok(())
+                                                        },
+                                                    )
+                                                    .await??;
+
+                                                    log::info!(
+                                                        "{} {} {} remote_playlist_recreated new_id={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("REMOTE_ID"),
+                                                        new_id
+                                                    );
+                                                    remote_id = new_id;
+                                                }
+                                                Err(e) => {
+                                                    log::error!(
+                                                        "{} {} {} remote_playlist_recreate_failed error={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("REMOTE_ID"),
+                                                        e
+                                                    );
+
+                                                    // Release lock and skip further processing for this playlist.
+                                                    active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                                                    release_lock_async(db_pool, &lock_key, worker_id)
+                                                        .await;
+                                                    failure =
+                                                        Some(format!("remote_playlist_recreate_failed: {}", e));
+                                                    provider_ok = false;
+                                                    continue;
+                                                }
+                                            }
+                                            break;
+                                        }
+                                        Ok(Some(current_remote_name)) => {
+                                            // Opportunistically cache the remote display name
+                                            // that the provider already fetched.
+                                            if !current_remote_name.is_empty() {
+                                                let pool = db_pool.clone();
+                                                let pl = playlist_name.clone();
+                                                let prov = provider_name.clone();
+                                                let rn = current_remote_name.clone();
+                                                let _ = tokio::task::spawn_blocking(move || {
+                                                    if let Ok(conn) = pool.get() {
+                                                        let _ =
+                                                            db::set_remote_display_name(&conn, &prov, &pl, &rn);
+                                                    }
+                                                })
+                                                .await;
+                                            }
+                                            // normal case, keep existing id
+                                            break;
+                                        }
+                                        Err(e) => {
+                                            if let ProviderError::RateLimited { retry_after } = &e {
+                                                // back off and retry
+                                                let exp = retry_after
+                                                    .unwrap_or_else(|| cfg.backoff_secs(attempt));
+                                                log::warn!(
+                                                    "{} {} {} rate_limited remote_id_wait_s={} error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("REMOTE_ID"),
+                                                    exp,
+                                                    e
+                                                );
+                                                log::info!(
+                                                    "{} {} {} Sleeping for {} seconds",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("REMOTE_ID"),
+                                                    exp
+                                                );
+                                                tokio::time::sleep(std::time::Duration::from_secs(exp)).await;
+                                                if attempt >= cfg.max_retries_on_error {
+                                                    log::error!(
+                                                        "{} {} {} remote_id_check_give_up attempts={} error={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("REMOTE_ID"),
+                                                        attempt,
+                                                        e
+                                                    );
+                                                    break;
+                                                }
+                                                continue;
+                                            } else {
+                                                log::warn!(
+                                                    "{} {} {} playlist_is_valid_check_failed id={} error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("REMOTE_ID"),
+                                                    remote_id,
+                                                    e
+                                                );
+                                                break;
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        } // end else !trust_cache
+
+                        // If there was no explicit rename event for this playlist, still
+                        // ensure that the remote playlist's display name matches what we
+                        // compute from configuration (e.g. to apply `online_root_playlist`
+                        // to playlists that were created before this option was enabled).
+                        //
+                        // We only attempt this if the desired remote display name differs
+                        // from the logical local playlist name to avoid unnecessary rename
+                        // calls when no online naming options are in use.
+                        //
+                        // To avoid hitting the provider API every run when the name is
+                        // already correct, we check the cached remote display name in
+                        // the database and skip the PATCH if it already matches.
+                        let needs_cfg_rename = if rename_opt.is_none()
+                            && remote_display_name != *playlist_name
+                            && !dry_run_new_playlist
+                        {
+                            let cached_name = {
                                 let pool = db_pool.clone();
                                 let prov = provider_name.clone();
                                 let pl = playlist_name.clone();
-                                let dn = remote_display_name.clone();
-                                let _ = tokio::task::spawn_blocking(move || {
-                                    if let Ok(conn) = pool.get() {
-                                        let _ = db::set_remote_display_name(&conn, &prov, &pl, &dn);
-                                    }
+                                tokio::task::spawn_blocking(move || -> Result<Option<String>, anyhow::Error> {
+                                    let conn = pool.get()?;
+                                    Ok(db::get_remote_display_name(&conn, &prov, &pl)?)
                                 })
-                                .await;
-                            }
-                            break;
-                        }
-                        Err(e) => {
-                            let s = format!("{}", e);
-                            // Special handling: if the provider reports that the
-                            // playlist id no longer exists (e.g. TIDAL 404),
-                            // recreate it from local state and update mappings.
-                            if s.contains("tidal rename failed: 404 Not Found")
-                                && s.contains("Playlists with id")
-                            {
-                                log::warn!(
-                                    "{} {} {} missing_before_cfg_rename id={}",
-                                    log_run_tag(&worker_id),
+                                .await??
+                            };
+                            if cached_name.as_deref() == Some(&remote_display_name) {
+                                log::info!(
+                                    "{} {} {} display_name_already_correct id={} name={}",
+                                    log_run_tag(worker_id),
                                     pl_tag,
                                     log_phase_tag("RENAME"),
-                                    remote_id
+                                    remote_id,
+                                    remote_display_name
                                 );
-
-                                match provider.ensure_playlist(&remote_display_name, "").await {
-                                    Ok(new_id) => {
-                                        let pool = db_pool.clone();
-                                        let pl = playlist_name.clone();
-                                        let prov = provider_name.clone();
-                                        let new_id_clone = new_id.clone();
-                                        tokio::task::spawn_blocking(
-                                            move || -> Result<(), anyhow::Error> {
-                                                let conn = pool.get()?;
-                                                db::upsert_playlist_map(
-                                                    &conn,
-                                                    &prov,
-                                                    &pl,
-                                                    &new_id_clone,
-                                                )?;
-                                                Ok(())
-                                            },
-                                        )
-                                        .await??;
-
-                                        remote_id = new_id;
+                                false
+                            } else {
+                                true
+                            }
+                        } else {
+                            false
+                        };
+                        if needs_cfg_rename {
+                            let mut attempt = 0u32;
+                            loop {
+                                if dry_run {
+                                    log::info!(
+                                        "{} {} {} dry_run would_rename id={} name={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("DRY_RUN"),
+                                        remote_id,
+                                        remote_display_name
+                                    );
+                                    run_summary.lock().unwrap().renames += 1;
+                                    break;
+                                }
+                                attempt += 1;
+                                let res = provider
+                                    .rename_playlist(&remote_id, &remote_display_name)
+                                    .await;
+                                match res {
+                                    Ok(_) => {
+                                        run_summary.lock().unwrap().renames += 1;
                                         log::info!(
-                                            "{} {} {} recreated_before_cfg_rename new_id={}",
-                                            log_run_tag(&worker_id),
+                                            "{} {} {} ensured_display_name id={} name={}",
+                                            log_run_tag(worker_id),
                                             pl_tag,
                                             log_phase_tag("RENAME"),
-                                            remote_id
+                                            remote_id,
+                                            remote_display_name
                                         );
-
-                                        // Newly created playlist already has the desired
-                                        // display name, so we can stop retrying.
-                                        // Cache it in DB too.
+                                        // Config-driven renames usually come from a template
+                                        // change; bring the description in line as well.
+                                        let description = playlist_description(cfg, playlist_name);
+                                        if !description.is_empty() {
+                                            if let Err(e) = provider
+                                                .update_playlist_description(&remote_id, &description)
+                                                .await
+                                            {
+                                                log::warn!(
+                                                    "{} {} {} description_update_failed id={} error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("RENAME"),
+                                                    remote_id,
+                                                    e
+                                                );
+                                            }
+                                        }
+                                        // Cache the display name so subsequent runs skip the rename.
                                         {
                                             let pool = db_pool.clone();
                                             let prov = provider_name.clone();
@@ -1509,795 +2730,1358 @@ pub async fn run_worker_once(
                                             let dn = remote_display_name.clone();
                                             let _ = tokio::task::spawn_blocking(move || {
                                                 if let Ok(conn) = pool.get() {
-                                                    let _ = db::set_remote_display_name(
-                                                        &conn, &prov, &pl, &dn,
-                                                    );
+                                                    let _ = db::set_remote_display_name(&conn, &prov, &pl, &dn);
                                                 }
                                             })
                                             .await;
                                         }
                                         break;
                                     }
-                                    Err(err) => {
-                                        log::error!(
-                                            "{} {} {} recreate_before_cfg_rename_failed error={}",
-                                            log_run_tag(&worker_id),
-                                            pl_tag,
-                                            log_phase_tag("RENAME"),
-                                            err
-                                        );
-                                        break;
+                                    Err(e) => {
+                                        // Special handling: if the provider reports that the
+                                        // playlist id no longer exists (e.g. TIDAL 404),
+                                        // recreate it from local state and update mappings.
+                                        if matches!(e, ProviderError::PlaylistNotFound { .. }) {
+                                            log::warn!(
+                                                "{} {} {} missing_before_cfg_rename id={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                remote_id
+                                            );
+
+                                            match provider
+                                .ensure_playlist(
+                                    &remote_display_name,
+                                    &playlist_description(cfg, playlist_name),
+                                )
+                                .await
+                            {
+                                                Ok(new_id) => {
+                                                    let pool = db_pool.clone();
+                                                    let pl = playlist_name.clone();
+                                                    let prov = provider_name.clone();
+                                                    let new_id_clone = new_id.clone();
+                                                    tokio::task::spawn_blocking(
+                                                        move || -> Result<(), anyhow::Error> {
+                                                            let conn = pool.get()?;
+                                                            db::upsert_playlist_map(
+                                                                &conn,
+                                                                &prov,
+                                                                &pl,
+                                                                &new_id_clone,
+                                                            )?;
+                                                            Ok(())
+                                                        },
+                                                    )
+                                                    .await??;
+
+                                                    remote_id = new_id;
+                                                    log::info!(
+                                                        "{} {} {} recreated_before_cfg_rename new_id={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("RENAME"),
+                                                        remote_id
+                                                    );
+
+                                                    // Newly created playlist already has the desired
+                                                    // display name, so we can stop retrying.
+                                                    // Cache it in DB too.
+                                                    {
+                                                        let pool = db_pool.clone();
+                                                        let prov = provider_name.clone();
+                                                        let pl = playlist_name.clone();
+                                                        let dn = remote_display_name.clone();
+                                                        let _ = tokio::task::spawn_blocking(move || {
+                                                            if let Ok(conn) = pool.get() {
+                                                                let _ = db::set_remote_display_name(
+                                                                    &conn, &prov, &pl, &dn,
+                                                                );
+                                                            }
+                                                        })
+                                                        .await;
+                                                    }
+                                                    break;
+                                                }
+                                                Err(err) => {
+                                                    log::error!(
+                                                        "{} {} {} recreate_before_cfg_rename_failed error={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("RENAME"),
+                                                        err
+                                                    );
+                                                    break;
+                                                }
+                                            }
+                                        }
+
+                                        if attempt >= cfg.max_retries_on_error {
+                                            log::error!(
+                                                "{} {} {} cfg_rename_failed attempts={} error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                attempt,
+                                                e
+                                            );
+                                            break;
+                                        } else {
+                                            let exp = cfg.backoff_secs(attempt);
+                                            log::warn!(
+                                                "{} {} {} cfg_rename_retry attempt={} error={} backoff_s={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                attempt,
+                                                e,
+                                                exp
+                                            );
+                                            log::info!(
+                                                "{} {} {} Sleeping for {} seconds",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                exp
+                                            );
+                                            tokio::time::sleep(std::time::Duration::from_secs(exp)).await;
+                                            continue;
+                                        }
                                     }
                                 }
                             }
-
-                            if attempt >= cfg.max_retries_on_error {
-                                log::error!(
-                                    "{} {} {} cfg_rename_failed attempts={} error={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    attempt,
-                                    e
-                                );
-                                break;
-                            } else {
-                                let exp = std::cmp::min(1u64 << attempt, 60);
-                                log::warn!(
-                                    "{} {} {} cfg_rename_retry attempt={} error={} backoff_s={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    attempt,
-                                    e,
-                                    exp
-                                );
-                                log::info!(
-                                    "{} {} {} Sleeping for {} seconds",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    exp
-                                );
-                                tokio::time::sleep(std::time::Duration::from_secs(exp)).await;
-                                continue;
-                            }
                         }
-                    }
-                }
-            }
 
-            // Apply rename first
-            if let Some((_from, to)) = rename_opt.clone() {
-                let new_remote_name = compute_remote_playlist_name(
-                    cfg,
-                    provider.name(),
-                    &to,
-                    provider.supports_folder_nesting(),
-                );
-                let mut attempt = 0u32;
-                loop {
-                    attempt += 1;
-                    let res = provider.rename_playlist(&remote_id, &new_remote_name).await;
-                    match res {
-                        Ok(_) => {
-                            log::info!(
-                                "{} {} {} explicit_rename id={} new_name={}",
-                                log_run_tag(&worker_id),
-                                pl_tag,
-                                log_phase_tag("RENAME"),
-                                remote_id,
-                                new_remote_name
+                        // Apply rename first
+                        if let Some((_from, to)) = rename_opt.clone() {
+                            let new_remote_name = compute_remote_playlist_name(
+                                cfg,
+                                provider.name(),
+                                &to,
+                                provider.capabilities().supports_folders,
                             );
-                            // On successful explicit rename, migrate the playlist_map
-                            // entry so that the logical playlist key follows the
-                            // folder rename. This prevents a subsequent run for the
-                            // new logical name from calling ensure_playlist and
-                            // creating a duplicate remote playlist.
-                            let pool = db_pool.clone();
-                            let prov = provider_name.clone();
-                            let pl_from = playlist_name.clone();
-                            let pl_to = to.clone();
-                            let new_name_clone = new_remote_name.clone();
-                            let _ = tokio::task::spawn_blocking(
-                                move || -> Result<(), anyhow::Error> {
-                                    let conn = pool.get()?;
-                                    crate::db::migrate_playlist_map(
-                                        &conn, &prov, &pl_from, &pl_to,
-                                    )?;
-                                    // keep playlist cache in sync as well
-                                    crate::db::migrate_playlist_cache(
-                                        &conn, &prov, &pl_from, &pl_to,
-                                    )?;
-                                    // Cache the new display name under the new key
-                                    crate::db::set_remote_display_name(
-                                        &conn,
-                                        &prov,
-                                        &pl_to,
-                                        &new_name_clone,
-                                    )?;
-                                    Ok(())
-                                },
-                            )
-                            .await;
-                            // update playlist_map name? Here we keep playlist_name as local key; remote_id unchanged.
-                            break;
-                        }
-                        Err(e) => {
-                            let s = format!("{}", e);
-                            // Special handling: if the provider reports that the
-                            // playlist id no longer exists (e.g. TIDAL 404),
-                            // recreate it from local state and update mappings
-                            // using the new target name.
-                            if s.contains("tidal rename failed: 404 Not Found")
-                                && s.contains("Playlists with id")
-                            {
-                                log::warn!(
-                                    "{} {} {} missing_before_explicit_rename id={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    remote_id
-                                );
-
-                                match provider.ensure_playlist(&new_remote_name, "").await {
-                                    Ok(new_id) => {
+                            let mut attempt = 0u32;
+                            loop {
+                                if dry_run {
+                                    log::info!(
+                                        "{} {} {} dry_run would_rename id={} new_name={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("DRY_RUN"),
+                                        if remote_id.is_empty() {
+                                            "<new>"
+                                        } else {
+                                            &remote_id
+                                        },
+                                        new_remote_name
+                                    );
+                                    run_summary.lock().unwrap().renames += 1;
+                                    break;
+                                }
+                                attempt += 1;
+                                let res = provider.rename_playlist(&remote_id, &new_remote_name).await;
+                                match res {
+                                    Ok(_) => {
+                                        run_summary.lock().unwrap().renames += 1;
+                                        log::info!(
+                                            "{} {} {} explicit_rename id={} new_name={}",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("RENAME"),
+                                            remote_id,
+                                            new_remote_name
+                                        );
+                                        // On successful explicit rename, migrate the playlist_map
+                                        // entry so that the logical playlist key follows the
+                                        // folder rename. This prevents a subsequent run for the
+                                        // new logical name from calling ensure_playlist and
+                                        // creating a duplicate remote playlist.
                                         let pool = db_pool.clone();
-                                        let pl = playlist_name.clone();
                                         let prov = provider_name.clone();
-                                        let new_id_clone = new_id.clone();
-                                        tokio::task::spawn_blocking(
+                                        let pl_from = playlist_name.clone();
+                                        let pl_to = to.clone();
+                                        let new_name_clone = new_remote_name.clone();
+                                        let _ = tokio::task::spawn_blocking(
                                             move || -> Result<(), anyhow::Error> {
                                                 let conn = pool.get()?;
-                                                db::upsert_playlist_map(
+                                                crate::db::migrate_playlist_map(
+                                                    &conn, &prov, &pl_from, &pl_to,
+                                                )?;
+                                                // keep playlist cache in sync as well
+                                                crate::db::migrate_playlist_cache(
+                                                    &conn, &prov, &pl_from, &pl_to,
+                                                )?;
+                                                // Cache the new display name under the new key
+                                                crate::db::set_remote_display_name(
                                                     &conn,
                                                     &prov,
-                                                    &pl,
-                                                    &new_id_clone,
+                                                    &pl_to,
+                                                    &new_name_clone,
                                                 )?;
                                                 Ok(())
                                             },
                                         )
-                                        .await??;
+                                        .await;
+                                        // update playlist_map name? Here we keep playlist_name as local key; remote_id unchanged.
+                                        break;
+                                    }
+                                    Err(e) => {
+                                        // Special handling: if the provider reports that the
+                                        // playlist id no longer exists (e.g. TIDAL 404),
+                                        // recreate it from local state and update mappings
+                                        // using the new target name.
+                                        if matches!(e, ProviderError::PlaylistNotFound { .. }) {
+                                            log::warn!(
+                                                "{} {} {} missing_before_explicit_rename id={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                remote_id
+                                            );
+
+                                            match provider
+                                .ensure_playlist(&new_remote_name, &playlist_description(cfg, &to))
+                                .await
+                            {
+                                                Ok(new_id) => {
+                                                    let pool = db_pool.clone();
+                                                    let pl = playlist_name.clone();
+                                                    let prov = provider_name.clone();
+                                                    let new_id_clone = new_id.clone();
+                                                    tokio::task::spawn_blocking(
+                                                        move || -> Result<(), anyhow::Error> {
+                                                            let conn = pool.get()?;
+                                                            db::upsert_playlist_map(
+                                                                &conn,
+                                                                &prov,
+                                                                &pl,
+                                                                &new_id_clone,
+                                                            )?;
+                                                            Ok(())
+                                                        },
+                                                    )
+                                                    .await??;
+
+                                                    remote_id = new_id;
+                                                    log::info!(
+                                                        "{} {} {} recreated_before_explicit_rename new_id={}",
+                                                        log_run_tag(worker_id),
+                                                        pl_tag,
+                                                        log_phase_tag("RENAME"),
+                                                        remote_id
+                                                    );
+
+                                                    // Newly created playlist already has the desired
+                                                    // display name (`new_remote_name`), so we can
+                                                    // stop retrying.
+                                                    break;
+                                                }
+                                                Err(err) => {
+                                                    log::error!(
+                                                    "{} {} {} recreate_before_explicit_rename_failed error={}",
+                                                    log_run_tag(worker_id),
+                                                    pl_tag,
+                                                    log_phase_tag("RENAME"),
+                                                    err
+                                                );
+                                                    break;
+                                                }
+                                            }
+                                        }
+
+                                        if attempt >= cfg.max_retries_on_error {
+                                            log::error!(
+                                                "{} {} {} explicit_rename_failed attempts={} error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                attempt,
+                                                e
+                                            );
+                                            break;
+                                        } else {
+                                            log::warn!(
+                                                "{} {} {} explicit_rename_retry attempt={} error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                attempt,
+                                                e
+                                            );
+                                            let sleep_secs = cfg.backoff_secs(attempt);
+                                            log::info!(
+                                                "{} {} {} Sleeping for {} seconds",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RENAME"),
+                                                sleep_secs
+                                            );
+                                            tokio::time::sleep(std::time::Duration::from_secs(sleep_secs))
+                                                .await;
+                                            continue;
+                                        }
+                                    }
+                                }
+                            }
+                        }
 
-                                        remote_id = new_id;
+                        // Seed add/remove lists from reconciliation diff so that remote
+                        // contents exactly match the local playlist when desired.
+                        let mut add_uris: Vec<String> = Vec::new();
+                        let mut remove_uris: Vec<String> = Vec::new();
+                        // Local `.m3u` order and pre-mutation remote order, kept so the
+                        // remote playlist can be reordered afterwards in "mirror" mode.
+                        let mut mirror_order: Option<(Vec<String>, Vec<String>)> = None;
+                        // Remote contents fetched for reconciliation, reused by dry runs.
+                        let mut remote_snapshot: Option<Vec<String>> = None;
+                        // Pre-mutation remote contents with their remote id and listing
+                        // time; updated with our changes into the stored snapshot below.
+                        let mut snapshot_base: Option<(String, Vec<String>, i64)> = None;
+                        let reconciled = reconcile_desired.is_some();
+                        if let Some(desired) = reconcile_desired.take() {
+                            // Fetch current remote contents: use the cache when trust_cache is
+                            // set and a matching entry exists; otherwise hit the provider live
+                            // and always write the result back to the cache.
+                            let remote_current_opt: Option<Vec<String>> = if trust_cache {
+                                let cached = tokio::task::spawn_blocking({
+                                    let pool = db_pool.clone();
+                                    let prov = provider_name.clone();
+                                    let pl = playlist_name.clone();
+                                    move || -> Result<Option<(String, String)>, anyhow::Error> {
+                                        let conn = pool.get()?;
+                                        Ok(db::get_remote_playlist_contents_cache(&conn, &prov, &pl)?)
+                                    }
+                                })
+                                .await??;
+                                match cached {
+                                    Some((cached_remote_id, uris_json)) if cached_remote_id == remote_id => {
                                         log::info!(
-                                            "{} {} {} recreated_before_explicit_rename new_id={}",
-                                            log_run_tag(&worker_id),
+                                            "{} {} {} trust_cache: using cached remote_contents count={}",
+                                            log_run_tag(worker_id),
                                             pl_tag,
-                                            log_phase_tag("RENAME"),
-                                            remote_id
+                                            log_phase_tag("RECONCILE"),
+                                            serde_json::from_str::<Vec<String>>(&uris_json)
+                                                .map(|v| v.len())
+                                                .unwrap_or(0)
                                         );
-
-                                        // Newly created playlist already has the desired
-                                        // display name (`new_remote_name`), so we can
-                                        // stop retrying.
-                                        break;
+                                        Some(serde_json::from_str(&uris_json).unwrap_or_default())
                                     }
-                                    Err(err) => {
-                                        log::error!(
-                                        "{} {} {} recreate_before_explicit_rename_failed error={}",
-                                        log_run_tag(&worker_id),
-                                        pl_tag,
-                                        log_phase_tag("RENAME"),
-                                        err
-                                    );
-                                        break;
+                                    _ => {
+                                        log::debug!(
+                                            "{} {} {} trust_cache: no matching remote_contents cache, fetching live",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("RECONCILE")
+                                        );
+                                        None
                                     }
                                 }
-                            }
+                            } else {
+                                None
+                            };
 
-                            if attempt >= cfg.max_retries_on_error {
-                                log::error!(
-                                    "{} {} {} explicit_rename_failed attempts={} error={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    attempt,
-                                    e
-                                );
-                                break;
+                            // Next best: the snapshot stored after the last sync, unless it
+                            // is too old or belongs to another remote id (drift suspected).
+                            let remote_current_opt = if remote_current_opt.is_some() || dry_run_new_playlist {
+                                remote_current_opt
                             } else {
-                                log::warn!(
-                                    "{} {} {} explicit_rename_retry attempt={} error={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    attempt,
-                                    e
-                                );
-                                let sleep_secs = 1 << attempt;
-                                log::info!(
-                                    "{} {} {} Sleeping for {} seconds",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RENAME"),
-                                    sleep_secs
-                                );
-                                tokio::time::sleep(std::time::Duration::from_secs(sleep_secs))
-                                    .await;
-                                continue;
-                            }
-                        }
-                    }
-                }
-            }
+                                let now = chrono::Utc::now().timestamp();
+                                let max_age = cfg.remote_snapshot_max_age_secs as i64;
+                                match load_remote_snapshot(db_pool, provider_name, playlist_name).await {
+                                    Some(snap)
+                                        if max_age > 0
+                                            && snap.remote_id == remote_id
+                                            && now - snap.listed_at < max_age =>
+                                    {
+                                        log::info!(
+                                            "{} {} {} remote_snapshot_used count={} age_s={}",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("RECONCILE"),
+                                            snap.uris.len(),
+                                            now - snap.listed_at
+                                        );
+                                        snapshot_base =
+                                            Some((snap.remote_id, snap.uris.clone(), snap.listed_at));
+                                        Some(snap.uris)
+                                    }
+                                    _ => None,
+                                }
+                            };
 
-            // Seed add/remove lists from reconciliation diff so that remote
-            // contents exactly match the local playlist when desired.
-            let mut add_uris: Vec<String> = Vec::new();
-            let mut remove_uris: Vec<String> = Vec::new();
-            if let Some(desired) = reconcile_desired.take() {
-                // Fetch current remote contents: use the cache when trust_cache is
-                // set and a matching entry exists; otherwise hit the provider live
-                // and always write the result back to the cache.
-                let remote_current_opt: Option<Vec<String>> = if trust_cache {
-                    let cached = tokio::task::spawn_blocking({
-                        let pool = db_pool.clone();
-                        let prov = provider_name.clone();
-                        let pl = playlist_name.clone();
-                        move || -> Result<Option<(String, String)>, anyhow::Error> {
-                            let conn = pool.get()?;
-                            Ok(db::get_remote_playlist_contents_cache(&conn, &prov, &pl)?)
-                        }
-                    })
-                    .await??;
-                    match cached {
-                        Some((cached_remote_id, uris_json)) if cached_remote_id == remote_id => {
-                            log::info!(
-                                "{} {} {} trust_cache: using cached remote_contents count={}",
-                                log_run_tag(&worker_id),
-                                pl_tag,
-                                log_phase_tag("RECONCILE"),
-                                serde_json::from_str::<Vec<String>>(&uris_json)
-                                    .map(|v| v.len())
-                                    .unwrap_or(0)
-                            );
-                            Some(serde_json::from_str(&uris_json).unwrap_or_default())
-                        }
-                        _ => {
-                            log::debug!(
-                                "{} {} {} trust_cache: no matching remote_contents cache, fetching live",
-                                log_run_tag(&worker_id),
-                                pl_tag,
-                                log_phase_tag("RECONCILE")
-                            );
-                            None
-                        }
-                    }
-                } else {
-                    None
-                };
+                            let remote_current_opt = if remote_current_opt.is_some() {
+                                remote_current_opt
+                            } else if dry_run_new_playlist {
+                                Some(Vec::new())
+                            } else {
+                                match provider.list_playlist_tracks(&remote_id).await {
+                                    Ok(remote_current) => {
+                                        let listed_at = chrono::Utc::now().timestamp();
+                                        if !dry_run {
+                                            store_remote_snapshot(
+                                                db_pool,
+                                                provider_name,
+                                                playlist_name,
+                                                Some((&remote_id, remote_current.clone(), listed_at)),
+                                            )
+                                            .await;
+                                        }
+                                        snapshot_base =
+                                            Some((remote_id.clone(), remote_current.clone(), listed_at));
+                                        // Always persist the live response so future runs
+                                        // with --trust-cache can skip this request.
+                                        let uris_json =
+                                            serde_json::to_string(&remote_current).unwrap_or_default();
+                                        let pool = db_pool.clone();
+                                        let prov = provider_name.clone();
+                                        let pl = playlist_name.clone();
+                                        let rid = remote_id.clone();
+                                        let _ = tokio::task::spawn_blocking(move || {
+                                            if let Ok(conn) = pool.get() {
+                                                let _ = db::upsert_remote_playlist_contents_cache(
+                                                    &conn, &prov, &pl, &rid, &uris_json,
+                                                );
+                                            }
+                                        })
+                                        .await;
+                                        Some(remote_current)
+                                    }
+                                    Err(e) => {
+                                        log::warn!(
+                                            "{} {} {} reconcile_list_remote_failed error={}",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("RECONCILE"),
+                                            e
+                                        );
+                                        None
+                                    }
+                                }
+                            };
 
-                let remote_current_opt = if remote_current_opt.is_some() {
-                    remote_current_opt
-                } else {
-                    match provider.list_playlist_tracks(&remote_id).await {
-                        Ok(remote_current) => {
-                            // Always persist the live response so future runs
-                            // with --trust-cache can skip this request.
-                            let uris_json =
-                                serde_json::to_string(&remote_current).unwrap_or_default();
-                            let pool = db_pool.clone();
-                            let prov = provider_name.clone();
-                            let pl = playlist_name.clone();
-                            let rid = remote_id.clone();
-                            let _ = tokio::task::spawn_blocking(move || {
-                                if let Ok(conn) = pool.get() {
-                                    let _ = db::upsert_remote_playlist_contents_cache(
-                                        &conn, &prov, &pl, &rid, &uris_json,
-                                    );
+                            if let Some(remote_current) = remote_current_opt {
+                                let (to_add, to_remove) = ordered_reconcile_diff(&desired, &remote_current);
+                                if dry_run {
+                                    remote_snapshot = Some(remote_current.clone());
+                                }
+
+                                if !to_add.is_empty() {
+                                    log::info!(
+                                        "{} {} {} reconcile_missing_tracks scheduling_adds={} ",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("RECONCILE"),
+                                        to_add.len()
+                                    );
+                                    add_uris.extend(to_add);
                                 }
-                            })
-                            .await;
-                            Some(remote_current)
-                        }
-                        Err(e) => {
-                            log::warn!(
-                                "{} {} {} reconcile_list_remote_failed error={}",
-                                log_run_tag(&worker_id),
-                                pl_tag,
-                                log_phase_tag("RECONCILE"),
-                                e
-                            );
-                            None
-                        }
-                    }
-                };
 
-                if let Some(remote_current) = remote_current_opt {
-                    use std::collections::HashSet;
-                    let desired_set: HashSet<String> = desired.into_iter().collect();
-                    let remote_set: HashSet<String> = remote_current.into_iter().collect();
+                                if !to_remove.is_empty() {
+                                    log::info!(
+                                        "{} {} {} reconcile_extra_tracks scheduling_removes={} ",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("RECONCILE"),
+                                        to_remove.len()
+                                    );
+                                    remove_uris.extend(to_remove);
+                                }
 
-                    let to_add: Vec<String> =
-                        desired_set.difference(&remote_set).cloned().collect();
-                    let to_remove: Vec<String> =
-                        remote_set.difference(&desired_set).cloned().collect();
+                                if cfg.playlist_order_mode == "mirror"
+                                    && provider.capabilities().supports_reorder
+                                {
+                                    mirror_order = Some((desired, remote_current));
+                                }
+                            }
+                        }
 
-                    if !to_add.is_empty() {
+                        // Resolve track URIs and build add/remove lists (prefer cache/ISRC when possible, then fallback to metadata search)
                         log::info!(
-                            "{} {} {} reconcile_missing_tracks scheduling_adds={} ",
-                            log_run_tag(&worker_id),
+                            "{} {} {} resolve_uris_start ops={}",
+                            log_run_tag(worker_id),
                             pl_tag,
-                            log_phase_tag("RECONCILE"),
-                            to_add.len()
+                            log_phase_tag("RESOLVE"),
+                            track_ops.len()
                         );
-                        add_uris.extend(to_add);
-                    }
 
-                    if !to_remove.is_empty() {
-                        log::info!(
-                            "{} {} {} reconcile_extra_tracks scheduling_removes={} ",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("RECONCILE"),
-                            to_remove.len()
-                        );
-                        remove_uris.extend(to_remove);
-                    }
-                }
-            }
+                        // Resolving thousands of tracks can outlast the lease; renew it
+                        // once a third of the TTL has passed.
+                        let renew_every = std::time::Duration::from_secs((cfg.lock_ttl_secs / 3).max(1) as u64);
+                        let mut last_renew = std::time::Instant::now();
+                        for (act, track_path_opt) in track_ops.clone().into_iter() {
+                            if last_renew.elapsed() >= renew_every {
+                                renew_lock_async(cfg, db_pool, &lock_key, worker_id).await;
+                                last_renew = std::time::Instant::now();
+                            }
+                            if let Some(mut tp) = track_path_opt {
+                                // provider-originated URIs may come in directly; we want to
+                                // re-resolve everything against the *local* files so that each
+                                // destination provider does its own availability lookup.  The
+                                // only case where we accept a bare URI is when the event was
+                                // generated locally (`uri::…`).
+                                if tp.starts_with("uri::") {
+                                    let uri = tp.trim_start_matches("uri::").to_string();
+                                    match act {
+                                        EventAction::Add => add_uris.push(uri),
+                                        EventAction::Remove => remove_uris.push(uri),
+                                        _ => {}
+                                    }
+                                    continue;
+                                }
 
-            // Resolve track URIs and build add/remove lists (prefer cache/ISRC when possible, then fallback to metadata search)
-            log::info!(
-                "{} {} {} resolve_uris_start ops={}",
-                log_run_tag(&worker_id),
-                pl_tag,
-                log_phase_tag("RESOLVE"),
-                track_ops.len()
-            );
+                                // non-local URIs (spotify:track:..., tidal:track:...) should be
+                                // mapped back to a local path via the cache.  If the cache has no
+                                // entry, we drop the operation entirely; the local playlist file
+                                // is the golden source.
+                                if tp.contains(':') && !tp.starts_with(&format!("{}:", provider.name())) {
+                                    let pool = db_pool.clone();
+                                    let uri_clone = tp.clone();
+                                    if let Some((_isrc, local_path, _)) = tokio::task::spawn_blocking(move || -> Result<Option<(Option<String>, String, i64)>, anyhow::Error> {
+                                        let conn = pool.get()?;
+                                        Ok(db::get_track_cache_by_remote(&conn, &uri_clone)?)
+                                    })
+                                    .await??
+                                    {
+                                        // track_cache stores the raw filesystem path; the provider
+                                        // lives in its own column.
+                                        tp = local_path;
+                                    } else {
+                                        log::warn!(
+                                            "reconcile: skipping provider URI {} because no corresponding local file was cached",
+                                            tp
+                                        );
+                                        continue;
+                                    }
+                                }
 
-            for (act, track_path_opt) in track_ops.clone().into_iter() {
-                if let Some(mut tp) = track_path_opt {
-                    // provider-originated URIs may come in directly; we want to
-                    // re-resolve everything against the *local* files so that each
-                    // destination provider does its own availability lookup.  The
-                    // only case where we accept a bare URI is when the event was
-                    // generated locally (`uri::…`).
-                    if tp.starts_with("uri::") {
-                        let uri = tp.trim_start_matches("uri::").to_string();
-                        match act {
-                            EventAction::Add => add_uris.push(uri),
-                            EventAction::Remove => remove_uris.push(uri),
-                            _ => {}
-                        }
-                        continue;
-                    }
+                                // Event paths are lossy strings; read the file they came from.
+                                let disk_path = crate::util::resolve_lossy_path(std::path::Path::new(&tp));
+                                let direct_uri = match provider.local_track_uri(&disk_path) {
+                                    Some(uri) => Some(uri),
+                                    None => override_uri(db_pool, provider.name(), &tp).await?,
+                                };
+                                if let Some(uri) = direct_uri {
+                                    match act {
+                                        EventAction::Add => add_uris.push(uri),
+                                        EventAction::Remove => remove_uris.push(uri),
+                                        _ => {}
+                                    }
+                                    continue;
+                                }
 
-                    // non-local URIs (spotify:track:..., tidal:track:...) should be
-                    // mapped back to a local path via the cache.  If the cache has no
-                    // entry, we drop the operation entirely; the local playlist file
-                    // is the golden source.
-                    if tp.contains(':') && !tp.starts_with(&format!("{}:", provider.name())) {
-                        let pool = db_pool.clone();
-                        let uri_clone = tp.clone();
-                        if let Some((_isrc, mut local_path, _)) = tokio::task::spawn_blocking(move || -> Result<Option<(Option<String>, String, i64)>, anyhow::Error> {
-                            let conn = pool.get()?;
-                            Ok(db::get_track_cache_by_remote(&conn, &uri_clone)?)
-                        })
-                        .await??
-                        {
-                            // the stored path may include an old provider prefix ("tidal::foo")
-                            // when we migrated the cache.  strip anything up to the first
-                            // "::" so that the downstream resolution logic always works with
-                            // the raw filesystem path.
-                            if let Some(idx) = local_path.find("::") {
-                                local_path = local_path[idx + 2..].to_string();
-                            }
-                            tp = local_path;
-                        } else {
-                            log::warn!(
-                                "reconcile: skipping provider URI {} because no corresponding local file was cached",
-                                tp
-                            );
-                            continue;
-                        }
-                    }
+                                // Try track cache first (with timestamp) to avoid unnecessary lookups
+                                let mut cached: Option<(Option<String>, Option<String>, i64)> =
+                                    tokio::task::spawn_blocking({
+                                        let pool = db_pool.clone();
+                                        let local_path = tp.clone();
+                                        let provider_name = provider.name().to_string();
+                                        move || -> Result<Option<(Option<String>, Option<String>, i64)>, anyhow::Error> {
+                                            let conn = pool.get()?;
+                                            Ok(db::get_track_cache_by_local(&conn, &provider_name, &local_path)?)
+                                        }
+                                    })
+                                    .await??;
+                                // Removes keep the cached URI: that is the track the
+                                // playlist holds, even if it was a bad match.
+                                if let (EventAction::Add, Some((_, Some(uri), _))) = (&act, &cached) {
+                                    if drop_stale_cached_uri(db_pool, provider.as_ref(), &tp, uri).await? {
+                                        cached = None;
+                                    }
+                                }
 
-                    // Try track cache first (with timestamp) to avoid unnecessary lookups
-                    let cached: Option<(Option<String>, Option<String>, i64)> =
-                        tokio::task::spawn_blocking({
-                            let pool = db_pool.clone();
-                            let local_path = tp.clone();
-                            let provider_name = provider.name().to_string();
-                            move || -> Result<Option<(Option<String>, Option<String>, i64)>, anyhow::Error> {
-                                let conn = pool.get()?;
-                                Ok(db::get_track_cache_by_local(&conn, &provider_name, &local_path)?)
-                            }
-                        })
-                        .await??;
+                                if let Some((_cached_isrc, cached_remote_id, _resolved_at)) = &cached {
+                                    if let Some(uri) = cached_remote_id {
+                                        match act {
+                                            EventAction::Add => add_uris.push(uri.clone()),
+                                            EventAction::Remove => remove_uris.push(uri.clone()),
+                                            _ => {}
+                                        }
+                                        continue;
+                                    } else if unresolved_recently(cfg, db_pool, provider.name(), &tp).await? {
+                                        // negative hit still fresh
+                                        continue;
+                                    }
+                                }
 
-                    if let Some((_cached_isrc, cached_remote_id, resolved_at)) = &cached {
-                        if let Some(uri) = cached_remote_id {
-                            match act {
-                                EventAction::Add => add_uris.push(uri.clone()),
-                                EventAction::Remove => remove_uris.push(uri.clone()),
-                                _ => {}
-                            }
-                            continue;
-                        } else {
-                            let now = Utc::now().timestamp();
-                            if now - *resolved_at < NEGATIVE_CACHE_TTL_SECS {
-                                // negative hit still fresh
-                                continue;
-                            }
-                        }
-                    }
+                                // Try to extract ISRC from local file metadata and perform an ISRC-based search
+                                let mut isrc_for_lookup: Option<String> =
+                                    cached.as_ref().and_then(|(i, _, _)| i.clone());
+                                if isrc_for_lookup.is_none() {
+                                    let (pool, name, key, p) = (
+                                        db_pool.clone(),
+                                        provider.name().to_string(),
+                                        tp.clone(),
+                                        disk_path.clone(),
+                                    );
+                                    let extracted = match tokio::task::spawn_blocking(move || {
+                                        local_file_isrc(&pool, &name, &key, &p)
+                                    })
+                                    .await
+                                    {
+                                        Ok(v) => v,
+                                        Err(e) => {
+                                            log::warn!(
+                                                "{} {} {} isrc_extract_task_failed track={} error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RESOLVE"),
+                                                tp,
+                                                e
+                                            );
+                                            None
+                                        }
+                                    };
+                                    let extracted = match extracted {
+                                        Some(code) => Some(code),
+                                        None => match shared_remote_isrc(db_pool, &tp).await {
+                                            Some(code) => Some(code),
+                                            None => {
+                                                musicbrainz_isrc(
+                                                    cfg,
+                                                    &disk_path,
+                                                    filename_regex.as_ref(),
+                                                )
+                                                .await
+                                            }
+                                        },
+                                    };
+                                    if let Some(code) = extracted {
+                                        isrc_for_lookup = Some(code.clone());
+                                        // persist locally extracted ISRC in cache (without remote id yet)
+                                        let pool = db_pool.clone();
+                                        let local_path = tp.clone();
+                                        let code_for_cache = code.clone();
+                                        let provider_name = provider.name().to_string();
+                                        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                                            let conn = pool.get()?;
+                                            let _ = crate::db::upsert_track_cache(
+                                                &conn,
+                                                &provider_name,
+                                                &local_path,
+                                                Some(code_for_cache.as_str()),
+                                                None,
+                                            )?;
+                                            Ok(())
+                                        })
+                                        .await??;
+                                    }
+                                }
 
-                    // Try to extract ISRC from local file metadata and perform an ISRC-based search
-                    let mut isrc_for_lookup: Option<String> =
-                        cached.as_ref().and_then(|(i, _, _)| i.clone());
-                    if isrc_for_lookup.is_none() {
-                        let p = std::path::Path::new(&tp).to_path_buf();
-                        let extracted = match tokio::task::spawn_blocking(move || {
-                            crate::util::extract_isrc_from_path(&p)
-                        })
-                        .await
-                        {
-                            Ok(v) => v,
-                            Err(e) => {
-                                log::warn!(
-                                    "{} {} {} isrc_extract_task_failed track={} error={}",
-                                    log_run_tag(&worker_id),
-                                    pl_tag,
-                                    log_phase_tag("RESOLVE"),
-                                    tp,
-                                    e
+                                if let Some(isrc) = isrc_for_lookup
+                                    .clone()
+                                    .filter(|_| provider.capabilities().supports_isrc_search)
+                                {
+                                    match provider.search_track_uri_by_isrc(&isrc).await {
+                                        Ok(Some(uri)) => {
+                                            match act {
+                                                EventAction::Add => add_uris.push(uri.clone()),
+                                                EventAction::Remove => remove_uris.push(uri.clone()),
+                                                _ => {}
+                                            }
+                                            // Persist cache with ISRC + resolved URI
+                                            let pool = db_pool.clone();
+                                            let local_path = tp.clone();
+                                            let uri_clone = uri.clone();
+                                            let isrc_for_cache = isrc.clone();
+                                            let provider_name = provider.name().to_string();
+                                            tokio::task::spawn_blocking(
+                                                move || -> Result<(), anyhow::Error> {
+                                                    let conn = pool.get()?;
+                                                    let _ = crate::db::upsert_track_cache(
+                                                        &conn,
+                                                        &provider_name,
+                                                        &local_path,
+                                                        Some(isrc_for_cache.as_str()),
+                                                        Some(&uri_clone),
+                                                    )?;
+                                                    Ok(())
+                                                },
+                                            )
+                                            .await??;
+                                            continue;
+                                        }
+                                        #[allow(non_snake_case)]
+                                        Ok(None) => {
+                                            // fall through to metadata-based search
+                                        }
+                                        Err(e) => {
+                                            log::warn!(
+                                                "{} {} {} isrc_search_failed track={} error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("RESOLVE"),
+                                                tp,
+                                                e
+                                            );
+                                            // fall through to metadata-based search
+                                        }
+                                    }
+                                }
+
+                                // Fallback: provider metadata search. Prefer artist/title read from
+                                // the file's tags; only when those are missing derive them from the
+                                // filename (via `filename_parse_regex` when configured, else trying
+                                // both "Artist - Title" and "Title - Artist" orders and keeping the match
+                                // that covers the file name best).
+                                let (candidates, album, duration_ms) = {
+                                    let p = disk_path.clone();
+                                    let re = filename_regex.clone();
+                                    let suffixes = copy_suffixes.clone();
+                                    tokio::task::spawn_blocking(move || {
+                                        (
+                                            crate::util::artist_title_candidates_stripped(
+                                                &p,
+                                                re.as_ref(),
+                                                &suffixes,
+                                            ),
+                                            crate::util::album_for_path_with(&p, re.as_ref()),
+                                            crate::util::duration_ms_from_path(&p),
+                                        )
+                                    })
+                                    .await
+                                    .unwrap_or_default()
+                                };
+                                // Exact terms first, then a normalized retry.
+                                let candidates = crate::util::with_normalized_search_terms(
+                                    candidates,
+                                    &cfg.search_strip_tokens,
+                                    cfg.search_strip_diacritics,
                                 );
-                                None
-                            }
-                        };
-                        if let Some(code) = extracted {
-                            isrc_for_lookup = Some(code.clone());
-                            // persist locally extracted ISRC in cache (without remote id yet)
-                            let pool = db_pool.clone();
-                            let local_path = tp.clone();
-                            let code_for_cache = code.clone();
-                            let provider_name = provider.name().to_string();
-                            tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                                let conn = pool.get()?;
-                                let _ = crate::db::upsert_track_cache(
-                                    &conn,
-                                    &provider_name,
-                                    &local_path,
-                                    Some(code_for_cache.as_str()),
-                                    None,
-                                )?;
-                                Ok(())
-                            })
-                            .await??;
-                        }
-                    }
 
-                    if let Some(isrc) = isrc_for_lookup.clone() {
-                        match provider.search_track_uri_by_isrc(&isrc).await {
-                            Ok(Some(uri)) => {
-                                match act {
-                                    EventAction::Add => add_uris.push(uri.clone()),
-                                    EventAction::Remove => remove_uris.push(uri.clone()),
-                                    _ => {}
+                                let mut resolved_uri: Option<String> = None;
+                                for (i, (artist, title)) in candidates.iter().enumerate() {
+                                    let (artist, title) = (artist.as_str(), title.trim());
+                                    let search = search_confident_match(
+                                        cfg,
+                                        provider.as_ref(),
+                                        title,
+                                        artist,
+                                        album.as_deref(),
+                                        duration_ms,
+                                    )
+                                    .await;
+                                    match search {
+                                        Ok(Some(m)) => {
+                                            // A hit for one ordering of a " - " file name does
+                                            // not rule out the other; search both and keep the
+                                            // better match.
+                                            let m = match swapped_ordering(&candidates, i) {
+                                                Some(j) => {
+                                                    let other = search_confident_match(
+                                                        cfg,
+                                                        provider.as_ref(),
+                                                        candidates[j].1.trim(),
+                                                        &candidates[j].0,
+                                                        album.as_deref(),
+                                                        duration_ms,
+                                                    )
+                                                    .await;
+                                                    better_ordering(
+                                                        &format!("{} {}", artist, title),
+                                                        m,
+                                                        other.ok().flatten(),
+                                                    )
+                                                }
+                                                None => m,
+                                            };
+                                            resolved_uri = Some(m.uri);
+                                            break;
+                                        }
+                                        Ok(None) => {
+                                            // try next candidate ordering
+                                        }
+                                        Err(e) => {
+                                            log::warn!(
+                                            "{} {} {} metadata_search_failed track={} artist={} title={} error={}",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("RESOLVE"),
+                                            tp,
+                                            artist,
+                                            title,
+                                            e
+                                        );
+                                        }
+                                    }
                                 }
-                                // Persist cache with ISRC + resolved URI
-                                let pool = db_pool.clone();
-                                let local_path = tp.clone();
-                                let uri_clone = uri.clone();
-                                let isrc_for_cache = isrc.clone();
-                                let provider_name = provider.name().to_string();
-                                tokio::task::spawn_blocking(
-                                    move || -> Result<(), anyhow::Error> {
+
+                                if let Some(uri) = resolved_uri {
+                                    crate::metrics::inc(
+                                        "tracks_resolved_total",
+                                        &[("provider", provider.name())],
+                                    );
+                                    match act {
+                                        EventAction::Add => add_uris.push(uri.clone()),
+                                        EventAction::Remove => remove_uris.push(uri.clone()),
+                                        _ => {}
+                                    }
+                                    // attempt to lookup ISRC from provider; persist resolved uri + isrc into track_cache
+                                    let maybe_isrc =
+                                        learn_remote_isrc(db_pool, provider.as_ref(), &tp, &uri)
+                                            .await;
+                                    let pool = db_pool.clone();
+                                    let local_path = tp.clone();
+                                    let uri_clone = uri.clone();
+                                    let provider_name = provider.name().to_string();
+                                    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
                                         let conn = pool.get()?;
                                         let _ = crate::db::upsert_track_cache(
                                             &conn,
                                             &provider_name,
                                             &local_path,
-                                            Some(isrc_for_cache.as_str()),
+                                            maybe_isrc.as_deref(),
                                             Some(&uri_clone),
                                         )?;
                                         Ok(())
-                                    },
-                                )
-                                .await??;
-                                continue;
-                            }
-                            #[allow(non_snake_case)]
-                            Ok(None) => {
-                                // fall through to metadata-based search
+                                    })
+                                    .await??;
+                                } else {
+                                    crate::metrics::inc(
+                                        "tracks_unresolved_total",
+                                        &[("provider", provider.name())],
+                                    );
+                                    run_summary.lock().unwrap().unresolved += 1;
+                                    log::warn!(
+                                        "{} {} {} track_unresolved track={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("RESOLVE"),
+                                        tp
+                                    );
+                                    // record negative cache entry so we don't retry soon
+                                    let pool = db_pool.clone();
+                                    let local_path_for_cache = tp.clone();
+                                    let provider_name_for_cache = provider.name().to_string();
+                                    let maybe_isrc = isrc_for_lookup.clone();
+                                    tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+                                        let conn = pool.get()?;
+                                        let _ = crate::db::mark_track_unresolved(
+                                            &conn,
+                                            &provider_name_for_cache,
+                                            &local_path_for_cache,
+                                            maybe_isrc.as_deref(),
+                                        );
+                                        Ok(())
+                                    })
+                                    .await??;
+                                }
                             }
-                            Err(e) => {
-                                log::warn!(
-                                    "{} {} {} isrc_search_failed track={} error={}",
-                                    log_run_tag(&worker_id),
+                        }
+
+                        // Deduplicate URIs in add/remove lists before applying batches.
+                        // This avoids double-applying the same track when both
+                        // reconciliation and event-driven paths schedule an operation
+                        // for the same URI in a single run.
+                        if !add_uris.is_empty() {
+                            use std::collections::HashSet;
+                            let mut seen: HashSet<String> = HashSet::new();
+                            add_uris.retain(|u| seen.insert(u.clone()));
+                        }
+                        if !remove_uris.is_empty() {
+                            use std::collections::HashSet;
+                            let mut seen: HashSet<String> = HashSet::new();
+                            remove_uris.retain(|u| seen.insert(u.clone()));
+                            // Removing a URI strips every copy, so keep one that
+                            // another listed file maps to.  URIs also being added
+                            // stay in both lists: remove-then-add leaves one copy.
+                            let listed =
+                                uris_still_listed(cfg, db_pool, provider_name, playlist_name)
+                                    .await?;
+                            let before = remove_uris.len();
+                            remove_uris.retain(|u| !listed.contains(u) || add_uris.contains(u));
+                            if remove_uris.len() < before {
+                                log::info!(
+                                    "{} {} {} kept_shared_uris count={}",
+                                    log_run_tag(worker_id),
                                     pl_tag,
                                     log_phase_tag("RESOLVE"),
-                                    tp,
-                                    e
+                                    before - remove_uris.len()
                                 );
-                                // fall through to metadata-based search
                             }
                         }
-                    }
 
-                    // Fallback: derive artist/title from filename and do provider metadata search.
-                    // Strip any extension and try both "Artist - Title" and "Title - Artist" orders.
-                    let fname = std::path::Path::new(&tp)
-                        .file_name()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("");
-                    let stem = if let Some((base, _ext)) = fname.rsplit_once('.') {
-                        base
-                    } else {
-                        fname
-                    };
-
-                    let mut candidates: Vec<(&str, &str)> = Vec::new();
-                    if let Some((left, right)) = stem.split_once(" - ") {
-                        let left = left.trim();
-                        let right = right.trim();
-                        // First assume "Artist - Title"
-                        candidates.push((left, right));
-                        // Then try "Title - Artist" if the first fails
-                        candidates.push((right, left));
-                    } else {
-                        candidates.push(("", stem));
-                    }
-
-                    let mut resolved_uri: Option<String> = None;
-                    for (artist, raw_title) in candidates.into_iter() {
-                        // Normalize common duplicate suffixes like " copy 5"
-                        let mut title = raw_title.trim();
-                        let lower = title.to_ascii_lowercase();
-                        if let Some(idx) = lower.rfind(" copy ") {
-                            // Ensure suffix is exactly " copy <digits>"
-                            let suffix = &lower[idx + 6..];
-                            if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) {
-                                title = title[..idx].trim_end();
+                        // Snapshot add/remove sets before they are moved into apply_in_batches
+                        // so that we can update remote_playlist_contents_cache after successful
+                        // mutations.  Without this update a subsequent `worker --trust-cache` run
+                        // would compare desired URIs against the pre-mutation cached remote state
+                        // and re-schedule the same operations again.
+                        let snapshot_add_uris = add_uris.clone();
+                        let snapshot_remove_uris: std::collections::HashSet<String> =
+                            remove_uris.iter().cloned().collect();
+
+                        if dry_run {
+                            // Event-driven runs skip reconciliation, so fetch the remote
+                            // contents here to make the planned diff accurate.
+                            let remote_current = if remote_snapshot.is_some() {
+                                remote_snapshot.take()
+                            } else if dry_run_new_playlist {
+                                Some(Vec::new())
+                            } else {
+                                match provider.list_playlist_tracks(&remote_id).await {
+                                    Ok(v) => Some(v),
+                                    Err(e) => {
+                                        log::warn!(
+                                            "{} {} {} dry_run list_remote_failed error={}",
+                                            log_run_tag(worker_id),
+                                            pl_tag,
+                                            log_phase_tag("DRY_RUN"),
+                                            e
+                                        );
+                                        None
+                                    }
+                                }
+                            };
+                            let (planned_adds, planned_removes) = log_dry_run_plan(
+                                worker_id,
+                                &pl_tag,
+                                &remote_id,
+                                &remote_display_name,
+                                &add_uris,
+                                &remove_uris,
+                                remote_current.as_deref(),
+                            );
+                            {
+                                let mut summary = run_summary.lock().unwrap();
+                                summary.tracks_added += planned_adds;
+                                summary.tracks_removed += planned_removes;
+                            }
+                            if let Some((desired_order, remote_before)) = mirror_order.take() {
+                                let expected = expected_order_after_mutations(
+                                    remote_before,
+                                    &snapshot_remove_uris,
+                                    &snapshot_add_uris,
+                                );
+                                if expected != desired_order {
+                                    log::info!(
+                                        "{} {} {} dry_run would_reorder count={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("DRY_RUN"),
+                                        desired_order.len()
+                                    );
+                                }
                             }
+                            active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                            release_lock_async(db_pool, &lock_key, worker_id).await;
+                            return Ok((provider_ok, failure));
                         }
 
-                        if let Ok(result) = provider.search_track_uri(title, artist).await {
-                            if let Some(uri) = result {
-                                resolved_uri = Some(uri);
-                                break;
-                            } else {
-                                // try next candidate ordering
-                            }
-                        } else if let Err(e) = provider.search_track_uri(title, artist).await {
-                            log::warn!(
-                            "{} {} {} metadata_search_failed track={} artist={} title={} error={}",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("RESOLVE"),
-                            tp,
-                            artist,
-                            title,
-                            e
-                        );
+                        let provider_arc = provider.clone();
+                        let mut batches_ok = true;
+                        let remove_count = remove_uris.len();
+                        if let Err(e) = apply_in_batches(
+                            provider_arc.clone(),
+                            &mut remote_id,
+                            &playlist_name,
+                            &remote_display_name,
+                            remove_uris,
+                            false,
+                            None,
+                            cfg,
+                            worker_id,
+                            db_pool,
+                        )
+                        .await
+                        {
+                            log::error!(
+                                "{} {} {} apply_removes_failed error={}",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("BATCH_REM"),
+                                e
+                            );
+                            failure = Some(format!("apply_removes_failed: {}", e));
+                            provider_ok = false;
+                            batches_ok = false;
+                        } else {
+                            run_summary.lock().unwrap().tracks_removed += remove_count;
                         }
-                    }
 
-                    if let Some(uri) = resolved_uri {
-                        match act {
-                            EventAction::Add => add_uris.push(uri.clone()),
-                            EventAction::Remove => remove_uris.push(uri.clone()),
-                            _ => {}
+                        // Event-driven adds are inserted where the local `.m3u` has them
+                        // when the provider supports it and the order is kept, using the
+                        // stored remote snapshot as the current order; otherwise (or
+                        // without a snapshot) they are appended.
+                        let mut add_groups: Vec<(Option<usize>, Vec<String>)> = Vec::new();
+                        let mut positioned_order: Option<Vec<String>> = None;
+                        if !reconciled
+                            && batches_ok
+                            && !add_uris.is_empty()
+                            && cfg.playlist_order_mode != "append"
+                            && provider.capabilities().supports_positional_add
+                        {
+                            let snapshot = load_remote_snapshot(db_pool, provider_name, playlist_name)
+                                .await
+                                .filter(|s| s.remote_id == remote_id);
+                            if let Some(snapshot) = snapshot {
+                                match desired_remote_uris_for_playlist(
+                                    cfg,
+                                    playlist_name,
+                                    provider.clone(),
+                                    db_pool,
+                                    trust_cache,
+                                )
+                                .await
+                                {
+                                    Ok((desired, _)) => {
+                                        let remote: Vec<String> = snapshot
+                                            .uris
+                                            .into_iter()
+                                            .filter(|u| !snapshot_remove_uris.contains(u))
+                                            .collect();
+                                        let (groups, order) = positioned_adds(&desired, remote, &add_uris);
+                                        add_groups =
+                                            groups.into_iter().map(|(pos, uris)| (Some(pos), uris)).collect();
+                                        positioned_order = Some(order);
+                                    }
+                                    Err(e) => log::warn!(
+                                        "{} {} {} positional_add_skipped error={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("BATCH_ADD"),
+                                        e
+                                    ),
+                                }
+                            }
+                        }
+                        if add_groups.is_empty() {
+                            add_groups.push((None, add_uris));
                         }
-                        // attempt to lookup ISRC from provider; persist resolved uri + isrc into track_cache
-                        let pool = db_pool.clone();
-                        let local_path = tp.clone();
-                        let uri_clone = uri.clone();
-                        let provider_clone = provider.clone();
-                        let maybe_isrc = provider_clone
-                            .lookup_track_isrc(&uri_clone)
+                        let id_before_adds = remote_id.clone();
+                        for (insert_at, uris) in add_groups {
+                            // A playlist recreated mid-run is empty, so stop inserting.
+                            let insert_at = insert_at.filter(|_| remote_id == id_before_adds);
+                            let add_count = uris.len();
+                            if let Err(e) = apply_in_batches(
+                                provider_arc.clone(),
+                                &mut remote_id,
+                                &playlist_name,
+                                &remote_display_name,
+                                uris,
+                                true,
+                                insert_at,
+                                cfg,
+                                worker_id,
+                                db_pool,
+                            )
                             .await
-                            .unwrap_or(None);
-                        let provider_name = provider.name().to_string();
-                        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                            let conn = pool.get()?;
-                            let _ = crate::db::upsert_track_cache(
-                                &conn,
-                                &provider_name,
-                                &local_path,
-                                maybe_isrc.as_deref(),
-                                Some(&uri_clone),
-                            )?;
-                            Ok(())
-                        })
-                        .await??;
-                    } else {
-                        log::warn!(
-                            "{} {} {} track_unresolved track={}",
-                            log_run_tag(&worker_id),
-                            pl_tag,
-                            log_phase_tag("RESOLVE"),
-                            tp
-                        );
-                        // record negative cache entry so we don't retry soon
-                        let pool = db_pool.clone();
-                        let local_path_for_cache = tp.clone();
-                        let provider_name_for_cache = provider.name().to_string();
-                        let maybe_isrc = cached.as_ref().and_then(|(i, _, _)| i.clone());
-                        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                            let conn = pool.get()?;
-                            let _ = crate::db::upsert_track_cache(
-                                &conn,
-                                &provider_name_for_cache,
-                                &local_path_for_cache,
-                                maybe_isrc.as_deref(),
-                                None,
-                            );
-                            Ok(())
-                        })
-                        .await??;
-                    }
-                }
-            }
+                            {
+                                log::error!(
+                                    "{} {} {} apply_adds_failed error={}",
+                                    log_run_tag(worker_id),
+                                    pl_tag,
+                                    log_phase_tag("BATCH_ADD"),
+                                    e
+                                );
+                                failure = Some(format!("apply_adds_failed: {}", e));
+                                provider_ok = false;
+                                batches_ok = false;
+                                break;
+                            }
+                            run_summary.lock().unwrap().tracks_added += add_count;
+                        }
 
-            // Deduplicate URIs in add/remove lists before applying batches.
-            // This avoids double-applying the same track when both
-            // reconciliation and event-driven paths schedule an operation
-            // for the same URI in a single run.
-            if !add_uris.is_empty() {
-                use std::collections::HashSet;
-                let mut seen: HashSet<String> = HashSet::new();
-                add_uris.retain(|u| seen.insert(u.clone()));
-            }
-            if !remove_uris.is_empty() {
-                use std::collections::HashSet;
-                let mut seen: HashSet<String> = HashSet::new();
-                remove_uris.retain(|u| seen.insert(u.clone()));
-            }
+                        // In "mirror" mode, reorder the remote playlist so it follows the
+                        // local `.m3u` sequence.  Adds are appended by the providers, so the
+                        // expected post-mutation order is the old remote order minus removes
+                        // plus adds; skip the reorder when that already matches.
+                        // Remote order known after the mutations (positional adds or a reorder).
+                        let mut reordered: Option<Vec<String>> = positioned_order;
+                        if batches_ok {
+                            if let Some((desired_order, remote_before)) = mirror_order.take() {
+                                let expected = expected_order_after_mutations(
+                                    remote_before,
+                                    &snapshot_remove_uris,
+                                    &snapshot_add_uris,
+                                );
+                                if expected != desired_order {
+                                    log::info!(
+                                        "{} {} {} reorder_tracks count={}",
+                                        log_run_tag(worker_id),
+                                        pl_tag,
+                                        log_phase_tag("REORDER"),
+                                        desired_order.len()
+                                    );
+                                    match provider.reorder_tracks(&remote_id, &desired_order).await {
+                                        Ok(()) => reordered = Some(desired_order),
+                                        Err(e) => {
+                                            log::error!(
+                                                "{} {} {} reorder_tracks_failed error={}",
+                                                log_run_tag(worker_id),
+                                                pl_tag,
+                                                log_phase_tag("REORDER"),
+                                                e
+                                            );
+                                            failure = Some(format!("reorder_tracks_failed: {}", e));
+                                            provider_ok = false;
+                                        }
+                                    }
+                                }
+                            }
+                        }
 
-            // Snapshot add/remove sets before they are moved into apply_in_batches
-            // so that we can update remote_playlist_contents_cache after successful
-            // mutations.  Without this update a subsequent `worker --trust-cache` run
-            // would compare desired URIs against the pre-mutation cached remote state
-            // and re-schedule the same operations again.
-            let snapshot_add_uris = add_uris.clone();
-            let snapshot_remove_uris: std::collections::HashSet<String> =
-                remove_uris.iter().cloned().collect();
-
-            let provider_arc = provider.clone();
-            let mut batches_ok = true;
-            if let Err(e) = apply_in_batches(
-                provider_arc.clone(),
-                &mut remote_id,
-                &playlist_name,
-                &remote_display_name,
-                remove_uris,
-                false,
-                cfg,
-                &worker_id,
-                &db_pool,
-            )
-            .await
-            {
-                log::error!(
-                    "{} {} {} apply_removes_failed error={}",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("BATCH_REM"),
-                    e
-                );
-                all_providers_ok = false;
-                batches_ok = false;
-            }
-            if let Err(e) = apply_in_batches(
-                provider_arc.clone(),
-                &mut remote_id,
-                &playlist_name,
-                &remote_display_name,
-                add_uris,
-                true,
-                cfg,
-                &worker_id,
-                &db_pool,
-            )
-            .await
-            {
-                log::error!(
-                    "{} {} {} apply_adds_failed error={}",
-                    log_run_tag(&worker_id),
-                    pl_tag,
-                    log_phase_tag("BATCH_ADD"),
-                    e
-                );
-                all_providers_ok = false;
-                batches_ok = false;
-            }
+                        // Keep the remote snapshot in step with our own changes (so the
+                        // next reconcile needs no listing); after any failure, or when the
+                        // playlist was recreated mid-run, drop it to force a relist.
+                        let snapshot_base = match snapshot_base.take() {
+                            Some(base) => Some(base),
+                            None => load_remote_snapshot(db_pool, provider_name, playlist_name)
+                                .await
+                                .map(|s| (s.remote_id, s.uris, s.listed_at)),
+                        };
+                        match snapshot_base {
+                            Some((base_id, base_uris, listed_at))
+                                if provider_ok && batches_ok && base_id == remote_id =>
+                            {
+                                let after = reordered.clone().unwrap_or_else(|| {
+                                    expected_order_after_mutations(
+                                        base_uris,
+                                        &snapshot_remove_uris,
+                                        &snapshot_add_uris,
+                                    )
+                                });
+                                store_remote_snapshot(
+                                    db_pool,
+                                    provider_name,
+                                    playlist_name,
+                                    Some((&remote_id, after, listed_at)),
+                                )
+                                .await;
+                            }
+                            Some(_) => {
+                                store_remote_snapshot(db_pool, provider_name, playlist_name, None).await
+                            }
+                            None => {}
+                        }
 
-            // After successful mutations, update remote_playlist_contents_cache by
-            // applying the add/remove delta to the cached pre-mutation snapshot.
-            // This ensures a subsequent `--trust-cache` run sees the correct remote
-            // state and does not re-apply the same mutations.
-            if batches_ok && (!snapshot_add_uris.is_empty() || !snapshot_remove_uris.is_empty()) {
-                let pool = db_pool.clone();
-                let prov = provider_name.clone();
-                let pl = playlist_name.clone();
-                let rid = remote_id.clone();
-                let _ = tokio::task::spawn_blocking(move || {
-                    if let Ok(conn) = pool.get() {
-                        if let Ok(Some((cached_rid, uris_json))) =
-                            db::get_remote_playlist_contents_cache(&conn, &prov, &pl)
+                        // After successful mutations, update remote_playlist_contents_cache by
+                        // applying the add/remove delta to the cached pre-mutation snapshot.
+                        // This ensures a subsequent `--trust-cache` run sees the correct remote
+                        // state and does not re-apply the same mutations.
+                        if batches_ok
+                            && (!snapshot_add_uris.is_empty()
+                                || !snapshot_remove_uris.is_empty()
+                                || reordered.is_some())
                         {
-                            if cached_rid == rid {
-                                let mut current: Vec<String> =
-                                    serde_json::from_str(&uris_json).unwrap_or_default();
-                                current.retain(|u| !snapshot_remove_uris.contains(u));
-                                for u in &snapshot_add_uris {
-                                    if !current.contains(u) {
-                                        current.push(u.clone());
+                            let pool = db_pool.clone();
+                            let prov = provider_name.clone();
+                            let pl = playlist_name.clone();
+                            let rid = remote_id.clone();
+                            let _ = tokio::task::spawn_blocking(move || {
+                                if let Ok(conn) = pool.get() {
+                                    if let Ok(Some((cached_rid, uris_json))) =
+                                        db::get_remote_playlist_contents_cache(&conn, &prov, &pl)
+                                    {
+                                        if cached_rid == rid {
+                                            let mut current: Vec<String> =
+                                                serde_json::from_str(&uris_json).unwrap_or_default();
+                                            current.retain(|u| !snapshot_remove_uris.contains(u));
+                                            for u in &snapshot_add_uris {
+                                                if !current.contains(u) {
+                                                    current.push(u.clone());
+                                                }
+                                            }
+                                            if let Some(order) = reordered {
+                                                current = order;
+                                            }
+                                            if let Ok(json) = serde_json::to_string(&current) {
+                                                let _ = db::upsert_remote_playlist_contents_cache(
+                                                    &conn, &prov, &pl, &rid, &json,
+                                                );
+                                            }
+                                        }
                                     }
                                 }
-                                if let Ok(json) = serde_json::to_string(&current) {
-                                    let _ = db::upsert_remote_playlist_contents_cache(
-                                        &conn, &prov, &pl, &rid, &json,
-                                    );
-                                }
+                            })
+                            .await;
+                        }
+
+                        // release lock
+                        active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                        release_lock_async(db_pool, &lock_key, worker_id).await;
+
+                        log::info!(
+                            "{} {} {} provider_done",
+                            log_run_tag(worker_id),
+                            pl_tag,
+                            log_phase_tag("FINALIZE"),
+                        );
+                        Ok((provider_ok, failure))
+                    }
+                    .await;
+                    match result {
+                        Ok(outcome) => outcome,
+                        Err(e) => {
+                            log::error!(
+                                "{} {} {} provider_failed error={:#}",
+                                log_run_tag(worker_id),
+                                pl_tag,
+                                log_phase_tag("FINALIZE"),
+                                e
+                            );
+                            // The remote may be half-changed: relist it next time.
+                            if !dry_run {
+                                store_remote_snapshot(db_pool, provider_name, playlist_name, None).await;
                             }
+                            // The lock is only released if this worker holds it.
+                            active_locks.lock().unwrap().retain(|n| n != &lock_key);
+                            release_lock_async(db_pool, &lock_key, worker_id).await;
+                            (false, Some(format!("{:#}", e)))
                         }
                     }
-                })
-                .await;
-            }
-
-            // release lock
-            active_locks.lock().unwrap().retain(|n| n != playlist_name);
-            release_lock_async(&db_pool, playlist_name, &worker_id).await;
+                }
+            },
+        ))
+        .await;
 
-            log::info!(
-                "{} {} {} provider_done",
-                log_run_tag(&worker_id),
-                pl_tag,
-                log_phase_tag("FINALIZE"),
-            );
+        let mut all_providers_ok = true;
+        // Most recent provider error for this playlist; counts as a failed
+        // attempt for its events (lock contention does not).
+        let mut failure: Option<String> = None;
+        for ((provider_name, _), (provider_ok, provider_failure)) in providers.iter().zip(outcomes)
+        {
+            all_providers_ok &= provider_ok;
+            if provider_failure.is_some() {
+                *run_summary
+                    .lock()
+                    .unwrap()
+                    .errors
+                    .entry(provider_name.clone())
+                    .or_default() += 1;
+                failure = provider_failure;
+            }
         }
 
         // Mark events as synced only when ALL providers succeeded.  If any
         // provider failed we leave the events unsynced so the next worker
         // cycle will retry them.
-        if all_providers_ok {
+        if dry_run {
+            log::info!(
+                "{} dry_run events_left_unsynced count={} playlist={:?}",
+                log_run_tag(worker_id),
+                original_ids.len(),
+                playlist_name
+            );
+        } else if all_providers_ok {
             mark_events_synced_async(db_pool.clone(), original_ids.clone()).await?;
             log::info!(
                 "{} events_synced count={} playlist={:?}",
-                log_run_tag(&worker_id),
+                log_run_tag(worker_id),
                 original_ids.len(),
                 playlist_name
             );
+        } else if let Some(err) = failure {
+            crate::metrics::inc("sync_errors_total", &[]);
+            let ids = original_ids.clone();
+            let max_attempts = cfg.max_retries_on_error;
+            let newly_failed = tokio::task::spawn_blocking({
+                let pool = db_pool.clone();
+                move || -> Result<usize> {
+                    let mut conn = pool.get()?;
+                    db::record_event_failure(&mut conn, &ids, &err, max_attempts)
+                }
+            })
+            .await??;
+            if newly_failed > 0 {
+                log::error!(
+                    "{} events_failed count={} playlist={:?} (gave up after {} attempts; see `queue-failed`)",
+                    log_run_tag(worker_id),
+                    newly_failed,
+                    playlist_name,
+                    max_attempts
+                );
+            } else {
+                log::warn!(
+                    "{} events_NOT_synced count={} playlist={:?} (some providers failed, will retry)",
+                    log_run_tag(worker_id),
+                    original_ids.len(),
+                    playlist_name
+                );
+            }
         } else {
             log::warn!(
-                "{} events_NOT_synced count={} playlist={:?} (some providers failed, will retry)",
-                log_run_tag(&worker_id),
+                "{} events_NOT_synced count={} playlist={:?} (playlist locked, will retry)",
+                log_run_tag(worker_id),
                 original_ids.len(),
                 playlist_name
             );
         }
     }
-    Ok(())
+    Ok(run_summary.into_inner().unwrap())
 }
 
-/// Nightly reconciliation: scan the root folder, write playlists for every folder,
+/// Nightly reconciliation: scan every root folder, write playlists for every folder,
 /// and enqueue a `Create` event so the worker will reconcile remote playlists later.
 pub fn run_nightly_reconcile(cfg: &Config) -> Result<()> {
+    run_reconcile_scan(cfg, false)
+}
+
+/// `sync_state` key holding the unix time the last reconcile scan started.
+const LAST_RECONCILE_KEY: &str = "last_reconcile_ts";
+
+/// Whether a track directly in the folder was modified at or after `since`
+/// (unreadable metadata counts as modified).
+fn folder_tracks_modified_since(node: &crate::watcher::FolderNode, since: i64) -> bool {
+    node.tracks.iter().any(|track| {
+        std::fs::metadata(track)
+            .and_then(|md| md.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .is_none_or(|d| d.as_secs() as i64 >= since)
+    })
+}
+
+/// Scan the roots, rewrite every local playlist and enqueue a `Create` per
+/// folder (see [`run_nightly_reconcile`]).
+///
+/// With `changed_only`, the `Create` is only enqueued for folders that
+/// changed since the previous scan started: a track was modified, the
+/// rewritten playlist lists different entries (tracks added, removed or
+/// renamed), or no provider has the playlist mapped yet.  Without a recorded
+/// previous scan every folder is reconciled.
+pub fn run_reconcile_scan(cfg: &Config, changed_only: bool) -> Result<()> {
+    let started_at = Utc::now().timestamp();
+    let roots = cfg.roots();
     log::info!(
-        "Starting nightly reconcile over root folder {:?}",
-        cfg.root_folder
+        "Starting {} reconcile over root folder(s) {:?}",
+        if changed_only {
+            "changed-only"
+        } else {
+            "nightly"
+        },
+        roots
     );
 
     let remote_whitelist = cfg.effective_remote_whitelist();
-    let tree = crate::watcher::InMemoryTree::build(
-        &cfg.root_folder,
+    let tree = crate::watcher::InMemoryTree::build_with_ignore(
+        &roots,
         if remote_whitelist.is_empty() {
             None
         } else {
             Some(remote_whitelist)
         },
         Some(&cfg.file_extensions),
+        cfg.ignore_matcher(),
     )?;
-    // collect thread handles for the enqueue operations so we can join before returning
     let db_pool = db::create_pool(&cfg.db_path)?;
-    let mut handles: Vec<std::thread::JoinHandle<()>> = Vec::new();
-    for (folder, _node) in tree.nodes.iter() {
+    let since: Option<i64> = if changed_only {
+        let last =
+            db::get_sync_state(&*db_pool.get()?, LAST_RECONCILE_KEY)?.and_then(|v| v.parse().ok());
+        if last.is_none() {
+            log::info!("No previous reconcile recorded; reconciling every folder");
+        }
+        last
+    } else {
+        None
+    };
+    // Folders are independent: write their playlists and enqueue their
+    // events `playlist_write_threads` at a time.
+    let folders: Vec<_> = tree.nodes.iter().collect();
+    let unchanged = AtomicUsize::new(0);
+    let first_error: std::sync::Mutex<Option<anyhow::Error>> = std::sync::Mutex::new(None);
+    let reconcile_folder = |folder: &std::path::PathBuf,
+                            node: &crate::watcher::FolderNode|
+     -> Result<()> {
         // Extra safety: respect the folder whitelist again here before
         // writing playlists and enqueueing events, so reconciliation
         // never touches non-whitelisted folders even if they slipped
@@ -2305,14 +4089,14 @@ pub fn run_nightly_reconcile(cfg: &Config) -> Result<()> {
         if let Some(ref wlvec) = tree.whitelist {
             let path_str = folder.to_string_lossy();
             if !wlvec.iter().any(|re| re.is_match(&path_str)) {
-                continue;
+                return Ok(());
             }
         }
-        let folder_name = folder.file_name().and_then(|s| s.to_str()).unwrap_or("");
-        let rel = folder
-            .strip_prefix(&cfg.root_folder)
-            .unwrap_or(folder)
-            .to_path_buf();
+        let folder_name = folder
+            .file_name()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default();
+        let rel = cfg.root_relative(folder);
 
         let path_to_parent = rel
             .parent()
@@ -2328,73 +4112,106 @@ pub fn run_nightly_reconcile(cfg: &Config) -> Result<()> {
             s
         };
 
-        let playlist_name = crate::util::expand_template(
-            &cfg.local_playlist_template,
-            folder_name,
-            &path_to_parent_str,
-        );
+        let playlist_name = cfg.local_playlist_file_name(folder, &folder_name, &path_to_parent_str);
         let playlist_path = folder.join(&playlist_name);
+        let entries_before =
+            since.map(|_| crate::playlist::read_playlist_entries(&playlist_path).ok());
 
         if cfg.playlist_mode == "flat" {
-            if let Err(e) = crate::playlist::write_flat_playlist(
+            if let Err(e) = crate::playlist::write_flat_playlist_as(
+                &cfg.playlist_format,
                 folder,
                 &playlist_path,
                 &cfg.playlist_order_mode,
                 &cfg.file_extensions,
+                &tree.ignore,
             ) {
                 log::warn!("Failed to write playlist {:?}: {}", playlist_path, e);
             }
+        } else if cfg.playlist_mode == "linked_with_local_tracks" {
+            if let Err(e) = crate::playlist::write_linked_playlist_with_local_tracks(
+                folder,
+                &playlist_path,
+                &cfg.linked_reference_format,
+                &cfg.local_playlist_template,
+                &cfg.playlist_order_mode,
+                &cfg.file_extensions,
+                &tree.ignore,
+            ) {
+                log::warn!("Failed to write linked playlist {:?}: {}", playlist_path, e);
+            }
         } else {
-            if let Err(e) = crate::playlist::write_linked_playlist(
+            if let Err(e) = crate::playlist::write_linked_playlist_filtered(
                 folder,
                 &playlist_path,
                 &cfg.linked_reference_format,
                 &cfg.local_playlist_template,
+                &cfg.file_extensions,
+                &tree.ignore,
             ) {
                 log::warn!("Failed to write linked playlist {:?}: {}", playlist_path, e);
             }
         }
 
-        // enqueue Create event in a background thread but keep the handle to join
-        let pname = rel.display().to_string();
-        let pool = db_pool.clone();
-        let h = std::thread::spawn(move || {
-            if let Ok(conn) = pool.get() {
-                if let Err(e) = crate::db::enqueue_event(
-                    &conn,
-                    &pname,
-                    &crate::models::EventAction::Create,
-                    None,
-                    None,
-                ) {
-                    log::warn!(
-                        "Failed to enqueue nightly create event for {}: {}",
-                        pname,
-                        e
-                    );
-                }
-            } else {
-                log::warn!("Failed to get pooled connection to enqueue nightly event");
+        if cfg.is_local_only(folder) {
+            return Ok(());
+        }
+        let pname = cfg.playlist_key_for_folder(folder);
+        if let (Some(since), Some(before)) = (since, entries_before) {
+            let entries_changed = before.is_none()
+                || before != crate::playlist::read_playlist_entries(&playlist_path).ok();
+            if !entries_changed
+                && !folder_tracks_modified_since(node, since)
+                && db::is_playlist_mapped(&*db_pool.get()?, &pname)?
+            {
+                unchanged.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
             }
-        });
-        handles.push(h);
+        }
+        if let Err(e) = db::enqueue_event(
+            &*db_pool.get()?,
+            &pname,
+            &crate::models::EventAction::Create,
+            None,
+            None,
+        ) {
+            log::warn!(
+                "Failed to enqueue nightly create event for {}: {}",
+                pname,
+                e
+            );
+        }
+        Ok(())
+    };
+    crate::util::for_each_parallel(&folders, cfg.playlist_write_threads, |(folder, node)| {
+        if let Err(e) = reconcile_folder(folder, node) {
+            log::warn!("Reconcile of {:?} failed: {:#}", folder, e);
+            first_error.lock().unwrap().get_or_insert(e);
+        }
+    });
+    if let Some(e) = first_error.into_inner().unwrap() {
+        return Err(e);
     }
+    let unchanged = unchanged.into_inner();
 
-    // join all enqueue threads to ensure events are persisted before returning
-    for h in handles {
-        let _ = h.join();
+    db::set_sync_state(
+        &*db_pool.get()?,
+        LAST_RECONCILE_KEY,
+        &started_at.to_string(),
+    )?;
+    if changed_only {
+        log::info!(
+            "Changed-only reconcile skipped {} unchanged folder(s)",
+            unchanged
+        );
     }
-
-    log::info!(
-        "Nightly reconcile completed for root folder {:?}",
-        cfg.root_folder
-    );
+    log::info!("Nightly reconcile completed for root folder(s) {:?}", roots);
 
     Ok(())
 }
 
 /// Scan the local `playlist_map` DB table and delete any entries whose
-/// corresponding local folder no longer exists under `cfg.root_folder`.
+/// corresponding local folder no longer exists under any configured root.
 ///
 /// For each stale entry:
 ///  – The remote playlist is deleted via the provider (if the provider is
@@ -2438,7 +4255,7 @@ pub async fn purge_deleted_playlists(cfg: &Config) -> Result<()> {
         }
     })
     .await??;
-    if has_spotify {
+    if use_provider(cfg, "spotify", has_spotify) {
         providers.insert(
             "spotify".to_string(),
             Arc::new(SpotifyProvider::new(
@@ -2458,7 +4275,7 @@ pub async fn purge_deleted_playlists(cfg: &Config) -> Result<()> {
         }
     })
     .await??;
-    if has_tidal {
+    if use_provider(cfg, "tidal", has_tidal) {
         providers.insert(
             "tidal".to_string(),
             Arc::new(TidalProvider::new(
@@ -2475,11 +4292,101 @@ pub async fn purge_deleted_playlists(cfg: &Config) -> Result<()> {
         );
     }
 
+    let has_ytmusic = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get()?;
+            Ok(db::load_credential_with_client(&conn, "ytmusic")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "ytmusic", has_ytmusic) {
+        providers.insert(
+            "ytmusic".to_string(),
+            Arc::new(YtMusicProvider::new(
+                String::new(),
+                String::new(),
+                cfg.db_path.clone(),
+                cfg.clone(),
+            )),
+        );
+    }
+
+    let has_subsonic = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get()?;
+            Ok(db::load_credential_with_client(&conn, "subsonic")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "subsonic", has_subsonic) {
+        providers.insert(
+            "subsonic".to_string(),
+            Arc::new(SubsonicProvider::new(cfg.db_path.clone(), cfg.clone())),
+        );
+    }
+
+    let has_deezer = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get()?;
+            Ok(db::load_credential_with_client(&conn, "deezer")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "deezer", has_deezer) {
+        providers.insert(
+            "deezer".to_string(),
+            Arc::new(DeezerProvider::new(cfg.db_path.clone(), cfg.clone())),
+        );
+    }
+
+    let has_apple_music = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get()?;
+            Ok(db::load_credential_with_client(&conn, "applemusic")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "applemusic", has_apple_music) {
+        providers.insert(
+            "applemusic".to_string(),
+            Arc::new(AppleMusicProvider::new(cfg.db_path.clone(), cfg.clone())),
+        );
+    }
+
+    let has_plex = tokio::task::spawn_blocking({
+        let pool = db_pool.clone();
+        move || -> Result<bool, anyhow::Error> {
+            let conn = pool.get()?;
+            Ok(db::load_credential_with_client(&conn, "plex")?.is_some())
+        }
+    })
+    .await??;
+    if use_provider(cfg, "plex", has_plex) {
+        providers.insert(
+            "plex".to_string(),
+            Arc::new(PlexProvider::new(cfg.db_path.clone(), cfg.clone())),
+        );
+    }
+    if use_provider(
+        cfg,
+        "filesystem",
+        !cfg.filesystem_export_dir.as_os_str().is_empty(),
+    ) {
+        providers.insert(
+            "filesystem".to_string(),
+            Arc::new(FileSystemProvider::new(cfg.clone())),
+        );
+    }
+
     let mut purged = 0usize;
     let mut skipped = 0usize;
 
     for (provider_name, playlist_name, remote_id) in &entries {
-        let local_folder = cfg.root_folder.join(playlist_name);
+        let local_folder = cfg.playlist_folder_for_key(playlist_name);
         if local_folder.is_dir() {
             continue; // still exists locally, nothing to do
         }
@@ -2498,8 +4405,14 @@ pub async fn purge_deleted_playlists(cfg: &Config) -> Result<()> {
                     match provider.delete_playlist(rid).await {
                         Ok(()) => {
                             log::info!(
-                                "purge_deleted_playlists: deleted remote playlist provider={} id={}",
-                                provider_name, rid
+                                "purge_deleted_playlists: {} remote playlist provider={} id={}",
+                                if provider.capabilities().hard_delete {
+                                    "deleted"
+                                } else {
+                                    "unfollowed"
+                                },
+                                provider_name,
+                                rid
                             );
                         }
                         Err(e) => {
@@ -2559,18 +4472,19 @@ pub async fn purge_deleted_playlists(cfg: &Config) -> Result<()> {
 /// folder that lives under `cfg.root_folder`.  The function:
 ///  1. Writes the local `.m3u` playlist for that folder (same logic as the nightly scan).
 ///  2. Enqueues a `Create` event for the playlist key.
-///  3. Runs the event-processing worker restricted to `provider_name`.
+///  3. Runs the event-processing worker restricted to `provider_name`,
+///     returning its summary.
 pub async fn reconcile_single_playlist(
     cfg: &Config,
     playlist_folder: &std::path::Path,
     provider_name: Option<&str>,
     trust_cache: bool,
-) -> Result<()> {
+) -> Result<WorkerRunSummary> {
     // Resolve the target folder to an absolute canonical path.
     let folder = if playlist_folder.is_absolute() {
         playlist_folder.to_path_buf()
     } else {
-        cfg.root_folder.join(playlist_folder)
+        cfg.primary_root().join(playlist_folder)
     };
     let folder = folder
         .canonicalize()
@@ -2584,17 +4498,34 @@ pub async fn reconcile_single_playlist(
         folder
     };
 
-    // Compute the relative path (playlist key) from root_folder.
-    let root = cfg
-        .root_folder
-        .canonicalize()
-        .with_context(|| format!("cannot canonicalize root_folder {:?}", cfg.root_folder))?;
-    let rel = folder
-        .strip_prefix(&root)
-        .with_context(|| format!("path {:?} is not under root_folder {:?}", folder, root))?
-        .to_path_buf();
+    // Find the (canonicalized) root containing the folder; the path relative
+    // to it drives the local template and the playlist key.
+    let mut containing: Option<(std::path::PathBuf, std::path::PathBuf)> = None;
+    for root in cfg.roots() {
+        let canonical = root
+            .canonicalize()
+            .with_context(|| format!("cannot canonicalize root folder {:?}", root))?;
+        if let Ok(rel) = folder.strip_prefix(&canonical) {
+            let deeper_root = containing
+                .as_ref()
+                .is_none_or(|(_, best)| rel.components().count() < best.components().count());
+            if deeper_root {
+                containing = Some((root, rel.to_path_buf()));
+            }
+        }
+    }
+    let (root, rel) = containing.with_context(|| {
+        format!(
+            "path {:?} is not under any root folder {:?}",
+            folder,
+            cfg.roots()
+        )
+    })?;
 
-    let folder_name = folder.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    let folder_name = folder
+        .file_name()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
     let path_to_parent = rel
         .parent()
         .map(|p| p.to_path_buf())
@@ -2609,11 +4540,8 @@ pub async fn reconcile_single_playlist(
         s
     };
 
-    let playlist_file_name = crate::util::expand_template(
-        &cfg.local_playlist_template,
-        folder_name,
-        &path_to_parent_str,
-    );
+    let playlist_file_name =
+        cfg.local_playlist_file_name(&folder, &folder_name, &path_to_parent_str);
     let playlist_file_path = folder.join(&playlist_file_name);
 
     log::info!(
@@ -2623,26 +4551,42 @@ pub async fn reconcile_single_playlist(
     );
 
     // Write the local m3u playlist.
+    let ignore = cfg.ignore_matcher();
     if cfg.playlist_mode == "flat" {
-        crate::playlist::write_flat_playlist(
+        crate::playlist::write_flat_playlist_as(
+            &cfg.playlist_format,
             &folder,
             &playlist_file_path,
             &cfg.playlist_order_mode,
             &cfg.file_extensions,
+            &ignore,
         )
         .with_context(|| format!("writing flat playlist {:?}", playlist_file_path))?;
+    } else if cfg.playlist_mode == "linked_with_local_tracks" {
+        crate::playlist::write_linked_playlist_with_local_tracks(
+            &folder,
+            &playlist_file_path,
+            &cfg.linked_reference_format,
+            &cfg.local_playlist_template,
+            &cfg.playlist_order_mode,
+            &cfg.file_extensions,
+            &ignore,
+        )
+        .with_context(|| format!("writing linked playlist {:?}", playlist_file_path))?;
     } else {
-        crate::playlist::write_linked_playlist(
+        crate::playlist::write_linked_playlist_filtered(
             &folder,
             &playlist_file_path,
             &cfg.linked_reference_format,
             &cfg.local_playlist_template,
+            &cfg.file_extensions,
+            &ignore,
         )
         .with_context(|| format!("writing linked playlist {:?}", playlist_file_path))?;
     }
 
     // Enqueue a Create event so the worker has something to process.
-    let pname = rel.display().to_string();
+    let pname = cfg.playlist_key_for_folder(&root.join(&rel));
     let db_pool = db::create_pool(&cfg.db_path)?;
     {
         let pool = db_pool.clone();
@@ -2666,5 +4610,252 @@ pub async fn reconcile_single_playlist(
     );
 
     // Process the queue restricted to the requested provider (or all providers).
-    run_worker_once(cfg, provider_name, trust_cache).await
+    run_worker_once(cfg, provider_name, trust_cache, false).await
+}
+
+/// Outcome of [`import_playlist`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Logical playlist key the remote playlist was mapped to.
+    pub playlist_name: String,
+    /// Local files paired with a remote track that has the same ISRC.
+    pub matched_by_isrc: usize,
+    /// Local files paired with the remote track at the same position.
+    pub matched_by_order: usize,
+    /// Local files left for the worker to resolve.
+    pub unmatched_local: usize,
+    /// Remote tracks no local file was paired with.
+    pub unmatched_remote: usize,
+}
+
+/// Adopt an existing remote playlist for a local playlist folder.
+///
+/// Maps the folder's playlist key to `remote_id` in `playlist_map` and seeds
+/// `track_cache` by pairing the entries of the folder's local playlist file
+/// with the tracks of the remote playlist: first by ISRC (local tags against
+/// `Provider::lookup_track_isrc`), then the remaining entries by position
+/// when both sides have the same number of them left.  Unpaired files are
+/// resolved by the worker as usual on the next sync.
+pub async fn import_playlist(
+    cfg: &Config,
+    provider: Arc<dyn Provider>,
+    playlist_folder: &std::path::Path,
+    remote_id: &str,
+) -> Result<ImportSummary> {
+    let folder = if playlist_folder.is_absolute() {
+        playlist_folder.to_path_buf()
+    } else {
+        cfg.primary_root().join(playlist_folder)
+    };
+    if cfg.root_for_path(&folder).is_none() {
+        anyhow::bail!(
+            "path {:?} is not under any root folder {:?}",
+            folder,
+            cfg.roots()
+        );
+    }
+    let playlist_name = cfg.playlist_key_for_folder(&folder);
+    // Key local files the way the worker does, from the key's folder.
+    let folder = cfg.playlist_folder_for_key(&playlist_name);
+    let playlist_path = local_playlist_path(cfg, &playlist_name);
+    let entries = crate::playlist::read_playlist_entries(&playlist_path)
+        .with_context(|| format!("reading local playlist {:?}", playlist_path))?;
+
+    let local: Vec<(String, Option<String>)> = entries
+        .iter()
+        .map(|line| crate::util::resolve_lossy_path(&folder.join(line)))
+        .filter(|p| p.exists())
+        .map(|p| {
+            let isrc = crate::util::extract_isrc_from_path(&p);
+            (p.to_string_lossy().to_string(), isrc)
+        })
+        .collect();
+    let remote = provider
+        .list_playlist_tracks(remote_id)
+        .await
+        .with_context(|| format!("listing remote playlist {}", remote_id))?;
+
+    let mut pairs: Vec<Option<usize>> = vec![None; local.len()];
+    let mut remote_taken = vec![false; remote.len()];
+    let mut remote_isrcs: Vec<Option<String>> = vec![None; remote.len()];
+    if local.iter().any(|(_, isrc)| isrc.is_some()) {
+        for (i, uri) in remote.iter().enumerate() {
+            remote_isrcs[i] = provider
+                .lookup_track_isrc(uri)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|isrc| crate::util::normalize_isrc(&isrc));
+        }
+    }
+    let mut summary = ImportSummary {
+        playlist_name: playlist_name.clone(),
+        ..Default::default()
+    };
+    for (li, (_, isrc)) in local.iter().enumerate() {
+        let Some(isrc) = isrc else { continue };
+        let hit = (0..remote.len())
+            .find(|&ri| !remote_taken[ri] && remote_isrcs[ri].as_deref() == Some(isrc.as_str()));
+        if let Some(ri) = hit {
+            pairs[li] = Some(ri);
+            remote_taken[ri] = true;
+            summary.matched_by_isrc += 1;
+        }
+    }
+    let local_left: Vec<usize> = (0..local.len()).filter(|&li| pairs[li].is_none()).collect();
+    let remote_left: Vec<usize> = (0..remote.len()).filter(|&ri| !remote_taken[ri]).collect();
+    if local_left.len() == remote_left.len() {
+        for (&li, &ri) in local_left.iter().zip(remote_left.iter()) {
+            pairs[li] = Some(ri);
+            summary.matched_by_order += 1;
+        }
+    } else {
+        summary.unmatched_local = local_left.len();
+        summary.unmatched_remote = remote_left.len();
+        log::warn!(
+            "import_playlist: {} local and {} remote tracks of \"{}\" could not be paired by ISRC; not pairing them by position",
+            local_left.len(),
+            remote_left.len(),
+            playlist_name
+        );
+    }
+
+    let conn = db::open_or_create(&cfg.db_path)?;
+    for (li, ri) in pairs.iter().enumerate() {
+        let Some(ri) = ri else { continue };
+        let (local_path, isrc) = &local[li];
+        let isrc = isrc.as_deref().or(remote_isrcs[*ri].as_deref());
+        db::upsert_track_cache(&conn, provider.name(), local_path, isrc, Some(&remote[*ri]))?;
+    }
+    db::upsert_playlist_map(&conn, provider.name(), &playlist_name, remote_id)?;
+    Ok(summary)
+}
+
+/// Outcome of [`verify_track_cache`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheVerifySummary {
+    /// Resolved track cache entries looked at.
+    pub checked: usize,
+    /// Entries whose remote track still exists.
+    pub alive: usize,
+    /// Entries the provider could not check (no lookup, or it failed).
+    pub unknown: usize,
+    /// Entries whose track was gone and that were resolved to another one.
+    pub repaired: usize,
+    /// Entries whose track was gone and that nothing else matched; they are
+    /// deleted so the worker resolves the file again.
+    pub removed: usize,
+}
+
+/// Check every resolved `track_cache` entry of `provider` with
+/// [`Provider::track_exists`] and re-resolve the ones whose remote track no
+/// longer exists: by the ISRC of the entry or the file, then by metadata
+/// search.  Entries nothing matches (or whose file is gone) are deleted.
+/// When anything changed the provider's playlist cache is cleared, so
+/// playlists holding the dead URIs are rebuilt on the next sync.
+pub async fn verify_track_cache(
+    cfg: &Config,
+    provider: Arc<dyn Provider>,
+) -> Result<CacheVerifySummary> {
+    let pool = db::create_pool(&cfg.db_path)?;
+    let entries = db::resolved_track_cache_entries(&*pool.get()?, provider.name())?;
+    let filename_regex = cfg.compiled_filename_parse_regex()?;
+    let copy_suffixes = cfg.compiled_filename_copy_suffixes()?;
+    let mut summary = CacheVerifySummary {
+        checked: entries.len(),
+        ..Default::default()
+    };
+    for (local_path, cached_isrc, uri) in entries {
+        match provider.track_exists(&uri).await {
+            Ok(Some(false)) => {}
+            Ok(Some(true)) => {
+                summary.alive += 1;
+                continue;
+            }
+            Ok(None) => {
+                summary.unknown += 1;
+                continue;
+            }
+            Err(e) => {
+                log::warn!(
+                    "cache verify: checking {} ({}) failed: {}",
+                    uri,
+                    local_path,
+                    e
+                );
+                summary.unknown += 1;
+                continue;
+            }
+        }
+
+        let disk_path = crate::util::resolve_lossy_path(std::path::Path::new(&local_path));
+        let mut found: Option<(String, Option<String>)> = None;
+        if disk_path.exists() {
+            let (file_pool, name, key, p) = (
+                pool.clone(),
+                provider.name().to_string(),
+                local_path.clone(),
+                disk_path.clone(),
+            );
+            let isrc =
+                tokio::task::spawn_blocking(move || local_file_isrc(&file_pool, &name, &key, &p))
+                    .await
+                    .unwrap_or(None)
+                    .or(cached_isrc);
+            if let Some(isrc) = isrc.as_deref() {
+                if provider.capabilities().supports_isrc_search {
+                    if let Ok(Some(u)) = provider.search_track_uri_by_isrc(isrc).await {
+                        found = Some((u, Some(isrc.to_string())));
+                    }
+                }
+            }
+            if found.is_none() {
+                found = search_file_by_metadata(
+                    cfg,
+                    provider.as_ref(),
+                    &disk_path,
+                    filename_regex.as_ref(),
+                    &copy_suffixes,
+                )
+                .await
+                .map(|m| (m.uri, isrc.clone()));
+            }
+        }
+
+        let conn = pool.get()?;
+        // The search may still return the dead track; that is no repair.
+        match found.filter(|(u, _)| u != &uri) {
+            Some((new_uri, isrc)) => {
+                log::info!(
+                    "cache verify: {} no longer exists on {}; {} now maps to {}",
+                    uri,
+                    provider.name(),
+                    local_path,
+                    new_uri
+                );
+                db::upsert_track_cache(
+                    &conn,
+                    provider.name(),
+                    &local_path,
+                    isrc.as_deref(),
+                    Some(&new_uri),
+                )?;
+                summary.repaired += 1;
+            }
+            None => {
+                log::info!(
+                    "cache verify: {} no longer exists on {}; removed the entry of {}",
+                    uri,
+                    provider.name(),
+                    local_path
+                );
+                db::delete_track_cache_entry(&conn, provider.name(), &local_path)?;
+                summary.removed += 1;
+            }
+        }
+    }
+    if summary.repaired + summary.removed > 0 {
+        db::invalidate_provider_playlist_cache(&*pool.get()?, provider.name())?;
+    }
+    Ok(summary)
 }
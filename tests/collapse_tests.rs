@@ -25,3 +25,89 @@ fn collapse_add_remove_pair() {
     // Should not contain add/remove for t.mp3 after collapse
     assert!(res.iter().all(|e| e.track_path.as_deref() != Some("t.mp3")));
 }
+
+fn ev(id: i64, action: EventAction, track: Option<&str>) -> Event {
+    Event {
+        id,
+        timestamp_ms: id,
+        playlist_name: "Artist/Album".into(),
+        action,
+        track_path: track.map(|t| t.to_string()),
+        extra: None,
+        is_synced: false,
+    }
+}
+
+fn summary(events: &[Event]) -> Vec<String> {
+    events
+        .iter()
+        .map(|e| match (&e.action, &e.track_path) {
+            (EventAction::Rename { from, to }, _) => format!("rename {} -> {}", from, to),
+            (action, Some(t)) => format!("{} {}", format!("{:?}", action).to_lowercase(), t),
+            (action, None) => format!("{:?}", action).to_lowercase(),
+        })
+        .collect()
+}
+
+#[test]
+fn collapse_nets_out_add_remove_in_either_order() {
+    let res = collapse_events(&[
+        ev(1, EventAction::Add, Some("/m/a.mp3")),
+        ev(2, EventAction::Remove, Some("/m/b.mp3")),
+        ev(3, EventAction::Remove, Some("/m/a.mp3")),
+        ev(4, EventAction::Add, Some("/m/b.mp3")),
+        ev(5, EventAction::Add, Some("/m/c.mp3")),
+    ]);
+    assert_eq!(summary(&res), vec!["add /m/c.mp3"]);
+
+    // remove -> add -> remove leaves a single remove
+    let res = collapse_events(&[
+        ev(1, EventAction::Remove, Some("/m/a.mp3")),
+        ev(2, EventAction::Add, Some("/m/a.mp3")),
+        ev(3, EventAction::Remove, Some("/m/a.mp3")),
+    ]);
+    assert_eq!(summary(&res), vec!["remove /m/a.mp3"]);
+}
+
+#[test]
+fn collapse_cancels_across_a_rename() {
+    let rename = EventAction::Rename {
+        from: "Artist/Old".into(),
+        to: "Artist/New".into(),
+    };
+    let res = collapse_events(&[
+        ev(1, EventAction::Add, Some("/m/Artist/Old/a.mp3")),
+        ev(2, EventAction::Add, Some("/m/Artist/Old/b.mp3")),
+        ev(3, rename, None),
+        ev(4, EventAction::Remove, Some("/m/Artist/New/a.mp3")),
+        ev(5, EventAction::Add, Some("/m/Artist/New/c.mp3")),
+    ]);
+    assert_eq!(
+        summary(&res),
+        vec![
+            "rename Artist/Old -> Artist/New",
+            "add /m/Artist/New/b.mp3",
+            "add /m/Artist/New/c.mp3",
+        ]
+    );
+}
+
+#[test]
+fn collapse_delete_supersedes_track_ops() {
+    let res = collapse_events(&[
+        ev(1, EventAction::Add, Some("/m/a.mp3")),
+        ev(2, EventAction::Remove, Some("/m/b.mp3")),
+        ev(3, EventAction::Delete, None),
+        ev(4, EventAction::Add, Some("/m/c.mp3")),
+    ]);
+    assert_eq!(summary(&res), vec!["delete"]);
+
+    // A Create after the Delete re-opens the playlist for later ops.
+    let res = collapse_events(&[
+        ev(1, EventAction::Add, Some("/m/a.mp3")),
+        ev(2, EventAction::Delete, None),
+        ev(3, EventAction::Create, None),
+        ev(4, EventAction::Add, Some("/m/c.mp3")),
+    ]);
+    assert_eq!(summary(&res), vec!["delete", "create", "add /m/c.mp3"]);
+}
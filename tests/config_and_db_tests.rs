@@ -4,6 +4,7 @@ use tempfile::tempdir;
 
 use music_file_playlist_online_sync::config::Config;
 use music_file_playlist_online_sync::db;
+use music_file_playlist_online_sync::worker::compute_remote_playlist_name;
 
 #[test]
 fn config_from_path_parses_toml() {
@@ -21,6 +22,225 @@ log_dir = "/tmp"
     assert_eq!(cfg.db_path.to_str().unwrap(), "/tmp/test.db");
 }
 
+#[test]
+fn config_from_path_rejects_bad_filename_regex() {
+    let td = tempdir().unwrap();
+    let cfg_path = td.path().join("cfg.toml");
+    for (pattern, expected) in [
+        ("(?P<title>.+", "invalid filename_parse_regex"),
+        (
+            "(?P<artist>.+) - (?P<name>.+)",
+            "named capture group `title`",
+        ),
+    ] {
+        let toml = format!(
+            "root_folder = \"/tmp/music\"\nfilename_parse_regex = '{}'\n",
+            pattern
+        );
+        std::fs::write(&cfg_path, toml).unwrap();
+        let err = Config::from_path(&cfg_path).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}", err);
+    }
+}
+
+#[test]
+fn whitelists_accept_arrays_and_the_legacy_joined_string() {
+    let cfg: Config = toml::from_str(
+        r#"
+root_folder = "/tmp/music"
+whitelist = ["^/tmp/music/[[:alpha:]]+$", "https?://x", " "]
+whitelist_playlist_watch_folders = "^/tmp/music/Rock: ^/tmp/music/Jazz"
+"#,
+    )
+    .unwrap();
+    cfg.validate().unwrap();
+    assert_eq!(
+        cfg.effective_remote_whitelist(),
+        ["^/tmp/music/[[:alpha:]]+$", "https?://x"]
+    );
+    assert_eq!(
+        cfg.effective_local_whitelist(),
+        ["^/tmp/music/Rock", "^/tmp/music/Jazz"]
+    );
+
+    let td = tempdir().unwrap();
+    let cfg_path = td.path().join("cfg.toml");
+    std::fs::write(
+        &cfg_path,
+        "root_folder = \"/tmp/music\"\nwhitelist_playlist_sync_to_remote_folders = [\"(Live\"]\n",
+    )
+    .unwrap();
+    let err = Config::from_path(&cfg_path).unwrap_err().to_string();
+    assert!(
+        err.contains("invalid whitelist_playlist_sync_to_remote_folders pattern \"(Live\""),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn local_playlist_file_name_follows_playlist_format() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    let folder = std::path::Path::new("/tmp/music/Album1");
+    assert_eq!(
+        cfg.local_playlist_file_name(folder, "Album1", ""),
+        "Album1.m3u"
+    );
+    cfg.playlist_format = "xspf".into();
+    assert_eq!(
+        cfg.local_playlist_file_name(folder, "Album1", ""),
+        "Album1.xspf"
+    );
+    cfg.local_playlist_template = "${folder_name}".into();
+    assert_eq!(
+        cfg.local_playlist_file_name(folder, "Album1", ""),
+        "Album1.xspf"
+    );
+    cfg.playlist_mode = "linked".into();
+    assert_eq!(cfg.local_playlist_file_name(folder, "Album1", ""), "Album1");
+}
+
+#[test]
+fn track_count_and_parent_name_placeholders() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("music");
+    let folder = root.join("Rock").join("Album1");
+    std::fs::create_dir_all(folder.join("CD2")).unwrap();
+    for f in ["a.mp3", "b.flac", "cover.jpg", "CD2/c.mp3"] {
+        std::fs::write(folder.join(f), b"").unwrap();
+    }
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\nlocal_playlist_template = \"${{parent_name}} - ${{folder_name}} (${{track_count}} tracks).m3u\"\n",
+        root
+    ))
+    .unwrap();
+    assert_eq!(
+        cfg.local_playlist_file_name(&folder, "Album1", "Rock/"),
+        "Rock - Album1 (3 tracks).m3u"
+    );
+    assert_eq!(
+        cfg.local_playlist_file_name(&root.join("Rock"), "Rock", ""),
+        " - Rock (3 tracks).m3u"
+    );
+
+    cfg.online_root_playlist = "Syncd".into();
+    cfg.remote_playlist_template_flat =
+        "${parent_name}: ${folder_name} [${track_count}] ${unknown}".into();
+    assert_eq!(
+        compute_remote_playlist_name(&cfg, "tidal", "Rock/Album1", false),
+        "Rock: Album1 [3] ${unknown}"
+    );
+}
+
+#[test]
+fn backoff_doubles_up_to_the_configured_cap() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    assert_eq!(cfg.lock_ttl_secs, 600);
+    assert_eq!(
+        (1..=7).map(|a| cfg.backoff_secs(a)).collect::<Vec<_>>(),
+        vec![2, 4, 8, 16, 32, 60, 60]
+    );
+    cfg.backoff_base_secs = 5;
+    cfg.backoff_max_secs = 30;
+    assert_eq!(cfg.backoff_secs(1), 10);
+    assert_eq!(cfg.backoff_secs(2), 20);
+    assert_eq!(cfg.backoff_secs(100), 30);
+    cfg.lock_ttl_secs = 0;
+    assert!(cfg.validate().is_err());
+}
+
+#[test]
+fn flattening_delimiter_defaults_only_under_a_root() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    let name = |cfg: &Config| compute_remote_playlist_name(cfg, "tidal", "Rock/Album1", false);
+
+    // No root, delim empty: the logical path keeps its separators.
+    assert_eq!(name(&cfg), "Rock/Album1");
+
+    // Root set, delim empty: the documented " - " default applies.
+    cfg.online_root_playlist = "Syncd".into();
+    assert_eq!(cfg.flattening_delimiter(), " - ");
+    assert_eq!(name(&cfg), "Syncd - Rock - Album1");
+
+    // Root set, delim "/": an explicit delimiter always wins.
+    cfg.online_folder_flattening_delimiter = "/".into();
+    assert_eq!(name(&cfg), "Syncd/Rock/Album1");
+
+    // Folder-style providers keep the hierarchy regardless.
+    cfg.online_folder_flattening_delimiter.clear();
+    cfg.online_playlist_structure = "folders".into();
+    assert_eq!(
+        compute_remote_playlist_name(&cfg, "spotify", "Rock/Album1", true),
+        "Syncd/Rock/Album1"
+    );
+}
+
+#[test]
+fn tidal_region_settings_are_validated() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    assert_eq!(
+        (cfg.tidal_country_code.as_str(), cfg.tidal_locale.as_str()),
+        ("US", "en-US")
+    );
+    for (cc, locale) in [("DE", "de-DE"), ("gb", "en"), ("BR", "pt-BR")] {
+        cfg.tidal_country_code = cc.into();
+        cfg.tidal_locale = locale.into();
+        cfg.validate().unwrap();
+    }
+    for (cc, locale, expected) in [
+        ("USA", "en-US", "tidal_country_code"),
+        ("U1", "en-US", "tidal_country_code"),
+        ("US", "english", "tidal_locale"),
+        ("US", "en-US-x", "tidal_locale"),
+        ("US", "en_US", "tidal_locale"),
+    ] {
+        cfg.tidal_country_code = cc.into();
+        cfg.tidal_locale = locale.into();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains(expected), "{} {}: {}", cc, locale, err);
+    }
+}
+
+#[test]
+fn spotify_market_is_validated() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    for ok in ["", "from_token", "DE"] {
+        cfg.spotify_market = ok.into();
+        cfg.validate().unwrap();
+    }
+    for bad in ["DEU", "from-token", "1A"] {
+        cfg.spotify_market = bad.into();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("spotify_market"), "{}: {}", bad, err);
+    }
+    cfg.spotify_market = String::new();
+    cfg.spotify_scopes = vec!["playlist-modify-public playlist-read-private".into()];
+    let err = cfg.validate().unwrap_err().to_string();
+    assert!(err.contains("spotify_scopes"), "{}", err);
+}
+
+#[test]
+fn log_level_and_format_are_validated() {
+    let mut cfg: Config = toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    assert_eq!(
+        (cfg.log_level.as_str(), cfg.log_format.as_str()),
+        ("info", "text")
+    );
+    for ok in ["debug", "WARN", "off"] {
+        cfg.log_level = ok.into();
+        cfg.validate().unwrap();
+    }
+    cfg.log_format = "json".into();
+    cfg.validate().unwrap();
+    cfg.log_level = "verbose".into();
+    let err = cfg.validate().unwrap_err().to_string();
+    assert!(err.contains("log_level"), "{}", err);
+    cfg.log_level = "info".into();
+    cfg.log_format = "logfmt".into();
+    let err = cfg.validate().unwrap_err().to_string();
+    assert!(err.contains("log_format"), "{}", err);
+}
+
 #[test]
 fn run_migrations_creates_tables() {
     let td = tempdir().unwrap();
@@ -36,3 +256,25 @@ fn run_migrations_creates_tables() {
     let found = rows.next().unwrap().is_some();
     assert!(found, "event_queue table should exist after migrations");
 }
+
+#[test]
+fn connections_use_wal_and_busy_timeout() {
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("tuned.db");
+    let conn = db::open_or_create(&db_path).unwrap();
+    let mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(mode.to_lowercase(), "wal");
+    let timeout: i64 = conn
+        .query_row("PRAGMA busy_timeout", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(timeout, 5000);
+
+    let pool = db::create_pool(&db_path).unwrap();
+    let pooled = pool.get().unwrap();
+    let sync: i64 = pooled
+        .query_row("PRAGMA synchronous", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(sync, 1, "synchronous=NORMAL");
+}
@@ -0,0 +1,80 @@
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::{db, secrets};
+use tempfile::tempdir;
+
+fn raw_token_json(conn: &rusqlite::Connection, provider: &str) -> String {
+    conn.query_row(
+        "SELECT token_json FROM credentials WHERE provider = ?1",
+        [provider],
+        |r| r.get(0),
+    )
+    .unwrap()
+}
+
+// The passphrase is process-wide, so everything runs in one test.
+#[test]
+fn tokens_are_encrypted_at_rest_once_a_passphrase_is_configured() {
+    std::env::remove_var(secrets::PASSPHRASE_ENV);
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("test.db")).unwrap();
+    let token = r#"{"access_token":"secret-token","refresh_token":"secret-refresh"}"#;
+
+    // Without a passphrase nothing changes.
+    db::save_credential_raw(&conn, "spotify", token, Some("cid"), Some("cs")).unwrap();
+    assert_eq!(raw_token_json(&conn, "spotify"), token);
+
+    // A key file configures the passphrase; the plaintext row is still read
+    // and re-saved encrypted on first load.
+    let key_file = td.path().join("credentials.key");
+    std::fs::write(&key_file, "correct horse battery staple\n").unwrap();
+    let cfg = Config {
+        credentials_key_file: Some(key_file.clone()),
+        ..Default::default()
+    };
+    secrets::configure(&cfg).unwrap();
+    assert!(secrets::is_enabled());
+    let (json, client_id, client_secret) = db::load_credential_with_client(&conn, "spotify")
+        .unwrap()
+        .unwrap();
+    assert_eq!(json, token);
+    assert_eq!(client_id.as_deref(), Some("cid"));
+    assert_eq!(client_secret.as_deref(), Some("cs"));
+    let stored = raw_token_json(&conn, "spotify");
+    assert!(secrets::is_encrypted(&stored), "{}", stored);
+    assert!(!stored.contains("secret"));
+
+    // New saves are encrypted too and read back transparently.
+    db::save_credential_raw(&conn, "tidal", token, None, None).unwrap();
+    assert!(secrets::is_encrypted(&raw_token_json(&conn, "tidal")));
+    let (json, _, _) = db::load_credential_with_client(&conn, "tidal")
+        .unwrap()
+        .unwrap();
+    assert_eq!(json, token);
+
+    // A row moved to another provider does not open.
+    conn.execute(
+        "UPDATE credentials SET token_json = ?1 WHERE provider = 'tidal'",
+        [&stored],
+    )
+    .unwrap();
+    assert!(db::load_credential_with_client(&conn, "tidal").is_err());
+
+    // The env var takes precedence; a wrong passphrase is reported.
+    std::env::set_var(secrets::PASSPHRASE_ENV, "wrong");
+    let err = db::load_credential_with_client(&conn, "spotify").unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("wrong passphrase"),
+        "{:#}",
+        err
+    );
+    std::env::remove_var(secrets::PASSPHRASE_ENV);
+
+    // Without any passphrase encrypted rows cannot be read.
+    secrets::set_passphrase(None);
+    assert!(!secrets::is_enabled());
+    let err = db::load_credential_with_client(&conn, "spotify").unwrap_err();
+    assert!(format!("{:#}", err).contains("encrypted"), "{:#}", err);
+
+    std::fs::write(&key_file, "\n").unwrap();
+    assert!(secrets::configure(&cfg).is_err());
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use chrono::Utc;
 use music_file_playlist_online_sync::api;
-use music_file_playlist_online_sync::api::Provider;
+use music_file_playlist_online_sync::api::{Provider, ProviderResult};
 use music_file_playlist_online_sync::db;
 use music_file_playlist_online_sync::models;
 use music_file_playlist_online_sync::models::EventAction;
@@ -47,6 +47,252 @@ fn playlist_map_and_track_cache_persistence() {
     assert!((Utc::now().timestamp() - ts2).abs() < 60);
 }
 
+#[test]
+fn playlist_map_and_track_cache_are_per_provider() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("multi.db")).unwrap();
+
+    db::upsert_playlist_map(&conn, "spotify", "Rock", "sp-1").unwrap();
+    db::upsert_playlist_map(&conn, "tidal", "Rock", "td-1").unwrap();
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "spotify", "Rock").unwrap(),
+        Some("sp-1".into())
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "tidal", "Rock").unwrap(),
+        Some("td-1".into())
+    );
+    let entries = db::list_playlist_map_entries(&conn, Some("tidal")).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].0, "tidal");
+    assert_eq!(entries[0].1, "Rock");
+
+    db::delete_playlist_map(&conn, "spotify", "Rock").unwrap();
+    assert!(db::get_remote_playlist_id(&conn, "spotify", "Rock")
+        .unwrap()
+        .is_none());
+    assert!(db::get_remote_playlist_id(&conn, "tidal", "Rock")
+        .unwrap()
+        .is_some());
+
+    db::upsert_track_cache(
+        &conn,
+        "spotify",
+        "/m/a.mp3",
+        Some("I1"),
+        Some("spotify:track:a"),
+    )
+    .unwrap();
+    db::upsert_track_cache(
+        &conn,
+        "tidal",
+        "/m/a.mp3",
+        Some("I1"),
+        Some("tidal:track:1"),
+    )
+    .unwrap();
+    let sp = db::get_track_cache_by_local(&conn, "spotify", "/m/a.mp3")
+        .unwrap()
+        .unwrap();
+    let td_ = db::get_track_cache_by_local(&conn, "tidal", "/m/a.mp3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(sp.1.as_deref(), Some("spotify:track:a"));
+    assert_eq!(td_.1.as_deref(), Some("tidal:track:1"));
+    let (_isrc, local, _) = db::get_track_cache_by_remote(&conn, "tidal:track:1")
+        .unwrap()
+        .unwrap();
+    assert_eq!(local, "/m/a.mp3");
+}
+
+#[test]
+fn list_playlist_maps_sorted_by_playlist_name() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("maps.db")).unwrap();
+
+    db::upsert_playlist_map(&conn, "tidal", "Zeppelin", "td-z").unwrap();
+    db::upsert_playlist_map(&conn, "spotify", "Zeppelin", "sp-z").unwrap();
+    db::upsert_playlist_map(&conn, "spotify", "Abba", "sp-a").unwrap();
+
+    let all = db::list_playlist_maps(&conn, None).unwrap();
+    let keys: Vec<(&str, &str)> = all
+        .iter()
+        .map(|(p, n, _, _)| (n.as_str(), p.as_str()))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            ("Abba", "spotify"),
+            ("Zeppelin", "spotify"),
+            ("Zeppelin", "tidal")
+        ]
+    );
+
+    let tidal = db::list_playlist_maps(&conn, Some("TIDAL")).unwrap();
+    assert_eq!(tidal.len(), 1);
+    assert_eq!(tidal[0].2.as_deref(), Some("td-z"));
+}
+
+#[test]
+fn unresolved_track_cache_entries_are_cleared_and_pruned() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("neg.db")).unwrap();
+
+    db::mark_track_unresolved(&conn, "spotify", "/music/a/missing.mp3", None).unwrap();
+    db::mark_track_unresolved(&conn, "tidal", "/music/a/missing.mp3", None).unwrap();
+    db::mark_track_unresolved(&conn, "spotify", "/music/a/renamed.mp3", Some("ISRC9")).unwrap();
+    db::upsert_track_cache(&conn, "spotify", "/music/a/found.mp3", None, Some("sp:1")).unwrap();
+
+    let ts = db::get_track_unresolved_at(&conn, "spotify", "/music/a/missing.mp3")
+        .unwrap()
+        .expect("negative entry recorded");
+    assert!((Utc::now().timestamp() - ts).abs() < 60);
+    assert!(
+        db::get_track_unresolved_at(&conn, "spotify", "/music/a/found.mp3")
+            .unwrap()
+            .is_none()
+    );
+
+    // a later successful resolution clears the negative marker
+    db::upsert_track_cache(
+        &conn,
+        "spotify",
+        "/music/a/renamed.mp3",
+        Some("ISRC9"),
+        Some("sp:2"),
+    )
+    .unwrap();
+    assert!(
+        db::get_track_unresolved_at(&conn, "spotify", "/music/a/renamed.mp3")
+            .unwrap()
+            .is_none()
+    );
+
+    // prune only drops negative entries, optionally scoped by provider
+    assert_eq!(
+        db::prune_unresolved_track_cache(&conn, Some("spotify")).unwrap(),
+        1
+    );
+    assert!(
+        db::get_track_cache_by_local(&conn, "spotify", "/music/a/missing.mp3")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        db::get_track_unresolved_at(&conn, "tidal", "/music/a/missing.mp3")
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        db::get_track_cache_by_local(&conn, "spotify", "/music/a/found.mp3")
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(db::prune_unresolved_track_cache(&conn, None).unwrap(), 1);
+}
+
+#[test]
+fn moved_track_keeps_cached_matches() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("move.db")).unwrap();
+
+    db::upsert_track_cache(
+        &conn,
+        "spotify",
+        "/music/a/song.mp3",
+        Some("ISRC1"),
+        Some("sp:1"),
+    )
+    .unwrap();
+    db::upsert_track_cache(&conn, "tidal", "/music/a/song.mp3", None, Some("td:1")).unwrap();
+    db::mark_track_unresolved(&conn, "subsonic", "/music/a/song.mp3", None).unwrap();
+
+    let n = db::copy_track_cache_entries(&conn, "/music/a/song.mp3", "/music/b/song.mp3").unwrap();
+    assert_eq!(n, 2);
+    let (isrc, remote, _) = db::get_track_cache_by_local(&conn, "spotify", "/music/b/song.mp3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(isrc.as_deref(), Some("ISRC1"));
+    assert_eq!(remote.as_deref(), Some("sp:1"));
+    assert!(
+        db::get_track_cache_by_local(&conn, "tidal", "/music/b/song.mp3")
+            .unwrap()
+            .is_some()
+    );
+    // negative entries stay behind so the destination is looked up normally
+    assert!(
+        db::get_track_cache_by_local(&conn, "subsonic", "/music/b/song.mp3")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn file_isrc_cache_is_keyed_by_mtime_and_size() {
+    let td = tempdir().unwrap();
+    let mut conn = db::open_or_create(&td.path().join("isrc.db")).unwrap();
+    let path = "/music/a/song.mp3";
+
+    assert_eq!(db::cached_file_isrc(&conn, path, 100, 2048).unwrap(), None);
+    db::store_file_isrc(&conn, "spotify", path, 100, 2048, Some("USRC17607839")).unwrap();
+    db::store_file_isrc(&conn, "spotify", "/music/a/untagged.mp3", 100, 10, None).unwrap();
+    // Any provider's row answers for the file.
+    assert_eq!(
+        db::cached_file_isrc(&conn, path, 100, 2048).unwrap(),
+        Some(Some("USRC17607839".to_string()))
+    );
+    assert_eq!(
+        db::cached_file_isrc(&conn, "/music/a/untagged.mp3", 100, 10).unwrap(),
+        Some(None)
+    );
+    // A retag changes the mtime or size, so the file is read again.
+    assert_eq!(db::cached_file_isrc(&conn, path, 101, 2048).unwrap(), None);
+    assert_eq!(db::cached_file_isrc(&conn, path, 100, 2049).unwrap(), None);
+
+    // Recording it keeps the resolved match of the row, and dropping the
+    // remote state keeps the file's ISRC.
+    db::upsert_track_cache(&conn, "spotify", path, None, Some("sp:1")).unwrap();
+    db::store_file_isrc(&conn, "spotify", path, 101, 2048, Some("GBAYE0601498")).unwrap();
+    let (_, remote, _) = db::get_track_cache_by_local(&conn, "spotify", path)
+        .unwrap()
+        .unwrap();
+    assert_eq!(remote.as_deref(), Some("sp:1"));
+    db::reset_remote_state(&mut conn, None).unwrap();
+    assert_eq!(
+        db::cached_file_isrc(&conn, path, 101, 2048).unwrap(),
+        Some(Some("GBAYE0601498".to_string()))
+    );
+}
+
+#[test]
+fn playlist_map_migration_splits_legacy_keys() {
+    let td = tempdir().unwrap();
+    let conn = Connection::open(td.path().join("legacy_map.db")).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE playlist_map (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             playlist_name TEXT UNIQUE NOT NULL,
+             remote_id TEXT,
+             remote_snapshot_id TEXT,
+             last_synced_at INTEGER
+         );
+         INSERT INTO playlist_map (playlist_name, remote_id) VALUES ('tidal::A/B', 'td-1');
+         INSERT INTO playlist_map (playlist_name, remote_id) VALUES ('Old', 'sp-1');",
+    )
+    .unwrap();
+
+    db::run_migrations(&conn).unwrap();
+
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "tidal", "A/B").unwrap(),
+        Some("td-1".into())
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "spotify", "Old").unwrap(),
+        Some("sp-1".into())
+    );
+}
+
 #[test]
 fn track_cache_migration_prefixes_spotify_entries() {
     let td = tempdir().unwrap();
@@ -75,17 +321,18 @@ fn track_cache_migration_prefixes_spotify_entries() {
     // running migrations should rewrite the entry
     db::run_migrations(&conn).unwrap();
 
-    let row: (Option<String>, Option<String>, String) = conn
+    let row: (Option<String>, Option<String>, String, String) = conn
         .query_row(
-            "SELECT isrc, remote_id, local_path FROM track_cache LIMIT 1",
+            "SELECT isrc, remote_id, provider, local_path FROM track_cache LIMIT 1",
             [],
-            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
         )
         .unwrap();
 
     assert_eq!(row.0.unwrap(), "ISRC");
     assert_eq!(row.1.unwrap(), "rid");
-    assert_eq!(row.2, "spotify::/foo/bar.mp3");
+    assert_eq!(row.2, "spotify");
+    assert_eq!(row.3, "/foo/bar.mp3");
 }
 
 #[tokio::test]
@@ -133,37 +380,37 @@ async fn playlist_cache_and_rename_migration() {
         fn is_authenticated(&self) -> bool {
             true
         }
-        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> anyhow::Result<String> {
+        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
             Ok("id".to_string())
         }
-        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> anyhow::Result<()> {
+        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
             Ok(())
         }
-        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn delete_playlist(&self, _playlist_id: &str) -> anyhow::Result<()> {
+        async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
             Ok(())
         }
         async fn search_track_uri(
             &self,
             _title: &str,
             _artist: &str,
-        ) -> anyhow::Result<Option<String>> {
+        ) -> ProviderResult<Option<String>> {
             let mut c = self.0.lock().unwrap();
             *c += 1;
             Ok(Some("uri".to_string()))
         }
-        async fn list_playlist_tracks(&self, _playlist_id: &str) -> anyhow::Result<Vec<String>> {
+        async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
             Ok(Vec::new())
         }
-        async fn playlist_is_valid(&self, _playlist_id: &str) -> anyhow::Result<Option<String>> {
+        async fn playlist_is_valid(&self, _playlist_id: &str) -> ProviderResult<Option<String>> {
             Ok(Some(String::new()))
         }
-        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
             // count as well
             let mut c = self.0.lock().unwrap();
             *c += 1;
@@ -174,10 +421,10 @@ async fn playlist_cache_and_rename_migration() {
             static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
             CLIENT.get_or_init(reqwest::Client::new)
         }
-        async fn get_bearer(&self) -> anyhow::Result<String> {
+        async fn get_bearer(&self) -> ProviderResult<String> {
             Ok("Bearer test".to_string())
         }
-        async fn refresh_token(&self) -> anyhow::Result<()> {
+        async fn refresh_token(&self) -> ProviderResult<()> {
             Ok(())
         }
     }
@@ -238,6 +485,214 @@ async fn playlist_cache_and_rename_migration() {
     .unwrap();
     assert_eq!(uris3, uris1);
     assert_eq!(calls_after_first, *counter.lock().unwrap());
+
+    // an override wins over the cached match and is used without a lookup
+    let track = root.join("bar").join("song.mp3").display().to_string();
+    music_file_playlist_online_sync::db::set_override(&conn, "count", &track, "pinned").unwrap();
+    let (uris4, _) = music_file_playlist_online_sync::worker::desired_remote_uris_for_playlist(
+        &cfg,
+        "bar",
+        provider.clone(),
+        &pool,
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(uris4, vec!["pinned".to_string()]);
+    assert_eq!(calls_after_first, *counter.lock().unwrap());
+}
+
+#[tokio::test]
+async fn cached_match_with_a_different_isrc_is_resolved_again() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    std::fs::create_dir_all(root.join("foo")).unwrap();
+    let song = root.join("foo").join("song.mp3");
+    // Tagged with ISRC GBAAA0000001.
+    std::fs::copy(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/isrc/isrc.mp3"),
+        &song,
+    )
+    .unwrap();
+    std::fs::write(root.join("foo").join("foo.m3u"), "song.mp3\n").unwrap();
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root,
+        td.path().join("test.db")
+    ))
+    .unwrap();
+    let pool = db::create_pool(&cfg.db_path).unwrap();
+    let local = song.display().to_string();
+    // An early run poisoned the cache with the wrong track.
+    db::upsert_track_cache(&pool.get().unwrap(), "isrc", &local, None, Some("wrong")).unwrap();
+
+    struct IsrcProvider(std::sync::Mutex<Vec<String>>);
+    #[async_trait::async_trait]
+    impl api::Provider for IsrcProvider {
+        fn name(&self) -> &str {
+            "isrc"
+        }
+        fn is_authenticated(&self) -> bool {
+            true
+        }
+        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
+            Ok("id".to_string())
+        }
+        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
+            Ok(())
+        }
+        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+        async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
+            Ok(())
+        }
+        async fn search_track_uri(
+            &self,
+            _title: &str,
+            _artist: &str,
+        ) -> ProviderResult<Option<String>> {
+            Ok(None)
+        }
+        async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
+            Ok(Vec::new())
+        }
+        async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
+            Ok((isrc == "GBAAA0000001").then(|| "right".to_string()))
+        }
+        async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
+            self.0.lock().unwrap().push(uri.to_string());
+            Ok(Some(
+                if uri == "right" {
+                    "GB-AAA-00-00001"
+                } else {
+                    "USXXX0000009"
+                }
+                .to_string(),
+            ))
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            use std::sync::OnceLock;
+            static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+            CLIENT.get_or_init(reqwest::Client::new)
+        }
+        async fn get_bearer(&self) -> ProviderResult<String> {
+            Ok("Bearer test".to_string())
+        }
+        async fn refresh_token(&self) -> ProviderResult<()> {
+            Ok(())
+        }
+    }
+    let provider = Arc::new(IsrcProvider(Default::default()));
+    let desired = || async {
+        // Bypass the playlist cache so every track goes through resolution.
+        pool.get()
+            .unwrap()
+            .execute("DELETE FROM playlist_cache", [])
+            .unwrap();
+        music_file_playlist_online_sync::worker::desired_remote_uris_for_playlist(
+            &cfg,
+            "foo",
+            provider.clone(),
+            &pool,
+            false,
+        )
+        .await
+        .unwrap()
+        .0
+    };
+
+    assert_eq!(desired().await, vec!["right".to_string()]);
+    let cached = db::get_track_cache_by_local(&pool.get().unwrap(), "isrc", &local).unwrap();
+    assert_eq!(cached.and_then(|(_, uri, _)| uri).as_deref(), Some("right"));
+
+    // The new match agrees with the file; it is checked once and then trusted.
+    assert_eq!(desired().await, vec!["right".to_string()]);
+    assert!(db::track_isrc_checked(&pool.get().unwrap(), "isrc", &local).unwrap());
+    assert_eq!(desired().await, vec!["right".to_string()]);
+    assert_eq!(*provider.0.lock().unwrap(), vec!["wrong", "right"]);
+}
+
+#[test]
+fn track_cache_stats_count_resolution_states() {
+    let td = tempdir().unwrap();
+    let conn = Connection::open(td.path().join("test.db")).unwrap();
+    db::run_migrations(&conn).unwrap();
+    assert!(db::track_cache_stats(&conn).unwrap().is_empty());
+    assert_eq!(db::count_mapped_playlists(&conn).unwrap(), 0);
+
+    db::upsert_track_cache(
+        &conn,
+        "spotify",
+        "/m/a.mp3",
+        Some("ISRC1"),
+        Some("spotify:1"),
+    )
+    .unwrap();
+    db::upsert_track_cache(&conn, "spotify", "/m/b.mp3", None, Some("spotify:2")).unwrap();
+    db::upsert_track_cache(&conn, "spotify", "/m/c.mp3", Some("ISRC3"), None).unwrap();
+    db::mark_track_unresolved(&conn, "spotify", "/m/d.mp3", None).unwrap();
+    db::upsert_track_cache(&conn, "tidal", "/m/a.mp3", Some("ISRC1"), Some("tidal:1")).unwrap();
+    db::upsert_playlist_map(&conn, "spotify", "Album1", "sp1").unwrap();
+    db::upsert_playlist_map(&conn, "tidal", "Album1", "td1").unwrap();
+    db::upsert_playlist_map(&conn, "tidal", "Album2", "td2").unwrap();
+
+    let stats = db::track_cache_stats(&conn).unwrap();
+    assert_eq!(
+        stats,
+        vec![
+            db::TrackCacheStats {
+                provider: "spotify".into(),
+                total: 4,
+                resolved: 2,
+                isrc_only: 1,
+                unresolved: 1,
+            },
+            db::TrackCacheStats {
+                provider: "tidal".into(),
+                total: 1,
+                resolved: 1,
+                isrc_only: 0,
+                unresolved: 0,
+            },
+        ]
+    );
+    assert_eq!(db::count_mapped_playlists(&conn).unwrap(), 2);
+}
+
+#[test]
+fn overrides_set_get_and_remove() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    db::run_migrations(&conn).unwrap();
+    assert_eq!(
+        db::get_override(&conn, "spotify", "/m/a.mp3").unwrap(),
+        None
+    );
+
+    db::set_override(&conn, "Spotify", "/m/a.mp3", "spotify:track:1").unwrap();
+    db::set_override(&conn, "spotify", "/m/a.mp3", "spotify:track:2").unwrap();
+    db::set_override(&conn, "tidal", "/m/a.mp3", "tidal:track:9").unwrap();
+    assert_eq!(
+        db::get_override(&conn, "spotify", "/m/a.mp3")
+            .unwrap()
+            .as_deref(),
+        Some("spotify:track:2")
+    );
+    assert_eq!(db::list_overrides(&conn, None).unwrap().len(), 2);
+    assert_eq!(db::list_overrides(&conn, Some("tidal")).unwrap().len(), 1);
+
+    assert!(db::remove_override(&conn, "spotify", "/m/a.mp3").unwrap());
+    assert!(!db::remove_override(&conn, "spotify", "/m/a.mp3").unwrap());
+    assert_eq!(
+        db::get_override(&conn, "spotify", "/m/a.mp3").unwrap(),
+        None
+    );
+    assert!(db::get_override(&conn, "tidal", "/m/a.mp3")
+        .unwrap()
+        .is_some());
 }
 
 // exercise the predicate that guards the expensive URI resolution step.  this
@@ -304,37 +759,37 @@ async fn skip_resolve_when_track_ops() {
         fn is_authenticated(&self) -> bool {
             true
         }
-        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> anyhow::Result<String> {
+        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
             Ok("id".into())
         }
-        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> anyhow::Result<()> {
+        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
             Ok(())
         }
-        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn delete_playlist(&self, _playlist_id: &str) -> anyhow::Result<()> {
+        async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
             Ok(())
         }
         async fn search_track_uri(
             &self,
             _title: &str,
             _artist: &str,
-        ) -> anyhow::Result<Option<String>> {
+        ) -> ProviderResult<Option<String>> {
             let mut c = self.0.lock().unwrap();
             *c += 1;
             Ok(Some("uri".to_string()))
         }
-        async fn list_playlist_tracks(&self, _playlist_id: &str) -> anyhow::Result<Vec<String>> {
+        async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
             Ok(Vec::new())
         }
-        async fn playlist_is_valid(&self, _playlist_id: &str) -> anyhow::Result<Option<String>> {
+        async fn playlist_is_valid(&self, _playlist_id: &str) -> ProviderResult<Option<String>> {
             Ok(Some(String::new()))
         }
-        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
             let mut c = self.0.lock().unwrap();
             *c += 1;
             Ok(None)
@@ -344,10 +799,10 @@ async fn skip_resolve_when_track_ops() {
             static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
             CLIENT.get_or_init(reqwest::Client::new)
         }
-        async fn get_bearer(&self) -> anyhow::Result<String> {
+        async fn get_bearer(&self) -> ProviderResult<String> {
             Ok("Bearer test".to_string())
         }
-        async fn refresh_token(&self) -> anyhow::Result<()> {
+        async fn refresh_token(&self) -> ProviderResult<()> {
             Ok(())
         }
     }
@@ -438,35 +893,35 @@ async fn provider_uri_ops_resolve_via_local() -> anyhow::Result<()> {
         fn is_authenticated(&self) -> bool {
             true
         }
-        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> anyhow::Result<String> {
+        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
             Ok("id".into())
         }
-        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> anyhow::Result<()> {
+        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
             Ok(())
         }
-        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn delete_playlist(&self, _playlist_id: &str) -> anyhow::Result<()> {
+        async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
             Ok(())
         }
         async fn search_track_uri(
             &self,
             _title: &str,
             _artist: &str,
-        ) -> anyhow::Result<Option<String>> {
+        ) -> ProviderResult<Option<String>> {
             Ok(None)
         }
-        async fn list_playlist_tracks(&self, _playlist_id: &str) -> anyhow::Result<Vec<String>> {
+        async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
             Ok(Vec::new())
         }
-        async fn playlist_is_valid(&self, _playlist_id: &str) -> anyhow::Result<Option<String>> {
+        async fn playlist_is_valid(&self, _playlist_id: &str) -> ProviderResult<Option<String>> {
             Ok(Some(String::new()))
         }
-        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
             Ok(None)
         }
         fn http_client(&self) -> &reqwest::Client {
@@ -474,10 +929,10 @@ async fn provider_uri_ops_resolve_via_local() -> anyhow::Result<()> {
             static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
             CLIENT.get_or_init(reqwest::Client::new)
         }
-        async fn get_bearer(&self) -> anyhow::Result<String> {
+        async fn get_bearer(&self) -> ProviderResult<String> {
             Ok("Bearer test".to_string())
         }
-        async fn refresh_token(&self) -> anyhow::Result<()> {
+        async fn refresh_token(&self) -> ProviderResult<()> {
             Ok(())
         }
     }
@@ -634,37 +1089,37 @@ async fn skip_resolve_on_rename_only() {
         fn is_authenticated(&self) -> bool {
             true
         }
-        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> anyhow::Result<String> {
+        async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
             Ok("id".into())
         }
-        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> anyhow::Result<()> {
+        async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
             Ok(())
         }
-        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+        async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
             Ok(())
         }
-        async fn delete_playlist(&self, _playlist_id: &str) -> anyhow::Result<()> {
+        async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
             Ok(())
         }
         async fn search_track_uri(
             &self,
             _title: &str,
             _artist: &str,
-        ) -> anyhow::Result<Option<String>> {
+        ) -> ProviderResult<Option<String>> {
             let mut c = self.0.lock().unwrap();
             *c += 1;
             Ok(Some("uri".to_string()))
         }
-        async fn list_playlist_tracks(&self, _playlist_id: &str) -> anyhow::Result<Vec<String>> {
+        async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
             Ok(Vec::new())
         }
-        async fn playlist_is_valid(&self, _playlist_id: &str) -> anyhow::Result<Option<String>> {
+        async fn playlist_is_valid(&self, _playlist_id: &str) -> ProviderResult<Option<String>> {
             Ok(Some(String::new()))
         }
-        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+        async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
             let mut c = self.0.lock().unwrap();
             *c += 1;
             Ok(None)
@@ -674,10 +1129,10 @@ async fn skip_resolve_on_rename_only() {
             static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
             CLIENT.get_or_init(reqwest::Client::new)
         }
-        async fn get_bearer(&self) -> anyhow::Result<String> {
+        async fn get_bearer(&self) -> ProviderResult<String> {
             Ok("Bearer test".to_string())
         }
-        async fn refresh_token(&self) -> anyhow::Result<()> {
+        async fn refresh_token(&self) -> ProviderResult<()> {
             Ok(())
         }
     }
@@ -708,3 +1163,299 @@ async fn skip_resolve_on_rename_only() {
     );
     assert_eq!(*counter.lock().unwrap(), 0);
 }
+
+#[test]
+fn prune_synced_events_keeps_recent_and_unsynced() {
+    let td = tempdir().unwrap();
+    let mut conn = db::open_or_create(&td.path().join("prune.db")).unwrap();
+    for name in ["old-synced", "old-unsynced", "new-synced"] {
+        db::enqueue_event(&conn, name, &EventAction::Create, None, None).unwrap();
+    }
+    let old_ts = Utc::now().timestamp_millis() - 10 * 24 * 3600 * 1000;
+    conn.execute(
+        "UPDATE event_queue SET timestamp = ?1 WHERE playlist_name LIKE 'old-%'",
+        params![old_ts],
+    )
+    .unwrap();
+    let ids: Vec<i64> = conn
+        .prepare("SELECT id FROM event_queue WHERE playlist_name LIKE '%-synced'")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    db::mark_events_synced(&mut conn, &ids).unwrap();
+
+    assert_eq!(db::prune_synced_events(&conn, 7 * 24 * 3600).unwrap(), 1);
+    let mut left: Vec<String> = conn
+        .prepare("SELECT playlist_name FROM event_queue")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    left.sort();
+    assert_eq!(left, vec!["new-synced", "old-unsynced"]);
+}
+
+#[test]
+fn queue_events_can_be_filtered_by_playlist_action_and_limit() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("filter.db")).unwrap();
+    db::enqueue_event(&conn, "Rock/A", &EventAction::Add, Some("/m/1.mp3"), None).unwrap();
+    db::enqueue_event(
+        &conn,
+        "Rock/A",
+        &EventAction::Remove,
+        Some("/m/2.mp3"),
+        None,
+    )
+    .unwrap();
+    db::enqueue_event(&conn, "Rock/A", &EventAction::Add, Some("/m/3.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "Jazz", &EventAction::Add, Some("/m/4.mp3"), None).unwrap();
+
+    assert_eq!(
+        db::fetch_events_filtered(&conn, None, None, None)
+            .unwrap()
+            .len(),
+        4
+    );
+    let adds = db::fetch_events_filtered(&conn, Some("Rock/A"), Some("add"), None).unwrap();
+    let tracks: Vec<_> = adds
+        .iter()
+        .map(|e| e.track_path.as_deref().unwrap())
+        .collect();
+    assert_eq!(tracks, vec!["/m/1.mp3", "/m/3.mp3"]);
+    assert_eq!(
+        db::fetch_events_filtered(&conn, None, Some("remove"), None).unwrap()[0].track_path,
+        Some("/m/2.mp3".to_string())
+    );
+    assert_eq!(
+        db::fetch_events_filtered(&conn, None, None, Some(3))
+            .unwrap()
+            .len(),
+        3
+    );
+    assert!(db::fetch_events_filtered(&conn, Some("Nope"), None, None)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn events_are_dead_lettered_after_max_attempts() {
+    let td = tempdir().unwrap();
+    let mut conn = db::open_or_create(&td.path().join("failed.db")).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    let id = db::fetch_unsynced_events(&conn).unwrap()[0].id;
+
+    assert_eq!(
+        db::record_event_failure(&mut conn, &[id], "HTTP 400", 2).unwrap(),
+        0
+    );
+    assert_eq!(db::fetch_unsynced_events(&conn).unwrap().len(), 1);
+    assert_eq!(
+        db::record_event_failure(&mut conn, &[id], "HTTP 400 again", 2).unwrap(),
+        1
+    );
+
+    // failed events are skipped by the worker but still listed, with the error
+    assert!(db::fetch_unsynced_events(&conn).unwrap().is_empty());
+    let failed = db::fetch_failed_events(&conn).unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].event.id, id);
+    assert_eq!(failed[0].attempts, 2);
+    assert_eq!(failed[0].last_error.as_deref(), Some("HTTP 400 again"));
+
+    assert_eq!(db::requeue_failed_events(&conn).unwrap(), 1);
+    assert_eq!(db::fetch_unsynced_events(&conn).unwrap().len(), 1);
+    assert!(db::fetch_failed_events(&conn).unwrap().is_empty());
+}
+
+#[test]
+fn enqueue_dedupes_pending_events() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("dedupe.db")).unwrap();
+    for _ in 0..100 {
+        db::enqueue_event(&conn, "Album", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    }
+    let events = db::fetch_unsynced_events(&conn).unwrap();
+    assert_eq!(events.len(), 1);
+
+    // add then remove of the same track keeps only the remove
+    db::enqueue_event(&conn, "Album", &EventAction::Remove, Some("/m/a.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    db::enqueue_event(&conn, "Other", &EventAction::Create, None, None).unwrap();
+    let events = db::fetch_unsynced_events(&conn).unwrap();
+    let summary: Vec<(String, String)> = events
+        .iter()
+        .map(|e| (e.playlist_name.clone(), format!("{:?}", e.action)))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("Album".to_string(), "Remove".to_string()),
+            ("Album".to_string(), "Create".to_string()),
+            ("Other".to_string(), "Create".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn enqueue_keeps_events_repeated_after_a_delete_and_create() {
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("dedupe.db")).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Delete, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Delete, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    let actions: Vec<String> = db::fetch_unsynced_events(&conn)
+        .unwrap()
+        .iter()
+        .map(|e| format!("{:?}", e.action))
+        .collect();
+    assert_eq!(
+        actions,
+        vec!["Add", "Delete", "Create", "Add", "Delete", "Create"]
+    );
+}
+
+#[test]
+fn queue_export_import_round_trips_every_action() {
+    let td = tempdir().unwrap();
+    let mut conn = db::open_or_create(&td.path().join("src.db")).unwrap();
+    let rename = serde_json::json!({ "from": "Old", "to": "New" }).to_string();
+    db::enqueue_event(&conn, "A", &EventAction::Create, None, None).unwrap();
+    db::enqueue_event(&conn, "A", &EventAction::Add, Some("/m/a.mp3"), None).unwrap();
+    db::enqueue_event(&conn, "A", &EventAction::Remove, Some("/m/b.mp3"), None).unwrap();
+    db::enqueue_event(
+        &conn,
+        "New",
+        &EventAction::Rename {
+            from: "Old".into(),
+            to: "New".into(),
+        },
+        None,
+        Some(&rename),
+    )
+    .unwrap();
+    db::enqueue_event(&conn, "Gone", &EventAction::Delete, None, None).unwrap();
+    db::mark_events_synced(&mut conn, &[1, 2]).unwrap();
+
+    let exported = db::export_events(&conn).unwrap();
+    assert_eq!(exported.len(), 5);
+    let json = serde_json::to_string(&exported).unwrap();
+    let parsed: Vec<models::Event> = serde_json::from_str(&json).unwrap();
+
+    let mut dst = db::open_or_create(&td.path().join("dst.db")).unwrap();
+    assert_eq!(db::import_events(&mut dst, &parsed).unwrap(), 5);
+    let reexported = serde_json::to_value(db::export_events(&dst).unwrap()).unwrap();
+    assert_eq!(reexported, serde_json::to_value(&exported).unwrap());
+    assert_eq!(db::fetch_unsynced_events(&dst).unwrap().len(), 3);
+
+    // A hand-written rename without `extra` still keeps its names.
+    let edited: Vec<models::Event> = serde_json::from_value(serde_json::json!([{
+        "id": 9, "timestamp_ms": 5, "playlist_name": "B2",
+        "action": { "Rename": { "from": "B1", "to": "B2" } },
+        "track_path": null, "extra": null, "is_synced": false
+    }]))
+    .unwrap();
+    db::import_events(&mut dst, &edited).unwrap();
+    let last = db::export_events(&dst).unwrap().pop().unwrap();
+    assert_eq!(last.id, 9);
+    match last.action {
+        EventAction::Rename { from, to } => assert_eq!((from.as_str(), to.as_str()), ("B1", "B2")),
+        other => panic!("unexpected action {:?}", other),
+    }
+}
+
+#[test]
+fn resync_all_forgets_remote_state_but_keeps_isrcs_and_overrides() {
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = db::open_or_create(&db_path).unwrap();
+    db::upsert_playlist_map(&conn, "spotify", "Rock", "sp1").unwrap();
+    db::upsert_playlist_map(&conn, "tidal", "Jazz", "td1").unwrap();
+    db::upsert_remote_snapshot(
+        &conn,
+        "spotify",
+        "Rock",
+        "sp1",
+        &["spotify:track:1".into()],
+        1,
+    )
+    .unwrap();
+    db::upsert_remote_snapshot(&conn, "tidal", "Jazz", "td1", &["tidal:track:1".into()], 1)
+        .unwrap();
+    db::upsert_track_cache(
+        &conn,
+        "spotify",
+        "/m/a.mp3",
+        Some("ISRC1"),
+        Some("spotify:track:1"),
+    )
+    .unwrap();
+    db::upsert_track_cache(&conn, "spotify", "/m/b.mp3", None, Some("spotify:track:2")).unwrap();
+    db::mark_track_unresolved(&conn, "spotify", "/m/c.mp3", None).unwrap();
+    db::upsert_track_cache(
+        &conn,
+        "tidal",
+        "/m/a.mp3",
+        Some("ISRC1"),
+        Some("tidal:track:1"),
+    )
+    .unwrap();
+    db::set_override(&conn, "spotify", "/m/d.mp3", "spotify:track:4").unwrap();
+
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        td.path().join("root"),
+        db_path
+    ))
+    .unwrap();
+    let (reset, queued) =
+        music_file_playlist_online_sync::worker::resync_all(&cfg, Some("Spotify")).unwrap();
+    assert_eq!((reset.snapshots, reset.track_matches, queued), (1, 3, 1));
+
+    assert!(db::get_remote_snapshot(&conn, "spotify", "Rock")
+        .unwrap()
+        .is_none());
+    assert!(db::get_remote_snapshot(&conn, "tidal", "Jazz")
+        .unwrap()
+        .is_some());
+    // The ISRC survives without its match; rows holding only a match go.
+    let (isrc, remote, _) = db::get_track_cache_by_local(&conn, "spotify", "/m/a.mp3")
+        .unwrap()
+        .unwrap();
+    assert_eq!((isrc.as_deref(), remote), (Some("ISRC1"), None));
+    assert!(db::get_track_cache_by_local(&conn, "spotify", "/m/b.mp3")
+        .unwrap()
+        .is_none());
+    assert!(db::get_track_unresolved_at(&conn, "spotify", "/m/c.mp3")
+        .unwrap()
+        .is_none());
+    let (_, tidal_remote, _) = db::get_track_cache_by_local(&conn, "tidal", "/m/a.mp3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(tidal_remote.as_deref(), Some("tidal:track:1"));
+    assert_eq!(
+        db::get_override(&conn, "spotify", "/m/d.mp3")
+            .unwrap()
+            .as_deref(),
+        Some("spotify:track:4")
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "spotify", "Rock")
+            .unwrap()
+            .as_deref(),
+        Some("sp1")
+    );
+
+    let events = db::fetch_unsynced_events(&conn).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].playlist_name, "Rock");
+    assert!(matches!(events[0].action, EventAction::Create));
+}
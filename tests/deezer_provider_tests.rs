@@ -0,0 +1,182 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::deezer::DeezerProvider;
+use music_file_playlist_online_sync::api::{Provider, ProviderError};
+use music_file_playlist_online_sync::db;
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use serde_json::json;
+use std::env;
+use std::sync::Mutex;
+use tempfile::{tempdir, TempDir};
+
+// These tests point DEEZER_API_BASE at a per-test mockito server, so they
+// must not run concurrently.
+static DEEZER_TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Prepare a DB with a permanent token and return a provider using it.
+fn provider_with_token(td: &TempDir) -> DeezerProvider {
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": 0,
+        "refresh_token": null,
+        "scope": "basic_access,manage_library"
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "deezer", &stored, Some("app"), Some("secret")).unwrap();
+    DeezerProvider::new(db_path, Default::default())
+}
+
+#[test]
+fn deezer_search_and_isrc_lookup_return_track_uris() {
+    let _guard = DEEZER_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("DEEZER_API_BASE", server.url());
+
+    let search = server
+        .mock("GET", "/search/track")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("access_token".into(), "valid".into()),
+            Matcher::UrlEncoded("q".into(), "artist:\"Artist\" track:\"Song\"".into()),
+        ]))
+        .with_status(200)
+        .with_body(
+            json!({ "data": [ {
+                "id": 3135556,
+                "title": "Song",
+                "duration": 200,
+                "artist": { "name": "Artist" },
+                "album": { "title": "Album" }
+            } ] })
+            .to_string(),
+        )
+        .create();
+    let by_isrc = server
+        .mock("GET", "/track/isrc:GBAAA0000001")
+        .match_query(Matcher::UrlEncoded("access_token".into(), "valid".into()))
+        .with_status(200)
+        .with_body(json!({ "id": 42, "isrc": "GBAAA0000001" }).to_string())
+        .create();
+    let _unknown = server
+        .mock("GET", "/track/isrc:GBAAA0000002")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "error": { "type": "DataException", "message": "no data", "code": 800 } })
+                .to_string(),
+        )
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    assert!(provider.is_authenticated());
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let candidates = rt
+        .block_on(provider.search_track_candidates("Song", "Artist", None, 5))
+        .unwrap();
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].uri, "deezer:track:3135556");
+    assert_eq!(candidates[0].duration_ms, Some(200_000));
+    assert_eq!(candidates[0].album.as_deref(), Some("Album"));
+    search.assert();
+
+    let found = rt
+        .block_on(provider.search_track_uri_by_isrc("GBAAA0000001"))
+        .unwrap();
+    assert_eq!(found.as_deref(), Some("deezer:track:42"));
+    by_isrc.assert();
+    let missing = rt
+        .block_on(provider.search_track_uri_by_isrc("GBAAA0000002"))
+        .unwrap();
+    assert_eq!(missing, None);
+}
+
+#[test]
+fn deezer_lists_pages_and_sends_track_ids() {
+    let _guard = DEEZER_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("DEEZER_API_BASE", server.url());
+
+    let _p1 = server
+        .mock("GET", "/playlist/7/tracks")
+        .match_query(Matcher::UrlEncoded("index".into(), "0".into()))
+        .with_status(200)
+        .with_body(json!({ "data": [ { "id": 1 }, { "id": 2 } ], "next": "more" }).to_string())
+        .create();
+    let _p2 = server
+        .mock("GET", "/playlist/7/tracks")
+        .match_query(Matcher::UrlEncoded("index".into(), "2".into()))
+        .with_status(200)
+        .with_body(json!({ "data": [ { "id": 1 }, { "id": 3 } ] }).to_string())
+        .create();
+    let add = server
+        .mock("POST", "/playlist/7/tracks")
+        .match_query(Matcher::UrlEncoded("songs".into(), "4,5".into()))
+        .with_status(200)
+        .with_body("true")
+        .create();
+    let remove = server
+        .mock("DELETE", "/playlist/7/tracks")
+        .match_query(Matcher::UrlEncoded("songs".into(), "1".into()))
+        .with_status(200)
+        .with_body("true")
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let tracks = rt.block_on(provider.list_playlist_tracks("7")).unwrap();
+    assert_eq!(
+        tracks,
+        vec!["deezer:track:1", "deezer:track:2", "deezer:track:3"]
+    );
+    rt.block_on(provider.add_tracks("7", &["deezer:track:4".into(), "deezer:track:5".into()]))
+        .unwrap();
+    rt.block_on(provider.remove_tracks("7", &["deezer:track:1".into()]))
+        .unwrap();
+    add.assert();
+    remove.assert();
+}
+
+#[test]
+fn deezer_error_objects_map_to_provider_errors() {
+    let _guard = DEEZER_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("DEEZER_API_BASE", server.url());
+
+    let _quota = server
+        .mock("GET", "/search/track")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "error": { "type": "Exception", "message": "Quota limit exceeded", "code": 4 } })
+                .to_string(),
+        )
+        .create();
+    let _missing = server
+        .mock("POST", "/playlist/gone/tracks")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "error": { "type": "DataException", "message": "no data", "code": 800 } })
+                .to_string(),
+        )
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let err = rt
+        .block_on(provider.search_track_uri("Song", "Artist"))
+        .unwrap_err();
+    assert!(matches!(err, ProviderError::RateLimited { .. }));
+    let err = rt
+        .block_on(provider.add_tracks("gone", &["deezer:track:1".into()]))
+        .unwrap_err();
+    assert!(
+        matches!(err, ProviderError::PlaylistNotFound { ref playlist_id } if playlist_id == "gone")
+    );
+}
@@ -0,0 +1,165 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::subsonic::SubsonicCredentials;
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::db;
+use music_file_playlist_online_sync::troubleshoot::{auth_status, doctor, explain_config};
+use serde_json::json;
+use tempfile::tempdir;
+
+fn write_config(dir: &std::path::Path) -> std::path::PathBuf {
+    let root = dir.join("music");
+    std::fs::create_dir_all(&root).unwrap();
+    let cfg_path = dir.join("cfg.toml");
+    std::fs::write(
+        &cfg_path,
+        format!(
+            "root_folder = {:?}\ndb_path = {:?}\nlog_dir = {:?}\n",
+            root,
+            dir.join("test.db"),
+            dir.join("logs")
+        ),
+    )
+    .unwrap();
+    cfg_path
+}
+
+#[tokio::test]
+async fn doctor_passes_with_working_credentials() {
+    let mut server = Server::new_async().await;
+    let td = tempdir().unwrap();
+    let cfg_path = write_config(td.path());
+    let conn = db::open_or_create(&td.path().join("test.db")).unwrap();
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+
+    let ping = server
+        .mock("GET", "/rest/ping")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "subsonic-response": { "status": "ok", "version": "1.16.1" } }).to_string(),
+        )
+        .create_async()
+        .await;
+
+    assert!(doctor(&cfg_path).await);
+    ping.assert_async().await;
+    assert!(td.path().join("logs").is_dir());
+}
+
+#[tokio::test]
+async fn doctor_fails_without_credentials_or_valid_config() {
+    let td = tempdir().unwrap();
+    let cfg_path = write_config(td.path());
+    // DB, schema and folders are fine, but nothing can be synced.
+    assert!(!doctor(&cfg_path).await);
+
+    std::fs::write(
+        &cfg_path,
+        "root_folder = \"/music\"\nwatch_mode = \"bogus\"\n",
+    )
+    .unwrap();
+    assert!(!doctor(&cfg_path).await);
+    assert!(!doctor(&td.path().join("missing.toml")).await);
+}
+
+#[test]
+fn explain_shows_names_per_provider_structure() {
+    let td = tempdir().unwrap();
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = \"/music\"\ndb_path = {:?}\n\
+         online_root_playlist = \"Syncd\"\n\
+         online_playlist_structure = \"folders\"\n\
+         online_folder_flattening_delimiter = \"| \"\n\
+         remote_playlist_template_flat = \"${{path_to_parent}}${{folder_name}}\"\n\
+         enabled_providers = [\"spotify\", \"tidal\"]\n",
+        td.path().join("test.db")
+    ))
+    .unwrap();
+    let out = explain_config(&cfg, &["Rock/Album1".to_string()]).unwrap();
+    assert!(
+        out.contains("online_folder_flattening_delimiter: \"| \"\n"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("template (folders): \"${relative_path}\" (remote_playlist_template)\n"),
+        "{}",
+        out
+    );
+    // Spotify nests folders; Tidal cannot, so it flattens with the delimiter.
+    assert!(
+        out.contains("spotify (folder nesting supported; uses folders structure):\n  \"Rock/Album1\" -> \"Syncd/Rock/Album1\"\n"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("tidal (folder nesting unsupported; uses flat structure):\n  \"Rock/Album1\" -> \"Syncd| Rock| Album1\"\n"),
+        "{}",
+        out
+    );
+    assert!(!out.contains("deezer"), "{}", out);
+}
+
+#[tokio::test]
+async fn auth_status_probes_stored_tokens_and_flags_missing_ones() {
+    let mut server = Server::new_async().await;
+    std::env::set_var("SPOTIFY_API_BASE", server.url());
+    let td = tempdir().unwrap();
+    let conn = db::open_or_create(&td.path().join("test.db")).unwrap();
+    let token = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "rt",
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &token, Some("cid"), Some("cs")).unwrap();
+    let me = server
+        .mock("GET", "/me")
+        .match_header("authorization", "Bearer valid")
+        .with_status(200)
+        .with_body(json!({ "id": "alice" }).to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    let mut cfg = Config {
+        db_path: td.path().join("test.db"),
+        enabled_providers: vec!["spotify".into(), "tidal".into()],
+        ..Default::default()
+    };
+    let (report, usable) = auth_status(&cfg).await.unwrap();
+    assert!(!usable, "{}", report);
+    assert!(
+        report.contains("spotify [enabled]\n  token: stored\n"),
+        "{}",
+        report
+    );
+    assert!(report.contains("(valid for 59 min)") || report.contains("(valid for 60 min)"));
+    assert!(report.contains("  refresh token: yes\n"), "{}", report);
+    assert!(
+        report.contains("  probe: GET /me ok (user alice)\n"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("tidal [enabled]: no credentials stored"),
+        "{}",
+        report
+    );
+
+    cfg.enabled_providers = vec!["spotify".into()];
+    let (report, usable) = auth_status(&cfg).await.unwrap();
+    assert!(usable, "{}", report);
+    assert!(!report.contains("tidal"));
+    me.assert_async().await;
+
+    // The token is only read: nothing was refreshed or rewritten.
+    let (stored, _, _) = db::load_credential_with_client(&conn, "spotify")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored, token);
+}
@@ -0,0 +1,25 @@
+//! Kept in its own test binary because it changes the process-wide CWD.
+
+use tempfile::tempdir;
+
+use music_file_playlist_online_sync::db;
+
+#[test]
+fn migrations_do_not_depend_on_cwd() {
+    let cwd = tempdir().unwrap();
+    let data = tempdir().unwrap();
+    let original = std::env::current_dir().unwrap();
+    std::env::set_current_dir(cwd.path()).unwrap();
+
+    let result = db::open_or_create(&data.path().join("test.db")).map(|conn| {
+        conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type='table' AND name='event_queue'",
+            [],
+            |r| r.get::<_, i64>(0),
+        )
+        .unwrap()
+    });
+
+    std::env::set_current_dir(original).unwrap();
+    assert_eq!(result.expect("migrations should succeed"), 1);
+}
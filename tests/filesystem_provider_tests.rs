@@ -0,0 +1,86 @@
+use music_file_playlist_online_sync::api::filesystem::FileSystemProvider;
+use music_file_playlist_online_sync::api::Provider;
+use music_file_playlist_online_sync::config::Config;
+use tempfile::tempdir;
+
+#[test]
+fn filesystem_playlists_keep_their_id_across_renames() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("music");
+    let out = td.path().join("playlists");
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\nenabled_providers = [\"filesystem\"]\n\
+         filesystem_export_dir = {:?}\nfilesystem_export_path_prefix = \"/music/\"\n",
+        root, out
+    ))
+    .unwrap();
+    cfg.validate().unwrap();
+    let provider = FileSystemProvider::new(cfg);
+    assert!(provider.is_authenticated());
+
+    let song = root.join("Rock").join("Album1").join("a.mp3");
+    assert_eq!(provider.server_path(&song), "/music/Rock/Album1/a.mp3");
+    assert_eq!(
+        provider.local_track_uri(&song).as_deref(),
+        Some("/music/Rock/Album1/a.mp3")
+    );
+    let outside = td.path().join("elsewhere.mp3");
+    assert_eq!(
+        provider.server_path(&outside),
+        outside.display().to_string()
+    );
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let id = rt
+        .block_on(provider.ensure_playlist("Rock/Album1", ""))
+        .unwrap();
+    assert_eq!(
+        rt.block_on(provider.ensure_playlist("Rock/Album1", ""))
+            .unwrap(),
+        id
+    );
+    rt.block_on(provider.add_tracks(&id, &["/music/a.mp3".into(), "/music/c.mp3".into()]))
+        .unwrap();
+    rt.block_on(provider.add_tracks_at(
+        &id,
+        &["/music/b.mp3".into(), "/music/a.mp3".into()],
+        Some(1),
+    ))
+    .unwrap();
+    assert_eq!(
+        rt.block_on(provider.list_playlist_tracks(&id)).unwrap(),
+        vec!["/music/a.mp3", "/music/b.mp3", "/music/c.mp3"]
+    );
+    let file = out.join("Rock - Album1.m3u");
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "#EXTM3U\n#PLAYLIST:Rock/Album1\n/music/a.mp3\n/music/b.mp3\n/music/c.mp3\n"
+    );
+
+    rt.block_on(provider.rename_playlist(&id, "Rock/Best Of"))
+        .unwrap();
+    assert!(!file.exists());
+    assert!(out.join("Rock - Best Of.m3u").exists());
+    assert_eq!(
+        rt.block_on(provider.playlist_is_valid(&id)).unwrap(),
+        Some("Rock/Best Of".to_string())
+    );
+    rt.block_on(provider.remove_tracks(&id, &["/music/b.mp3".into()]))
+        .unwrap();
+    rt.block_on(provider.reorder_tracks(&id, &["/music/c.mp3".into()]))
+        .unwrap();
+    assert_eq!(
+        rt.block_on(provider.list_playlist_tracks(&id)).unwrap(),
+        vec!["/music/c.mp3", "/music/a.mp3"]
+    );
+
+    // The old name's stem is still the renamed playlist's id.
+    let other = rt
+        .block_on(provider.ensure_playlist("Rock/Album1", ""))
+        .unwrap();
+    assert_ne!(other, id);
+
+    rt.block_on(provider.delete_playlist(&id)).unwrap();
+    assert!(!out.join("Rock - Best Of.m3u").exists());
+    assert_eq!(rt.block_on(provider.playlist_is_valid(&id)).unwrap(), None);
+}
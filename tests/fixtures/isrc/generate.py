@@ -0,0 +1,97 @@
+#!/usr/bin/env python3
+"""Regenerate the minimal tagged audio files used by util_tests.rs.
+
+Each container carries the same ISRC written the way a different tagger
+would store it. The files hold no real audio.
+"""
+import struct, os
+out=os.path.dirname(os.path.abspath(__file__))
+
+# ---------- MP3 (ID3v2.3 TSRC + a few MPEG frames) ----------
+def syncsafe(n):
+    return bytes([(n>>21)&0x7f,(n>>14)&0x7f,(n>>7)&0x7f,n&0x7f])
+def id3_frame(fid, text):
+    data=b'\x00'+text.encode('latin-1')
+    return fid.encode()+struct.pack('>I',len(data))+b'\x00\x00'+data
+frames=id3_frame('TIT2','Song')+id3_frame('TPE1','Artist')+id3_frame('TSRC','gb-aaa-00-00001')
+id3=b'ID3\x03\x00\x00'+syncsafe(len(frames))+frames
+mpeg=b''
+for _ in range(4):
+    mpeg+=b'\xff\xfb\x90\x64'+b'\x00'*(417-4)
+open(f'{out}/isrc.mp3','wb').write(id3+mpeg)
+
+# ---------- FLAC (STREAMINFO + VORBIS_COMMENT) ----------
+def vorbis_comments(comments, vendor=b'fixture'):
+    b=struct.pack('<I',len(vendor))+vendor+struct.pack('<I',len(comments))
+    for c in comments:
+        c=c.encode(); b+=struct.pack('<I',len(c))+c
+    return b
+# bitfields: sample rate 20, channels-1 3, bps-1 5, total samples 36
+sr, ch, bps, total = 44100, 1, 15, 44100
+bits = (sr<<44)|(ch<<41)|(bps<<36)|total
+streaminfo=struct.pack('>HH',4096,4096)+b'\x00'*6+bits.to_bytes(8,'big')+b'\x00'*16
+assert len(streaminfo)==34
+vc=vorbis_comments(['TITLE=Song','ARTIST=Artist','ISRC=GB-AAA-00-00001'])
+flac=b'fLaC'+bytes([0])+len(streaminfo).to_bytes(3,'big')+streaminfo+bytes([0x80|4])+len(vc).to_bytes(3,'big')+vc
+open(f'{out}/isrc.flac','wb').write(flac)
+
+# ---------- Ogg Vorbis (ident, comment, setup packets) ----------
+def crc_table():
+    t=[]
+    for i in range(256):
+        r=i<<24
+        for _ in range(8):
+            r=((r<<1)^0x04c11db7) if r&0x80000000 else (r<<1)
+        t.append(r&0xffffffff)
+    return t
+T=crc_table()
+def ogg_crc(data):
+    c=0
+    for b in data:
+        c=((c<<8)&0xffffffff)^T[((c>>24)&0xff)^b]
+    return c
+def ogg_page(packet_data_list, seq, flags, granule, serial=0x1234):
+    segs=[]; body=b''
+    for p in packet_data_list:
+        n=len(p)
+        while n>=255:
+            segs.append(255); n-=255
+        segs.append(n); body+=p
+    hdr=b'OggS'+bytes([0,flags])+struct.pack('<qII',granule,serial,seq)+b'\x00\x00\x00\x00'+bytes([len(segs)])+bytes(segs)
+    page=hdr+body
+    c=ogg_crc(page)
+    return page[:22]+struct.pack('<I',c)+page[26:]
+ident=b'\x01vorbis'+struct.pack('<IBIiiiB',0,2,44100,0,128000,0,0xB8)+b'\x01'
+comment=b'\x03vorbis'+vorbis_comments(['TITLE=Song','ARTIST=Artist','ISRC= gbaaa0000001 '])+b'\x01'
+setup=b'\x05vorbis'+b'\x00'*32
+ogg=ogg_page([ident],0,0x02,0)+ogg_page([comment,setup],1,0x00,0)+ogg_page([b'\x00'*8],2,0x04,44100)
+open(f'{out}/isrc.ogg','wb').write(ogg)
+
+# ---------- M4A (ftyp + moov/mvhd + trak + udta/meta/ilst) ----------
+def atom(name, payload):
+    return struct.pack('>I',8+len(payload))+name+payload
+def full(name, payload, ver=0, flags=0):
+    return atom(name, bytes([ver])+flags.to_bytes(3,'big')+payload)
+ftyp=atom(b'ftyp', b'M4A '+struct.pack('>I',0)+b'M4A isom')
+matrix=struct.pack('>9I',0x10000,0,0,0,0x10000,0,0,0,0x40000000)
+mvhd=full(b'mvhd', struct.pack('>IIII',0,0,1000,1000)+struct.pack('>IH',0x10000,0x100)+b'\x00'*10+matrix+b'\x00'*24+struct.pack('>I',2))
+tkhd=full(b'tkhd', struct.pack('>IIIII',0,0,1,0,1000)+b'\x00'*8+struct.pack('>hhhH',0,0,0x100,0)+matrix+struct.pack('>II',0,0), flags=3)
+mdhd=full(b'mdhd', struct.pack('>IIII',0,0,44100,44100)+struct.pack('>HH',0x55c4,0))
+hdlr_soun=full(b'hdlr', struct.pack('>I',0)+b'soun'+b'\x00'*12+b'\x00')
+esds=full(b'esds', bytes([0x03,0x19])+struct.pack('>HB',1,0)+bytes([0x04,0x11,0x40,0x15])+b'\x00\x00\x00'+struct.pack('>II',128000,128000)+bytes([0x05,0x02,0x12,0x10])+bytes([0x06,0x01,0x02]))
+mp4a=atom(b'mp4a', b'\x00'*6+struct.pack('>H',1)+b'\x00'*8+struct.pack('>HHHHI',2,16,0,0,44100<<16)+esds)
+stsd=full(b'stsd', struct.pack('>I',1)+mp4a)
+stbl=atom(b'stbl', stsd+full(b'stts',struct.pack('>I',0))+full(b'stsc',struct.pack('>I',0))+full(b'stsz',struct.pack('>II',0,0))+full(b'stco',struct.pack('>I',0)))
+minf=atom(b'minf', full(b'smhd',struct.pack('>HH',0,0))+atom(b'dinf',full(b'dref',struct.pack('>I',1)+full(b'url ',b'',flags=1)))+stbl)
+trak=atom(b'trak', tkhd+atom(b'mdia', mdhd+hdlr_soun+minf))
+def data_atom(text):
+    return atom(b'data', struct.pack('>II',1,0)+text.encode())
+ilst=atom(b'ilst',
+    atom(b'\xa9nam', data_atom('Song'))+
+    atom(b'\xa9ART', data_atom('Artist'))+
+    atom(b'----', full(b'mean', b'com.apple.iTunes')+full(b'name', b'ISRC')+data_atom('GB AAA 00 00001')))
+hdlr_mdir=full(b'hdlr', struct.pack('>I',0)+b'mdir'+b'appl'+b'\x00'*8+b'\x00')
+meta=full(b'meta', hdlr_mdir+ilst)
+moov=atom(b'moov', mvhd+trak+atom(b'udta', meta))
+mdat=atom(b'mdat', b'')
+open(f'{out}/isrc.m4a','wb').write(ftyp+moov+mdat)
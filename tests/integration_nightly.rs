@@ -13,9 +13,9 @@ fn nightly_reconcile_enqueues_events() -> Result<(), Box<dyn std::error::Error>>
     // build a minimal config
     let cfg = music_file_playlist_online_sync::config::Config {
         root_folder: root.clone(),
-        whitelist: String::new(),
-        local_whitelist: String::new(),
-        remote_whitelist: String::new(),
+        whitelist: Vec::new(),
+        local_whitelist: Vec::new(),
+        remote_whitelist: Vec::new(),
         local_playlist_template: "${folder_name}.m3u".into(),
         remote_playlist_template: "${relative_path}".into(),
         remote_playlist_template_flat: String::new(),
@@ -23,21 +23,57 @@ fn nightly_reconcile_enqueues_events() -> Result<(), Box<dyn std::error::Error>>
         playlist_description_template: String::new(),
         playlist_order_mode: "append".into(),
         playlist_mode: "flat".into(),
+        playlist_format: "m3u".into(),
+        playlist_write_threads: 4,
         linked_reference_format: "relative".into(),
         debounce_ms: 100,
         watcher_instant_trigger_threshold: 20,
         watcher_deferred_trigger_delay_sec: 300,
         log_dir: tmp.path().join("logs"),
+        log_level: "info".into(),
+        log_format: "text".into(),
         token_refresh_interval: 3600,
+        credentials_key_file: None,
         nightly_reconcile_cron: "0 3 * * *".into(),
         queue_length_stop_cloud_sync_threshold: None,
         max_retries_on_error: 3,
         max_batch_size_spotify: 100,
         max_batch_size_tidal: 20,
+        synced_event_retention_secs: 7 * 24 * 3600,
+        poll_interval_sec: 60,
+        metrics_listen: String::new(),
+        watch_mode: "inotify".into(),
+        ignore_patterns: Vec::new(),
+        no_playlist_marker: ".noplaylist".into(),
+        local_only_marker: ".localonly".into(),
+        root_folders: Vec::new(),
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        spotify_market: String::new(),
+        spotify_scopes: Vec::new(),
+        tidal_country_code: "US".into(),
+        tidal_locale: "en-US".into(),
+        unresolved_retry_secs: 30 * 24 * 3600,
+        match_duration_tolerance_secs: 10,
+        min_match_confidence: 0.0,
+        search_strip_tokens: Vec::new(),
+        search_strip_diacritics: true,
+        enable_musicbrainz: false,
+        enabled_providers: Vec::new(),
+        filesystem_export_dir: std::path::PathBuf::new(),
+        filesystem_export_path_prefix: String::new(),
+        max_concurrent_providers: 4,
+        remote_snapshot_max_age_secs: 24 * 3600,
+        lock_ttl_secs: 600,
+        backoff_base_secs: 1,
+        backoff_max_secs: 60,
         file_extensions: vec!["*.mp3".into()],
         online_root_playlist: String::new(),
         online_playlist_structure: "flat".into(),
         online_folder_flattening_delimiter: String::new(),
+        filename_parse_regex: String::new(),
+        strip_filename_copy_suffix: true,
+        filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
         db_path: tmp.path().join("test.db"),
     };
 
@@ -54,3 +90,209 @@ fn nightly_reconcile_enqueues_events() -> Result<(), Box<dyn std::error::Error>>
 
     Ok(())
 }
+
+#[test]
+fn nightly_reconcile_keys_playlists_per_root() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempfile::tempdir()?;
+    let music = tmp.path().join("music");
+    let archive = tmp.path().join("archive");
+    for album in [music.join("Album1"), archive.join("Album1")] {
+        std::fs::create_dir_all(&album)?;
+        std::fs::write(album.join("01 - Test Track.mp3"), b"")?;
+    }
+
+    let toml = format!(
+        "root_folder = {:?}\nroot_folders = [{:?}]\ndb_path = {:?}\n",
+        music,
+        archive,
+        tmp.path().join("test.db")
+    );
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&toml)?;
+    cfg.validate()?;
+    assert_eq!(
+        cfg.playlist_key_for_folder(&archive.join("Album1")),
+        "archive/Album1"
+    );
+    assert_eq!(
+        cfg.playlist_folder_for_key("archive/Album1"),
+        archive.join("Album1")
+    );
+    assert_eq!(cfg.playlist_folder_for_key("Album1"), music.join("Album1"));
+
+    music_file_playlist_online_sync::worker::run_nightly_reconcile(&cfg)?;
+
+    // Both roots get their local playlist written...
+    assert!(music.join("Album1").join("Album1.m3u").exists());
+    assert!(archive.join("Album1").join("Album1.m3u").exists());
+    // ...and enqueue distinct playlist keys.
+    let conn = music_file_playlist_online_sync::db::open_or_create(&cfg.db_path)?;
+    let mut stmt = conn.prepare("SELECT DISTINCT playlist_name FROM event_queue")?;
+    let mut names: Vec<String> = stmt
+        .query_map([], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    names.sort();
+    assert!(names.contains(&"Album1".to_string()), "{:?}", names);
+    assert!(names.contains(&"archive/Album1".to_string()), "{:?}", names);
+
+    Ok(())
+}
+
+#[test]
+fn changed_only_reconcile_enqueues_only_changed_folders() -> Result<(), Box<dyn std::error::Error>>
+{
+    use music_file_playlist_online_sync::{db, worker};
+    let tmp = tempfile::tempdir()?;
+    let root = tmp.path().join("root");
+    // Tracks modified in the same second as a scan count as changed, so
+    // backdate them.
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    for album in ["Album1", "Album2"] {
+        std::fs::create_dir_all(root.join(album))?;
+        let track = root.join(album).join("01 - Test Track.mp3");
+        std::fs::write(&track, b"")?;
+        std::fs::File::options()
+            .write(true)
+            .open(&track)?
+            .set_modified(an_hour_ago)?;
+    }
+    let toml = format!(
+        "root_folder = {:?}\ndb_path = {:?}\nfile_extensions = [\"*.mp3\"]\n",
+        root,
+        tmp.path().join("test.db")
+    );
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&toml)?;
+    let conn = db::open_or_create(&cfg.db_path)?;
+    let queued = |conn: &rusqlite::Connection| -> rusqlite::Result<Vec<String>> {
+        let mut stmt =
+            conn.prepare("SELECT DISTINCT playlist_name FROM event_queue ORDER BY playlist_name")?;
+        let names = stmt.query_map([], |r| r.get(0))?.collect();
+        names
+    };
+
+    // Without a previous scan everything (including the root) is reconciled.
+    worker::run_reconcile_scan(&cfg, true)?;
+    assert_eq!(queued(&conn)?, vec!["", "Album1", "Album2"]);
+    for album in ["", "Album1", "Album2"] {
+        db::upsert_playlist_map(&conn, "mock", album, &format!("id-{}", album))?;
+    }
+    conn.execute("DELETE FROM event_queue", [])?;
+
+    // Nothing changed since: nothing is enqueued.
+    worker::run_reconcile_scan(&cfg, true)?;
+    assert!(queued(&conn)?.is_empty());
+
+    // A new track changes the playlist entries of its folder and of the
+    // root, whose flat playlist includes subfolders.
+    std::fs::write(root.join("Album2").join("02 - New.mp3"), b"")?;
+    worker::run_reconcile_scan(&cfg, true)?;
+    assert_eq!(queued(&conn)?, vec!["", "Album2"]);
+    conn.execute("DELETE FROM event_queue", [])?;
+
+    // A full reconcile still enqueues every folder.
+    worker::run_nightly_reconcile(&cfg)?;
+    assert_eq!(queued(&conn)?, vec!["", "Album1", "Album2"]);
+
+    Ok(())
+}
+
+#[test]
+fn reconcile_writes_every_playlist_from_several_threads() -> Result<(), Box<dyn std::error::Error>>
+{
+    let tmp = tempfile::tempdir()?;
+    let root = tmp.path().join("root");
+    let albums: Vec<String> = (1..=12).map(|i| format!("Album{}", i)).collect();
+    for album in &albums {
+        std::fs::create_dir_all(root.join(album))?;
+        std::fs::write(root.join(album).join("01 - Test Track.mp3"), b"")?;
+    }
+    let toml = format!(
+        "root_folder = {:?}\ndb_path = {:?}\nplaylist_write_threads = 3\n",
+        root,
+        tmp.path().join("test.db")
+    );
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&toml)?;
+    cfg.validate()?;
+
+    music_file_playlist_online_sync::worker::run_nightly_reconcile(&cfg)?;
+
+    let conn = music_file_playlist_online_sync::db::open_or_create(&cfg.db_path)?;
+    let mut stmt = conn.prepare("SELECT DISTINCT playlist_name FROM event_queue")?;
+    let names: Vec<String> = stmt
+        .query_map([], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    assert_eq!(names.len(), albums.len() + 1, "{:?}", names);
+    for album in &albums {
+        assert!(root.join(album).join(format!("{}.m3u", album)).exists());
+        assert!(names.contains(album), "{:?}", names);
+    }
+
+    let zero: music_file_playlist_online_sync::config::Config =
+        toml::from_str("root_folder = \".\"\nplaylist_write_threads = 0\n")?;
+    assert!(zero.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn local_only_folders_get_a_playlist_but_are_never_synced() -> Result<(), Box<dyn std::error::Error>>
+{
+    use music_file_playlist_online_sync::api::subsonic::SubsonicCredentials;
+    use music_file_playlist_online_sync::models::EventAction;
+    use music_file_playlist_online_sync::{db, worker};
+    let mut server = mockito::Server::new();
+    let tmp = tempfile::tempdir()?;
+    let root = tmp.path().join("root");
+    for album in ["Album1", "Local"] {
+        std::fs::create_dir_all(root.join(album))?;
+        std::fs::write(root.join(album).join("01 - Test Track.mp3"), b"")?;
+    }
+    std::fs::write(root.join("Local").join(".localonly"), b"")?;
+    let toml = format!(
+        "root_folder = {:?}\ndb_path = {:?}\nfile_extensions = [\"*.mp3\"]\n",
+        root,
+        tmp.path().join("test.db")
+    );
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&toml)?;
+    assert!(cfg.is_local_only(&root.join("Local")));
+    assert!(!cfg.is_local_only(&root.join("Album1")));
+
+    worker::run_nightly_reconcile(&cfg)?;
+    assert!(root.join("Local").join("Local.m3u").exists());
+    let conn = db::open_or_create(&cfg.db_path)?;
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT playlist_name FROM event_queue ORDER BY playlist_name")?;
+    let names: Vec<String> = stmt
+        .query_map([], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    assert_eq!(names, vec!["", "Album1"]);
+
+    // Events queued anyway are dropped without calling the provider.
+    conn.execute("DELETE FROM event_queue", [])?;
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    db::save_credential_raw(
+        &conn,
+        "subsonic",
+        &serde_json::to_string(&creds)?,
+        Some("alice"),
+        None,
+    )?;
+    db::enqueue_event(
+        &conn,
+        "Local",
+        &EventAction::Add,
+        Some("uri::subsonic:song:1"),
+        None,
+    )?;
+    let untouched = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(worker::run_worker_once(&cfg, None, false, false))?;
+    untouched.assert();
+    let pending: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM event_queue WHERE is_synced = 0",
+        [],
+        |r| r.get(0),
+    )?;
+    assert_eq!(pending, 0);
+
+    Ok(())
+}
@@ -30,6 +30,34 @@ fn test_try_acquire_release_lock() {
     assert!(got4, "should acquire after release");
 }
 
+#[test]
+fn renew_extends_only_the_owners_lock() {
+    let td = tempdir().unwrap();
+    let mut conn = Connection::open(td.path().join("test.db")).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let expires = |conn: &Connection| -> i64 {
+        conn.query_row(
+            "SELECT expires_at FROM processing_locks WHERE playlist_name = 'pl1'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+
+    assert!(db::try_acquire_playlist_lock(&mut conn, "pl1", "worker1", 10).unwrap());
+    let before = expires(&conn);
+    assert!(db::renew_playlist_lock(&conn, "pl1", "worker1", 600).unwrap());
+    assert!(expires(&conn) >= before + 590);
+
+    // Another worker cannot renew (or steal) the lease.
+    assert!(!db::renew_playlist_lock(&conn, "pl1", "worker2", 6000).unwrap());
+    assert!(expires(&conn) < before + 6000);
+    assert!(!db::try_acquire_playlist_lock(&mut conn, "pl1", "worker2", 10).unwrap());
+
+    db::release_playlist_lock(&mut conn, "pl1", "worker1").unwrap();
+    assert!(!db::renew_playlist_lock(&conn, "pl1", "worker1", 600).unwrap());
+}
+
 #[test]
 fn collapse_add_remove_pair() {
     let a = Event {
@@ -0,0 +1,94 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::subsonic::SubsonicCredentials;
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::models::EventAction;
+use music_file_playlist_online_sync::{db, metrics, worker};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tempfile::tempdir;
+
+fn http_get(addr: &str, path: &str) -> String {
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr);
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn worker_runs_store_counters_that_the_endpoint_serves() {
+    let mut server = Server::new();
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = db::open_or_create(&db_path).unwrap();
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    db::upsert_playlist_map(&conn, "subsonic", "Mapped", "pl1").unwrap();
+    db::enqueue_event(
+        &conn,
+        "Mapped",
+        &EventAction::Add,
+        Some("uri::subsonic:song:1"),
+        None,
+    )
+    .unwrap();
+    let _list = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "subsonic-response": {
+                "status": "ok",
+                "version": "1.16.1",
+                "playlist": { "id": "pl1", "name": "Mapped", "entry": [ { "id": "1" } ] }
+            } })
+            .to_string(),
+        )
+        .create();
+
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nmetrics_listen = \"127.0.0.1:9110\"\n",
+        td.path().join("root"),
+        db_path
+    ))
+    .unwrap();
+    cfg.validate().unwrap();
+    // A dry run only reads the playlist, leaving the event pending.
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(worker::run_worker_once(&cfg, None, false, true))
+        .unwrap();
+    metrics::inc("sync_errors_total", &[]);
+    metrics::flush(&conn).unwrap();
+
+    // Serve on a free port rather than the configured one.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let handle =
+        metrics::serve(&addr, db::create_pool(&db_path).unwrap(), shutdown.clone()).unwrap();
+
+    let response = http_get(&addr, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(
+        response.contains("provider_requests_total{provider=\"subsonic\",status=\"200\"} "),
+        "{}",
+        response
+    );
+    assert!(response.contains("\nsync_errors_total 1\n"), "{}", response);
+    assert!(response.contains("\nevents_pending 1\n"), "{}", response);
+    assert!(response.contains("# TYPE events_pending gauge\n"));
+    assert!(http_get(&addr, "/").starts_with("HTTP/1.1 404"));
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap();
+
+    let bad: Result<Config, _> = toml::from_str("root_folder = \".\"\nmetrics_listen = \"nope\"\n");
+    assert!(bad.unwrap().validate().is_err());
+}
@@ -0,0 +1,64 @@
+use music_file_playlist_online_sync::api::oauth_callback::{
+    code_from_redirect_url, CallbackListener,
+};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// Reserve a free loopback port and return a redirect URI on it.
+fn free_redirect_uri(path: &str) -> (String, u16) {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    (format!("http://127.0.0.1:{}{}", port, path), port)
+}
+
+fn get(port: u16, target: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n", target).unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    resp
+}
+
+#[test]
+fn callback_listener_captures_code_and_ignores_other_paths() {
+    let (redirect_uri, port) = free_redirect_uri("/callback");
+    let listener = CallbackListener::bind(&redirect_uri).expect("bind loopback port");
+    let waiter = std::thread::spawn(move || listener.wait_for_code(Some("st")));
+
+    assert!(get(port, "/favicon.ico").starts_with("HTTP/1.1 404"));
+    assert!(get(port, "/callback?code=abc&state=st").starts_with("HTTP/1.1 200"));
+    assert_eq!(waiter.join().unwrap().unwrap(), "abc");
+}
+
+#[test]
+fn callback_listener_rejects_state_mismatch() {
+    let (redirect_uri, port) = free_redirect_uri("/");
+    let listener = CallbackListener::bind(&redirect_uri).expect("bind loopback port");
+    let waiter = std::thread::spawn(move || listener.wait_for_code(Some("expected")));
+
+    get(port, "/?code=abc&state=forged");
+    assert!(waiter.join().unwrap().is_err());
+}
+
+#[test]
+fn callback_listener_falls_back_for_non_loopback_uri() {
+    assert!(CallbackListener::bind("https://example.com/callback").is_none());
+}
+
+#[test]
+fn pasted_redirect_url_is_parsed() {
+    assert_eq!(
+        code_from_redirect_url("http://127.0.0.1:8888/?code=xyz&state=s", "s").unwrap(),
+        "xyz"
+    );
+    // Providers that do not echo `state` are still accepted.
+    assert_eq!(
+        code_from_redirect_url("http://127.0.0.1:8888/?code=xyz", "s").unwrap(),
+        "xyz"
+    );
+    assert!(code_from_redirect_url("http://127.0.0.1:8888/?code=xyz&state=t", "s").is_err());
+    assert!(code_from_redirect_url("http://127.0.0.1:8888/?error=access_denied", "s").is_err());
+}
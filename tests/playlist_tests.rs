@@ -46,6 +46,43 @@ fn flat_playlist_ordering() {
     assert!(lines2[1].contains("a_song.mp3"));
 }
 
+#[test]
+fn flat_playlist_never_lists_playlist_files() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("Album");
+    fs::create_dir_all(root.join("Disc 2")).unwrap();
+    fs::write(root.join("song.mp3"), b"").unwrap();
+    fs::write(root.join("stray.M3U"), b"#EXTM3U\n").unwrap();
+    fs::write(root.join("Disc 2").join("other.pls"), b"").unwrap();
+    fs::write(root.join("Disc 2").join("other.xspf"), b"").unwrap();
+    // Even when the extensions name them, playlist files are not tracks,
+    // and neither is the playlist being written.
+    let exts: Vec<String> = ["*.mp3", "*.m3u", "*.pls", "*.xspf", "*.list"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let plist = root.join("Album.list");
+    fs::write(&plist, b"old").unwrap();
+    playlist::write_flat_playlist(&root, &plist, "append", &exts).unwrap();
+    let s = fs::read_to_string(&plist).unwrap();
+    let entries: Vec<&str> = s.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(entries, vec!["song.mp3"], "{}", s);
+    assert_eq!(
+        playlist::count_tracks(&root, &exts[..4], &Default::default()),
+        1
+    );
+
+    // The watcher never turns a playlist file into a track event.
+    let mut tree =
+        music_file_playlist_online_sync::watcher::InMemoryTree::build(&root, None, Some(&exts))
+            .unwrap();
+    assert!(!tree.nodes[&root].tracks.contains(&root.join("stray.M3U")));
+    let ops = tree.apply_synthetic_event(
+        music_file_playlist_online_sync::watcher::SyntheticEvent::FolderCreate(root.clone()),
+    );
+    assert_eq!(ops.len(), 1, "{:?}", ops);
+}
+
 #[test]
 fn linked_playlist_children_refs() {
     let td = tempdir().unwrap();
@@ -68,3 +105,349 @@ fn linked_playlist_children_refs() {
     assert_eq!(lines.len(), 2);
     assert!(lines[0].contains("c1.m3u") || lines[1].contains("c1.m3u"));
 }
+
+#[test]
+fn linked_with_local_tracks_lists_children_then_own_tracks() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("Artist");
+    fs::create_dir_all(root.join("Album1")).unwrap();
+    File::create(root.join("Album1").join("01 - Deep.mp3")).unwrap();
+    File::create(root.join("b - Single.mp3")).unwrap();
+    File::create(root.join("a - Single.mp3")).unwrap();
+    File::create(root.join("cover.jpg")).unwrap();
+
+    let out = root.join("Artist.m3u");
+    playlist::write_linked_playlist_with_local_tracks(
+        &root,
+        &out,
+        "relative",
+        "${folder_name}.m3u",
+        "append",
+        &["*.mp3".into()],
+        &Default::default(),
+    )
+    .unwrap();
+    let s = fs::read_to_string(&out).unwrap();
+    assert!(s.starts_with("#EXTM3U\n"));
+    assert!(s.contains("#EXTINF:-1,a - Single.mp3\n"));
+    // Child references come first, then the folder's own tracks in order;
+    // tracks of subfolders are only reached through their playlist.
+    assert_eq!(
+        playlist::read_playlist_entries(&out).unwrap(),
+        vec!["Album1/Album1.m3u", "a - Single.mp3", "b - Single.mp3"]
+    );
+}
+
+/// Write a silent 8 kHz mono 8-bit PCM WAV lasting `secs` seconds.
+fn write_silent_wav(path: &std::path::Path, secs: u32) {
+    let data_len = 8000 * secs;
+    let mut b = Vec::new();
+    b.extend_from_slice(b"RIFF");
+    b.extend_from_slice(&(36 + data_len).to_le_bytes());
+    b.extend_from_slice(b"WAVEfmt ");
+    b.extend_from_slice(&16u32.to_le_bytes());
+    b.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    b.extend_from_slice(&1u16.to_le_bytes()); // mono
+    b.extend_from_slice(&8000u32.to_le_bytes()); // sample rate
+    b.extend_from_slice(&8000u32.to_le_bytes()); // byte rate
+    b.extend_from_slice(&1u16.to_le_bytes()); // block align
+    b.extend_from_slice(&8u16.to_le_bytes()); // bits per sample
+    b.extend_from_slice(b"data");
+    b.extend_from_slice(&data_len.to_le_bytes());
+    b.resize(b.len() + data_len as usize, 0x80);
+    fs::write(path, b).unwrap();
+}
+
+#[test]
+fn flat_playlist_extinf_uses_duration_and_tags() {
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+
+    let td = tempdir().unwrap();
+    let root = td.path();
+    let tagged = root.join("a_tagged.wav");
+    write_silent_wav(&tagged, 3);
+    let mut tf = lofty::probe::read_from_path(&tagged).unwrap();
+    let mut tag = Tag::new(TagType::RiffInfo);
+    tag.set_artist("Some Artist".into());
+    tag.set_title("Some Title".into());
+    tf.insert_tag(tag);
+    tf.save_to_path(&tagged, WriteOptions::default()).unwrap();
+
+    // Untagged but valid audio keeps the file name; garbage keeps -1 too.
+    write_silent_wav(&root.join("b_untagged.wav"), 2);
+    fs::write(root.join("c_broken.wav"), b"not audio").unwrap();
+
+    let plist = root.join("out.m3u");
+    playlist::write_flat_playlist(root, &plist, "append", &["*.wav".to_string()]).unwrap();
+    let s = fs::read_to_string(&plist).unwrap();
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(lines[0], "#EXTM3U");
+    assert_eq!(lines[1], "#EXTINF:3,Some Artist - Some Title");
+    assert_eq!(lines[2], "a_tagged.wav");
+    assert_eq!(lines[3], "#EXTINF:2,b_untagged.wav");
+    assert_eq!(lines[5], "#EXTINF:-1,c_broken.wav");
+    assert_eq!(lines[6], "c_broken.wav");
+}
+
+#[test]
+fn flat_playlist_track_number_order() {
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+
+    let td = tempdir().unwrap();
+    let root = td.path();
+    // (file name, disc, track): alphabetical and tag order disagree.
+    for (name, disc, track) in [("a.wav", 2, 1), ("b.wav", 1, 2), ("c.wav", 1, 1)] {
+        let p = root.join(name);
+        write_silent_wav(&p, 1);
+        let mut tf = lofty::probe::read_from_path(&p).unwrap();
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_disk(disc);
+        tag.set_track(track);
+        tf.insert_tag(tag);
+        tf.save_to_path(&p, WriteOptions::default()).unwrap();
+    }
+    write_silent_wav(&root.join("0_untagged.wav"), 1);
+
+    let plist = root.join("out.m3u");
+    playlist::write_flat_playlist(root, &plist, "track_number", &["*.wav".to_string()]).unwrap();
+    let s = fs::read_to_string(&plist).unwrap();
+    let tracks: Vec<&str> = s.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(tracks, vec!["c.wav", "b.wav", "a.wav", "0_untagged.wav"]);
+}
+
+/// Minimal XML well-formedness check: one root element, balanced and
+/// properly nested tags, and only known entity references in text.
+fn assert_well_formed_xml(xml: &str) {
+    let body = xml
+        .strip_prefix("<?xml version=\"1.0\" encoding=\"UTF-8\"?>")
+        .expect("xml declaration");
+    let mut stack: Vec<String> = Vec::new();
+    let mut roots = 0;
+    let mut rest = body;
+    while let Some(lt) = rest.find('<') {
+        let text = &rest[..lt];
+        assert!(!text.contains('>'), "stray '>' in {:?}", text);
+        for entity in text.split('&').skip(1) {
+            let name = &entity[..entity.find(';').expect("unterminated entity")];
+            assert!(
+                ["amp", "lt", "gt", "quot", "apos"].contains(&name) || name.starts_with('#'),
+                "unknown entity {}",
+                name
+            );
+        }
+        let gt = rest[lt..].find('>').expect("unterminated tag") + lt;
+        let tag = &rest[lt + 1..gt];
+        if let Some(name) = tag.strip_prefix('/') {
+            assert_eq!(stack.pop().as_deref(), Some(name), "mismatched </{}>", name);
+        } else if !tag.ends_with('/') {
+            if stack.is_empty() {
+                roots += 1;
+            }
+            stack.push(tag.split_whitespace().next().unwrap().to_string());
+        }
+        rest = &rest[gt + 1..];
+    }
+    assert!(rest.trim().is_empty());
+    assert!(stack.is_empty(), "unclosed {:?}", stack);
+    assert_eq!(roots, 1);
+}
+
+#[test]
+fn xspf_playlist_round_trip() {
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("Rock & Roll");
+    fs::create_dir_all(root.join("CD 1")).unwrap();
+    let tagged = root.join("CD 1").join("01 <Intro>.wav");
+    write_silent_wav(&tagged, 2);
+    let mut tf = lofty::probe::read_from_path(&tagged).unwrap();
+    let mut tag = Tag::new(TagType::RiffInfo);
+    tag.set_artist("Simon & Garfunkel".into());
+    tag.set_title("\"Intro\" <1>".into());
+    tf.insert_tag(tag);
+    tf.save_to_path(&tagged, WriteOptions::default()).unwrap();
+    write_silent_wav(&root.join("02 Ünïcode.wav"), 1);
+
+    let plist = root.join("Rock & Roll.xspf");
+    playlist::write_flat_playlist_as(
+        "xspf",
+        &root,
+        &plist,
+        "append",
+        &["*.wav".to_string()],
+        &Default::default(),
+    )
+    .unwrap();
+    let xml = fs::read_to_string(&plist).unwrap();
+    assert_well_formed_xml(&xml);
+    assert!(xml.contains("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">"));
+    assert!(xml.contains("<title>Rock &amp; Roll</title>"));
+    assert!(xml.contains("<location>CD%201/01%20%3CIntro%3E.wav</location>"));
+    assert!(xml.contains("<title>&quot;Intro&quot; &lt;1&gt;</title>"));
+    assert!(xml.contains("<creator>Simon &amp; Garfunkel</creator>"));
+    assert!(xml.contains("<duration>2000</duration>"));
+
+    // Reading it back yields the same relative paths the M3U writer emits.
+    let entries = playlist::read_playlist_entries(&plist).unwrap();
+    assert_eq!(entries, vec!["02 Ünïcode.wav", "CD 1/01 <Intro>.wav"]);
+    let m3u = root.join("Rock & Roll.m3u");
+    playlist::write_flat_playlist(&root, &m3u, "append", &["*.wav".to_string()]).unwrap();
+    assert_eq!(playlist::read_playlist_entries(&m3u).unwrap(), entries);
+}
+
+#[test]
+fn pls_playlist_entries_and_edge_cases() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("Album");
+    fs::create_dir_all(root.join("CD2")).unwrap();
+    write_silent_wav(&root.join("01 Song.wav"), 3);
+    // Not decodable: no duration, title falls back to the file name.
+    File::create(root.join("CD2").join("02 Broken.wav")).unwrap();
+
+    let plist = root.join("Album.pls");
+    playlist::write_flat_playlist_as(
+        "pls",
+        &root,
+        &plist,
+        "append",
+        &["*.wav".to_string()],
+        &Default::default(),
+    )
+    .unwrap();
+    let s = fs::read_to_string(&plist).unwrap();
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(lines[0], "[playlist]");
+    assert!(lines.contains(&"File1=01 Song.wav"), "{}", s);
+    assert!(lines.contains(&"Length1=3"), "{}", s);
+    assert!(lines.contains(&"File2=CD2/02 Broken.wav"), "{}", s);
+    assert!(lines.contains(&"Title2=02 Broken.wav"), "{}", s);
+    assert!(lines.contains(&"Length2=-1"), "{}", s);
+    assert_eq!(
+        &lines[lines.len() - 2..],
+        ["NumberOfEntries=2", "Version=2"]
+    );
+    assert_eq!(
+        playlist::read_playlist_entries(&plist).unwrap(),
+        vec!["01 Song.wav", "CD2/02 Broken.wav"]
+    );
+
+    // A folder without tracks still gets a valid, empty playlist.
+    let empty = td.path().join("Empty");
+    fs::create_dir_all(&empty).unwrap();
+    let plist = empty.join("Empty.pls");
+    playlist::write_pls_playlist(
+        &empty,
+        &plist,
+        "append",
+        &["*.wav".to_string()],
+        &Default::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&plist).unwrap(),
+        "[playlist]\nNumberOfEntries=0\nVersion=2\n"
+    );
+    assert!(playlist::read_playlist_entries(&plist).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn playlist_rewrite_is_atomic_and_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let td = tempdir().unwrap();
+    let root = td.path();
+    File::create(root.join("a.mp3")).unwrap();
+    let plist = root.join("list.m3u");
+    fs::write(&plist, "#EXTM3U\nold.mp3\n").unwrap();
+    fs::set_permissions(&plist, fs::Permissions::from_mode(0o640)).unwrap();
+
+    playlist::write_flat_playlist(root, &plist, "append", &["*.mp3".to_string()]).unwrap();
+    let s = fs::read_to_string(&plist).unwrap();
+    assert!(s.contains("a.mp3") && !s.contains("old.mp3"), "{}", s);
+    let mode = fs::metadata(&plist).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o640);
+
+    // Linked playlists go through the same path; no temp file is left behind.
+    fs::create_dir_all(root.join("child")).unwrap();
+    playlist::write_linked_playlist(root, &plist, "relative", "${folder_name}.m3u").unwrap();
+    assert_eq!(fs::read_to_string(&plist).unwrap(), "child/child.m3u\n");
+    let names: Vec<String> = fs::read_dir(root)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert!(names.iter().all(|n| !n.contains(".tmp-")), "{:?}", names);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn latin1_file_names_are_kept_in_playlists() {
+    use music_file_playlist_online_sync::api::filesystem::FileSystemProvider;
+    use music_file_playlist_online_sync::config::Config;
+    use music_file_playlist_online_sync::{db, util, worker};
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    // "Café/Señor.mp3" in Latin-1, which is not valid UTF-8.
+    let album = root.join(OsStr::from_bytes(b"Caf\xe9"));
+    fs::create_dir_all(&album).unwrap();
+    let song = album.join(OsStr::from_bytes(b"Se\xf1or.mp3"));
+    File::create(&song).unwrap();
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nfilesystem_export_dir = {:?}\n",
+        root,
+        td.path().join("test.db"),
+        td.path().join("out")
+    ))
+    .unwrap();
+
+    let key = cfg.playlist_key_for_folder(&album);
+    assert_eq!(key, "Caf\u{FFFD}");
+    assert_eq!(cfg.playlist_folder_for_key(&key), album);
+
+    let plist = album.join(cfg.local_playlist_file_name(&album, "Caf\u{FFFD}", ""));
+    playlist::write_flat_playlist(&album, &plist, "append", &cfg.file_extensions).unwrap();
+    let entries = playlist::read_playlist_entries(&plist).unwrap();
+    assert_eq!(entries, vec!["Se\u{FFFD}or.mp3"]);
+    assert_eq!(util::resolve_lossy_path(&album.join(&entries[0])), song);
+
+    // The worker finds the file behind the lossy entry instead of skipping it.
+    let pool = db::create_pool(&cfg.db_path).unwrap();
+    let provider = std::sync::Arc::new(FileSystemProvider::new(cfg.clone()));
+    let (uris, local_count) =
+        worker::desired_remote_uris_for_playlist(&cfg, &key, provider, &pool, false)
+            .await
+            .unwrap();
+    assert_eq!(local_count, 1);
+    assert_eq!(uris, vec![song.to_string_lossy().to_string()]);
+}
+
+#[test]
+fn mtime_desc_lists_newest_files_first() {
+    let td = tempdir().unwrap();
+    let root = td.path();
+    let now = std::time::SystemTime::now();
+    // a is 1h old, c 2h and b 3h, so neither mtime order is the name order.
+    for (name, hours_ago) in [("a.mp3", 1), ("b.mp3", 3), ("c.mp3", 2)] {
+        let f = File::create(root.join(name)).unwrap();
+        f.set_modified(now - Duration::from_secs(hours_ago * 3600))
+            .unwrap();
+    }
+    let exts = ["*.mp3".to_string()];
+    let tracks = |mode: &str| {
+        let plist = root.join(format!("{}.m3u", mode));
+        playlist::write_flat_playlist(root, &plist, mode, &exts).unwrap();
+        playlist::read_playlist_entries(&plist).unwrap()
+    };
+    assert_eq!(tracks("mtime_desc"), ["a.mp3", "c.mp3", "b.mp3"]);
+    assert_eq!(tracks("sync_order"), ["b.mp3", "c.mp3", "a.mp3"]);
+}
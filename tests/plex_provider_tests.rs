@@ -0,0 +1,203 @@
+use mockito::{Matcher, Server, ServerGuard};
+use music_file_playlist_online_sync::api::plex::{PlexCredentials, PlexProvider};
+use music_file_playlist_online_sync::api::{Provider, ProviderError};
+use serde_json::json;
+
+fn provider_for(server: &Server, library: Option<&str>) -> PlexProvider {
+    let creds = PlexCredentials::new(&format!("{}/", server.url()), "tok", library);
+    PlexProvider::with_credentials(
+        Some(creds),
+        std::path::PathBuf::from("/dev/null"),
+        Default::default(),
+    )
+}
+
+/// Mock `/identity` and `/library/sections` with a photo library and two
+/// music libraries.
+async fn mock_library(server: &mut ServerGuard) -> Vec<mockito::Mock> {
+    vec![
+        server
+            .mock("GET", "/identity")
+            .match_header("x-plex-token", "tok")
+            .with_body(json!({ "MediaContainer": { "machineIdentifier": "m1" } }).to_string())
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/library/sections")
+            .with_body(
+                json!({ "MediaContainer": { "Directory": [
+                    { "key": "1", "type": "photo", "title": "Photos" },
+                    { "key": "2", "type": "artist", "title": "Music" },
+                    { "key": "3", "type": "artist", "title": "Audiobooks" }
+                ] } })
+                .to_string(),
+            )
+            .create_async()
+            .await,
+    ]
+}
+
+#[tokio::test]
+async fn plex_maps_shared_paths_and_searches_the_rest() {
+    let mut server = Server::new_async().await;
+    let _lib = mock_library(&mut server).await;
+    let all = server
+        .mock("GET", "/library/sections/3/all")
+        .match_query(Matcher::UrlEncoded("type".into(), "10".into()))
+        .with_body(
+            json!({ "MediaContainer": { "Metadata": [
+                { "ratingKey": "101", "Media": [ { "Part": [ { "file": "/music/a.flac" } ] } ] },
+                { "ratingKey": 102, "Media": [ { "Part": [ { "file": "/music/b.flac" } ] } ] }
+            ] } })
+            .to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let search = server
+        .mock("GET", "/library/sections/3/search")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("type".into(), "10".into()),
+            Matcher::UrlEncoded("query".into(), "Song".into()),
+        ]))
+        .with_body(
+            json!({ "MediaContainer": { "Metadata": [
+                { "ratingKey": "7", "title": "Song", "grandparentTitle": "Other" },
+                { "ratingKey": "8", "title": "Song", "grandparentTitle": "Artist" }
+            ] } })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let p = provider_for(&server, Some("audiobooks"));
+    assert_eq!(p.local_track_uri("/music/a.flac".as_ref()), None);
+    assert_eq!(p.load_library().await.unwrap(), 2);
+    assert_eq!(p.load_library().await.unwrap(), 2);
+    all.assert_async().await;
+    assert_eq!(
+        p.local_track_uri("/music/b.flac".as_ref()).as_deref(),
+        Some("plex:item:102")
+    );
+    assert_eq!(p.local_track_uri("/elsewhere/b.flac".as_ref()), None);
+
+    let uri = p.search_track_uri("Song", "Artist").await.unwrap();
+    assert_eq!(uri.as_deref(), Some("plex:item:8"));
+    search.assert_async().await;
+    assert!(p.validate_uri("plex:item:8"));
+    assert!(!p.validate_uri("plex:item:"));
+
+    let missing = provider_for(&server, Some("Podcasts"));
+    let err = missing.ping().await.unwrap_err().to_string();
+    assert!(err.contains("no plex music library named"), "{}", err);
+}
+
+#[tokio::test]
+async fn plex_playlists_are_created_filled_and_reordered() {
+    let mut server = Server::new_async().await;
+    let _lib = mock_library(&mut server).await;
+    let _list = server
+        .mock("GET", "/playlists")
+        .match_query(Matcher::UrlEncoded("playlistType".into(), "audio".into()))
+        .with_body(
+            json!({ "MediaContainer": { "Metadata": [
+                { "ratingKey": "50", "title": "Rock", "smart": true }
+            ] } })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let create = server
+        .mock("POST", "/playlists")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("title".into(), "Rock".into()),
+            Matcher::UrlEncoded(
+                "uri".into(),
+                "server://m1/com.plexapp.plugins.library".into(),
+            ),
+        ]))
+        .with_body(
+            json!({ "MediaContainer": { "Metadata": [ { "ratingKey": "60" } ] } }).to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let add = server
+        .mock("PUT", "/playlists/60/items")
+        .match_query(Matcher::UrlEncoded(
+            "uri".into(),
+            "server://m1/com.plexapp.plugins.library/library/metadata/1,2,3".into(),
+        ))
+        .create_async()
+        .await;
+    let _items = server
+        .mock("GET", "/playlists/60/items")
+        .with_body(
+            json!({ "MediaContainer": { "Metadata": [
+                { "playlistItemID": 11, "ratingKey": "1" },
+                { "playlistItemID": 12, "ratingKey": "2" },
+                { "playlistItemID": 13, "ratingKey": "3" }
+            ] } })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let to_top = server
+        .mock("PUT", "/playlists/60/items/13/move")
+        .match_query(Matcher::Missing)
+        .expect(1)
+        .create_async()
+        .await;
+    let after_13 = server
+        .mock("PUT", "/playlists/60/items/12/move")
+        .match_query(Matcher::UrlEncoded("after".into(), "13".into()))
+        .expect(1)
+        .create_async()
+        .await;
+    let remove = server
+        .mock("DELETE", "/playlists/60/items/12")
+        .expect(1)
+        .create_async()
+        .await;
+    let _gone = server
+        .mock("GET", "/playlists/99")
+        .with_status(404)
+        .create_async()
+        .await;
+    let _gone_delete = server
+        .mock("DELETE", "/playlists/99")
+        .with_status(404)
+        .create_async()
+        .await;
+
+    let p = provider_for(&server, None);
+    // The smart playlist of the same name is not reused.
+    let id = p.ensure_playlist("Rock", "").await.unwrap();
+    assert_eq!(id, "60");
+    assert_eq!(p.ensure_playlist("Rock", "").await.unwrap(), "60");
+    create.assert_async().await;
+
+    let uris: Vec<String> = ["plex:item:1", "plex:item:2", "plex:item:3"]
+        .iter()
+        .map(|u| u.to_string())
+        .collect();
+    p.add_tracks(&id, &uris).await.unwrap();
+    add.assert_async().await;
+    assert_eq!(p.list_playlist_tracks(&id).await.unwrap(), uris);
+
+    // 3,2,1: item 13 moves to the top and 12 after it; 11 is then in place.
+    p.reorder_tracks(&id, &["plex:item:3".into(), "plex:item:2".into()])
+        .await
+        .unwrap();
+    to_top.assert_async().await;
+    after_13.assert_async().await;
+
+    p.remove_tracks(&id, &["plex:item:2".into()]).await.unwrap();
+    remove.assert_async().await;
+
+    assert_eq!(p.playlist_is_valid("99").await.unwrap(), None);
+    assert!(matches!(
+        p.delete_playlist("99").await,
+        Err(ProviderError::PlaylistNotFound { .. })
+    ));
+}
@@ -185,3 +185,342 @@ fn spotify_ensure_playlist_reuses_existing_exact_name() {
         assert_eq!(res.unwrap(), "existing_playlist_id");
     });
 }
+
+#[test]
+fn spotify_search_with_album_prefers_matching_album() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let _m_search = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "tracks": { "items": [
+                    { "uri": "spotify:track:compilation", "duration_ms": 200000, "album": { "name": "Greatest Hits" } },
+                    { "uri": "spotify:track:original", "duration_ms": 181000, "external_ids": { "isrc": "GBAAA0000001" }, "album": { "name": "Debut (Deluxe Edition)" } }
+                ] } })
+                .to_string(),
+            )
+            .create();
+
+        let td = tempdir().unwrap();
+        let db_path = td.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+        db::run_migrations(&conn).unwrap();
+        let stored = json!({
+            "access_token": "valid_token",
+            "token_type": "Bearer",
+            "expires_at": chrono::Utc::now().timestamp() + 3600,
+            "refresh_token": "refresh_token_value",
+            "scope": "playlist-modify-private"
+        })
+        .to_string();
+        db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+
+        let provider = SpotifyProvider::new(
+            "cid".into(),
+            "csecret".into(),
+            db_path.clone(),
+            Default::default(),
+        );
+
+        let with_album = provider
+            .search_track_uri_with_album("Song", "Artist", Some("Debut"))
+            .await
+            .unwrap();
+        assert_eq!(with_album.as_deref(), Some("spotify:track:original"));
+
+        // No known album (or no matching result) keeps the first result.
+        let without = provider
+            .search_track_uri_with_album("Song", "Artist", None)
+            .await
+            .unwrap();
+        assert_eq!(without.as_deref(), Some("spotify:track:compilation"));
+        let unmatched = provider
+            .search_track_uri_with_album("Song", "Artist", Some("Live"))
+            .await
+            .unwrap();
+        assert_eq!(unmatched.as_deref(), Some("spotify:track:compilation"));
+
+        let candidates = provider
+            .search_track_candidates("Song", "Artist", Some("Debut"), 10)
+            .await
+            .unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].uri, "spotify:track:original");
+        assert_eq!(candidates[0].duration_ms, Some(181_000));
+        assert_eq!(candidates[0].isrc.as_deref(), Some("GBAAA0000001"));
+        assert_eq!(candidates[0].album.as_deref(), Some("Debut (Deluxe Edition)"));
+        assert_eq!(candidates[1].duration_ms, Some(200_000));
+    });
+}
+
+#[test]
+fn spotify_set_playlist_cover_uploads_base64_jpeg() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+
+    let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+    let m_cover = server
+        .mock("PUT", "/playlists/pl1/images")
+        .match_header("content-type", "image/jpeg")
+        .match_header("authorization", "Bearer valid")
+        .match_body("/9j/4AAQ")
+        .with_status(202)
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": "ugc-image-upload"
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+    let provider =
+        SpotifyProvider::new("cid".into(), "csecret".into(), db_path, Default::default());
+    assert!(provider.supports_playlist_cover());
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(provider.set_playlist_cover("pl1", &jpeg))
+        .unwrap();
+    m_cover.assert();
+}
+
+#[test]
+fn spotify_update_playlist_description_puts_description() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+
+    let m_put = server
+        .mock("PUT", "/playlists/pl1")
+        .match_body(mockito::Matcher::Json(
+            json!({ "description": "Album1 (3 tracks)" }),
+        ))
+        .with_status(200)
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+    let provider =
+        SpotifyProvider::new("cid".into(), "csecret".into(), db_path, Default::default());
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(provider.update_playlist_description("pl1", "Album1 (3 tracks)"))
+        .unwrap();
+    m_put.assert();
+}
+
+#[test]
+fn spotify_search_in_market_skips_unplayable_and_falls_back_to_isrc() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+    use mockito::Matcher;
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // Name search: every result is region-locked.
+        let by_name = server
+            .mock("GET", "/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "track:Song artist:Artist".into()),
+                Matcher::UrlEncoded("market".into(), "DE".into()),
+                Matcher::UrlEncoded("limit".into(), "5".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "tracks": { "items": [
+                    { "uri": "spotify:track:locked", "is_playable": false, "external_ids": { "isrc": "GBAAA0000001" } }
+                ] } })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
+        // ISRC search: the first release is locked too, a reissue is not.
+        let by_isrc = server
+            .mock("GET", "/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "isrc:GBAAA0000001".into()),
+                Matcher::UrlEncoded("market".into(), "DE".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "tracks": { "items": [
+                    { "uri": "spotify:track:locked", "is_playable": false },
+                    { "uri": "spotify:track:reissue", "is_playable": true }
+                ] } })
+                .to_string(),
+            )
+            .expect(2)
+            .create();
+
+        let td = tempdir().unwrap();
+        let db_path = td.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+        db::run_migrations(&conn).unwrap();
+        let stored = json!({
+            "access_token": "valid_token",
+            "token_type": "Bearer",
+            "expires_at": chrono::Utc::now().timestamp() + 3600,
+            "refresh_token": "refresh_token_value",
+            "scope": "playlist-modify-private"
+        })
+        .to_string();
+        db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+
+        let config = music_file_playlist_online_sync::config::Config {
+            spotify_market: "DE".into(),
+            ..Default::default()
+        };
+        let provider = SpotifyProvider::new("cid".into(), "csecret".into(), db_path, config);
+
+        let uri = provider.search_track_uri("Song", "Artist").await.unwrap();
+        assert_eq!(uri.as_deref(), Some("spotify:track:reissue"));
+        let direct = provider
+            .search_track_uri_by_isrc("GBAAA0000001")
+            .await
+            .unwrap();
+        assert_eq!(direct.as_deref(), Some("spotify:track:reissue"));
+        by_name.assert();
+        by_isrc.assert();
+    });
+}
+
+#[test]
+fn spotify_add_tracks_at_sends_the_insert_position() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+
+    let m_insert = server
+        .mock("POST", "/playlists/pl1/tracks")
+        .match_body(mockito::Matcher::Json(
+            json!({ "uris": ["spotify:track:b"], "position": 1 }),
+        ))
+        .with_status(201)
+        .create();
+    let m_append = server
+        .mock("POST", "/playlists/pl1/tracks")
+        .match_body(mockito::Matcher::Json(
+            json!({ "uris": ["spotify:track:c"] }),
+        ))
+        .with_status(201)
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+    let provider =
+        SpotifyProvider::new("cid".into(), "csecret".into(), db_path, Default::default());
+    assert!(provider.supports_positional_add());
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(provider.add_tracks_at("pl1", &["spotify:track:b".into()], Some(1)))
+        .unwrap();
+    rt.block_on(provider.add_tracks("pl1", &["spotify:track:c".into()]))
+        .unwrap();
+    m_insert.assert();
+    m_append.assert();
+}
+
+#[test]
+fn spotify_read_only_token_is_refused_writes_without_a_request() {
+    let _guard = test_env_lock().lock().unwrap();
+    let mut server = Server::new();
+    let mock_url = server.url();
+    env::set_var("SPOTIFY_AUTH_BASE", &mock_url);
+    env::set_var("SPOTIFY_API_BASE", &mock_url);
+
+    let m_add = server
+        .mock("POST", "/playlists/pl1/tracks")
+        .with_status(403)
+        .expect(0)
+        .create();
+    let m_tracks = server
+        .mock("GET", "/playlists/pl1/tracks")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_body(json!({ "items": [], "next": null }).to_string())
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": "playlist-read-private user-read-private"
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &stored, None, None).unwrap();
+    let provider =
+        SpotifyProvider::new("cid".into(), "csecret".into(), db_path, Default::default());
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    // Reads still work; the add fails before reaching Spotify.
+    assert!(rt
+        .block_on(provider.list_playlist_tracks("pl1"))
+        .unwrap()
+        .is_empty());
+    let err = rt
+        .block_on(provider.add_tracks("pl1", &["spotify:track:a".into()]))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("playlist-modify-private or playlist-modify-public")
+            && err.contains("auth spotify"),
+        "{}",
+        err
+    );
+    m_tracks.assert();
+    m_add.assert();
+}
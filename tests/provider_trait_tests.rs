@@ -1,6 +1,10 @@
 use music_file_playlist_online_sync::api::{
-    mock::MockProvider, spotify::SpotifyProvider, tidal::TidalProvider, Provider,
+    match_confidence, mock::MockProvider, pick_confident_match, pick_track_match,
+    spotify::SpotifyProvider, stem_match_score, tidal::TidalProvider, LocalTrack, Provider,
+    ProviderError, ProviderResult, TrackMatch,
 };
+use music_file_playlist_online_sync::config::Config;
+use reqwest::StatusCode;
 
 #[test]
 fn test_mock_provider_trait() {
@@ -57,6 +61,37 @@ fn test_tidal_provider_not_authenticated() {
     assert!(!tidal.is_authenticated());
 }
 
+#[test]
+fn test_provider_error_from_status() {
+    let e = ProviderError::from_status(StatusCode::TOO_MANY_REQUESTS, "", Some(7), "add", None);
+    assert!(matches!(
+        e,
+        ProviderError::RateLimited {
+            retry_after: Some(7)
+        }
+    ));
+
+    let e = ProviderError::from_status(StatusCode::UNAUTHORIZED, "", None, "add", None);
+    assert!(matches!(e, ProviderError::Unauthorized));
+
+    let e = ProviderError::from_status(StatusCode::NOT_FOUND, "", None, "add", Some("pl1"));
+    assert!(
+        matches!(e, ProviderError::PlaylistNotFound { ref playlist_id } if playlist_id == "pl1")
+    );
+
+    // Without a playlist id a 404 is just an error with context.
+    let e = ProviderError::from_status(StatusCode::NOT_FOUND, "gone", None, "add", None);
+    assert!(matches!(e, ProviderError::Other(_)));
+    assert_eq!(e.to_string(), "add: 404 Not Found => gone");
+
+    // Variants survive a round trip through anyhow.
+    let any: anyhow::Error = ProviderError::Unauthorized.into();
+    assert!(matches!(
+        ProviderError::from(any),
+        ProviderError::Unauthorized
+    ));
+}
+
 #[tokio::test]
 async fn test_provider_auth_playlist_ops() {
     use std::env;
@@ -148,3 +183,332 @@ async fn test_provider_auth_playlist_ops() {
         println!("Renamed Tidal playlist to: {}", rename_name);
     }
 }
+
+#[test]
+fn pick_track_match_rejects_durations_outside_tolerance() {
+    let m = |uri: &str, duration_ms: Option<u64>| TrackMatch {
+        uri: uri.into(),
+        duration_ms,
+        ..Default::default()
+    };
+    let candidates = vec![
+        m("karaoke", Some(242_000)),
+        m("sped_up", Some(150_000)),
+        m("original", Some(181_500)),
+    ];
+    let picked = pick_track_match(candidates.clone(), Some(180_000), 10).unwrap();
+    assert_eq!(picked.uri, "original");
+    // Unknown local duration or a zero tolerance keep the first result.
+    assert_eq!(
+        pick_track_match(candidates.clone(), None, 10).unwrap().uri,
+        "karaoke"
+    );
+    assert_eq!(
+        pick_track_match(candidates, Some(180_000), 0).unwrap().uri,
+        "karaoke"
+    );
+    // Nothing within tolerance resolves nothing; unknown remote durations pass.
+    assert!(pick_track_match(vec![m("live", Some(300_000))], Some(180_000), 10).is_none());
+    assert!(pick_track_match(vec![m("unknown", None)], Some(180_000), 10).is_some());
+}
+
+#[test]
+fn pick_confident_match_abstains_below_min_confidence() {
+    let m = |uri: &str, name: &str, artist: &str, duration_ms: u64| TrackMatch {
+        uri: uri.into(),
+        name: Some(name.into()),
+        artist: Some(artist.into()),
+        album: Some("Album".into()),
+        duration_ms: Some(duration_ms),
+        ..Default::default()
+    };
+    let local = LocalTrack {
+        title: "Song Title",
+        artist: "The Artist",
+        album: Some("Album (Deluxe Edition)"),
+        duration_ms: Some(180_000),
+    };
+    let exact = m("exact", "Song Title", "The Artist", 181_000);
+    let cover = m("cover", "Song Title", "Tribute Band", 180_000);
+    assert!(match_confidence(&local, &exact) > 0.95);
+    let cover_confidence = match_confidence(&local, &cover);
+    assert!(
+        cover_confidence > 0.5 && cover_confidence < 0.8,
+        "{}",
+        cover_confidence
+    );
+    // Nothing to compare is not evidence against a match.
+    assert_eq!(
+        match_confidence(&local, &TrackMatch::from_uri("bare".into())),
+        1.0
+    );
+
+    let candidates = vec![cover.clone(), exact.clone()];
+    // Without a threshold the first result is kept, as before.
+    assert_eq!(
+        pick_confident_match(candidates.clone(), &local, 10, 0.0)
+            .unwrap()
+            .uri,
+        "cover"
+    );
+    // With one the best-scoring result wins...
+    assert_eq!(
+        pick_confident_match(candidates, &local, 10, 0.8)
+            .unwrap()
+            .uri,
+        "exact"
+    );
+    // ...and a search whose best result scores too low abstains.
+    assert!(pick_confident_match(vec![cover], &local, 10, 0.8).is_none());
+
+    let bad: Config = toml::from_str("root_folder = \".\"\nmin_match_confidence = 1.5\n").unwrap();
+    assert!(bad.validate().is_err());
+}
+
+/// Answers every search with a track whose metadata echoes the query, so a
+/// search with artist and title swapped returns a track that does not
+/// exist, except for the real "Yesterday" by "The Beatles".
+struct EchoSearchProvider;
+
+#[async_trait::async_trait]
+impl Provider for EchoSearchProvider {
+    fn http_client(&self) -> &reqwest::Client {
+        use std::sync::OnceLock;
+        static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+        CLIENT.get_or_init(reqwest::Client::new)
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(String::new())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
+        Ok(name.to_string())
+    }
+    async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
+        Ok(vec![])
+    }
+    async fn search_track_uri(
+        &self,
+        _title: &str,
+        _artist: &str,
+    ) -> ProviderResult<Option<String>> {
+        Ok(None)
+    }
+    async fn search_track_candidates(
+        &self,
+        title: &str,
+        artist: &str,
+        _album: Option<&str>,
+        _limit: usize,
+    ) -> ProviderResult<Vec<TrackMatch>> {
+        let m = if (title, artist) == ("Yesterday", "The Beatles") {
+            TrackMatch {
+                uri: "echo:right".into(),
+                name: Some("Yesterday".into()),
+                artist: Some("The Beatles".into()),
+                ..Default::default()
+            }
+        } else {
+            TrackMatch {
+                uri: "echo:wrong".into(),
+                name: Some(format!("{} Medley", title)),
+                artist: Some("Various Artists".into()),
+                ..Default::default()
+            }
+        };
+        Ok(vec![m])
+    }
+    fn name(&self) -> &str {
+        "echo"
+    }
+    fn is_authenticated(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn both_orderings_of_a_file_name_are_searched_and_the_better_match_wins() {
+    use music_file_playlist_online_sync::{db, worker};
+
+    let td = tempfile::tempdir().unwrap();
+    let album = td.path().join("root").join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    // No tags: "Artist - Title" is tried first and finds the wrong track.
+    std::fs::write(album.join("Yesterday - The Beatles.mp3"), b"").unwrap();
+    std::fs::write(album.join("Album.m3u"), "Yesterday - The Beatles.mp3\n").unwrap();
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        td.path().join("root"),
+        td.path().join("test.db")
+    ))
+    .unwrap();
+    let pool = db::create_pool(&cfg.db_path).unwrap();
+    let (uris, _) = worker::desired_remote_uris_for_playlist(
+        &cfg,
+        &cfg.playlist_key_for_folder(&album),
+        std::sync::Arc::new(EchoSearchProvider),
+        &pool,
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(uris, vec!["echo:right"]);
+
+    let wrong = TrackMatch {
+        uri: "x".into(),
+        name: Some("The Beatles Medley".into()),
+        artist: Some("Various Artists".into()),
+        ..Default::default()
+    };
+    let right = TrackMatch {
+        uri: "y".into(),
+        name: Some("Yesterday".into()),
+        artist: Some("The Beatles".into()),
+        ..Default::default()
+    };
+    let stem = "Yesterday The Beatles";
+    assert_eq!(stem_match_score(stem, &right), Some(1.0));
+    assert_eq!(stem_match_score(stem, &wrong), Some(0.5));
+    assert_eq!(
+        stem_match_score(stem, &TrackMatch::from_uri("bare".into())),
+        None
+    );
+}
+
+/// Finds tracks by metadata as `<name>:by-metadata` and, when
+/// `isrc_search` is set, by ISRC as `<name>:by-isrc`; every match reports
+/// the same ISRC.
+struct IsrcSharingProvider {
+    name: &'static str,
+    isrc_search: bool,
+}
+
+#[async_trait::async_trait]
+impl Provider for IsrcSharingProvider {
+    fn http_client(&self) -> &reqwest::Client {
+        use std::sync::OnceLock;
+        static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+        CLIENT.get_or_init(reqwest::Client::new)
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok(String::new())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
+        Ok(name.to_string())
+    }
+    async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
+        Ok(vec![])
+    }
+    async fn search_track_uri(
+        &self,
+        _title: &str,
+        _artist: &str,
+    ) -> ProviderResult<Option<String>> {
+        Ok(Some(format!("{}:by-metadata", self.name)))
+    }
+    async fn search_track_uri_by_isrc(&self, isrc: &str) -> ProviderResult<Option<String>> {
+        assert!(self.isrc_search);
+        Ok((isrc == "USRC17607839").then(|| format!("{}:by-isrc", self.name)))
+    }
+    async fn lookup_track_isrc(&self, _uri: &str) -> ProviderResult<Option<String>> {
+        Ok(Some("us-rc1-76-07839".into()))
+    }
+    fn capabilities(&self) -> music_file_playlist_online_sync::api::ProviderCapabilities {
+        music_file_playlist_online_sync::api::ProviderCapabilities {
+            supports_isrc_search: self.isrc_search,
+            ..Default::default()
+        }
+    }
+    fn name(&self) -> &str {
+        self.name
+    }
+    fn is_authenticated(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn an_isrc_learned_on_one_provider_is_searched_on_the_next() {
+    use music_file_playlist_online_sync::{db, worker};
+
+    let td = tempfile::tempdir().unwrap();
+    let album = td.path().join("root").join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    // No tags, so no ISRC of its own.
+    std::fs::write(album.join("Artist - Song.mp3"), b"").unwrap();
+    std::fs::write(album.join("Album.m3u"), "Artist - Song.mp3\n").unwrap();
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        td.path().join("root"),
+        td.path().join("test.db")
+    ))
+    .unwrap();
+    let pool = db::create_pool(&cfg.db_path).unwrap();
+    let key = cfg.playlist_key_for_folder(&album);
+    let resolve = |provider: IsrcSharingProvider| {
+        let (cfg, key, pool) = (&cfg, &key, &pool);
+        async move {
+            worker::desired_remote_uris_for_playlist(
+                cfg,
+                key,
+                std::sync::Arc::new(provider),
+                pool,
+                false,
+            )
+            .await
+            .unwrap()
+            .0
+        }
+    };
+
+    let first = IsrcSharingProvider {
+        name: "first",
+        isrc_search: false,
+    };
+    assert_eq!(resolve(first).await, vec!["first:by-metadata"]);
+    let track = album
+        .join("Artist - Song.mp3")
+        .to_string_lossy()
+        .to_string();
+    assert_eq!(
+        db::shared_remote_isrc(&pool.get().unwrap(), &track)
+            .unwrap()
+            .as_deref(),
+        Some("USRC17607839")
+    );
+
+    let second = IsrcSharingProvider {
+        name: "second",
+        isrc_search: true,
+    };
+    assert_eq!(resolve(second).await, vec!["second:by-isrc"]);
+}
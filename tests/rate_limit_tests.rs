@@ -0,0 +1,29 @@
+use music_file_playlist_online_sync::api::rate_limit::RateLimiter;
+use std::time::{Duration, Instant};
+
+#[test]
+fn rate_limiter_disabled_for_non_positive_rates() {
+    assert!(RateLimiter::new(0.0).is_none());
+    assert!(RateLimiter::new(-1.0).is_none());
+    assert!(RateLimiter::new(f64::NAN).is_none());
+    assert!(RateLimiter::new(0.5).is_some());
+}
+
+#[tokio::test]
+async fn rate_limiter_is_shared_between_clones() {
+    let limiter = RateLimiter::new(50.0).unwrap();
+    let other = limiter.clone();
+    let start = Instant::now();
+    // The burst of 50 is consumed through both handles; the remaining 10
+    // requests must then be spaced out at 50/s (~200ms in total).
+    for i in 0..60 {
+        if i % 2 == 0 {
+            limiter.acquire().await;
+        } else {
+            other.acquire().await;
+        }
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(150), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
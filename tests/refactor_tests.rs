@@ -3,7 +3,7 @@
 //! all_providers_ok logic that prevents events from being marked synced
 //! when a provider fails.
 
-use music_file_playlist_online_sync::api::Provider;
+use music_file_playlist_online_sync::api::{Provider, ProviderResult};
 use music_file_playlist_online_sync::models::EventAction;
 use music_file_playlist_online_sync::{api, db, models};
 use tempfile::tempdir;
@@ -110,6 +110,32 @@ fn tidal_provider_no_folder_nesting() {
     assert!(!tp.supports_folder_nesting());
 }
 
+#[test]
+fn provider_capabilities_describe_each_provider() {
+    use api::ProviderCapabilities;
+    let failing = FailingProvider;
+    assert_eq!(failing.capabilities(), ProviderCapabilities::default());
+    assert!(failing.supports_folder_nesting() && !failing.supports_positional_add());
+
+    let mp = api::mock::MockProvider::new().with_positional_add();
+    assert!(mp.capabilities().supports_positional_add);
+    assert!(mp.supports_positional_add());
+
+    let cfg: music_file_playlist_online_sync::config::Config =
+        toml::from_str("root_folder = \"/tmp/music\"\n").unwrap();
+    let spotify = api::spotify::SpotifyProvider::new(
+        String::new(),
+        String::new(),
+        std::path::PathBuf::from("/dev/null"),
+        cfg.clone(),
+    );
+    let caps = spotify.capabilities();
+    assert!(caps.supports_cover_art && caps.supports_isrc_search && !caps.hard_delete);
+    let fs = api::filesystem::FileSystemProvider::new(cfg);
+    let caps = fs.capabilities();
+    assert!(!caps.supports_folders && caps.supports_reorder && !caps.supports_isrc_search);
+}
+
 // ---------------------------------------------------------------------------
 // 3. all_providers_ok logic test (events not synced on failure)
 // ---------------------------------------------------------------------------
@@ -126,31 +152,31 @@ impl Provider for FailingProvider {
     fn is_authenticated(&self) -> bool {
         true
     }
-    async fn ensure_playlist(&self, _name: &str, _desc: &str) -> anyhow::Result<String> {
+    async fn ensure_playlist(&self, _name: &str, _desc: &str) -> ProviderResult<String> {
         Ok("pl_id".to_string())
     }
-    async fn rename_playlist(&self, _id: &str, _name: &str) -> anyhow::Result<()> {
+    async fn rename_playlist(&self, _id: &str, _name: &str) -> ProviderResult<()> {
         Ok(())
     }
-    async fn add_tracks(&self, _id: &str, _uris: &[String]) -> anyhow::Result<()> {
-        anyhow::bail!("simulated failure")
+    async fn add_tracks(&self, _id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Err(anyhow::anyhow!("simulated failure").into())
     }
-    async fn remove_tracks(&self, _id: &str, _uris: &[String]) -> anyhow::Result<()> {
+    async fn remove_tracks(&self, _id: &str, _uris: &[String]) -> ProviderResult<()> {
         Ok(())
     }
-    async fn delete_playlist(&self, _id: &str) -> anyhow::Result<()> {
+    async fn delete_playlist(&self, _id: &str) -> ProviderResult<()> {
         Ok(())
     }
-    async fn search_track_uri(&self, _t: &str, _a: &str) -> anyhow::Result<Option<String>> {
+    async fn search_track_uri(&self, _t: &str, _a: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
-    async fn list_playlist_tracks(&self, _id: &str) -> anyhow::Result<Vec<String>> {
+    async fn list_playlist_tracks(&self, _id: &str) -> ProviderResult<Vec<String>> {
         Ok(Vec::new())
     }
-    async fn playlist_is_valid(&self, _id: &str) -> anyhow::Result<Option<String>> {
+    async fn playlist_is_valid(&self, _id: &str) -> ProviderResult<Option<String>> {
         Ok(Some(String::new()))
     }
-    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
     fn http_client(&self) -> &reqwest::Client {
@@ -158,10 +184,10 @@ impl Provider for FailingProvider {
         static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
         CLIENT.get_or_init(reqwest::Client::new)
     }
-    async fn get_bearer(&self) -> anyhow::Result<String> {
+    async fn get_bearer(&self) -> ProviderResult<String> {
         Ok("Bearer test".to_string())
     }
-    async fn refresh_token(&self) -> anyhow::Result<()> {
+    async fn refresh_token(&self) -> ProviderResult<()> {
         Ok(())
     }
 }
@@ -263,6 +289,19 @@ fn should_precompute_desired_matrix() {
     assert!(!should_precompute_desired(true, true, true)); // all three
 }
 
+#[test]
+fn ordered_reconcile_diff_preserves_local_order() {
+    use music_file_playlist_online_sync::worker::ordered_reconcile_diff;
+
+    let s = |v: &[&str]| v.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    let desired = s(&["d", "a", "c", "b", "d"]);
+    let remote = s(&["z", "a", "y", "b"]);
+    let (add, remove) = ordered_reconcile_diff(&desired, &remote);
+    // adds follow the .m3u order (deduplicated), removes follow remote order
+    assert_eq!(add, s(&["d", "c"]));
+    assert_eq!(remove, s(&["z", "y"]));
+}
+
 // ---------------------------------------------------------------------------
 // 5. Retry module integration
 // ---------------------------------------------------------------------------
@@ -0,0 +1,58 @@
+use lib::db;
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync as lib;
+use serde_json::json;
+
+#[test]
+fn spotify_pkce_token_refresh_sends_client_id_and_rotates_refresh_token() {
+    let mut server = Server::new();
+    std::env::set_var("SPOTIFY_AUTH_BASE", server.url());
+
+    // PKCE refreshes carry the client_id in the body and no Basic auth.
+    let m = server
+        .mock("POST", "/api/token")
+        .match_header("authorization", Matcher::Missing)
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+            Matcher::UrlEncoded("refresh_token".into(), "refresh-1".into()),
+            Matcher::UrlEncoded("client_id".into(), "pkce_id".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({"access_token": "fresh", "expires_in": 3600, "refresh_token": "refresh-2"})
+                .to_string(),
+        )
+        .create();
+
+    let dir = tempfile::tempdir().expect("tmpdir");
+    let db_path = dir.path().join("music-sync.db");
+    let conn = db::open_or_create(&db_path).expect("open db");
+    let init_token = json!({
+        "access_token": "old",
+        "token_type": "Bearer",
+        "expires_at": 0,
+        "refresh_token": "refresh-1"
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "spotify", &init_token, Some("pkce_id"), None)
+        .expect("save cred");
+
+    let provider = lib::api::spotify::SpotifyProvider::new(
+        String::new(),
+        String::new(),
+        db_path.clone(),
+        Default::default(),
+    );
+    assert!(lib::api::Provider::is_authenticated(&provider));
+    let rt = tokio::runtime::Runtime::new().expect("rt");
+    assert_eq!(rt.block_on(provider.get_bearer()).unwrap(), "Bearer fresh");
+    m.assert();
+
+    let (token_json, client_id, _) = db::load_credential_with_client(&conn, "spotify")
+        .unwrap()
+        .unwrap();
+    let stored: serde_json::Value = serde_json::from_str(&token_json).unwrap();
+    assert_eq!(stored["refresh_token"], "refresh-2");
+    assert_eq!(client_id.as_deref(), Some("pkce_id"));
+}
@@ -0,0 +1,254 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::subsonic::{SubsonicCredentials, SubsonicProvider};
+use music_file_playlist_online_sync::api::{Provider, ProviderError};
+use music_file_playlist_online_sync::db;
+use rusqlite::Connection;
+use serde_json::json;
+use tempfile::tempdir;
+
+fn ok(body: serde_json::Value) -> String {
+    let mut inner = json!({ "status": "ok", "version": "1.16.1" });
+    if let (Some(obj), Some(extra)) = (inner.as_object_mut(), body.as_object()) {
+        obj.extend(extra.clone());
+    }
+    json!({ "subsonic-response": inner }).to_string()
+}
+
+fn provider_for(server: &Server) -> SubsonicProvider {
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "sesame", "c19b2d");
+    SubsonicProvider::with_credentials(
+        Some(creds),
+        std::path::PathBuf::from("/dev/null"),
+        Default::default(),
+    )
+}
+
+#[test]
+fn subsonic_salted_token_matches_spec_example() {
+    // Example from the Subsonic API documentation.
+    let creds = SubsonicCredentials::with_salt("http://x/", "u", "sesame", "c19b2d");
+    assert_eq!(creds.token, "26719a1196d2a940705a59634eb18eab");
+    assert_eq!(creds.server_url, "http://x");
+
+    let random = SubsonicCredentials::from_password("http://x", "u", "sesame");
+    assert_eq!(random.salt.len(), 12);
+    assert_ne!(random.token, "sesame");
+}
+
+#[test]
+fn subsonic_provider_loads_credentials_from_db() {
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let p = SubsonicProvider::new(db_path.clone(), Default::default());
+    assert!(!p.is_authenticated());
+
+    let creds = SubsonicCredentials::with_salt("http://nd", "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    let p = SubsonicProvider::new(db_path, Default::default());
+    assert!(p.is_authenticated());
+    assert_eq!(p.name(), "subsonic");
+    assert!(!p.supports_folder_nesting());
+    assert!(p.validate_uri("subsonic:song:abc"));
+    assert!(!p.validate_uri("subsonic:song:"));
+    assert!(!p.validate_uri("spotify:track:abc"));
+}
+
+#[tokio::test]
+async fn subsonic_search_sends_auth_and_prefers_exact_match() {
+    let mut server = Server::new_async().await;
+    let m = server
+        .mock("GET", "/rest/search3")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("u".into(), "alice".into()),
+            Matcher::UrlEncoded("t".into(), "26719a1196d2a940705a59634eb18eab".into()),
+            Matcher::UrlEncoded("s".into(), "c19b2d".into()),
+            Matcher::UrlEncoded("f".into(), "json".into()),
+            Matcher::UrlEncoded("query".into(), "Artist Song".into()),
+        ]))
+        .with_status(200)
+        .with_body(ok(json!({ "searchResult3": { "song": [
+            { "id": "s1", "title": "Song (Live)", "artist": "Artist" },
+            { "id": "s2", "title": "Song", "artist": "Artist" }
+        ] } })))
+        .create_async()
+        .await;
+
+    let p = provider_for(&server);
+    let uri = p.search_track_uri("Song", "Artist").await.unwrap();
+    assert_eq!(uri.as_deref(), Some("subsonic:song:s2"));
+    m.assert_async().await;
+}
+
+#[tokio::test]
+async fn subsonic_remove_tracks_uses_playlist_indices() {
+    let mut server = Server::new_async().await;
+    let _get = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::UrlEncoded("id".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl1", "name": "P", "entry": [
+            { "id": "a" }, { "id": "b" }, { "id": "a" }, { "id": "c" }
+        ] } }),
+        ))
+        .create_async()
+        .await;
+    let update = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::Regex(
+            "playlistId=pl1&songIndexToRemove=0&songIndexToRemove=2$".into(),
+        ))
+        .with_status(200)
+        .with_body(ok(json!({})))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let p = provider_for(&server);
+    p.remove_tracks("pl1", &["subsonic:song:a".into()])
+        .await
+        .unwrap();
+    update.assert_async().await;
+
+    let tracks = p.list_playlist_tracks("pl1").await.unwrap();
+    assert_eq!(
+        tracks,
+        vec!["subsonic:song:a", "subsonic:song:b", "subsonic:song:c"]
+    );
+}
+
+#[tokio::test]
+async fn subsonic_missing_playlist_maps_to_playlist_not_found() {
+    let mut server = Server::new_async().await;
+    let failed = json!({ "subsonic-response": {
+        "status": "failed",
+        "version": "1.16.1",
+        "error": { "code": 70, "message": "Playlist not found" }
+    } })
+    .to_string();
+    let _get = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(&failed)
+        .create_async()
+        .await;
+    let _update = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(&failed)
+        .create_async()
+        .await;
+
+    let p = provider_for(&server);
+    assert_eq!(p.playlist_is_valid("gone").await.unwrap(), None);
+    let err = p
+        .add_tracks("gone", &["subsonic:song:a".into()])
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ProviderError::PlaylistNotFound { ref playlist_id } if playlist_id == "gone")
+    );
+}
+
+#[tokio::test]
+async fn cache_verify_repairs_and_removes_entries_of_deleted_songs() {
+    let mut server = Server::new_async().await;
+    let get_song = |id: &str| {
+        Matcher::AllOf(vec![
+            Matcher::UrlEncoded("u".into(), "alice".into()),
+            Matcher::UrlEncoded("id".into(), id.into()),
+        ])
+    };
+    let gone = json!({ "subsonic-response": {
+        "status": "failed",
+        "version": "1.16.1",
+        "error": { "code": 70, "message": "Song not found" }
+    } })
+    .to_string();
+    let _alive = server
+        .mock("GET", "/rest/getSong")
+        .match_query(get_song("s1"))
+        .with_body(ok(json!({ "song": { "id": "s1" } })))
+        .create_async()
+        .await;
+    let _gone = server
+        .mock("GET", "/rest/getSong")
+        .match_query(Matcher::AnyOf(vec![get_song("s2"), get_song("s4")]))
+        .with_body(gone)
+        .create_async()
+        .await;
+    let search = server
+        .mock("GET", "/rest/search3")
+        .match_query(Matcher::UrlEncoded("query".into(), "Artist Gone".into()))
+        .with_body(ok(json!({ "searchResult3": { "song": [
+            { "id": "s3", "title": "Gone", "artist": "Artist" }
+        ] } })))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    std::fs::create_dir_all(&root).unwrap();
+    let moved = root.join("Artist - Gone.mp3");
+    std::fs::write(&moved, b"").unwrap();
+    let db_path = td.path().join("test.db");
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    let conn = db::open_or_create(&db_path).unwrap();
+    let moved = moved.to_string_lossy().to_string();
+    let still = root.join("still.mp3").to_string_lossy().to_string();
+    let deleted = root.join("deleted.mp3").to_string_lossy().to_string();
+    db::upsert_track_cache(&conn, "subsonic", &still, None, Some("subsonic:song:s1")).unwrap();
+    db::upsert_track_cache(&conn, "subsonic", &moved, None, Some("subsonic:song:s2")).unwrap();
+    db::upsert_track_cache(&conn, "subsonic", &deleted, None, Some("subsonic:song:s4")).unwrap();
+    db::mark_track_unresolved(
+        &conn,
+        "subsonic",
+        &root.join("x.mp3").to_string_lossy(),
+        None,
+    )
+    .unwrap();
+
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "sesame", "c19b2d");
+    let p = SubsonicProvider::with_credentials(Some(creds), db_path.clone(), cfg.clone());
+    assert_eq!(
+        p.track_exists("subsonic:song:s1").await.unwrap(),
+        Some(true)
+    );
+    assert_eq!(
+        p.track_exists("subsonic:song:s2").await.unwrap(),
+        Some(false)
+    );
+
+    let summary =
+        music_file_playlist_online_sync::worker::verify_track_cache(&cfg, std::sync::Arc::new(p))
+            .await
+            .unwrap();
+    assert_eq!(summary.checked, 3);
+    assert_eq!(summary.alive, 1);
+    assert_eq!(summary.repaired, 1);
+    assert_eq!(summary.removed, 1);
+    assert_eq!(summary.unknown, 0);
+    search.assert_async().await;
+
+    let uri = |path: &str| {
+        db::get_track_cache_by_local(&conn, "subsonic", path)
+            .unwrap()
+            .and_then(|(_, uri, _)| uri)
+    };
+    assert_eq!(uri(&still).as_deref(), Some("subsonic:song:s1"));
+    assert_eq!(uri(&moved).as_deref(), Some("subsonic:song:s3"));
+    assert!(db::get_track_cache_by_local(&conn, "subsonic", &deleted)
+        .unwrap()
+        .is_none());
+}
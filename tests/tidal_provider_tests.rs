@@ -206,3 +206,523 @@ fn tidal_search_helpers_ignore_zero_ids() {
     let res2 = rt.block_on(provider.search_track_uri_by_isrc("XYZ"));
     assert!(res2.unwrap().is_none(), "isrc search should ignore zero id");
 }
+
+#[test]
+fn tidal_search_candidates_returns_ranked_metadata() {
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": null,
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, None, None).unwrap();
+    let provider = TidalProvider::new(
+        "cid".into(),
+        "csecret".into(),
+        db_path.clone(),
+        None,
+        Default::default(),
+    );
+
+    let body = json!({ "items": [
+        { "id": 0 },
+        { "id": 11, "title": "Song", "duration": 200, "artists": [{ "name": "Artist" }],
+          "album": { "title": "Greatest Hits" } },
+        { "id": "12", "attributes": { "title": "Song", "duration": "PT3M1.5S",
+          "isrc": "GBAAA0000001", "album": { "title": "Debut" } } }
+    ] })
+    .to_string();
+    let _m = server
+        .mock(
+            "GET",
+            "/search/tracks?query=Song%20Artist&limit=5&countryCode=US",
+        )
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let res = rt
+        .block_on(provider.search_track_candidates("Song", "Artist", Some("Debut"), 5))
+        .unwrap();
+    assert_eq!(res.len(), 2, "zero ids are skipped: {:?}", res);
+    assert_eq!(res[0].uri, "tidal:track:12");
+    assert_eq!(res[0].duration_ms, Some(181_500));
+    assert_eq!(res[0].isrc.as_deref(), Some("GBAAA0000001"));
+    assert_eq!(res[1].uri, "tidal:track:11");
+    assert_eq!(res[1].artist.as_deref(), Some("Artist"));
+    assert_eq!(res[1].duration_ms, Some(200_000));
+}
+
+#[test]
+fn tidal_worker_add_is_chunked_by_tidal_batch_limit() {
+    use music_file_playlist_online_sync::config::Config;
+    use music_file_playlist_online_sync::models::EventAction;
+
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+
+    // 250 tracks at 20 per request => 13 requests.
+    let m_add = server
+        .mock(
+            "POST",
+            Matcher::Regex("^//?playlists/pl1/relationships/items".into()),
+        )
+        .match_body(Matcher::Regex(
+            r#"^\{"data":\[(\{"id":"\d+","meta":\{\},"type":"tracks"\},?){1,20}\]\}$"#.into(),
+        ))
+        .with_status(201)
+        .expect(13)
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": null,
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, Some("cid"), Some("csecret")).unwrap();
+    db::upsert_playlist_map(&conn, "tidal", "Big", "pl1").unwrap();
+    for i in 1..=250 {
+        let tp = format!("uri::tidal:track:{}", i);
+        db::enqueue_event(&conn, "Big", &EventAction::Add, Some(&tp), None).unwrap();
+    }
+
+    let cfg = Config {
+        root_folder: td.path().join("root"),
+        debounce_ms: 100,
+        log_dir: td.path().join("logs"),
+        max_retries_on_error: 1,
+        // Larger than the TIDAL hard limit: must not be used for Tidal.
+        max_batch_size_tidal: 500,
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        search_strip_tokens: Vec::new(),
+        file_extensions: vec!["*.mp3".into()],
+        db_path: db_path.clone(),
+        ..Default::default()
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    // trust_cache skips the playlist validity check against the mock.
+    rt.block_on(music_file_playlist_online_sync::worker::run_worker_once(
+        &cfg, None, true, false,
+    ))
+    .unwrap();
+    m_add.assert();
+}
+
+#[test]
+fn tidal_requests_use_the_configured_country_code() {
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+    env::remove_var("TIDAL_COUNTRY_CODE");
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let now = chrono::Utc::now().timestamp();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": now + 3600,
+        "refresh_token": null,
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, None, None).unwrap();
+
+    let config = music_file_playlist_online_sync::config::Config {
+        tidal_country_code: "DE".into(),
+        tidal_locale: "de-DE".into(),
+        ..Default::default()
+    };
+    let provider = TidalProvider::new("cid".into(), "csecret".into(), db_path, None, config);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    let m = server
+        .mock("GET", "/tracks?countryCode=DE&filter%5Bisrc%5D=XYZ")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "data": [{ "id": "42" }] }).to_string())
+        .expect(1)
+        .create();
+    let uri = rt
+        .block_on(provider.search_track_uri_by_isrc("XYZ"))
+        .unwrap();
+    assert_eq!(uri.as_deref(), Some("tidal:track:42"));
+    m.assert();
+}
+
+#[test]
+fn tidal_playlist_is_valid_asks_for_the_playlist_itself() {
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+    env::remove_var("TIDAL_COUNTRY_CODE");
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": null,
+        "scope": "",
+        "user_id": 777
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, None, None).unwrap();
+    // No library listing: the answer must come from GET /playlists/{id}.
+    let listing = server
+        .mock("GET", Matcher::Regex("^/userCollections/".into()))
+        .expect(0)
+        .create();
+    let live = server
+        .mock("GET", Matcher::Regex("^/playlists/pl1\\?countryCode=".into()))
+        .with_status(200)
+        .with_body(
+            json!({ "data": { "id": "pl1", "type": "playlists", "attributes": { "name": "Mix" } } })
+                .to_string(),
+        )
+        .create();
+    let gone = server
+        .mock(
+            "GET",
+            Matcher::Regex("^/playlists/gone\\?countryCode=".into()),
+        )
+        .with_status(404)
+        .with_body(json!({ "errors": [{ "status": "404" }] }).to_string())
+        .create();
+    let _broken = server
+        .mock(
+            "GET",
+            Matcher::Regex("^/playlists/broken\\?countryCode=".into()),
+        )
+        .with_status(500)
+        .create();
+
+    let provider = TidalProvider::new(
+        "cid".into(),
+        "csecret".into(),
+        db_path,
+        None,
+        Default::default(),
+    );
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    assert_eq!(
+        rt.block_on(provider.playlist_is_valid("pl1")).unwrap(),
+        Some("Mix".to_string())
+    );
+    assert_eq!(
+        rt.block_on(provider.playlist_is_valid("gone")).unwrap(),
+        None
+    );
+    assert!(rt.block_on(provider.playlist_is_valid("broken")).is_err());
+    live.assert();
+    gone.assert();
+    listing.assert();
+}
+
+#[test]
+fn tidal_device_code_flow_polls_until_authorized_and_stores_the_user_id() {
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+
+    let device = server
+        .mock("POST", "/v1/oauth2/device_authorization")
+        .match_body(Matcher::UrlEncoded("client_id".into(), "cid".into()))
+        .with_status(200)
+        .with_body(
+            json!({
+                "deviceCode": "dev-1",
+                "userCode": "ABCDE",
+                "verificationUri": "link.tidal.com",
+                "verificationUriComplete": "link.tidal.com/ABCDE",
+                "expiresIn": 300,
+                "interval": 0
+            })
+            .to_string(),
+        )
+        .create();
+    // The first poll is still pending; mockito moves on to the next mock
+    // once this one has been hit.
+    let pending = server
+        .mock("POST", "/v1/oauth2/token")
+        .with_status(400)
+        .with_body(json!({ "error": "authorization_pending" }).to_string())
+        .expect(1)
+        .create();
+    let granted = server
+        .mock("POST", "/v1/oauth2/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded(
+                "grant_type".into(),
+                "urn:ietf:params:oauth:grant-type:device_code".into(),
+            ),
+            Matcher::UrlEncoded("device_code".into(), "dev-1".into()),
+        ]))
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": "at",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+                "refresh_token": "rt"
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+    let me = server
+        .mock("GET", "/users/me")
+        .match_header("authorization", "Bearer at")
+        .with_status(200)
+        .with_body(json!({ "data": { "id": "12345", "type": "users" } }).to_string())
+        .create();
+
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    db::open_or_create(&db_path).unwrap();
+    let cfg = music_file_playlist_online_sync::config::Config {
+        db_path: db_path.clone(),
+        ..Default::default()
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(
+        music_file_playlist_online_sync::api::tidal_auth::run_tidal_device_auth(
+            &cfg, "cid", "csecret",
+        ),
+    )
+    .unwrap();
+    device.assert();
+    pending.assert();
+    granted.assert();
+    me.assert();
+
+    let conn = Connection::open(&db_path).unwrap();
+    let (blob, client_id, _) = db::load_credential_with_client(&conn, "tidal")
+        .unwrap()
+        .unwrap();
+    assert_eq!(client_id.as_deref(), Some("cid"));
+    let token: serde_json::Value = serde_json::from_str(&blob).unwrap();
+    assert_eq!(token["access_token"], "at");
+    assert_eq!(token["refresh_token"], "rt");
+    assert_eq!(token["user_id"], 12345);
+}
+
+#[test]
+fn tidal_user_id_is_discovered_once_and_kept_across_refreshes() {
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+
+    // An expired token without a user id, as pasted by older versions.
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "old",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() - 10,
+        "refresh_token": "rt",
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, Some("cid"), Some("csecret")).unwrap();
+
+    let refresh = server
+        .mock("POST", "/v1/oauth2/token")
+        .match_body(Matcher::UrlEncoded(
+            "grant_type".into(),
+            "refresh_token".into(),
+        ))
+        .with_status(200)
+        .with_body(json!({ "access_token": "new", "expires_in": 3600 }).to_string())
+        .expect(2)
+        .create();
+    let me = server
+        .mock("GET", "/users/me")
+        .match_header("authorization", "Bearer new")
+        .with_status(200)
+        .with_body(json!({ "data": { "id": "777", "type": "users" } }).to_string())
+        .expect(1)
+        .create();
+    let collections = server
+        .mock("GET", Matcher::Regex("^/userCollections/777\\?".into()))
+        .with_status(200)
+        .with_body(
+            json!({
+                "data": { "id": "777", "relationships": { "playlists": { "links": {} } } },
+                "included": [
+                    { "id": "pl1", "type": "playlists", "attributes": { "name": "Mix" } }
+                ]
+            })
+            .to_string(),
+        )
+        .create();
+
+    let stored_user_id = || {
+        let (blob, _, _) = db::load_credential_with_client(&conn, "tidal")
+            .unwrap()
+            .unwrap();
+        serde_json::from_str::<serde_json::Value>(&blob).unwrap()["user_id"].clone()
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let provider = TidalProvider::new(
+        "cid".into(),
+        "csecret".into(),
+        db_path.clone(),
+        None,
+        Default::default(),
+    );
+    let playlists = rt.block_on(provider.list_user_playlists()).unwrap();
+    assert_eq!(playlists, vec![("pl1".to_string(), "Mix".to_string())]);
+    collections.assert();
+    assert_eq!(stored_user_id(), 777);
+
+    rt.block_on(provider.test_refresh_token()).unwrap();
+    refresh.assert();
+    me.assert();
+    assert_eq!(stored_user_id(), 777);
+}
+
+#[test]
+fn tidal_reconcile_resolves_a_playlists_isrcs_in_one_request() {
+    use lofty::config::{ParseOptions, WriteOptions};
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::probe::Probe;
+    use lofty::tag::ItemKey;
+
+    let _guard = TIDAL_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+    env::set_var("TIDAL_API_BASE", &base);
+    env::set_var("TIDAL_AUTH_BASE", &base);
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Artist").join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/isrc");
+    std::fs::copy(fixture.join("isrc.mp3"), album.join("01 One.mp3")).unwrap();
+    std::fs::copy(fixture.join("isrc.flac"), album.join("02 One Again.flac")).unwrap();
+    let other = album.join("03 Two.mp3");
+    std::fs::copy(fixture.join("isrc.mp3"), &other).unwrap();
+    let mut tf = Probe::open(&other)
+        .unwrap()
+        .options(ParseOptions::new().read_properties(false))
+        .read()
+        .unwrap();
+    tf.primary_tag_mut()
+        .unwrap()
+        .insert_text(ItemKey::Isrc, "GBBBB0000002".into());
+    tf.save_to_path(&other, WriteOptions::default()).unwrap();
+    std::fs::write(
+        album.join("Album.m3u"),
+        "#EXTM3U\n01 One.mp3\n02 One Again.flac\n03 Two.mp3\n",
+    )
+    .unwrap();
+
+    let db_path = td.path().join("test.db");
+    let conn = db::open_or_create(&db_path).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": null,
+        "scope": "",
+        "user_id": 1
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "tidal", &stored, None, None).unwrap();
+
+    // Both ISRCs (the first one once) in a single request; the answer order
+    // does not follow the request.
+    let batch = server
+        .mock("GET", "/tracks")
+        .match_query(Matcher::Exact(
+            "countryCode=US&filter%5Bisrc%5D=GBAAA0000001&filter%5Bisrc%5D=GBBBB0000002".into(),
+        ))
+        .with_status(200)
+        .with_body(
+            json!({ "data": [
+                { "id": "22", "type": "tracks", "attributes": { "isrc": "GBBBB0000002" } },
+                { "id": "11", "type": "tracks", "attributes": { "isrc": "GBAAA0000001" } }
+            ] })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    let provider: std::sync::Arc<dyn Provider> = std::sync::Arc::new(TidalProvider::new(
+        "cid".into(),
+        "csecret".into(),
+        db_path.clone(),
+        None,
+        cfg.clone(),
+    ));
+    let pool = db::create_pool(&db_path).unwrap();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let (uris, local_count) = rt
+        .block_on(
+            music_file_playlist_online_sync::worker::desired_remote_uris_for_playlist(
+                &cfg,
+                "Artist/Album",
+                provider,
+                &pool,
+                false,
+            ),
+        )
+        .unwrap();
+    batch.assert();
+    assert_eq!(local_count, 3);
+    assert_eq!(uris, vec!["tidal:track:11", "tidal:track:22"]);
+    let cached =
+        db::get_track_cache_by_local(&conn, "tidal", &album.join("03 Two.mp3").to_string_lossy())
+            .unwrap()
+            .unwrap();
+    assert_eq!(cached.0.as_deref(), Some("GBBBB0000002"));
+    assert_eq!(cached.1.as_deref(), Some("tidal:track:22"));
+}
@@ -1,8 +1,14 @@
 use lib::db;
 use mockito::Server;
 use music_file_playlist_online_sync as lib;
+use once_cell::sync::Lazy;
 use serde_json::json;
 use std::path::PathBuf;
+use std::sync::Mutex;
+
+// The Spotify tests point SPOTIFY_AUTH_BASE at their own mockito server, so
+// they must not run concurrently.
+static SPOTIFY_TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 fn save_credentials(
     conn: &rusqlite::Connection,
@@ -23,6 +29,7 @@ fn save_credentials(
 
 #[test]
 fn spotify_token_refresh_success_and_preserve_client() {
+    let _guard = SPOTIFY_TEST_LOCK.lock().unwrap();
     let mut server = Server::new();
     let base = server.url();
 
@@ -95,6 +102,7 @@ fn spotify_token_refresh_success_and_preserve_client() {
 
 #[test]
 fn spotify_token_refresh_failure_invalid_client() {
+    let _guard = SPOTIFY_TEST_LOCK.lock().unwrap();
     let mut server = Server::new();
     let base = server.url();
 
@@ -254,3 +262,235 @@ fn tidal_token_refresh_failure_invalid_client() {
         assert_eq!(bearer, "Bearer old");
     }
 }
+
+#[test]
+fn concurrent_callers_share_a_single_spotify_refresh() {
+    use lib::api::Provider;
+    let _guard = SPOTIFY_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let base = server.url();
+
+    // Spotify rotates refresh tokens: each one is accepted once, so a second
+    // refresh racing the first would send a token that is no longer valid.
+    let first = server
+        .mock("POST", "/api/token")
+        .match_body(mockito::Matcher::UrlEncoded(
+            "refresh_token".into(),
+            "rt1".into(),
+        ))
+        .with_status(200)
+        .with_body(
+            json!({"access_token": "a2", "expires_in": 3600, "refresh_token": "rt2"}).to_string(),
+        )
+        .expect(1)
+        .create();
+    let second = server
+        .mock("POST", "/api/token")
+        .match_body(mockito::Matcher::UrlEncoded(
+            "refresh_token".into(),
+            "rt2".into(),
+        ))
+        .with_status(200)
+        .with_body(
+            json!({"access_token": "a3", "expires_in": 3600, "refresh_token": "rt3"}).to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let dir = tempfile::tempdir().expect("tmpdir");
+    let db_path = dir.path().join("music-sync.db");
+    let conn = db::open_or_create(&db_path).expect("open db");
+    let init_token = json!({
+        "access_token": "a1",
+        "token_type": "Bearer",
+        "expires_at": 0,
+        "refresh_token": "rt1",
+    })
+    .to_string();
+    save_credentials(&conn, "spotify", &init_token, "test_id", "test_secret");
+    std::env::set_var("SPOTIFY_AUTH_BASE", &base);
+
+    let provider = lib::api::spotify::SpotifyProvider::new(
+        String::new(),
+        String::new(),
+        db_path.clone(),
+        Default::default(),
+    );
+    let rt = tokio::runtime::Runtime::new().expect("rt");
+    let bearers = rt.block_on(futures::future::join_all(
+        (0..10).map(|_| provider.get_bearer()),
+    ));
+    for bearer in bearers {
+        assert_eq!(bearer.unwrap(), "Bearer a2");
+    }
+    first.assert();
+
+    // Requests rejected with 401 at the same time force one refresh between
+    // them.
+    let forced = rt.block_on(futures::future::join_all(
+        (0..10).map(|_| Provider::refresh_token(&provider)),
+    ));
+    assert!(forced.iter().all(|r| r.is_ok()));
+    second.assert();
+    assert_eq!(rt.block_on(provider.get_bearer()).unwrap(), "Bearer a3");
+}
+
+#[test]
+fn auth_refresh_forces_a_refresh_of_a_valid_token() {
+    let _guard = SPOTIFY_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    let refreshed = server
+        .mock("POST", "/api/token")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({"access_token": "forced", "expires_in": 3600}).to_string())
+        .expect(1)
+        .create();
+
+    let dir = tempfile::tempdir().expect("tmpdir");
+    let db_path = dir.path().join("music-sync.db");
+    let conn = db::open_or_create(&db_path).expect("open db");
+    let cfg: lib::config::Config =
+        toml::from_str(&format!("root_folder = \".\"\ndb_path = {:?}\n", db_path)).unwrap();
+    let rt = tokio::runtime::Runtime::new().expect("rt");
+    let err = rt
+        .block_on(lib::troubleshoot::refresh_credentials(&cfg, "spotify"))
+        .unwrap_err();
+    assert!(err.to_string().contains("auth spotify"), "{}", err);
+    assert!(rt
+        .block_on(lib::troubleshoot::refresh_credentials(&cfg, "napster"))
+        .is_err());
+
+    // Still valid for a day, but refreshed anyway.
+    let valid = json!({
+        "access_token": "old",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 86400,
+        "refresh_token": "refresh-spotify",
+    })
+    .to_string();
+    save_credentials(&conn, "spotify", &valid, "test_id", "test_secret");
+    std::env::set_var("SPOTIFY_AUTH_BASE", server.url());
+    let report = rt
+        .block_on(lib::troubleshoot::refresh_credentials(&cfg, "Spotify"))
+        .unwrap();
+    refreshed.assert();
+    assert!(
+        ["valid for 59 min", "valid for 60 min"]
+            .iter()
+            .any(|s| report.contains(s)),
+        "{}",
+        report
+    );
+    let (json, _, _) = db::load_credential_with_client(&conn, "spotify")
+        .unwrap()
+        .unwrap();
+    assert!(json.contains("forced"), "{}", json);
+}
+
+#[test]
+fn requests_rejected_with_the_same_token_share_a_refresh_when_answered_in_turn() {
+    use lib::api::{Provider, RequestSpec};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    let _guard = SPOTIFY_TEST_LOCK.lock().unwrap();
+    fn sent_bearer(req: &mockito::Request, token: &str) -> bool {
+        req.header("authorization")
+            .iter()
+            .any(|v| *v == format!("Bearer {}", token).as_str())
+    }
+    let mut auth = Server::new();
+    let refreshed = auth
+        .mock("POST", "/api/token")
+        .with_status(200)
+        .with_body(
+            json!({"access_token": "a2", "expires_in": 3600, "refresh_token": "rt2"}).to_string(),
+        )
+        .expect(1)
+        .create();
+    std::env::set_var("SPOTIFY_AUTH_BASE", auth.url());
+
+    // Each request gets its own server: B's server holds back its 401 until
+    // A has refreshed and retried, without blocking A.
+    let a_retried = Arc::new(AtomicBool::new(false));
+    let mut server_a = Server::new();
+    let a_stale = server_a
+        .mock("GET", "/a")
+        .match_header("authorization", "Bearer a1")
+        .with_status(401)
+        .expect(1)
+        .create();
+    let a_fresh = server_a
+        .mock("GET", "/a")
+        .match_request({
+            let a_retried = a_retried.clone();
+            move |req| {
+                let fresh = sent_bearer(req, "a2");
+                a_retried.fetch_or(fresh, Ordering::SeqCst);
+                fresh
+            }
+        })
+        .with_status(200)
+        .expect(1)
+        .create();
+    let mut server_b = Server::new();
+    let b_stale = server_b
+        .mock("GET", "/b")
+        .match_request({
+            let a_retried = a_retried.clone();
+            move |req| {
+                if !sent_bearer(req, "a1") {
+                    return false;
+                }
+                let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+                while !a_retried.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                true
+            }
+        })
+        .with_status(401)
+        .expect(1)
+        .create();
+    let b_fresh = server_b
+        .mock("GET", "/b")
+        .match_header("authorization", "Bearer a2")
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let dir = tempfile::tempdir().expect("tmpdir");
+    let db_path = dir.path().join("music-sync.db");
+    let conn = db::open_or_create(&db_path).expect("open db");
+    let token = json!({
+        "access_token": "a1",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "rt1",
+    })
+    .to_string();
+    save_credentials(&conn, "spotify", &token, "test_id", "test_secret");
+    let provider = lib::api::spotify::SpotifyProvider::new(
+        String::new(),
+        String::new(),
+        db_path,
+        Default::default(),
+    );
+
+    let rt = tokio::runtime::Runtime::new().expect("rt");
+    let (a, b) = rt.block_on(async {
+        let a_spec = RequestSpec::get(format!("{}/a", server_a.url()));
+        let b_spec = RequestSpec::get(format!("{}/b", server_b.url()));
+        futures::join!(
+            provider.execute_request("a", &a_spec),
+            provider.execute_request("b", &b_spec)
+        )
+    });
+    assert_eq!(a.unwrap().status(), 200);
+    assert_eq!(b.unwrap().status(), 200);
+    a_stale.assert();
+    a_fresh.assert();
+    b_stale.assert();
+    b_fresh.assert();
+    refreshed.assert();
+}
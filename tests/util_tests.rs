@@ -0,0 +1,333 @@
+use std::path::Path;
+
+use music_file_playlist_online_sync::util;
+
+#[test]
+fn filename_candidates_split_on_dash() {
+    let c = util::filename_artist_title_candidates(Path::new("/music/Artist - Song.mp3"));
+    assert_eq!(
+        c,
+        vec![
+            ("Artist".to_string(), "Song".to_string()),
+            ("Song".to_string(), "Artist".to_string()),
+        ]
+    );
+    let c = util::filename_artist_title_candidates(Path::new("/music/01 Track.flac"));
+    assert_eq!(c, vec![(String::new(), "01 Track".to_string())]);
+}
+
+#[test]
+fn untagged_file_falls_back_to_filename() {
+    let td = tempfile::tempdir().unwrap();
+    let f = td.path().join("Artist - Song.mp3");
+    std::fs::write(&f, b"not really audio").unwrap();
+    assert!(util::extract_artist_title_from_path(&f).is_none());
+    assert_eq!(
+        util::artist_title_candidates(&f),
+        util::filename_artist_title_candidates(&f)
+    );
+}
+
+#[test]
+fn filename_regex_used_before_dash_heuristic() {
+    let re = regex::Regex::new(r"^\d+ (?P<title>.+) \((?P<artist>[^)]+)\)$").unwrap();
+    let td = tempfile::tempdir().unwrap();
+    let f = td.path().join("03 Song Name (The Artist).mp3");
+    std::fs::write(&f, b"not really audio").unwrap();
+    let meta = util::filename_metadata_from_regex(&f, &re).unwrap();
+    assert_eq!(meta.title.as_deref(), Some("Song Name"));
+    assert_eq!(meta.artist.as_deref(), Some("The Artist"));
+    assert_eq!(meta.album, None);
+    assert_eq!(
+        util::artist_title_candidates_with(&f, Some(&re)),
+        vec![("The Artist".to_string(), "Song Name".to_string())]
+    );
+
+    // Non-matching names keep the " - " heuristic.
+    let g = td.path().join("Artist - Song.mp3");
+    std::fs::write(&g, b"not really audio").unwrap();
+    assert_eq!(
+        util::artist_title_candidates_with(&g, Some(&re)),
+        util::filename_artist_title_candidates(&g)
+    );
+}
+
+#[test]
+fn copy_suffixes_are_stripped_from_file_names_only_when_enabled() {
+    let td = tempfile::tempdir().unwrap();
+    let cfg: music_file_playlist_online_sync::config::Config = toml::from_str(
+        "root_folder = \".\"\nfilename_copy_suffix_patterns = ['(?i) copy \\d+', ' - Copy', ' \\(\\d+\\)']\n",
+    )
+    .unwrap();
+    let suffixes = cfg.compiled_filename_copy_suffixes().unwrap();
+    let candidates = |name: &str| {
+        let f = td.path().join(name);
+        std::fs::write(&f, b"not really audio").unwrap();
+        util::artist_title_candidates_stripped(&f, None, &suffixes)
+    };
+    let song = vec![(String::new(), "Song".to_string())];
+    assert_eq!(candidates("Song Copy 2.mp3"), song);
+    assert_eq!(candidates("Song - Copy.mp3"), song);
+    assert_eq!(candidates("Song (1).mp3"), song);
+    assert_eq!(
+        candidates("Artist - Song copy 3.mp3")[0],
+        ("Artist".to_string(), "Song".to_string())
+    );
+    // Only a trailing suffix is removed.
+    assert_eq!(
+        candidates("Copy Machine.mp3"),
+        vec![(String::new(), "Copy Machine".to_string())]
+    );
+
+    let off: music_file_playlist_online_sync::config::Config =
+        toml::from_str("root_folder = \".\"\nstrip_filename_copy_suffix = false\n").unwrap();
+    assert!(off.compiled_filename_copy_suffixes().unwrap().is_empty());
+    let bad: music_file_playlist_online_sync::config::Config =
+        toml::from_str("root_folder = \".\"\nfilename_copy_suffix_patterns = ['(']\n").unwrap();
+    assert!(bad.validate().is_err());
+}
+
+#[test]
+fn ignore_matcher_gitignore_semantics() {
+    let root = Path::new("/music");
+    let m = util::IgnoreMatcher::new(
+        &[
+            "# comment".into(),
+            "@eaDir".into(),
+            "*.part".into(),
+            "Scratch/".into(),
+            "/Incoming/**".into(),
+        ],
+        vec![root.to_path_buf()],
+    );
+    // bare names match at any depth, including everything below them
+    assert!(m.is_ignored(&root.join("A/@eaDir"), true));
+    assert!(m.is_ignored(&root.join("A/@eaDir/thumb.mp3"), false));
+    assert!(m.is_ignored(&root.join("A/B/song.mp3.part"), false));
+    // trailing slash: folders only
+    assert!(m.is_ignored(&root.join("Scratch/x.mp3"), false));
+    assert!(!m.is_ignored(&root.join("A/Scratch"), false));
+    // leading slash: anchored at the root
+    assert!(m.is_ignored(&root.join("Incoming/New/x.mp3"), false));
+    assert!(!m.is_ignored(&root.join("A/Incoming/x.mp3"), false));
+    assert!(!m.is_ignored(&root.join("A/song.mp3"), false));
+    assert!(!util::IgnoreMatcher::default().is_ignored(&root.join("@eaDir"), true));
+}
+
+#[test]
+fn musicbrainz_lookup_picks_confident_recording_with_matching_length() {
+    let mut server = mockito::Server::new();
+    std::env::set_var("MUSICBRAINZ_API_BASE", server.url());
+    let _m = server
+        .mock("GET", "/recording")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded(
+                "query".into(),
+                "recording:\"Song\" AND artist:\"Artist\" AND release:\"Debut\"".into(),
+            ),
+            mockito::Matcher::UrlEncoded("fmt".into(), "json".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            serde_json::json!({ "recordings": [
+                { "score": 60, "length": 180000, "isrcs": ["LOWSCORE0001"] },
+                { "score": 100, "length": 300000, "isrcs": ["LONGLIVE0001"] },
+                { "score": 95, "length": 181000 },
+                { "score": 95, "length": 182000, "isrcs": ["gbaaa0000001"] }
+            ] })
+            .to_string(),
+        )
+        .create();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let isrc = rt
+        .block_on(util::musicbrainz_lookup_isrc(
+            "Artist",
+            "Song",
+            Some("Debut"),
+            Some(180_000),
+            10,
+        ))
+        .unwrap();
+    assert_eq!(isrc.as_deref(), Some("GBAAA0000001"));
+}
+
+#[test]
+fn isrc_is_read_and_normalized_for_each_container() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/isrc");
+    // Each fixture stores the same code the way a different tagger would:
+    // ID3v2 TSRC "gb-aaa-00-00001", FLAC "GB-AAA-00-00001", Ogg Vorbis
+    // " gbaaa0000001 " and an iTunes ISRC atom "GB AAA 00 00001".
+    for name in ["isrc.mp3", "isrc.flac", "isrc.ogg", "isrc.m4a"] {
+        assert_eq!(
+            util::extract_isrc_from_path(&fixtures.join(name)).as_deref(),
+            Some("GBAAA0000001"),
+            "{}",
+            name
+        );
+        let meta = util::extract_artist_title_from_path(&fixtures.join(name));
+        assert_eq!(
+            meta.map(|m| (m.artist, m.title)),
+            Some((Some("Artist".into()), Some("Song".into()))),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn normalize_isrc_strips_separators() {
+    assert_eq!(
+        util::normalize_isrc("us-rc1-76-07839").as_deref(),
+        Some("USRC17607839")
+    );
+    assert_eq!(
+        util::normalize_isrc(" USRC17607839\n").as_deref(),
+        Some("USRC17607839")
+    );
+    assert_eq!(util::normalize_isrc(" - "), None);
+}
+
+#[test]
+fn normalize_search_terms_folds_unicode_and_strips_noise() {
+    let tokens: Vec<String> = ["remastered", "radio edit", "feat"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let n = |t: &str, a: &str| util::normalize_search_terms(t, a, &tokens, true);
+    assert_eq!(
+        n("Jóga (Remastered 2011)", "Björk"),
+        ("Joga".to_string(), "Bjork".to_string())
+    );
+    assert_eq!(n("Song - Radio Edit", "Ａｒｔｉｓｔ").0, "Song");
+    assert_eq!(n("Song [feat. Someone]", "A").0, "Song");
+    // Groups and suffixes without a noise token are kept.
+    assert_eq!(
+        n("Song (Part 2) - Live at Wembley", "A").0,
+        "Song (Part 2) - Live at Wembley"
+    );
+    // "remastered" must be a whole word.
+    assert_eq!(n("Song (Unremastered)", "A").0, "Song (Unremastered)");
+    // Diacritics survive when not stripped (in decomposed form).
+    let (_, artist) = util::normalize_search_terms("Song", "Björk", &tokens, false);
+    assert_eq!(artist, "Bjo\u{308}rk");
+
+    // Exact terms come first; unchanged forms are not repeated.
+    let c = util::with_normalized_search_terms(
+        vec![
+            ("Björk".into(), "Jóga (Remastered 2011)".into()),
+            ("Artist".into(), "Song".into()),
+        ],
+        &tokens,
+        true,
+    );
+    assert_eq!(
+        c,
+        vec![
+            ("Björk".to_string(), "Jóga (Remastered 2011)".to_string()),
+            ("Artist".to_string(), "Song".to_string()),
+            ("Bjork".to_string(), "Joga".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn cover_image_lookup_and_downscaling() {
+    let td = tempfile::tempdir().unwrap();
+    assert_eq!(util::find_cover_image(td.path()), None);
+    std::fs::write(td.path().join("Cover.PNG"), b"").unwrap();
+    std::fs::write(td.path().join("other.jpg"), b"").unwrap();
+    assert_eq!(
+        util::find_cover_image(td.path()),
+        Some(td.path().join("Cover.PNG"))
+    );
+    std::fs::write(td.path().join("folder.jpg"), b"").unwrap();
+    assert_eq!(
+        util::find_cover_image(td.path()),
+        Some(td.path().join("folder.jpg"))
+    );
+
+    // A noisy PNG is re-encoded as a JPEG that fits the limit.
+    let img = image::RgbImage::from_fn(800, 800, |x, y| {
+        let v = (x.wrapping_mul(7919) ^ y.wrapping_mul(104_729) ^ (x * y)) as u8;
+        image::Rgb([v, v.wrapping_mul(3), v.wrapping_add(x as u8)])
+    });
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let jpeg = util::jpeg_within_size(&png, 40_000).unwrap();
+    assert!(jpeg.len() <= 40_000, "{} bytes", jpeg.len());
+    assert!(jpeg.starts_with(&[0xFF, 0xD8, 0xFF]));
+    // ...and small JPEGs are passed through untouched.
+    assert_eq!(util::jpeg_within_size(&jpeg, 40_000).unwrap(), jpeg);
+    assert!(util::jpeg_within_size(b"not an image", 40_000).is_err());
+}
+
+#[test]
+fn file_extensions_take_plain_extensions_and_file_name_globs() {
+    let plain: Vec<String> = vec!["*.mp3".into(), "FLAC".into(), ".ogg".into()];
+    for name in ["a.mp3", "b.MP3", "c.flac", "d.ogg"] {
+        assert!(
+            util::path_matches_extensions(Path::new("/m").join(name).as_path(), &plain),
+            "{}",
+            name
+        );
+    }
+    assert!(!util::path_matches_extensions(
+        Path::new("/m/e.wav"),
+        &plain
+    ));
+    assert!(!util::path_matches_extensions(Path::new("/m/mp3"), &plain));
+
+    let globs: Vec<String> = vec!["*.hi-res.flac".into(), "track-??.wav".into()];
+    assert!(util::path_matches_extensions(
+        Path::new("/m/Song.Hi-Res.flac"),
+        &globs
+    ));
+    assert!(util::path_matches_extensions(
+        Path::new("/m/track-01.wav"),
+        &globs
+    ));
+    assert!(!util::path_matches_extensions(
+        Path::new("/m/Song.flac"),
+        &globs
+    ));
+    assert!(!util::path_matches_extensions(
+        Path::new("/m/track-001.wav"),
+        &globs
+    ));
+
+    // Playlist files are never tracks, whatever the patterns say.
+    let all: Vec<String> = vec!["*".into(), "m3u".into()];
+    assert!(!util::path_matches_extensions(
+        Path::new("/m/Album.m3u"),
+        &all
+    ));
+    assert!(util::path_matches_extensions(
+        Path::new("/m/cover.jpg"),
+        &all
+    ));
+
+    assert!(util::validate_extension_pattern("*.hi-res.flac").is_ok());
+    assert!(util::validate_extension_pattern("[z-a].mp3").is_err());
+}
+
+#[test]
+fn plain_file_extension_patterns_compare_the_last_extension() {
+    let matches = |pat: &str, name: &str| {
+        util::path_matches_extensions(&Path::new("/music").join(name), &[pat.to_string()])
+    };
+    for pat in ["*.mp3", ".mp3", "mp3", "MP3", " *.Mp3 "] {
+        assert!(matches(pat, "song.mp3"), "{}", pat);
+        assert!(matches(pat, "SONG.MP3"), "{}", pat);
+        // Only the last extension counts.
+        assert!(matches(pat, "01. Intro.v2.mp3"), "{}", pat);
+        assert!(!matches(pat, "song.mp3.part"), "{}", pat);
+        // No extension at all, including a dotfile named like one.
+        assert!(!matches(pat, "mp3"), "{}", pat);
+        assert!(!matches(pat, ".mp3"), "{}", pat);
+        assert!(!matches(pat, "song.flac"), "{}", pat);
+    }
+    assert!(!matches("", "song.mp3"));
+}
@@ -46,3 +46,235 @@ local_playlist_template = "${{folder_name}}.m3u"
     assert!(p_a.exists(), "playlist for a should exist");
     assert!(p_b.exists(), "playlist for b should exist");
 }
+
+#[test]
+fn shutdown_flushes_pending_playlist_writes() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a")).unwrap();
+    let _ = File::create(root.join("a").join("s1.mp3")).unwrap();
+
+    // Poll mode with a debounce far longer than the test: the new track only
+    // reaches the playlist through the shutdown flush.
+    let cfg_path = td.path().join("cfg.toml");
+    let cfg_toml = format!(
+        r#"
+root_folder = "{}"
+db_path = "{}"
+log_dir = "{}"
+debounce_ms = 600000
+watch_mode = "poll"
+poll_interval_sec = 1
+playlist_mode = "flat"
+local_playlist_template = "${{folder_name}}.m3u"
+"#,
+        root.display(),
+        td.path().join("db.sqlite").display(),
+        td.path().display()
+    );
+    fs::write(&cfg_path, cfg_toml).unwrap();
+    let cfg = Config::from_path(&cfg_path).expect("load cfg");
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let cfg = cfg.clone();
+        let shutdown = shutdown.clone();
+        std::thread::spawn(move || watcher::run_watcher_until(&cfg, shutdown, Arc::default()))
+    };
+
+    let playlist = root.join("a").join("a.m3u");
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while !playlist.exists() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "initial pass did not finish"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let _ = File::create(root.join("a").join("s2.mp3")).unwrap();
+    // Let at least one rescan pick up the new file.
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(!fs::read_to_string(&playlist).unwrap().contains("s2.mp3"));
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle
+        .join()
+        .unwrap()
+        .expect("watcher returns Ok after shutdown");
+
+    let contents = fs::read_to_string(&playlist).unwrap();
+    assert!(contents.contains("s2.mp3"), "{}", contents);
+}
+
+#[test]
+fn flush_now_writes_pending_playlists_without_stopping() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a")).unwrap();
+    let _ = File::create(root.join("a").join("s1.mp3")).unwrap();
+
+    let cfg_path = td.path().join("cfg.toml");
+    let cfg_toml = format!(
+        r#"
+root_folder = "{}"
+db_path = "{}"
+log_dir = "{}"
+debounce_ms = 600000
+watch_mode = "poll"
+poll_interval_sec = 1
+watcher_instant_trigger_threshold = 0
+watcher_deferred_trigger_delay_sec = 0
+playlist_mode = "flat"
+local_playlist_template = "${{folder_name}}.m3u"
+"#,
+        root.display(),
+        td.path().join("db.sqlite").display(),
+        td.path().display()
+    );
+    fs::write(&cfg_path, cfg_toml).unwrap();
+    let cfg = Config::from_path(&cfg_path).expect("load cfg");
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flush_now = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let cfg = cfg.clone();
+        let shutdown = shutdown.clone();
+        let flush_now = flush_now.clone();
+        std::thread::spawn(move || watcher::run_watcher_until(&cfg, shutdown, flush_now))
+    };
+
+    let playlist = root.join("a").join("a.m3u");
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while !playlist.exists() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "initial pass did not finish"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let _ = File::create(root.join("a").join("s2.mp3")).unwrap();
+    std::thread::sleep(Duration::from_millis(2500));
+
+    flush_now.store(true, Ordering::SeqCst);
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while !fs::read_to_string(&playlist).unwrap().contains("s2.mp3") {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "forced flush did not write"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // The flag is consumed and the watcher keeps running.
+    assert!(!flush_now.load(Ordering::SeqCst));
+    assert!(!handle.is_finished());
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap().unwrap();
+    let conn = music_file_playlist_online_sync::db::open_or_create(&cfg.db_path).unwrap();
+    let creates: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM event_queue WHERE playlist_name = 'a' AND action = 'create'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert!(creates >= 1);
+}
+
+#[test]
+fn reload_applies_debounce_and_keeps_roots_until_restart() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a")).unwrap();
+    let _ = File::create(root.join("a").join("s1.mp3")).unwrap();
+
+    let cfg_path = td.path().join("cfg.toml");
+    let write_cfg = |root: &std::path::Path, debounce_ms: u64| {
+        let cfg_toml = format!(
+            r#"
+root_folder = "{}"
+db_path = "{}"
+log_dir = "{}"
+debounce_ms = {}
+watch_mode = "poll"
+poll_interval_sec = 1
+watcher_instant_trigger_threshold = 0
+watcher_deferred_trigger_delay_sec = 0
+playlist_mode = "flat"
+local_playlist_template = "${{folder_name}}.m3u"
+"#,
+            root.display(),
+            td.path().join("db.sqlite").display(),
+            td.path().display(),
+            debounce_ms
+        );
+        fs::write(&cfg_path, cfg_toml).unwrap();
+    };
+    write_cfg(&root, 600000);
+    let cfg = Config::from_path(&cfg_path).expect("load cfg");
+
+    // Only the debounce change is applied; the root needs a restart.
+    let mut edited = cfg.clone();
+    edited.debounce_ms = 100;
+    edited.root_folder = td.path().join("elsewhere");
+    let plan = watcher::plan_config_reload(&cfg, edited);
+    assert_eq!(plan.applied, vec!["debounce_ms: 600000 -> 100"]);
+    assert_eq!(plan.needs_restart.len(), 1);
+    assert!(plan.needs_restart[0].starts_with("root_folder: "));
+    assert_eq!(plan.config.root_folder, root);
+    assert_eq!(plan.config.debounce_ms, 100);
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let reload = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let cfg = cfg.clone();
+        let cfg_path = cfg_path.clone();
+        let shutdown = shutdown.clone();
+        let reload = reload.clone();
+        std::thread::spawn(move || {
+            watcher::run_watcher_until_reloading(&cfg, &cfg_path, shutdown, Arc::default(), reload)
+        })
+    };
+
+    let playlist = root.join("a").join("a.m3u");
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while !playlist.exists() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "initial pass did not finish"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    write_cfg(&td.path().join("elsewhere"), 100);
+    reload.store(true, Ordering::SeqCst);
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while reload.load(Ordering::SeqCst) {
+        assert!(std::time::Instant::now() < deadline, "reload not taken");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // With the new debounce the track is written without a flush, and the
+    // original root is still watched.
+    let _ = File::create(root.join("a").join("s2.mp3")).unwrap();
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while !fs::read_to_string(&playlist).unwrap().contains("s2.mp3") {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "reloaded debounce was not applied"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap().unwrap();
+}
@@ -47,3 +47,239 @@ fn in_memory_tree_applies_synthetic_events() {
     assert!(matches!(ops.get(0).unwrap(), LogicalOp::Remove { .. }));
     assert!(matches!(ops.get(1).unwrap(), LogicalOp::Add { .. }));
 }
+
+#[test]
+fn ignore_patterns_exclude_folders_and_events() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a").join("@eaDir")).unwrap();
+    fs::create_dir_all(root.join(".stfolder")).unwrap();
+    fs::write(root.join("a").join("song.mp3"), b"").unwrap();
+    fs::write(root.join("a").join("@eaDir").join("thumb.mp3"), b"").unwrap();
+
+    let ignore = music_file_playlist_online_sync::util::IgnoreMatcher::new(
+        &["@eaDir/".into(), ".stfolder".into()],
+        vec![root.clone()],
+    );
+    let mut tree =
+        InMemoryTree::build_with_ignore(std::slice::from_ref(&root), None, None, ignore.clone())
+            .unwrap();
+    assert!(tree.nodes.contains_key(&root.join("a")));
+    assert!(!tree.nodes.contains_key(&root.join("a").join("@eaDir")));
+    assert!(!tree.nodes.contains_key(&root.join(".stfolder")));
+
+    // events inside ignored folders produce no operations
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileCreate(
+        root.join("a").join("@eaDir").join("new.mp3"),
+    ));
+    assert!(ops.is_empty());
+    // moving a folder out of an ignored location is a create
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FolderRename {
+        from: root.join(".stfolder").join("b"),
+        to: root.join("b"),
+    });
+    assert!(matches!(ops.as_slice(), [LogicalOp::Create { .. }]));
+
+    // flat playlists leave out ignored subfolders
+    let m3u = root.join("a").join("a.m3u");
+    music_file_playlist_online_sync::playlist::write_flat_playlist_filtered(
+        &root.join("a"),
+        &m3u,
+        "append",
+        &["*.mp3".into()],
+        &ignore,
+    )
+    .unwrap();
+    let body = fs::read_to_string(&m3u).unwrap();
+    assert!(body.contains("song.mp3"));
+    assert!(!body.contains("thumb.mp3"));
+}
+
+#[test]
+fn marker_file_excludes_folder_and_descendants() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let podcasts = root.join("Podcasts");
+    fs::create_dir_all(podcasts.join("Show")).unwrap();
+    fs::create_dir_all(root.join("Album")).unwrap();
+    fs::write(podcasts.join(".noplaylist"), b"").unwrap();
+    fs::write(podcasts.join("Show").join("ep1.mp3"), b"").unwrap();
+    fs::write(root.join("Album").join("song.mp3"), b"").unwrap();
+
+    // `build` honours the default marker name...
+    let tree = InMemoryTree::build(&root, None, None).unwrap();
+    assert!(tree.nodes.contains_key(&root.join("Album")));
+    assert!(!tree.nodes.contains_key(&podcasts));
+    assert!(!tree.nodes.contains_key(&podcasts.join("Show")));
+
+    // ...and the configured one applies to events and playlist writes.
+    let ignore = music_file_playlist_online_sync::util::IgnoreMatcher::new(&[], vec![root.clone()])
+        .with_marker(".noplaylist");
+    let mut tree =
+        InMemoryTree::build_with_ignore(std::slice::from_ref(&root), None, None, ignore.clone())
+            .unwrap();
+    assert!(!tree.nodes.contains_key(&podcasts));
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileCreate(
+        podcasts.join("Show").join("ep2.mp3"),
+    ));
+    assert!(ops.is_empty());
+    let m3u = root.join("root.m3u");
+    music_file_playlist_online_sync::playlist::write_flat_playlist_filtered(
+        &root,
+        &m3u,
+        "append",
+        &["*.mp3".into()],
+        &ignore,
+    )
+    .unwrap();
+    let body = fs::read_to_string(&m3u).unwrap();
+    assert!(body.contains("song.mp3"));
+    assert!(!body.contains("ep1.mp3"));
+
+    // An empty marker name disables the check.
+    let off = music_file_playlist_online_sync::util::IgnoreMatcher::new(&[], vec![root.clone()])
+        .with_marker("");
+    assert!(!off.is_ignored(&podcasts.join("Show"), true));
+}
+
+#[test]
+fn remove_then_create_elsewhere_is_a_move() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a")).unwrap();
+    fs::create_dir_all(root.join("b")).unwrap();
+    let from = root.join("a").join("song.mp3");
+    fs::write(&from, b"audio").unwrap();
+    let mut tree = InMemoryTree::build(&root, None, None).unwrap();
+
+    // notify reports the move as two unrelated events
+    let to = root.join("b").join("song.mp3");
+    fs::rename(&from, &to).unwrap();
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileRemove(from.clone()));
+    assert!(matches!(ops.as_slice(), [LogicalOp::Remove { .. }]));
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileCreate(to.clone()));
+    assert!(matches!(ops.first(), Some(LogicalOp::Add { .. })));
+    match ops.get(1) {
+        Some(LogicalOp::TrackMoved { from_path, to_path }) => {
+            assert_eq!(from_path, &from);
+            assert_eq!(to_path, &to);
+        }
+        other => panic!("expected TrackMoved, got {:?}", other),
+    }
+
+    // a different file with the same name is not mistaken for a move
+    let other = root.join("a").join("other.mp3");
+    fs::write(&other, b"x").unwrap();
+    let mut tree = InMemoryTree::build(&root, None, None).unwrap();
+    let unrelated = root.join("b").join("other.mp3");
+    fs::write(&unrelated, b"much longer content").unwrap();
+    fs::remove_file(&other).unwrap();
+    tree.apply_synthetic_event(SyntheticEvent::FileRemove(other.clone()));
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileCreate(unrelated));
+    assert_eq!(ops.len(), 1);
+
+    // nor is a distinct file of the same size
+    let first = root.join("a").join("same.mp3");
+    fs::write(&first, b"12345").unwrap();
+    let mut tree = InMemoryTree::build(&root, None, None).unwrap();
+    let second = root.join("b").join("same.mp3");
+    fs::write(&second, b"abcde").unwrap();
+    fs::remove_file(&first).unwrap();
+    tree.apply_synthetic_event(SyntheticEvent::FileRemove(first));
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FileCreate(second));
+    assert!(matches!(ops.as_slice(), [LogicalOp::Add { .. }]), "{:?}", ops);
+}
+
+#[test]
+fn poll_diff_reports_changes_between_scans() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    fs::create_dir_all(root.join("a")).unwrap();
+    fs::create_dir_all(root.join("gone").join("deep")).unwrap();
+    fs::write(root.join("a").join("keep.mp3"), b"").unwrap();
+    fs::write(root.join("a").join("old.mp3"), b"").unwrap();
+    let before = InMemoryTree::build(&root, None, None).unwrap();
+
+    fs::remove_file(root.join("a").join("old.mp3")).unwrap();
+    fs::remove_dir_all(root.join("gone")).unwrap();
+    fs::create_dir_all(root.join("new").join("inner")).unwrap();
+    fs::write(root.join("new").join("inner").join("song.mp3"), b"").unwrap();
+    let after = InMemoryTree::build(&root, None, None).unwrap();
+
+    let events: Vec<String> = before
+        .diff_events(&after)
+        .into_iter()
+        .map(|e| match e {
+            SyntheticEvent::FileCreate(p) => {
+                format!("+{}", p.strip_prefix(&root).unwrap().display())
+            }
+            SyntheticEvent::FileRemove(p) => {
+                format!("-{}", p.strip_prefix(&root).unwrap().display())
+            }
+            SyntheticEvent::FolderCreate(p) => {
+                format!("+{}/", p.strip_prefix(&root).unwrap().display())
+            }
+            SyntheticEvent::FolderRemove(p) => {
+                format!("-{}/", p.strip_prefix(&root).unwrap().display())
+            }
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            "-a/old.mp3",
+            "-gone/deep/",
+            "-gone/",
+            "+new/",
+            "+new/inner/",
+            "+new/inner/song.mp3",
+        ]
+    );
+    assert!(after.diff_events(&after).is_empty());
+}
+
+#[test]
+fn known_folders_are_rescanned_when_created_or_renamed_again() {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("a");
+    fs::create_dir_all(&album).unwrap();
+    fs::write(album.join("one.mp3"), b"1").unwrap();
+    let exts = vec!["*.mp3".to_string()];
+    let mut tree = InMemoryTree::build(&root, None, Some(&exts)).unwrap();
+
+    // Changes made while the watcher was not running.
+    fs::remove_file(album.join("one.mp3")).unwrap();
+    fs::write(album.join("two.mp3"), b"2").unwrap();
+    fs::write(album.join("cover.jpg"), b"").unwrap();
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FolderCreate(album.clone()));
+    assert_eq!(ops.len(), 3, "{:?}", ops);
+    assert!(matches!(&ops[0], LogicalOp::Create { playlist_folder } if playlist_folder == &album));
+    assert!(
+        matches!(&ops[1], LogicalOp::Remove { track_path, .. } if track_path == &album.join("one.mp3"))
+    );
+    assert!(
+        matches!(&ops[2], LogicalOp::Add { playlist_folder, track_path }
+            if playlist_folder == &album && track_path == &album.join("two.mp3"))
+    );
+    // Nothing changed since: a rescan finds no drift.
+    assert!(tree.rescan_folder(&album).is_empty());
+
+    // A renamed folder keeps its tracks; only the missed add is reported.
+    let renamed = root.join("c");
+    fs::rename(&album, &renamed).unwrap();
+    fs::write(renamed.join("three.mp3"), b"3").unwrap();
+    let ops = tree.apply_synthetic_event(SyntheticEvent::FolderRename {
+        from: album.clone(),
+        to: renamed.clone(),
+    });
+    assert_eq!(ops.len(), 2, "{:?}", ops);
+    assert!(matches!(&ops[0], LogicalOp::PlaylistRename { .. }));
+    assert!(
+        matches!(&ops[1], LogicalOp::Add { track_path, .. } if track_path == &renamed.join("three.mp3"))
+    );
+    let node = &tree.nodes[&renamed];
+    assert_eq!(node.path, renamed);
+    assert!(node.tracks.contains(&renamed.join("two.mp3")));
+}
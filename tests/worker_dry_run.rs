@@ -0,0 +1,98 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::subsonic::SubsonicCredentials;
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::db;
+use music_file_playlist_online_sync::models::EventAction;
+use music_file_playlist_online_sync::worker::run_worker_once;
+use rusqlite::Connection;
+use serde_json::json;
+use tempfile::tempdir;
+
+fn ok(body: serde_json::Value) -> String {
+    let mut inner = json!({ "status": "ok", "version": "1.16.1" });
+    if let (Some(obj), Some(extra)) = (inner.as_object_mut(), body.as_object()) {
+        obj.extend(extra.clone());
+    }
+    json!({ "subsonic-response": inner }).to_string()
+}
+
+#[test]
+fn dry_run_lists_remote_but_does_not_mutate_or_mark_synced() {
+    let mut server = Server::new();
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    db::upsert_playlist_map(&conn, "subsonic", "Mapped", "pl1").unwrap();
+    for uri in ["subsonic:song:1", "subsonic:song:2"] {
+        let tp = format!("uri::{}", uri);
+        db::enqueue_event(&conn, "Mapped", &EventAction::Add, Some(&tp), None).unwrap();
+    }
+    db::enqueue_event(
+        &conn,
+        "Unmapped",
+        &EventAction::Add,
+        Some("uri::subsonic:song:3"),
+        None,
+    )
+    .unwrap();
+
+    // The existing playlist is read (validity check + planned diff)...
+    let list = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::UrlEncoded("id".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl1", "name": "Mapped", "entry": [ { "id": "1" } ] } }),
+        ))
+        .expect_at_least(1)
+        .create();
+    // ...but nothing is created or modified.
+    let update = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+    let create = server
+        .mock("GET", "/rest/createPlaylist")
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+
+    let cfg = Config {
+        root_folder: td.path().join("root"),
+        debounce_ms: 100,
+        log_dir: td.path().join("logs"),
+        max_retries_on_error: 1,
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        search_strip_tokens: Vec::new(),
+        file_extensions: vec!["*.mp3".into()],
+        db_path: db_path.clone(),
+        ..Default::default()
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(run_worker_once(&cfg, None, false, true))
+        .unwrap();
+
+    list.assert();
+    update.assert();
+    create.assert();
+    let unsynced: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM event_queue WHERE is_synced = 0",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(unsynced, 3);
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "subsonic", "Unmapped").unwrap(),
+        None
+    );
+}
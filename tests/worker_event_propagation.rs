@@ -1,4 +1,4 @@
-use music_file_playlist_online_sync::api::Provider;
+use music_file_playlist_online_sync::api::{Provider, ProviderResult};
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -11,39 +11,39 @@ struct TestProvider {
 
 #[async_trait::async_trait]
 impl Provider for TestProvider {
-    async fn ensure_playlist(&self, name: &str, _description: &str) -> anyhow::Result<String> {
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
         self.called
             .lock()
             .unwrap()
             .insert(format!("{}:ensure_playlist:{}", self.name, name));
         Ok(format!("playlist-{}", name))
     }
-    async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> anyhow::Result<()> {
+    async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
         Ok(())
     }
-    async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+    async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
         Ok(())
     }
-    async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> anyhow::Result<()> {
+    async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
         Ok(())
     }
-    async fn list_playlist_tracks(&self, _playlist_id: &str) -> anyhow::Result<Vec<String>> {
+    async fn list_playlist_tracks(&self, _playlist_id: &str) -> ProviderResult<Vec<String>> {
         Ok(vec![])
     }
     async fn search_track_uri(
         &self,
         _title: &str,
         _artist: &str,
-    ) -> anyhow::Result<Option<String>> {
+    ) -> ProviderResult<Option<String>> {
         Ok(None)
     }
-    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> anyhow::Result<Option<String>> {
+    async fn search_track_uri_by_isrc(&self, _isrc: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
-    async fn lookup_track_isrc(&self, _uri: &str) -> anyhow::Result<Option<String>> {
+    async fn lookup_track_isrc(&self, _uri: &str) -> ProviderResult<Option<String>> {
         Ok(None)
     }
-    async fn delete_playlist(&self, _playlist_id: &str) -> anyhow::Result<()> {
+    async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
         Ok(())
     }
     fn name(&self) -> &str {
@@ -57,10 +57,10 @@ impl Provider for TestProvider {
         static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
         CLIENT.get_or_init(reqwest::Client::new)
     }
-    async fn get_bearer(&self) -> anyhow::Result<String> {
+    async fn get_bearer(&self) -> ProviderResult<String> {
         Ok("Bearer test".to_string())
     }
-    async fn refresh_token(&self) -> anyhow::Result<()> {
+    async fn refresh_token(&self) -> ProviderResult<()> {
         Ok(())
     }
 }
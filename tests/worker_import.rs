@@ -0,0 +1,154 @@
+use music_file_playlist_online_sync::api::{Provider, ProviderResult};
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::db;
+use music_file_playlist_online_sync::worker::import_playlist;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+// Remote playlist with fixed tracks; only `isrc:track` reports an ISRC.
+struct ImportProvider {
+    tracks: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl Provider for ImportProvider {
+    async fn ensure_playlist(&self, name: &str, _description: &str) -> ProviderResult<String> {
+        Ok(name.to_string())
+    }
+    async fn rename_playlist(&self, _playlist_id: &str, _new_name: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn add_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn remove_tracks(&self, _playlist_id: &str, _uris: &[String]) -> ProviderResult<()> {
+        Ok(())
+    }
+    async fn list_playlist_tracks(&self, playlist_id: &str) -> ProviderResult<Vec<String>> {
+        assert_eq!(playlist_id, "remote-pl");
+        Ok(self.tracks.clone())
+    }
+    async fn search_track_uri(
+        &self,
+        _title: &str,
+        _artist: &str,
+    ) -> ProviderResult<Option<String>> {
+        Ok(None)
+    }
+    async fn lookup_track_isrc(&self, uri: &str) -> ProviderResult<Option<String>> {
+        Ok((uri == "isrc:track").then(|| "gb-aaa-00-00001".to_string()))
+    }
+    async fn delete_playlist(&self, _playlist_id: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+    fn name(&self) -> &str {
+        "mock"
+    }
+    fn is_authenticated(&self) -> bool {
+        true
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        use std::sync::OnceLock;
+        static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+        CLIENT.get_or_init(reqwest::Client::new)
+    }
+    async fn get_bearer(&self) -> ProviderResult<String> {
+        Ok("Bearer test".to_string())
+    }
+    async fn refresh_token(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+}
+
+fn setup(tracks: &[&str]) -> (tempfile::TempDir, Config, Arc<dyn Provider>) {
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Artist").join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    std::fs::write(album.join("01 One.mp3"), b"").unwrap();
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/isrc");
+    std::fs::copy(fixture.join("isrc.mp3"), album.join("02 Two.mp3")).unwrap();
+    std::fs::write(album.join("03 Three.mp3"), b"").unwrap();
+    std::fs::write(
+        album.join("Album.m3u"),
+        "#EXTM3U\n01 One.mp3\n02 Two.mp3\n03 Three.mp3\n",
+    )
+    .unwrap();
+
+    let toml = format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root,
+        td.path().join("test.db")
+    );
+    let cfg: Config = toml::from_str(&toml).unwrap();
+    let provider: Arc<dyn Provider> = Arc::new(ImportProvider {
+        tracks: tracks.iter().map(|s| s.to_string()).collect(),
+    });
+    (td, cfg, provider)
+}
+
+#[tokio::test]
+async fn import_pairs_by_isrc_then_position() {
+    let (_td, cfg, provider) = setup(&["isrc:track", "remote:a", "remote:c"]);
+    let summary = import_playlist(
+        &cfg,
+        provider,
+        std::path::Path::new("Artist/Album"),
+        "remote-pl",
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.playlist_name, "Artist/Album");
+    assert_eq!(summary.matched_by_isrc, 1);
+    assert_eq!(summary.matched_by_order, 2);
+    assert_eq!((summary.unmatched_local, summary.unmatched_remote), (0, 0));
+
+    let conn = db::open_or_create(&cfg.db_path).unwrap();
+    let album = cfg.root_folder.join("Artist").join("Album");
+    let cached = |name: &str| {
+        db::get_track_cache_by_local(&conn, "mock", &album.join(name).display().to_string())
+            .unwrap()
+            .map(|(isrc, remote, _)| (isrc, remote))
+    };
+    assert_eq!(cached("01 One.mp3"), Some((None, Some("remote:a".into()))));
+    assert_eq!(
+        cached("02 Two.mp3"),
+        Some((Some("GBAAA0000001".into()), Some("isrc:track".into())))
+    );
+    assert_eq!(
+        cached("03 Three.mp3"),
+        Some((None, Some("remote:c".into())))
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "mock", "Artist/Album").unwrap(),
+        Some("remote-pl".into())
+    );
+}
+
+#[tokio::test]
+async fn import_does_not_guess_positions_when_counts_differ() {
+    let (_td, cfg, provider) = setup(&["remote:a", "isrc:track"]);
+    let summary = import_playlist(
+        &cfg,
+        provider,
+        &cfg.root_folder.join("Artist").join("Album"),
+        "remote-pl",
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.matched_by_isrc, 1);
+    assert_eq!(summary.matched_by_order, 0);
+    assert_eq!((summary.unmatched_local, summary.unmatched_remote), (2, 1));
+
+    let conn = db::open_or_create(&cfg.db_path).unwrap();
+    let one = cfg.root_folder.join("Artist/Album/01 One.mp3");
+    assert!(
+        db::get_track_cache_by_local(&conn, "mock", &one.display().to_string())
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "mock", "Artist/Album").unwrap(),
+        Some("remote-pl".into())
+    );
+}
@@ -1,7 +1,13 @@
+use music_file_playlist_online_sync::api::mock::{MockCall, MockProvider};
+use music_file_playlist_online_sync::api::Provider;
 use music_file_playlist_online_sync::config::Config;
 use music_file_playlist_online_sync::db;
-use music_file_playlist_online_sync::worker::run_worker_once;
+use music_file_playlist_online_sync::models::EventAction;
+use music_file_playlist_online_sync::worker::{
+    run_worker_once, run_worker_once_with, WorkerRunSummary,
+};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tempfile::NamedTempFile;
 
 #[tokio::test]
@@ -11,9 +17,9 @@ async fn test_worker_no_credentials() {
     let cfg = Config {
         db_path: db_path.clone().into(),
         root_folder: PathBuf::new(),
-        whitelist: String::new(),
-        local_whitelist: String::new(),
-        remote_whitelist: String::new(),
+        whitelist: Vec::new(),
+        local_whitelist: Vec::new(),
+        remote_whitelist: Vec::new(),
         local_playlist_template: String::new(),
         remote_playlist_template: String::new(),
         remote_playlist_template_flat: String::new(),
@@ -21,10 +27,15 @@ async fn test_worker_no_credentials() {
         playlist_description_template: String::new(),
         playlist_order_mode: String::new(),
         playlist_mode: String::new(),
+        playlist_format: "m3u".into(),
+        playlist_write_threads: 4,
         linked_reference_format: String::new(),
         debounce_ms: 0,
         log_dir: PathBuf::new(),
+        log_level: "info".into(),
+        log_format: "text".into(),
         token_refresh_interval: 0,
+        credentials_key_file: None,
         watcher_instant_trigger_threshold: 0,
         watcher_deferred_trigger_delay_sec: 0,
         nightly_reconcile_cron: String::new(),
@@ -32,16 +43,47 @@ async fn test_worker_no_credentials() {
         max_retries_on_error: 0,
         max_batch_size_spotify: 0,
         max_batch_size_tidal: 0,
+        synced_event_retention_secs: 7 * 24 * 3600,
+        poll_interval_sec: 60,
+        metrics_listen: String::new(),
+        watch_mode: "inotify".into(),
+        ignore_patterns: Vec::new(),
+        no_playlist_marker: ".noplaylist".into(),
+        local_only_marker: ".localonly".into(),
+        root_folders: Vec::new(),
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        spotify_market: String::new(),
+        spotify_scopes: Vec::new(),
+        tidal_country_code: "US".into(),
+        tidal_locale: "en-US".into(),
+        unresolved_retry_secs: 30 * 24 * 3600,
+        match_duration_tolerance_secs: 10,
+        min_match_confidence: 0.0,
+        search_strip_tokens: Vec::new(),
+        search_strip_diacritics: true,
+        enable_musicbrainz: false,
+        enabled_providers: Vec::new(),
+        filesystem_export_dir: std::path::PathBuf::new(),
+        filesystem_export_path_prefix: String::new(),
+        max_concurrent_providers: 4,
+        remote_snapshot_max_age_secs: 24 * 3600,
+        lock_ttl_secs: 600,
+        backoff_base_secs: 1,
+        backoff_max_secs: 60,
         file_extensions: Vec::new(),
         online_root_playlist: String::new(),
         online_playlist_structure: "flat".into(),
         online_folder_flattening_delimiter: String::new(),
+        filename_parse_regex: String::new(),
+        strip_filename_copy_suffix: true,
+        filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
     };
     // Run migrations to set up schema in the temp DB
     let conn = rusqlite::Connection::open(&cfg.db_path).unwrap();
     db::run_migrations(&conn).unwrap();
     // Should not panic or process events
-    let result = run_worker_once(&cfg, None, false).await;
+    let result = run_worker_once(&cfg, None, false, false).await;
     assert!(result.is_ok());
 }
 
@@ -54,9 +96,9 @@ async fn test_worker_with_mock_and_real_providers() {
     let cfg = Config {
         db_path: db_path.clone().into(),
         root_folder: PathBuf::new(),
-        whitelist: String::new(),
-        local_whitelist: String::new(),
-        remote_whitelist: String::new(),
+        whitelist: Vec::new(),
+        local_whitelist: Vec::new(),
+        remote_whitelist: Vec::new(),
         local_playlist_template: String::new(),
         remote_playlist_template: String::new(),
         remote_playlist_template_flat: String::new(),
@@ -64,10 +106,15 @@ async fn test_worker_with_mock_and_real_providers() {
         playlist_description_template: String::new(),
         playlist_order_mode: String::new(),
         playlist_mode: String::new(),
+        playlist_format: "m3u".into(),
+        playlist_write_threads: 4,
         linked_reference_format: String::new(),
         debounce_ms: 0,
         log_dir: PathBuf::new(),
+        log_level: "info".into(),
+        log_format: "text".into(),
         token_refresh_interval: 0,
+        credentials_key_file: None,
         watcher_instant_trigger_threshold: 0,
         watcher_deferred_trigger_delay_sec: 0,
         nightly_reconcile_cron: String::new(),
@@ -75,15 +122,460 @@ async fn test_worker_with_mock_and_real_providers() {
         max_retries_on_error: 0,
         max_batch_size_spotify: 0,
         max_batch_size_tidal: 0,
+        synced_event_retention_secs: 7 * 24 * 3600,
+        poll_interval_sec: 60,
+        metrics_listen: String::new(),
+        watch_mode: "inotify".into(),
+        ignore_patterns: Vec::new(),
+        no_playlist_marker: ".noplaylist".into(),
+        local_only_marker: ".localonly".into(),
+        root_folders: Vec::new(),
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        spotify_market: String::new(),
+        spotify_scopes: Vec::new(),
+        tidal_country_code: "US".into(),
+        tidal_locale: "en-US".into(),
+        unresolved_retry_secs: 30 * 24 * 3600,
+        match_duration_tolerance_secs: 10,
+        min_match_confidence: 0.0,
+        search_strip_tokens: Vec::new(),
+        search_strip_diacritics: true,
+        enable_musicbrainz: false,
+        enabled_providers: Vec::new(),
+        filesystem_export_dir: std::path::PathBuf::new(),
+        filesystem_export_path_prefix: String::new(),
+        max_concurrent_providers: 4,
+        remote_snapshot_max_age_secs: 24 * 3600,
+        lock_ttl_secs: 600,
+        backoff_base_secs: 1,
+        backoff_max_secs: 60,
         file_extensions: Vec::new(),
         online_root_playlist: String::new(),
         online_playlist_structure: "flat".into(),
         online_folder_flattening_delimiter: String::new(),
+        filename_parse_regex: String::new(),
+        strip_filename_copy_suffix: true,
+        filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
     };
     // Run migrations to set up schema in the temp DB
     let conn = rusqlite::Connection::open(&cfg.db_path).unwrap();
     db::run_migrations(&conn).unwrap();
     // Should not panic or process events
-    let result = run_worker_once(&cfg, None, false).await;
+    let result = run_worker_once(&cfg, None, false, false).await;
     assert!(result.is_ok());
 }
+
+#[tokio::test]
+async fn injected_mock_provider_records_the_worker_calls() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    for f in ["01 - A.mp3", "02 - B.mp3"] {
+        std::fs::write(album.join(f), b"").unwrap();
+    }
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n02 - B.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let mock = MockProvider::authenticated();
+    assert!(mock.is_authenticated());
+    let calls = mock.call_log();
+    let providers: Vec<(String, Arc<dyn Provider>)> = vec![("mock".into(), Arc::new(mock))];
+
+    // Create: the playlist is made and both tracks are added in one batch.
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    let recorded = std::mem::take(&mut *calls.lock().unwrap());
+    assert_eq!(
+        recorded,
+        vec![
+            MockCall::EnsurePlaylist {
+                name: "Album".into()
+            },
+            MockCall::AddTracks {
+                playlist_id: "mock-playlist-Album".into(),
+                uris: vec!["mock:track:A:01".into(), "mock:track:B:02".into()],
+            },
+        ]
+    );
+
+    // An Add followed by a Remove of the same track collapses to the removal.
+    let b = album.join("02 - B.mp3");
+    std::fs::remove_file(&b).unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let b = b.to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some(&b), None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Remove, Some(&b), None).unwrap();
+    run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![MockCall::RemoveTracks {
+            playlist_id: "mock-playlist-Album".into(),
+            uris: vec!["mock:track:B:02".into()],
+        }]
+    );
+    let pending: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM event_queue WHERE is_synced = 0",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(pending, 0);
+}
+
+#[tokio::test]
+async fn files_sharing_a_uri_add_it_once_and_keep_it_until_the_last_goes() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    let files = ["01 - A.mp3", "01 - A (rip 2).mp3"];
+    for f in files {
+        std::fs::write(album.join(f), b"").unwrap();
+    }
+    std::fs::write(
+        album.join("Album.m3u"),
+        "#EXTM3U\n01 - A.mp3\n01 - A (rip 2).mp3\n",
+    )
+    .unwrap();
+    let db_path = td.path().join("test.db");
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    for f in files {
+        let path = album.join(f).to_string_lossy().to_string();
+        db::set_override(&conn, "mock", &path, "mock:track:A").unwrap();
+    }
+
+    let mock = MockProvider::authenticated();
+    let calls = mock.call_log();
+    let providers: Vec<(String, Arc<dyn Provider>)> = vec![("mock".into(), Arc::new(mock))];
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    let recorded = std::mem::take(&mut *calls.lock().unwrap());
+    assert_eq!(
+        recorded.last(),
+        Some(&MockCall::AddTracks {
+            playlist_id: "mock-playlist-Album".into(),
+            uris: vec!["mock:track:A".into()],
+        })
+    );
+
+    // Removing one rip leaves the URI in place: the other still wants it.
+    let rip = album.join(files[1]);
+    std::fs::remove_file(&rip).unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let rip = rip.to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Remove, Some(&rip), None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    assert!(
+        calls.lock().unwrap().is_empty(),
+        "{:?}",
+        calls.lock().unwrap()
+    );
+
+    // Removing the last one removes it.
+    let a = album.join(files[0]);
+    std::fs::remove_file(&a).unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n").unwrap();
+    let a = a.to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Remove, Some(&a), None).unwrap();
+    run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![MockCall::RemoveTracks {
+            playlist_id: "mock-playlist-Album".into(),
+            uris: vec!["mock:track:A".into()],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn incremental_add_is_inserted_at_its_local_position() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    for f in ["01 - A.mp3", "03 - C.mp3"] {
+        std::fs::write(album.join(f), b"").unwrap();
+    }
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n03 - C.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nplaylist_order_mode = \"sync_order\"\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let mock = MockProvider::authenticated().with_positional_add();
+    let calls = mock.call_log();
+    let mock: Arc<dyn Provider> = Arc::new(mock);
+    let providers: Vec<(String, Arc<dyn Provider>)> = vec![("mock".into(), mock.clone())];
+
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    calls.lock().unwrap().clear();
+
+    // B lands between A and C instead of being appended.
+    let b = album.join("02 - B.mp3");
+    std::fs::write(&b, b"").unwrap();
+    std::fs::write(
+        album.join("Album.m3u"),
+        "#EXTM3U\n01 - A.mp3\n02 - B.mp3\n03 - C.mp3\n",
+    )
+    .unwrap();
+    let b = b.to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some(&b), None).unwrap();
+    run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![MockCall::InsertTracks {
+            playlist_id: "mock-playlist-Album".into(),
+            uris: vec!["mock:track:B:02".into()],
+            position: 1,
+        }]
+    );
+    assert_eq!(
+        mock.list_playlist_tracks("mock-playlist-Album")
+            .await
+            .unwrap(),
+        vec![
+            "mock:track:A:01".to_string(),
+            "mock:track:B:02".to_string(),
+            "mock:track:C:03".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rename_event_renames_the_mapped_playlist() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let old = root.join("Old");
+    std::fs::create_dir_all(&old).unwrap();
+    std::fs::write(old.join("01 - A.mp3"), b"").unwrap();
+    std::fs::write(old.join("Old.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let mock = MockProvider::authenticated();
+    let calls = mock.call_log();
+    let providers: Vec<(String, Arc<dyn Provider>)> = vec![("mock".into(), Arc::new(mock))];
+    db::enqueue_event(&conn, "Old", &EventAction::Create, None, None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    calls.lock().unwrap().clear();
+
+    // The folder is renamed on disk; the watcher keys the event by the old name.
+    let new = root.join("New");
+    std::fs::rename(&old, &new).unwrap();
+    std::fs::rename(new.join("Old.m3u"), new.join("New.m3u")).unwrap();
+    let extra = serde_json::json!({ "from": "Old", "to": "New" }).to_string();
+    db::enqueue_event(
+        &conn,
+        "Old",
+        &EventAction::Rename {
+            from: "Old".into(),
+            to: "New".into(),
+        },
+        None,
+        Some(&extra),
+    )
+    .unwrap();
+    run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![MockCall::RenamePlaylist {
+            playlist_id: "mock-playlist-Old".into(),
+            new_name: "New".into(),
+        }]
+    );
+    let mapped: Vec<String> = conn
+        .prepare("SELECT playlist_name FROM playlist_map WHERE provider = 'mock'")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(mapped, vec!["New".to_string()]);
+}
+
+#[tokio::test]
+async fn worker_runs_return_what_they_did() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    for f in ["01 - A.mp3", "02 - B.mp3"] {
+        std::fs::write(album.join(f), b"").unwrap();
+    }
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n02 - B.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nfile_extensions = [\"*.mp3\"]\n",
+        root, db_path
+    ))
+    .unwrap();
+    let mut conn = db::open_or_create(&db_path).unwrap();
+    let providers: Vec<(String, Arc<dyn Provider>)> =
+        vec![("mock".into(), Arc::new(MockProvider::authenticated()))];
+
+    let idle = run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    assert_eq!(idle, WorkerRunSummary::default());
+
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    let created = run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        created,
+        WorkerRunSummary {
+            playlists_processed: 1,
+            tracks_added: 2,
+            ..Default::default()
+        }
+    );
+
+    // A dry run counts only what it would change: A is already on the remote.
+    let a = album.join("01 - A.mp3").to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some(&a), None).unwrap();
+    let planned = run_worker_once_with(&cfg, providers.clone(), false, true)
+        .await
+        .unwrap();
+    assert_eq!((planned.tracks_added, planned.tracks_removed), (0, 0));
+    // The dry run leaves the add queued; drop it.
+    let queued: Vec<i64> = db::fetch_unsynced_events(&conn)
+        .unwrap()
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    db::mark_events_synced(&mut conn, &queued).unwrap();
+
+    let b = album.join("02 - B.mp3");
+    std::fs::remove_file(&b).unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let b = b.to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Remove, Some(&b), None).unwrap();
+    let removed = run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    assert_eq!((removed.tracks_added, removed.tracks_removed), (0, 1));
+
+    db::enqueue_event(&conn, "Album", &EventAction::Delete, None, None).unwrap();
+    let deleted = run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+    assert_eq!(deleted.deletes, 1);
+    assert!(deleted.errors.is_empty());
+    let json = serde_json::to_value(&deleted).unwrap();
+    assert_eq!(json["deletes"], 1);
+    assert_eq!(json["errors"], serde_json::json!({}));
+}
+
+#[tokio::test]
+async fn delete_then_create_recreates_the_playlist_with_later_adds() {
+    let td = tempfile::tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    std::fs::write(album.join("01 - A.mp3"), b"").unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nfile_extensions = [\"*.mp3\"]\n",
+        root, db_path
+    ))
+    .unwrap();
+    let conn = db::open_or_create(&db_path).unwrap();
+    let mock = MockProvider::authenticated();
+    let calls = mock.call_log();
+    let mock: Arc<dyn Provider> = Arc::new(mock);
+    let providers: Vec<(String, Arc<dyn Provider>)> = vec![("mock".into(), mock.clone())];
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    run_worker_once_with(&cfg, providers.clone(), false, false)
+        .await
+        .unwrap();
+    calls.lock().unwrap().clear();
+
+    // The folder is deleted and re-created before the worker runs again.
+    let a = album.join("01 - A.mp3").to_string_lossy().to_string();
+    db::enqueue_event(&conn, "Album", &EventAction::Delete, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+    db::enqueue_event(&conn, "Album", &EventAction::Add, Some(&a), None).unwrap();
+    let summary = run_worker_once_with(&cfg, providers, false, false)
+        .await
+        .unwrap();
+
+    assert_eq!((summary.deletes, summary.tracks_added), (1, 1));
+    assert!(summary.errors.is_empty());
+    let recorded = calls.lock().unwrap().clone();
+    assert!(
+        matches!(
+            recorded.as_slice(),
+            [
+                MockCall::DeletePlaylist { .. },
+                MockCall::EnsurePlaylist { .. },
+                MockCall::AddTracks { .. },
+            ]
+        ),
+        "{:?}",
+        recorded
+    );
+    assert_eq!(
+        mock.list_playlist_tracks("mock-playlist-Album")
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        db::get_remote_playlist_id(&conn, "mock", "Album").unwrap(),
+        Some("mock-playlist-Album".into())
+    );
+    assert!(db::fetch_unsynced_events(&conn).unwrap().is_empty());
+}
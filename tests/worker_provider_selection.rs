@@ -17,9 +17,9 @@ fn run_worker_uses_mock_provider_and_marks_events_synced() {
 
     let cfg = Config {
         root_folder: td.path().join("root"),
-        whitelist: String::new(),
-        local_whitelist: String::new(),
-        remote_whitelist: String::new(),
+        whitelist: Vec::new(),
+        local_whitelist: Vec::new(),
+        remote_whitelist: Vec::new(),
         local_playlist_template: "${folder_name}.m3u".into(),
         remote_playlist_template: "${relative_path}".into(),
         remote_playlist_template_flat: String::new(),
@@ -27,27 +27,63 @@ fn run_worker_uses_mock_provider_and_marks_events_synced() {
         playlist_description_template: String::new(),
         playlist_order_mode: "append".into(),
         playlist_mode: "flat".into(),
+        playlist_format: "m3u".into(),
+        playlist_write_threads: 4,
         linked_reference_format: "relative".into(),
         debounce_ms: 100,
         watcher_instant_trigger_threshold: 20,
         watcher_deferred_trigger_delay_sec: 300,
         log_dir: td.path().join("logs"),
+        log_level: "info".into(),
+        log_format: "text".into(),
         token_refresh_interval: 3600,
+        credentials_key_file: None,
         nightly_reconcile_cron: "0 3 * * *".into(),
         queue_length_stop_cloud_sync_threshold: None,
         max_retries_on_error: 3,
         max_batch_size_spotify: 100,
         max_batch_size_tidal: 20,
+        synced_event_retention_secs: 7 * 24 * 3600,
+        poll_interval_sec: 60,
+        metrics_listen: String::new(),
+        watch_mode: "inotify".into(),
+        ignore_patterns: Vec::new(),
+        no_playlist_marker: ".noplaylist".into(),
+        local_only_marker: ".localonly".into(),
+        root_folders: Vec::new(),
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        spotify_market: String::new(),
+        spotify_scopes: Vec::new(),
+        tidal_country_code: "US".into(),
+        tidal_locale: "en-US".into(),
+        unresolved_retry_secs: 30 * 24 * 3600,
+        match_duration_tolerance_secs: 10,
+        min_match_confidence: 0.0,
+        search_strip_tokens: Vec::new(),
+        search_strip_diacritics: true,
+        enable_musicbrainz: false,
+        enabled_providers: Vec::new(),
+        filesystem_export_dir: std::path::PathBuf::new(),
+        filesystem_export_path_prefix: String::new(),
+        max_concurrent_providers: 4,
+        remote_snapshot_max_age_secs: 24 * 3600,
+        lock_ttl_secs: 600,
+        backoff_base_secs: 1,
+        backoff_max_secs: 60,
         file_extensions: vec!["*.mp3".into()],
         online_root_playlist: String::new(),
         online_playlist_structure: "flat".into(),
         online_folder_flattening_delimiter: String::new(),
+        filename_parse_regex: String::new(),
+        strip_filename_copy_suffix: true,
+        filename_copy_suffix_patterns: vec![r"(?i) copy \d+".to_string()],
         db_path: db_path.clone(),
     };
 
     // run worker once
     let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async move { run_worker_once(&cfg, None, false).await.unwrap() });
+    rt.block_on(async move { run_worker_once(&cfg, None, false, false).await.unwrap() });
 
     // verify event is NOT marked synced (since no real provider is configured)
     let cnt: i64 = conn
@@ -59,3 +95,54 @@ fn run_worker_uses_mock_provider_and_marks_events_synced() {
         .unwrap();
     assert_eq!(cnt, 1);
 }
+
+#[test]
+fn run_worker_skips_providers_missing_from_enabled_providers() {
+    let mut server = mockito::Server::new();
+    let td = tempdir().unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let creds = music_file_playlist_online_sync::api::subsonic::SubsonicCredentials::with_salt(
+        &server.url(),
+        "alice",
+        "pw",
+        "salt",
+    );
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    db::enqueue_event(&conn, "pl1", &EventAction::Create, None, None).unwrap();
+
+    // Subsonic has credentials but is not enabled: it must not be contacted.
+    let untouched = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+    let cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nenabled_providers = [\"spotify\"]\n",
+        td.path().join("root"),
+        db_path
+    ))
+    .unwrap();
+    cfg.validate().unwrap();
+    assert!(cfg.provider_enabled("Spotify"));
+    assert!(!cfg.provider_enabled("subsonic"));
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async { run_worker_once(&cfg, None, false, false).await.unwrap() });
+
+    untouched.assert();
+    let cnt: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM event_queue WHERE is_synced = 0",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(cnt, 1);
+
+    let mut bad = cfg.clone();
+    bad.enabled_providers = vec!["napster".into()];
+    assert!(bad
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("enabled_providers"));
+}
@@ -0,0 +1,356 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::subsonic::SubsonicCredentials;
+use music_file_playlist_online_sync::config::Config;
+use music_file_playlist_online_sync::db;
+use music_file_playlist_online_sync::models::EventAction;
+use music_file_playlist_online_sync::worker::{run_worker_once, sync_one_playlist};
+use rusqlite::Connection;
+use serde_json::json;
+use tempfile::tempdir;
+
+fn ok(body: serde_json::Value) -> String {
+    let mut inner = json!({ "status": "ok", "version": "1.16.1" });
+    if let (Some(obj), Some(extra)) = (inner.as_object_mut(), body.as_object()) {
+        obj.extend(extra.clone());
+    }
+    json!({ "subsonic-response": inner }).to_string()
+}
+
+#[test]
+fn sync_one_processes_only_the_requested_playlist() {
+    let mut server = Server::new();
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    std::fs::create_dir_all(root.join("Mapped")).unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    db::upsert_playlist_map(&conn, "subsonic", "Mapped", "pl1").unwrap();
+    db::enqueue_event(
+        &conn,
+        "Other",
+        &EventAction::Add,
+        Some("uri::subsonic:song:3"),
+        None,
+    )
+    .unwrap();
+
+    let _list = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::UrlEncoded("id".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl1", "name": "Mapped", "entry": [ { "id": "1" } ] } }),
+        ))
+        .create();
+    // The empty local folder means the stale remote entry is removed.
+    let update = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::UrlEncoded("playlistId".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(json!({})))
+        .expect_at_least(1)
+        .create();
+    let create = server
+        .mock("GET", "/rest/createPlaylist")
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+
+    let cfg = Config {
+        root_folder: root,
+        debounce_ms: 100,
+        log_dir: td.path().join("logs"),
+        // A normal worker run would stop at this threshold.
+        queue_length_stop_cloud_sync_threshold: Some(0),
+        max_retries_on_error: 1,
+        spotify_requests_per_sec: 0.0,
+        tidal_requests_per_sec: 0.0,
+        search_strip_tokens: Vec::new(),
+        file_extensions: vec!["*.mp3".into()],
+        db_path: db_path.clone(),
+        ..Default::default()
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(sync_one_playlist(&cfg, "Mapped", None, false))
+        .unwrap();
+
+    update.assert();
+    create.assert();
+    let unsynced: Vec<String> = conn
+        .prepare("SELECT playlist_name FROM event_queue WHERE is_synced = 0")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(unsynced, vec!["Other".to_string()]);
+}
+
+#[test]
+fn failing_provider_does_not_stop_the_others() {
+    let mut subsonic = Server::new();
+    let mut ytmusic = Server::new();
+    std::env::set_var("YTMUSIC_API_BASE", ytmusic.url());
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    std::fs::create_dir_all(root.join("Mapped")).unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+
+    let creds = SubsonicCredentials::with_salt(&subsonic.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    let token = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": ""
+    });
+    db::save_credential_raw(
+        &conn,
+        "ytmusic",
+        &token.to_string(),
+        Some("cid"),
+        Some("csecret"),
+    )
+    .unwrap();
+    db::upsert_playlist_map(&conn, "subsonic", "Mapped", "pl1").unwrap();
+    db::upsert_playlist_map(&conn, "ytmusic", "Mapped", "yt1").unwrap();
+    db::enqueue_event(&conn, "Mapped", &EventAction::Create, None, None).unwrap();
+
+    let _list = subsonic
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::UrlEncoded("id".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl1", "name": "Mapped", "entry": [ { "id": "1" } ] } }),
+        ))
+        .create();
+    let update = subsonic
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::UrlEncoded("playlistId".into(), "pl1".into()))
+        .with_status(200)
+        .with_body(ok(json!({})))
+        .expect_at_least(1)
+        .create();
+    // YouTube lists one stale item but rejects removing it.
+    let _yt_list = ytmusic
+        .mock("GET", "/playlistItems")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(
+            json!({ "items": [ { "id": "i1", "contentDetails": { "videoId": "a" } } ] })
+                .to_string(),
+        )
+        .create();
+    let _yt_remove = ytmusic
+        .mock("DELETE", "/playlistItems")
+        .match_query(Matcher::Any)
+        .with_status(400)
+        .with_body("boom")
+        .create();
+
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nmax_retries_on_error = 3\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(run_worker_once(&cfg, None, false, false))
+        .unwrap();
+
+    // Subsonic was still updated; the event stays queued with the error.
+    update.assert();
+    let (synced, attempts, last_error): (i64, i64, Option<String>) = conn
+        .query_row(
+            "SELECT is_synced, attempts, last_error FROM event_queue WHERE playlist_name = 'Mapped'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((synced, attempts), (0, 1));
+    assert!(last_error.is_some());
+    // Every per-provider lock was released.
+    let locks: i64 = conn
+        .query_row("SELECT COUNT(*) FROM processing_locks", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(locks, 0);
+}
+
+#[test]
+fn created_playlist_gets_the_expanded_description() {
+    let mut server = Server::new();
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Artist").join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    for f in ["01 - A.mp3", "02 - B.mp3"] {
+        std::fs::write(album.join(f), b"").unwrap();
+    }
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n02 - B.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+    db::enqueue_event(&conn, "Artist/Album", &EventAction::Create, None, None).unwrap();
+
+    let _lists = server
+        .mock("GET", "/rest/getPlaylists")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({ "playlists": { "playlist": [] } })))
+        .create();
+    let _create = server
+        .mock("GET", "/rest/createPlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl9", "name": "Artist/Album" } }),
+        ))
+        .create();
+    let _search = server
+        .mock("GET", "/rest/search3")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({ "searchResult3": {} })))
+        .create();
+    let _get = server
+        .mock("GET", "/rest/getPlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(
+            json!({ "playlist": { "id": "pl9", "name": "Artist/Album" } }),
+        ))
+        .create();
+    let described = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("playlistId".into(), "pl9".into()),
+            Matcher::UrlEncoded("comment".into(), "Album from Artist/ (2 tracks)".into()),
+        ]))
+        .with_status(200)
+        .with_body(ok(json!({})))
+        .expect(1)
+        .create();
+
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\nplaylist_description_template = {:?}\n",
+        root, db_path, "${folder_name} from ${path_to_parent} (${track_count} tracks)"
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(sync_one_playlist(&cfg, "Artist/Album", None, false))
+        .unwrap();
+
+    described.assert();
+    let mapped: String = conn
+        .query_row(
+            "SELECT remote_id FROM playlist_map WHERE playlist_name = 'Artist/Album'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(mapped, "pl9");
+}
+
+#[test]
+fn fresh_remote_snapshot_skips_listing_until_forced() {
+    let mut server = Server::new();
+    let td = tempdir().unwrap();
+    let root = td.path().join("root");
+    let album = root.join("Album");
+    std::fs::create_dir_all(&album).unwrap();
+    std::fs::write(album.join("01 - A.mp3"), b"").unwrap();
+    std::fs::write(album.join("Album.m3u"), "#EXTM3U\n01 - A.mp3\n").unwrap();
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let creds = SubsonicCredentials::with_salt(&server.url(), "alice", "pw", "salt");
+    let blob = serde_json::to_string(&creds).unwrap();
+    db::save_credential_raw(&conn, "subsonic", &blob, Some("alice"), None).unwrap();
+
+    let _lists = server
+        .mock("GET", "/rest/getPlaylists")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({ "playlists": { "playlist": [] } })))
+        .create();
+    let _create = server
+        .mock("GET", "/rest/createPlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({ "playlist": { "id": "pl9", "name": "Album" } })))
+        .create();
+    let _search = server
+        .mock("GET", "/rest/search3")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({ "searchResult3": { "song": [
+            { "id": "s1", "title": "A", "artist": "01" }
+        ] } })))
+        .create();
+    let _update = server
+        .mock("GET", "/rest/updatePlaylist")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(ok(json!({})))
+        .create();
+    let get_playlist = |server: &mut Server, hits: usize| {
+        server
+            .mock("GET", "/rest/getPlaylist")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(ok(json!({ "playlist": { "id": "pl9", "name": "Album" } })))
+            .expect(hits)
+            .create()
+    };
+
+    let mut cfg: Config = toml::from_str(&format!(
+        "root_folder = {:?}\ndb_path = {:?}\n",
+        root, db_path
+    ))
+    .unwrap();
+    cfg.file_extensions = vec!["*.mp3".into()];
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let sync = |cfg: &Config| {
+        db::enqueue_event(&conn, "Album", &EventAction::Create, None, None).unwrap();
+        rt.block_on(sync_one_playlist(cfg, "Album", None, false))
+            .unwrap();
+    };
+
+    // The first sync lists the remote and stores the snapshot.
+    let listed = get_playlist(&mut server, 2);
+    sync(&cfg);
+    listed.remove();
+    let snap = db::get_remote_snapshot(&conn, "subsonic", "Album")
+        .unwrap()
+        .expect("snapshot stored after the sync");
+    assert_eq!(snap.remote_id, "pl9");
+    // Listed empty, then updated with the track the sync added.
+    assert_eq!(snap.uris, vec!["subsonic:song:s1".to_string()]);
+
+    // A second sync within the max age diffs against the snapshot; the only
+    // getPlaylist request left is the mapping's validity check.
+    let skipped = get_playlist(&mut server, 1);
+    sync(&cfg);
+    skipped.assert();
+    skipped.remove();
+
+    // --force-relist (max age 0) lists again.
+    let relisted = get_playlist(&mut server, 2);
+    cfg.remote_snapshot_max_age_secs = 0;
+    sync(&cfg);
+    relisted.assert();
+}
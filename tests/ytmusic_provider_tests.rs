@@ -0,0 +1,148 @@
+use mockito::{Matcher, Server};
+use music_file_playlist_online_sync::api::ytmusic::YtMusicProvider;
+use music_file_playlist_online_sync::api::{Provider, ProviderError};
+use music_file_playlist_online_sync::db;
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use serde_json::json;
+use std::env;
+use std::sync::Mutex;
+use tempfile::{tempdir, TempDir};
+
+// These tests point YTMUSIC_API_BASE at a per-test mockito server, so they
+// must not run concurrently.
+static YTMUSIC_TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Prepare a DB with a valid token and return a provider using it.
+fn provider_with_token(td: &TempDir) -> YtMusicProvider {
+    let db_path = td.path().join("test.db");
+    let conn = Connection::open(&db_path).unwrap();
+    db::run_migrations(&conn).unwrap();
+    let stored = json!({
+        "access_token": "valid",
+        "token_type": "Bearer",
+        "expires_at": chrono::Utc::now().timestamp() + 3600,
+        "refresh_token": "r",
+        "scope": ""
+    })
+    .to_string();
+    db::save_credential_raw(&conn, "ytmusic", &stored, Some("cid"), Some("csecret")).unwrap();
+    YtMusicProvider::new(String::new(), String::new(), db_path, Default::default())
+}
+
+#[test]
+fn ytmusic_search_returns_video_uri() {
+    let _guard = YTMUSIC_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("YTMUSIC_API_BASE", server.url());
+
+    let m = server
+        .mock("GET", "/search")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("type".into(), "video".into()),
+            Matcher::UrlEncoded("q".into(), "Artist Song".into()),
+        ]))
+        .match_header("authorization", "Bearer valid")
+        .with_status(200)
+        .with_body(
+            json!({ "items": [ { "id": { "kind": "youtube#video", "videoId": "v1" } } ] })
+                .to_string(),
+        )
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    assert!(provider.is_authenticated());
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let res = rt
+        .block_on(provider.search_track_uri("Song", "Artist"))
+        .unwrap();
+    assert_eq!(res.as_deref(), Some("ytmusic:video:v1"));
+    m.assert();
+}
+
+#[test]
+fn ytmusic_list_tracks_paginates_and_remove_uses_item_ids() {
+    let _guard = YTMUSIC_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("YTMUSIC_API_BASE", server.url());
+
+    let _p1 = server
+        .mock("GET", "/playlistItems")
+        .match_query(Matcher::Regex("playlistId=pl1$".into()))
+        .with_status(200)
+        .with_body(
+            json!({
+                "items": [ { "id": "i1", "contentDetails": { "videoId": "a" } } ],
+                "nextPageToken": "p2"
+            })
+            .to_string(),
+        )
+        .create();
+    let _p2 = server
+        .mock("GET", "/playlistItems")
+        .match_query(Matcher::UrlEncoded("pageToken".into(), "p2".into()))
+        .with_status(200)
+        .with_body(
+            json!({
+                "items": [
+                    { "id": "i2", "contentDetails": { "videoId": "b" } },
+                    { "id": "i3", "contentDetails": { "videoId": "a" } }
+                ]
+            })
+            .to_string(),
+        )
+        .create();
+    let del = server
+        .mock("DELETE", "/playlistItems")
+        .match_query(Matcher::Regex("^id=i[13]$".into()))
+        .with_status(204)
+        .expect(2)
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let tracks = rt.block_on(provider.list_playlist_tracks("pl1")).unwrap();
+    assert_eq!(tracks, vec!["ytmusic:video:a", "ytmusic:video:b"]);
+
+    rt.block_on(provider.remove_tracks("pl1", &["ytmusic:video:a".into()]))
+        .unwrap();
+    del.assert();
+}
+
+#[test]
+fn ytmusic_quota_and_missing_playlist_errors() {
+    let _guard = YTMUSIC_TEST_LOCK.lock().unwrap();
+    let mut server = Server::new();
+    env::set_var("YTMUSIC_API_BASE", server.url());
+
+    let _quota = server
+        .mock("GET", "/search")
+        .match_query(Matcher::Any)
+        .with_status(403)
+        .with_body(json!({ "error": { "errors": [ { "reason": "quotaExceeded" } ] } }).to_string())
+        .create();
+    let _missing = server
+        .mock("POST", "/playlistItems")
+        .match_query(Matcher::Any)
+        .with_status(404)
+        .with_body(
+            json!({ "error": { "errors": [ { "reason": "playlistNotFound" } ] } }).to_string(),
+        )
+        .create();
+
+    let td = tempdir().unwrap();
+    let provider = provider_with_token(&td);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let err = rt
+        .block_on(provider.search_track_uri("Song", "Artist"))
+        .unwrap_err();
+    assert!(matches!(err, ProviderError::RateLimited { .. }));
+    let err = rt
+        .block_on(provider.add_tracks("gone", &["ytmusic:video:a".into()]))
+        .unwrap_err();
+    assert!(
+        matches!(err, ProviderError::PlaylistNotFound { ref playlist_id } if playlist_id == "gone")
+    );
+}